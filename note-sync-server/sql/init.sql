@@ -7,6 +7,7 @@
 --   - 允许同一设备多账号（复合主键）
 --   - 完整的设备追踪和同步字段
 --   - 同步锁支持工作空间隔离（防止并发冲突）
+--   - 变更序号（change_seq）支持基于游标的增量同步
 -- ============================================
 
 -- ============================================
@@ -22,6 +23,7 @@ CREATE TABLE IF NOT EXISTS users (
   id VARCHAR(10) PRIMARY KEY COMMENT '10位数字用户ID',
   email VARCHAR(255) UNIQUE NOT NULL,
   password_hash VARCHAR(255) NOT NULL,
+  email_verified BOOLEAN NOT NULL DEFAULT TRUE COMMENT '邮箱是否已验证（启用邮箱验证时新注册账号为 FALSE）',
   created_at BIGINT NOT NULL
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
 
@@ -32,9 +34,12 @@ CREATE TABLE IF NOT EXISTS devices (
   id VARCHAR(64) NOT NULL COMMENT '设备ID（UUID或默认格式：default-<md5>）',
   user_id VARCHAR(10) NOT NULL COMMENT '10位数字用户ID',
   device_name VARCHAR(255) NOT NULL,
+  custom_name VARCHAR(255) NULL COMMENT '用户自定义设备名称（为空时使用自动生成的名称）',
   device_type VARCHAR(50) DEFAULT 'desktop',
   revoked BOOLEAN DEFAULT FALSE,
+  trusted BOOLEAN NOT NULL DEFAULT FALSE COMMENT '是否为受信任设备',
   last_seen_at BIGINT NOT NULL,
+  last_heartbeat_at BIGINT NULL COMMENT '最近心跳时间（为空表示从未发送心跳）',
   created_at BIGINT NOT NULL,
   FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
   PRIMARY KEY (user_id, id) COMMENT '复合主键：允许同一设备被多个账号使用',
@@ -52,6 +57,7 @@ CREATE TABLE IF NOT EXISTS notes (
 
   title TEXT,
   content MEDIUMTEXT,
+  search_text MEDIUMTEXT DEFAULT NULL COMMENT '正文纯文本（用于搜索）',
   folder_id CHAR(36),
 
   is_deleted BOOLEAN DEFAULT FALSE,
@@ -66,6 +72,7 @@ CREATE TABLE IF NOT EXISTS notes (
   markdown_cache TEXT DEFAULT NULL COMMENT 'Markdown 缓存',
   is_favorite BOOLEAN DEFAULT FALSE COMMENT '是否收藏',
   is_pinned BOOLEAN DEFAULT FALSE COMMENT '是否置顶',
+  is_private BOOLEAN DEFAULT FALSE COMMENT '是否私密（不参与全局搜索）',
   author VARCHAR(255) DEFAULT NULL COMMENT '作者',
   word_count INT DEFAULT 0 COMMENT '字数统计',
   read_time_minutes INT DEFAULT 0 COMMENT '阅读时长（分钟）',
@@ -74,6 +81,9 @@ CREATE TABLE IF NOT EXISTS notes (
   device_id VARCHAR(64) DEFAULT NULL COMMENT '最后修改的设备ID',
   updated_by_device VARCHAR(255) DEFAULT NULL COMMENT '设备描述',
 
+  -- 增量同步字段
+  change_seq BIGINT NOT NULL DEFAULT 0 COMMENT '变更序号（增量同步游标）',
+
   FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
   INDEX idx_user_notes (user_id),
   INDEX idx_updated_at (updated_at),
@@ -81,7 +91,8 @@ CREATE TABLE IF NOT EXISTS notes (
   INDEX idx_device_id (device_id),
   INDEX idx_workspace_id (workspace_id),
   INDEX idx_is_favorite (is_favorite),
-  INDEX idx_is_pinned (is_pinned)
+  INDEX idx_is_pinned (is_pinned),
+  INDEX idx_notes_change_seq (user_id, change_seq)
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
 
 -- ============================================
@@ -107,13 +118,17 @@ CREATE TABLE IF NOT EXISTS folders (
   device_id VARCHAR(64) DEFAULT NULL COMMENT '最后修改的设备ID',
   updated_by_device VARCHAR(255) DEFAULT NULL COMMENT '设备描述',
 
+  -- 增量同步字段
+  change_seq BIGINT NOT NULL DEFAULT 0 COMMENT '变更序号（增量同步游标）',
+
   FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
   FOREIGN KEY (parent_id) REFERENCES folders(id) ON DELETE CASCADE,
   INDEX idx_user_folders (user_id),
   INDEX idx_parent_id (parent_id),
   INDEX idx_device_id (device_id),
   INDEX idx_workspace_id (workspace_id),
-  INDEX idx_folders_deleted (is_deleted)
+  INDEX idx_folders_deleted (is_deleted),
+  INDEX idx_folders_change_seq (user_id, change_seq)
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
 
 -- ============================================
@@ -135,11 +150,15 @@ CREATE TABLE IF NOT EXISTS note_versions (
   device_id VARCHAR(64) DEFAULT NULL COMMENT '创建快照的设备ID',
   server_ver INT NOT NULL DEFAULT 1 COMMENT '服务器版本号',
 
+  -- 增量同步字段
+  change_seq BIGINT NOT NULL DEFAULT 0 COMMENT '变更序号（增量同步游标）',
+
   FOREIGN KEY (note_id) REFERENCES notes(id) ON DELETE CASCADE,
   FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
   INDEX idx_note_versions (note_id, created_at),
   INDEX idx_device_id (device_id),
-  INDEX idx_workspace_id (workspace_id)
+  INDEX idx_workspace_id (workspace_id),
+  INDEX idx_note_versions_change_seq (user_id, change_seq)
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
 
 -- ============================================
@@ -148,6 +167,7 @@ CREATE TABLE IF NOT EXISTS note_versions (
 CREATE TABLE IF NOT EXISTS sync_history (
   id CHAR(36) PRIMARY KEY,
   user_id VARCHAR(10) NOT NULL COMMENT '10位数字用户ID',
+  device_id VARCHAR(64) NULL COMMENT '执行同步的设备ID（旧记录为空）',
   sync_type VARCHAR(20) NOT NULL COMMENT 'push, pull, full',
   pushed_count INT DEFAULT 0 COMMENT '推送的笔记/文件夹数量',
   pulled_count INT DEFAULT 0 COMMENT '拉取的笔记/文件夹数量',
@@ -156,7 +176,8 @@ CREATE TABLE IF NOT EXISTS sync_history (
   duration_ms BIGINT DEFAULT 0 COMMENT '耗时（毫秒）',
   created_at BIGINT NOT NULL COMMENT '同步时间戳',
   FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
-  INDEX idx_user_sync_history (user_id, created_at DESC)
+  INDEX idx_user_sync_history (user_id, created_at DESC),
+  INDEX idx_user_sync_history_device (user_id, device_id)
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COMMENT='同步历史记录表';
 
 -- ============================================
@@ -218,13 +239,17 @@ CREATE TABLE IF NOT EXISTS workspaces (
   device_id VARCHAR(50) DEFAULT NULL COMMENT '设备ID（用于追踪哪个设备创建了/修改了记录）',
   updated_by_device VARCHAR(255) DEFAULT NULL COMMENT '最后更新设备的ID（用于跨设备编辑检测）',
 
+  -- 增量同步字段
+  change_seq BIGINT NOT NULL DEFAULT 0 COMMENT '变更序号（增量同步游标）',
+
   FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
   INDEX idx_user_id (user_id),
   INDEX idx_is_default (is_default),
   INDEX idx_is_deleted (is_deleted),
   INDEX idx_device_id (device_id),
   INDEX idx_server_ver (server_ver),
-  UNIQUE KEY unique_default_workspace (user_id, is_default)
+  UNIQUE KEY unique_default_workspace (user_id, is_default),
+  INDEX idx_workspaces_change_seq (user_id, change_seq)
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4
 COMMENT='用户工作空间表';
 
@@ -249,12 +274,16 @@ CREATE TABLE IF NOT EXISTS tags (
   device_id VARCHAR(64) DEFAULT NULL COMMENT '最后修改的设备ID',
   updated_by_device VARCHAR(255) DEFAULT NULL COMMENT '设备描述',
 
+  -- 增量同步字段
+  change_seq BIGINT NOT NULL DEFAULT 0 COMMENT '变更序号（增量同步游标）',
+
   FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
   INDEX idx_user_tags (user_id),
   INDEX idx_updated_at (updated_at),
   INDEX idx_tags_deleted (is_deleted),
   INDEX idx_device_id (device_id),
-  INDEX idx_workspace_id (workspace_id)
+  INDEX idx_workspace_id (workspace_id),
+  INDEX idx_tags_change_seq (user_id, change_seq)
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COMMENT='标签表';
 
 -- ============================================
@@ -270,11 +299,15 @@ CREATE TABLE IF NOT EXISTS note_tags (
   is_deleted BOOLEAN DEFAULT FALSE COMMENT '是否已删除（软删除）',
   deleted_at BIGINT COMMENT '删除时间戳',
 
+  -- 增量同步字段
+  change_seq BIGINT NOT NULL DEFAULT 0 COMMENT '变更序号（增量同步游标）',
+
   PRIMARY KEY (note_id, tag_id),
   FOREIGN KEY (note_id) REFERENCES notes(id) ON DELETE CASCADE,
   FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE,
   INDEX idx_note_tags_user (user_id),
-  INDEX idx_workspace_id (workspace_id)
+  INDEX idx_workspace_id (workspace_id),
+  INDEX idx_note_tags_change_seq (user_id, change_seq)
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COMMENT='笔记标签关联表';
 
 -- ============================================
@@ -291,6 +324,178 @@ CREATE TABLE IF NOT EXISTS sync_locks (
   INDEX idx_workspace_lock (user_id, device_id, workspace_id) COMMENT '支持工作空间的并发控制'
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COMMENT='同步操作锁表（支持工作空间隔离）';
 
+-- ============================================
+-- 13. 用户变更序号计数器（增量同步游标）
+-- ============================================
+CREATE TABLE IF NOT EXISTS sync_change_seq (
+  user_id VARCHAR(10) PRIMARY KEY COMMENT '用户ID',
+  seq BIGINT NOT NULL DEFAULT 0 COMMENT '最近分配的变更序号'
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COMMENT='用户变更序号计数器';
+
+-- ============================================
+-- 14. 笔记附件表（Base64 存储 + 软删除 + 工作空间）
+-- ============================================
+CREATE TABLE IF NOT EXISTS attachments (
+  id CHAR(36) PRIMARY KEY,
+  note_id CHAR(36) NOT NULL,
+  user_id VARCHAR(10) NOT NULL COMMENT '10位数字用户ID',
+  workspace_id VARCHAR(36) DEFAULT NULL COMMENT '工作空间ID',
+
+  filename VARCHAR(255) NOT NULL COMMENT '文件名',
+  mime_type VARCHAR(127) NOT NULL COMMENT 'MIME 类型',
+  size_bytes BIGINT NOT NULL COMMENT '解码后的大小（字节）',
+  data LONGTEXT NOT NULL COMMENT '文件数据（Base64 编码）',
+
+  created_at BIGINT NOT NULL,
+  updated_at BIGINT NOT NULL,
+  is_deleted BOOLEAN DEFAULT FALSE COMMENT '软删除标记',
+  deleted_at BIGINT DEFAULT NULL COMMENT '删除时间戳',
+
+  -- 设备追踪字段
+  device_id VARCHAR(64) DEFAULT NULL COMMENT '最后修改附件的设备ID',
+  server_ver INT NOT NULL DEFAULT 1 COMMENT '服务器版本号',
+
+  -- 增量同步字段
+  change_seq BIGINT NOT NULL DEFAULT 0 COMMENT '变更序号（增量同步游标）',
+
+  FOREIGN KEY (note_id) REFERENCES notes(id) ON DELETE CASCADE,
+  FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+  INDEX idx_attachments_note (note_id),
+  INDEX idx_workspace_id (workspace_id),
+  INDEX idx_attachments_change_seq (user_id, change_seq)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COMMENT='笔记附件表';
+
+-- 同步数据表插入/更新时分配新的变更序号
+DELIMITER $$
+
+DROP TRIGGER IF EXISTS trg_workspaces_change_seq_insert$$
+CREATE TRIGGER trg_workspaces_change_seq_insert BEFORE INSERT ON workspaces
+FOR EACH ROW
+BEGIN
+  INSERT INTO sync_change_seq (user_id, seq) VALUES (NEW.user_id, LAST_INSERT_ID(1))
+    ON DUPLICATE KEY UPDATE seq = LAST_INSERT_ID(seq + 1);
+  SET NEW.change_seq = LAST_INSERT_ID();
+END$$
+
+DROP TRIGGER IF EXISTS trg_workspaces_change_seq_update$$
+CREATE TRIGGER trg_workspaces_change_seq_update BEFORE UPDATE ON workspaces
+FOR EACH ROW
+BEGIN
+  INSERT INTO sync_change_seq (user_id, seq) VALUES (NEW.user_id, LAST_INSERT_ID(1))
+    ON DUPLICATE KEY UPDATE seq = LAST_INSERT_ID(seq + 1);
+  SET NEW.change_seq = LAST_INSERT_ID();
+END$$
+
+DROP TRIGGER IF EXISTS trg_notes_change_seq_insert$$
+CREATE TRIGGER trg_notes_change_seq_insert BEFORE INSERT ON notes
+FOR EACH ROW
+BEGIN
+  INSERT INTO sync_change_seq (user_id, seq) VALUES (NEW.user_id, LAST_INSERT_ID(1))
+    ON DUPLICATE KEY UPDATE seq = LAST_INSERT_ID(seq + 1);
+  SET NEW.change_seq = LAST_INSERT_ID();
+END$$
+
+DROP TRIGGER IF EXISTS trg_notes_change_seq_update$$
+CREATE TRIGGER trg_notes_change_seq_update BEFORE UPDATE ON notes
+FOR EACH ROW
+BEGIN
+  INSERT INTO sync_change_seq (user_id, seq) VALUES (NEW.user_id, LAST_INSERT_ID(1))
+    ON DUPLICATE KEY UPDATE seq = LAST_INSERT_ID(seq + 1);
+  SET NEW.change_seq = LAST_INSERT_ID();
+END$$
+
+DROP TRIGGER IF EXISTS trg_folders_change_seq_insert$$
+CREATE TRIGGER trg_folders_change_seq_insert BEFORE INSERT ON folders
+FOR EACH ROW
+BEGIN
+  INSERT INTO sync_change_seq (user_id, seq) VALUES (NEW.user_id, LAST_INSERT_ID(1))
+    ON DUPLICATE KEY UPDATE seq = LAST_INSERT_ID(seq + 1);
+  SET NEW.change_seq = LAST_INSERT_ID();
+END$$
+
+DROP TRIGGER IF EXISTS trg_folders_change_seq_update$$
+CREATE TRIGGER trg_folders_change_seq_update BEFORE UPDATE ON folders
+FOR EACH ROW
+BEGIN
+  INSERT INTO sync_change_seq (user_id, seq) VALUES (NEW.user_id, LAST_INSERT_ID(1))
+    ON DUPLICATE KEY UPDATE seq = LAST_INSERT_ID(seq + 1);
+  SET NEW.change_seq = LAST_INSERT_ID();
+END$$
+
+DROP TRIGGER IF EXISTS trg_tags_change_seq_insert$$
+CREATE TRIGGER trg_tags_change_seq_insert BEFORE INSERT ON tags
+FOR EACH ROW
+BEGIN
+  INSERT INTO sync_change_seq (user_id, seq) VALUES (NEW.user_id, LAST_INSERT_ID(1))
+    ON DUPLICATE KEY UPDATE seq = LAST_INSERT_ID(seq + 1);
+  SET NEW.change_seq = LAST_INSERT_ID();
+END$$
+
+DROP TRIGGER IF EXISTS trg_tags_change_seq_update$$
+CREATE TRIGGER trg_tags_change_seq_update BEFORE UPDATE ON tags
+FOR EACH ROW
+BEGIN
+  INSERT INTO sync_change_seq (user_id, seq) VALUES (NEW.user_id, LAST_INSERT_ID(1))
+    ON DUPLICATE KEY UPDATE seq = LAST_INSERT_ID(seq + 1);
+  SET NEW.change_seq = LAST_INSERT_ID();
+END$$
+
+DROP TRIGGER IF EXISTS trg_note_versions_change_seq_insert$$
+CREATE TRIGGER trg_note_versions_change_seq_insert BEFORE INSERT ON note_versions
+FOR EACH ROW
+BEGIN
+  INSERT INTO sync_change_seq (user_id, seq) VALUES (NEW.user_id, LAST_INSERT_ID(1))
+    ON DUPLICATE KEY UPDATE seq = LAST_INSERT_ID(seq + 1);
+  SET NEW.change_seq = LAST_INSERT_ID();
+END$$
+
+DROP TRIGGER IF EXISTS trg_note_versions_change_seq_update$$
+CREATE TRIGGER trg_note_versions_change_seq_update BEFORE UPDATE ON note_versions
+FOR EACH ROW
+BEGIN
+  INSERT INTO sync_change_seq (user_id, seq) VALUES (NEW.user_id, LAST_INSERT_ID(1))
+    ON DUPLICATE KEY UPDATE seq = LAST_INSERT_ID(seq + 1);
+  SET NEW.change_seq = LAST_INSERT_ID();
+END$$
+
+DROP TRIGGER IF EXISTS trg_note_tags_change_seq_insert$$
+CREATE TRIGGER trg_note_tags_change_seq_insert BEFORE INSERT ON note_tags
+FOR EACH ROW
+BEGIN
+  INSERT INTO sync_change_seq (user_id, seq) VALUES (NEW.user_id, LAST_INSERT_ID(1))
+    ON DUPLICATE KEY UPDATE seq = LAST_INSERT_ID(seq + 1);
+  SET NEW.change_seq = LAST_INSERT_ID();
+END$$
+
+DROP TRIGGER IF EXISTS trg_note_tags_change_seq_update$$
+CREATE TRIGGER trg_note_tags_change_seq_update BEFORE UPDATE ON note_tags
+FOR EACH ROW
+BEGIN
+  INSERT INTO sync_change_seq (user_id, seq) VALUES (NEW.user_id, LAST_INSERT_ID(1))
+    ON DUPLICATE KEY UPDATE seq = LAST_INSERT_ID(seq + 1);
+  SET NEW.change_seq = LAST_INSERT_ID();
+END$$
+
+DROP TRIGGER IF EXISTS trg_attachments_change_seq_insert$$
+CREATE TRIGGER trg_attachments_change_seq_insert BEFORE INSERT ON attachments
+FOR EACH ROW
+BEGIN
+  INSERT INTO sync_change_seq (user_id, seq) VALUES (NEW.user_id, LAST_INSERT_ID(1))
+    ON DUPLICATE KEY UPDATE seq = LAST_INSERT_ID(seq + 1);
+  SET NEW.change_seq = LAST_INSERT_ID();
+END$$
+
+DROP TRIGGER IF EXISTS trg_attachments_change_seq_update$$
+CREATE TRIGGER trg_attachments_change_seq_update BEFORE UPDATE ON attachments
+FOR EACH ROW
+BEGIN
+  INSERT INTO sync_change_seq (user_id, seq) VALUES (NEW.user_id, LAST_INSERT_ID(1))
+    ON DUPLICATE KEY UPDATE seq = LAST_INSERT_ID(seq + 1);
+  SET NEW.change_seq = LAST_INSERT_ID();
+END$$
+
+DELIMITER ;
+
 -- ============================================
 -- 初始化完成
 -- ============================================
@@ -0,0 +1,6 @@
+-- 迁移 008：为 notes 表添加 is_private 字段
+--
+-- 目的：支持私密笔记
+-- 说明：私密笔记不出现在客户端的跨工作空间全局搜索中，在所属工作空间内仍可搜索
+
+ALTER TABLE notes ADD COLUMN is_private BOOLEAN DEFAULT FALSE COMMENT '是否私密（不参与全局搜索）' AFTER is_pinned;
@@ -0,0 +1,133 @@
+-- 迁移 009：添加变更序号（change_seq），支持基于游标的增量同步
+--
+-- 目的：增量同步不再依赖 updated_at 时间戳
+-- 问题：按 updated_at > last_sync_at 拉取在设备时钟偏差或同一秒内多次更新时会漏拉或重复拉取
+-- 解决方案：每个用户维护一个单调递增的变更计数器，同步数据表的每次插入/更新都由触发器
+--           分配新的 change_seq，客户端保存服务器返回的游标，下次同步只拉取 change_seq > 游标的数据
+-- 说明：计数器行在写入事务提交前保持行锁，同一用户的变更序号按提交顺序分配，不会出现空洞被跳过
+
+-- 1. 用户变更计数器表
+CREATE TABLE IF NOT EXISTS sync_change_seq (
+  user_id VARCHAR(10) PRIMARY KEY COMMENT '用户ID',
+  seq BIGINT NOT NULL DEFAULT 0 COMMENT '最近分配的变更序号'
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COMMENT='用户变更序号计数器';
+
+-- 2. 为同步数据表添加 change_seq 字段和索引
+ALTER TABLE workspaces ADD COLUMN change_seq BIGINT NOT NULL DEFAULT 0 COMMENT '变更序号（增量同步游标）';
+CREATE INDEX idx_workspaces_change_seq ON workspaces(user_id, change_seq);
+
+ALTER TABLE notes ADD COLUMN change_seq BIGINT NOT NULL DEFAULT 0 COMMENT '变更序号（增量同步游标）';
+CREATE INDEX idx_notes_change_seq ON notes(user_id, change_seq);
+
+ALTER TABLE folders ADD COLUMN change_seq BIGINT NOT NULL DEFAULT 0 COMMENT '变更序号（增量同步游标）';
+CREATE INDEX idx_folders_change_seq ON folders(user_id, change_seq);
+
+ALTER TABLE tags ADD COLUMN change_seq BIGINT NOT NULL DEFAULT 0 COMMENT '变更序号（增量同步游标）';
+CREATE INDEX idx_tags_change_seq ON tags(user_id, change_seq);
+
+ALTER TABLE note_versions ADD COLUMN change_seq BIGINT NOT NULL DEFAULT 0 COMMENT '变更序号（增量同步游标）';
+CREATE INDEX idx_note_versions_change_seq ON note_versions(user_id, change_seq);
+
+ALTER TABLE note_tags ADD COLUMN change_seq BIGINT NOT NULL DEFAULT 0 COMMENT '变更序号（增量同步游标）';
+CREATE INDEX idx_note_tags_change_seq ON note_tags(user_id, change_seq);
+
+-- 3. 插入/更新时分配新的变更序号
+DELIMITER $$
+
+CREATE TRIGGER trg_workspaces_change_seq_insert BEFORE INSERT ON workspaces
+FOR EACH ROW
+BEGIN
+  INSERT INTO sync_change_seq (user_id, seq) VALUES (NEW.user_id, LAST_INSERT_ID(1))
+    ON DUPLICATE KEY UPDATE seq = LAST_INSERT_ID(seq + 1);
+  SET NEW.change_seq = LAST_INSERT_ID();
+END$$
+
+CREATE TRIGGER trg_workspaces_change_seq_update BEFORE UPDATE ON workspaces
+FOR EACH ROW
+BEGIN
+  INSERT INTO sync_change_seq (user_id, seq) VALUES (NEW.user_id, LAST_INSERT_ID(1))
+    ON DUPLICATE KEY UPDATE seq = LAST_INSERT_ID(seq + 1);
+  SET NEW.change_seq = LAST_INSERT_ID();
+END$$
+
+CREATE TRIGGER trg_notes_change_seq_insert BEFORE INSERT ON notes
+FOR EACH ROW
+BEGIN
+  INSERT INTO sync_change_seq (user_id, seq) VALUES (NEW.user_id, LAST_INSERT_ID(1))
+    ON DUPLICATE KEY UPDATE seq = LAST_INSERT_ID(seq + 1);
+  SET NEW.change_seq = LAST_INSERT_ID();
+END$$
+
+CREATE TRIGGER trg_notes_change_seq_update BEFORE UPDATE ON notes
+FOR EACH ROW
+BEGIN
+  INSERT INTO sync_change_seq (user_id, seq) VALUES (NEW.user_id, LAST_INSERT_ID(1))
+    ON DUPLICATE KEY UPDATE seq = LAST_INSERT_ID(seq + 1);
+  SET NEW.change_seq = LAST_INSERT_ID();
+END$$
+
+CREATE TRIGGER trg_folders_change_seq_insert BEFORE INSERT ON folders
+FOR EACH ROW
+BEGIN
+  INSERT INTO sync_change_seq (user_id, seq) VALUES (NEW.user_id, LAST_INSERT_ID(1))
+    ON DUPLICATE KEY UPDATE seq = LAST_INSERT_ID(seq + 1);
+  SET NEW.change_seq = LAST_INSERT_ID();
+END$$
+
+CREATE TRIGGER trg_folders_change_seq_update BEFORE UPDATE ON folders
+FOR EACH ROW
+BEGIN
+  INSERT INTO sync_change_seq (user_id, seq) VALUES (NEW.user_id, LAST_INSERT_ID(1))
+    ON DUPLICATE KEY UPDATE seq = LAST_INSERT_ID(seq + 1);
+  SET NEW.change_seq = LAST_INSERT_ID();
+END$$
+
+CREATE TRIGGER trg_tags_change_seq_insert BEFORE INSERT ON tags
+FOR EACH ROW
+BEGIN
+  INSERT INTO sync_change_seq (user_id, seq) VALUES (NEW.user_id, LAST_INSERT_ID(1))
+    ON DUPLICATE KEY UPDATE seq = LAST_INSERT_ID(seq + 1);
+  SET NEW.change_seq = LAST_INSERT_ID();
+END$$
+
+CREATE TRIGGER trg_tags_change_seq_update BEFORE UPDATE ON tags
+FOR EACH ROW
+BEGIN
+  INSERT INTO sync_change_seq (user_id, seq) VALUES (NEW.user_id, LAST_INSERT_ID(1))
+    ON DUPLICATE KEY UPDATE seq = LAST_INSERT_ID(seq + 1);
+  SET NEW.change_seq = LAST_INSERT_ID();
+END$$
+
+CREATE TRIGGER trg_note_versions_change_seq_insert BEFORE INSERT ON note_versions
+FOR EACH ROW
+BEGIN
+  INSERT INTO sync_change_seq (user_id, seq) VALUES (NEW.user_id, LAST_INSERT_ID(1))
+    ON DUPLICATE KEY UPDATE seq = LAST_INSERT_ID(seq + 1);
+  SET NEW.change_seq = LAST_INSERT_ID();
+END$$
+
+CREATE TRIGGER trg_note_versions_change_seq_update BEFORE UPDATE ON note_versions
+FOR EACH ROW
+BEGIN
+  INSERT INTO sync_change_seq (user_id, seq) VALUES (NEW.user_id, LAST_INSERT_ID(1))
+    ON DUPLICATE KEY UPDATE seq = LAST_INSERT_ID(seq + 1);
+  SET NEW.change_seq = LAST_INSERT_ID();
+END$$
+
+CREATE TRIGGER trg_note_tags_change_seq_insert BEFORE INSERT ON note_tags
+FOR EACH ROW
+BEGIN
+  INSERT INTO sync_change_seq (user_id, seq) VALUES (NEW.user_id, LAST_INSERT_ID(1))
+    ON DUPLICATE KEY UPDATE seq = LAST_INSERT_ID(seq + 1);
+  SET NEW.change_seq = LAST_INSERT_ID();
+END$$
+
+CREATE TRIGGER trg_note_tags_change_seq_update BEFORE UPDATE ON note_tags
+FOR EACH ROW
+BEGIN
+  INSERT INTO sync_change_seq (user_id, seq) VALUES (NEW.user_id, LAST_INSERT_ID(1))
+    ON DUPLICATE KEY UPDATE seq = LAST_INSERT_ID(seq + 1);
+  SET NEW.change_seq = LAST_INSERT_ID();
+END$$
+
+DELIMITER ;
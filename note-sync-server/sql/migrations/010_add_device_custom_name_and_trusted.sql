@@ -0,0 +1,9 @@
+-- 迁移 010：添加设备自定义名称和受信任标记
+--
+-- 目的：允许用户重命名设备、将设备标记为受信任
+-- 问题：device_name 在每次登录时都会根据设备标识重新生成，用户修改的名称会被覆盖
+-- 解决方案：用户设置的名称单独保存在 custom_name 中，登录时只更新 device_name，
+--           展示时 custom_name 非空则优先使用
+
+ALTER TABLE devices ADD COLUMN custom_name VARCHAR(255) NULL COMMENT '用户自定义设备名称（为空时使用自动生成的名称）';
+ALTER TABLE devices ADD COLUMN trusted BOOLEAN NOT NULL DEFAULT FALSE COMMENT '是否为受信任设备';
@@ -0,0 +1,7 @@
+-- 迁移 011：添加设备最近心跳时间
+--
+-- 目的：设备列表展示在线状态
+-- 问题：last_seen_at 在登录和心跳时都会更新，无法区分设备是否仍在持续发送心跳
+-- 解决方案：心跳时单独记录 last_heartbeat_at，服务器按配置的在线阈值计算设备是否在线
+
+ALTER TABLE devices ADD COLUMN last_heartbeat_at BIGINT NULL COMMENT '最近心跳时间（为空表示从未发送心跳）';
@@ -0,0 +1,8 @@
+-- 迁移 012：同步历史记录执行同步的设备
+--
+-- 目的：让用户区分每次同步的变更来自哪台设备
+-- 问题：sync_history 只记录推送/拉取数量，无法按设备统计
+-- 解决方案：记录同步请求中的 device_id，并提供按设备汇总推送/拉取总数的接口
+
+ALTER TABLE sync_history ADD COLUMN device_id VARCHAR(64) NULL COMMENT '执行同步的设备ID（旧记录为空）';
+CREATE INDEX idx_user_sync_history_device ON sync_history(user_id, device_id);
@@ -0,0 +1,58 @@
+-- 迁移 013：笔记附件（粘贴的图片等）
+--
+-- 目的：笔记中粘贴的图片等附件可以随笔记同步到其他设备
+-- 问题：笔记内容只能保存文本，附件只存在于本地
+-- 解决方案：新增 attachments 表，文件数据以 Base64 编码存储（与头像一致），
+--           单个附件大小受 limits.max_attachment_bytes 限制；附件参与增量同步，支持软删除
+
+-- 1. 附件表
+CREATE TABLE IF NOT EXISTS attachments (
+  id CHAR(36) PRIMARY KEY,
+  note_id CHAR(36) NOT NULL,
+  user_id VARCHAR(10) NOT NULL COMMENT '10位数字用户ID',
+  workspace_id VARCHAR(36) DEFAULT NULL COMMENT '工作空间ID',
+
+  filename VARCHAR(255) NOT NULL COMMENT '文件名',
+  mime_type VARCHAR(127) NOT NULL COMMENT 'MIME 类型',
+  size_bytes BIGINT NOT NULL COMMENT '解码后的大小（字节）',
+  data LONGTEXT NOT NULL COMMENT '文件数据（Base64 编码）',
+
+  created_at BIGINT NOT NULL,
+  updated_at BIGINT NOT NULL,
+  is_deleted BOOLEAN DEFAULT FALSE COMMENT '软删除标记',
+  deleted_at BIGINT DEFAULT NULL COMMENT '删除时间戳',
+
+  -- 设备追踪字段
+  device_id VARCHAR(64) DEFAULT NULL COMMENT '最后修改附件的设备ID',
+  server_ver INT NOT NULL DEFAULT 1 COMMENT '服务器版本号',
+
+  -- 增量同步字段
+  change_seq BIGINT NOT NULL DEFAULT 0 COMMENT '变更序号（增量同步游标）',
+
+  FOREIGN KEY (note_id) REFERENCES notes(id) ON DELETE CASCADE,
+  FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+  INDEX idx_attachments_note (note_id),
+  INDEX idx_workspace_id (workspace_id),
+  INDEX idx_attachments_change_seq (user_id, change_seq)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COMMENT='笔记附件表';
+
+-- 2. 插入/更新时分配新的变更序号
+DELIMITER $$
+
+CREATE TRIGGER trg_attachments_change_seq_insert BEFORE INSERT ON attachments
+FOR EACH ROW
+BEGIN
+  INSERT INTO sync_change_seq (user_id, seq) VALUES (NEW.user_id, LAST_INSERT_ID(1))
+    ON DUPLICATE KEY UPDATE seq = LAST_INSERT_ID(seq + 1);
+  SET NEW.change_seq = LAST_INSERT_ID();
+END$$
+
+CREATE TRIGGER trg_attachments_change_seq_update BEFORE UPDATE ON attachments
+FOR EACH ROW
+BEGIN
+  INSERT INTO sync_change_seq (user_id, seq) VALUES (NEW.user_id, LAST_INSERT_ID(1))
+    ON DUPLICATE KEY UPDATE seq = LAST_INSERT_ID(seq + 1);
+  SET NEW.change_seq = LAST_INSERT_ID();
+END$$
+
+DELIMITER ;
@@ -0,0 +1,20 @@
+-- 迁移 014：注册邮箱验证
+--
+-- 目的：确认注册邮箱属于用户本人
+-- 问题：注册后立即返回 token，可以使用任意邮箱地址注册
+-- 解决方案：启用 email_verification 后新注册账号标记为未验证，并发送验证链接；
+--           未验证的账号可以登录，但同步会被拒绝，访问验证链接后解除限制
+-- 说明：已有账号默认视为已验证
+
+-- 1. 用户邮箱验证状态
+ALTER TABLE users ADD COLUMN email_verified BOOLEAN NOT NULL DEFAULT TRUE COMMENT '邮箱是否已验证（启用邮箱验证时新注册账号为 FALSE）';
+
+-- 2. 验证令牌（只保存哈希）
+CREATE TABLE IF NOT EXISTS email_verification_tokens (
+  token_hash VARCHAR(64) PRIMARY KEY COMMENT '验证令牌的 SHA-256 哈希',
+  user_id VARCHAR(10) NOT NULL COMMENT '10位数字用户ID',
+  expires_at BIGINT NOT NULL COMMENT '过期时间戳',
+  created_at BIGINT NOT NULL,
+  FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+  INDEX idx_email_verification_user (user_id)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COMMENT='邮箱验证令牌表';
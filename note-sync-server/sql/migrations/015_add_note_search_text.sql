@@ -0,0 +1,9 @@
+-- 迁移 015：为 notes 表添加 search_text 字段
+--
+-- 目的：云端搜索只匹配笔记正文
+-- 问题：content 保存的是 Tiptap JSON，LIKE 会匹配到 "paragraph"、"type" 等结构字段，
+--       这些笔记占满候选数量上限后真正命中的笔记反而搜不到
+-- 解决方案：写入笔记时同时保存正文纯文本，搜索时对纯文本做 LIKE 匹配
+-- 说明：已有笔记的 search_text 为空，下次同步写入时生成；为空时搜索退回匹配 content
+
+ALTER TABLE notes ADD COLUMN search_text MEDIUMTEXT DEFAULT NULL COMMENT '正文纯文本（用于搜索）' AFTER content;
@@ -0,0 +1,9 @@
+-- 迁移 016：邮箱验证令牌改存 Redis
+--
+-- 目的：与 Token 黑名单、限流、幂等键共用同一套带过期时间的键值存储
+-- 问题：验证令牌单独建表，过期的令牌需要手动清理
+-- 解决方案：令牌保存在 Redis（email_verification:{token_hash} → user_id），到期自动清除；
+--           users.email_verified 仍保存在 MySQL
+-- 说明：迁移前发出但尚未使用的验证链接会失效（邮箱验证与本迁移在同一版本发布，正常升级不受影响）
+
+DROP TABLE IF EXISTS email_verification_tokens;
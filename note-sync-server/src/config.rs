@@ -1,4 +1,4 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use config::{Config, ConfigError, Environment, File};
 use std::path::PathBuf;
 use std::env;
@@ -7,6 +7,9 @@ use std::env;
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
+    /// 停止时等待进行中请求完成的最长时间（秒）
+    #[serde(default = "default_shutdown_timeout_secs")]
+    pub shutdown_timeout_secs: u64,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -30,12 +33,269 @@ pub struct RedisConfig {
     pub password: Option<String>,
 }
 
+/// 服务器限制（通过 `/limits` 暴露给客户端）
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct LimitsConfig {
+    /// 单篇笔记内容最大字符数（0 表示不限制）
+    #[serde(default)]
+    pub max_note_length: usize,
+    /// 每篇笔记保留的最大快照数量
+    #[serde(default = "default_max_snapshots_per_note")]
+    pub max_snapshots_per_note: i64,
+    /// 单个附件（解码后）的最大字节数
+    #[serde(default = "default_max_attachment_bytes")]
+    pub max_attachment_bytes: i64,
+    /// 单次同步请求体（解压后）的最大字节数
+    #[serde(default = "default_max_sync_body_bytes")]
+    pub max_sync_body_bytes: usize,
+}
+
+impl LimitsConfig {
+    /// `/sync` 实际使用的请求体上限
+    ///
+    /// 附件以 Base64 随同步请求上传（体积约为原文件的 4/3），
+    /// 上限至少要能容纳一个最大尺寸的附件和其余同步数据
+    pub fn sync_body_limit(&self) -> usize {
+        let max_attachment = usize::try_from(self.max_attachment_bytes.max(0)).unwrap_or(usize::MAX);
+        let attachment_base64 = max_attachment.div_ceil(3).saturating_mul(4);
+        self.max_sync_body_bytes.max(attachment_base64.saturating_add(SYNC_BODY_OVERHEAD_BYTES))
+    }
+}
+
+/// 同步请求中除附件外的数据预留的大小
+const SYNC_BODY_OVERHEAD_BYTES: usize = 1024 * 1024;
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_note_length: 0,
+            max_snapshots_per_note: default_max_snapshots_per_note(),
+            max_attachment_bytes: default_max_attachment_bytes(),
+            max_sync_body_bytes: default_max_sync_body_bytes(),
+        }
+    }
+}
+
+/// HTTP gzip 压缩配置
+#[derive(Debug, Deserialize, Clone)]
+pub struct CompressionConfig {
+    /// 响应体达到该大小（字节）且客户端支持 gzip 时压缩响应（0 表示不压缩）
+    #[serde(default = "default_min_response_bytes")]
+    pub min_response_bytes: usize,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            min_response_bytes: default_min_response_bytes(),
+        }
+    }
+}
+
+/// CORS 配置
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct CorsConfig {
+    /// 允许跨域访问的来源（不支持 `*`）；为空时开发环境允许本地来源，生产环境必须配置
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+}
+
+/// 同步锁配置
+#[derive(Debug, Deserialize, Clone)]
+pub struct SyncLockConfig {
+    /// 同步锁有效期（秒）；获取时间早于该时长的锁视为遗留锁，可被其他设备回收
+    #[serde(default = "default_sync_lock_ttl_secs")]
+    pub ttl_secs: i64,
+}
+
+impl Default for SyncLockConfig {
+    fn default() -> Self {
+        Self {
+            ttl_secs: default_sync_lock_ttl_secs(),
+        }
+    }
+}
+
+/// 登录 / 注册限流配置
+#[derive(Debug, Deserialize, Clone)]
+pub struct RateLimitConfig {
+    /// 是否启用限流
+    #[serde(default = "default_rate_limit_enabled")]
+    pub enabled: bool,
+    /// 计数窗口（秒）
+    #[serde(default = "default_rate_limit_window_secs")]
+    pub window_secs: u64,
+    /// 窗口内同一邮箱允许的登录失败次数
+    #[serde(default = "default_max_failed_logins_per_email")]
+    pub max_failed_logins_per_email: u32,
+    /// 窗口内同一 IP 允许的登录 / 注册请求次数
+    #[serde(default = "default_max_requests_per_ip")]
+    pub max_requests_per_ip: u32,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_rate_limit_enabled(),
+            window_secs: default_rate_limit_window_secs(),
+            max_failed_logins_per_email: default_max_failed_logins_per_email(),
+            max_requests_per_ip: default_max_requests_per_ip(),
+        }
+    }
+}
+
+/// 设备管理配置
+#[derive(Debug, Deserialize, Clone)]
+pub struct DevicesConfig {
+    /// 在线阈值（分钟）；最近心跳在该时长内的设备视为在线
+    #[serde(default = "default_online_threshold_minutes")]
+    pub online_threshold_minutes: i64,
+}
+
+impl Default for DevicesConfig {
+    fn default() -> Self {
+        Self {
+            online_threshold_minutes: default_online_threshold_minutes(),
+        }
+    }
+}
+
+/// 数据维护配置
+#[derive(Debug, Deserialize, Clone)]
+pub struct MaintenanceConfig {
+    /// 已删除笔记的版本快照保留天数；超过后可被清理
+    #[serde(default = "default_note_version_retention_days")]
+    pub note_version_retention_days: i64,
+    /// 清理时每批（每个事务）删除的最大记录数
+    #[serde(default = "default_purge_batch_size")]
+    pub purge_batch_size: i64,
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        Self {
+            note_version_retention_days: default_note_version_retention_days(),
+            purge_batch_size: default_purge_batch_size(),
+        }
+    }
+}
+
+/// 注册邮箱验证配置
+#[derive(Debug, Deserialize, Clone)]
+pub struct EmailVerificationConfig {
+    /// 是否启用；启用后新注册的账号需验证邮箱才能同步
+    #[serde(default)]
+    pub enabled: bool,
+    /// 验证链接有效期（小时）
+    #[serde(default = "default_verification_token_ttl_hours")]
+    pub token_ttl_hours: i64,
+    /// 验证链接地址（邮件中的链接为 `{verify_url}?token=...`）
+    #[serde(default = "default_verify_url")]
+    pub verify_url: String,
+}
+
+impl Default for EmailVerificationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            token_ttl_hours: default_verification_token_ttl_hours(),
+            verify_url: default_verify_url(),
+        }
+    }
+}
+
+/// 幂等键配置（重试的创建/同步请求返回首次处理的结果）
+#[derive(Debug, Deserialize, Clone)]
+pub struct IdempotencyConfig {
+    /// 幂等键的保留时间（秒），超过后同一个键会被当作新请求处理
+    #[serde(default = "default_idempotency_ttl_secs")]
+    pub ttl_secs: u64,
+    /// 保存的响应体上限（字节），超过时不保存结果（重试会被重新处理）
+    #[serde(default = "default_idempotency_max_response_bytes")]
+    pub max_response_bytes: usize,
+    /// 相同幂等键的请求仍在处理中时，重试请求等待首次处理结果的最长时间（毫秒），超时返回 409
+    #[serde(default = "default_idempotency_in_progress_wait_ms")]
+    pub in_progress_wait_ms: u64,
+}
+
+impl Default for IdempotencyConfig {
+    fn default() -> Self {
+        Self {
+            ttl_secs: default_idempotency_ttl_secs(),
+            max_response_bytes: default_idempotency_max_response_bytes(),
+            in_progress_wait_ms: default_idempotency_in_progress_wait_ms(),
+        }
+    }
+}
+
+/// 注册密码强度策略
+#[derive(Debug, Deserialize, Clone)]
+pub struct PasswordPolicyConfig {
+    /// 最小长度（字符数）
+    #[serde(default = "default_password_min_length")]
+    pub min_length: usize,
+    /// 至少包含的字符类别数（小写字母、大写字母、数字、其他符号，取值 0–4）
+    #[serde(default = "default_password_min_character_classes")]
+    pub min_character_classes: usize,
+}
+
+impl Default for PasswordPolicyConfig {
+    fn default() -> Self {
+        Self {
+            min_length: default_password_min_length(),
+            min_character_classes: default_password_min_character_classes(),
+        }
+    }
+}
+
+/// 笔记统计配置（字数和阅读时间由服务器统一计算）
+#[derive(Debug, Deserialize, Clone)]
+pub struct NoteStatsConfig {
+    /// 阅读速度（每分钟字数），用于计算预计阅读时间
+    #[serde(default = "default_reading_speed_wpm")]
+    pub reading_speed_wpm: u32,
+}
+
+impl Default for NoteStatsConfig {
+    fn default() -> Self {
+        Self {
+            reading_speed_wpm: default_reading_speed_wpm(),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct AppConfig {
     pub server: ServerConfig,
     pub database: DatabaseConfig,
     pub auth: AuthConfig,
     pub redis: RedisConfig,
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    #[serde(default)]
+    pub compression: CompressionConfig,
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    #[serde(default)]
+    pub cors: CorsConfig,
+    #[serde(default)]
+    pub sync_lock: SyncLockConfig,
+    #[serde(default)]
+    pub devices: DevicesConfig,
+    #[serde(default)]
+    pub maintenance: MaintenanceConfig,
+    #[serde(default)]
+    pub email_verification: EmailVerificationConfig,
+    #[serde(default)]
+    pub idempotency: IdempotencyConfig,
+    #[serde(default)]
+    pub password_policy: PasswordPolicyConfig,
+    #[serde(default)]
+    pub note_stats: NoteStatsConfig,
+}
+
+fn default_shutdown_timeout_secs() -> u64 {
+    30
 }
 
 fn default_max_connections() -> u32 {
@@ -46,6 +306,86 @@ fn default_jwt_expiration_days() -> i64 {
     7
 }
 
+fn default_max_snapshots_per_note() -> i64 {
+    20
+}
+
+fn default_max_attachment_bytes() -> i64 {
+    10 * 1024 * 1024
+}
+
+fn default_max_sync_body_bytes() -> usize {
+    64 * 1024 * 1024
+}
+
+fn default_verification_token_ttl_hours() -> i64 {
+    24
+}
+
+fn default_verify_url() -> String {
+    "http://localhost:3000/auth/verify".to_string()
+}
+
+fn default_idempotency_ttl_secs() -> u64 {
+    24 * 3600
+}
+
+fn default_idempotency_max_response_bytes() -> usize {
+    1024 * 1024
+}
+
+fn default_idempotency_in_progress_wait_ms() -> u64 {
+    10_000
+}
+
+fn default_min_response_bytes() -> usize {
+    8 * 1024
+}
+
+fn default_sync_lock_ttl_secs() -> i64 {
+    30
+}
+
+fn default_online_threshold_minutes() -> i64 {
+    5
+}
+
+fn default_note_version_retention_days() -> i64 {
+    30
+}
+
+fn default_purge_batch_size() -> i64 {
+    500
+}
+
+fn default_password_min_length() -> usize {
+    8
+}
+
+fn default_password_min_character_classes() -> usize {
+    2
+}
+
+fn default_reading_speed_wpm() -> u32 {
+    200
+}
+
+fn default_rate_limit_enabled() -> bool {
+    true
+}
+
+fn default_rate_limit_window_secs() -> u64 {
+    15 * 60
+}
+
+fn default_max_failed_logins_per_email() -> u32 {
+    5
+}
+
+fn default_max_requests_per_ip() -> u32 {
+    30
+}
+
 /// 获取可执行文件所在目录
 fn get_exe_dir() -> PathBuf {
     env::current_exe()
@@ -165,3 +505,24 @@ impl AppConfig {
         settings.try_deserialize()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sync_body_limit_fits_largest_attachment() {
+        let limits = LimitsConfig::default();
+        // 默认配置下 10 MiB 的附件（Base64 约 13.4 MiB）可以随同步上传
+        assert!(limits.sync_body_limit() > 14 * 1024 * 1024);
+        assert_eq!(limits.sync_body_limit(), limits.max_sync_body_bytes);
+
+        // 请求体上限配置得过小时，仍能容纳一个最大尺寸的附件
+        let limits = LimitsConfig {
+            max_attachment_bytes: 30 * 1024 * 1024,
+            max_sync_body_bytes: 2 * 1024 * 1024,
+            ..LimitsConfig::default()
+        };
+        assert_eq!(limits.sync_body_limit(), 40 * 1024 * 1024 + SYNC_BODY_OVERHEAD_BYTES);
+    }
+}
@@ -1,11 +1,15 @@
-use axum::{Json, extract::{State, Extension}, http::StatusCode};
+use axum::{Json, extract::{Query, State, Extension}, http::StatusCode};
 use axum::http::{HeaderMap, header};
+use axum::body::Body;
+use axum::response::{IntoResponse, Response};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use crate::AppState;
-use crate::services::auth_service::AuthService;
+use crate::services::auth_service::{AuthService, PasswordChangeError, RefreshTokenReused};
 use crate::services::device_service::DeviceService;
+use crate::services::export_service::ExportService;
 use crate::services::device_identifier_service::DeviceIdentifierService;
+use crate::middleware::auth::TokenSession;
 use crate::middleware::logging::{RequestId, log_info};
 use super::ErrorResponse;
 use std::fmt;
@@ -57,6 +61,26 @@ pub struct AuthResponse {
     pub device_id: String,
 }
 
+/// 当前会话对应的账号信息
+#[derive(Debug, Serialize)]
+pub struct MeResponse {
+    pub user_id: String,
+    pub email: String,
+}
+
+/// 邮箱验证请求（GET 时通过查询参数传入）
+#[derive(Deserialize)]
+pub struct VerifyEmailRequest {
+    pub token: String,
+}
+
+/// 邮箱验证结果
+#[derive(Debug, Serialize)]
+pub struct VerifyEmailResponse {
+    pub user_id: String,
+    pub email_verified: bool,
+}
+
 #[derive(Deserialize)]
 pub struct RefreshRequest {
     pub refresh_token: String,
@@ -67,6 +91,63 @@ pub struct DeleteAccountRequest {
     pub password: String,
 }
 
+/// 修改密码请求
+#[derive(Deserialize)]
+pub struct ChangePasswordRequest {
+    pub old_password: String,
+    pub new_password: String,
+    /// 是否同时登出其他设备（当前设备保持登录）
+    #[serde(default)]
+    pub logout_other_devices: bool,
+    /// 当前设备的 refresh token（登出其他设备时不会被清除）
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+}
+
+// 自定义 Debug 实现，隐藏密码
+impl fmt::Debug for ChangePasswordRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChangePasswordRequest")
+            .field("old_password", &"***")
+            .field("new_password", &"***")
+            .field("logout_other_devices", &self.logout_other_devices)
+            .finish()
+    }
+}
+
+/// 修改密码结果
+#[derive(Debug, Serialize)]
+pub struct ChangePasswordResponse {
+    pub revoked_refresh_tokens: u64,
+    pub logged_out_other_devices: bool,
+}
+
+/// 登出请求（旧版客户端不发送请求体）
+#[derive(Deserialize, Default)]
+pub struct LogoutRequest {
+    /// 当前设备的 refresh token（提供时一并吊销）
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+}
+
+/// 登出所有设备请求
+#[derive(Deserialize, Default)]
+pub struct LogoutAllRequest {
+    /// 是否保留当前设备的登录状态
+    #[serde(default)]
+    pub keep_current_device: bool,
+    /// 当前设备的 refresh token（保留当前设备时不会被清除）
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+}
+
+/// 登出所有设备结果
+#[derive(Debug, Serialize)]
+pub struct LogoutAllResponse {
+    pub revoked_refresh_tokens: u64,
+    pub kept_current_device: bool,
+}
+
 // 自定义 Debug 实现，隐藏 token
 impl fmt::Debug for AuthResponse {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -90,7 +171,13 @@ pub async fn register(
     let service = AuthService::new(state.pool.clone());
     let device_service = DeviceService::new(state.pool.clone());
 
-    // 1. 检查邮箱是否已存在
+    // 1. 校验密码强度
+    if let Err(reason) = AuthService::validate_password(&payload.password, &state.config.password_policy) {
+        log_info(&request_id, "密码强度不足", &reason);
+        return Err(ErrorResponse::new_with_code(reason, 400, "WEAK_PASSWORD"));
+    }
+
+    // 2. 检查邮箱是否已存在
     let exists = service.check_email_exists(&payload.email).await
         .map_err(|e| ErrorResponse::new(format!("检查邮箱失败: {}", e)))?;
 
@@ -99,19 +186,23 @@ pub async fn register(
         return Err(ErrorResponse::new("邮箱已注册".to_string()));
     }
 
-    // 2. 哈希密码（同步操作）
+    // 3. 哈希密码（同步操作）
     let password_hash = service.hash_password(&payload.password)
         .map_err(|e| ErrorResponse::new(format!("密码哈希失败: {}", e)))?;
 
-    // 3. 生成唯一的用户 ID
+    // 4. 生成唯一的用户 ID
     let user_id = service.generate_unique_user_id().await
         .map_err(|e| ErrorResponse::new(format!("生成用户 ID 失败: {}", e)))?;
 
-    // 4. 创建用户
+    // 5. 创建用户
     let created_at = service.create_user(&payload.email, &password_hash, &user_id).await
         .map_err(|e| ErrorResponse::new(format!("创建用户失败: {}", e)))?;
 
-    // 5. 注册设备（使用客户端提供的 device_id 或生成默认值）
+    // 启用邮箱验证时标记为未验证并发送验证链接（验证前不能同步）
+    state.email_verification.start_verification(&user_id, &payload.email).await
+        .map_err(|e| ErrorResponse::new(format!("创建邮箱验证失败: {}", e)))?;
+
+    // 6. 注册设备（使用客户端提供的 device_id 或生成默认值）
     let client_device_id = payload.device_id.clone().unwrap_or_else(|| {
         format!("default-{:x}", md5::compute(&payload.email))
     });
@@ -163,7 +254,7 @@ pub async fn register(
 
     log_info(&request_id, "设备注册成功", &format!("device_id={}, name={}", device.id, device_name));
 
-    // 6. 生成 token 并完成注册
+    // 7. 生成 token 并完成注册
     let (user, token, refresh_token) = service.complete_registration(&user_id, &payload.email, created_at, Some(device.id.clone())).await
         .map_err(|e| ErrorResponse::new(format!("生成 token 失败: {}", e)))?;
 
@@ -261,6 +352,37 @@ pub async fn login(
     }
 }
 
+/// 验证注册邮箱（邮件中的链接，GET /auth/verify?token=...）
+pub async fn verify_email_link(
+    request_id: Extension<RequestId>,
+    state: State<AppState>,
+    Query(payload): Query<VerifyEmailRequest>,
+) -> Result<Json<VerifyEmailResponse>, ErrorResponse> {
+    verify_email(request_id, state, Json(payload)).await
+}
+
+/// 验证注册邮箱（POST /auth/verify）
+pub async fn verify_email(
+    Extension(request_id): Extension<RequestId>,
+    State(state): State<AppState>,
+    Json(payload): Json<VerifyEmailRequest>,
+) -> Result<Json<VerifyEmailResponse>, ErrorResponse> {
+    log_info(&request_id, "邮箱验证请求", "");
+
+    let user_id = state.email_verification.verify(payload.token.trim()).await
+        .map_err(|e| {
+            log_info(&request_id, "邮箱验证失败", e.to_string());
+            ErrorResponse::new("邮箱验证失败")
+        })?
+        .ok_or_else(|| {
+            log_info(&request_id, "邮箱验证失败", "令牌无效或已过期");
+            ErrorResponse::new_with_code("验证链接无效或已过期", 400, "INVALID_VERIFICATION_TOKEN")
+        })?;
+
+    log_info(&request_id, "邮箱验证成功", format!("user_id={}", user_id));
+    Ok(Json(VerifyEmailResponse { user_id, email_verified: true }))
+}
+
 pub async fn refresh(
     Extension(request_id): Extension<RequestId>,
     State(state): State<AppState>,
@@ -271,7 +393,7 @@ pub async fn refresh(
     let service = AuthService::new(state.pool.clone());
     let device_service = DeviceService::new(state.pool.clone());
 
-    match service.refresh_access_token(&payload.refresh_token, "default".to_string()).await {
+    match service.refresh_access_token(&payload.refresh_token, "default".to_string(), &state.token_blacklist).await {
         Ok((access_token, refresh_token)) => {
             // 获取用户信息
             // 从 access_token 中解码 user_id
@@ -328,14 +450,23 @@ pub async fn refresh(
         }
         Err(e) => {
             log_info(&request_id, "刷新失败", &e.to_string());
+            if e.downcast_ref::<RefreshTokenReused>().is_some() {
+                return Err(ErrorResponse::new_with_code(e.to_string(), 401, "REFRESH_REUSE"));
+            }
             Err(ErrorResponse::new(e.to_string()))
         }
     }
 }
 
+/// 登出当前设备
+///
+/// 将 access token 加入黑名单；请求体中带有 refresh token 时同时吊销，
+/// 避免登出后仍能用它换取新的 access token
 pub async fn logout(
     State(state): State<AppState>,
+    Extension(user_id): Extension<String>,
     headers: axum::http::HeaderMap,
+    payload: Option<Json<LogoutRequest>>,
 ) -> Result<StatusCode, ErrorResponse> {
     // 提取 Authorization header
     let auth_header = match headers.get("Authorization").and_then(|h| h.to_str().ok()) {
@@ -360,16 +491,180 @@ pub async fn logout(
     };
 
     let mut blacklist = state.token_blacklist.clone();
-    match blacklist.add(token, ttl_seconds).await {
-        Ok(_) => {
-            tracing::info!("Token 已加入黑名单");
-            Ok(StatusCode::OK)
-        }
-        Err(e) => {
-            tracing::error!("将 Token 加入黑名单失败: {:?}", e);
-            Err(ErrorResponse::new(format!("登出失败: {}", e)))
-        }
+    if let Err(e) = blacklist.add(token, ttl_seconds).await {
+        tracing::error!("将 Token 加入黑名单失败: {:?}", e);
+        return Err(ErrorResponse::new(format!("登出失败: {}", e)));
+    }
+    tracing::info!("Token 已加入黑名单");
+
+    if let Some(refresh_token) = payload.and_then(|Json(payload)| payload.refresh_token) {
+        AuthService::new(state.pool.clone())
+            .revoke_refresh_token(&user_id, &refresh_token, &state.config.auth.jwt_secret, &state.token_blacklist)
+            .await
+            .map_err(|e| {
+                tracing::error!("吊销 refresh token 失败: {:?}", e);
+                ErrorResponse::new(format!("登出失败: {}", e))
+            })?;
+        tracing::info!("refresh token 已吊销: user_id={}", user_id);
     }
+
+    Ok(StatusCode::OK)
+}
+
+/// 登出所有设备
+///
+/// 吊销该用户此前签发的所有 access token 并清除 refresh token，
+/// `keep_current_device` 为 true 时保留发起请求的会话
+pub async fn logout_all(
+    Extension(request_id): Extension<RequestId>,
+    State(state): State<AppState>,
+    Extension(user_id): Extension<String>,
+    Extension(session): Extension<TokenSession>,
+    Json(payload): Json<LogoutAllRequest>,
+) -> Result<Json<LogoutAllResponse>, ErrorResponse> {
+    log_info(&request_id, "登出所有设备请求", format!("user_id={}, keep_current_device={}", user_id, payload.keep_current_device));
+
+    let keep_session = payload.keep_current_device.then_some(session.0.as_str());
+    let keep_refresh_token = payload.refresh_token.as_deref().filter(|_| payload.keep_current_device);
+    let revoked_refresh_tokens = revoke_sessions(&state, &user_id, keep_session, keep_refresh_token)
+        .await
+        .map_err(|e| {
+            log_info(&request_id, "吊销会话失败", e.to_string());
+            ErrorResponse::new(format!("登出所有设备失败: {}", e))
+        })?;
+
+    let response = LogoutAllResponse {
+        revoked_refresh_tokens,
+        kept_current_device: payload.keep_current_device,
+    };
+    log_info(&request_id, "登出所有设备成功", &response);
+
+    Ok(Json(response))
+}
+
+/// 吊销用户此前签发的所有 access token 并清除 refresh token（`keep_*` 对应的会话除外）
+///
+/// 返回清除的 refresh token 数量
+async fn revoke_sessions(
+    state: &AppState,
+    user_id: &str,
+    keep_session: Option<&str>,
+    keep_refresh_token: Option<&str>,
+) -> anyhow::Result<u64> {
+    let ttl_seconds = (state.config.auth.jwt_expiration_days.max(1) * 24 * 3600) as u64;
+
+    state.token_blacklist
+        .revoke_user_sessions(user_id, chrono::Utc::now().timestamp(), keep_session, ttl_seconds)
+        .await?;
+
+    AuthService::new(state.pool.clone())
+        .revoke_refresh_tokens(user_id, keep_refresh_token)
+        .await
+}
+
+/// 修改密码
+///
+/// 验证原密码并按密码策略校验新密码；`logout_other_devices` 为 true 时
+/// 吊销其他设备的登录状态（发起请求的会话保持登录）
+pub async fn change_password(
+    Extension(request_id): Extension<RequestId>,
+    State(state): State<AppState>,
+    Extension(user_id): Extension<String>,
+    Extension(session): Extension<TokenSession>,
+    Json(payload): Json<ChangePasswordRequest>,
+) -> Result<Json<ChangePasswordResponse>, ErrorResponse> {
+    log_info(&request_id, "修改密码请求", format!("user_id={}, payload={:?}", user_id, payload));
+
+    AuthService::new(state.pool.clone())
+        .change_password(&user_id, &payload.old_password, &payload.new_password, &state.config.password_policy)
+        .await
+        .map_err(|e| {
+            log_info(&request_id, "修改密码失败", e.to_string());
+            match e.downcast_ref::<PasswordChangeError>() {
+                Some(PasswordChangeError::WrongPassword) => ErrorResponse::new_with_code(e.to_string(), 401, "WRONG_PASSWORD"),
+                Some(PasswordChangeError::WeakPassword(reason)) => ErrorResponse::new_with_code(reason.clone(), 400, "WEAK_PASSWORD"),
+                None => ErrorResponse::new(format!("修改密码失败: {}", e)),
+            }
+        })?;
+
+    let revoked_refresh_tokens = if payload.logout_other_devices {
+        revoke_sessions(&state, &user_id, Some(session.0.as_str()), payload.refresh_token.as_deref())
+            .await
+            .map_err(|e| {
+                log_info(&request_id, "吊销其他设备会话失败", e.to_string());
+                ErrorResponse::new(format!("密码已修改，但登出其他设备失败: {}", e))
+            })?
+    } else {
+        0
+    };
+
+    let response = ChangePasswordResponse {
+        revoked_refresh_tokens,
+        logged_out_other_devices: payload.logout_other_devices,
+    };
+    log_info(&request_id, "修改密码成功", &response);
+
+    Ok(Json(response))
+}
+
+/// 获取当前 token 对应的账号
+///
+/// 客户端用于校验本地保存的账号与服务器识别的账号是否一致
+pub async fn me(
+    Extension(request_id): Extension<RequestId>,
+    State(state): State<AppState>,
+    Extension(user_id): Extension<String>,
+) -> Result<Json<MeResponse>, ErrorResponse> {
+    log_info(&request_id, "获取当前账号请求", format!("user_id={}", user_id));
+
+    let email: Option<String> = sqlx::query_scalar("SELECT email FROM users WHERE id = ?")
+        .bind(&user_id)
+        .fetch_optional(&state.pool)
+        .await
+        .map_err(|e| {
+            log_info(&request_id, "查询用户失败", e.to_string());
+            ErrorResponse::new("查询用户失败")
+        })?;
+
+    let email = match email {
+        Some(email) => email,
+        None => {
+            log_info(&request_id, "用户不存在", format!("user_id={}", user_id));
+            return Err(ErrorResponse::new_with_code("用户不存在", 404, "USER_NOT_FOUND"));
+        }
+    };
+
+    let response = MeResponse { user_id, email };
+    log_info(&request_id, "获取当前账号成功", &response);
+
+    Ok(Json(response))
+}
+
+/// 导出账号数据
+///
+/// 以 JSON 流的形式返回用户在服务器上的全部数据（所有工作空间的笔记、文件夹、标签、
+/// 快照、附件，以及个人资料和设备），不包含密码哈希和令牌
+pub async fn export_account(
+    Extension(request_id): Extension<RequestId>,
+    State(state): State<AppState>,
+    Extension(user_id): Extension<String>,
+) -> Response {
+    log_info(&request_id, "导出账号数据请求", format!("user_id={}", user_id));
+
+    let filename = format!(
+        "cloud-memo-export-{}-{}.json",
+        user_id,
+        chrono::Utc::now().format("%Y%m%d")
+    );
+    let stream = ExportService::new(state.pool.clone()).stream(user_id);
+
+    (
+        [
+            (header::CONTENT_TYPE, "application/json".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        Body::from_stream(stream),
+    ).into_response()
 }
 
 /// 删除用户账号
@@ -422,3 +717,73 @@ fn extract_token_ttl(token: &str) -> Option<u64> {
         Some(60)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, USER_ID};
+
+    /// 准备一个密码为 `oldpass123` 的测试用户
+    async fn state_with_password() -> AppState {
+        let state = testing::state(testing::mysql_pool().await);
+        let hash = AuthService::new(state.pool.clone()).hash_password("oldpass123").unwrap();
+        sqlx::query("UPDATE users SET password_hash = ? WHERE id = ?")
+            .bind(&hash)
+            .bind(USER_ID)
+            .execute(&state.pool)
+            .await
+            .unwrap();
+        state
+    }
+
+    async fn change(state: &AppState, old_password: &str, new_password: &str) -> Result<Json<ChangePasswordResponse>, ErrorResponse> {
+        change_password(
+            Extension(RequestId("test".to_string())),
+            State(state.clone()),
+            Extension(USER_ID.to_string()),
+            Extension(TokenSession("session-1".to_string())),
+            Json(ChangePasswordRequest {
+                old_password: old_password.to_string(),
+                new_password: new_password.to_string(),
+                logout_other_devices: false,
+                refresh_token: None,
+            }),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    #[ignore = "需要 MySQL：设置 TEST_DATABASE_URL 后运行 cargo test -- --ignored"]
+    async fn test_change_password_with_wrong_old_password_is_unauthorized() {
+        let state = state_with_password().await;
+
+        let err = change(&state, "wrongpass1", "newpass456").await.unwrap_err();
+
+        assert_eq!(err.status, Some(401));
+        assert_eq!(err.error_code.as_deref(), Some("WRONG_PASSWORD"));
+    }
+
+    #[tokio::test]
+    #[ignore = "需要 MySQL：设置 TEST_DATABASE_URL 后运行 cargo test -- --ignored"]
+    async fn test_change_password_with_weak_new_password_is_bad_request() {
+        let state = state_with_password().await;
+
+        let err = change(&state, "oldpass123", "short").await.unwrap_err();
+
+        assert_eq!(err.status, Some(400));
+        assert_eq!(err.error_code.as_deref(), Some("WEAK_PASSWORD"));
+    }
+
+    #[tokio::test]
+    #[ignore = "需要 MySQL：设置 TEST_DATABASE_URL 后运行 cargo test -- --ignored"]
+    async fn test_change_password_succeeds_and_old_password_stops_working() {
+        let state = state_with_password().await;
+
+        let response = change(&state, "oldpass123", "newpass456").await.unwrap();
+        assert_eq!(response.revoked_refresh_tokens, 0);
+        assert!(!response.logged_out_other_devices);
+
+        let err = change(&state, "oldpass123", "another789").await.unwrap_err();
+        assert_eq!(err.status, Some(401));
+    }
+}
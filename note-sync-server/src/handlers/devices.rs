@@ -1,6 +1,6 @@
 use super::ErrorResponse;
 use crate::middleware::logging::{log_info, RequestId};
-use crate::models::Device;
+use crate::models::{Device, UpdateDeviceRequest};
 use crate::services::device_service::DeviceService;
 use crate::AppState;
 use axum::http::StatusCode;
@@ -19,7 +19,9 @@ pub async fn list_devices(
 
     let service = DeviceService::new(state.pool);
 
-    match service.list_devices(&user_id).await {
+    let online_threshold_secs = state.config.devices.online_threshold_minutes * 60;
+
+    match service.list_devices(&user_id, online_threshold_secs).await {
         Ok(devices) => {
             log_info(
                 &request_id,
@@ -66,6 +68,43 @@ pub async fn revoke_device(
     }
 }
 
+/// 更新设备（重命名、标记受信任）
+pub async fn update_device(
+    Extension(request_id): Extension<RequestId>,
+    State(state): State<AppState>,
+    Extension(user_id): Extension<String>,
+    Path(device_id): Path<String>,
+    Json(req): Json<UpdateDeviceRequest>,
+) -> Result<Json<Device>, ErrorResponse> {
+    log_info(
+        &request_id,
+        "更新设备请求",
+        format!("user_id={}, device_id={}, name={:?}, trusted={:?}", user_id, device_id, req.name, req.trusted),
+    );
+
+    let service = DeviceService::new(state.pool);
+
+    let online_threshold_secs = state.config.devices.online_threshold_minutes * 60;
+
+    match service.update_device(&device_id, &user_id, &req, online_threshold_secs).await {
+        Ok(device) => {
+            log_info(&request_id, "更新成功", format!("device_name={}", device.device_name));
+            Ok(Json(device))
+        }
+        Err(e) => {
+            let msg = e.to_string();
+            log_info(&request_id, "更新失败", &msg);
+            if msg.contains("not found") {
+                Err(ErrorResponse::new("设备不存在"))
+            } else if msg.contains("must not exceed") {
+                Err(ErrorResponse::new("设备名称过长"))
+            } else {
+                Err(ErrorResponse::new("更新设备失败"))
+            }
+        }
+    }
+}
+
 /// 设备心跳
 pub async fn device_heartbeat(
     Extension(request_id): Extension<RequestId>,
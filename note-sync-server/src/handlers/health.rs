@@ -0,0 +1,127 @@
+use axum::{Json, extract::State, http::StatusCode};
+use serde::Serialize;
+use std::future::Future;
+use std::time::Duration;
+use crate::AppState;
+
+/// 单个依赖的探测超时时间
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 依赖状态
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct DependencyStatus {
+    pub status: &'static str,  // "up" / "down"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,  // 不可用的原因
+}
+
+impl DependencyStatus {
+    fn is_up(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// 健康检查结果
+#[derive(Debug, Serialize)]
+pub struct HealthReport {
+    pub status: &'static str,  // "ok" / "degraded"
+    pub version: &'static str,  // 服务器版本
+    pub database: DependencyStatus,
+    pub redis: DependencyStatus,
+}
+
+impl HealthReport {
+    fn new(database: DependencyStatus, redis: DependencyStatus) -> Self {
+        let healthy = database.is_up() && redis.is_up();
+        Self {
+            status: if healthy { "ok" } else { "degraded" },
+            version: env!("CARGO_PKG_VERSION"),
+            database,
+            redis,
+        }
+    }
+
+    /// 任一关键依赖不可用时返回 503
+    fn status_code(&self) -> StatusCode {
+        if self.database.is_up() && self.redis.is_up() {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+    }
+}
+
+/// 在超时时间内执行依赖探测
+async fn probe<F>(check: F, timeout: Duration) -> DependencyStatus
+where
+    F: Future<Output = anyhow::Result<()>>,
+{
+    let error = match tokio::time::timeout(timeout, check).await {
+        Ok(Ok(())) => None,
+        Ok(Err(e)) => Some(e.to_string()),
+        Err(_) => Some(format!("探测超时（{}ms）", timeout.as_millis())),
+    };
+    DependencyStatus {
+        status: if error.is_none() { "up" } else { "down" },
+        error,
+    }
+}
+
+/// 健康检查端点
+///
+/// 分别探测 MySQL 和 Redis，任一不可用时返回 503
+pub async fn health_check(State(state): State<AppState>) -> (StatusCode, Json<HealthReport>) {
+    let database = probe(
+        async {
+            state.pool.acquire().await?;
+            Ok(())
+        },
+        PROBE_TIMEOUT,
+    );
+    let redis = probe(state.token_blacklist.ping(), PROBE_TIMEOUT);
+    let (database, redis) = tokio::join!(database, redis);
+
+    let report = HealthReport::new(database, redis);
+    if report.status_code() != StatusCode::OK {
+        tracing::warn!("健康检查失败: {:?}", report);
+    }
+    (report.status_code(), Json(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_all_dependencies_healthy() {
+        let database = probe(async { Ok(()) }, PROBE_TIMEOUT).await;
+        let redis = probe(async { Ok(()) }, PROBE_TIMEOUT).await;
+
+        let report = HealthReport::new(database, redis);
+
+        assert_eq!(report.status_code(), StatusCode::OK);
+        assert_eq!(report.status, "ok");
+        assert_eq!(report.redis, DependencyStatus { status: "up", error: None });
+    }
+
+    #[tokio::test]
+    async fn test_redis_down_returns_service_unavailable() {
+        let database = probe(async { Ok(()) }, PROBE_TIMEOUT).await;
+        let redis = probe(async { Err(anyhow::anyhow!("Connection refused")) }, PROBE_TIMEOUT).await;
+
+        let report = HealthReport::new(database, redis);
+
+        assert_eq!(report.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(report.status, "degraded");
+        assert_eq!(report.database.status, "up");
+        assert_eq!(report.redis.status, "down");
+        assert!(report.redis.error.as_deref().unwrap().contains("Connection refused"));
+    }
+
+    #[tokio::test]
+    async fn test_hanging_probe_times_out() {
+        let status = probe(std::future::pending(), Duration::from_millis(10)).await;
+
+        assert_eq!(status.status, "down");
+    }
+}
@@ -2,32 +2,59 @@ use axum::{Json, extract::{Query, State}, Extension};
 use serde::Deserialize;
 use axum::http::StatusCode;
 use crate::AppState;
-use crate::services::sync_history_service::SyncHistoryService;
-use crate::models::SyncHistoryEntry;
+use crate::services::sync_history_service::{SyncHistoryService, SyncHistoryFilter, SyncOutcome, HistoryCursor, DEFAULT_HISTORY_PAGE_SIZE};
+use crate::models::{DeviceSyncSummary, SyncHistoryPage};
 use crate::middleware::logging::{RequestId, log_info};
 use super::ErrorResponse;
 
 #[derive(Debug, Deserialize)]
 pub struct HistoryQueryParams {
     limit: Option<usize>,
+    #[serde(default)]
+    outcome: SyncOutcome,  // all | conflicts | errors
+    sync_type: Option<String>,  // 同步类型
+    from: Option<i64>,  // 起始时间戳（秒）
+    to: Option<i64>,  // 结束时间戳（秒）
+    before: Option<String>,  // 分页游标（上一页返回的 next_cursor）
 }
 
-/// 获取同步历史记录
+/// 获取同步历史记录（默认返回最近 50 条，通过 next_cursor 翻页）
 pub async fn get_history(
     Extension(request_id): Extension<RequestId>,
     State(state): State<AppState>,
     Extension(user_id): Extension<String>,
     Query(params): Query<HistoryQueryParams>,
-) -> Result<Json<Vec<SyncHistoryEntry>>, ErrorResponse> {
-    let limit = params.limit.unwrap_or(50);
-    log_info(&request_id, "获取同步历史请求", &format!("user_id={}, limit={}", user_id, limit));
+) -> Result<Json<SyncHistoryPage>, ErrorResponse> {
+    let before = match params.before.as_deref() {
+        Some(value) => Some(HistoryCursor::parse(value).ok_or_else(|| {
+            ErrorResponse::new_with_code("分页游标无效", 400, "INVALID_CURSOR")
+        })?),
+        None => None,
+    };
+    let filter = SyncHistoryFilter {
+        outcome: params.outcome,
+        sync_type: params.sync_type.filter(|t| !t.is_empty()),
+        from: params.from,
+        to: params.to,
+        before,
+        limit: params.limit.unwrap_or(DEFAULT_HISTORY_PAGE_SIZE),
+    };
+    log_info(&request_id, "获取同步历史请求", format!("user_id={}, filter={:?}", user_id, filter));
+
+    if !filter.has_valid_range() {
+        return Err(ErrorResponse::new_with_code("起始时间不能晚于结束时间", 400, "INVALID_DATE_RANGE"));
+    }
 
     let service = SyncHistoryService::new(state.pool);
 
-    match service.list(&user_id, limit).await {
-        Ok(history) => {
-            log_info(&request_id, "获取成功", &format!("记录数量={}", history.len()));
-            Ok(Json(history))
+    match service.list(&user_id, &filter).await {
+        Ok(page) => {
+            log_info(
+                &request_id,
+                "获取成功",
+                format!("记录数量={}, next_cursor={:?}", page.entries.len(), page.next_cursor),
+            );
+            Ok(Json(page))
         }
         Err(e) => {
             log_info(&request_id, "获取失败", &e.to_string());
@@ -36,6 +63,28 @@ pub async fn get_history(
     }
 }
 
+/// 按设备汇总同步历史
+pub async fn get_device_summary(
+    Extension(request_id): Extension<RequestId>,
+    State(state): State<AppState>,
+    Extension(user_id): Extension<String>,
+) -> Result<Json<Vec<DeviceSyncSummary>>, ErrorResponse> {
+    log_info(&request_id, "获取设备同步统计请求", format!("user_id={}", user_id));
+
+    let service = SyncHistoryService::new(state.pool);
+
+    match service.summarize_by_device(&user_id).await {
+        Ok(summaries) => {
+            log_info(&request_id, "获取成功", format!("设备数量={}", summaries.len()));
+            Ok(Json(summaries))
+        }
+        Err(e) => {
+            log_info(&request_id, "获取失败", e.to_string());
+            Err(ErrorResponse::new("获取设备同步统计失败"))
+        }
+    }
+}
+
 /// 清空同步历史
 pub async fn clear_history(
     Extension(request_id): Extension<RequestId>,
@@ -57,3 +106,35 @@ pub async fn clear_history(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    async fn history(query: &str) -> Result<Json<SyncHistoryPage>, ErrorResponse> {
+        let uri: axum::http::Uri = format!("/sync/history?{}", query).parse().unwrap();
+        get_history(
+            Extension(RequestId("test".to_string())),
+            State(testing::state(testing::lazy_pool())),
+            Extension(testing::USER_ID.to_string()),
+            Query::try_from_uri(&uri).unwrap(),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_inverted_date_range_is_rejected() {
+        let err = history("from=300&to=200").await.unwrap_err();
+
+        assert_eq!(err.status, Some(400));
+        assert_eq!(err.error_code.as_deref(), Some("INVALID_DATE_RANGE"));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_cursor_is_rejected() {
+        let err = history("before=not-a-cursor").await.unwrap_err();
+
+        assert_eq!(err.error_code.as_deref(), Some("INVALID_CURSOR"));
+    }
+}
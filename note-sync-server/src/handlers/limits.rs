@@ -0,0 +1,15 @@
+use axum::{Json, extract::State, Extension};
+use crate::AppState;
+use crate::config::LimitsConfig;
+use crate::middleware::logging::{RequestId, log_info};
+
+/// 获取服务器限制
+///
+/// 客户端据此在超出限制前提前提示（如笔记长度、快照数量）
+pub async fn get_limits(
+    Extension(request_id): Extension<RequestId>,
+    State(state): State<AppState>,
+) -> Json<LimitsConfig> {
+    log_info(&request_id, "获取服务器限制", format!("{:?}", state.config.limits));
+    Json(state.config.limits.clone())
+}
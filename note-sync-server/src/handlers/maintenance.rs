@@ -0,0 +1,51 @@
+use axum::{Json, extract::State, Extension};
+use serde::Deserialize;
+use crate::AppState;
+use crate::services::maintenance_service::{MaintenanceService, PurgeNoteVersionsResult};
+use crate::middleware::logging::{RequestId, log_info};
+use super::ErrorResponse;
+
+/// 清理版本快照请求
+#[derive(Debug, Default, Deserialize)]
+pub struct PurgeNoteVersionsRequest {
+    workspace_id: Option<String>,  // 只清理该工作空间（为空时清理全部）
+    retention_days: Option<i64>,  // 已删除笔记的快照保留天数（为空时使用服务器配置）
+}
+
+/// 清理无效的版本快照（所属笔记已不存在或已删除超过保留期）
+pub async fn purge_note_versions(
+    Extension(request_id): Extension<RequestId>,
+    State(state): State<AppState>,
+    Extension(user_id): Extension<String>,
+    req: Option<Json<PurgeNoteVersionsRequest>>,
+) -> Result<Json<PurgeNoteVersionsResult>, ErrorResponse> {
+    let req = req.map(|Json(req)| req).unwrap_or_default();
+    log_info(&request_id, "清理版本快照请求", format!("user_id={}, req={:?}", user_id, req));
+
+    let retention_days = req.retention_days
+        .unwrap_or(state.config.maintenance.note_version_retention_days);
+    if retention_days < 0 {
+        return Err(ErrorResponse::new_with_code("保留天数不能为负数", 400, "INVALID_RETENTION_DAYS"));
+    }
+
+    let service = MaintenanceService::new(state.pool);
+
+    match service
+        .purge_note_versions(
+            &user_id,
+            req.workspace_id.as_deref(),
+            retention_days,
+            state.config.maintenance.purge_batch_size,
+        )
+        .await
+    {
+        Ok(result) => {
+            log_info(&request_id, "清理成功", format!("{:?}", result));
+            Ok(Json(result))
+        }
+        Err(e) => {
+            log_info(&request_id, "清理失败", e.to_string());
+            Err(ErrorResponse::new("清理版本快照失败"))
+        }
+    }
+}
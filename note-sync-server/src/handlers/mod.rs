@@ -1,5 +1,9 @@
-use axum::{http::StatusCode, Json, response::IntoResponse};
+use axum::{http::{header, HeaderMap, HeaderValue, StatusCode}, Json, response::{IntoResponse, Response}};
 use serde::Serialize;
+use std::future::Future;
+use crate::middleware::logging::{log_info, RequestId};
+use crate::services::idempotency_store::IdempotencyError;
+use crate::AppState;
 
 pub mod auth;
 pub mod sync;
@@ -9,6 +13,10 @@ pub mod devices;
 pub mod history;
 pub mod profile;
 pub mod workspaces;
+pub mod maintenance;
+pub mod health;
+pub mod limits;
+pub mod search;
 
 /// 统一的错误响应结构
 #[derive(Debug, Serialize)]
@@ -18,6 +26,8 @@ pub struct ErrorResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error_code: Option<String>,  // 应用错误代码
     pub error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_secs: Option<u64>,  // 建议的重试等待时间（秒）
 }
 
 impl ErrorResponse {
@@ -26,6 +36,7 @@ impl ErrorResponse {
             status: None,
             error_code: None,
             error: message.into(),
+            retry_after_secs: None,
         }
     }
 
@@ -34,8 +45,15 @@ impl ErrorResponse {
             status: Some(status),
             error_code: Some(error_code.into()),
             error: message.into(),
+            retry_after_secs: None,
         }
     }
+
+    /// 附加重试等待时间（同时写入 `Retry-After` 响应头）
+    pub fn with_retry_after(mut self, secs: u64) -> Self {
+        self.retry_after_secs = Some(secs.max(1));
+        self
+    }
 }
 
 impl IntoResponse for ErrorResponse {
@@ -43,6 +61,101 @@ impl IntoResponse for ErrorResponse {
         let status_code = self.status
             .and_then(|s| StatusCode::from_u16(s).ok())
             .unwrap_or(StatusCode::BAD_REQUEST);
-        (status_code, Json(self)).into_response()
+        let retry_after = self.retry_after_secs;
+        let mut response = (status_code, Json(self)).into_response();
+        if let Some(secs) = retry_after {
+            response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(secs));
+        }
+        response
+    }
+}
+
+/// 幂等键请求头
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+/// 重放响应的标记请求头
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+/// 幂等键最大长度
+const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+
+/// 校验幂等键（1–255 个可见 ASCII 字符）
+pub(crate) fn validate_idempotency_key(key: &str) -> Result<(), ErrorResponse> {
+    if key.is_empty() || key.len() > MAX_IDEMPOTENCY_KEY_LEN || !key.bytes().all(|b| b.is_ascii_graphic()) {
+        return Err(ErrorResponse::new_with_code(
+            format!("无效的幂等键（需为 1–{} 个可见 ASCII 字符）", MAX_IDEMPOTENCY_KEY_LEN),
+            400,
+            "INVALID_IDEMPOTENCY_KEY",
+        ));
+    }
+    Ok(())
+}
+
+/// 读取并校验 `Idempotency-Key` 请求头
+pub(crate) fn idempotency_key_from_headers(headers: &HeaderMap) -> Result<Option<String>, ErrorResponse> {
+    let Some(value) = headers.get(IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(None);
+    };
+    let key = value.to_str().unwrap_or_default();
+    validate_idempotency_key(key)?;
+    Ok(Some(key.to_string()))
+}
+
+/// 以幂等方式执行 handler，返回 JSON 响应
+///
+/// 相同 `scope` 下重复的幂等键返回首次处理的响应（带 `Idempotent-Replayed: true` 响应头），
+/// 首次请求在等待时间内仍未完成时返回 409（带 `Retry-After`，客户端可用同一个键重试），
+/// 幂等键已用于内容不同的请求（`fingerprint` 不同）时返回 422
+pub(crate) async fn idempotent_json<T, F, Fut>(
+    state: &AppState,
+    request_id: &RequestId,
+    scope: &str,
+    key: Option<&str>,
+    fingerprint: &str,
+    op: F,
+) -> Result<Response, ErrorResponse>
+where
+    T: Serialize,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<Json<T>, ErrorResponse>>,
+{
+    let result = state.idempotency
+        .run(scope, key, fingerprint, || async { op().await.map(|Json(value)| value) })
+        .await;
+
+    let response = match result {
+        Ok(response) => response,
+        Err(IdempotencyError::Failed(e)) => return Err(e),
+        Err(IdempotencyError::InProgress) => {
+            log_info(request_id, "幂等键对应的请求仍在处理中", format!("key={:?}", key));
+            return Err(ErrorResponse::new_with_code(
+                "相同幂等键的请求正在处理中，请稍后重试",
+                409,
+                "IDEMPOTENCY_KEY_IN_USE",
+            ).with_retry_after(1));
+        }
+        Err(IdempotencyError::Mismatch) => {
+            log_info(request_id, "幂等键已用于内容不同的请求", format!("key={:?}", key));
+            return Err(ErrorResponse::new_with_code(
+                "幂等键已用于内容不同的请求",
+                422,
+                "IDEMPOTENCY_KEY_MISMATCH",
+            ));
+        }
+        Err(IdempotencyError::Store(e)) => {
+            log_info(request_id, "幂等键读写失败", e.to_string());
+            return Err(ErrorResponse::new_with_code("幂等键检查失败", 503, "IDEMPOTENCY_UNAVAILABLE"));
+        }
+    };
+
+    if response.replayed {
+        log_info(request_id, "重放幂等请求的首次处理结果", format!("key={:?}", key));
+    }
+
+    let mut http_response = (
+        [(header::CONTENT_TYPE, HeaderValue::from_static("application/json"))],
+        response.body,
+    ).into_response();
+    if response.replayed {
+        http_response.headers_mut().insert(IDEMPOTENT_REPLAYED_HEADER, HeaderValue::from_static("true"));
     }
+    Ok(http_response)
 }
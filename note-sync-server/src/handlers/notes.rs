@@ -1,10 +1,27 @@
-use super::ErrorResponse;
+use super::{idempotency_key_from_headers, idempotent_json, ErrorResponse};
+use super::search::note_plain_text;
+use super::sync::{note_stats, verify_workspace_ownership};
 use crate::middleware::logging::{log_info, RequestId};
 use crate::models::Note;
+use crate::services::idempotency_store::request_fingerprint;
 use crate::AppState;
 use axum::{Extension, Json};
 use axum::extract::State;
-use serde::Deserialize;
+use axum::http::HeaderMap;
+use axum::response::Response;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateNoteRequest {
+    pub title: String,
+    #[serde(default)]
+    pub content: String,
+    #[serde(default)]
+    pub folder_id: Option<String>,
+    /// 目标工作空间（未指定时使用默认空间）
+    #[serde(default)]
+    pub workspace_id: Option<String>,
+}
 
 #[derive(Debug, Deserialize)]
 pub struct CreateSnapshotRequest {
@@ -73,6 +90,141 @@ pub async fn list_notes(
     Ok(Json(notes))
 }
 
+/// 创建笔记
+///
+/// 支持 `Idempotency-Key` 请求头：超时重试时使用同一个键，返回首次创建的笔记而不会重复创建
+pub async fn create_note(
+    Extension(request_id): Extension<RequestId>,
+    State(state): State<AppState>,
+    Extension(user_id): Extension<String>,
+    headers: HeaderMap,
+    Json(req): Json<CreateNoteRequest>,
+) -> Result<Response, ErrorResponse> {
+    log_info(&request_id, "创建笔记请求", format!("user_id={}, workspace_id={:?}, title={}", user_id, req.workspace_id, req.title));
+
+    let idempotency_key = idempotency_key_from_headers(&headers)?;
+    let scope = format!("notes:{}", user_id);
+    let fingerprint = request_fingerprint(&req);
+
+    idempotent_json(&state, &request_id, &scope, idempotency_key.as_deref(), &fingerprint, || {
+        insert_note(&request_id, &state, &user_id, req)
+    })
+    .await
+}
+
+async fn insert_note(
+    request_id: &RequestId,
+    state: &AppState,
+    user_id: &str,
+    req: CreateNoteRequest,
+) -> Result<Json<Note>, ErrorResponse> {
+    let max_note_length = state.config.limits.max_note_length;
+    if max_note_length > 0 && req.content.chars().count() > max_note_length {
+        return Err(ErrorResponse::new_with_code(
+            format!("笔记「{}」超出长度限制（{} 字符）", req.title, max_note_length),
+            413,
+            "NOTE_TOO_LARGE",
+        ));
+    }
+
+    let workspace_id: Option<String> = match req.workspace_id {
+        Some(ws_id) => match verify_workspace_ownership(&state.pool, user_id, &ws_id).await {
+            Ok(true) => Some(ws_id),
+            Ok(false) => {
+                log_info(request_id, "工作空间验证失败", format!("workspace_id={} 不属于用户 user_id={}", ws_id, user_id));
+                return Err(ErrorResponse::new_with_code(
+                    format!("工作空间 {} 不属于当前用户", ws_id),
+                    403,
+                    "WORKSPACE_NOT_OWNED",
+                ));
+            }
+            Err(e) => {
+                log_info(request_id, "工作空间验证错误", &e);
+                return Err(ErrorResponse::new_with_code("验证工作空间归属失败", 500, "WORKSPACE_VERIFICATION_ERROR"));
+            }
+        },
+        None => sqlx::query_scalar(
+            "SELECT id FROM workspaces WHERE user_id = ? AND is_default = TRUE AND is_deleted = FALSE LIMIT 1"
+        )
+        .bind(user_id)
+        .fetch_optional(&state.pool)
+        .await
+        .map_err(|e| {
+            log_info(request_id, "查询默认工作空间失败", e.to_string());
+            ErrorResponse::new("查询默认工作空间失败")
+        })?,
+    };
+
+    let note_id = uuid::Uuid::new_v4().to_string();
+    let now = chrono::Utc::now().timestamp();
+    let (word_count, read_time_minutes) = note_stats(&req.content, state.config.note_stats.reading_speed_wpm);
+
+    sqlx::query(
+        "INSERT INTO notes (id, user_id, workspace_id, title, content, search_text, folder_id,
+                            created_at, updated_at, server_ver, word_count, read_time_minutes)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, 1, ?, ?)"
+    )
+    .bind(&note_id)
+    .bind(user_id)
+    .bind(&workspace_id)
+    .bind(&req.title)
+    .bind(&req.content)
+    .bind(note_plain_text(&req.content))
+    .bind(&req.folder_id)
+    .bind(now)
+    .bind(now)
+    .bind(word_count)
+    .bind(read_time_minutes)
+    .execute(&state.pool)
+    .await
+    .map_err(|e| {
+        log_info(request_id, "创建笔记失败", format!("error={}", e));
+        ErrorResponse::new("创建笔记失败")
+    })?;
+
+    let note = sqlx::query_as::<_, Note>("SELECT * FROM notes WHERE id = ?")
+        .bind(&note_id)
+        .fetch_one(&state.pool)
+        .await
+        .map_err(|e| {
+            log_info(request_id, "查询新创建的笔记失败", format!("error={}", e));
+            ErrorResponse::new("查询笔记失败")
+        })?;
+
+    log_info(request_id, "创建笔记成功", format!("note_id={}", note_id));
+    Ok(Json(note))
+}
+
+/// 获取单个笔记（包括已删除的笔记，便于客户端对比本地版本）
+pub async fn get_note(
+    Extension(request_id): Extension<RequestId>,
+    State(state): State<AppState>,
+    Extension(user_id): Extension<String>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<Json<Note>, ErrorResponse> {
+    log_info(&request_id, "获取笔记请求", &format!("user_id={}, note_id={}", user_id, id));
+
+    let note = sqlx::query_as::<_, Note>(
+        "SELECT * FROM notes WHERE id = ? AND user_id = ?"
+    )
+    .bind(&id)
+    .bind(&user_id)
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(|e| {
+        log_info(&request_id, "查询笔记失败", &e.to_string());
+        ErrorResponse::new("查询笔记失败")
+    })?;
+
+    match note {
+        Some(note) => {
+            log_info(&request_id, "查询笔记成功", &format!("note_id={}, server_ver={}", note.id, note.server_ver));
+            Ok(Json(note))
+        }
+        None => Err(ErrorResponse::new_with_code("笔记不存在", 404, "NOTE_NOT_FOUND")),
+    }
+}
+
 pub async fn create_snapshot(
     Extension(request_id): Extension<RequestId>,
     id: axum::extract::Path<String>,
@@ -93,3 +245,66 @@ pub async fn list_snapshots(
     log_info(&request_id, "列出快照", "TODO: 未实现");
     Err(ErrorResponse::new("快照功能暂未实现"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::{IDEMPOTENCY_KEY_HEADER, IDEMPOTENT_REPLAYED_HEADER};
+    use crate::testing::{self, USER_ID};
+    use axum::http::HeaderValue;
+
+    async fn create(state: &AppState, key: &str) -> Response {
+        let mut headers = HeaderMap::new();
+        headers.insert(IDEMPOTENCY_KEY_HEADER, HeaderValue::from_str(key).unwrap());
+        let req = CreateNoteRequest {
+            title: "周报".to_string(),
+            content: "本周完成同步改造".to_string(),
+            folder_id: None,
+            workspace_id: None,
+        };
+
+        create_note(
+            Extension(RequestId("test".to_string())),
+            State(state.clone()),
+            Extension(USER_ID.to_string()),
+            headers,
+            Json(req),
+        )
+        .await
+        .unwrap()
+    }
+
+    async fn body_note(response: Response) -> Note {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    #[ignore = "需要 MySQL：设置 TEST_DATABASE_URL 后运行 cargo test -- --ignored"]
+    async fn test_retried_create_with_same_key_inserts_one_note() {
+        let state = testing::state(testing::mysql_pool().await);
+
+        let first = create(&state, "create-1").await;
+        assert!(!first.headers().contains_key(IDEMPOTENT_REPLAYED_HEADER));
+        let first = body_note(first).await;
+
+        let replay = create(&state, "create-1").await;
+        assert_eq!(replay.headers()[IDEMPOTENT_REPLAYED_HEADER], "true");
+        assert_eq!(body_note(replay).await.id, first.id);
+
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM notes WHERE user_id = ?")
+            .bind(USER_ID)
+            .fetch_one(&state.pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+
+        create(&state, "create-2").await;
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM notes WHERE user_id = ?")
+            .bind(USER_ID)
+            .fetch_one(&state.pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+    }
+}
@@ -0,0 +1,361 @@
+use super::ErrorResponse;
+use super::sync::verify_workspace_ownership;
+use crate::middleware::logging::{log_info, RequestId};
+use crate::models::Note;
+use crate::AppState;
+use axum::extract::State;
+use axum::{Extension, Json};
+use serde::{Deserialize, Serialize};
+use sqlx::MySqlPool;
+
+/// 默认返回的搜索结果数量
+const DEFAULT_SEARCH_LIMIT: usize = 20;
+/// 最多返回的搜索结果数量
+const MAX_SEARCH_LIMIT: usize = 100;
+/// 数据库预筛选的候选笔记上限
+const MAX_SEARCH_CANDIDATES: i64 = 500;
+/// 片段中命中词前后保留的字符数
+const SNIPPET_CONTEXT_CHARS: usize = 30;
+
+#[derive(Debug, Deserialize)]
+pub struct SearchRequest {
+    pub query: String,
+    /// 搜索的工作空间（未指定时使用默认空间）
+    #[serde(default)]
+    pub workspace_id: Option<String>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// 搜索结果（笔记摘要）
+#[derive(Debug, Serialize, PartialEq)]
+pub struct SearchResult {
+    pub id: String,
+    pub title: String,
+    pub excerpt: Option<String>,
+    /// 命中词附近的正文片段
+    pub snippet: String,
+    pub updated_at: i64,
+}
+
+/// 在云端笔记中搜索（标题或正文包含关键词，不区分大小写）
+///
+/// 用于新设备尚未完成首次同步时搜索云端笔记
+pub async fn search_notes(
+    Extension(request_id): Extension<RequestId>,
+    State(state): State<AppState>,
+    Extension(user_id): Extension<String>,
+    Json(req): Json<SearchRequest>,
+) -> Result<Json<Vec<SearchResult>>, ErrorResponse> {
+    let query = req.query.trim();
+    log_info(&request_id, "搜索笔记请求", format!("user_id={}, workspace_id={:?}, query={}", user_id, req.workspace_id, query));
+
+    if query.is_empty() {
+        return Err(ErrorResponse::new_with_code("搜索关键词不能为空", 400, "EMPTY_QUERY"));
+    }
+
+    let workspace_id = match req.workspace_id {
+        Some(ws_id) => match verify_workspace_ownership(&state.pool, &user_id, &ws_id).await {
+            Ok(true) => Some(ws_id),
+            Ok(false) => {
+                log_info(&request_id, "工作空间验证失败", format!("workspace_id={} 不属于用户 user_id={}", ws_id, user_id));
+                return Err(ErrorResponse::new_with_code(
+                    format!("工作空间 {} 不属于当前用户", ws_id),
+                    403,
+                    "WORKSPACE_NOT_OWNED",
+                ));
+            }
+            Err(e) => {
+                log_info(&request_id, "工作空间验证错误", &e);
+                return Err(ErrorResponse::new_with_code(
+                    "验证工作空间归属失败".to_string(),
+                    500,
+                    "WORKSPACE_VERIFICATION_ERROR",
+                ));
+            }
+        },
+        None => sqlx::query_scalar(
+            "SELECT id FROM workspaces WHERE user_id = ? AND is_default = TRUE AND is_deleted = FALSE LIMIT 1"
+        )
+        .bind(&user_id)
+        .fetch_optional(&state.pool)
+        .await
+        .map_err(|e| {
+            log_info(&request_id, "查询默认工作空间失败", e.to_string());
+            ErrorResponse::new("查询默认工作空间失败")
+        })?,
+    };
+
+    let candidates = find_candidates(&state.pool, &user_id, workspace_id.as_deref(), query)
+        .await
+        .map_err(|e| {
+            log_info(&request_id, "搜索笔记失败", e.to_string());
+            ErrorResponse::new("搜索笔记失败")
+        })?;
+
+    let limit = req.limit.unwrap_or(DEFAULT_SEARCH_LIMIT).clamp(1, MAX_SEARCH_LIMIT);
+    let results = rank_search_results(candidates, &user_id, query, limit);
+
+    log_info(&request_id, "搜索笔记成功", format!("count={}", results.len()));
+    Ok(Json(results))
+}
+
+/// 查询标题或正文纯文本包含关键词的候选笔记（按更新时间倒序，最多 MAX_SEARCH_CANDIDATES 条）
+///
+/// 正文匹配 search_text（写入时从 Tiptap JSON 提取的纯文本），不会命中 JSON 的结构字段；
+/// 尚未生成 search_text 的旧笔记退回匹配 content，由 [`rank_search_results`] 再按正文过滤
+async fn find_candidates(
+    pool: &MySqlPool,
+    user_id: &str,
+    workspace_id: Option<&str>,
+    query: &str,
+) -> sqlx::Result<Vec<Note>> {
+    let pattern = like_pattern(query);
+    sqlx::query_as::<_, Note>(
+        "SELECT * FROM notes
+         WHERE user_id = ? AND (workspace_id = ? OR workspace_id IS NULL) AND is_deleted = FALSE
+           AND (title LIKE ? OR COALESCE(search_text, content) LIKE ?)
+         ORDER BY updated_at DESC
+         LIMIT ?"
+    )
+    .bind(user_id)
+    .bind(workspace_id)
+    .bind(&pattern)
+    .bind(&pattern)
+    .bind(MAX_SEARCH_CANDIDATES)
+    .fetch_all(pool)
+    .await
+}
+
+/// 生成 LIKE 匹配模式（转义 `%`、`_` 和 `\`）
+fn like_pattern(query: &str) -> String {
+    let mut pattern = String::with_capacity(query.len() + 2);
+    pattern.push('%');
+    for c in query.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+/// 从候选笔记中筛选真正命中的结果
+///
+/// 只保留属于 `user_id` 且未删除的笔记；标题命中的排在前面，其余保持候选顺序（按更新时间倒序）
+fn rank_search_results(candidates: Vec<Note>, user_id: &str, query: &str, limit: usize) -> Vec<SearchResult> {
+    let needle = query.to_lowercase();
+
+    let mut hits: Vec<(bool, SearchResult)> = candidates
+        .into_iter()
+        .filter(|note| note.user_id == user_id && !note.is_deleted)
+        .filter_map(|note| {
+            let text = note_plain_text(&note.content);
+            let title_hit = note.title.to_lowercase().contains(&needle);
+            let snippet = match snippet_around(&text, &needle) {
+                Some(snippet) => snippet,
+                None if title_hit => text.chars().take(SNIPPET_CONTEXT_CHARS * 2).collect(),
+                None => return None,
+            };
+
+            Some((title_hit, SearchResult {
+                id: note.id,
+                title: note.title,
+                excerpt: note.excerpt,
+                snippet,
+                updated_at: note.updated_at,
+            }))
+        })
+        .collect();
+
+    hits.sort_by_key(|(title_hit, _)| !title_hit);
+    hits.into_iter().take(limit).map(|(_, result)| result).collect()
+}
+
+/// 截取命中词附近的片段（未命中时返回 None），前后被截断时加省略号
+fn snippet_around(text: &str, needle: &str) -> Option<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let lower: Vec<char> = chars.iter().map(|c| c.to_lowercase().next().unwrap_or(*c)).collect();
+    let needle: Vec<char> = needle.chars().collect();
+    if needle.is_empty() || needle.len() > lower.len() {
+        return None;
+    }
+
+    let start = lower.windows(needle.len()).position(|window| window == needle.as_slice())?;
+    let from = start.saturating_sub(SNIPPET_CONTEXT_CHARS);
+    let to = (start + needle.len() + SNIPPET_CONTEXT_CHARS).min(chars.len());
+
+    let mut snippet = String::new();
+    if from > 0 {
+        snippet.push('…');
+    }
+    snippet.extend(&chars[from..to]);
+    if to < chars.len() {
+        snippet.push('…');
+    }
+    Some(snippet)
+}
+
+/// 提取笔记正文的纯文本（Tiptap JSON 只保留文本节点，其他内容原样返回），连续空白合并为一个空格
+pub(crate) fn note_plain_text(content: &str) -> String {
+    fn collect(node: &serde_json::Value, out: &mut String) {
+        if let Some(text) = node.get("text").and_then(|t| t.as_str()) {
+            out.push_str(text);
+        }
+        if let Some(children) = node.get("content").and_then(|c| c.as_array()) {
+            for child in children {
+                collect(child, out);
+            }
+            out.push(' ');
+        }
+    }
+
+    let doc = content.trim_start()
+        .starts_with('{')
+        .then(|| serde_json::from_str::<serde_json::Value>(content).ok())
+        .flatten()
+        .filter(|doc| doc.get("type").and_then(|t| t.as_str()) == Some("doc"));
+
+    let text = match doc {
+        Some(doc) => {
+            let mut text = String::new();
+            collect(&doc, &mut text);
+            text
+        }
+        None => content.to_string(),
+    };
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    fn note(id: &str, user_id: &str, title: &str, content: &str, updated_at: i64) -> Note {
+        Note {
+            id: id.to_string(),
+            user_id: user_id.to_string(),
+            workspace_id: Some("ws-1".to_string()),
+            title: title.to_string(),
+            content: content.to_string(),
+            folder_id: None,
+            is_deleted: false,
+            deleted_at: None,
+            created_at: 0,
+            updated_at,
+            server_ver: 1,
+            device_id: None,
+            updated_by_device: None,
+            excerpt: None,
+            markdown_cache: None,
+            is_favorite: false,
+            is_pinned: false,
+            is_private: false,
+            author: None,
+            word_count: 0,
+            read_time_minutes: 0,
+        }
+    }
+
+    #[test]
+    fn test_search_returns_matching_notes_of_user() {
+        let candidates = vec![
+            note("body-hit", "1000000001", "周报", "本周完成了同步服务的重构", 3),
+            note("other-user", "1000000002", "同步", "别人的笔记", 2),
+            note("title-hit", "1000000001", "同步设计", "草稿", 1),
+            note("miss", "1000000001", "购物清单", "牛奶", 0),
+        ];
+
+        let results = rank_search_results(candidates, "1000000001", "同步", 10);
+
+        let ids: Vec<_> = results.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["title-hit", "body-hit"]);
+        assert_eq!(results[1].snippet, "本周完成了同步服务的重构");
+    }
+
+    #[test]
+    fn test_search_ignores_tiptap_structure_and_respects_limit() {
+        let tiptap = r#"{"type":"doc","content":[{"type":"paragraph","content":[{"type":"text","text":"Hello World"}]}]}"#;
+        let candidates = vec![
+            note("a", "1000000001", "A", tiptap, 2),
+            note("b", "1000000001", "B", "hello again", 1),
+        ];
+
+        assert!(rank_search_results(candidates, "1000000001", "paragraph", 10).is_empty());
+
+        let candidates = vec![
+            note("a", "1000000001", "A", tiptap, 2),
+            note("b", "1000000001", "B", "hello again", 1),
+        ];
+        let results = rank_search_results(candidates, "1000000001", "HELLO", 1);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, "a");
+        assert_eq!(results[0].snippet, "Hello World");
+    }
+
+    #[test]
+    fn test_snippet_is_trimmed_around_match() {
+        let text = format!("{}关键词{}", "前".repeat(40), "后".repeat(40));
+        let snippet = snippet_around(&text, "关键词").unwrap();
+
+        assert_eq!(snippet, format!("…{}关键词{}…", "前".repeat(30), "后".repeat(30)));
+    }
+
+    #[test]
+    fn test_like_pattern_escapes_wildcards() {
+        assert_eq!(like_pattern("50%_off\\"), "%50\\%\\_off\\\\%");
+    }
+
+    /// 通过创建笔记接口写入测试笔记（与正式环境一样生成 search_text）
+    async fn create(state: &AppState, title: &str, content: &str) -> Note {
+        let response = crate::handlers::notes::create_note(
+            Extension(RequestId("test".to_string())),
+            State(state.clone()),
+            Extension(testing::USER_ID.to_string()),
+            axum::http::HeaderMap::new(),
+            Json(crate::handlers::notes::CreateNoteRequest {
+                title: title.to_string(),
+                content: content.to_string(),
+                folder_id: None,
+                workspace_id: None,
+            }),
+        )
+        .await
+        .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn tiptap(text: &str) -> String {
+        serde_json::json!({
+            "type": "doc",
+            "content": [{ "type": "paragraph", "content": [{ "type": "text", "text": text }] }],
+        })
+        .to_string()
+    }
+
+    #[tokio::test]
+    #[ignore = "需要 MySQL：设置 TEST_DATABASE_URL 后运行 cargo test -- --ignored"]
+    async fn test_candidates_match_plain_text_not_tiptap_structure() {
+        let state = testing::state(testing::mysql_pool().await);
+        for i in 0..3 {
+            create(&state, &format!("笔记 {}", i), &tiptap("牛奶和面包")).await;
+        }
+        let hit = create(&state, "排版", &tiptap("每个 paragraph 之间空一行")).await;
+        let plain = create(&state, "旧格式", "纯文本里的 Paragraph").await;
+
+        let candidates = find_candidates(&state.pool, testing::USER_ID, None, "paragraph").await.unwrap();
+
+        let mut ids: Vec<_> = candidates.iter().map(|n| n.id.clone()).collect();
+        ids.sort();
+        let mut expected = vec![hit.id, plain.id];
+        expected.sort();
+        assert_eq!(ids, expected);
+
+        // 结构字段不会命中，标题仍然可以命中
+        assert!(find_candidates(&state.pool, testing::USER_ID, None, "\"type\"").await.unwrap().is_empty());
+        assert_eq!(find_candidates(&state.pool, testing::USER_ID, None, "笔记 1").await.unwrap().len(), 1);
+    }
+}
@@ -1,21 +1,28 @@
-use axum::{extract::State, Extension, Json, http::HeaderMap};
+use axum::{extract::State, Extension, Json, http::HeaderMap, response::Response};
+use base64::{engine::general_purpose, Engine as _};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
 use sqlx::MySqlPool;
 
-use super::ErrorResponse;
+use super::search::note_plain_text;
+use super::{idempotency_key_from_headers, idempotent_json, validate_idempotency_key, ErrorResponse};
 use crate::middleware::logging::{log_info, RequestId};
-use crate::models::{Folder, Note, Tag, NoteVersion, NoteTagRelation, Workspace, ConflictResolutionStrategy};
-use crate::services::sync_history_service::SyncHistoryService;
-use crate::services::sync_lock_service::SyncLockService;
+use crate::models::{Attachment, Folder, Note, Tag, NoteVersion, NoteTagRelation, Workspace, ConflictResolutionStrategy, ConflictStrategies};
+use crate::services::idempotency_store::request_fingerprint;
+use crate::services::sync_history_service::{NewSyncHistory, SyncHistoryService};
+use crate::services::sync_lock_service::{SyncLockHeld, SyncLockService};
 use crate::AppState;
 
 /// 统一同步请求
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct SyncRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub last_sync_at: Option<i64>,
 
+    /// 上次同步返回的变更游标（提供时优先于 last_sync_at）
+    #[serde(default)]
+    pub cursor: Option<i64>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub workspace_id: Option<String>,
 
@@ -37,13 +44,25 @@ pub struct SyncRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub note_tags: Option<Vec<NoteTagRelation>>,
 
+    /// 笔记附件（数据以 Base64 编码）
+    #[serde(default)]
+    pub attachments: Option<Vec<Attachment>>,
+
     /// 冲突解决策略（默认：创建冲突副本）
     #[serde(default)]
     pub conflict_resolution: ConflictResolutionStrategy,
 
+    /// 按实体类型覆盖的冲突解决策略
+    #[serde(default)]
+    pub conflict_strategies: ConflictStrategies,
+
     /// 设备ID（用于操作锁和设备追踪）
     #[serde(default)]
     pub device_id: Option<String>,
+
+    /// 幂等键（与 `Idempotency-Key` 请求头等价，重试同一次同步时保持不变）
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
 }
 
 /// 统一同步响应
@@ -52,6 +71,8 @@ pub struct SyncResponse {
     pub status: String,  // "success" | "partial_success" | "error"
     pub server_time: i64,
     pub last_sync_at: i64,
+    /// 变更游标（客户端保存后在下次同步时回传）
+    pub cursor: i64,
 
     // 云端新增/更新的数据
     pub upserted_workspaces: Vec<Workspace>,
@@ -60,6 +81,7 @@ pub struct SyncResponse {
     pub upserted_tags: Vec<Tag>,
     pub upserted_snapshots: Vec<NoteVersion>,
     pub upserted_note_tags: Vec<NoteTagRelation>,
+    pub upserted_attachments: Vec<Attachment>,
 
     // 云端删除的数据（只返回 ID）
     pub deleted_note_ids: Vec<String>,
@@ -67,6 +89,7 @@ pub struct SyncResponse {
     pub deleted_tag_ids: Vec<String>,
     #[serde(default)]
     pub deleted_workspace_ids: Vec<String>,
+    pub deleted_attachment_ids: Vec<String>,
 
     // 推送统计（服务器确认实际更新的数量）
     pub pushed_workspaces: usize,
@@ -75,6 +98,7 @@ pub struct SyncResponse {
     pub pushed_tags: usize,
     pub pushed_snapshots: usize,
     pub pushed_note_tags: usize,
+    pub pushed_attachments: usize,
     pub pushed_total: usize,  // 推送总数
 
     // 拉取统计（服务器端真正的新数据，不包括客户端刚推送的数据）
@@ -84,6 +108,7 @@ pub struct SyncResponse {
     pub pulled_tags: usize,
     pub pulled_snapshots: usize,
     pub pulled_note_tags: usize,
+    pub pulled_attachments: usize,
     pub pulled_total: usize,  // 拉取总数
 
     // 冲突列表
@@ -91,19 +116,414 @@ pub struct SyncResponse {
     pub conflicts: Vec<ConflictInfo>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Default, Serialize)]
 pub struct ConflictInfo {
     pub id: String,
     pub entity_type: String,
     pub local_version: i32,
     pub server_version: i32,
     pub title: String,
+    /// 以下字段仅笔记冲突时返回，供客户端展示双方差异
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub local_content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub local_updated_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_updated_at: Option<i64>,
+}
+
+impl ConflictInfo {
+    /// 笔记冲突（附带客户端推送的内容和服务器当前内容）
+    fn note(local: &Note, server: &Note) -> Self {
+        Self {
+            id: local.id.clone(),
+            entity_type: "note".to_string(),
+            local_version: local.server_ver,
+            server_version: server.server_ver,
+            title: local.title.clone(),
+            local_content: Some(local.content.clone()),
+            server_content: Some(server.content.clone()),
+            local_updated_at: Some(local.updated_at),
+            server_updated_at: Some(server.updated_at),
+        }
+    }
+}
+
+/// LastWriteWins 比较 updated_at 时允许的设备时钟偏差（秒）
+const LAST_WRITE_WINS_SKEW_SECS: i64 = 5;
+
+/// LastWriteWins 的比较结果
+#[derive(Debug, PartialEq, Eq)]
+enum LastWriteWinner {
+    Local,
+    Server,
+    /// 两者相差不超过时钟偏差
+    Unclear,
+}
+
+/// 按 updated_at 判断哪个版本较新
+fn last_write_winner(local_updated_at: i64, server_updated_at: i64) -> LastWriteWinner {
+    let diff = local_updated_at - server_updated_at;
+    if diff > LAST_WRITE_WINS_SKEW_SECS {
+        LastWriteWinner::Local
+    } else if diff < -LAST_WRITE_WINS_SKEW_SECS {
+        LastWriteWinner::Server
+    } else {
+        LastWriteWinner::Unclear
+    }
+}
+
+/// 工作空间、文件夹、标签和附件支持的冲突解决策略（KeepBoth 只适用于笔记）
+const ENTITY_STRATEGIES: &[ConflictResolutionStrategy] = &[
+    ConflictResolutionStrategy::KeepServer,
+    ConflictResolutionStrategy::KeepLocal,
+    ConflictResolutionStrategy::ManualMerge,
+    ConflictResolutionStrategy::LastWriteWins,
+];
+
+/// 快照支持的冲突解决策略（快照没有修改时间，不支持 LastWriteWins）
+const SNAPSHOT_STRATEGIES: &[ConflictResolutionStrategy] = &[
+    ConflictResolutionStrategy::KeepServer,
+    ConflictResolutionStrategy::KeepLocal,
+    ConflictResolutionStrategy::ManualMerge,
+];
+
+/// 实体类型实际使用的冲突解决策略
+///
+/// 在 `conflict_strategies` 中为实体显式指定不支持的策略时返回 400；
+/// 继承自 `conflict_resolution` 的策略该实体不支持时（如默认的 KeepBoth）按 KeepServer 处理
+fn entity_strategy(
+    entity_type: &str,
+    configured: Option<ConflictResolutionStrategy>,
+    default: ConflictResolutionStrategy,
+    supported: &[ConflictResolutionStrategy],
+) -> Result<ConflictResolutionStrategy, ErrorResponse> {
+    match configured {
+        Some(strategy) if supported.contains(&strategy) => Ok(strategy),
+        Some(strategy) => Err(ErrorResponse::new_with_code(
+            format!("{} 不支持冲突解决策略 {:?}", entity_type, strategy),
+            400,
+            "UNSUPPORTED_CONFLICT_STRATEGY",
+        )),
+        None if supported.contains(&default) => Ok(default),
+        None => Ok(ConflictResolutionStrategy::KeepServer),
+    }
+}
+
+/// 笔记以外的实体发生版本冲突时的处理方式
+#[derive(Debug, PartialEq, Eq)]
+enum EntityConflictAction {
+    /// 用客户端版本覆盖服务器版本
+    Overwrite,
+    /// 保留服务器版本并记录冲突
+    KeepServer,
+    /// 服务器修改较新：保留服务器版本，不记录冲突（客户端会在本次响应中拉取服务器版本）
+    SkipStale,
+}
+
+/// 按冲突解决策略决定如何处理笔记以外的实体的版本冲突
+fn entity_conflict_action(
+    strategy: ConflictResolutionStrategy,
+    local_updated_at: i64,
+    server_updated_at: i64,
+) -> EntityConflictAction {
+    match strategy {
+        ConflictResolutionStrategy::KeepLocal => EntityConflictAction::Overwrite,
+        ConflictResolutionStrategy::LastWriteWins => match last_write_winner(local_updated_at, server_updated_at) {
+            LastWriteWinner::Local => EntityConflictAction::Overwrite,
+            LastWriteWinner::Server => EntityConflictAction::SkipStale,
+            LastWriteWinner::Unclear => EntityConflictAction::KeepServer,
+        },
+        ConflictResolutionStrategy::KeepServer
+        | ConflictResolutionStrategy::ManualMerge
+        | ConflictResolutionStrategy::KeepBoth => EntityConflictAction::KeepServer,
+    }
+}
+
+/// 写入后的 server_ver：取服务器与客户端版本中较大的一个再加 1
+///
+/// 本地优先（KeepLocal）覆盖较新的服务器版本时，版本号也不能回退，
+/// 否则其他设备会把这次修改当作旧版本
+fn next_server_ver(existing: Option<i32>, local: i32) -> i32 {
+    existing.unwrap_or(0).max(local) + 1
+}
+
+/// 用服务器计算的字数和阅读时间覆盖客户端上传的值，保证各设备显示一致
+fn with_server_note_stats(notes: Vec<Note>, reading_speed_wpm: u32) -> Vec<Note> {
+    notes
+        .into_iter()
+        .map(|mut note| {
+            (note.word_count, note.read_time_minutes) = note_stats(&note.content, reading_speed_wpm);
+            note
+        })
+        .collect()
+}
+
+/// 计算笔记字数和预计阅读时间（分钟）
+///
+/// 按正文纯文本计数（Tiptap JSON 的结构不计入）：每个中日韩字符计为一个字，
+/// 其余按空白分隔计词；阅读时间向上取整，最少 1 分钟
+pub(crate) fn note_stats(content: &str, reading_speed_wpm: u32) -> (i32, i32) {
+    let mut words = 0usize;
+    let mut in_word = false;
+
+    for c in note_plain_text(content).chars() {
+        if is_cjk(c) {
+            words += 1;
+            in_word = false;
+        } else if c.is_whitespace() {
+            in_word = false;
+        } else if !in_word {
+            words += 1;
+            in_word = true;
+        }
+    }
+
+    let words = words.min(i32::MAX as usize) as i32;
+    let read_time = (words as u32).div_ceil(reading_speed_wpm.max(1)).max(1);
+    (words, read_time as i32)
+}
+
+/// 是否为中日韩文字（汉字、假名、谚文）
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}'     // 平假名、片假名
+        | '\u{3400}'..='\u{4DBF}'   // 汉字扩展 A
+        | '\u{4E00}'..='\u{9FFF}'   // 常用汉字
+        | '\u{AC00}'..='\u{D7AF}'   // 谚文音节
+        | '\u{F900}'..='\u{FAFF}'   // 兼容汉字
+        | '\u{20000}'..='\u{2FA1F}' // 汉字扩展 B 及以后
+    )
+}
+
+/// 校验附件数据，返回解码后的大小（字节）
+///
+/// 数据必须是有效的 Base64，解码后不能超过 `max_bytes`
+fn validate_attachment(attachment: &Attachment, max_bytes: i64) -> Result<i64, ErrorResponse> {
+    let decoded = general_purpose::STANDARD.decode(&attachment.data).map_err(|_| {
+        ErrorResponse::new_with_code(
+            format!("附件「{}」数据不是有效的 Base64", attachment.filename),
+            400,
+            "INVALID_ATTACHMENT",
+        )
+    })?;
+
+    let size_bytes = decoded.len() as i64;
+    if size_bytes > max_bytes {
+        return Err(ErrorResponse::new_with_code(
+            format!("附件「{}」超出大小限制（{} 字节）", attachment.filename, max_bytes),
+            413,
+            "ATTACHMENT_TOO_LARGE",
+        ));
+    }
+
+    Ok(size_bytes)
+}
+
+/// 拉取云端更新的条件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PullFilter {
+    /// 拉取 change_seq 大于游标的数据
+    ChangeSeq(i64),
+    /// 未提供游标的旧客户端：拉取时间戳晚于 last_sync_at 的数据
+    Timestamp(i64),
+}
+
+impl PullFilter {
+    fn from_request(req: &SyncRequest) -> Self {
+        match req.cursor {
+            Some(cursor) => PullFilter::ChangeSeq(cursor),
+            None => PullFilter::Timestamp(req.last_sync_at.unwrap_or(0)),
+        }
+    }
+
+    /// 用于比较的列（`seq_column` 或 `time_column`）
+    fn column<'a>(&self, seq_column: &'a str, time_column: &'a str) -> &'a str {
+        match self {
+            PullFilter::ChangeSeq(_) => seq_column,
+            PullFilter::Timestamp(_) => time_column,
+        }
+    }
+
+    /// 与列比较的下界（不含）
+    fn value(&self) -> i64 {
+        match self {
+            PullFilter::ChangeSeq(cursor) => *cursor,
+            PullFilter::Timestamp(last_sync_at) => *last_sync_at,
+        }
+    }
+}
+
+/// 批量更新笔记删除状态时每条 UPDATE 处理的笔记数量
+const NOTE_DELETE_STATE_BATCH_SIZE: usize = 500;
+
+/// 是否只修改了删除状态（移到回收站或从回收站恢复），且没有版本冲突
+///
+/// 这类修改不需要逐条执行完整的 upsert，可以合并为一条 UPDATE；
+/// 其他字段有变化时仍然走逐条处理的流程
+fn is_delete_state_only_change(local: &Note, server: &Note) -> bool {
+    server.server_ver <= local.server_ver
+        && local.is_deleted != server.is_deleted
+        && local.title == server.title
+        && local.content == server.content
+        && local.folder_id == server.folder_id
+        && local.excerpt == server.excerpt
+        && local.markdown_cache == server.markdown_cache
+        && local.is_favorite == server.is_favorite
+        && local.is_pinned == server.is_pinned
+        && local.is_private == server.is_private
+        && local.author == server.author
+}
+
+/// 批量更新 `count` 篇笔记删除状态的 SQL
+///
+/// 绑定顺序：每篇笔记的 (id, is_deleted)、每篇笔记的 (id, deleted_at)、每篇笔记的 (id, server_ver)、
+/// device_id、updated_by_device、user_id、每篇笔记的 id
+fn note_delete_state_update_sql(count: usize) -> String {
+    let cases = vec!["WHEN ? THEN ?"; count].join(" ");
+    format!(
+        "UPDATE notes SET
+            is_deleted = CASE id {cases} END,
+            deleted_at = CASE id {cases} END,
+            updated_at = UNIX_TIMESTAMP(),
+            server_ver = CASE id {cases} END,
+            device_id = ?,
+            updated_by_device = ?
+         WHERE user_id = ? AND id IN ({})",
+        vec!["?"; count].join(", ")
+    )
+}
+
+/// 按服务器上的当前版本拆分推送的笔记，返回 (可以批量更新删除状态的笔记, 需要逐条处理的笔记)
+///
+/// 服务器上不存在的笔记（新建）也需要逐条处理
+fn split_delete_state_changes(
+    notes: Vec<Note>,
+    existing: &std::collections::HashMap<String, Note>,
+) -> (Vec<Note>, Vec<Note>) {
+    notes.into_iter().partition(|note| {
+        existing.get(&note.id).is_some_and(|server| is_delete_state_only_change(note, server))
+    })
+}
+
+/// 将只修改了删除状态的笔记批量写入（每批一条 UPDATE），返回 (需要逐条处理的笔记, 批量写入的数量)
+async fn apply_note_delete_states(
+    tx: &mut sqlx::Transaction<'_, sqlx::MySql>,
+    request_id: &RequestId,
+    user_id: &str,
+    workspace_id: &Option<String>,
+    notes: Vec<Note>,
+    device_id: &Option<String>,
+    updated_by_device: &str,
+) -> Result<(Vec<Note>, usize), ErrorResponse> {
+    let mut remaining = Vec::with_capacity(notes.len());
+    let mut applied = 0;
+    let mut notes = notes.into_iter().peekable();
+
+    while notes.peek().is_some() {
+        let chunk: Vec<Note> = notes.by_ref().take(NOTE_DELETE_STATE_BATCH_SIZE).collect();
+
+        let sql = format!(
+            "SELECT * FROM notes WHERE user_id = ? AND (workspace_id = ? OR workspace_id IS NULL) AND id IN ({}) FOR UPDATE",
+            vec!["?"; chunk.len()].join(", ")
+        );
+        let mut query = sqlx::query_as::<_, Note>(&sql).bind(user_id).bind(workspace_id);
+        for note in &chunk {
+            query = query.bind(&note.id);
+        }
+        let existing: std::collections::HashMap<String, Note> = query
+            .fetch_all(&mut **tx)
+            .await
+            .map_err(|e| {
+                log_info(request_id, "批量查询笔记失败", e.to_string());
+                ErrorResponse::new("查询笔记失败")
+            })?
+            .into_iter()
+            .map(|note| (note.id.clone(), note))
+            .collect();
+
+        let (batch, rest) = split_delete_state_changes(chunk, &existing);
+        remaining.extend(rest);
+        if batch.is_empty() {
+            continue;
+        }
+
+        let sql = note_delete_state_update_sql(batch.len());
+        let mut query = sqlx::query(&sql);
+        for note in &batch {
+            query = query.bind(&note.id).bind(note.is_deleted);
+        }
+        for note in &batch {
+            query = query.bind(&note.id).bind(if note.is_deleted { note.deleted_at } else { None });
+        }
+        for note in &batch {
+            let server_ver = next_server_ver(existing.get(&note.id).map(|server| server.server_ver), note.server_ver);
+            query = query.bind(&note.id).bind(server_ver);
+        }
+        query = query.bind(device_id).bind(updated_by_device).bind(user_id);
+        for note in &batch {
+            query = query.bind(&note.id);
+        }
+        query.execute(&mut **tx).await.map_err(|e| {
+            log_info(request_id, "批量更新笔记删除状态失败", e.to_string());
+            ErrorResponse::new("更新笔记失败")
+        })?;
+
+        log_info(request_id, "批量更新笔记删除状态", format!("count={}", batch.len()));
+        applied += batch.len();
+    }
+
+    Ok((remaining, applied))
+}
+
+/// 文件夹的父子关系（用于检测循环引用）
+///
+/// 记录文件夹 id 到 parent_id 的映射，同步过程中每写入一个文件夹就更新一次
+#[derive(Debug, Default)]
+struct FolderTree {
+    parents: std::collections::HashMap<String, Option<String>>,
+}
+
+impl FolderTree {
+    fn new(links: impl IntoIterator<Item = (String, Option<String>)>) -> Self {
+        let parents = links.into_iter()
+            .map(|(id, parent_id)| (id, normalize_parent_id(parent_id.as_deref())))
+            .collect();
+        Self { parents }
+    }
+
+    /// 把 `folder_id` 移动到 `parent_id` 下是否会让它成为自己的祖先
+    ///
+    /// 从新的父文件夹开始向上查找，遇到自己（或已存在的环）即为循环引用
+    fn would_create_cycle(&self, folder_id: &str, parent_id: Option<&str>) -> bool {
+        let mut visited = std::collections::HashSet::new();
+        let mut current = normalize_parent_id(parent_id);
+
+        while let Some(id) = current {
+            if id == folder_id || !visited.insert(id.clone()) {
+                return true;
+            }
+            current = self.parents.get(&id).cloned().flatten();
+        }
+        false
+    }
+
+    fn set_parent(&mut self, folder_id: &str, parent_id: Option<&str>) {
+        self.parents.insert(folder_id.to_string(), normalize_parent_id(parent_id));
+    }
+}
+
+/// 空字符串的 parent_id 与 NULL 等价（根文件夹）
+fn normalize_parent_id(parent_id: Option<&str>) -> Option<String> {
+    parent_id.filter(|p| !p.is_empty()).map(str::to_string)
 }
 
 /// 验证工作空间是否属于当前用户
 ///
 /// 在同步前验证，防止恶意客户端访问其他用户的工作空间
-async fn verify_workspace_ownership(
+pub(crate) async fn verify_workspace_ownership(
     pool: &MySqlPool,
     user_id: &str,
     workspace_id: &str,
@@ -121,7 +541,34 @@ async fn verify_workspace_ownership(
 }
 
 /// 统一同步接口：合并 push 和 pull
+///
+/// 请求带幂等键（`Idempotency-Key` 请求头或请求体的 `idempotency_key`）时，
+/// 重试同一次同步返回首次处理的结果，不会重复应用推送（避免产生冲突副本）
 pub async fn sync(
+    Extension(request_id): Extension<RequestId>,
+    State(state): State<AppState>,
+    Extension(user_id): Extension<String>,
+    headers: HeaderMap,
+    Json(mut req): Json<SyncRequest>,
+) -> Result<Response, ErrorResponse> {
+    let idempotency_key = match req.idempotency_key.take() {
+        Some(key) => {
+            validate_idempotency_key(&key)?;
+            Some(key)
+        }
+        None => idempotency_key_from_headers(&headers)?,
+    };
+    let scope = format!("sync:{}", user_id);
+    let fingerprint = request_fingerprint(&req);
+
+    idempotent_json(&state, &request_id, &scope, idempotency_key.as_deref(), &fingerprint, || {
+        sync_once(Extension(request_id.clone()), State(state.clone()), Extension(user_id.clone()), headers.clone(), Json(req))
+    })
+    .await
+}
+
+/// 处理一次同步请求
+async fn sync_once(
     Extension(request_id): Extension<RequestId>,
     State(state): State<AppState>,
     Extension(user_id): Extension<String>,
@@ -177,12 +624,36 @@ pub async fn sync(
     log_info(&request_id, "工作空间ID", &format!("workspace_id={:?}", workspace_id));
 
     // 处理可选字段，None 转为空数组
+    let pull_filter = PullFilter::from_request(&req);
     let workspaces = req.workspaces.unwrap_or_default();
-    let notes = req.notes.unwrap_or_default();
+    let reading_speed_wpm = state.config.note_stats.reading_speed_wpm;
+    let notes = with_server_note_stats(req.notes.unwrap_or_default(), reading_speed_wpm);
     let folders = req.folders.unwrap_or_default();
     let tags = req.tags.unwrap_or_default();
     let snapshots = req.snapshots.unwrap_or_default();
     let note_tags = req.note_tags.unwrap_or_default();
+    let mut attachments = req.attachments.unwrap_or_default();
+
+    // 检查笔记长度限制（按字符计数，0 表示不限制）
+    let max_note_length = state.config.limits.max_note_length;
+    if max_note_length > 0 {
+        if let Some(note) = notes.iter().find(|n| n.content.chars().count() > max_note_length) {
+            log_info(&request_id, "笔记超出长度限制", format!("id={}, max_note_length={}", note.id, max_note_length));
+            return Err(ErrorResponse::new_with_code(
+                format!("笔记「{}」超出长度限制（{} 字符）", note.title, max_note_length),
+                413,
+                "NOTE_TOO_LARGE",
+            ));
+        }
+    }
+
+    // 检查附件大小限制（已删除的附件不再校验数据），大小以服务器解码结果为准
+    let max_attachment_bytes = state.config.limits.max_attachment_bytes;
+    for attachment in attachments.iter_mut().filter(|a| !a.is_deleted) {
+        attachment.size_bytes = validate_attachment(attachment, max_attachment_bytes).inspect_err(|e| {
+            log_info(&request_id, "附件校验失败", format!("id={}, error={}", attachment.id, e.error));
+        })?;
+    }
 
     let workspaces_count = workspaces.len();
     let notes_count = notes.len();
@@ -190,6 +661,7 @@ pub async fn sync(
     let tags_count = tags.len();
     let snapshots_count = snapshots.len();
     let note_tags_count = note_tags.len();
+    let attachments_count = attachments.len();
 
     // 收集客户端推送的 workspace ID（用于后续计算真实的 pulled 统计）
     let pushed_workspace_ids: std::collections::HashSet<String> = workspaces.iter().map(|w| w.id.clone()).collect();
@@ -201,16 +673,18 @@ pub async fn sync(
     let mut pushed_tags = 0usize;
     let mut pushed_snapshots = 0usize;
     let mut pushed_note_tags = 0usize;
+    let mut pushed_attachments = 0usize;
 
     // 打印同步请求参数
     log_info(
         &request_id,
         "同步请求参数",
         &format!(
-            "user_id={}, device_id={:?}, conflict_resolution={:?}, last_sync_at={:?}, workspaces={}, notes={}, folders={}, tags={}, snapshots={}, note_tags={}",
+            "user_id={}, device_id={:?}, conflict_resolution={:?}, conflict_strategies={:?}, last_sync_at={:?}, workspaces={}, notes={}, folders={}, tags={}, snapshots={}, note_tags={}",
             user_id,
             req.device_id,
             req.conflict_resolution,
+            req.conflict_strategies,
             req.last_sync_at,
             workspaces_count,
             notes_count,
@@ -299,26 +773,40 @@ pub async fn sync(
         );
     }
 
+    // 各实体类型实际使用的冲突解决策略（未单独配置时使用 conflict_resolution）
+    let strategies = req.conflict_strategies;
+    let default_strategy = req.conflict_resolution;
+    let workspace_strategy = entity_strategy("workspace", strategies.workspace, default_strategy, ENTITY_STRATEGIES)?;
+    let note_strategy = strategies.note.unwrap_or(default_strategy);
+    let folder_strategy = entity_strategy("folder", strategies.folder, default_strategy, ENTITY_STRATEGIES)?;
+    let tag_strategy = entity_strategy("tag", strategies.tag, default_strategy, ENTITY_STRATEGIES)?;
+    let snapshot_strategy = entity_strategy("snapshot", strategies.snapshot, default_strategy, SNAPSHOT_STRATEGIES)?;
+    let attachment_strategy = entity_strategy("attachment", strategies.attachment, default_strategy, ENTITY_STRATEGIES)?;
+
     let start_time = Utc::now().timestamp();
     let history_service = SyncHistoryService::new(state.pool.clone());
     let lock_service = SyncLockService::new(state.pool.clone());
 
     // 获取操作锁（使用 RAII Guard 自动释放，包含工作空间）
-    // 锁持续时间：30秒（作为兜底，实际会在 sync 完成后立即释放）
+    // 锁有效期为 sync_lock.ttl_secs（作为兜底，实际会在 sync 完成后立即释放；超时的遗留锁可被回收）
     let device_id = req.device_id.as_deref().unwrap_or("unknown");
     let _lock_guard = lock_service.acquire_guard(
         &user_id,
         device_id,
         workspace_id.as_deref(),  // 传入 workspace_id
-        30,
+        state.config.sync_lock.ttl_secs,
     ).await
         .map_err(|e| {
             log_info(&request_id, "获取同步锁失败", &e.to_string());
-            ErrorResponse::new_with_code(
-                format!("该用户的其他工作空间正在同步，请稍后重试"),
+            let response = ErrorResponse::new_with_code(
+                "该用户的其他工作空间正在同步，请稍后重试",
                 409,  // Conflict
                 "SYNC_IN_PROGRESS",
-            )
+            );
+            match e.downcast_ref::<SyncLockHeld>() {
+                Some(held) => response.with_retry_after(held.retry_after_secs as u64),
+                None => response,
+            }
         })?;
 
     log_info(&request_id, "获取同步锁成功", &format!("device_id={}, workspace_id={:?}", device_id, workspace_id));
@@ -329,6 +817,19 @@ pub async fn sync(
         ErrorResponse::new("开始事务失败")
     })?;
 
+    // 锁定用户的变更计数器：事务提交前其他写入需等待，保证拉取时不会漏掉序号更小的变更
+    sqlx::query(
+        "INSERT INTO sync_change_seq (user_id, seq) VALUES (?, 0)
+         ON DUPLICATE KEY UPDATE seq = seq"
+    )
+    .bind(&user_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| {
+        log_info(&request_id, "锁定变更计数器失败", e.to_string());
+        ErrorResponse::new("锁定变更计数器失败")
+    })?;
+
     let mut conflicts = Vec::new();
 
     // ===== 1. 保存客户端更改（带版本冲突检测） =====
@@ -350,19 +851,34 @@ pub async fn sync(
                     ErrorResponse::new("查询工作空间失败")
                 })?;
 
+        let existing_server_ver = existing.as_ref().map(|ws| ws.server_ver);
+
         if let Some(existing_ws) = existing {
             log_info(&request_id, "工作空间已存在", &format!("id={}, server_ver={}", workspace.id, existing_ws.server_ver));
-            // 冲突检测：如果服务器版本比本地版本新，记录冲突并跳过
+            // 冲突检测：如果服务器版本比本地版本新，根据策略处理
             if existing_ws.server_ver > workspace.server_ver {
                 log_info(&request_id, "检测到冲突", &format!("id={}, local_ver={}, server_ver={}", workspace.id, workspace.server_ver, existing_ws.server_ver));
-                conflicts.push(ConflictInfo {
-                    id: workspace.id.clone(),
-                    entity_type: "workspace".to_string(),
-                    local_version: workspace.server_ver,
-                    server_version: existing_ws.server_ver,
-                    title: workspace.name.clone(),
-                });
-                continue;
+                match entity_conflict_action(workspace_strategy, workspace.updated_at, existing_ws.updated_at) {
+                    EntityConflictAction::Overwrite => {
+                        log_info(&request_id, "冲突解决：本地版本覆盖服务器版本", format!("workspace_id={}, strategy={:?}",
+                            workspace.id, workspace_strategy));
+                    }
+                    EntityConflictAction::SkipStale => {
+                        log_info(&request_id, "冲突解决：服务器修改较新", format!("workspace_id={}", workspace.id));
+                        continue;
+                    }
+                    EntityConflictAction::KeepServer => {
+                        conflicts.push(ConflictInfo {
+                            id: workspace.id.clone(),
+                            entity_type: "workspace".to_string(),
+                            local_version: workspace.server_ver,
+                            server_version: existing_ws.server_ver,
+                            title: workspace.name.clone(),
+                            ..Default::default()
+                        });
+                        continue;
+                    }
+                }
             } else {
                 log_info(&request_id, "无冲突，正常更新", &format!("id={}, server_ver={} -> {}", workspace.id, existing_ws.server_ver, existing_ws.server_ver + 1));
             }
@@ -371,7 +887,7 @@ pub async fn sync(
         }
 
         // 插入或更新工作空间
-        let new_server_ver = workspace.server_ver + 1;
+        let new_server_ver = next_server_ver(existing_server_ver, workspace.server_ver);
 
         // 构建设备描述
         let updated_by_device = format!(
@@ -395,7 +911,7 @@ pub async fn sync(
                 is_deleted = VALUES(is_deleted),
                 deleted_at = VALUES(deleted_at),
                 updated_at = UNIX_TIMESTAMP(),
-                server_ver = server_ver + 1,
+                server_ver = GREATEST(server_ver + 1, VALUES(server_ver)),
                 device_id = VALUES(device_id),
                 updated_by_device = VALUES(updated_by_device)"
         )
@@ -448,6 +964,22 @@ pub async fn sync(
     // 提前收集客户端推送的数据 ID（用于后续计算真实的 pulled 统计）
     let pushed_note_ids: std::collections::HashSet<String> = notes.iter().map(|n| n.id.clone()).collect();
 
+    // 批量处理移到回收站 / 从回收站恢复的笔记（一次删除数百篇笔记时避免逐条 upsert）
+    let (notes, bulk_applied_notes) = apply_note_delete_states(
+        &mut tx,
+        &request_id,
+        &user_id,
+        &workspace_id,
+        notes,
+        &req.device_id,
+        &format!(
+            "{} ({})",
+            req.device_id.as_deref().unwrap_or("unknown"),
+            user_agent.as_deref().unwrap_or("Unknown Device")
+        ),
+    ).await?;
+    pushed_notes += bulk_applied_notes;
+
     for note in notes {
         // 使用 FOR UPDATE 锁定行，防止并发修改
         log_info(&request_id, "查询笔记", &format!("id={}, local_ver={}", note.id, note.server_ver));
@@ -463,22 +995,18 @@ pub async fn sync(
                     ErrorResponse::new("查询笔记失败")
                 })?;
 
+        let existing_server_ver = existing.as_ref().map(|n| n.server_ver);
+
         if let Some(existing_note) = existing {
             log_info(&request_id, "笔记已存在", &format!("id={}, server_ver={}", note.id, existing_note.server_ver));
             // 冲突检测：如果服务器版本比本地版本新，根据策略处理
             if existing_note.server_ver > note.server_ver {
                 log_info(&request_id, "检测到冲突", &format!("id={}, local_ver={}, server_ver={}", note.id, note.server_ver, existing_note.server_ver));
-                match req.conflict_resolution {
+                match note_strategy {
                     ConflictResolutionStrategy::KeepServer => {
                         // 服务器版本优先，跳过更新
                         log_info(&request_id, "冲突解决：保留服务器版本", &format!("id={}", note.id));
-                        conflicts.push(ConflictInfo {
-                            id: note.id.clone(),
-                            entity_type: "note".to_string(),
-                            local_version: note.server_ver,
-                            server_version: existing_note.server_ver,
-                            title: note.title.clone(),
-                        });
+                        conflicts.push(ConflictInfo::note(&note, &existing_note));
                         continue;
                     }
                     ConflictResolutionStrategy::KeepLocal => {
@@ -498,18 +1026,19 @@ pub async fn sync(
                         );
 
                         sqlx::query(
-                            "INSERT INTO notes (id, user_id, workspace_id, title, content, folder_id,
+                            "INSERT INTO notes (id, user_id, workspace_id, title, content, search_text, folder_id,
                               is_deleted, deleted_at, created_at, updated_at, server_ver,
-                              excerpt, markdown_cache, is_favorite, is_pinned, author,
+                              excerpt, markdown_cache, is_favorite, is_pinned, is_private, author,
                               word_count, read_time_minutes,
                               device_id, updated_by_device)
-                             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+                             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
                         )
                         .bind(&conflict_copy_id)
                         .bind(&user_id)
                         .bind(&workspace_id)
                         .bind(&format!("{} (冲突副本-本地)", note.title))
                         .bind(&note.content)
+                        .bind(note_plain_text(&note.content))
                         .bind(&note.folder_id)
                         .bind(note.is_deleted)
                         .bind(note.deleted_at)
@@ -520,6 +1049,7 @@ pub async fn sync(
                         .bind(&note.markdown_cache)
                         .bind(note.is_favorite)
                         .bind(note.is_pinned)
+                        .bind(note.is_private)
                         .bind(&note.author)
                         .bind(note.word_count)
                         .bind(note.read_time_minutes)
@@ -533,26 +1063,35 @@ pub async fn sync(
                         })?;
 
                         log_info(&request_id, "创建冲突副本", &format!("original_id={}, copy_id={}", note.id, conflict_copy_id));
-                        conflicts.push(ConflictInfo {
-                            id: note.id.clone(),
-                            entity_type: "note".to_string(),
-                            local_version: note.server_ver,
-                            server_version: existing_note.server_ver,
-                            title: note.title.clone(),
-                        });
+                        conflicts.push(ConflictInfo::note(&note, &existing_note));
                         continue;
                     }
                     ConflictResolutionStrategy::ManualMerge => {
                         // 等待手动合并，记录冲突
-                        conflicts.push(ConflictInfo {
-                            id: note.id.clone(),
-                            entity_type: "note".to_string(),
-                            local_version: note.server_ver,
-                            server_version: existing_note.server_ver,
-                            title: note.title.clone(),
-                        });
+                        conflicts.push(ConflictInfo::note(&note, &existing_note));
                         continue;
                     }
+                    ConflictResolutionStrategy::LastWriteWins => {
+                        match last_write_winner(note.updated_at, existing_note.updated_at) {
+                            LastWriteWinner::Local => {
+                                // 本地修改较新，强制更新
+                                log_info(&request_id, "冲突解决：本地修改较新", format!("note_id={}, local_updated_at={}, server_updated_at={}",
+                                    note.id, note.updated_at, existing_note.updated_at));
+                            }
+                            LastWriteWinner::Server => {
+                                // 服务器修改较新，跳过更新（客户端会在本次响应中拉取服务器版本）
+                                log_info(&request_id, "冲突解决：服务器修改较新", format!("note_id={}, local_updated_at={}, server_updated_at={}",
+                                    note.id, note.updated_at, existing_note.updated_at));
+                                continue;
+                            }
+                            LastWriteWinner::Unclear => {
+                                // 修改时间过于接近，无法判断先后，保留服务器版本并记录冲突
+                                log_info(&request_id, "冲突解决：修改时间相近，保留服务器版本", format!("note_id={}", note.id));
+                                conflicts.push(ConflictInfo::note(&note, &existing_note));
+                                continue;
+                            }
+                        }
+                    }
                 }
             } else {
                 log_info(&request_id, "无冲突，正常更新", &format!("id={}, server_ver={} -> {}", note.id, existing_note.server_ver, existing_note.server_ver + 1));
@@ -562,8 +1101,8 @@ pub async fn sync(
         }
 
         // 插入或更新笔记
-        // 注意：VALUES(server_ver) + 1 确保第一次插入时 server_ver = 1（客户端发送 0），更新时 server_ver = server_ver + 1
-        let new_server_ver = note.server_ver + 1;
+        // 注意：第一次插入时 server_ver = 1（客户端发送 0），更新时取服务器与客户端版本的较大值 + 1
+        let new_server_ver = next_server_ver(existing_server_ver, note.server_ver);
 
         // 构建设备描述
         let updated_by_device = format!(
@@ -573,24 +1112,26 @@ pub async fn sync(
         );
 
         sqlx::query(
-            "INSERT INTO notes (id, user_id, workspace_id, title, content, folder_id,
+            "INSERT INTO notes (id, user_id, workspace_id, title, content, search_text, folder_id,
                               is_deleted, deleted_at, created_at, updated_at, server_ver,
-                              excerpt, markdown_cache, is_favorite, is_pinned, author,
+                              excerpt, markdown_cache, is_favorite, is_pinned, is_private, author,
                               word_count, read_time_minutes,
                               device_id, updated_by_device)
-             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
              ON DUPLICATE KEY UPDATE
                 title = VALUES(title),
                 content = VALUES(content),
+                search_text = VALUES(search_text),
                 folder_id = VALUES(folder_id),
                 is_deleted = VALUES(is_deleted),
                 deleted_at = VALUES(deleted_at),
                 updated_at = UNIX_TIMESTAMP(),
-                server_ver = server_ver + 1,
+                server_ver = GREATEST(server_ver + 1, VALUES(server_ver)),
                 excerpt = VALUES(excerpt),
                 markdown_cache = VALUES(markdown_cache),
                 is_favorite = VALUES(is_favorite),
                 is_pinned = VALUES(is_pinned),
+                is_private = VALUES(is_private),
                 author = VALUES(author),
                 word_count = VALUES(word_count),
                 read_time_minutes = VALUES(read_time_minutes),
@@ -602,16 +1143,18 @@ pub async fn sync(
         .bind(&workspace_id)
         .bind(&note.title)
         .bind(&note.content)
+        .bind(note_plain_text(&note.content))
         .bind(&note.folder_id)
         .bind(note.is_deleted)
         .bind(note.deleted_at)
         .bind(note.created_at)
         .bind(note.updated_at)
-        .bind(new_server_ver)  // ✅ 使用 new_server_ver（客户端 server_ver + 1）
+        .bind(new_server_ver)  // ✅ 使用 new_server_ver（服务器与客户端版本的较大值 + 1）
         .bind(&note.excerpt)
         .bind(&note.markdown_cache)
         .bind(note.is_favorite)
         .bind(note.is_pinned)
+        .bind(note.is_private)
         .bind(&note.author)
         .bind(note.word_count)
         .bind(note.read_time_minutes)
@@ -652,6 +1195,24 @@ pub async fn sync(
     let mut iteration_count = 0;
     let max_iterations = folders.len() + 1; // 防止无限循环
 
+    // 当前的文件夹树，用于拒绝会形成循环引用的 parent_id（例如把文件夹移动到自己的子文件夹下）
+    let folder_links: Vec<(String, Option<String>)> = if folders.is_empty() {
+        Vec::new()
+    } else {
+        sqlx::query_as(
+            "SELECT id, parent_id FROM folders WHERE user_id = ? AND (workspace_id = ? OR workspace_id IS NULL)"
+        )
+        .bind(&user_id)
+        .bind(&workspace_id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| {
+            log_info(&request_id, "查询文件夹树失败", e.to_string());
+            ErrorResponse::new("查询文件夹树失败")
+        })?
+    };
+    let mut folder_tree = FolderTree::new(folder_links);
+
     while !remaining_ids.is_empty() && iteration_count < max_iterations {
         iteration_count += 1;
         let mut inserted_in_this_iteration = Vec::new();
@@ -703,24 +1264,56 @@ pub async fn sync(
                             ErrorResponse::new("查询文件夹失败")
                         })?;
 
+                let existing_server_ver = existing.as_ref().map(|f| f.server_ver);
+
                 if let Some(existing_folder) = existing {
                     if existing_folder.server_ver > folder.server_ver {
-                        conflicts.push(ConflictInfo {
-                            id: folder.id.clone(),
-                            entity_type: "folder".to_string(),
-                            local_version: folder.server_ver,
-                            server_version: existing_folder.server_ver,
-                            title: folder.name.clone(),
-                        });
-                        inserted_in_this_iteration.push(folder.id.clone());
-                        continue;
+                        match entity_conflict_action(folder_strategy, folder.updated_at, existing_folder.updated_at) {
+                            EntityConflictAction::Overwrite => {
+                                log_info(&request_id, "冲突解决：本地版本覆盖服务器版本", format!("folder_id={}, strategy={:?}, local_ver={}, server_ver={}",
+                                    folder.id, folder_strategy, folder.server_ver, existing_folder.server_ver));
+                            }
+                            EntityConflictAction::SkipStale => {
+                                log_info(&request_id, "冲突解决：服务器修改较新", format!("folder_id={}", folder.id));
+                                inserted_in_this_iteration.push(folder.id.clone());
+                                continue;
+                            }
+                            EntityConflictAction::KeepServer => {
+                                conflicts.push(ConflictInfo {
+                                    id: folder.id.clone(),
+                                    entity_type: "folder".to_string(),
+                                    local_version: folder.server_ver,
+                                    server_version: existing_folder.server_ver,
+                                    title: folder.name.clone(),
+                                    ..Default::default()
+                                });
+                                inserted_in_this_iteration.push(folder.id.clone());
+                                continue;
+                            }
+                        }
                     }
                 } else {
                     log_info(&request_id, "文件夹不存在，新建", &format!("id={}, name={}", folder.id, folder.name));
                 }
 
+                // 拒绝循环引用：记录为冲突，客户端拉取云端的文件夹树后恢复
+                if folder_tree.would_create_cycle(&folder.id, folder.parent_id.as_deref()) {
+                    log_info(&request_id, "拒绝循环引用的文件夹", format!("folder_id={}, parent_id={:?}",
+                        folder.id, folder.parent_id));
+                    conflicts.push(ConflictInfo {
+                        id: folder.id.clone(),
+                        entity_type: "folder".to_string(),
+                        local_version: folder.server_ver,
+                        server_version: existing_server_ver.unwrap_or(0),
+                        title: folder.name.clone(),
+                        ..Default::default()
+                    });
+                    inserted_in_this_iteration.push(folder.id.clone());
+                    continue;
+                }
+
                 // 插入或更新文件夹
-                let new_server_ver = folder.server_ver + 1;
+                let new_server_ver = next_server_ver(existing_server_ver, folder.server_ver);
 
                 // 构建设备描述
                 let updated_by_device = format!(
@@ -738,7 +1331,7 @@ pub async fn sync(
                         name = VALUES(name),
                         parent_id = VALUES(parent_id),
                         updated_at = UNIX_TIMESTAMP(),
-                        server_ver = server_ver + 1,
+                        server_ver = GREATEST(server_ver + 1, VALUES(server_ver)),
                         device_id = VALUES(device_id),
                         updated_by_device = VALUES(updated_by_device)",
                 )
@@ -749,7 +1342,7 @@ pub async fn sync(
                 .bind(&folder.parent_id)
                 .bind(folder.created_at)
                 .bind(folder.updated_at)
-                .bind(new_server_ver)  // ✅ 使用 new_server_ver（客户端 server_ver + 1）
+                .bind(new_server_ver)  // ✅ 使用 new_server_ver（服务器与客户端版本的较大值 + 1）
                 .bind(&req.device_id)
                 .bind(&updated_by_device)
                 .execute(&mut *tx)
@@ -761,6 +1354,7 @@ pub async fn sync(
 
                 // ✅ 推送成功，递增计数器
                 pushed_folders += 1;
+                folder_tree.set_parent(&folder.id, folder.parent_id.as_deref());
 
                 // 验证：只查询 server_ver 字段
                 let verify_server_ver: Option<i32> = sqlx::query_scalar(
@@ -815,21 +1409,36 @@ pub async fn sync(
                     ErrorResponse::new("查询标签失败")
                 })?;
 
+        let existing_server_ver = existing.as_ref().map(|t| t.server_ver);
+
         if let Some(existing_tag) = existing {
             if existing_tag.server_ver > tag.server_ver {
-                conflicts.push(ConflictInfo {
-                    id: tag.id.clone(),
-                    entity_type: "tag".to_string(),
-                    local_version: tag.server_ver,
-                    server_version: existing_tag.server_ver,
-                    title: tag.name.clone(),
-                });
-                continue;
+                match entity_conflict_action(tag_strategy, tag.updated_at, existing_tag.updated_at) {
+                    EntityConflictAction::Overwrite => {
+                        log_info(&request_id, "冲突解决：本地版本覆盖服务器版本", format!("tag_id={}, strategy={:?}, local_ver={}, server_ver={}",
+                            tag.id, tag_strategy, tag.server_ver, existing_tag.server_ver));
+                    }
+                    EntityConflictAction::SkipStale => {
+                        log_info(&request_id, "冲突解决：服务器修改较新", format!("tag_id={}", tag.id));
+                        continue;
+                    }
+                    EntityConflictAction::KeepServer => {
+                        conflicts.push(ConflictInfo {
+                            id: tag.id.clone(),
+                            entity_type: "tag".to_string(),
+                            local_version: tag.server_ver,
+                            server_version: existing_tag.server_ver,
+                            title: tag.name.clone(),
+                            ..Default::default()
+                        });
+                        continue;
+                    }
+                }
             }
         }
 
         // 插入或更新标签
-        let new_server_ver = tag.server_ver + 1;
+        let new_server_ver = next_server_ver(existing_server_ver, tag.server_ver);
 
         // 构建设备描述
         let updated_by_device = format!(
@@ -846,7 +1455,7 @@ pub async fn sync(
                 name = VALUES(name),
                 color = VALUES(color),
                 updated_at = UNIX_TIMESTAMP(),
-                server_ver = server_ver + 1,
+                server_ver = GREATEST(server_ver + 1, VALUES(server_ver)),
                 device_id = VALUES(device_id),
                 updated_by_device = VALUES(updated_by_device)",
         )
@@ -857,7 +1466,7 @@ pub async fn sync(
         .bind(&tag.color)
         .bind(tag.created_at)
         .bind(tag.updated_at)
-        .bind(new_server_ver)  // ✅ 使用 new_server_ver（客户端 server_ver + 1）
+        .bind(new_server_ver)  // ✅ 使用 new_server_ver（服务器与客户端版本的较大值 + 1）
         .bind(&req.device_id)
         .bind(&updated_by_device)
         .execute(&mut *tx)
@@ -871,7 +1480,8 @@ pub async fn sync(
         pushed_tags += 1;
     }
 
-    // 更新 snapshots（限制每个笔记最多 20 个）
+    // 更新 snapshots（限制每个笔记的快照数量，见 limits.max_snapshots_per_note）
+    let max_snapshots_per_note = state.config.limits.max_snapshots_per_note;
     log_info(&request_id, "开始处理快照同步", &format!("snapshots_count={}", snapshots_count));
 
     // 提前收集 snapshots 的 ID（用于后续计算 pulled 统计）
@@ -927,9 +1537,9 @@ pub async fn sync(
         // 计算同步后总数量
         let total_after_sync = current_snapshot_count + new_snapshot_count;
 
-        // 3. 如果超过20，需要删除最久远的快照
-        if total_after_sync > 20 {
-            let to_delete = total_after_sync - 20;
+        // 3. 如果超过上限，需要删除最久远的快照
+        if total_after_sync > max_snapshots_per_note {
+            let to_delete = total_after_sync - max_snapshots_per_note;
             log_info(&request_id, "快照数量超限，删除最久远的快照",
                      &format!("note_id={}, current={}, new={}, total={}, to_delete={}",
                               note_id, current_snapshot_count, new_snapshot_count, total_after_sync, to_delete));
@@ -973,22 +1583,34 @@ pub async fn sync(
                 ErrorResponse::new("查询快照失败")
             })?;
 
+        let existing_server_ver = existing.as_ref().map(|v| v.server_ver);
+
         if let Some(existing_snapshot) = existing {
             if existing_snapshot.server_ver > snapshot.server_ver {
-                conflicts.push(ConflictInfo {
-                    id: snapshot.id.clone(),
-                    entity_type: "snapshot".to_string(),
-                    local_version: snapshot.server_ver,
-                    server_version: existing_snapshot.server_ver,
-                    title: snapshot.snapshot_name.clone()
-                        .unwrap_or_else(|| snapshot.title.clone()),
-                });
-                continue;
+                // 快照没有修改时间（不支持 LastWriteWins），按创建时间传入
+                match entity_conflict_action(snapshot_strategy, snapshot.created_at, existing_snapshot.created_at) {
+                    EntityConflictAction::Overwrite => {
+                        log_info(&request_id, "冲突解决：本地版本覆盖服务器版本", format!("snapshot_id={}, strategy={:?}, local_ver={}, server_ver={}",
+                            snapshot.id, snapshot_strategy, snapshot.server_ver, existing_snapshot.server_ver));
+                    }
+                    EntityConflictAction::SkipStale | EntityConflictAction::KeepServer => {
+                        conflicts.push(ConflictInfo {
+                            id: snapshot.id.clone(),
+                            entity_type: "snapshot".to_string(),
+                            local_version: snapshot.server_ver,
+                            server_version: existing_snapshot.server_ver,
+                            title: snapshot.snapshot_name.clone()
+                                .unwrap_or_else(|| snapshot.title.clone()),
+                            ..Default::default()
+                        });
+                        continue;
+                    }
+                }
             }
         }
 
         // 插入或更新快照
-        let new_server_ver = snapshot.server_ver + 1;
+        let new_server_ver = next_server_ver(existing_server_ver, snapshot.server_ver);
 
         sqlx::query(
             "INSERT INTO note_versions
@@ -1000,7 +1622,7 @@ pub async fn sync(
                 content = VALUES(content),
                 snapshot_name = VALUES(snapshot_name),
                 device_id = VALUES(device_id),
-                server_ver = server_ver + 1",
+                server_ver = GREATEST(server_ver + 1, VALUES(server_ver))",
         )
         .bind(&snapshot.id)
         .bind(&snapshot.note_id)
@@ -1011,7 +1633,7 @@ pub async fn sync(
         .bind(&snapshot.snapshot_name)
         .bind(snapshot.created_at)
         .bind(&req.device_id)
-        .bind(new_server_ver)  // ✅ 使用 new_server_ver（客户端 server_ver + 1）
+        .bind(new_server_ver)  // ✅ 使用 new_server_ver（服务器与客户端版本的较大值 + 1）
         .execute(&mut *tx)
         .await
         .map_err(|e| {
@@ -1031,15 +1653,21 @@ pub async fn sync(
         .collect();
 
     for relation in note_tags {
+        // 客户端只推送有变化的关联，is_deleted 表示取消了该标签
         sqlx::query(
-            "INSERT IGNORE INTO note_tags (note_id, tag_id, user_id, workspace_id, created_at)
-             VALUES (?, ?, ?, ?, ?)"
+            "INSERT INTO note_tags (note_id, tag_id, user_id, workspace_id, created_at, is_deleted, deleted_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?)
+             ON DUPLICATE KEY UPDATE
+                is_deleted = VALUES(is_deleted),
+                deleted_at = VALUES(deleted_at)"
         )
         .bind(&relation.note_id)
         .bind(&relation.tag_id)
         .bind(&user_id)
         .bind(&workspace_id)
         .bind(relation.created_at)
+        .bind(relation.is_deleted)
+        .bind(relation.deleted_at)
         .execute(&mut *tx)
         .await
         .map_err(|e| {
@@ -1051,17 +1679,106 @@ pub async fn sync(
         pushed_note_tags += 1;
     }
 
+    // 更新 attachments（附件依赖笔记，在笔记之后处理）
+    log_info(&request_id, "开始处理附件同步", format!("attachments_count={}", attachments_count));
+    let pushed_attachment_ids: std::collections::HashSet<String> = attachments.iter().map(|a| a.id.clone()).collect();
+
+    for attachment in attachments {
+        let existing: Option<Attachment> =
+            sqlx::query_as::<_, Attachment>(
+                "SELECT * FROM attachments WHERE id = ? AND user_id = ? FOR UPDATE"
+            )
+            .bind(&attachment.id)
+            .bind(&user_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| {
+                log_info(&request_id, "查询附件失败", e.to_string());
+                ErrorResponse::new("查询附件失败")
+            })?;
+
+        let existing_server_ver = existing.as_ref().map(|a| a.server_ver);
+
+        if let Some(existing_attachment) = existing {
+            if existing_attachment.server_ver > attachment.server_ver {
+                match entity_conflict_action(attachment_strategy, attachment.updated_at, existing_attachment.updated_at) {
+                    EntityConflictAction::Overwrite => {
+                        log_info(&request_id, "冲突解决：本地版本覆盖服务器版本", format!("attachment_id={}, strategy={:?}, local_ver={}, server_ver={}",
+                            attachment.id, attachment_strategy, attachment.server_ver, existing_attachment.server_ver));
+                    }
+                    EntityConflictAction::SkipStale => {
+                        log_info(&request_id, "冲突解决：服务器修改较新", format!("attachment_id={}", attachment.id));
+                        continue;
+                    }
+                    EntityConflictAction::KeepServer => {
+                        conflicts.push(ConflictInfo {
+                            id: attachment.id.clone(),
+                            entity_type: "attachment".to_string(),
+                            local_version: attachment.server_ver,
+                            server_version: existing_attachment.server_ver,
+                            title: attachment.filename.clone(),
+                            ..Default::default()
+                        });
+                        continue;
+                    }
+                }
+            }
+        }
+
+        let new_server_ver = next_server_ver(existing_server_ver, attachment.server_ver);
+
+        sqlx::query(
+            "INSERT INTO attachments
+             (id, note_id, user_id, workspace_id, filename, mime_type, size_bytes, data,
+              created_at, updated_at, is_deleted, deleted_at, device_id, server_ver)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+             ON DUPLICATE KEY UPDATE
+                filename = VALUES(filename),
+                mime_type = VALUES(mime_type),
+                size_bytes = VALUES(size_bytes),
+                data = VALUES(data),
+                updated_at = VALUES(updated_at),
+                is_deleted = VALUES(is_deleted),
+                deleted_at = VALUES(deleted_at),
+                device_id = VALUES(device_id),
+                server_ver = GREATEST(server_ver + 1, VALUES(server_ver))",
+        )
+        .bind(&attachment.id)
+        .bind(&attachment.note_id)
+        .bind(&user_id)
+        .bind(&workspace_id)
+        .bind(&attachment.filename)
+        .bind(&attachment.mime_type)
+        .bind(attachment.size_bytes)
+        .bind(&attachment.data)
+        .bind(attachment.created_at)
+        .bind(attachment.updated_at)
+        .bind(attachment.is_deleted)
+        .bind(attachment.deleted_at)
+        .bind(&req.device_id)
+        .bind(new_server_ver)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            log_info(&request_id, "更新附件失败", e.to_string());
+            ErrorResponse::new("更新附件失败")
+        })?;
+
+        // ✅ 推送成功，递增计数器
+        pushed_attachments += 1;
+    }
+
     // ===== 2. 查询云端更新（包括软删除） =====
-    let last_sync = req.last_sync_at.unwrap_or(0);
-    log_info(&request_id, "开始查询云端更新", &format!("last_sync_at={}", last_sync));
+    log_info(&request_id, "开始查询云端更新", format!("filter={:?}", pull_filter));
 
     // 查询工作空间（不要加 is_deleted = false!）
-    let all_workspaces: Vec<Workspace> = sqlx::query_as::<_, Workspace>(
+    let all_workspaces: Vec<Workspace> = sqlx::query_as::<_, Workspace>(&format!(
         "SELECT * FROM workspaces
-         WHERE user_id = ? AND updated_at > ?"
-    )
+         WHERE user_id = ? AND {} > ?",
+        pull_filter.column("change_seq", "updated_at")
+    ))
     .bind(&user_id)
-    .bind(last_sync)
+    .bind(pull_filter.value())
     .fetch_all(&mut *tx)
     .await
     .map_err(|e| {
@@ -1071,13 +1788,14 @@ pub async fn sync(
     log_info(&request_id, "查询云端工作空间", &format!("found={}", all_workspaces.len()));
 
     // 查询笔记（不要加 is_deleted = false!）
-    let all_notes: Vec<Note> = sqlx::query_as::<_, Note>(
+    let all_notes: Vec<Note> = sqlx::query_as::<_, Note>(&format!(
         "SELECT * FROM notes
-         WHERE user_id = ? AND (workspace_id = ? OR workspace_id IS NULL) AND updated_at > ?"
-    )
+         WHERE user_id = ? AND (workspace_id = ? OR workspace_id IS NULL) AND {} > ?",
+        pull_filter.column("change_seq", "updated_at")
+    ))
     .bind(&user_id)
     .bind(&workspace_id)
-    .bind(last_sync)
+    .bind(pull_filter.value())
     .fetch_all(&mut *tx)
     .await
     .map_err(|e| {
@@ -1087,13 +1805,14 @@ pub async fn sync(
     log_info(&request_id, "查询云端笔记", &format!("found={}", all_notes.len()));
 
     // 查询文件夹
-    let all_folders: Vec<Folder> = sqlx::query_as::<_, Folder>(
+    let all_folders: Vec<Folder> = sqlx::query_as::<_, Folder>(&format!(
         "SELECT * FROM folders
-         WHERE user_id = ? AND (workspace_id = ? OR workspace_id IS NULL) AND updated_at > ?"
-    )
+         WHERE user_id = ? AND (workspace_id = ? OR workspace_id IS NULL) AND {} > ?",
+        pull_filter.column("change_seq", "updated_at")
+    ))
     .bind(&user_id)
     .bind(&workspace_id)
-    .bind(last_sync)
+    .bind(pull_filter.value())
     .fetch_all(&mut *tx)
     .await
     .map_err(|e| {
@@ -1103,13 +1822,14 @@ pub async fn sync(
     log_info(&request_id, "查询云端文件夹", &format!("found={}", all_folders.len()));
 
     // 查询标签
-    let all_tags: Vec<Tag> = sqlx::query_as::<_, Tag>(
+    let all_tags: Vec<Tag> = sqlx::query_as::<_, Tag>(&format!(
         "SELECT * FROM tags
-         WHERE user_id = ? AND (workspace_id = ? OR workspace_id IS NULL) AND updated_at > ?"
-    )
+         WHERE user_id = ? AND (workspace_id = ? OR workspace_id IS NULL) AND {} > ?",
+        pull_filter.column("change_seq", "updated_at")
+    ))
     .bind(&user_id)
     .bind(&workspace_id)
-    .bind(last_sync)
+    .bind(pull_filter.value())
     .fetch_all(&mut *tx)
     .await
     .map_err(|e| {
@@ -1118,14 +1838,15 @@ pub async fn sync(
     })?;
     log_info(&request_id, "查询云端标签", &format!("found={}", all_tags.len()));
 
-    // 查询快照（使用 created_at，因为快照创建后不会修改）
-    let all_snapshots: Vec<NoteVersion> = sqlx::query_as::<_, NoteVersion>(
+    // 查询快照（按时间戳拉取时使用 created_at，因为快照创建后不会修改）
+    let all_snapshots: Vec<NoteVersion> = sqlx::query_as::<_, NoteVersion>(&format!(
         "SELECT * FROM note_versions
-         WHERE user_id = ? AND (workspace_id = ? OR workspace_id IS NULL) AND created_at > ?"
-    )
+         WHERE user_id = ? AND (workspace_id = ? OR workspace_id IS NULL) AND {} > ?",
+        pull_filter.column("change_seq", "created_at")
+    ))
     .bind(&user_id)
     .bind(&workspace_id)
-    .bind(last_sync)
+    .bind(pull_filter.value())
     .fetch_all(&mut *tx)
     .await
     .map_err(|e| {
@@ -1135,14 +1856,15 @@ pub async fn sync(
     log_info(&request_id, "查询云端快照", &format!("found={}", all_snapshots.len()));
 
     // 查询笔记标签关联
-    let all_note_tags: Vec<NoteTagRelation> = sqlx::query_as::<_, NoteTagRelation>(
+    let all_note_tags: Vec<NoteTagRelation> = sqlx::query_as::<_, NoteTagRelation>(&format!(
         "SELECT nt.* FROM note_tags nt
          INNER JOIN tags t ON nt.tag_id = t.id
-         WHERE t.user_id = ? AND (t.workspace_id = ? OR t.workspace_id IS NULL) AND t.updated_at > ?"
-    )
+         WHERE t.user_id = ? AND (t.workspace_id = ? OR t.workspace_id IS NULL) AND {} > ?",
+        pull_filter.column("nt.change_seq", "t.updated_at")
+    ))
     .bind(&user_id)
     .bind(&workspace_id)
-    .bind(last_sync)
+    .bind(pull_filter.value())
     .fetch_all(&mut *tx)
     .await
     .map_err(|e| {
@@ -1151,6 +1873,33 @@ pub async fn sync(
     })?;
     log_info(&request_id, "查询云端笔记标签关联", &format!("found={}", all_note_tags.len()));
 
+    // 查询附件
+    let all_attachments: Vec<Attachment> = sqlx::query_as::<_, Attachment>(&format!(
+        "SELECT * FROM attachments
+         WHERE user_id = ? AND (workspace_id = ? OR workspace_id IS NULL) AND {} > ?",
+        pull_filter.column("change_seq", "updated_at")
+    ))
+    .bind(&user_id)
+    .bind(&workspace_id)
+    .bind(pull_filter.value())
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| {
+        log_info(&request_id, "查询附件失败", e.to_string());
+        ErrorResponse::new("查询附件失败")
+    })?;
+    log_info(&request_id, "查询云端附件", format!("found={}", all_attachments.len()));
+
+    // 当前变更游标：计数器已被本事务锁定，之后不会再有其他变更提交
+    let cursor: i64 = sqlx::query_scalar("SELECT seq FROM sync_change_seq WHERE user_id = ?")
+        .bind(&user_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| {
+            log_info(&request_id, "查询变更游标失败", e.to_string());
+            ErrorResponse::new("查询变更游标失败")
+        })?;
+
     // ===== 3. 分类数据（upserted vs deleted） =====
     // 工作空间：支持软删除，分类 upserted 和 deleted
     let mut upserted_workspaces = Vec::new();
@@ -1204,10 +1953,19 @@ pub async fn sync(
     // 快照不需要 deleted_ids，因为快照是不可变的，不会被软删除
     let upserted_snapshots = all_snapshots;
 
-    // note_tags 也支持软删除
-    let upserted_note_tags: Vec<NoteTagRelation> = all_note_tags.into_iter()
-        .filter(|nt| !nt.is_deleted)
-        .collect();
+    // note_tags 也支持软删除：已删除的关联同样返回（is_deleted = true），其他设备据此取消该标签
+    let upserted_note_tags = all_note_tags;
+
+    // 附件：支持软删除，分类 upserted 和 deleted
+    let mut upserted_attachments = Vec::new();
+    let mut deleted_attachment_ids = Vec::new();
+    for attachment in all_attachments {
+        if attachment.is_deleted {
+            deleted_attachment_ids.push(attachment.id);
+        } else {
+            upserted_attachments.push(attachment);
+        }
+    }
 
     // 提交事务
     tx.commit().await.map_err(|e| {
@@ -1225,15 +1983,15 @@ pub async fn sync(
     let conflict_count = conflicts.len() as i32;
 
     if let Err(e) = history_service
-        .create(
-            &user_id,
-            "sync",
+        .create(&user_id, NewSyncHistory {
+            device_id: req.device_id.as_deref(),
+            sync_type: "sync",
             pushed_count,
             pulled_count,
             conflict_count,
-            None,
-            duration_s * 1000,  // 转换为毫秒
-        )
+            error: None,
+            duration_ms: duration_s * 1000,  // 转换为毫秒
+        })
         .await
     {
         log_info(&request_id, "记录同步历史失败", &e.to_string());
@@ -1266,8 +2024,12 @@ pub async fn sync(
     let pulled_snapshots = upserted_snapshots.iter().filter(|s| !pushed_snapshot_ids.contains(&s.id)).count();
     let pulled_note_tags = upserted_note_tags.iter().filter(|nt| !pushed_note_tag_keys.contains(&(nt.note_id.clone(), nt.tag_id.clone()))).count();
 
-    let pushed_total = pushed_workspaces + pushed_notes + pushed_folders + pushed_tags + pushed_snapshots + pushed_note_tags;
-    let pulled_total = pulled_workspaces + pulled_notes + pulled_folders + pulled_tags + pulled_snapshots + pulled_note_tags;
+    let pulled_attachments = upserted_attachments.iter().filter(|a| !pushed_attachment_ids.contains(&a.id)).count();
+
+    let pushed_total = pushed_workspaces + pushed_notes + pushed_folders + pushed_tags + pushed_snapshots + pushed_note_tags
+        + pushed_attachments;
+    let pulled_total = pulled_workspaces + pulled_notes + pulled_folders + pulled_tags + pulled_snapshots + pulled_note_tags
+        + pulled_attachments;
 
     // ===== 6. 返回响应 =====
     Ok(Json(SyncResponse {
@@ -1278,16 +2040,19 @@ pub async fn sync(
         },
         server_time: Utc::now().timestamp(),
         last_sync_at: Utc::now().timestamp(),
+        cursor,
         upserted_workspaces,
         upserted_notes,
         upserted_folders,
         upserted_tags,
         upserted_snapshots,
         upserted_note_tags,
+        upserted_attachments,
         deleted_note_ids,
         deleted_folder_ids,
         deleted_tag_ids,
         deleted_workspace_ids,
+        deleted_attachment_ids,
         // 推送统计（服务器确认实际更新的数量）
         pushed_workspaces,
         pushed_notes,
@@ -1295,6 +2060,7 @@ pub async fn sync(
         pushed_tags,
         pushed_snapshots,
         pushed_note_tags,
+        pushed_attachments,
         pushed_total,
         // 拉取统计（服务器端真正的新数据）
         pulled_workspaces,
@@ -1303,7 +2069,516 @@ pub async fn sync(
         pulled_tags,
         pulled_snapshots,
         pulled_note_tags,
+        pulled_attachments,
         pulled_total,
         conflicts,
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn test_last_write_wins_prefers_newer_local() {
+        assert_eq!(last_write_winner(2_000, 1_000), LastWriteWinner::Local);
+        assert_eq!(last_write_winner(1_000 + LAST_WRITE_WINS_SKEW_SECS + 1, 1_000), LastWriteWinner::Local);
+    }
+
+    #[test]
+    fn test_last_write_wins_prefers_newer_server() {
+        assert_eq!(last_write_winner(1_000, 2_000), LastWriteWinner::Server);
+        assert_eq!(last_write_winner(1_000, 1_000 + LAST_WRITE_WINS_SKEW_SECS + 1), LastWriteWinner::Server);
+    }
+
+    #[test]
+    fn test_last_write_wins_within_skew_is_unclear() {
+        assert_eq!(last_write_winner(1_000, 1_000), LastWriteWinner::Unclear);
+        assert_eq!(last_write_winner(1_000 + LAST_WRITE_WINS_SKEW_SECS, 1_000), LastWriteWinner::Unclear);
+        assert_eq!(last_write_winner(1_000, 1_000 + LAST_WRITE_WINS_SKEW_SECS), LastWriteWinner::Unclear);
+    }
+
+    #[test]
+    fn test_entity_conflict_action_follows_strategy() {
+        use ConflictResolutionStrategy::*;
+        assert_eq!(entity_conflict_action(KeepLocal, 1_000, 2_000), EntityConflictAction::Overwrite);
+        assert_eq!(entity_conflict_action(KeepServer, 2_000, 1_000), EntityConflictAction::KeepServer);
+        assert_eq!(entity_conflict_action(ManualMerge, 2_000, 1_000), EntityConflictAction::KeepServer);
+        assert_eq!(entity_conflict_action(LastWriteWins, 2_000, 1_000), EntityConflictAction::Overwrite);
+        assert_eq!(entity_conflict_action(LastWriteWins, 1_000, 2_000), EntityConflictAction::SkipStale);
+        assert_eq!(entity_conflict_action(LastWriteWins, 1_000, 1_000), EntityConflictAction::KeepServer);
+    }
+
+    #[test]
+    fn test_unsupported_entity_strategy_is_rejected_only_when_explicit() {
+        use ConflictResolutionStrategy::*;
+        let err = entity_strategy("folder", Some(KeepBoth), KeepServer, ENTITY_STRATEGIES).unwrap_err();
+        assert_eq!(err.status, Some(400));
+        assert!(entity_strategy("snapshot", Some(LastWriteWins), KeepServer, SNAPSHOT_STRATEGIES).is_err());
+
+        // 继承默认的 KeepBoth 时按 KeepServer 处理
+        assert_eq!(entity_strategy("folder", None, KeepBoth, ENTITY_STRATEGIES).unwrap(), KeepServer);
+        assert_eq!(entity_strategy("snapshot", None, LastWriteWins, SNAPSHOT_STRATEGIES).unwrap(), KeepServer);
+        assert_eq!(entity_strategy("tag", None, LastWriteWins, ENTITY_STRATEGIES).unwrap(), LastWriteWins);
+        assert_eq!(entity_strategy("tag", Some(KeepLocal), KeepBoth, ENTITY_STRATEGIES).unwrap(), KeepLocal);
+    }
+
+    #[test]
+    fn test_keep_local_does_not_move_server_ver_backwards() {
+        // 新建
+        assert_eq!(next_server_ver(None, 0), 1);
+        // 正常更新
+        assert_eq!(next_server_ver(Some(3), 3), 4);
+        // 本地优先覆盖较新的服务器版本
+        assert_eq!(next_server_ver(Some(7), 2), 8);
+        assert_eq!(next_server_ver(Some(2), 7), 8);
+    }
+    fn attachment_with_data(data: &str) -> Attachment {
+        Attachment {
+            id: "att-1".to_string(),
+            note_id: "note-1".to_string(),
+            user_id: String::new(),
+            workspace_id: None,
+            filename: "image.png".to_string(),
+            mime_type: "image/png".to_string(),
+            size_bytes: 0,
+            data: data.to_string(),
+            created_at: 0,
+            updated_at: 0,
+            is_deleted: false,
+            deleted_at: None,
+            server_ver: 0,
+            device_id: None,
+        }
+    }
+
+    #[test]
+    fn test_validate_attachment_returns_decoded_size() {
+        let attachment = attachment_with_data(&general_purpose::STANDARD.encode(b"1234"));
+        assert_eq!(validate_attachment(&attachment, 4).unwrap(), 4);
+
+        let err = validate_attachment(&attachment, 3).unwrap_err();
+        assert_eq!(err.status, Some(413));
+        assert_eq!(err.error_code.as_deref(), Some("ATTACHMENT_TOO_LARGE"));
+    }
+
+    #[test]
+    fn test_validate_attachment_rejects_invalid_base64() {
+        let err = validate_attachment(&attachment_with_data("not base64!"), 1024).unwrap_err();
+        assert_eq!(err.status, Some(400));
+        assert_eq!(err.error_code.as_deref(), Some("INVALID_ATTACHMENT"));
+    }
+
+    #[test]
+    fn test_note_stats_counts_words_and_cjk_characters() {
+        assert_eq!(note_stats("hello world", 200), (2, 1));
+        assert_eq!(note_stats("  多端  同步 note-app\n", 200), (5, 1));
+        assert_eq!(note_stats("", 200), (0, 1));
+    }
+
+    /// 只有一个段落的 Tiptap 文档
+    fn tiptap_doc(text: &str) -> String {
+        serde_json::json!({
+            "type": "doc",
+            "content": [{ "type": "paragraph", "content": [{ "type": "text", "text": text }] }],
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_note_stats_counts_only_tiptap_text() {
+        assert_eq!(note_stats(&tiptap_doc("同步 sync"), 200), (3, 1));
+    }
+
+    #[test]
+    fn test_note_stats_read_time_uses_reading_speed() {
+        let content = "word ".repeat(450);
+        assert_eq!(note_stats(&content, 200), (450, 3));
+        assert_eq!(note_stats(&content, 450), (450, 1));
+        // 阅读速度配置为 0 时按 1 处理，不会除零
+        assert_eq!(note_stats("a b", 0), (2, 2));
+    }
+
+    #[test]
+    fn test_note_stats_ignore_client_supplied_values() {
+        let upload = |word_count: i32, read_time_minutes: i32| -> Note {
+            serde_json::from_value(serde_json::json!({
+                "id": "note-1", "user_id": "user-1", "workspace_id": null, "title": "t",
+                "content": tiptap_doc("同步 sync"), "folder_id": null, "is_deleted": false, "deleted_at": null,
+                "created_at": 0, "updated_at": 0, "server_ver": 0,
+                "word_count": word_count, "read_time_minutes": read_time_minutes,
+            }))
+            .unwrap()
+        };
+
+        let notes = with_server_note_stats(vec![upload(0, 0), upload(999, 42)], 200);
+        let stats: Vec<(i32, i32)> = notes.iter().map(|n| (n.word_count, n.read_time_minutes)).collect();
+        assert_eq!(stats, [(3, 1), (3, 1)]);
+    }
+
+    fn request(cursor: Option<i64>, last_sync_at: Option<i64>) -> SyncRequest {
+        serde_json::from_value(serde_json::json!({
+            "cursor": cursor,
+            "last_sync_at": last_sync_at,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_pull_filter_prefers_cursor() {
+        assert_eq!(PullFilter::from_request(&request(Some(42), Some(1_000))), PullFilter::ChangeSeq(42));
+        assert_eq!(PullFilter::ChangeSeq(42).column("change_seq", "updated_at"), "change_seq");
+    }
+
+    #[test]
+    fn test_pull_filter_falls_back_to_last_sync_at() {
+        assert_eq!(PullFilter::from_request(&request(None, Some(1_000))), PullFilter::Timestamp(1_000));
+        assert_eq!(PullFilter::from_request(&request(None, None)), PullFilter::Timestamp(0));
+        assert_eq!(PullFilter::Timestamp(1_000).column("change_seq", "updated_at"), "updated_at");
+    }
+
+    /// 根 → a → b → c，另有独立的根文件夹 x
+    fn folder_tree() -> FolderTree {
+        FolderTree::new([
+            ("a".to_string(), None),
+            ("b".to_string(), Some("a".to_string())),
+            ("c".to_string(), Some("b".to_string())),
+            ("x".to_string(), Some(String::new())),
+        ])
+    }
+
+    /// 按同步时的规则依次应用 parent_id 修改，返回被拒绝的文件夹
+    fn apply_moves(tree: &mut FolderTree, moves: &[(&str, Option<&str>)]) -> Vec<String> {
+        let mut refused = Vec::new();
+        for (id, parent_id) in moves {
+            if tree.would_create_cycle(id, *parent_id) {
+                refused.push(id.to_string());
+            } else {
+                tree.set_parent(id, *parent_id);
+            }
+        }
+        refused
+    }
+
+    /// 每个文件夹向上都能到达根文件夹
+    fn is_valid_tree(tree: &FolderTree) -> bool {
+        tree.parents.keys().all(|id| {
+            let mut current = tree.parents.get(id).cloned().flatten();
+            for _ in 0..=tree.parents.len() {
+                match current {
+                    None => return true,
+                    Some(parent) => current = tree.parents.get(&parent).cloned().flatten(),
+                }
+            }
+            false
+        })
+    }
+
+    #[test]
+    fn test_folder_cycle_is_detected() {
+        let tree = folder_tree();
+
+        assert!(tree.would_create_cycle("a", Some("a")));
+        assert!(tree.would_create_cycle("a", Some("b")));
+        assert!(tree.would_create_cycle("a", Some("c")));
+        assert!(tree.would_create_cycle("b", Some("c")));
+
+        assert!(!tree.would_create_cycle("c", None));
+        assert!(!tree.would_create_cycle("c", Some("")));
+        assert!(!tree.would_create_cycle("c", Some("a")));
+        assert!(!tree.would_create_cycle("a", Some("x")));
+        // 新文件夹或父文件夹尚未同步到服务器
+        assert!(!tree.would_create_cycle("new", Some("c")));
+        assert!(!tree.would_create_cycle("b", Some("missing")));
+    }
+
+    #[test]
+    fn test_cycle_inducing_folder_moves_are_refused_and_tree_stays_valid() {
+        let mut tree = folder_tree();
+
+        // 同一次同步中：先把 x 移到 c 下，再试图把 a 移到 x 下（a → x → c → b → a）
+        let refused = apply_moves(&mut tree, &[
+            ("x", Some("c")),
+            ("a", Some("x")),
+            ("c", Some("a")),
+        ]);
+
+        assert_eq!(refused, ["a"]);
+        assert_eq!(tree.parents["a"], None);
+        assert_eq!(tree.parents["x"].as_deref(), Some("c"));
+        assert_eq!(tree.parents["c"].as_deref(), Some("a"));
+        assert!(is_valid_tree(&tree));
+    }
+
+    #[test]
+    fn test_existing_cycle_does_not_hang_detection() {
+        let tree = FolderTree::new([
+            ("a".to_string(), Some("b".to_string())),
+            ("b".to_string(), Some("a".to_string())),
+        ]);
+
+        assert!(tree.would_create_cycle("c", Some("a")));
+    }
+
+    fn synced_note(id: &str, server_ver: i32) -> Note {
+        serde_json::from_value(serde_json::json!({
+            "id": id, "user_id": "1000000001", "workspace_id": "ws-1", "title": "标题",
+            "content": "内容", "folder_id": null, "is_deleted": false, "deleted_at": null,
+            "created_at": 0, "updated_at": 0, "server_ver": server_ver,
+        }))
+        .unwrap()
+    }
+
+    fn trashed(mut note: Note) -> Note {
+        note.is_deleted = true;
+        note.deleted_at = Some(1_700_000_000);
+        note
+    }
+
+    #[test]
+    fn test_delete_state_only_change_is_detected() {
+        let server = synced_note("n1", 3);
+
+        assert!(is_delete_state_only_change(&trashed(synced_note("n1", 3)), &server));
+        // 从回收站恢复
+        assert!(is_delete_state_only_change(&synced_note("n1", 3), &trashed(synced_note("n1", 3))));
+
+        // 没有变化、同时修改了内容、或服务器版本更新时走逐条处理
+        assert!(!is_delete_state_only_change(&synced_note("n1", 3), &server));
+        let mut edited = trashed(synced_note("n1", 3));
+        edited.content = "删除前修改的内容".to_string();
+        assert!(!is_delete_state_only_change(&edited, &server));
+        assert!(!is_delete_state_only_change(&trashed(synced_note("n1", 2)), &server));
+    }
+
+    fn note_ids(notes: &[Note]) -> Vec<&str> {
+        notes.iter().map(|note| note.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_mixed_push_splits_delete_states_from_edits() {
+        let existing: std::collections::HashMap<String, Note> = ["trashed", "restored", "edited", "stale"]
+            .into_iter()
+            .map(|id| (id.to_string(), synced_note(id, 3)))
+            .map(|(id, note)| if id == "restored" { (id, trashed(note)) } else { (id, note) })
+            .collect();
+
+        let mut edited = trashed(synced_note("edited", 3));
+        edited.content = "删除前修改的内容".to_string();
+        let pushed = vec![
+            trashed(synced_note("trashed", 3)),
+            edited,
+            synced_note("restored", 3),
+            trashed(synced_note("stale", 2)),
+            trashed(synced_note("new", 0)),
+        ];
+
+        let (batch, rest) = split_delete_state_changes(pushed, &existing);
+        assert_eq!(note_ids(&batch), ["trashed", "restored"]);
+        assert_eq!(note_ids(&rest), ["edited", "stale", "new"]);
+    }
+
+    async fn insert_synced_note(pool: &crate::db::DbPool, note: &Note) {
+        sqlx::query(
+            "INSERT INTO notes (id, user_id, workspace_id, title, content, is_deleted, deleted_at, created_at, updated_at, server_ver)
+             VALUES (?, ?, ?, ?, ?, ?, ?, 0, 0, ?)"
+        )
+        .bind(&note.id)
+        .bind(&note.user_id)
+        .bind(&note.workspace_id)
+        .bind(&note.title)
+        .bind(&note.content)
+        .bind(note.is_deleted)
+        .bind(note.deleted_at)
+        .bind(note.server_ver)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "需要 MySQL：设置 TEST_DATABASE_URL 后运行 cargo test -- --ignored"]
+    async fn test_delete_states_are_written_in_bulk_and_edits_are_left_over() {
+        let pool = testing::mysql_pool().await;
+        for id in ["trashed-1", "trashed-2", "edited"] {
+            insert_synced_note(&pool, &synced_note(id, 1)).await;
+        }
+
+        let mut edited = trashed(synced_note("edited", 1));
+        edited.content = "删除前修改的内容".to_string();
+        // 客户端记录的版本高于服务器时，版本号取两者较大值再加一（与逐条写入一致）
+        let pushed = vec![trashed(synced_note("trashed-1", 1)), edited, trashed(synced_note("trashed-2", 3))];
+
+        let mut tx = pool.begin().await.unwrap();
+        let (rest, applied) = apply_note_delete_states(
+            &mut tx,
+            &RequestId("test".to_string()),
+            testing::USER_ID,
+            &Some("ws-1".to_string()),
+            pushed,
+            &Some("device-a".to_string()),
+            "device-a (test)",
+        )
+        .await
+        .unwrap();
+        tx.commit().await.unwrap();
+
+        assert_eq!(applied, 2);
+        assert_eq!(note_ids(&rest), ["edited"]);
+
+        let rows: Vec<(String, bool, Option<i64>, i32)> = sqlx::query_as(
+            "SELECT id, is_deleted, deleted_at, server_ver FROM notes WHERE user_id = ? ORDER BY id"
+        )
+        .bind(testing::USER_ID)
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(rows, [
+            ("edited".to_string(), false, None, 1),
+            ("trashed-1".to_string(), true, Some(1_700_000_000), 2),
+            ("trashed-2".to_string(), true, Some(1_700_000_000), 4),
+        ]);
+    }
+
+    /// 以测试用户身份调用同步接口，返回响应 JSON
+    async fn run_sync(state: &AppState, body: serde_json::Value) -> serde_json::Value {
+        let req: SyncRequest = serde_json::from_value(body).unwrap();
+        let response = sync(
+            Extension(RequestId("test".to_string())),
+            State(state.clone()),
+            Extension(testing::USER_ID.to_string()),
+            HeaderMap::new(),
+            Json(req),
+        )
+        .await
+        .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    #[ignore = "需要 MySQL：设置 TEST_DATABASE_URL 后运行 cargo test -- --ignored"]
+    async fn test_sync_is_recorded_under_requesting_device() {
+        let state = testing::state(testing::mysql_pool().await);
+
+        run_sync(&state, serde_json::json!({ "device_id": "device-a" })).await;
+
+        let summaries = SyncHistoryService::new(state.pool.clone())
+            .summarize_by_device(testing::USER_ID)
+            .await
+            .unwrap();
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(summaries[0].device_id.as_deref(), Some("device-a"));
+        assert_eq!(summaries[0].sync_count, 1);
+    }
+
+    /// 直接写入一篇笔记（由触发器分配变更序号），模拟其他设备在同一秒内的更新
+    async fn insert_note_at(pool: &crate::db::DbPool, id: &str, updated_at: i64) {
+        sqlx::query(
+            "INSERT INTO notes (id, user_id, title, content, created_at, updated_at, server_ver)
+             VALUES (?, ?, '标题', '内容', ?, ?, 1)"
+        )
+        .bind(id)
+        .bind(testing::USER_ID)
+        .bind(updated_at)
+        .bind(updated_at)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    fn pulled_note_ids(response: &serde_json::Value) -> Vec<&str> {
+        let mut ids: Vec<&str> = response["upserted_notes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|note| note["id"].as_str().unwrap())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    #[tokio::test]
+    #[ignore = "需要 MySQL：设置 TEST_DATABASE_URL 后运行 cargo test -- --ignored"]
+    async fn test_rows_updated_in_same_second_are_pulled_with_cursor() {
+        let state = testing::state(testing::mysql_pool().await);
+
+        let first = run_sync(&state, serde_json::json!({ "device_id": "device-a" })).await;
+        let last_sync_at = first["last_sync_at"].as_i64().unwrap();
+        let cursor = first["cursor"].as_i64().unwrap();
+
+        // 上次同步完成的同一秒内又有两篇笔记被更新
+        insert_note_at(&state.pool, "same-second-1", last_sync_at).await;
+        insert_note_at(&state.pool, "same-second-2", last_sync_at).await;
+
+        let by_timestamp = run_sync(&state, serde_json::json!({ "device_id": "device-a", "last_sync_at": last_sync_at })).await;
+        assert!(pulled_note_ids(&by_timestamp).is_empty());
+
+        let by_cursor = run_sync(&state, serde_json::json!({ "device_id": "device-a", "cursor": cursor })).await;
+        assert_eq!(pulled_note_ids(&by_cursor), ["same-second-1", "same-second-2"]);
+
+        // 使用返回的新游标不会重复拉取
+        let next_cursor = by_cursor["cursor"].as_i64().unwrap();
+        let again = run_sync(&state, serde_json::json!({ "device_id": "device-a", "cursor": next_cursor })).await;
+        assert!(pulled_note_ids(&again).is_empty());
+    }
+
+    #[tokio::test]
+    #[ignore = "需要 MySQL：设置 TEST_DATABASE_URL 后运行 cargo test -- --ignored"]
+    async fn test_pushed_note_stores_server_computed_stats() {
+        let state = testing::state(testing::mysql_pool().await);
+
+        run_sync(&state, serde_json::json!({
+            "device_id": "device-a",
+            "notes": [{
+                "id": "note-1", "user_id": testing::USER_ID, "workspace_id": null, "title": "t",
+                "content": tiptap_doc("同步 sync"), "folder_id": null, "is_deleted": false, "deleted_at": null,
+                "created_at": 0, "updated_at": 0, "server_ver": 0,
+                "word_count": 999, "read_time_minutes": 42,
+            }],
+        }))
+        .await;
+
+        let stats: (i32, i32) = sqlx::query_as("SELECT word_count, read_time_minutes FROM notes WHERE id = 'note-1'")
+            .fetch_one(&state.pool)
+            .await
+            .unwrap();
+        assert_eq!(stats, (3, 1));
+    }
+
+    #[tokio::test]
+    #[ignore = "需要 MySQL：设置 TEST_DATABASE_URL 后运行 cargo test -- --ignored"]
+    async fn test_removed_note_tag_is_pulled_by_other_device() {
+        let state = testing::state(testing::mysql_pool().await);
+        let relation = |is_deleted: bool| serde_json::json!({
+            "note_id": "note-1", "tag_id": "tag-1", "user_id": testing::USER_ID, "workspace_id": null,
+            "created_at": 0, "is_deleted": is_deleted, "deleted_at": if is_deleted { Some(1_700_000_000) } else { None },
+        });
+
+        run_sync(&state, serde_json::json!({
+            "device_id": "device-a",
+            "notes": [{
+                "id": "note-1", "user_id": testing::USER_ID, "workspace_id": null, "title": "t",
+                "content": "内容", "folder_id": null, "is_deleted": false, "deleted_at": null,
+                "created_at": 0, "updated_at": 0, "server_ver": 0,
+            }],
+            "tags": [{
+                "id": "tag-1", "user_id": testing::USER_ID, "workspace_id": null, "name": "待办",
+                "color": null, "created_at": 0, "updated_at": 0, "server_ver": 0,
+            }],
+            "note_tags": [relation(false)],
+        }))
+        .await;
+        let cursor = run_sync(&state, serde_json::json!({ "device_id": "device-b" })).await["cursor"].as_i64().unwrap();
+
+        // 设备 A 取消标签
+        run_sync(&state, serde_json::json!({ "device_id": "device-a", "note_tags": [relation(true)] })).await;
+
+        let pulled = run_sync(&state, serde_json::json!({ "device_id": "device-b", "cursor": cursor })).await;
+        let relations = pulled["upserted_note_tags"].as_array().unwrap();
+        assert_eq!(relations.len(), 1);
+        assert_eq!(relations[0]["tag_id"], "tag-1");
+        assert_eq!(relations[0]["is_deleted"], true);
+    }
+}
@@ -5,6 +5,66 @@ use crate::AppState;
 use axum::{Extension, Json};
 use axum::extract::State;
 
+/// 工作空间名称最大长度（字符数，与 workspaces.name VARCHAR(100) 一致）
+const MAX_WORKSPACE_NAME_LENGTH: usize = 100;
+/// 工作空间描述最大长度（字符数）
+const MAX_WORKSPACE_DESCRIPTION_LENGTH: usize = 500;
+/// 工作空间图标最大长度（字符数，图标为 emoji）
+const MAX_WORKSPACE_ICON_LENGTH: usize = 16;
+
+/// 去除首尾空白并校验工作空间字段（与客户端的限制一致）
+///
+/// 名称不能为空；描述、图标、颜色为空字符串时表示清除
+fn normalize_workspace_fields(
+    name: Option<&mut String>,
+    description: Option<&mut String>,
+    icon: Option<&mut String>,
+    color: Option<&mut String>,
+) -> Result<(), ErrorResponse> {
+    fn invalid(message: String) -> ErrorResponse {
+        ErrorResponse::new_with_code(message, 400, "INVALID_WORKSPACE")
+    }
+    fn trim(value: &mut String) -> &str {
+        *value = value.trim().to_string();
+        value.as_str()
+    }
+
+    if let Some(name) = name.map(trim) {
+        if name.is_empty() {
+            return Err(invalid("工作空间名称不能为空".to_string()));
+        }
+        if name.chars().count() > MAX_WORKSPACE_NAME_LENGTH {
+            return Err(invalid(format!("工作空间名称不能超过 {} 个字符", MAX_WORKSPACE_NAME_LENGTH)));
+        }
+    }
+
+    if description.map(trim).is_some_and(|d| d.chars().count() > MAX_WORKSPACE_DESCRIPTION_LENGTH) {
+        return Err(invalid(format!("工作空间描述不能超过 {} 个字符", MAX_WORKSPACE_DESCRIPTION_LENGTH)));
+    }
+
+    if icon.map(trim).is_some_and(|i| {
+        i.chars().count() > MAX_WORKSPACE_ICON_LENGTH || i.chars().any(|c| c.is_whitespace() || c.is_control())
+    }) {
+        return Err(invalid(format!(
+            "工作空间图标无效（不能包含空白字符，且不超过 {} 个字符）",
+            MAX_WORKSPACE_ICON_LENGTH
+        )));
+    }
+
+    if color.map(trim).is_some_and(|c| !c.is_empty() && !is_hex_color(c)) {
+        return Err(invalid("工作空间颜色格式无效（应为 #RGB 或 #RRGGBB）".to_string()));
+    }
+
+    Ok(())
+}
+
+/// 是否为合法的十六进制颜色（`#RGB` 或 `#RRGGBB`）
+fn is_hex_color(color: &str) -> bool {
+    color
+        .strip_prefix('#')
+        .is_some_and(|hex| matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
 pub async fn list_workspaces(
     Extension(request_id): Extension<RequestId>,
     State(state): State<AppState>,
@@ -31,10 +91,13 @@ pub async fn create_workspace(
     Extension(request_id): Extension<RequestId>,
     State(state): State<AppState>,
     Extension(user_id): Extension<String>,
-    Json(req): Json<CreateWorkspaceRequest>,
+    Json(mut req): Json<CreateWorkspaceRequest>,
 ) -> Result<Json<Workspace>, ErrorResponse> {
     log_info(&request_id, "创建工作空间请求", &format!("user_id={}, name={}", user_id, req.name));
 
+    normalize_workspace_fields(Some(&mut req.name), req.description.as_mut(), req.icon.as_mut(), req.color.as_mut())
+        .inspect_err(|e| log_info(&request_id, "工作空间参数无效", &e.error))?;
+
     let workspace_id = uuid::Uuid::new_v4().to_string();
     let now = chrono::Utc::now().timestamp();
 
@@ -76,10 +139,13 @@ pub async fn update_workspace(
     State(state): State<AppState>,
     Extension(user_id): Extension<String>,
     axum::extract::Path(id): axum::extract::Path<String>,
-    Json(req): Json<UpdateWorkspaceRequest>,
+    Json(mut req): Json<UpdateWorkspaceRequest>,
 ) -> Result<Json<Workspace>, ErrorResponse> {
     log_info(&request_id, "更新工作空间请求", &format!("user_id={}, workspace_id={}", user_id, id));
 
+    normalize_workspace_fields(req.name.as_mut(), req.description.as_mut(), req.icon.as_mut(), req.color.as_mut())
+        .inspect_err(|e| log_info(&request_id, "工作空间参数无效", &e.error))?;
+
     let now = chrono::Utc::now().timestamp();
 
     sqlx::query(
@@ -203,3 +269,59 @@ pub async fn set_default_workspace(
     log_info(&request_id, "设置默认工作空间成功", &format!("workspace_id={}", id));
     Ok(Json(()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalize(name: Option<&str>, description: Option<&str>, icon: Option<&str>, color: Option<&str>) -> Result<Vec<Option<String>>, ErrorResponse> {
+        let mut fields: Vec<Option<String>> = [name, description, icon, color]
+            .into_iter()
+            .map(|f| f.map(str::to_string))
+            .collect();
+        let [name, description, icon, color] = fields.as_mut_slice() else { unreachable!() };
+        normalize_workspace_fields(name.as_mut(), description.as_mut(), icon.as_mut(), color.as_mut())?;
+        Ok(fields)
+    }
+
+    #[test]
+    fn test_empty_workspace_name_is_rejected() {
+        let err = normalize(Some("   "), None, None, None).unwrap_err();
+
+        assert_eq!(err.status, Some(400));
+        assert_eq!(err.error_code.as_deref(), Some("INVALID_WORKSPACE"));
+        assert!(err.error.contains("名称不能为空"));
+    }
+
+    #[test]
+    fn test_over_length_workspace_fields_are_rejected() {
+        let name = "名".repeat(MAX_WORKSPACE_NAME_LENGTH);
+        assert!(normalize(Some(&name), None, None, None).is_ok());
+
+        let err = normalize(Some(&format!("{}名", name)), None, None, None).unwrap_err();
+        assert!(err.error.contains("名称"));
+
+        let err = normalize(None, Some(&"x".repeat(MAX_WORKSPACE_DESCRIPTION_LENGTH + 1)), None, None).unwrap_err();
+        assert!(err.error.contains("描述"));
+
+        let err = normalize(None, None, Some(&"📁".repeat(MAX_WORKSPACE_ICON_LENGTH + 1)), None).unwrap_err();
+        assert!(err.error.contains("图标"));
+    }
+
+    #[test]
+    fn test_workspace_fields_are_trimmed_and_formats_checked() {
+        let fields = normalize(Some("  工作  "), Some(" 描述 "), Some("📁"), Some("#3B82F6")).unwrap();
+        assert_eq!(fields, vec![
+            Some("工作".to_string()),
+            Some("描述".to_string()),
+            Some("📁".to_string()),
+            Some("#3B82F6".to_string()),
+        ]);
+
+        // 空字符串表示清除
+        assert!(normalize(None, Some(""), Some(""), Some("")).is_ok());
+        assert!(normalize(None, None, Some("my icon"), None).is_err());
+        assert!(normalize(None, None, None, Some("blue")).is_err());
+        assert!(normalize(None, None, None, Some("#12345")).is_err());
+    }
+}
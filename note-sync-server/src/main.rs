@@ -4,18 +4,21 @@ mod handlers;
 mod middleware;
 mod models;
 mod services;
+mod shutdown;
+#[cfg(test)]
+mod testing;
 
 use axum::{
-    extract::State,
-    http::StatusCode,
-    response::IntoResponse,
+    extract::DefaultBodyLimit,
     routing::{get, post},
     Router,
 };
 use clap::Parser;
+use services::email_verification_service::EmailVerificationService;
+use services::idempotency_store::IdempotencyStore;
+use services::rate_limiter::RateLimiter;
 use services::token_blacklist::TokenBlacklist;
 use std::sync::Arc;
-use tower_http::cors::{Any, CorsLayer};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 /// 命令行参数
@@ -37,6 +40,8 @@ struct CliArgs {
 pub struct AppState {
     pub pool: db::DbPool,
     pub token_blacklist: Arc<TokenBlacklist>,
+    pub email_verification: Arc<EmailVerificationService>,
+    pub idempotency: Arc<IdempotencyStore>,
     pub config: config::AppConfig,
 }
 
@@ -52,6 +57,9 @@ async fn main() -> anyhow::Result<()> {
 
     // 加载配置
     let config = config::AppConfig::load(args.config).expect("Failed to load configuration");
+    let environment = std::env::var("CLOUDMEMO_ENV")
+        .unwrap_or_else(|_| "development".to_string())
+        .to_lowercase();
 
     // 初始化日志
     tracing_subscriber::registry()
@@ -99,35 +107,93 @@ async fn main() -> anyhow::Result<()> {
     );
     tracing::info!("Connected to Redis at {}", config.redis.url);
 
+    // 幂等键、限流和邮箱验证令牌与 Token 黑名单共用 Redis 连接
+    let kv_store = token_blacklist.store();
+
     // 创建应用状态
     let app_state = AppState {
         pool: pool.clone(),
+        idempotency: Arc::new(IdempotencyStore::new(
+            kv_store.clone(),
+            config.idempotency.clone(),
+        )),
         token_blacklist,
+        email_verification: Arc::new(EmailVerificationService::new(
+            pool.clone(),
+            kv_store.clone(),
+            config.email_verification.clone(),
+        )),
         config: config.clone(),
     };
 
+    // 保留 Redis 连接句柄，服务器停止后释放
+    let redis_connection = app_state.token_blacklist.clone();
+
+    // 登录 / 注册限流
+    let rate_limit_state = middleware::rate_limit::RateLimitState {
+        limiter: Arc::new(RateLimiter::new(kv_store)),
+        config: config.rate_limit.clone(),
+    };
+
     // ========== 公开路由（无需认证） ==========
-    let public_routes = Router::new()
-        .route("/health", get(health_check))
+    let auth_routes = Router::new()
         .route("/auth/register", post(handlers::auth::register))
         .route("/auth/login", post(handlers::auth::login))
-        .route("/auth/refresh", post(handlers::auth::refresh)); // refresh token（公开，需要 refresh_token）
+        .route_layer(axum::middleware::from_fn_with_state(
+            rate_limit_state,
+            middleware::rate_limit::auth_rate_limit_middleware,
+        ));
+
+    let public_routes = Router::new()
+        .route("/health", get(handlers::health::health_check))
+        .route("/auth/refresh", post(handlers::auth::refresh)) // refresh token（公开，需要 refresh_token）
+        .route(
+            "/auth/verify",
+            get(handlers::auth::verify_email_link).post(handlers::auth::verify_email),
+        )
+        .merge(auth_routes);
 
     // ========== 受保护路由（需要认证） ==========
     let protected_routes = Router::new()
         .route("/auth/logout", post(handlers::auth::logout))
+        .route("/auth/logout-all", post(handlers::auth::logout_all))
+        .route("/auth/change-password", post(handlers::auth::change_password))
+        .route("/auth/me", get(handlers::auth::me))
+        .route("/auth/export", get(handlers::auth::export_account))
+        .route("/limits", get(handlers::limits::get_limits))
         .route(
             "/auth/delete",
             axum::routing::delete(handlers::auth::delete_account),
         )
-        // 同步端点
-        .route("/sync", post(handlers::sync::sync))
+        // 同步端点（启用邮箱验证时，未验证邮箱的账号不能同步）
+        // 附件随同步请求上传，请求体上限按配置放宽（默认 2 MB 放不下较大的附件）
+        .merge(
+            Router::new()
+                .route("/sync", post(handlers::sync::sync))
+                .route_layer(axum::middleware::from_fn_with_state(
+                    app_state.clone(),
+                    middleware::email_verified::require_verified_email,
+                ))
+                .layer(DefaultBodyLimit::max(config.limits.sync_body_limit()))
+                // gzip 请求解压 / 响应压缩（只用于同步接口）
+                .layer(axum::middleware::from_fn_with_state(
+                    middleware::compression::GzipOptions {
+                        compression: config.compression.clone(),
+                        max_body_bytes: config.limits.sync_body_limit(),
+                    },
+                    middleware::compression::gzip_middleware,
+                )),
+        )
         // 同步历史端点
         .route("/sync/history", get(handlers::history::get_history))
         .route(
             "/sync/history",
             axum::routing::delete(handlers::history::clear_history),
         )
+        .route(
+            "/sync/history/devices",
+            get(handlers::history::get_device_summary),
+        )
         // 用户资料端点
         .route("/profile/:user_id", get(handlers::profile::get_profile))
         .route(
@@ -136,6 +202,9 @@ async fn main() -> anyhow::Result<()> {
         )
         .route("/profile/sync", post(handlers::profile::sync_profile))
         // 笔记端点
+        .route("/search", post(handlers::search::search_notes))
+        .route("/notes", post(handlers::notes::create_note))
+        .route("/notes/:id", get(handlers::notes::get_note))
         .route(
             "/notes/:id/snapshots",
             post(handlers::notes::create_snapshot),
@@ -159,11 +228,17 @@ async fn main() -> anyhow::Result<()> {
             "/workspaces/:id/default",
             post(handlers::workspaces::set_default_workspace),
         )
+        // 数据维护端点
+        .route(
+            "/maintenance/purge-note-versions",
+            post(handlers::maintenance::purge_note_versions),
+        )
         // 设备管理端点
         .route("/devices", get(handlers::devices::list_devices))
         .route(
             "/devices/:id",
-            axum::routing::delete(handlers::devices::revoke_device),
+            axum::routing::delete(handlers::devices::revoke_device)
+                .patch(handlers::devices::update_device),
         )
         .route(
             "/devices/:id/heartbeat",
@@ -178,13 +253,8 @@ async fn main() -> anyhow::Result<()> {
     // ========== 合并路由 ==========
     let app = public_routes
         .merge(protected_routes)
-        // CORS（应用于所有路由）
-        .layer(
-            CorsLayer::new()
-                .allow_origin(Any)
-                .allow_methods(Any)
-                .allow_headers(Any),
-        )
+        // CORS（应用于所有路由，只允许配置的来源）
+        .layer(middleware::cors::cors_layer(&config.cors, &environment)?)
         // 自定义日志中间件（应用于所有路由）
         .layer(axum::middleware::from_fn(
             middleware::logging::request_logging_middleware,
@@ -197,15 +267,18 @@ async fn main() -> anyhow::Result<()> {
     let listener = tokio::net::TcpListener::bind(&addr).await?;
     tracing::info!("Server listening on {}", addr);
 
-    axum::serve(listener, app).await?;
+    // 收到 SIGINT / SIGTERM 后等待进行中的请求完成，再关闭数据库和 Redis 连接
+    shutdown::serve_with_graceful_shutdown(
+        listener,
+        app,
+        shutdown::shutdown_signal(),
+        std::time::Duration::from_secs(config.server.shutdown_timeout_secs),
+    )
+    .await?;
 
-    Ok(())
-}
+    pool.close().await;
+    drop(redis_connection);
+    tracing::info!("Server stopped");
 
-// 健康检查端点
-async fn health_check(State(state): State<AppState>) -> impl IntoResponse {
-    match state.pool.acquire().await {
-        Ok(_) => (StatusCode::OK, "OK"),
-        Err(_) => (StatusCode::SERVICE_UNAVAILABLE, "Database unavailable"),
-    }
+    Ok(())
 }
@@ -4,18 +4,26 @@ use axum::{
     http::{HeaderMap, StatusCode},
     middleware::Next,
     response::Response,
-    Extension,
 };
 use jsonwebtoken::{decode, Validation, DecodingKey};
 use serde::Deserialize;
+use crate::services::token_service::TokenService;
 use crate::AppState;
 
 #[derive(Deserialize)]
 pub struct Claims {
     pub sub: String,  // user_id
     pub exp: usize,
+    #[serde(default)]
+    pub iat: Option<i64>,  // 签发时间（旧版本签发的 token 没有）
+    #[serde(default)]
+    pub jti: Option<String>,
 }
 
+/// 当前请求 token 的会话标识（见 [`TokenService::session_id`]）
+#[derive(Debug, Clone)]
+pub struct TokenSession(pub String);
+
 pub async fn auth_middleware(
     State(state): State<AppState>,
     headers: HeaderMap,
@@ -54,8 +62,88 @@ pub async fn auth_middleware(
         &Validation::default(),
     ).map_err(|_| StatusCode::UNAUTHORIZED)?;
 
-    // 4. 将 user_id 添加到请求扩展
-    req.extensions_mut().insert(token_data.claims.sub);
+    // 4. 检查是否已被“登出所有设备”吊销
+    let claims = token_data.claims;
+    let session_id = TokenService::session_id(token, claims.jti.as_deref());
+    if state.token_blacklist.is_session_revoked(&claims.sub, claims.iat.unwrap_or(0), &session_id).await
+        .map_err(|e| {
+            tracing::error!("Failed to check revoked sessions: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+    {
+        tracing::warn!("Token revoked by logout-all, rejecting request");
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    // 5. 将 user_id 和会话标识添加到请求扩展
+    req.extensions_mut().insert(claims.sub);
+    req.extensions_mut().insert(TokenSession(session_id));
 
     Ok(next.run(req).await)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, JWT_SECRET as SECRET, USER_ID};
+    use axum::{body::Body, middleware::from_fn_with_state, routing::get, Extension, Router};
+    use tower::Service;
+
+    fn state() -> AppState {
+        testing::state(testing::lazy_pool())
+    }
+
+    fn app(state: AppState) -> Router {
+        Router::new()
+            .route("/session", get(|Extension(session): Extension<TokenSession>| async move { session.0 }))
+            .route_layer(from_fn_with_state(state, auth_middleware))
+    }
+
+    async fn call(app: &mut Router, token: &str) -> (StatusCode, String) {
+        let request = Request::get("/session")
+            .header("Authorization", format!("Bearer {}", token))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.call(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_logout_all_rejects_tokens_of_other_devices() {
+        let state = state();
+        let mut app = app(state.clone());
+
+        let current = TokenService::generate_access_token(USER_ID, 7, SECRET).unwrap();
+        let other_device = TokenService::generate_access_token(USER_ID, 7, SECRET).unwrap();
+        let other_user = TokenService::generate_access_token("1000000002", 7, SECRET).unwrap();
+        assert_eq!(call(&mut app, &other_device).await.0, StatusCode::OK);
+
+        // 登出所有设备，保留当前设备
+        let (status, current_session) = call(&mut app, &current).await;
+        assert_eq!(status, StatusCode::OK);
+        state.token_blacklist
+            .revoke_user_sessions(USER_ID, chrono::Utc::now().timestamp(), Some(&current_session), 3600)
+            .await
+            .unwrap();
+
+        assert_eq!(call(&mut app, &other_device).await.0, StatusCode::UNAUTHORIZED);
+        assert_eq!(call(&mut app, &current).await.0, StatusCode::OK);
+        assert_eq!(call(&mut app, &other_user).await.0, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_logout_all_without_keeping_current_device() {
+        let state = state();
+        let mut app = app(state.clone());
+
+        let current = TokenService::generate_access_token(USER_ID, 7, SECRET).unwrap();
+        state.token_blacklist
+            .revoke_user_sessions(USER_ID, chrono::Utc::now().timestamp(), None, 3600)
+            .await
+            .unwrap();
+
+        assert_eq!(call(&mut app, &current).await.0, StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,219 @@
+// gzip 压缩中间件
+use axum::{
+    body::{to_bytes, Body, HttpBody},
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use std::io::{Read, Write};
+use crate::config::CompressionConfig;
+
+/// gzip 中间件的配置
+#[derive(Debug, Clone)]
+pub struct GzipOptions {
+    /// 响应压缩配置
+    pub compression: CompressionConfig,
+    /// 请求体上限（字节），压缩前和解压后都不能超过（防止压缩炸弹）
+    pub max_body_bytes: usize,
+}
+
+/// gzip 压缩中间件（只用于 `/sync`，其他接口的请求和响应都很小）
+///
+/// - 请求带 `Content-Encoding: gzip` 时先解压，再交给 handler
+/// - 客户端声明 `Accept-Encoding: gzip` 且响应体达到 `min_response_bytes` 时压缩响应；
+///   长度未知的流式响应不压缩，避免把整个流读入内存
+pub async fn gzip_middleware(
+    State(options): State<GzipOptions>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let config = &options.compression;
+    let accepts_gzip = req
+        .headers()
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .any(|v| v.split(',').any(|enc| enc.trim().split(';').next() == Some("gzip")));
+
+    let req = if is_gzip(req.headers().get(header::CONTENT_ENCODING)) {
+        match decompress_request(req, options.max_body_bytes).await {
+            Ok(req) => req,
+            Err((status, message)) => {
+                tracing::warn!("解压请求体失败: {}", message);
+                return (status, message).into_response();
+            }
+        }
+    } else {
+        req
+    };
+
+    let response = next.run(req).await;
+
+    if !accepts_gzip
+        || config.min_response_bytes == 0
+        || response.headers().contains_key(header::CONTENT_ENCODING)
+    {
+        return response;
+    }
+
+    match response.body().size_hint().exact() {
+        Some(len) if len as usize >= config.min_response_bytes => compress_response(response, len as usize).await,
+        _ => response,
+    }
+}
+
+fn is_gzip(value: Option<&HeaderValue>) -> bool {
+    value.is_some_and(|v| v.as_bytes().eq_ignore_ascii_case(b"gzip"))
+}
+
+async fn decompress_request(req: Request, max_bytes: usize) -> Result<Request, (StatusCode, String)> {
+    let (mut parts, body) = req.into_parts();
+    let compressed = to_bytes(body, max_bytes)
+        .await
+        .map_err(|e| (StatusCode::PAYLOAD_TOO_LARGE, format!("读取请求体失败: {}", e)))?;
+
+    let mut decompressed = Vec::new();
+    GzDecoder::new(compressed.as_ref())
+        .take(max_bytes as u64 + 1)
+        .read_to_end(&mut decompressed)
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("无效的 gzip 请求体: {}", e)))?;
+    if decompressed.len() > max_bytes {
+        return Err((StatusCode::PAYLOAD_TOO_LARGE, "解压后的请求体过大".to_string()));
+    }
+
+    parts.headers.remove(header::CONTENT_ENCODING);
+    parts.headers.insert(header::CONTENT_LENGTH, HeaderValue::from(decompressed.len()));
+    Ok(Request::from_parts(parts, Body::from(decompressed)))
+}
+
+/// 压缩长度已知（`len` 字节）的响应
+async fn compress_response(response: Response, len: usize) -> Response {
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, len).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::error!("读取响应体失败: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    parts.headers.append(header::VARY, HeaderValue::from_static("accept-encoding"));
+    let mut encoder = GzEncoder::new(Vec::with_capacity(bytes.len() / 4), Compression::default());
+    match encoder.write_all(&bytes).and_then(|_| encoder.finish()) {
+        Ok(compressed) => {
+            parts.headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+            parts.headers.insert(header::CONTENT_LENGTH, HeaderValue::from(compressed.len()));
+            Response::from_parts(parts, Body::from(compressed))
+        }
+        Err(e) => {
+            tracing::error!("压缩响应体失败: {}", e);
+            Response::from_parts(parts, Body::from(bytes))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{middleware::from_fn_with_state, routing::post, Router};
+    use tower::Service;
+
+    fn options(min_response_bytes: usize, max_body_bytes: usize) -> GzipOptions {
+        GzipOptions {
+            compression: CompressionConfig { min_response_bytes },
+            max_body_bytes,
+        }
+    }
+
+    fn app(min_response_bytes: usize) -> Router {
+        Router::new()
+            .route("/sync", post(|body: String| async move { body }))
+            .layer(from_fn_with_state(options(min_response_bytes, 1024 * 1024), gzip_middleware))
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_large_body_round_trips_compressed() {
+        let payload = "{\"content\":\"长内容\"}".repeat(10_000);
+        let request = Request::post("/sync")
+            .header(header::CONTENT_ENCODING, "gzip")
+            .header(header::ACCEPT_ENCODING, "gzip")
+            .body(Body::from(gzip(payload.as_bytes())))
+            .unwrap();
+
+        let response = app(1024).call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+
+        let compressed = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(compressed.len() < payload.len());
+        let mut decoded = String::new();
+        GzDecoder::new(compressed.as_ref()).read_to_string(&mut decoded).unwrap();
+        assert_eq!(decoded, payload);
+    }
+
+    #[tokio::test]
+    async fn test_small_body_stays_uncompressed() {
+        let request = Request::post("/sync")
+            .header(header::ACCEPT_ENCODING, "gzip")
+            .body(Body::from("{}"))
+            .unwrap();
+
+        let response = app(1024).call(request).await.unwrap();
+        assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+        assert_eq!(to_bytes(response.into_body(), usize::MAX).await.unwrap(), "{}");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_gzip_body_is_rejected() {
+        let request = Request::post("/sync")
+            .header(header::CONTENT_ENCODING, "gzip")
+            .body(Body::from("not gzip"))
+            .unwrap();
+
+        let response = app(1024).call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_decompressed_body_over_limit_is_rejected() {
+        // 压缩后很小，解压后超过上限
+        let payload = vec![b'a'; 4096];
+        let request = Request::post("/sync")
+            .header(header::CONTENT_ENCODING, "gzip")
+            .body(Body::from(gzip(&payload)))
+            .unwrap();
+
+        let mut app = Router::new()
+            .route("/sync", post(|body: String| async move { body }))
+            .layer(from_fn_with_state(options(1024, 1024), gzip_middleware));
+
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_streaming_response_is_not_buffered() {
+        let mut app = Router::new()
+            .route("/sync", axum::routing::get(|| async {
+                let chunks = vec![Ok::<_, std::io::Error>("x".repeat(4096))];
+                Body::from_stream(tokio_stream::iter(chunks))
+            }))
+            .layer(from_fn_with_state(options(1024, 1024), gzip_middleware));
+        let request = Request::get("/sync")
+            .header(header::ACCEPT_ENCODING, "gzip")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = app.call(request).await.unwrap();
+        assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+        assert_eq!(to_bytes(response.into_body(), usize::MAX).await.unwrap().len(), 4096);
+    }
+}
@@ -0,0 +1,127 @@
+// CORS 配置
+use axum::http::{header, HeaderName, HeaderValue, Method};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use crate::config::CorsConfig;
+use crate::handlers::{IDEMPOTENCY_KEY_HEADER, IDEMPOTENT_REPLAYED_HEADER};
+use crate::middleware::logging::REQUEST_ID_HEADER;
+
+/// 开发环境未配置 allowed_origins 时允许的本地来源（Vite 开发服务器和 Tauri WebView）
+const DEV_ALLOWED_ORIGINS: &[&str] = &[
+    "http://localhost:1420",
+    "http://127.0.0.1:1420",
+    "tauri://localhost",
+    "http://tauri.localhost",
+    "https://tauri.localhost",
+];
+
+/// 根据配置构建 CORS 层
+///
+/// - 只允许 `allowed_origins` 中列出的来源；请求携带 Authorization 凭证，因此不接受 `*`
+/// - 未配置时开发环境使用本地来源，生产环境（`CLOUDMEMO_ENV=production`）必须显式配置
+pub fn cors_layer(config: &CorsConfig, environment: &str) -> anyhow::Result<CorsLayer> {
+    let origins: Vec<String> = if !config.allowed_origins.is_empty() {
+        config.allowed_origins.clone()
+    } else if environment == "production" {
+        anyhow::bail!("生产环境必须在 [cors] allowed_origins 中配置允许跨域访问的来源");
+    } else {
+        DEV_ALLOWED_ORIGINS.iter().map(|o| o.to_string()).collect()
+    };
+
+    if origins.iter().any(|o| o.trim() == "*") {
+        anyhow::bail!("[cors] allowed_origins 不能包含 *：跨域请求会携带 Authorization 凭证");
+    }
+
+    let origins = origins
+        .iter()
+        .map(|o| {
+            HeaderValue::from_str(o.trim_end_matches('/'))
+                .map_err(|_| anyhow::anyhow!("无效的 CORS 来源: {}", o))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    tracing::info!("CORS allowed origins: {:?}", origins);
+
+    Ok(CorsLayer::new()
+        .allow_origin(AllowOrigin::list(origins))
+        .allow_methods([Method::GET, Method::POST, Method::PATCH, Method::DELETE, Method::OPTIONS])
+        .allow_headers([
+            header::AUTHORIZATION,
+            header::CONTENT_TYPE,
+            header::CONTENT_ENCODING,
+            header::ACCEPT_ENCODING,
+            REQUEST_ID_HEADER.clone(),
+            HeaderName::from_static(IDEMPOTENCY_KEY_HEADER),
+        ])
+        .expose_headers([
+            REQUEST_ID_HEADER.clone(),
+            HeaderName::from_static(IDEMPOTENT_REPLAYED_HEADER),
+            header::RETRY_AFTER,
+        ]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, extract::Request, routing::get, Router};
+    use tower::Service;
+
+    fn config(origins: &[&str]) -> CorsConfig {
+        CorsConfig {
+            allowed_origins: origins.iter().map(|o| o.to_string()).collect(),
+        }
+    }
+
+    fn request(origin: &str) -> Request {
+        Request::get("/health")
+            .header(header::ORIGIN, origin)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_only_allowed_origin_gets_cors_header() {
+        let layer = cors_layer(&config(&["https://notes.example.com"]), "production").unwrap();
+        let mut app = Router::new()
+            .route("/health", get(|| async { "OK" }))
+            .layer(layer);
+
+        let response = app.call(request("https://notes.example.com")).await.unwrap();
+        assert_eq!(
+            response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://notes.example.com"
+        );
+
+        let response = app.call(request("https://evil.example.com")).await.unwrap();
+        assert!(!response.headers().contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[tokio::test]
+    async fn test_preflight_allows_idempotency_key() {
+        let layer = cors_layer(&config(&["https://notes.example.com"]), "production").unwrap();
+        let mut app = Router::new()
+            .route("/notes", axum::routing::post(|| async { "OK" }))
+            .layer(layer);
+
+        let preflight = Request::options("/notes")
+            .header(header::ORIGIN, "https://notes.example.com")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "authorization,idempotency-key")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.call(preflight).await.unwrap();
+
+        let allowed = response.headers()[header::ACCESS_CONTROL_ALLOW_HEADERS].to_str().unwrap().to_string();
+        assert!(allowed.contains("idempotency-key"), "allowed headers: {}", allowed);
+    }
+
+    #[test]
+    fn test_wildcard_origin_is_rejected() {
+        assert!(cors_layer(&config(&["*"]), "development").is_err());
+    }
+
+    #[test]
+    fn test_production_requires_explicit_origins() {
+        assert!(cors_layer(&config(&[]), "production").is_err());
+        assert!(cors_layer(&config(&[]), "development").is_ok());
+    }
+}
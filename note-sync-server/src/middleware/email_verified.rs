@@ -0,0 +1,145 @@
+// 邮箱验证中间件
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use crate::handlers::ErrorResponse;
+use crate::AppState;
+
+/// 启用邮箱验证时拒绝未验证邮箱的账号（需在认证中间件之后执行）
+pub async fn require_verified_email(
+    State(state): State<AppState>,
+    req: Request,
+    next: Next,
+) -> Result<Response, ErrorResponse> {
+    let user_id = req.extensions().get::<String>().cloned().unwrap_or_default();
+
+    let verified = state.email_verification.is_verified(&user_id).await
+        .map_err(|e| {
+            tracing::error!("Failed to check email verification: {}", e);
+            ErrorResponse::new_with_code("检查邮箱验证状态失败", 500, "EMAIL_VERIFICATION_ERROR")
+        })?;
+
+    if !verified {
+        tracing::warn!("Email not verified, rejecting request: user_id={}", user_id);
+        return Err(ErrorResponse::new_with_code(
+            "邮箱尚未验证，请先通过邮件中的链接完成验证",
+            403,
+            "EMAIL_UNVERIFIED",
+        ));
+    }
+
+    Ok(next.run(req).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::auth_middleware;
+    use crate::middleware::logging::request_logging_middleware;
+    use crate::services::email_verification_service::tests::{enabled_config, mysql_service, RecordingSender};
+    use crate::services::email_verification_service::EmailVerificationService;
+    use crate::services::kv_store::KvStore;
+    use crate::services::token_service::TokenService;
+    use crate::testing::{self, JWT_SECRET as SECRET, USER_ID};
+    use axum::{body::Body, http::StatusCode, middleware::from_fn_with_state, routing::{get, post}, Router};
+    use std::sync::Arc;
+    use tower::Service;
+
+    fn state(email_verification: EmailVerificationService) -> AppState {
+        AppState {
+            email_verification: Arc::new(email_verification),
+            ..testing::state(testing::lazy_pool())
+        }
+    }
+
+    fn app(state: AppState) -> Router {
+        let protected = Router::new()
+            .route("/sync", post(|| async { "synced" }))
+            .route_layer(from_fn_with_state(state.clone(), require_verified_email))
+            .route_layer(from_fn_with_state(state.clone(), auth_middleware));
+
+        Router::new()
+            .route(
+                "/auth/verify",
+                get(crate::handlers::auth::verify_email_link).post(crate::handlers::auth::verify_email),
+            )
+            .merge(protected)
+            .layer(axum::middleware::from_fn(request_logging_middleware))
+            .with_state(state)
+    }
+
+    async fn call(app: &mut Router, request: Request) -> (StatusCode, String) {
+        let response = app.call(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    fn sync_request(token: &str) -> Request {
+        Request::post("/sync")
+            .header("Authorization", format!("Bearer {}", token))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    #[ignore = "需要 MySQL：设置 TEST_DATABASE_URL 后运行 cargo test -- --ignored"]
+    async fn test_sync_blocked_until_email_verified() {
+        let sender = Arc::new(RecordingSender::default());
+        let state = state(mysql_service(enabled_config(), sender.clone()).await);
+        let mut app = app(state.clone());
+        let token = TokenService::generate_access_token(USER_ID, 7, SECRET).unwrap();
+
+        state.email_verification.start_verification(USER_ID, "a@example.com").await.unwrap();
+
+        let (status, body) = call(&mut app, sync_request(&token)).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert!(body.contains("EMAIL_UNVERIFIED"), "{}", body);
+
+        let (status, body) = call(
+            &mut app,
+            Request::get("/auth/verify?token=invalid").body(Body::empty()).unwrap(),
+        ).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(body.contains("INVALID_VERIFICATION_TOKEN"), "{}", body);
+
+        let verify = Request::get(format!("/auth/verify?token={}", sender.last_token()))
+            .body(Body::empty())
+            .unwrap();
+        let (status, body) = call(&mut app, verify).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+        assert!(body.contains("\"email_verified\":true"), "{}", body);
+
+        assert_eq!(call(&mut app, sync_request(&token)).await, (StatusCode::OK, "synced".to_string()));
+    }
+
+    #[tokio::test]
+    #[ignore = "需要 MySQL：设置 TEST_DATABASE_URL 后运行 cargo test -- --ignored"]
+    async fn test_verify_endpoint_accepts_post() {
+        let sender = Arc::new(RecordingSender::default());
+        let state = state(mysql_service(enabled_config(), sender.clone()).await);
+        let mut app = app(state.clone());
+
+        state.email_verification.start_verification(USER_ID, "a@example.com").await.unwrap();
+        let verify = Request::post("/auth/verify")
+            .header("Content-Type", "application/json")
+            .body(Body::from(format!("{{\"token\":\"{}\"}}", sender.last_token())))
+            .unwrap();
+
+        assert_eq!(call(&mut app, verify).await.0, StatusCode::OK);
+        assert!(state.email_verification.is_verified(USER_ID).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_sync_allowed_when_verification_disabled() {
+        let state = state(EmailVerificationService::new(testing::lazy_pool(), KvStore::in_memory(), Default::default()));
+        let mut app = app(state.clone());
+        let token = TokenService::generate_access_token(USER_ID, 7, SECRET).unwrap();
+
+        state.email_verification.start_verification(USER_ID, "a@example.com").await.unwrap();
+
+        assert_eq!(call(&mut app, sync_request(&token)).await.0, StatusCode::OK);
+    }
+}
@@ -1,13 +1,31 @@
 use axum::{
     extract::{Request, Extension},
+    http::{HeaderName, HeaderValue},
     response::Response,
 };
 use std::time::Instant;
 
+/// 请求 ID 请求头（客户端传入时沿用，响应中回传）
+pub static REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// 客户端传入的请求 ID 最大长度
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
 /// Request ID 标记（用于 Extension）
 #[derive(Clone)]
 pub struct RequestId(pub String);
 
+/// 读取客户端传入的请求 ID
+///
+/// 只接受长度不超过 128 的字母、数字、`-`、`_`、`.`，避免日志注入；不合法时返回 None
+fn incoming_request_id(req: &Request) -> Option<String> {
+    let value = req.headers().get(&REQUEST_ID_HEADER)?.to_str().ok()?.trim();
+    let valid = !value.is_empty()
+        && value.len() <= MAX_REQUEST_ID_LENGTH
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    valid.then(|| value.to_string())
+}
+
 /// 请求日志中间件
 ///
 /// 简化为3条日志：
@@ -25,8 +43,9 @@ pub async fn request_logging_middleware(
     let path = req.uri().path().to_string();
     let query = req.uri().query().map(|s| s.to_string());
 
-    // 生成请求 ID
-    let request_id = uuid::Uuid::new_v4().to_string();
+    // 优先沿用客户端传入的请求 ID，便于对照客户端日志；否则生成新的
+    let request_id = incoming_request_id(&req)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
 
     // 将 request_id 存储到请求扩展中，供 handler 使用
     req.extensions_mut().insert(RequestId(request_id.clone()));
@@ -49,7 +68,10 @@ pub async fn request_logging_middleware(
     );
 
     // 调用下一个处理器
-    let response = next.run(req).await;
+    let mut response = next.run(req).await;
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER.clone(), value);
+    }
 
     // ========== 第3条日志：请求完成 ==========
     let duration = start.elapsed();
@@ -101,3 +123,49 @@ pub fn log_info<T: std::fmt::Debug>(request_id: &RequestId, label: &str, data: T
         truncated
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::{to_bytes, Body}, middleware::from_fn, routing::get, Router};
+    use tower::Service;
+
+    fn app() -> Router {
+        Router::new()
+            .route("/sync", get(|Extension(request_id): Extension<RequestId>| async move { request_id.0 }))
+            .layer(from_fn(request_logging_middleware))
+    }
+
+    async fn call(request_id: Option<&str>) -> (Option<String>, String) {
+        let mut request = Request::get("/sync");
+        if let Some(id) = request_id {
+            request = request.header(&REQUEST_ID_HEADER, id);
+        }
+
+        let response = app().call(request.body(Body::empty()).unwrap()).await.unwrap();
+        let header = response.headers()
+            .get(&REQUEST_ID_HEADER)
+            .map(|v| v.to_str().unwrap().to_string());
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (header, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_incoming_request_id_is_used_and_echoed() {
+        let (header, handler_id) = call(Some("client-sync-42")).await;
+
+        assert_eq!(header.as_deref(), Some("client-sync-42"));
+        assert_eq!(handler_id, "client-sync-42");
+    }
+
+    #[tokio::test]
+    async fn test_request_id_is_generated_when_missing_or_invalid() {
+        for incoming in [None, Some("bad id"), Some("")] {
+            let (header, handler_id) = call(incoming).await;
+
+            let header = header.unwrap();
+            assert!(uuid::Uuid::parse_str(&header).is_ok());
+            assert_eq!(handler_id, header);
+        }
+    }
+}
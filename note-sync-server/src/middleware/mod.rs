@@ -1,4 +1,8 @@
 pub mod auth;
+pub mod compression;
+pub mod cors;
+pub mod email_verified;
 pub mod logging;
+pub mod rate_limit;
 
 pub use auth::auth_middleware;
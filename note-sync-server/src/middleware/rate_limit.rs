@@ -0,0 +1,194 @@
+// 登录 / 注册限流中间件
+use axum::{
+    body::{to_bytes, Body},
+    extract::{ConnectInfo, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use crate::config::RateLimitConfig;
+use crate::handlers::ErrorResponse;
+use crate::services::rate_limiter::RateLimiter;
+
+/// 登录 / 注册请求体上限（只需要读取 email）
+const MAX_AUTH_BODY_BYTES: usize = 64 * 1024;
+
+/// 限流中间件状态
+#[derive(Clone)]
+pub struct RateLimitState {
+    pub limiter: Arc<RateLimiter>,
+    pub config: RateLimitConfig,
+}
+
+/// 登录 / 注册限流中间件
+///
+/// - 同一 IP 在窗口内最多发起 `max_requests_per_ip` 次登录或注册请求
+/// - 同一邮箱在窗口内登录失败 `max_failed_logins_per_email` 次后拒绝继续登录，登录成功时清零
+///
+/// 超限时返回 429、`Retry-After` 和 `RATE_LIMITED` 错误码。Redis 不可用时放行请求
+pub async fn auth_rate_limit_middleware(
+    State(state): State<RateLimitState>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let config = &state.config;
+    if !config.enabled {
+        return next.run(req).await;
+    }
+
+    let path = req.uri().path().to_string();
+    let ip = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|info| info.0.ip().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    // 1. 按 IP 限制请求次数
+    match state.limiter.hit(&format!("ip:{}:{}", path, ip), config.window_secs).await {
+        Ok((count, retry_after)) if count > config.max_requests_per_ip as u64 => {
+            tracing::warn!("请求过于频繁: path={}, ip={}, count={}", path, ip, count);
+            return too_many_requests(retry_after);
+        }
+        Ok(_) => {}
+        Err(e) => tracing::warn!("限流计数失败，放行请求: {}", e),
+    }
+
+    if !path.ends_with("/login") {
+        return next.run(req).await;
+    }
+
+    // 2. 按邮箱限制登录失败次数（读取请求体中的 email 后原样交给 handler）
+    let (parts, body) = req.into_parts();
+    let bytes = match to_bytes(body, MAX_AUTH_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(_) => return (StatusCode::PAYLOAD_TOO_LARGE, "请求体过大").into_response(),
+    };
+    let email = serde_json::from_slice::<serde_json::Value>(&bytes)
+        .ok()
+        .and_then(|v| v.get("email")?.as_str().map(|e| e.trim().to_lowercase()));
+    let req = Request::from_parts(parts, Body::from(bytes));
+
+    let Some(email) = email else {
+        return next.run(req).await;
+    };
+    let email_key = format!("login_fail:{}", email);
+
+    match state.limiter.peek(&email_key).await {
+        Ok((failures, retry_after)) if failures >= config.max_failed_logins_per_email as u64 => {
+            tracing::warn!("登录失败次数过多: email={}, failures={}", email, failures);
+            return too_many_requests(retry_after);
+        }
+        Ok(_) => {}
+        Err(e) => tracing::warn!("读取登录失败次数失败，放行请求: {}", e),
+    }
+
+    let response = next.run(req).await;
+
+    let result = if response.status().is_success() {
+        state.limiter.reset(&email_key).await
+    } else if response.status().is_client_error() {
+        state.limiter.hit(&email_key, config.window_secs).await.map(|_| ())
+    } else {
+        Ok(())
+    };
+    if let Err(e) = result {
+        tracing::warn!("更新登录失败次数失败: {}", e);
+    }
+
+    response
+}
+
+fn too_many_requests(retry_after: u64) -> Response {
+    ErrorResponse::new_with_code("请求过于频繁，请稍后再试", 429, "RATE_LIMITED")
+        .with_retry_after(retry_after)
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::header, middleware::from_fn_with_state, routing::post, Json, Router};
+    use tower::Service;
+
+    fn app(config: RateLimitConfig) -> Router {
+        let state = RateLimitState {
+            limiter: Arc::new(RateLimiter::in_memory()),
+            config,
+        };
+
+        Router::new()
+            .route("/auth/login", post(|Json(body): Json<serde_json::Value>| async move {
+                if body["password"] == "correct" {
+                    Ok("token")
+                } else {
+                    Err(ErrorResponse::new("邮箱或密码错误"))
+                }
+            }))
+            .route("/auth/register", post(|| async { "ok" }))
+            .layer(from_fn_with_state(state, auth_rate_limit_middleware))
+    }
+
+    fn login(email: &str, password: &str) -> Request {
+        Request::post("/auth/login")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(serde_json::json!({ "email": email, "password": password }).to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_sixth_failed_login_is_rejected() {
+        let mut app = app(RateLimitConfig::default());
+
+        for _ in 0..5 {
+            let response = app.call(login("user@example.com", "wrong")).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+
+        // 第 6 次即使密码正确也会被拒绝
+        let response = app.call(login("User@Example.com", "correct")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key(header::RETRY_AFTER));
+
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error_code"], "RATE_LIMITED");
+
+        // 其他邮箱不受影响
+        let response = app.call(login("other@example.com", "correct")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_successful_login_resets_failures() {
+        let mut app = app(RateLimitConfig::default());
+
+        for _ in 0..4 {
+            app.call(login("user@example.com", "wrong")).await.unwrap();
+        }
+        let response = app.call(login("user@example.com", "correct")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        for _ in 0..5 {
+            let response = app.call(login("user@example.com", "wrong")).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_requests_per_ip_are_limited() {
+        let mut app = app(RateLimitConfig {
+            max_requests_per_ip: 3,
+            ..RateLimitConfig::default()
+        });
+
+        for _ in 0..3 {
+            let response = app.call(Request::post("/auth/register").body(Body::empty()).unwrap()).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        let response = app.call(Request::post("/auth/register").body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+}
@@ -13,16 +13,23 @@ pub struct Device {
     pub id: String,
     pub user_id: String,
     pub device_name: String,
+    pub custom_name: Option<String>,  // 用户自定义名称（优先于自动生成的 device_name）
     pub device_type: String,  // "desktop", "laptop", "mobile", "tablet"
     pub revoked: bool,  // ✅ 已存在
+    pub trusted: bool,
     pub last_seen_at: i64,
+    pub last_heartbeat_at: Option<i64>,
     pub created_at: i64,
+    /// 是否在线（最近心跳在在线阈值内，由服务器计算）
+    #[sqlx(default)]
+    pub online: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize, FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct SyncHistoryEntry {
     pub id: String,
     pub user_id: String,
+    pub device_id: Option<String>,  // 执行同步的设备（旧记录为空）
     pub sync_type: String,  // "push", "pull", "full"
     pub pushed_count: i32,
     pub pulled_count: i32,
@@ -32,6 +39,24 @@ pub struct SyncHistoryEntry {
     pub created_at: i64,
 }
 
+/// 同步历史分页结果
+#[derive(Debug, Serialize)]
+pub struct SyncHistoryPage {
+    pub entries: Vec<SyncHistoryEntry>,
+    pub next_cursor: Option<String>,  // 下一页游标（为空表示没有更多记录）
+}
+
+/// 按设备汇总的同步统计
+#[derive(Debug, Serialize, PartialEq, Eq, FromRow)]
+pub struct DeviceSyncSummary {
+    pub device_id: Option<String>,  // 设备 ID（为空表示未记录设备的旧记录）
+    pub sync_count: i64,
+    pub pushed_total: i64,
+    pub pulled_total: i64,
+    pub conflict_total: i64,
+    pub last_sync_at: i64,
+}
+
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct Note {
     pub id: String,
@@ -60,6 +85,8 @@ pub struct Note {
     #[serde(default)]
     pub is_pinned: bool,
     #[serde(default)]
+    pub is_private: bool,
+    #[serde(default)]
     pub author: Option<String>,
     #[serde(default)]
     pub word_count: i32,
@@ -125,6 +152,30 @@ pub struct NoteVersion {
     pub device_id: Option<String>,
 }
 
+/// 笔记附件（数据以 Base64 编码存储）
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Attachment {
+    pub id: String,
+    pub note_id: String,
+    #[serde(default)]
+    pub user_id: String,
+    pub workspace_id: Option<String>,
+    pub filename: String,
+    pub mime_type: String,
+    pub size_bytes: i64,
+    pub data: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    #[serde(default)]
+    pub is_deleted: bool,
+    pub deleted_at: Option<i64>,
+    #[serde(default)]
+    pub server_ver: i32,
+    // ===== 设备追踪字段 =====
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device_id: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct NoteTagRelation {
     pub note_id: String,
@@ -172,6 +223,13 @@ pub struct CreateWorkspaceRequest {
     pub color: Option<String>,
 }
 
+/// 更新设备请求（name 为空字符串时清除自定义名称）
+#[derive(Debug, Deserialize)]
+pub struct UpdateDeviceRequest {
+    pub name: Option<String>,
+    pub trusted: Option<bool>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct UpdateWorkspaceRequest {
     pub name: Option<String>,
@@ -203,4 +261,21 @@ pub enum ConflictResolutionStrategy {
     KeepLocal,
     /// 手动合并（等待用户处理）
     ManualMerge,
+    /// 修改时间较新的版本优先（updated_at 相差在时钟偏差内时按 KeepServer 处理）
+    LastWriteWins,
+}
+
+/// 按实体类型覆盖的冲突解决策略
+///
+/// 未设置的实体类型使用请求中的 `conflict_resolution`。
+/// 只有笔记支持 KeepBoth（创建冲突副本），快照不支持 LastWriteWins（没有修改时间）；
+/// 为实体显式指定不支持的策略时同步接口返回 400，继承来的不支持的策略按 KeepServer 处理
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
+pub struct ConflictStrategies {
+    pub workspace: Option<ConflictResolutionStrategy>,
+    pub note: Option<ConflictResolutionStrategy>,
+    pub folder: Option<ConflictResolutionStrategy>,
+    pub tag: Option<ConflictResolutionStrategy>,
+    pub snapshot: Option<ConflictResolutionStrategy>,
+    pub attachment: Option<ConflictResolutionStrategy>,
 }
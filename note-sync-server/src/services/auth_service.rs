@@ -7,8 +7,45 @@ use anyhow::Result;
 use sqlx::MySqlPool;
 use rand::Rng;
 
+use crate::config::PasswordPolicyConfig;
 use crate::models::User;
-use super::token_service::TokenService;
+use super::token_blacklist::TokenBlacklist;
+use super::token_service::{RefreshTokenClaims, TokenService};
+
+/// refresh token 有效期（秒），与 TokenService 签发的有效期一致
+const REFRESH_TOKEN_TTL_SECS: u64 = 30 * 24 * 3600;
+
+/// 已轮换的刷新令牌被再次使用（视为令牌泄露，整个令牌族已被吊销）
+#[derive(Debug)]
+pub struct RefreshTokenReused;
+
+impl std::fmt::Display for RefreshTokenReused {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "刷新令牌已失效，请重新登录")
+    }
+}
+
+impl std::error::Error for RefreshTokenReused {}
+
+/// 修改密码失败的原因（需要区分返回的状态码）
+#[derive(Debug, PartialEq)]
+pub enum PasswordChangeError {
+    /// 原密码错误
+    WrongPassword,
+    /// 新密码不满足密码策略
+    WeakPassword(String),
+}
+
+impl std::fmt::Display for PasswordChangeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::WrongPassword => write!(f, "原密码错误"),
+            Self::WeakPassword(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for PasswordChangeError {}
 
 pub struct AuthService {
     pool: MySqlPool,
@@ -31,6 +68,30 @@ impl AuthService {
         Ok(existing > 0)
     }
 
+    /// 校验密码强度（同步操作）
+    ///
+    /// 不满足策略时返回可直接展示给用户的原因
+    pub fn validate_password(password: &str, policy: &PasswordPolicyConfig) -> std::result::Result<(), String> {
+        if password.chars().count() < policy.min_length {
+            return Err(format!("密码长度至少为 {} 个字符", policy.min_length));
+        }
+
+        let classes = [
+            password.chars().any(|c| c.is_lowercase()),
+            password.chars().any(|c| c.is_uppercase()),
+            password.chars().any(|c| c.is_ascii_digit()),
+            password.chars().any(|c| !c.is_alphanumeric()),
+        ];
+        if classes.iter().filter(|present| **present).count() < policy.min_character_classes {
+            return Err(format!(
+                "密码需至少包含以下 {} 类字符：小写字母、大写字母、数字、符号",
+                policy.min_character_classes
+            ));
+        }
+
+        Ok(())
+    }
+
     /// 哈希密码（同步操作）
     pub fn hash_password(&self, password: &str) -> Result<String> {
         let salt = SaltString::generate(&mut OsRng);
@@ -242,11 +303,38 @@ impl AuthService {
     }
 
     /// 使用 refresh_token 刷新 access_token
-    pub async fn refresh_access_token(&self, refresh_token: &str, device_id: String) -> Result<(String, String)> {
-        // 1. 计算 refresh_token 的哈希
-        let token_hash = TokenService::hash_token(refresh_token);
+    ///
+    /// 每次刷新都会轮换 refresh token：新令牌沿用原令牌族，原令牌记入黑名单。
+    /// 已轮换的令牌再次出现时吊销整个令牌族并返回 [`RefreshTokenReused`]
+    pub async fn refresh_access_token(&self, refresh_token: &str, device_id: String, blacklist: &TokenBlacklist) -> Result<(String, String)> {
+        let config = crate::config::AppConfig::load(None)?;
+
+        // 1. 解析 refresh_token，占用轮换记录（同一令牌只有一个请求能通过）
+        let claims = TokenService::decode_refresh_token(refresh_token, &config.auth.jwt_secret)?;
+        Self::claim_refresh_rotation(blacklist, &claims).await?;
+
+        match self.rotate_refresh_token(refresh_token, &claims, device_id, &config).await {
+            Ok(tokens) => Ok(tokens),
+            Err(e) => {
+                // 轮换未完成：释放占用，客户端可以用原令牌重试
+                if let Err(release_err) = blacklist.remove(&rotated_refresh_key(&claims.jti)).await {
+                    tracing::warn!("Failed to release refresh token rotation: {}", release_err);
+                }
+                Err(e)
+            }
+        }
+    }
 
+    /// 校验数据库中的 refresh_token 记录并签发新的令牌对
+    async fn rotate_refresh_token(
+        &self,
+        refresh_token: &str,
+        claims: &RefreshTokenClaims,
+        device_id: String,
+        config: &crate::config::AppConfig,
+    ) -> Result<(String, String)> {
         // 2. 查询 refresh_token 记录
+        let token_hash = TokenService::hash_token(refresh_token);
         let (user_id, expires_at): (String, i64) = sqlx::query_as(
             "SELECT user_id, expires_at FROM refresh_tokens WHERE token_hash = ? AND device_id = ?"
         )
@@ -276,16 +364,135 @@ impl AuthService {
         .await?
         .ok_or_else(|| anyhow::anyhow!("用户不存在"))?;
 
-        // 5. 生成新的 token
-        let config = crate::config::AppConfig::load(None)?;
-        let (access_token, new_refresh_token) = TokenService::generate_token_pair(&user.id, config.auth.jwt_expiration_days, &config.auth.jwt_secret)?;
+        // 5. 生成新的 token（沿用原令牌族）
+        let (access_token, new_refresh_token) = TokenService::rotate_token_pair(&user.id, &claims.family, config.auth.jwt_expiration_days, &config.auth.jwt_secret)?;
 
-        // 6. 保存新的 refresh_token（轮换策略）
+        // 6. 保存新的 refresh_token
         self.save_refresh_token(&user_id, &new_refresh_token, device_id).await?;
 
         Ok((access_token, new_refresh_token))
     }
 
+    /// 清除用户的 refresh token（`keep_refresh_token` 对应的记录除外），返回清除的数量
+    pub async fn revoke_refresh_tokens(&self, user_id: &str, keep_refresh_token: Option<&str>) -> Result<u64> {
+        let keep_hash = keep_refresh_token.map(TokenService::hash_token).unwrap_or_default();
+
+        let result = sqlx::query(
+            "DELETE FROM refresh_tokens WHERE user_id = ? AND token_hash <> ?"
+        )
+        .bind(user_id)
+        .bind(&keep_hash)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// 吊销当前设备的 refresh token（登出时调用）
+    ///
+    /// 删除数据库中的记录并吊销所属令牌族，该令牌及其轮换出的令牌都不能再用于刷新；
+    /// 令牌不属于 `user_id` 时返回错误
+    pub async fn revoke_refresh_token(
+        &self,
+        user_id: &str,
+        refresh_token: &str,
+        jwt_secret: &str,
+        blacklist: &TokenBlacklist,
+    ) -> Result<()> {
+        let claims = TokenService::decode_refresh_token(refresh_token, jwt_secret)?;
+        if claims.user_id != user_id {
+            return Err(anyhow::anyhow!("无效的刷新令牌"));
+        }
+
+        Self::revoke_refresh_family(blacklist, &claims).await?;
+
+        sqlx::query("DELETE FROM refresh_tokens WHERE user_id = ? AND token_hash = ?")
+            .bind(user_id)
+            .bind(TokenService::hash_token(refresh_token))
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// 吊销 refresh token 所属的令牌族
+    pub async fn revoke_refresh_family(blacklist: &TokenBlacklist, claims: &RefreshTokenClaims) -> Result<()> {
+        blacklist.add(&refresh_family_key(&claims.family), REFRESH_TOKEN_TTL_SECS).await
+    }
+
+    /// 占用 refresh token 的轮换记录（SET NX，保留到该令牌过期）
+    ///
+    /// 所属令牌族已被吊销，或令牌已被轮换（包括并发的另一个刷新请求已占用）时返回
+    /// `RefreshTokenReused`；已轮换的令牌再次出现说明令牌可能已泄露，此时吊销整个令牌族
+    /// （包括最新签发的令牌）
+    pub async fn claim_refresh_rotation(blacklist: &TokenBlacklist, claims: &RefreshTokenClaims) -> Result<()> {
+        if blacklist.contains(&refresh_family_key(&claims.family)).await? {
+            return Err(RefreshTokenReused.into());
+        }
+
+        let ttl_seconds = (claims.exp as i64 - Utc::now().timestamp()).max(1) as u64;
+        if !blacklist.add_if_absent(&rotated_refresh_key(&claims.jti), ttl_seconds).await? {
+            tracing::warn!(
+                "Refresh token reused, revoking token family: user_id={}, family={}",
+                claims.user_id,
+                claims.family
+            );
+            Self::revoke_refresh_family(blacklist, claims).await?;
+            return Err(RefreshTokenReused.into());
+        }
+
+        Ok(())
+    }
+
+    /// 校验原密码并生成新密码的哈希（同步操作）
+    ///
+    /// 原密码错误或新密码不满足策略时返回 `PasswordChangeError`
+    pub fn prepare_password_change(
+        &self,
+        stored_hash: &str,
+        old_password: &str,
+        new_password: &str,
+        policy: &PasswordPolicyConfig,
+    ) -> Result<String> {
+        let parsed_hash = PasswordHash::new(stored_hash)
+            .map_err(|e| anyhow::anyhow!("解析密码哈希失败: {}", e))?;
+        Argon2::default()
+            .verify_password(old_password.as_bytes(), &parsed_hash)
+            .map_err(|_| PasswordChangeError::WrongPassword)?;
+
+        Self::validate_password(new_password, policy)
+            .map_err(PasswordChangeError::WeakPassword)?;
+
+        self.hash_password(new_password)
+    }
+
+    /// 修改密码（需要验证原密码）
+    pub async fn change_password(
+        &self,
+        user_id: &str,
+        old_password: &str,
+        new_password: &str,
+        policy: &PasswordPolicyConfig,
+    ) -> Result<()> {
+        let stored_hash: String = sqlx::query_scalar(
+            "SELECT password_hash FROM users WHERE id = ?"
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("用户不存在"))?;
+
+        let new_hash = self.prepare_password_change(&stored_hash, old_password, new_password, policy)?;
+
+        sqlx::query("UPDATE users SET password_hash = ? WHERE id = ?")
+            .bind(&new_hash)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
     /// 删除用户账号（级联删除所有相关数据）
     pub async fn delete_user(&self, user_id: &str, password: &str) -> Result<()> {
         // 1. 验证密码
@@ -313,3 +520,203 @@ impl AuthService {
         Ok(())
     }
 }
+
+/// 已轮换的 refresh token 在黑名单中的标识
+fn rotated_refresh_key(jti: &str) -> String {
+    format!("refresh:{}", jti)
+}
+
+/// 已吊销的令牌族在黑名单中的标识
+fn refresh_family_key(family: &str) -> String {
+    format!("refresh_family:{}", family)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(min_length: usize, min_character_classes: usize) -> PasswordPolicyConfig {
+        PasswordPolicyConfig { min_length, min_character_classes }
+    }
+
+    #[test]
+    fn test_validate_password_length_boundary() {
+        let policy = policy(8, 0);
+        assert!(AuthService::validate_password("abcdefgh", &policy).is_ok());
+        assert!(AuthService::validate_password("abcdefg", &policy).is_err());
+        // 按字符而不是字节计数
+        assert!(AuthService::validate_password("密码密码密码密码", &policy).is_ok());
+        assert!(AuthService::validate_password("密码密码密码密", &policy).is_err());
+    }
+
+    #[test]
+    fn test_validate_password_character_class_boundary() {
+        let policy = policy(8, 3);
+        assert!(AuthService::validate_password("abcdefG1", &policy).is_ok());
+        assert!(AuthService::validate_password("abcdef!1", &policy).is_ok());
+
+        let reason = AuthService::validate_password("abcdefg1", &policy).unwrap_err();
+        assert!(reason.contains('3'));
+        assert!(AuthService::validate_password("abcdefgh", &policy).is_err());
+    }
+
+    #[test]
+    fn test_validate_password_default_policy() {
+        let policy = PasswordPolicyConfig::default();
+        assert!(AuthService::validate_password("password1", &policy).is_ok());
+        assert!(AuthService::validate_password("password", &policy).is_err());
+        assert!(AuthService::validate_password("pass1", &policy).is_err());
+    }
+
+    fn service() -> AuthService {
+        AuthService::new(sqlx::mysql::MySqlPoolOptions::new().connect_lazy("mysql://root@localhost/test").unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_prepare_password_change_success() {
+        let service = service();
+        let stored_hash = service.hash_password("oldpass123").unwrap();
+
+        let new_hash = service
+            .prepare_password_change(&stored_hash, "oldpass123", "newpass456", &PasswordPolicyConfig::default())
+            .unwrap();
+
+        let parsed = PasswordHash::new(&new_hash).unwrap();
+        assert!(Argon2::default().verify_password(b"newpass456", &parsed).is_ok());
+        assert!(Argon2::default().verify_password(b"oldpass123", &parsed).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_prepare_password_change_wrong_old_password() {
+        let service = service();
+        let stored_hash = service.hash_password("oldpass123").unwrap();
+
+        let err = service
+            .prepare_password_change(&stored_hash, "wrongpass1", "newpass456", &PasswordPolicyConfig::default())
+            .unwrap_err();
+
+        assert_eq!(err.downcast_ref::<PasswordChangeError>(), Some(&PasswordChangeError::WrongPassword));
+    }
+
+    #[tokio::test]
+    async fn test_prepare_password_change_weak_new_password() {
+        let service = service();
+        let stored_hash = service.hash_password("oldpass123").unwrap();
+
+        let err = service
+            .prepare_password_change(&stored_hash, "oldpass123", "short", &PasswordPolicyConfig::default())
+            .unwrap_err();
+
+        assert!(matches!(err.downcast_ref::<PasswordChangeError>(), Some(PasswordChangeError::WeakPassword(_))));
+    }
+
+    const SECRET: &str = "test-secret";
+
+    fn refresh_claims(token: &str) -> RefreshTokenClaims {
+        TokenService::decode_refresh_token(token, SECRET).unwrap()
+    }
+
+    #[test]
+    fn test_rotated_refresh_token_keeps_family() {
+        let (_, first) = TokenService::generate_token_pair("1000000001", 7, SECRET).unwrap();
+        let first = refresh_claims(&first);
+        let (_, second) = TokenService::rotate_token_pair("1000000001", &first.family, 7, SECRET).unwrap();
+        let second = refresh_claims(&second);
+
+        assert_eq!(second.family, first.family);
+        assert_ne!(second.jti, first.jti);
+        assert_eq!(second.user_id, "1000000001");
+    }
+
+    #[test]
+    fn test_access_token_is_not_a_refresh_token() {
+        let (access, _) = TokenService::generate_token_pair("1000000001", 7, SECRET).unwrap();
+        assert!(TokenService::decode_refresh_token(&access, SECRET).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reused_refresh_token_revokes_family() {
+        let blacklist = TokenBlacklist::in_memory();
+        let (_, first) = TokenService::generate_token_pair("1000000001", 7, SECRET).unwrap();
+        let first = refresh_claims(&first);
+
+        // 正常轮换
+        AuthService::claim_refresh_rotation(&blacklist, &first).await.unwrap();
+        let (_, second) = TokenService::rotate_token_pair("1000000001", &first.family, 7, SECRET).unwrap();
+        let second = refresh_claims(&second);
+
+        // 旧令牌再次出现：拒绝并吊销整个令牌族
+        let err = AuthService::claim_refresh_rotation(&blacklist, &first).await.unwrap_err();
+        assert!(err.downcast_ref::<RefreshTokenReused>().is_some());
+        let err = AuthService::claim_refresh_rotation(&blacklist, &second).await.unwrap_err();
+        assert!(err.downcast_ref::<RefreshTokenReused>().is_some());
+
+        // 其他令牌族不受影响
+        let (_, other) = TokenService::generate_token_pair("1000000001", 7, SECRET).unwrap();
+        AuthService::claim_refresh_rotation(&blacklist, &refresh_claims(&other)).await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_concurrent_refresh_with_same_token_is_reuse() {
+        let blacklist = std::sync::Arc::new(TokenBlacklist::in_memory());
+        let (_, token) = TokenService::generate_token_pair("1000000001", 7, SECRET).unwrap();
+        let claims = refresh_claims(&token);
+
+        let attempts: Vec<_> = (0..2)
+            .map(|_| {
+                let blacklist = blacklist.clone();
+                let claims = claims.clone();
+                tokio::spawn(async move { AuthService::claim_refresh_rotation(&blacklist, &claims).await })
+            })
+            .collect();
+        let mut results = Vec::new();
+        for attempt in attempts {
+            results.push(attempt.await.unwrap());
+        }
+
+        // 只有一个请求能轮换，另一个按重复使用处理并吊销令牌族
+        assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 1);
+        let err = results.into_iter().find_map(|result| result.err()).unwrap();
+        assert!(err.downcast_ref::<RefreshTokenReused>().is_some());
+        let (_, rotated) = TokenService::rotate_token_pair("1000000001", &claims.family, 7, SECRET).unwrap();
+        assert!(AuthService::claim_refresh_rotation(&blacklist, &refresh_claims(&rotated)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_revoked_family_cannot_refresh() {
+        let blacklist = TokenBlacklist::in_memory();
+        let (_, token) = TokenService::generate_token_pair("1000000001", 7, SECRET).unwrap();
+        let claims = refresh_claims(&token);
+
+        AuthService::revoke_refresh_family(&blacklist, &claims).await.unwrap();
+
+        let err = AuthService::claim_refresh_rotation(&blacklist, &claims).await.unwrap_err();
+        assert!(err.downcast_ref::<RefreshTokenReused>().is_some());
+    }
+
+    #[tokio::test]
+    #[ignore = "需要 MySQL：设置 TEST_DATABASE_URL 后运行 cargo test -- --ignored"]
+    async fn test_revoke_refresh_token_deletes_only_that_device() {
+        let service = AuthService::new(crate::testing::mysql_pool().await);
+        let blacklist = TokenBlacklist::in_memory();
+        let user_id = crate::testing::USER_ID;
+        let (_, current) = TokenService::generate_token_pair(user_id, 7, SECRET).unwrap();
+        let (_, other) = TokenService::generate_token_pair(user_id, 7, SECRET).unwrap();
+        service.save_refresh_token(user_id, &current, "device-a".to_string()).await.unwrap();
+        service.save_refresh_token(user_id, &other, "device-b".to_string()).await.unwrap();
+
+        // 令牌不属于该用户
+        assert!(service.revoke_refresh_token("1000000002", &current, SECRET, &blacklist).await.is_err());
+
+        service.revoke_refresh_token(user_id, &current, SECRET, &blacklist).await.unwrap();
+
+        let devices: Vec<String> = sqlx::query_scalar("SELECT device_id FROM refresh_tokens WHERE user_id = ?")
+            .bind(user_id)
+            .fetch_all(&service.pool)
+            .await
+            .unwrap();
+        assert_eq!(devices, ["device-b"]);
+        assert!(AuthService::claim_refresh_rotation(&blacklist, &refresh_claims(&current)).await.is_err());
+        AuthService::claim_refresh_rotation(&blacklist, &refresh_claims(&other)).await.unwrap();
+    }
+}
@@ -79,7 +79,6 @@ pub struct DeviceInfo {
     pub device_type: DeviceType,
     pub platform: Platform,
     pub uuid: String,
-    pub raw_id: String,
 }
 
 /// 设备标识解析服务
@@ -100,7 +99,7 @@ impl DeviceIdentifierService {
     pub fn parse_device_id(device_id: &str) -> Result<DeviceInfo> {
         let parts: Vec<&str> = device_id.split('-').collect();
 
-        let (device_type, platform, uuid, raw_id) = if parts.len() >= 3 {
+        let (device_type, platform, uuid) = if parts.len() >= 3 {
             // 新格式: <type>-<platform>-<uuid>
             let device_type = parts[0].parse::<DeviceType>()
                 .unwrap_or(DeviceType::Unknown);
@@ -108,12 +107,12 @@ impl DeviceIdentifierService {
                 .unwrap_or(Platform::Unknown);
             let uuid = parts[2..].join("-");
 
-            (device_type, platform, uuid, device_id.to_string())
+            (device_type, platform, uuid)
         } else if parts.len() == 2 && parts[0] == "default" {
             // 旧格式: default-<md5>
             let uuid = parts[1].to_string();
 
-            (DeviceType::Unknown, Platform::Unknown, uuid, device_id.to_string())
+            (DeviceType::Unknown, Platform::Unknown, uuid)
         } else if parts.len() == 2 {
             // 过渡格式: <platform>-<uuid>
             let platform = parts[0].parse::<Platform>()
@@ -127,7 +126,7 @@ impl DeviceIdentifierService {
                 Platform::Unknown => DeviceType::Unknown,
             };
 
-            (device_type, platform, uuid, device_id.to_string())
+            (device_type, platform, uuid)
         } else {
             return Err(anyhow::anyhow!("Invalid device_id format: {}", device_id));
         };
@@ -136,7 +135,6 @@ impl DeviceIdentifierService {
             device_type,
             platform,
             uuid,
-            raw_id,
         })
     }
 
@@ -199,6 +197,17 @@ impl DeviceIdentifierService {
         }
     }
 
+    /// 解析设备的展示名称
+    ///
+    /// 用户设置了自定义名称时优先使用，否则使用自动生成的名称
+    pub fn resolve_device_name(derived_name: &str, custom_name: Option<&str>) -> String {
+        custom_name
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .unwrap_or(derived_name)
+            .to_string()
+    }
+
     /// 获取设备图标
     pub fn get_device_icon(info: &DeviceInfo) -> &'static str {
         match (info.device_type, info.platform) {
@@ -225,7 +234,7 @@ mod tests {
 
         assert_eq!(info.device_type, DeviceType::Desktop);
         assert_eq!(info.platform, Platform::Windows);
-        assert_eq!(info.raw_id, device_id);
+        assert_eq!(info.uuid, "a1b2c3d4-e5f6-7890-abcd-ef1234567890");
     }
 
     #[test]
@@ -254,20 +263,34 @@ mod tests {
             device_type: DeviceType::Desktop,
             platform: Platform::Windows,
             uuid: "a1b2c3d4-e5f6-7890-abcd-ef1234567890".to_string(),
-            raw_id: "desktop-windows-a1b2c3d4-e5f6-7890-abcd-ef1234567890".to_string(),
         };
 
         let name = DeviceIdentifierService::get_device_name(&info);
         assert_eq!(name, "Windows 电脑");
     }
 
+    #[test]
+    fn test_resolve_device_name() {
+        assert_eq!(
+            DeviceIdentifierService::resolve_device_name("Windows 电脑", Some("工作笔记本")),
+            "工作笔记本"
+        );
+        assert_eq!(
+            DeviceIdentifierService::resolve_device_name("Windows 电脑", None),
+            "Windows 电脑"
+        );
+        assert_eq!(
+            DeviceIdentifierService::resolve_device_name("Windows 电脑", Some("  ")),
+            "Windows 电脑"
+        );
+    }
+
     #[test]
     fn test_device_icon() {
         let info = DeviceInfo {
             device_type: DeviceType::Tablet,
             platform: Platform::IOS,
             uuid: "a1b2c3d4-e5f6-7890-abcd-ef1234567890".to_string(),
-            raw_id: "mobile-ios-a1b2c3d4-e5f6-7890-abcd-ef1234567890".to_string(),
         };
 
         let icon = DeviceIdentifierService::get_device_icon(&info);
@@ -1,7 +1,11 @@
 use anyhow::Result;
 use sqlx::MySqlPool;
 use chrono::Utc;
-use crate::models::Device;
+use crate::models::{Device, UpdateDeviceRequest};
+use crate::services::device_identifier_service::DeviceIdentifierService;
+
+/// 设备自定义名称最大长度（字符数）
+const MAX_DEVICE_NAME_LENGTH: usize = 255;
 
 /// 设备服务
 pub struct DeviceService {
@@ -90,7 +94,7 @@ impl DeviceService {
             device.last_seen_at = now;
             device.device_name = device_name.to_string();
             device.device_type = device_type.to_string();
-            Ok(device)
+            Ok(Self::with_display_name(device))
         } else {
             // 创建新设备记录（该用户首次使用此设备）
             tracing::info!(
@@ -115,16 +119,22 @@ impl DeviceService {
                 id: device_id.to_string(),
                 user_id: user_id.to_string(),
                 device_name: device_name.to_string(),
+                custom_name: None,
                 device_type: device_type.to_string(),
                 revoked: false,
+                trusted: false,
                 last_seen_at: now,
+                last_heartbeat_at: None,
                 created_at: now,
+                online: false,
             })
         }
     }
 
     /// 获取用户的所有设备
-    pub async fn list_devices(&self, user_id: &str) -> Result<Vec<Device>> {
+    ///
+    /// 最近心跳在 `online_threshold_secs` 内的设备标记为在线
+    pub async fn list_devices(&self, user_id: &str, online_threshold_secs: i64) -> Result<Vec<Device>> {
         let devices = sqlx::query_as::<_, Device>(
             "SELECT * FROM devices
              WHERE user_id = ? AND revoked = false
@@ -134,7 +144,83 @@ impl DeviceService {
         .fetch_all(&self.pool)
         .await?;
 
-        Ok(devices)
+        let now = Utc::now().timestamp();
+        Ok(devices
+            .into_iter()
+            .map(|device| with_online_status(Self::with_display_name(device), now, online_threshold_secs))
+            .collect())
+    }
+
+    /// 更新设备（重命名、标记受信任）
+    ///
+    /// - `name` 为空字符串时清除自定义名称，恢复使用自动生成的名称
+    /// - 自定义名称单独保存，登录时重新生成的 device_name 不会覆盖它
+    pub async fn update_device(
+        &self,
+        device_id: &str,
+        user_id: &str,
+        req: &UpdateDeviceRequest,
+        online_threshold_secs: i64,
+    ) -> Result<Device> {
+        let custom_name = req.name.as_deref().map(str::trim);
+        if custom_name.is_some_and(|name| name.chars().count() > MAX_DEVICE_NAME_LENGTH) {
+            return Err(anyhow::anyhow!(
+                "Device name must not exceed {} characters",
+                MAX_DEVICE_NAME_LENGTH
+            ));
+        }
+
+        let mut device = sqlx::query_as::<_, Device>(
+            "SELECT * FROM devices
+             WHERE user_id = ? AND id = ? AND revoked = false
+             LIMIT 1"
+        )
+        .bind(user_id)
+        .bind(device_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Device not found or revoked"))?;
+
+        if let Some(name) = custom_name {
+            let name = Some(name.to_string()).filter(|name| !name.is_empty());
+            sqlx::query("UPDATE devices SET custom_name = ? WHERE user_id = ? AND id = ?")
+                .bind(&name)
+                .bind(user_id)
+                .bind(device_id)
+                .execute(&self.pool)
+                .await?;
+            device.custom_name = name;
+        }
+
+        if let Some(trusted) = req.trusted {
+            sqlx::query("UPDATE devices SET trusted = ? WHERE user_id = ? AND id = ?")
+                .bind(trusted)
+                .bind(user_id)
+                .bind(device_id)
+                .execute(&self.pool)
+                .await?;
+            device.trusted = trusted;
+        }
+
+        tracing::info!(
+            "更新设备: user_id={}, device_id={}, custom_name={:?}, trusted={}",
+            user_id, device_id, device.custom_name, device.trusted
+        );
+
+        Ok(with_online_status(
+            Self::with_display_name(device),
+            Utc::now().timestamp(),
+            online_threshold_secs,
+        ))
+    }
+
+    /// 将 device_name 替换为展示名称（自定义名称优先）
+    fn with_display_name(mut device: Device) -> Device {
+        device.device_name = DeviceIdentifierService::resolve_device_name(
+            &device.device_name,
+            device.custom_name.as_deref(),
+        );
+        device
     }
 
     /// 撤销设备
@@ -161,10 +247,11 @@ impl DeviceService {
         let now = Utc::now().timestamp();
 
         let rows_affected = sqlx::query(
-            "UPDATE devices SET last_seen_at = ?
+            "UPDATE devices SET last_seen_at = ?, last_heartbeat_at = ?
              WHERE id = ? AND user_id = ? AND revoked = false"
         )
         .bind(now)
+        .bind(now)
         .bind(device_id)
         .bind(user_id)
         .execute(&self.pool)
@@ -179,9 +266,57 @@ impl DeviceService {
     }
 }
 
+/// 根据最近心跳时间计算设备是否在线
+///
+/// 从未发送心跳的设备视为离线，展示 last_seen_at 作为最后活跃时间
+fn with_online_status(mut device: Device, now: i64, online_threshold_secs: i64) -> Device {
+    device.online = device
+        .last_heartbeat_at
+        .is_some_and(|heartbeat_at| now - heartbeat_at <= online_threshold_secs);
+    device
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::{self, USER_ID};
+
+    fn device(last_seen_at: i64, last_heartbeat_at: Option<i64>) -> Device {
+        Device {
+            id: "device-1".to_string(),
+            user_id: "user-1".to_string(),
+            device_name: "Linux 电脑".to_string(),
+            custom_name: None,
+            device_type: "desktop".to_string(),
+            revoked: false,
+            trusted: false,
+            last_seen_at,
+            last_heartbeat_at,
+            created_at: 0,
+            online: false,
+        }
+    }
+
+    #[test]
+    fn test_recent_heartbeat_is_online() {
+        let status = with_online_status(device(1_000, Some(1_000)), 1_200, 300);
+        assert!(status.online);
+
+        let status = with_online_status(device(1_000, Some(1_000)), 1_300, 300);
+        assert!(status.online);
+    }
+
+    #[test]
+    fn test_stale_heartbeat_is_offline_with_last_seen() {
+        let status = with_online_status(device(1_000, Some(1_000)), 1_301, 300);
+        assert!(!status.online);
+        assert_eq!(status.last_seen_at, 1_000);
+
+        // 从未发送心跳（只登录过）
+        let status = with_online_status(device(1_250, None), 1_300, 300);
+        assert!(!status.online);
+        assert_eq!(status.last_seen_at, 1_250);
+    }
 
     #[test]
     fn test_parse_device_type_desktop() {
@@ -256,4 +391,30 @@ mod tests {
 
         println!("✅ Edge cases handled correctly");
     }
+
+    fn rename(name: &str) -> UpdateDeviceRequest {
+        UpdateDeviceRequest { name: Some(name.to_string()), trusted: None }
+    }
+
+    #[tokio::test]
+    #[ignore = "需要 MySQL：设置 TEST_DATABASE_URL 后运行 cargo test -- --ignored"]
+    async fn test_rename_persists_across_relogin_and_can_be_cleared() {
+        let service = DeviceService::new(testing::mysql_pool().await);
+        service.register_or_update(USER_ID, "device-1", "Linux 电脑", "desktop").await.unwrap();
+
+        let updated = service.update_device("device-1", USER_ID, &rename("  书房电脑  "), 300).await.unwrap();
+        assert_eq!(updated.custom_name.as_deref(), Some("书房电脑"));
+
+        // 重新登录会刷新自动生成的名称，但不会覆盖自定义名称
+        service.register_or_update(USER_ID, "device-1", "Ubuntu 电脑", "desktop").await.unwrap();
+        let devices = service.list_devices(USER_ID, 300).await.unwrap();
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].custom_name.as_deref(), Some("书房电脑"));
+        assert_eq!(devices[0].device_name, "书房电脑");
+
+        service.update_device("device-1", USER_ID, &rename(""), 300).await.unwrap();
+        let devices = service.list_devices(USER_ID, 300).await.unwrap();
+        assert_eq!(devices[0].custom_name, None);
+        assert_eq!(devices[0].device_name, "Ubuntu 电脑");
+    }
 }
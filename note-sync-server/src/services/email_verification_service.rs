@@ -0,0 +1,202 @@
+use anyhow::Result;
+use sqlx::MySqlPool;
+use std::sync::Arc;
+
+use crate::config::EmailVerificationConfig;
+use crate::services::kv_store::KvStore;
+use crate::services::token_service::TokenService;
+
+/// 验证邮件发送接口（可替换为 SMTP 等实际实现）
+pub trait EmailSender: Send + Sync {
+    fn send_verification_email(&self, email: &str, verify_link: &str) -> Result<()>;
+}
+
+/// 默认的发送实现：只记录日志，不实际发送邮件
+pub struct LoggingEmailSender;
+
+impl EmailSender for LoggingEmailSender {
+    fn send_verification_email(&self, email: &str, verify_link: &str) -> Result<()> {
+        tracing::info!("邮箱验证链接（未配置邮件发送）: email={}, link={}", email, verify_link);
+        Ok(())
+    }
+}
+
+/// 注册邮箱验证服务
+///
+/// 启用后新注册的账号标记为未验证，验证前不能同步；未启用时所有账号视为已验证。
+/// 验证状态保存在 users.email_verified，验证令牌只保存哈希，存放在键值存储中到期自动清除
+pub struct EmailVerificationService {
+    pool: MySqlPool,
+    tokens: KvStore,
+    sender: Arc<dyn EmailSender>,
+    config: EmailVerificationConfig,
+}
+
+impl EmailVerificationService {
+    pub fn new(pool: MySqlPool, tokens: KvStore, config: EmailVerificationConfig) -> Self {
+        Self {
+            pool,
+            tokens,
+            sender: Arc::new(LoggingEmailSender),
+            config,
+        }
+    }
+
+    /// 替换验证邮件的发送实现
+    pub fn with_sender(mut self, sender: Arc<dyn EmailSender>) -> Self {
+        self.sender = sender;
+        self
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// 将新注册的账号标记为未验证，并发送验证链接（未启用时不做任何操作）
+    ///
+    /// 邮件发送失败只记录日志，不影响注册
+    pub async fn start_verification(&self, user_id: &str, email: &str) -> Result<()> {
+        if !self.enabled() {
+            return Ok(());
+        }
+
+        let token = format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple());
+        let ttl_seconds = (self.config.token_ttl_hours * 3600).max(1) as u64;
+
+        sqlx::query("UPDATE users SET email_verified = FALSE WHERE id = ?")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        self.tokens
+            .set_ex(&token_key(&token), user_id, ttl_seconds)
+            .await?;
+
+        let verify_link = format!("{}?token={}", self.config.verify_url, token);
+        if let Err(e) = self.sender.send_verification_email(email, &verify_link) {
+            tracing::error!("发送验证邮件失败: user_id={}, error={}", user_id, e);
+        }
+        Ok(())
+    }
+
+    /// 使用验证令牌完成验证
+    ///
+    /// 令牌只能使用一次；返回验证成功的用户 ID，令牌无效或已过期时返回 None
+    pub async fn verify(&self, token: &str) -> Result<Option<String>> {
+        let Some(user_id) = self.tokens.take(&token_key(token)).await? else {
+            return Ok(None);
+        };
+
+        sqlx::query("UPDATE users SET email_verified = TRUE WHERE id = ?")
+            .bind(&user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(Some(user_id))
+    }
+
+    /// 账号是否允许同步（未启用邮箱验证时总是返回 true）
+    pub async fn is_verified(&self, user_id: &str) -> Result<bool> {
+        if !self.enabled() {
+            return Ok(true);
+        }
+
+        let verified: Option<bool> = sqlx::query_scalar("SELECT email_verified FROM users WHERE id = ?")
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(verified.unwrap_or(false))
+    }
+}
+
+/// 验证令牌在键值存储中的键（只保存令牌哈希）
+fn token_key(token: &str) -> String {
+    format!("email_verification:{}", TokenService::hash_token(token))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::testing::{self, USER_ID};
+    use std::sync::Mutex;
+
+    /// 记录发送的验证链接
+    #[derive(Default)]
+    pub(crate) struct RecordingSender {
+        pub(crate) links: Mutex<Vec<(String, String)>>,
+    }
+
+    impl RecordingSender {
+        /// 最近一次发送的验证令牌
+        pub(crate) fn last_token(&self) -> String {
+            let links = self.links.lock().unwrap();
+            let (_, link) = links.last().expect("应已发送验证邮件");
+            link.split("token=").nth(1).unwrap().to_string()
+        }
+    }
+
+    impl EmailSender for RecordingSender {
+        fn send_verification_email(&self, email: &str, verify_link: &str) -> Result<()> {
+            self.links.lock().unwrap().push((email.to_string(), verify_link.to_string()));
+            Ok(())
+        }
+    }
+
+    pub(crate) fn enabled_config() -> EmailVerificationConfig {
+        EmailVerificationConfig {
+            enabled: true,
+            ..Default::default()
+        }
+    }
+
+    /// 使用 MySQL 测试库的服务（验证令牌保存在内存中）
+    pub(crate) async fn mysql_service(config: EmailVerificationConfig, sender: Arc<RecordingSender>) -> EmailVerificationService {
+        EmailVerificationService::new(testing::mysql_pool().await, KvStore::in_memory(), config).with_sender(sender)
+    }
+
+    #[tokio::test]
+    #[ignore = "需要 MySQL：设置 TEST_DATABASE_URL 后运行 cargo test -- --ignored"]
+    async fn test_verification_token_marks_user_verified_once() {
+        let sender = Arc::new(RecordingSender::default());
+        let service = mysql_service(enabled_config(), sender.clone()).await;
+
+        service.start_verification(USER_ID, "a@example.com").await.unwrap();
+        assert!(!service.is_verified(USER_ID).await.unwrap());
+
+        let token = sender.last_token();
+        assert!(sender.links.lock().unwrap()[0].1.starts_with("http://localhost:3000/auth/verify?token="));
+        assert_eq!(service.verify("wrong-token").await.unwrap(), None);
+        assert_eq!(service.verify(&token).await.unwrap().as_deref(), Some(USER_ID));
+        assert!(service.is_verified(USER_ID).await.unwrap());
+        assert_eq!(service.verify(&token).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    #[ignore = "需要 MySQL：设置 TEST_DATABASE_URL 后运行 cargo test -- --ignored"]
+    async fn test_expired_token_is_rejected() {
+        let sender = Arc::new(RecordingSender::default());
+        let config = EmailVerificationConfig {
+            token_ttl_hours: 0,
+            ..enabled_config()
+        };
+        let service = mysql_service(config, sender.clone()).await;
+
+        service.start_verification(USER_ID, "a@example.com").await.unwrap();
+        // 有效期为 0 时令牌保留 1 秒
+        tokio::time::sleep(std::time::Duration::from_millis(1_100)).await;
+
+        assert_eq!(service.verify(&sender.last_token()).await.unwrap(), None);
+        assert!(!service.is_verified(USER_ID).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_disabled_verification_is_noop() {
+        let sender = Arc::new(RecordingSender::default());
+        let service = EmailVerificationService::new(testing::lazy_pool(), KvStore::in_memory(), EmailVerificationConfig::default())
+            .with_sender(sender.clone());
+
+        service.start_verification(USER_ID, "a@example.com").await.unwrap();
+
+        assert!(sender.links.lock().unwrap().is_empty());
+        assert!(service.is_verified(USER_ID).await.unwrap());
+    }
+}
@@ -0,0 +1,288 @@
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::mysql::MySqlRow;
+use sqlx::{FromRow, MySqlPool};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::models::{Attachment, Device, Folder, Note, NoteTagRelation, NoteVersion, Tag, User, Workspace};
+use crate::services::profile_service::UserProfile;
+
+/// 导出格式版本（导出结构变化时递增）
+const EXPORT_FORMAT_VERSION: u32 = 1;
+/// 每次查询的行数（按主键分页，避免一次加载所有数据）
+const EXPORT_PAGE_SIZE: i64 = 200;
+/// 等待写出的数据块数量上限（客户端读取慢时暂停查询）
+const EXPORT_CHANNEL_CAPACITY: usize = 8;
+
+/// 导出文件中的数据分区（按顺序写出）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExportSection {
+    Workspaces,
+    Folders,
+    Notes,
+    Tags,
+    NoteTags,
+    Snapshots,
+    Attachments,
+    Devices,
+}
+
+impl ExportSection {
+    pub const ALL: [ExportSection; 8] = [
+        Self::Workspaces,
+        Self::Folders,
+        Self::Notes,
+        Self::Tags,
+        Self::NoteTags,
+        Self::Snapshots,
+        Self::Attachments,
+        Self::Devices,
+    ];
+
+    /// 导出 JSON 中的字段名
+    pub fn key(self) -> &'static str {
+        match self {
+            Self::Workspaces => "workspaces",
+            Self::Folders => "folders",
+            Self::Notes => "notes",
+            Self::Tags => "tags",
+            Self::NoteTags => "note_tags",
+            Self::Snapshots => "snapshots",
+            Self::Attachments => "attachments",
+            Self::Devices => "devices",
+        }
+    }
+
+    /// 行的分页游标（note_tags 为复合主键，其余为 id）
+    fn cursor(self, row: &Value) -> String {
+        let field = |name: &str| row.get(name).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+        match self {
+            Self::NoteTags => format!("{}:{}", field("note_id"), field("tag_id")),
+            _ => field("id"),
+        }
+    }
+}
+
+/// 账号数据导出服务
+///
+/// 导出用户在服务器上的全部数据（所有工作空间），不包含密码哈希和令牌
+pub struct ExportService {
+    pool: MySqlPool,
+}
+
+impl ExportService {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+
+    /// 以流的形式输出导出 JSON
+    ///
+    /// 后台任务按分区、按页查询并逐块写出；查询失败时流以错误结束
+    pub fn stream(self, user_id: String) -> ReceiverStream<std::io::Result<String>> {
+        let (tx, rx) = mpsc::channel(EXPORT_CHANNEL_CAPACITY);
+
+        tokio::spawn(async move {
+            if let Err(e) = self.write_export(&user_id, &tx).await {
+                tracing::error!("导出账号数据失败: user_id={}, error={}", user_id, e);
+                let _ = tx.send(Err(std::io::Error::other(e.to_string()))).await;
+            }
+        });
+
+        ReceiverStream::new(rx)
+    }
+
+    async fn write_export(&self, user_id: &str, tx: &mpsc::Sender<std::io::Result<String>>) -> Result<()> {
+        let (user, profile) = self.fetch_account(user_id).await?;
+        let header = format!(
+            "{{\"format_version\":{},\"exported_at\":{},\"user\":{},\"profile\":{}",
+            EXPORT_FORMAT_VERSION,
+            chrono::Utc::now().timestamp(),
+            serde_json::to_string(&user)?,
+            serde_json::to_string(&profile)?,
+        );
+        send(tx, header).await?;
+
+        for section in ExportSection::ALL {
+            send(tx, format!(",\"{}\":[", section.key())).await?;
+
+            let mut cursor = String::new();
+            let mut first = true;
+            loop {
+                let rows = self.fetch_page(section, user_id, &cursor, EXPORT_PAGE_SIZE).await?;
+                let Some(last) = rows.last() else {
+                    break;
+                };
+                cursor = section.cursor(last);
+
+                let mut chunk = String::new();
+                for row in &rows {
+                    if !first {
+                        chunk.push(',');
+                    }
+                    first = false;
+                    chunk.push_str(&serde_json::to_string(row)?);
+                }
+                send(tx, chunk).await?;
+
+                if (rows.len() as i64) < EXPORT_PAGE_SIZE {
+                    break;
+                }
+            }
+
+            send(tx, "]".to_string()).await?;
+        }
+
+        send(tx, "}".to_string()).await
+    }
+
+    /// 查询用户基本信息和个人资料（只选取不含密码哈希的字段）
+    async fn fetch_account(&self, user_id: &str) -> Result<(Value, Value)> {
+        let user: User = sqlx::query_as("SELECT id, email, created_at FROM users WHERE id = ?")
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("用户不存在"))?;
+        let profile: Option<UserProfile> = sqlx::query_as("SELECT * FROM user_profiles WHERE user_id = ?")
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok((serde_json::to_value(user)?, serde_json::to_value(profile)?))
+    }
+
+    /// 查询某个分区中游标之后的一页数据（按主键升序）
+    async fn fetch_page(&self, section: ExportSection, user_id: &str, cursor: &str, limit: i64) -> Result<Vec<Value>> {
+        let (pool, page) = (&self.pool, Page { user_id, cursor, limit });
+        match section {
+            ExportSection::Workspaces => page.fetch::<Workspace>(pool, "SELECT * FROM workspaces WHERE user_id = ? AND id > ? ORDER BY id LIMIT ?").await,
+            ExportSection::Folders => page.fetch::<Folder>(pool, "SELECT * FROM folders WHERE user_id = ? AND id > ? ORDER BY id LIMIT ?").await,
+            ExportSection::Notes => page.fetch::<Note>(pool, "SELECT * FROM notes WHERE user_id = ? AND id > ? ORDER BY id LIMIT ?").await,
+            ExportSection::Tags => page.fetch::<Tag>(pool, "SELECT * FROM tags WHERE user_id = ? AND id > ? ORDER BY id LIMIT ?").await,
+            ExportSection::NoteTags => page.fetch::<NoteTagRelation>(
+                pool,
+                "SELECT * FROM note_tags WHERE user_id = ? AND CONCAT(note_id, ':', tag_id) > ? ORDER BY note_id, tag_id LIMIT ?",
+            ).await,
+            ExportSection::Snapshots => page.fetch::<NoteVersion>(pool, "SELECT * FROM note_versions WHERE user_id = ? AND id > ? ORDER BY id LIMIT ?").await,
+            ExportSection::Attachments => page.fetch::<Attachment>(pool, "SELECT * FROM attachments WHERE user_id = ? AND id > ? ORDER BY id LIMIT ?").await,
+            ExportSection::Devices => page.fetch::<Device>(pool, "SELECT * FROM devices WHERE user_id = ? AND id > ? ORDER BY id LIMIT ?").await,
+        }
+    }
+}
+
+/// 分页查询参数（SQL 依次绑定 user_id、游标、行数）
+struct Page<'a> {
+    user_id: &'a str,
+    cursor: &'a str,
+    limit: i64,
+}
+
+impl Page<'_> {
+    async fn fetch<T>(&self, pool: &MySqlPool, sql: &str) -> Result<Vec<Value>>
+    where
+        T: for<'r> FromRow<'r, MySqlRow> + Serialize + Send + Unpin,
+    {
+        let rows: Vec<T> = sqlx::query_as(sql)
+            .bind(self.user_id)
+            .bind(self.cursor)
+            .bind(self.limit)
+            .fetch_all(pool)
+            .await?;
+
+        rows.iter().map(|row| Ok(serde_json::to_value(row)?)).collect()
+    }
+}
+
+async fn send(tx: &mpsc::Sender<std::io::Result<String>>, chunk: String) -> Result<()> {
+    tx.send(Ok(chunk)).await.map_err(|_| anyhow::anyhow!("客户端已断开连接"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, USER_ID};
+
+    const OTHER: &str = "1000000002";
+
+    /// 用户数据的行数（笔记超过一页，验证分页能取到所有数据）
+    fn expected_rows(section: ExportSection) -> usize {
+        if section == ExportSection::Notes { EXPORT_PAGE_SIZE as usize + 5 } else { 2 }
+    }
+
+    /// 为 `user_id` 写入每个分区的数据，ID 以用户 ID 开头便于区分
+    async fn seed(pool: &MySqlPool, user_id: &str, notes: usize) {
+        let run = |sql: String| async move { sqlx::raw_sql(&sql).execute(pool).await.unwrap() };
+
+        run(format!(
+            "INSERT IGNORE INTO users (id, email, password_hash, created_at) VALUES ('{u}', '{u}@example.com', 'secret-hash', 0);
+             INSERT INTO user_profiles (id, user_id, username, created_at, updated_at) VALUES ('profile-{u}', '{u}', '{u}', 0, 0);",
+            u = user_id
+        )).await;
+        for i in 0..notes {
+            run(format!(
+                "INSERT INTO notes (id, user_id, title, content, is_deleted, created_at, updated_at, server_ver)
+                 VALUES ('{u}-note-{i:04}', '{u}', '标题', '内容', FALSE, 0, 0, 1)",
+                u = user_id, i = i
+            )).await;
+        }
+        for i in 0..2 {
+            run(format!(
+                "INSERT INTO workspaces (id, user_id, name, is_default, sort_order, is_deleted, created_at, updated_at, server_ver)
+                 VALUES ('{u}-ws-{i}', '{u}', '空间', FALSE, 0, FALSE, 0, 0, 1);
+                 INSERT INTO folders (id, user_id, name, is_deleted, created_at, updated_at, server_ver)
+                 VALUES ('{u}-folder-{i}', '{u}', '文件夹', FALSE, 0, 0, 1);
+                 INSERT INTO tags (id, user_id, name, is_deleted, created_at, updated_at, server_ver)
+                 VALUES ('{u}-tag-{i}', '{u}', '标签{i}', FALSE, 0, 0, 1);
+                 INSERT INTO note_tags (note_id, tag_id, user_id, is_deleted, created_at)
+                 VALUES ('{u}-note-0000', '{u}-tag-{i}', '{u}', FALSE, 0);
+                 INSERT INTO note_versions (id, note_id, user_id, title, content, created_at, server_ver)
+                 VALUES ('{u}-version-{i}', '{u}-note-0000', '{u}', '标题', '内容', 0, 1);
+                 INSERT INTO attachments (id, note_id, user_id, filename, mime_type, size_bytes, data, is_deleted, created_at, updated_at, server_ver)
+                 VALUES ('{u}-attachment-{i}', '{u}-note-0000', '{u}', 'a.png', 'image/png', 1, 'QQ==', FALSE, 0, 0, 1);
+                 INSERT INTO devices (id, user_id, device_name, device_type, revoked, trusted, last_seen_at, created_at)
+                 VALUES ('{u}-device-{i}', '{u}', '电脑', 'desktop', FALSE, FALSE, 0, 0);",
+                u = user_id, i = i
+            )).await;
+        }
+    }
+
+    async fn export(pool: &MySqlPool, user_id: &str) -> std::result::Result<axum::body::Bytes, axum::Error> {
+        let stream = ExportService::new(pool.clone()).stream(user_id.to_string());
+        axum::body::to_bytes(axum::body::Body::from_stream(stream), usize::MAX).await
+    }
+
+    #[tokio::test]
+    #[ignore = "需要 MySQL：设置 TEST_DATABASE_URL 后运行 cargo test -- --ignored"]
+    async fn test_export_contains_all_entities_of_user_only() {
+        let pool = testing::mysql_pool().await;
+        seed(&pool, USER_ID, EXPORT_PAGE_SIZE as usize + 5).await;
+        seed(&pool, OTHER, 1).await;
+
+        let bytes = export(&pool, USER_ID).await.unwrap();
+        let export: Value = serde_json::from_slice(&bytes).unwrap();
+
+        assert_eq!(export["format_version"], EXPORT_FORMAT_VERSION);
+        assert_eq!(export["user"]["id"], USER_ID);
+        assert!(export["user"].get("password_hash").is_none());
+        assert_eq!(export["profile"]["user_id"], USER_ID);
+
+        for section in ExportSection::ALL {
+            let rows = export[section.key()].as_array().unwrap();
+            assert_eq!(rows.len(), expected_rows(section), "{}", section.key());
+            assert!(rows.iter().all(|row| row["user_id"] == USER_ID), "{}", section.key());
+        }
+
+        let text = String::from_utf8(bytes.to_vec()).unwrap();
+        assert!(!text.contains(OTHER));
+        assert!(!text.contains("secret-hash"));
+    }
+
+    #[tokio::test]
+    #[ignore = "需要 MySQL：设置 TEST_DATABASE_URL 后运行 cargo test -- --ignored"]
+    async fn test_export_unknown_user_fails() {
+        let pool = testing::mysql_pool().await;
+
+        assert!(export(&pool, "1000000003").await.is_err());
+    }
+}
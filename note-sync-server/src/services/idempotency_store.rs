@@ -0,0 +1,344 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use crate::config::IdempotencyConfig;
+use crate::services::kv_store::KvStore;
+use crate::services::token_service::TokenService;
+
+/// 处理中标记的保留时间（秒），防止进程异常退出后键一直处于处理中
+const PENDING_TTL_SECS: u64 = 300;
+/// 记录格式：处理中为 `pending:{请求指纹}`，已完成为 `done:{请求指纹}:{响应 JSON}`
+const PENDING_PREFIX: &str = "pending:";
+const DONE_PREFIX: &str = "done:";
+/// 等待处理中的请求时检查结果的间隔
+const IN_PROGRESS_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 幂等处理结果
+#[derive(Debug, PartialEq)]
+pub struct IdempotentResponse {
+    /// 响应 JSON
+    pub body: String,
+    /// 是否为重放的首次处理结果
+    pub replayed: bool,
+}
+
+/// 幂等处理失败的原因
+#[derive(Debug)]
+pub enum IdempotencyError<E> {
+    /// 相同幂等键的请求仍在处理中
+    InProgress,
+    /// 幂等键已用于请求内容不同的另一个请求
+    Mismatch,
+    /// 读写幂等记录失败
+    Store(anyhow::Error),
+    /// 请求本身处理失败（幂等键已释放，可以重试）
+    Failed(E),
+}
+
+/// 幂等键存储
+///
+/// 与 TokenBlacklist 共用 Redis 连接，记录到期后由 Redis 自动清除
+pub struct IdempotencyStore {
+    store: KvStore,
+    config: IdempotencyConfig,
+}
+
+impl IdempotencyStore {
+    /// 使用已有的键值存储创建幂等键存储
+    pub fn new(store: KvStore, config: IdempotencyConfig) -> Self {
+        Self { store, config }
+    }
+
+    /// 内存存储（仅用于测试）
+    #[cfg(test)]
+    pub fn in_memory(config: IdempotencyConfig) -> Self {
+        Self::new(KvStore::in_memory(), config)
+    }
+
+    /// 以幂等方式执行 `op`
+    ///
+    /// - 未提供幂等键时直接执行
+    /// - 首次出现的键：执行并保存响应，保留 `ttl_secs` 秒
+    /// - 已完成的键：不再执行，返回保存的响应
+    /// - 键已用于其他请求（`fingerprint` 不同）：不执行，返回 `Mismatch`
+    /// - 仍在处理中的键：最多等待 `in_progress_wait_ms` 毫秒，期间首次请求完成则返回其响应，
+    ///   首次请求失败释放了键则重新执行，仍未完成时返回 `InProgress`
+    ///
+    /// 执行被取消（如客户端断开连接）时释放幂等键，重试不必等待处理中标记过期
+    ///
+    /// `scope` 区分用户和接口，不同用户使用相同的键互不影响；
+    /// `fingerprint` 是请求内容的指纹（见 [`request_fingerprint`]），与幂等键一起保存
+    pub async fn run<T, E, F, Fut>(
+        &self,
+        scope: &str,
+        key: Option<&str>,
+        fingerprint: &str,
+        op: F,
+    ) -> std::result::Result<IdempotentResponse, IdempotencyError<E>>
+    where
+        T: Serialize,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = std::result::Result<T, E>>,
+    {
+        let Some(key) = key else {
+            let value = op().await.map_err(IdempotencyError::Failed)?;
+            return serialize(&value).map(|body| IdempotentResponse { body, replayed: false });
+        };
+        let key = format!("idempotency:{}:{}", scope, key);
+
+        let wait_until = Instant::now() + Duration::from_millis(self.config.in_progress_wait_ms);
+        while let Some(existing) = self.reserve(&key, fingerprint).await.map_err(IdempotencyError::Store)? {
+            let (stored_fingerprint, body) = parse_record(&existing);
+            if stored_fingerprint != fingerprint {
+                return Err(IdempotencyError::Mismatch);
+            }
+            if let Some(body) = body {
+                return Ok(IdempotentResponse { body: body.to_string(), replayed: true });
+            }
+            if Instant::now() >= wait_until {
+                return Err(IdempotencyError::InProgress);
+            }
+            tokio::time::sleep(IN_PROGRESS_POLL_INTERVAL).await;
+        }
+
+        let reservation = Reservation { store: self.store.clone(), key: Some(key) };
+        let result = op().await.map(|value| serialize(&value));
+        let body = match result {
+            Ok(Ok(body)) => body,
+            Ok(Err(e)) => {
+                reservation.release().await;
+                return Err(e);
+            }
+            Err(e) => {
+                reservation.release().await;
+                return Err(IdempotencyError::Failed(e));
+            }
+        };
+
+        let key = reservation.keep();
+        if body.len() > self.config.max_response_bytes {
+            tracing::warn!("Idempotent response too large to store: key={}, bytes={}", key, body.len());
+            release_quietly(&self.store, &key).await;
+        } else if let Err(e) = self.store.set_ex(&key, &format!("{}{}:{}", DONE_PREFIX, fingerprint, body), self.config.ttl_secs).await {
+            // 请求已处理成功，保存失败只影响之后的重试
+            tracing::error!("Failed to store idempotent response: key={}, error={}", key, e);
+            release_quietly(&self.store, &key).await;
+        }
+
+        Ok(IdempotentResponse { body, replayed: false })
+    }
+
+    /// 占用幂等键（SET NX），键已存在时返回已有的记录
+    async fn reserve(&self, key: &str, fingerprint: &str) -> Result<Option<String>> {
+        let pending = format!("{}{}", PENDING_PREFIX, fingerprint);
+        if self.store.set_nx_ex(key, &pending, PENDING_TTL_SECS).await? {
+            return Ok(None);
+        }
+
+        // 读取前记录刚好过期时按处理中返回，由客户端稍后重试
+        let existing = self.store.get(key).await?;
+        Ok(Some(existing.unwrap_or(pending)))
+    }
+}
+
+/// 请求内容的指纹（请求 JSON 的 SHA-256），用于识别复用幂等键的不同请求
+pub fn request_fingerprint<T: Serialize>(request: &T) -> String {
+    TokenService::hash_token(&serde_json::to_string(request).unwrap_or_default())
+}
+
+/// 解析幂等记录，返回 (请求指纹, 已完成时的响应 JSON)
+fn parse_record(record: &str) -> (&str, Option<&str>) {
+    match record.strip_prefix(DONE_PREFIX) {
+        Some(rest) => {
+            let (fingerprint, body) = rest.split_once(':').unwrap_or(("", rest));
+            (fingerprint, Some(body))
+        }
+        None => (record.strip_prefix(PENDING_PREFIX).unwrap_or_default(), None),
+    }
+}
+
+/// 释放幂等键（处理失败后允许使用同一个键重试）
+async fn release_quietly(store: &KvStore, key: &str) {
+    if let Err(e) = store.del(key).await {
+        tracing::error!("Failed to release idempotency key: key={}, error={}", key, e);
+    }
+}
+
+/// 已占用的幂等键
+///
+/// 未调用 `release` 或 `keep` 就被丢弃（请求处理被取消）时在后台释放
+struct Reservation {
+    store: KvStore,
+    key: Option<String>,
+}
+
+impl Reservation {
+    /// 立即释放幂等键
+    async fn release(mut self) {
+        if let Some(key) = self.key.take() {
+            release_quietly(&self.store, &key).await;
+        }
+    }
+
+    /// 保留幂等键（由调用方保存处理结果），返回键名
+    fn keep(mut self) -> String {
+        self.key.take().unwrap_or_default()
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        let Some(key) = self.key.take() else {
+            return;
+        };
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        tracing::warn!("Idempotent request cancelled, releasing key: key={}", key);
+        let store = self.store.clone();
+        runtime.spawn(async move { release_quietly(&store, &key).await });
+    }
+}
+
+fn serialize<T: Serialize, E>(value: &T) -> std::result::Result<String, IdempotencyError<E>> {
+    serde_json::to_string(value).map_err(|e| IdempotencyError::Store(e.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// 每次执行都计数，返回本次是第几次执行
+    async fn counted(calls: &AtomicUsize) -> std::result::Result<usize, String> {
+        Ok(calls.fetch_add(1, Ordering::SeqCst) + 1)
+    }
+
+    #[tokio::test]
+    async fn test_replayed_key_returns_first_response_without_running_again() {
+        let store = IdempotencyStore::in_memory(IdempotencyConfig::default());
+        let calls = AtomicUsize::new(0);
+
+        let first = store.run("notes:1000000001", Some("key-1"), "fp", || counted(&calls)).await.unwrap();
+        let replay = store.run("notes:1000000001", Some("key-1"), "fp", || counted(&calls)).await.unwrap();
+
+        assert!(!first.replayed);
+        assert!(replay.replayed);
+        assert_eq!(first.body, replay.body);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // 不同的键或不同的用户作为新请求处理
+        store.run("notes:1000000001", Some("key-2"), "fp", || counted(&calls)).await.unwrap();
+        store.run("notes:1000000002", Some("key-1"), "fp", || counted(&calls)).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_requests_without_key_are_not_deduplicated() {
+        let store = IdempotencyStore::in_memory(IdempotencyConfig::default());
+        let calls = AtomicUsize::new(0);
+
+        store.run("notes:1000000001", None, "fp", || counted(&calls)).await.unwrap();
+        store.run("notes:1000000001", None, "fp", || counted(&calls)).await.unwrap();
+
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_failed_request_releases_key() {
+        let store = IdempotencyStore::in_memory(IdempotencyConfig::default());
+        let calls = AtomicUsize::new(0);
+
+        let failed = store
+            .run("notes:1000000001", Some("key-1"), "fp", || async { Err::<usize, _>("数据库不可用".to_string()) })
+            .await;
+        assert!(matches!(failed, Err(IdempotencyError::Failed(_))));
+
+        let retried = store.run("notes:1000000001", Some("key-1"), "fp", || counted(&calls)).await.unwrap();
+        assert!(!retried.replayed);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_pending_key_is_rejected_after_wait() {
+        let config = IdempotencyConfig { in_progress_wait_ms: 0, ..Default::default() };
+        let store = IdempotencyStore::in_memory(config);
+        store.reserve("idempotency:notes:1000000001:key-1", "fp").await.unwrap();
+
+        let result = store
+            .run("notes:1000000001", Some("key-1"), "fp", || async { Ok::<_, String>(1) })
+            .await;
+
+        assert!(matches!(result, Err(IdempotencyError::InProgress)));
+    }
+
+    #[tokio::test]
+    async fn test_retry_waits_for_pending_request() {
+        let store = Arc::new(IdempotencyStore::in_memory(IdempotencyConfig::default()));
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let first = {
+            let (store, calls) = (store.clone(), calls.clone());
+            tokio::spawn(async move {
+                store.run("sync:1000000001", Some("key-1"), "fp", || async {
+                    tokio::time::sleep(Duration::from_millis(300)).await;
+                    counted(&calls).await
+                }).await.unwrap()
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let retry = store.run("sync:1000000001", Some("key-1"), "fp", || counted(&calls)).await.unwrap();
+        let first = first.await.unwrap();
+
+        assert!(retry.replayed);
+        assert_eq!(retry.body, first.body);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_request_releases_key() {
+        let store = IdempotencyStore::in_memory(IdempotencyConfig { in_progress_wait_ms: 0, ..Default::default() });
+        let calls = AtomicUsize::new(0);
+
+        // 处理过程中客户端断开：handler 的 future 被丢弃
+        let cancelled = tokio::time::timeout(
+            Duration::from_millis(20),
+            store.run("sync:1000000001", Some("key-1"), "fp", std::future::pending::<std::result::Result<usize, String>>),
+        ).await;
+        assert!(cancelled.is_err());
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let retried = store.run("sync:1000000001", Some("key-1"), "fp", || counted(&calls)).await.unwrap();
+        assert!(!retried.replayed);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_response_is_not_stored() {
+        let config = IdempotencyConfig { max_response_bytes: 0, ..Default::default() };
+        let store = IdempotencyStore::in_memory(config);
+        let calls = AtomicUsize::new(0);
+
+        store.run("notes:1000000001", Some("key-1"), "fp", || counted(&calls)).await.unwrap();
+        let second = store.run("notes:1000000001", Some("key-1"), "fp", || counted(&calls)).await.unwrap();
+
+        assert!(!second.replayed);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_reused_key_with_different_request_is_rejected() {
+        let store = IdempotencyStore::in_memory(IdempotencyConfig::default());
+        let calls = AtomicUsize::new(0);
+
+        store.run("notes:1000000001", Some("key-1"), "fp-a", || counted(&calls)).await.unwrap();
+        let result = store.run("notes:1000000001", Some("key-1"), "fp-b", || counted(&calls)).await;
+
+        assert!(matches!(result, Err(IdempotencyError::Mismatch)));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,284 @@
+use redis::{AsyncCommands, Client};
+use redis::aio::ConnectionManager;
+use anyhow::Result;
+use std::sync::Arc;
+#[cfg(test)]
+use std::collections::HashMap;
+#[cfg(test)]
+use std::time::{Duration, Instant};
+
+/// 带过期时间的键值存储
+///
+/// 生产环境使用 Redis，测试使用内存实现。Token 黑名单、限流、幂等键和邮箱验证令牌
+/// 都建立在它之上，共用同一个 Redis 连接；所有写入的键都带过期时间，到期后自动清除
+#[derive(Clone)]
+pub struct KvStore {
+    backend: Backend,
+}
+
+#[derive(Clone)]
+enum Backend {
+    Redis(Arc<tokio::sync::Mutex<ConnectionManager>>),
+    #[cfg(test)]
+    Memory(Arc<std::sync::Mutex<HashMap<String, (String, Instant)>>>),
+}
+
+/// 计数加一；计数键没有过期时间时（第一次计数）设置窗口，返回 {计数, 剩余秒数}
+const INCR_IN_WINDOW_SCRIPT: &str = r"
+local count = redis.call('INCR', KEYS[1])
+if redis.call('TTL', KEYS[1]) < 0 then
+    redis.call('EXPIRE', KEYS[1], ARGV[1])
+end
+return {count, redis.call('TTL', KEYS[1])}
+";
+
+#[cfg(test)]
+type MemoryEntries = HashMap<String, (String, Instant)>;
+
+impl KvStore {
+    /// 连接 Redis
+    pub async fn connect(redis_url: &str) -> Result<Self> {
+        let client = Client::open(redis_url)?;
+        let manager = ConnectionManager::new(client).await?;
+        Ok(Self {
+            backend: Backend::Redis(Arc::new(tokio::sync::Mutex::new(manager))),
+        })
+    }
+
+    /// 内存存储（仅用于测试）
+    #[cfg(test)]
+    pub fn in_memory() -> Self {
+        Self {
+            backend: Backend::Memory(Arc::new(std::sync::Mutex::new(HashMap::new()))),
+        }
+    }
+
+    /// 检查连接是否可用（PING）
+    pub async fn ping(&self) -> Result<()> {
+        match &self.backend {
+            Backend::Redis(manager) => {
+                let mut conn = manager.lock().await;
+                redis::cmd("PING")
+                    .query_async::<_, String>(&mut *conn)
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Redis ping failed: {}", e))?;
+            }
+            #[cfg(test)]
+            Backend::Memory(_) => {}
+        }
+        Ok(())
+    }
+
+    /// 读取未过期的值
+    pub async fn get(&self, key: &str) -> Result<Option<String>> {
+        match &self.backend {
+            Backend::Redis(manager) => {
+                let mut conn = manager.lock().await;
+                conn.get(key)
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Redis GET {} failed: {}", key, e))
+            }
+            #[cfg(test)]
+            Backend::Memory(entries) => Ok(live(&mut entries.lock().unwrap(), key).map(|(value, _)| value.clone())),
+        }
+    }
+
+    /// 写入值，`ttl_seconds` 秒后过期
+    pub async fn set_ex(&self, key: &str, value: &str, ttl_seconds: u64) -> Result<()> {
+        match &self.backend {
+            Backend::Redis(manager) => {
+                let mut conn = manager.lock().await;
+                conn.set_ex::<_, _, ()>(key, value, ttl_seconds)
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Redis SET {} failed: {}", key, e))?;
+            }
+            #[cfg(test)]
+            Backend::Memory(entries) => {
+                entries.lock().unwrap().insert(key.to_string(), (value.to_string(), expires_at(ttl_seconds)));
+            }
+        }
+        Ok(())
+    }
+
+    /// 键不存在时写入值（SET NX EX），返回是否写入成功
+    pub async fn set_nx_ex(&self, key: &str, value: &str, ttl_seconds: u64) -> Result<bool> {
+        match &self.backend {
+            Backend::Redis(manager) => {
+                let mut conn = manager.lock().await;
+                let reply: Option<String> = redis::cmd("SET")
+                    .arg(key)
+                    .arg(value)
+                    .arg("NX")
+                    .arg("EX")
+                    .arg(ttl_seconds)
+                    .query_async(&mut *conn)
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Redis SET NX {} failed: {}", key, e))?;
+                Ok(reply.is_some())
+            }
+            #[cfg(test)]
+            Backend::Memory(entries) => {
+                let mut entries = entries.lock().unwrap();
+                if live(&mut entries, key).is_some() {
+                    return Ok(false);
+                }
+                entries.insert(key.to_string(), (value.to_string(), expires_at(ttl_seconds)));
+                Ok(true)
+            }
+        }
+    }
+
+    /// 键是否存在且未过期
+    pub async fn exists(&self, key: &str) -> Result<bool> {
+        match &self.backend {
+            Backend::Redis(manager) => {
+                let mut conn = manager.lock().await;
+                conn.exists(key)
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Redis EXISTS {} failed: {}", key, e))
+            }
+            #[cfg(test)]
+            Backend::Memory(entries) => Ok(live(&mut entries.lock().unwrap(), key).is_some()),
+        }
+    }
+
+    /// 删除键
+    pub async fn del(&self, key: &str) -> Result<()> {
+        match &self.backend {
+            Backend::Redis(manager) => {
+                let mut conn = manager.lock().await;
+                conn.del::<_, ()>(key)
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Redis DEL {} failed: {}", key, e))?;
+            }
+            #[cfg(test)]
+            Backend::Memory(entries) => {
+                entries.lock().unwrap().remove(key);
+            }
+        }
+        Ok(())
+    }
+
+    /// 读取并删除键（GET + DEL 在同一个事务中执行），值只能被取走一次
+    pub async fn take(&self, key: &str) -> Result<Option<String>> {
+        match &self.backend {
+            Backend::Redis(manager) => {
+                let mut conn = manager.lock().await;
+                let (value,): (Option<String>,) = redis::pipe()
+                    .atomic()
+                    .get(key)
+                    .del(key)
+                    .ignore()
+                    .query_async(&mut *conn)
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Redis GET/DEL {} failed: {}", key, e))?;
+                Ok(value)
+            }
+            #[cfg(test)]
+            Backend::Memory(entries) => {
+                let mut entries = entries.lock().unwrap();
+                let value = live(&mut entries, key).map(|(value, _)| value.clone());
+                entries.remove(key);
+                Ok(value)
+            }
+        }
+    }
+
+    /// 键的剩余过期秒数（键不存在时为 0）
+    pub async fn ttl(&self, key: &str) -> Result<u64> {
+        match &self.backend {
+            Backend::Redis(manager) => {
+                let mut conn = manager.lock().await;
+                let ttl: i64 = conn.ttl(key)
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Redis TTL {} failed: {}", key, e))?;
+                Ok(ttl.max(0) as u64)
+            }
+            #[cfg(test)]
+            Backend::Memory(entries) => Ok(live(&mut entries.lock().unwrap(), key)
+                .map(|(_, expires_at)| expires_at.saturating_duration_since(Instant::now()).as_secs())
+                .unwrap_or(0)),
+        }
+    }
+
+    /// 计数加一，返回 (计数, 窗口剩余秒数)；第一次计数时开始 `window_secs` 秒的窗口
+    ///
+    /// Redis 中 INCR 和 EXPIRE 在同一个 Lua 脚本里执行，不会留下没有过期时间的计数键
+    pub async fn incr_in_window(&self, key: &str, window_secs: u64) -> Result<(u64, u64)> {
+        match &self.backend {
+            Backend::Redis(manager) => {
+                let mut conn = manager.lock().await;
+
+                let (count, ttl): (u64, i64) = redis::Script::new(INCR_IN_WINDOW_SCRIPT)
+                    .key(key)
+                    .arg(window_secs)
+                    .invoke_async(&mut *conn)
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Redis INCR {} failed: {}", key, e))?;
+
+                Ok((count, ttl.max(0) as u64))
+            }
+            #[cfg(test)]
+            Backend::Memory(entries) => {
+                let mut entries = entries.lock().unwrap();
+                let count = live(&mut entries, key)
+                    .and_then(|(value, _)| value.parse::<u64>().ok())
+                    .unwrap_or(0) + 1;
+                let expires_at = match entries.get(key) {
+                    Some((_, expires_at)) if count > 1 => *expires_at,
+                    _ => expires_at(window_secs),
+                };
+                entries.insert(key.to_string(), (count.to_string(), expires_at));
+
+                Ok((count, expires_at.saturating_duration_since(Instant::now()).as_secs()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+fn expires_at(ttl_seconds: u64) -> Instant {
+    Instant::now() + Duration::from_secs(ttl_seconds)
+}
+
+/// 未过期的记录（已过期的记录顺便删除）
+#[cfg(test)]
+fn live<'a>(entries: &'a mut MemoryEntries, key: &str) -> Option<&'a (String, Instant)> {
+    if entries.get(key).is_some_and(|(_, expires_at)| *expires_at <= Instant::now()) {
+        entries.remove(key);
+    }
+    entries.get(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_set_nx_only_writes_missing_key() {
+        let store = KvStore::in_memory();
+
+        assert!(store.set_nx_ex("key", "first", 60).await.unwrap());
+        assert!(!store.set_nx_ex("key", "second", 60).await.unwrap());
+        assert_eq!(store.get("key").await.unwrap().as_deref(), Some("first"));
+    }
+
+    #[tokio::test]
+    async fn test_take_returns_value_once() {
+        let store = KvStore::in_memory();
+        store.set_ex("key", "value", 60).await.unwrap();
+
+        assert_eq!(store.take("key").await.unwrap().as_deref(), Some("value"));
+        assert_eq!(store.take("key").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_expired_key_is_gone() {
+        let store = KvStore::in_memory();
+        store.set_ex("key", "value", 0).await.unwrap();
+
+        assert!(!store.exists("key").await.unwrap());
+        assert_eq!(store.ttl("key").await.unwrap(), 0);
+        assert!(store.set_nx_ex("key", "again", 60).await.unwrap());
+    }
+}
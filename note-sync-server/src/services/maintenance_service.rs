@@ -0,0 +1,205 @@
+use anyhow::Result;
+use sqlx::{FromRow, MySqlPool};
+use chrono::Utc;
+use serde::Serialize;
+
+/// 待清理的版本快照（LEFT JOIN 所属笔记，笔记不存在时 note_found 为空）
+#[derive(Debug, FromRow)]
+struct VersionCandidate {
+    id: String,
+    note_found: Option<String>,
+}
+
+/// 版本快照清理结果
+#[derive(Debug, Default, Serialize, PartialEq, Eq)]
+pub struct PurgeNoteVersionsResult {
+    pub orphaned_removed: u64,  // 所属笔记已不存在的快照数量
+    pub expired_removed: u64,  // 所属笔记已删除超过保留期的快照数量
+    pub batches: u64,  // 执行的批次数
+}
+
+/// 数据维护服务
+pub struct MaintenanceService {
+    pool: MySqlPool,
+}
+
+impl MaintenanceService {
+    pub fn new(pool: MySqlPool) -> Self {
+        Self { pool }
+    }
+
+    /// 清理用户的无效版本快照
+    ///
+    /// 删除所属笔记已不存在、或所属笔记已删除超过 `retention_days` 天的 note_versions。
+    /// 按 `batch_size` 分批执行，每批一个事务，避免长时间锁表；
+    /// 指定 `workspace_id` 时只清理该工作空间的快照
+    pub async fn purge_note_versions(
+        &self,
+        user_id: &str,
+        workspace_id: Option<&str>,
+        retention_days: i64,
+        batch_size: i64,
+    ) -> Result<PurgeNoteVersionsResult> {
+        let cutoff = Utc::now().timestamp() - retention_days * 24 * 60 * 60;
+        let batch_size = batch_size.max(1);
+        let mut result = PurgeNoteVersionsResult::default();
+
+        loop {
+            let mut tx = self.pool.begin().await?;
+
+            let candidates = sqlx::query_as::<_, VersionCandidate>(
+                "SELECT v.id, n.id AS note_found
+                 FROM note_versions v
+                 LEFT JOIN notes n ON n.id = v.note_id
+                 WHERE v.user_id = ?
+                 AND (? IS NULL OR v.workspace_id = ?)
+                 AND (n.id IS NULL OR (n.is_deleted = TRUE AND n.deleted_at < ?))
+                 LIMIT ?
+                 FOR UPDATE"
+            )
+            .bind(user_id)
+            .bind(workspace_id)
+            .bind(workspace_id)
+            .bind(cutoff)
+            .bind(batch_size)
+            .fetch_all(&mut *tx)
+            .await?;
+
+            if candidates.is_empty() {
+                tx.rollback().await?;
+                break;
+            }
+
+            for candidate in &candidates {
+                match candidate.note_found {
+                    None => result.orphaned_removed += 1,
+                    Some(_) => result.expired_removed += 1,
+                }
+            }
+
+            let placeholders = vec!["?"; candidates.len()].join(", ");
+            let sql = format!(
+                "DELETE FROM note_versions WHERE user_id = ? AND id IN ({})",
+                placeholders
+            );
+            let mut query = sqlx::query(&sql).bind(user_id);
+            for candidate in &candidates {
+                query = query.bind(&candidate.id);
+            }
+            query.execute(&mut *tx).await?;
+
+            tx.commit().await?;
+            result.batches += 1;
+
+            if (candidates.len() as i64) < batch_size {
+                break;
+            }
+        }
+
+        tracing::info!(
+            "清理版本快照: user_id={}, workspace_id={:?}, orphaned={}, expired={}, batches={}",
+            user_id, workspace_id, result.orphaned_removed, result.expired_removed, result.batches
+        );
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, USER_ID};
+
+    const DAY: i64 = 24 * 60 * 60;
+
+    /// 写入笔记（`deleted_days_ago` 为空表示未删除）及其一个版本快照
+    async fn note_with_version(pool: &MySqlPool, id: &str, workspace_id: &str, deleted_days_ago: Option<i64>) {
+        let deleted_at = deleted_days_ago.map(|days| Utc::now().timestamp() - days * DAY);
+        sqlx::query(
+            "INSERT INTO notes (id, user_id, workspace_id, title, content, is_deleted, deleted_at, created_at, updated_at)
+             VALUES (?, ?, ?, '标题', '内容', ?, ?, 0, 0)"
+        )
+        .bind(id)
+        .bind(USER_ID)
+        .bind(workspace_id)
+        .bind(deleted_at.is_some())
+        .bind(deleted_at)
+        .execute(pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO note_versions (id, note_id, user_id, workspace_id, title, content, created_at)
+             VALUES (?, ?, ?, ?, '标题', '内容', 0)"
+        )
+        .bind(format!("{}-v", id))
+        .bind(id)
+        .bind(USER_ID)
+        .bind(workspace_id)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    /// 所属笔记已不存在的快照（外键约束之前遗留的数据）
+    async fn orphaned_version(pool: &MySqlPool, id: &str, workspace_id: &str) {
+        let mut conn = pool.acquire().await.unwrap();
+        sqlx::query("SET FOREIGN_KEY_CHECKS = 0").execute(&mut *conn).await.unwrap();
+        sqlx::query(
+            "INSERT INTO note_versions (id, note_id, user_id, workspace_id, title, content, created_at)
+             VALUES (?, 'missing-note', ?, ?, '标题', '内容', 0)"
+        )
+        .bind(id)
+        .bind(USER_ID)
+        .bind(workspace_id)
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+        sqlx::query("SET FOREIGN_KEY_CHECKS = 1").execute(&mut *conn).await.unwrap();
+    }
+
+    async fn remaining_versions(pool: &MySqlPool) -> Vec<String> {
+        sqlx::query_scalar("SELECT id FROM note_versions WHERE user_id = ? ORDER BY id")
+            .bind(USER_ID)
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    #[ignore = "需要 MySQL：设置 TEST_DATABASE_URL 后运行 cargo test -- --ignored"]
+    async fn test_purge_removes_orphaned_and_expired_versions_only() {
+        let pool = testing::mysql_pool().await;
+        note_with_version(&pool, "live", "ws-1", None).await;
+        note_with_version(&pool, "expired-1", "ws-1", Some(40)).await;
+        note_with_version(&pool, "expired-2", "ws-1", Some(31)).await;
+        note_with_version(&pool, "recent", "ws-1", Some(29)).await;
+        orphaned_version(&pool, "orphan-v", "ws-1").await;
+
+        // 每批一条，验证分批执行能清理完
+        let result = MaintenanceService::new(pool.clone())
+            .purge_note_versions(USER_ID, None, 30, 1)
+            .await
+            .unwrap();
+
+        assert_eq!(result.orphaned_removed, 1);
+        assert_eq!(result.expired_removed, 2);
+        assert_eq!(result.batches, 3);
+        assert_eq!(remaining_versions(&pool).await, ["live-v", "recent-v"]);
+    }
+
+    #[tokio::test]
+    #[ignore = "需要 MySQL：设置 TEST_DATABASE_URL 后运行 cargo test -- --ignored"]
+    async fn test_purge_only_touches_requested_workspace() {
+        let pool = testing::mysql_pool().await;
+        note_with_version(&pool, "expired-a", "ws-a", Some(40)).await;
+        note_with_version(&pool, "expired-b", "ws-b", Some(40)).await;
+
+        let result = MaintenanceService::new(pool.clone())
+            .purge_note_versions(USER_ID, Some("ws-a"), 30, 100)
+            .await
+            .unwrap();
+
+        assert_eq!(result, PurgeNoteVersionsResult { orphaned_removed: 0, expired_removed: 1, batches: 1 });
+        assert_eq!(remaining_versions(&pool).await, ["expired-b-v"]);
+    }
+}
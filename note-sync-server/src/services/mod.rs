@@ -1,8 +1,14 @@
 pub mod auth_service;
 pub mod token_service;
 pub mod token_blacklist;
+pub mod kv_store;
 pub mod device_service;
 pub mod device_identifier_service;
 pub mod sync_history_service;
 pub mod sync_lock_service;
 pub mod profile_service;
+pub mod rate_limiter;
+pub mod maintenance_service;
+pub mod email_verification_service;
+pub mod export_service;
+pub mod idempotency_store;
@@ -0,0 +1,62 @@
+use anyhow::Result;
+
+use crate::services::kv_store::KvStore;
+
+/// 固定窗口限流计数器
+///
+/// 与 TokenBlacklist 共用 Redis 连接，窗口到期后由 Redis 自动清除计数
+pub struct RateLimiter {
+    store: KvStore,
+}
+
+impl RateLimiter {
+    /// 使用已有的键值存储创建限流器
+    pub fn new(store: KvStore) -> Self {
+        Self { store }
+    }
+
+    /// 内存计数器（仅用于测试）
+    #[cfg(test)]
+    pub fn in_memory() -> Self {
+        Self::new(KvStore::in_memory())
+    }
+
+    /// 计数加一，返回窗口内的计数和窗口剩余秒数
+    /// key: "ratelimit:{key}"
+    pub async fn hit(&self, key: &str, window_secs: u64) -> Result<(u64, u64)> {
+        self.store
+            .incr_in_window(&format!("ratelimit:{}", key), window_secs)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to increment rate limit counter: {}", e))
+    }
+
+    /// 读取窗口内的计数和窗口剩余秒数（没有计数时为 (0, 0)）
+    pub async fn peek(&self, key: &str) -> Result<(u64, u64)> {
+        let key = format!("ratelimit:{}", key);
+
+        let count = self.store
+            .get(&key)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to read rate limit counter: {}", e))?
+            .and_then(|count| count.parse::<u64>().ok())
+            .unwrap_or(0);
+        if count == 0 {
+            return Ok((0, 0));
+        }
+
+        let ttl = self.store
+            .ttl(&key)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to read rate limit window: {}", e))?;
+
+        Ok((count, ttl))
+    }
+
+    /// 清除计数
+    pub async fn reset(&self, key: &str) -> Result<()> {
+        self.store
+            .del(&format!("ratelimit:{}", key))
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to reset rate limit counter: {}", e))
+    }
+}
@@ -2,7 +2,96 @@ use anyhow::Result;
 use sqlx::MySqlPool;
 use uuid::Uuid;
 use chrono::Utc;
-use crate::models::SyncHistoryEntry;
+use crate::models::{DeviceSyncSummary, SyncHistoryEntry, SyncHistoryPage};
+use serde::Deserialize;
+
+/// 同步结果过滤条件
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncOutcome {
+    /// 全部记录
+    #[default]
+    All,
+    /// 仅包含冲突的同步
+    Conflicts,
+    /// 仅失败的同步
+    Errors,
+}
+
+/// 每页默认记录数
+pub const DEFAULT_HISTORY_PAGE_SIZE: usize = 50;
+
+/// 每页最大记录数
+const MAX_HISTORY_PAGE_SIZE: usize = 100;
+
+/// 同步历史分页游标（上一页最后一条记录的 created_at 和 id）
+///
+/// 记录按 (created_at, id) 倒序排列，下一页只返回排在游标之后的记录
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryCursor {
+    pub created_at: i64,
+    pub id: String,
+}
+
+impl HistoryCursor {
+    /// 解析 `<created_at>:<id>` 格式的游标
+    pub fn parse(value: &str) -> Option<Self> {
+        let (created_at, id) = value.split_once(':')?;
+        if id.is_empty() {
+            return None;
+        }
+        Some(Self {
+            created_at: created_at.parse().ok()?,
+            id: id.to_string(),
+        })
+    }
+
+    fn of(entry: &SyncHistoryEntry) -> Self {
+        Self {
+            created_at: entry.created_at,
+            id: entry.id.clone(),
+        }
+    }
+}
+
+impl std::fmt::Display for HistoryCursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.created_at, self.id)
+    }
+}
+
+/// 同步历史查询条件
+#[derive(Debug, Clone)]
+pub struct SyncHistoryFilter {
+    pub outcome: SyncOutcome,
+    pub sync_type: Option<String>,  // 同步类型（sync / push / pull / full）
+    pub from: Option<i64>,  // 起始时间（包含）
+    pub to: Option<i64>,  // 结束时间（包含）
+    pub before: Option<HistoryCursor>,  // 分页游标（只返回该记录之前的记录）
+    pub limit: usize,
+}
+
+impl SyncHistoryFilter {
+    /// 时间范围是否有效（起始时间不晚于结束时间）
+    pub fn has_valid_range(&self) -> bool {
+        match (self.from, self.to) {
+            (Some(from), Some(to)) => from <= to,
+            _ => true,
+        }
+    }
+}
+
+/// 待写入的同步历史记录
+#[derive(Debug, Clone, Default)]
+pub struct NewSyncHistory<'a> {
+    pub device_id: Option<&'a str>,
+    pub sync_type: &'a str,  // 同步类型（sync / push / pull / full）
+    pub pushed_count: i32,
+    pub pulled_count: i32,
+    pub conflict_count: i32,
+    pub error: Option<String>,
+    pub duration_ms: i64,
+}
 
 /// 同步历史服务
 pub struct SyncHistoryService {
@@ -19,25 +108,18 @@ impl SyncHistoryService {
     /// 自动清理策略：
     /// - 保留最近 1000 条记录
     /// - 或保留最近 90 天的记录
-    pub async fn create(
-        &self,
-        user_id: &str,
-        sync_type: &str,
-        pushed_count: i32,
-        pulled_count: i32,
-        conflict_count: i32,
-        error: Option<String>,
-        duration_ms: i64,
-    ) -> Result<SyncHistoryEntry> {
+    pub async fn create(&self, user_id: &str, history: NewSyncHistory<'_>) -> Result<SyncHistoryEntry> {
+        let NewSyncHistory { device_id, sync_type, pushed_count, pulled_count, conflict_count, error, duration_ms } = history;
         let id = Uuid::new_v4().to_string();
         let now = Utc::now().timestamp();
 
         sqlx::query(
-            "INSERT INTO sync_history (id, user_id, sync_type, pushed_count, pulled_count, conflict_count, error, duration_ms, created_at)
-             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"
+            "INSERT INTO sync_history (id, user_id, device_id, sync_type, pushed_count, pulled_count, conflict_count, error, duration_ms, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
         )
         .bind(&id)
         .bind(user_id)
+        .bind(device_id)
         .bind(sync_type)
         .bind(pushed_count)
         .bind(pulled_count)
@@ -60,6 +142,7 @@ impl SyncHistoryService {
         Ok(SyncHistoryEntry {
             id,
             user_id: user_id.to_string(),
+            device_id: device_id.map(str::to_string),
             sync_type: sync_type.to_string(),
             pushed_count,
             pulled_count,
@@ -137,22 +220,77 @@ impl SyncHistoryService {
         Ok(())
     }
 
-    /// 获取用户的同步历史记录
-    pub async fn list(&self, user_id: &str, limit: usize) -> Result<Vec<SyncHistoryEntry>> {
-        let limit = limit.min(100); // 最多 100 条
+    /// 获取用户的同步历史记录（按时间倒序分页）
+    ///
+    /// 支持按结果（仅冲突/仅错误）、同步类型和时间范围过滤；
+    /// 使用 (created_at, id) 游标分页，翻页期间新增的记录不会导致重复或遗漏
+    pub async fn list(&self, user_id: &str, filter: &SyncHistoryFilter) -> Result<SyncHistoryPage> {
+        let limit = filter.limit.clamp(1, MAX_HISTORY_PAGE_SIZE);
+
+        let mut sql = String::from("SELECT * FROM sync_history WHERE user_id = ?");
+        match filter.outcome {
+            SyncOutcome::All => {}
+            SyncOutcome::Conflicts => sql.push_str(" AND conflict_count > 0"),
+            SyncOutcome::Errors => sql.push_str(" AND error IS NOT NULL"),
+        }
+        if filter.sync_type.is_some() {
+            sql.push_str(" AND sync_type = ?");
+        }
+        if filter.from.is_some() {
+            sql.push_str(" AND created_at >= ?");
+        }
+        if filter.to.is_some() {
+            sql.push_str(" AND created_at <= ?");
+        }
+        if filter.before.is_some() {
+            sql.push_str(" AND (created_at < ? OR (created_at = ? AND id < ?))");
+        }
+        sql.push_str(" ORDER BY created_at DESC, id DESC LIMIT ?");
+
+        let mut query = sqlx::query_as::<_, SyncHistoryEntry>(&sql).bind(user_id);
+        if let Some(sync_type) = &filter.sync_type {
+            query = query.bind(sync_type);
+        }
+        if let Some(from) = filter.from {
+            query = query.bind(from);
+        }
+        if let Some(to) = filter.to {
+            query = query.bind(to);
+        }
+        if let Some(before) = &filter.before {
+            query = query.bind(before.created_at).bind(before.created_at).bind(&before.id);
+        }
+
+        // 多取一条用于判断是否还有下一页
+        let history = query
+            .bind((limit + 1) as i64)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(into_page(history, limit))
+    }
 
-        let history = sqlx::query_as::<_, SyncHistoryEntry>(
-            "SELECT * FROM sync_history
+    /// 按设备汇总用户的同步历史（推送/拉取总数）
+    ///
+    /// 在数据库中按 device_id 分组统计，按最近同步时间倒序排列
+    pub async fn summarize_by_device(&self, user_id: &str) -> Result<Vec<DeviceSyncSummary>> {
+        let summaries = sqlx::query_as::<_, DeviceSyncSummary>(
+            "SELECT device_id,
+                    COUNT(*) AS sync_count,
+                    CAST(COALESCE(SUM(pushed_count), 0) AS SIGNED) AS pushed_total,
+                    CAST(COALESCE(SUM(pulled_count), 0) AS SIGNED) AS pulled_total,
+                    CAST(COALESCE(SUM(conflict_count), 0) AS SIGNED) AS conflict_total,
+                    MAX(created_at) AS last_sync_at
+             FROM sync_history
              WHERE user_id = ?
-             ORDER BY created_at DESC
-             LIMIT ?"
+             GROUP BY device_id
+             ORDER BY last_sync_at DESC"
         )
         .bind(user_id)
-        .bind(limit as i64)
         .fetch_all(&self.pool)
         .await?;
 
-        Ok(history)
+        Ok(summaries)
     }
 
     /// 清空用户的同步历史
@@ -176,3 +314,139 @@ impl SyncHistoryService {
         Ok(result.rows_affected())
     }
 }
+
+/// 将多取一条的查询结果转换为分页结果
+fn into_page(mut history: Vec<SyncHistoryEntry>, limit: usize) -> SyncHistoryPage {
+    let next_cursor = if history.len() > limit {
+        history.truncate(limit);
+        history.last().map(|entry| HistoryCursor::of(entry).to_string())
+    } else {
+        None
+    };
+
+    SyncHistoryPage { entries: history, next_cursor }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, USER_ID};
+
+    fn filter(from: Option<i64>, to: Option<i64>, before: Option<HistoryCursor>, limit: usize) -> SyncHistoryFilter {
+        SyncHistoryFilter {
+            outcome: SyncOutcome::All,
+            sync_type: None,
+            from,
+            to,
+            before,
+            limit,
+        }
+    }
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor = HistoryCursor { created_at: 1_700_000_000, id: Uuid::new_v4().to_string() };
+
+        assert_eq!(HistoryCursor::parse(&cursor.to_string()), Some(cursor));
+        assert_eq!(HistoryCursor::parse("not-a-cursor"), None);
+        assert_eq!(HistoryCursor::parse("abc:id"), None);
+        assert_eq!(HistoryCursor::parse("100:"), None);
+    }
+
+    async fn record(service: &SyncHistoryService, device_id: Option<&str>, pushed: i32, pulled: i32, created_at: i64) {
+        let history = NewSyncHistory {
+            device_id,
+            sync_type: "sync",
+            pushed_count: pushed,
+            pulled_count: pulled,
+            ..Default::default()
+        };
+        let entry = service.create(USER_ID, history).await.unwrap();
+        sqlx::query("UPDATE sync_history SET created_at = ? WHERE id = ?")
+            .bind(created_at)
+            .bind(&entry.id)
+            .execute(&service.pool)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "需要 MySQL：设置 TEST_DATABASE_URL 后运行 cargo test -- --ignored"]
+    async fn test_pages_follow_each_other_without_gaps() {
+        let service = SyncHistoryService::new(testing::mysql_pool().await);
+        let now = Utc::now().timestamp();
+        // 其中两条记录在同一秒
+        for created_at in [now - 300, now - 200, now - 200, now - 100] {
+            record(&service, Some("device-a"), 1, 0, created_at).await;
+        }
+        let all = service.list(USER_ID, &filter(None, None, None, 10)).await.unwrap();
+        assert_eq!(all.entries.len(), 4);
+        assert_eq!(all.next_cursor, None);
+
+        let mut seen = Vec::new();
+        let mut before: Option<HistoryCursor> = None;
+        loop {
+            let page = service.list(USER_ID, &filter(None, None, before, 2)).await.unwrap();
+            assert!(page.entries.len() <= 2);
+            seen.extend(page.entries.into_iter().map(|e| e.id));
+            match page.next_cursor {
+                Some(cursor) => before = HistoryCursor::parse(&cursor),
+                None => break,
+            }
+        }
+
+        let expected: Vec<String> = all.entries.into_iter().map(|e| e.id).collect();
+        assert_eq!(seen, expected);
+    }
+
+    #[tokio::test]
+    #[ignore = "需要 MySQL：设置 TEST_DATABASE_URL 后运行 cargo test -- --ignored"]
+    async fn test_list_filters_by_inclusive_date_range() {
+        let service = SyncHistoryService::new(testing::mysql_pool().await);
+        let now = Utc::now().timestamp();
+        for created_at in [now - 300, now - 200, now - 100] {
+            record(&service, Some("device-a"), 1, 0, created_at).await;
+        }
+        let created = |page: SyncHistoryPage| page.entries.into_iter().map(|e| e.created_at).collect::<Vec<_>>();
+
+        let page = service.list(USER_ID, &filter(Some(now - 300), Some(now - 200), None, 10)).await.unwrap();
+        assert_eq!(created(page), [now - 200, now - 300]);
+
+        let page = service.list(USER_ID, &filter(Some(now - 150), None, None, 10)).await.unwrap();
+        assert_eq!(created(page), [now - 100]);
+
+        let page = service.list(USER_ID, &filter(Some(now - 200), Some(now - 200), None, 10)).await.unwrap();
+        assert_eq!(created(page), [now - 200]);
+    }
+
+    #[tokio::test]
+    #[ignore = "需要 MySQL：设置 TEST_DATABASE_URL 后运行 cargo test -- --ignored"]
+    async fn test_summary_groups_totals_by_device() {
+        let service = SyncHistoryService::new(testing::mysql_pool().await);
+        let now = Utc::now().timestamp();
+        record(&service, Some("device-a"), 3, 1, now - 300).await;
+        record(&service, Some("device-b"), 0, 4, now - 200).await;
+        record(&service, Some("device-a"), 2, 0, now - 100).await;
+        record(&service, None, 1, 1, now - 400).await;
+
+        let summaries = service.summarize_by_device(USER_ID).await.unwrap();
+
+        assert_eq!(summaries.len(), 3);
+        assert_eq!(
+            summaries[0],
+            DeviceSyncSummary {
+                device_id: Some("device-a".to_string()),
+                sync_count: 2,
+                pushed_total: 5,
+                pulled_total: 1,
+                conflict_total: 0,
+                last_sync_at: now - 100,
+            }
+        );
+        assert_eq!(summaries[1].device_id.as_deref(), Some("device-b"));
+        assert_eq!(summaries[1].pulled_total, 4);
+        // 未记录设备的旧记录单独成组
+        assert_eq!(summaries[2].device_id, None);
+        assert_eq!(summaries[2].sync_count, 1);
+    }
+}
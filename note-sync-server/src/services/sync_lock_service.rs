@@ -2,9 +2,46 @@ use anyhow::Result;
 use sqlx::MySqlPool;
 use uuid::Uuid;
 use chrono::Utc;
-use std::sync::Arc;
+use std::fmt;
 use crate::models::SyncLock;
 
+/// 同步锁仍被持有（其他设备，或同一设备的其他工作空间）
+#[derive(Debug)]
+pub struct SyncLockHeld {
+    pub reason: &'static str,
+    /// 预计锁释放或可被回收前的等待时间（秒）
+    pub retry_after_secs: i64,
+}
+
+impl fmt::Display for SyncLockHeld {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}，约 {} 秒后可重试", self.reason, self.retry_after_secs)
+    }
+}
+
+impl std::error::Error for SyncLockHeld {}
+
+/// 已有锁的状态
+#[derive(Debug, PartialEq, Eq)]
+enum LockState {
+    /// 锁仍有效，需等待 retry_after_secs 秒
+    Active { retry_after_secs: i64 },
+    /// 获取时间已超过 TTL（持有方可能已崩溃），可以回收
+    Stale,
+}
+
+/// 判断已有锁是否仍有效
+///
+/// 锁在 `expires_at` 或 `acquired_at + ttl_secs` 中较早的时刻失效
+fn lock_state(lock: &SyncLock, now: i64, ttl_secs: i64) -> LockState {
+    let stale_at = (lock.acquired_at + ttl_secs).min(lock.expires_at);
+    if stale_at <= now {
+        LockState::Stale
+    } else {
+        LockState::Active { retry_after_secs: stale_at - now }
+    }
+}
+
 /// 同步锁服务
 /// 用于获取和释放同步操作锁，防止并发冲突
 #[derive(Clone)]
@@ -68,18 +105,19 @@ impl SyncLockService {
 
     /// 获取同步操作锁（包含工作空间支持）
     ///
-    /// 如果锁已被其他设备持有且未过期，返回 Err
-    /// 如果同一用户的其他工作空间正在同步，也返回 Err
+    /// 如果锁已被其他设备持有且未过期，返回 `SyncLockHeld` 错误
+    /// 如果同一用户的其他工作空间正在同步，也返回 `SyncLockHeld` 错误
+    /// 获取时间早于 `ttl_secs` 的遗留锁会被回收
     /// 成功获取锁后，返回锁 ID
     pub async fn acquire_lock(
         &self,
         user_id: &str,
         device_id: &str,
         workspace_id: Option<&str>,
-        lock_duration_seconds: i64,
+        ttl_secs: i64,
     ) -> Result<String> {
         let now = Utc::now().timestamp();
-        let expires_at = now + lock_duration_seconds;
+        let expires_at = now + ttl_secs;
         let lock_id = Uuid::new_v4().to_string();
 
         // 首先清理过期的锁
@@ -104,73 +142,101 @@ impl SyncLockService {
         .await?;
 
         if let Some(lock) = existing_lock {
-            // 如果锁的工作空间不同，拒绝获取锁
-            if workspace_id.is_some() && lock.workspace_id != workspace_id.map(|s| s.to_string()) {
-                tracing::info!("拒绝获取同步锁: user_id={}, 该用户的其他工作空间正在同步 (existing_ws={:?}, requested_ws={:?})",
-                    user_id, lock.workspace_id, workspace_id);
-                return Err(anyhow::anyhow!("该用户的其他工作空间正在同步"));
-            }
-
-            // 同一工作空间（或都是 None），延长锁的时间
-            sqlx::query(
-                "UPDATE sync_locks SET expires_at = ? WHERE id = ?"
-            )
-            .bind(expires_at)
-            .bind(&lock.id)
-            .execute(&self.pool)
-            .await?;
-
-            tracing::info!("延长同步锁: lock_id={}, user_id={}, device_id={}, workspace_id={:?}",
-                lock.id, user_id, device_id, workspace_id);
-            Ok(lock.id)
-        } else {
-            // 检查是否有其他设备持有同一用户+工作空间的锁
-            let other_device_lock: Option<SyncLock> = if let Some(ws_id) = workspace_id {
-                sqlx::query_as::<_, SyncLock>(
-                    "SELECT * FROM sync_locks
-                     WHERE user_id = ? AND device_id != ? AND workspace_id = ? AND expires_at > ?"
+            if workspace_id.is_none() || lock.workspace_id.as_deref() == workspace_id {
+                // 同一工作空间（或都是 None），续期锁
+                sqlx::query(
+                    "UPDATE sync_locks SET acquired_at = ?, expires_at = ? WHERE id = ?"
                 )
-                .bind(user_id)
-                .bind(device_id)
-                .bind(ws_id)
                 .bind(now)
-                .fetch_optional(&self.pool)
-                .await?
-            } else {
-                // 如果没有指定 workspace_id，检查是否有任何其他设备的锁
-                sqlx::query_as::<_, SyncLock>(
-                    "SELECT * FROM sync_locks
-                     WHERE user_id = ? AND device_id != ? AND workspace_id IS NULL AND expires_at > ?"
-                )
-                .bind(user_id)
-                .bind(device_id)
-                .bind(now)
-                .fetch_optional(&self.pool)
-                .await?
-            };
+                .bind(expires_at)
+                .bind(&lock.id)
+                .execute(&self.pool)
+                .await?;
 
-            if other_device_lock.is_some() {
-                tracing::info!("拒绝获取同步锁: user_id={}, 同一工作空间的锁已被其他设备持有", user_id);
-                return Err(anyhow::anyhow!("同步锁已被其他设备持有"));
+                tracing::info!("延长同步锁: lock_id={}, user_id={}, device_id={}, workspace_id={:?}",
+                    lock.id, user_id, device_id, workspace_id);
+                return Ok(lock.id);
             }
 
-            // 创建新锁
-            sqlx::query(
-                "INSERT INTO sync_locks (id, user_id, device_id, workspace_id, acquired_at, expires_at)
-                 VALUES (?, ?, ?, ?, ?, ?)"
+            // 锁的工作空间不同，除非锁已遗留，否则拒绝获取锁
+            self.reclaim_or_reject(&lock, now, ttl_secs, "该用户的其他工作空间正在同步").await?;
+        }
+
+        // 检查是否有其他设备持有同一用户+工作空间的锁
+        let other_device_lock: Option<SyncLock> = if let Some(ws_id) = workspace_id {
+            sqlx::query_as::<_, SyncLock>(
+                "SELECT * FROM sync_locks
+                 WHERE user_id = ? AND device_id != ? AND workspace_id = ? AND expires_at > ?
+                 ORDER BY acquired_at DESC
+                 LIMIT 1"
             )
-            .bind(&lock_id)
             .bind(user_id)
             .bind(device_id)
-            .bind(workspace_id)
+            .bind(ws_id)
             .bind(now)
-            .bind(expires_at)
-            .execute(&self.pool)
-            .await?;
+            .fetch_optional(&self.pool)
+            .await?
+        } else {
+            // 如果没有指定 workspace_id，检查是否有任何其他设备的锁
+            sqlx::query_as::<_, SyncLock>(
+                "SELECT * FROM sync_locks
+                 WHERE user_id = ? AND device_id != ? AND workspace_id IS NULL AND expires_at > ?
+                 ORDER BY acquired_at DESC
+                 LIMIT 1"
+            )
+            .bind(user_id)
+            .bind(device_id)
+            .bind(now)
+            .fetch_optional(&self.pool)
+            .await?
+        };
+
+        if let Some(lock) = other_device_lock {
+            self.reclaim_or_reject(&lock, now, ttl_secs, "同步锁已被其他设备持有").await?;
+        }
 
-            tracing::info!("获取同步锁: lock_id={}, user_id={}, device_id={}, workspace_id={:?}",
-                lock_id, user_id, device_id, workspace_id);
-            Ok(lock_id)
+        // 创建新锁
+        sqlx::query(
+            "INSERT INTO sync_locks (id, user_id, device_id, workspace_id, acquired_at, expires_at)
+             VALUES (?, ?, ?, ?, ?, ?)"
+        )
+        .bind(&lock_id)
+        .bind(user_id)
+        .bind(device_id)
+        .bind(workspace_id)
+        .bind(now)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await?;
+
+        tracing::info!("获取同步锁: lock_id={}, user_id={}, device_id={}, workspace_id={:?}",
+            lock_id, user_id, device_id, workspace_id);
+        Ok(lock_id)
+    }
+
+    /// 回收遗留锁；锁仍有效时返回 `SyncLockHeld`
+    async fn reclaim_or_reject(
+        &self,
+        lock: &SyncLock,
+        now: i64,
+        ttl_secs: i64,
+        reason: &'static str,
+    ) -> Result<()> {
+        match lock_state(lock, now, ttl_secs) {
+            LockState::Stale => {
+                tracing::warn!("回收遗留同步锁: lock_id={}, user_id={}, device_id={}, workspace_id={:?}, held_for={}s",
+                    lock.id, lock.user_id, lock.device_id, lock.workspace_id, now - lock.acquired_at);
+                sqlx::query("DELETE FROM sync_locks WHERE id = ?")
+                    .bind(&lock.id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+            LockState::Active { retry_after_secs } => {
+                tracing::info!("拒绝获取同步锁: user_id={}, {} (lock_id={}, retry_after={}s)",
+                    lock.user_id, reason, lock.id, retry_after_secs);
+                Err(SyncLockHeld { reason, retry_after_secs }.into())
+            }
         }
     }
 
@@ -208,9 +274,45 @@ impl SyncLockService {
         user_id: &str,
         device_id: &str,
         workspace_id: Option<&str>,
-        lock_duration_seconds: i64,
+        ttl_secs: i64,
     ) -> Result<SyncLockGuard> {
-        let lock_id = self.acquire_lock(user_id, device_id, workspace_id, lock_duration_seconds).await?;
+        let lock_id = self.acquire_lock(user_id, device_id, workspace_id, ttl_secs).await?;
         Ok(SyncLockGuard::new(lock_id, user_id.to_string(), self.clone()))
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lock(acquired_at: i64, expires_at: i64) -> SyncLock {
+        SyncLock {
+            id: "lock-1".to_string(),
+            user_id: "user-1".to_string(),
+            device_id: "device-a".to_string(),
+            workspace_id: None,
+            acquired_at,
+            expires_at,
+        }
+    }
+
+    #[test]
+    fn test_abandoned_lock_is_reclaimed_after_ttl() {
+        // 持有方崩溃后锁一直未释放，且 expires_at 远在未来
+        let abandoned = lock(1_000, 10_000);
+
+        assert_eq!(lock_state(&abandoned, 1_029, 30), LockState::Active { retry_after_secs: 1 });
+        assert_eq!(lock_state(&abandoned, 1_030, 30), LockState::Stale);
+        assert_eq!(lock_state(&abandoned, 5_000, 30), LockState::Stale);
+    }
+
+    #[test]
+    fn test_fresh_lock_blocks_with_retry_after() {
+        let fresh = lock(1_000, 1_030);
+        assert_eq!(lock_state(&fresh, 1_005, 30), LockState::Active { retry_after_secs: 25 });
+
+        // 锁有效期短于 TTL 时以 expires_at 为准
+        let short = lock(1_000, 1_010);
+        assert_eq!(lock_state(&short, 1_005, 30), LockState::Active { retry_after_secs: 5 });
+    }
+}
@@ -1,34 +1,46 @@
-use redis::{AsyncCommands, Client};
-use redis::aio::ConnectionManager;
 use anyhow::Result;
-use std::sync::Arc;
+
+use crate::services::kv_store::KvStore;
 
 /// Redis Token 黑名单服务
-/// 用于实现登出功能，将已登出的 token 加入黑名单
+/// 用于实现登出功能，将已登出的 token 加入黑名单；也用于记录已轮换的刷新令牌
 pub struct TokenBlacklist {
-    manager: Arc<tokio::sync::Mutex<ConnectionManager>>,
+    store: KvStore,
 }
 
 impl TokenBlacklist {
     /// 创建新的 Token 黑名单服务实例
     pub async fn new(redis_url: &str) -> Result<Self> {
-        let client = Client::open(redis_url)?;
-        let manager = ConnectionManager::new(client).await?;
         Ok(Self {
-            manager: Arc::new(tokio::sync::Mutex::new(manager)),
+            store: KvStore::connect(redis_url).await?,
         })
     }
 
+    /// 内存黑名单（仅用于测试）
+    #[cfg(test)]
+    pub fn in_memory() -> Self {
+        Self {
+            store: KvStore::in_memory(),
+        }
+    }
+
+    /// 共享的键值存储（供限流等其他服务复用 Redis 连接）
+    pub fn store(&self) -> KvStore {
+        self.store.clone()
+    }
+
+    /// 检查 Redis 连接是否可用（PING）
+    pub async fn ping(&self) -> Result<()> {
+        self.store.ping().await
+    }
+
     /// 将 token 加入黑名单
     /// key: "blacklist:{token}"
     /// value: "1"
     /// ttl: 与 token 过期时间一致（秒）
     pub async fn add(&self, token: &str, ttl_seconds: u64) -> Result<()> {
-        let mut conn = self.manager.lock().await;
-        let key = format!("blacklist:{}", token);
-
-        // 设置 key 并带上过期时间
-        conn.set_ex::<_, _, ()>(key, "1", ttl_seconds)
+        self.store
+            .set_ex(&format!("blacklist:{}", token), "1", ttl_seconds)
             .await
             .map_err(|e| anyhow::anyhow!("Failed to add token to blacklist: {}", e))?;
 
@@ -36,17 +48,53 @@ impl TokenBlacklist {
         Ok(())
     }
 
+    /// token 不在黑名单中时加入黑名单（SET NX），返回是否由本次调用加入
+    pub async fn add_if_absent(&self, token: &str, ttl_seconds: u64) -> Result<bool> {
+        self.store
+            .set_nx_ex(&format!("blacklist:{}", token), "1", ttl_seconds)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to add token to blacklist: {}", e))
+    }
+
     /// 检查 token 是否在黑名单中
     pub async fn contains(&self, token: &str) -> Result<bool> {
-        let mut conn = self.manager.lock().await;
-        let key = format!("blacklist:{}", token);
+        self.store
+            .exists(&format!("blacklist:{}", token))
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to check blacklist: {}", e))
+    }
+
+    /// 吊销用户在 `issued_before`（含）之前签发的所有 token，`keep_session` 对应的会话除外
+    /// key: "revoked_sessions:{user_id}"
+    /// value: "{issued_before}:{keep_session}"
+    /// ttl: 与 access token 有效期一致（秒），之后被吊销的 token 均已过期
+    pub async fn revoke_user_sessions(
+        &self,
+        user_id: &str,
+        issued_before: i64,
+        keep_session: Option<&str>,
+        ttl_seconds: u64,
+    ) -> Result<()> {
+        let key = format!("revoked_sessions:{}", user_id);
+        let value = format!("{}:{}", issued_before, keep_session.unwrap_or_default());
+
+        self.store
+            .set_ex(&key, &value, ttl_seconds)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to revoke user sessions: {}", e))?;
+
+        tracing::debug!("Sessions issued before {} revoked for user {}", issued_before, user_id);
+        Ok(())
+    }
 
-        let exists: bool = conn
-            .exists::<_, bool>(key)
+    /// 检查 token 是否已被 [`revoke_user_sessions`](Self::revoke_user_sessions) 吊销
+    pub async fn is_session_revoked(&self, user_id: &str, issued_at: i64, session_id: &str) -> Result<bool> {
+        let entry = self.store
+            .get(&format!("revoked_sessions:{}", user_id))
             .await
-            .map_err(|e| anyhow::anyhow!("Failed to check blacklist: {}", e))?;
+            .map_err(|e| anyhow::anyhow!("Failed to check revoked sessions: {}", e))?;
 
-        Ok(exists)
+        Ok(entry.is_some_and(|entry| session_revoked(&entry, issued_at, session_id)))
     }
 
     /// 清理过期的 token（Redis 会自动处理，这里仅作为手动清理接口）
@@ -58,10 +106,8 @@ impl TokenBlacklist {
 
     /// 从黑名单中移除 token（用于测试或特殊情况）
     pub async fn remove(&self, token: &str) -> Result<()> {
-        let mut conn = self.manager.lock().await;
-        let key = format!("blacklist:{}", token);
-
-        conn.del::<_, ()>(key)
+        self.store
+            .del(&format!("blacklist:{}", token))
             .await
             .map_err(|e| anyhow::anyhow!("Failed to remove token from blacklist: {}", e))?;
 
@@ -70,10 +116,29 @@ impl TokenBlacklist {
     }
 }
 
+/// 按吊销记录（"{issued_before}:{keep_session}"）判断会话是否已被吊销
+fn session_revoked(entry: &str, issued_at: i64, session_id: &str) -> bool {
+    let (issued_before, keep_session) = entry.split_once(':').unwrap_or((entry, ""));
+    let Ok(issued_before) = issued_before.parse::<i64>() else {
+        return false;
+    };
+
+    issued_at <= issued_before && session_id != keep_session
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_session_revoked_keeps_current_session() {
+        assert!(session_revoked("1000:current", 1000, "other"));
+        assert!(session_revoked("1000:", 999, "current"));
+        assert!(!session_revoked("1000:current", 1000, "current"));
+        // 吊销之后签发的 token 不受影响
+        assert!(!session_revoked("1000:current", 1001, "other"));
+    }
+
     #[tokio::test]
     async fn test_blacklist_operations() {
         let blacklist = TokenBlacklist::new("redis://localhost:6379").await.unwrap();
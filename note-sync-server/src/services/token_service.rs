@@ -1,6 +1,6 @@
 use anyhow::Result;
 use chrono::{Utc, Duration};
-use jsonwebtoken::{encode, EncodingKey, Header};
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use sha2::{Sha256, Digest};
 use base64::{Engine as _, engine::general_purpose};
 use serde::{Serialize, Deserialize};
@@ -11,6 +11,7 @@ pub struct TokenService;
 impl TokenService {
     /// 生成 JWT access token
     pub fn generate_access_token(user_id: &str, expiration_days: i64, jwt_secret: &str) -> Result<String> {
+        let issued_at = Utc::now().timestamp() as usize;
         let expiration = Utc::now()
             .checked_add_signed(Duration::days(expiration_days))
             .expect("valid timestamp")
@@ -20,6 +21,9 @@ impl TokenService {
             sub: user_id.to_string(),
             exp: expiration,
             token_type: TokenType::Access,
+            iat: Some(issued_at),
+            jti: Some(uuid::Uuid::new_v4().to_string()),
+            family: None,
         };
 
         let token = encode(&Header::default(), &claims, &EncodingKey::from_secret(jwt_secret.as_ref()))?;
@@ -27,8 +31,15 @@ impl TokenService {
         Ok(token)
     }
 
-    /// 生成 refresh token
+    /// 生成 refresh token（开始新的令牌族）
     pub fn generate_refresh_token(user_id: &str, jwt_secret: &str) -> Result<String> {
+        let family = uuid::Uuid::new_v4().to_string();
+        Self::generate_refresh_token_in_family(user_id, &family, jwt_secret)
+    }
+
+    /// 在已有令牌族中生成 refresh token（轮换时使用）
+    pub fn generate_refresh_token_in_family(user_id: &str, family: &str, jwt_secret: &str) -> Result<String> {
+        let issued_at = Utc::now().timestamp() as usize;
         let expiration = Utc::now()
             .checked_add_signed(Duration::days(30)) // refresh token 有效期 30 天
             .expect("valid timestamp")
@@ -38,6 +49,9 @@ impl TokenService {
             sub: user_id.to_string(),
             exp: expiration,
             token_type: TokenType::Refresh,
+            iat: Some(issued_at),
+            jti: Some(uuid::Uuid::new_v4().to_string()),
+            family: Some(family.to_string()),
         };
 
         let token = encode(&Header::default(), &claims, &EncodingKey::from_secret(jwt_secret.as_ref()))?;
@@ -53,6 +67,39 @@ impl TokenService {
         Ok((access_token, refresh_token))
     }
 
+    /// 轮换 token：生成新的 access token 和同一令牌族中的 refresh token
+    pub fn rotate_token_pair(user_id: &str, family: &str, expiration_days: i64, jwt_secret: &str) -> Result<(String, String)> {
+        let access_token = Self::generate_access_token(user_id, expiration_days, jwt_secret)?;
+        let refresh_token = Self::generate_refresh_token_in_family(user_id, family, jwt_secret)?;
+
+        Ok((access_token, refresh_token))
+    }
+
+    /// 验证并解析 refresh token
+    ///
+    /// 旧版本签发的 refresh token 没有 jti / family，此时使用 token 哈希作为两者的值
+    pub fn decode_refresh_token(token: &str, jwt_secret: &str) -> Result<RefreshTokenClaims> {
+        let claims = decode::<Claims>(
+            token,
+            &DecodingKey::from_secret(jwt_secret.as_ref()),
+            &Validation::default(),
+        )
+        .map_err(|_| anyhow::anyhow!("无效的刷新令牌"))?
+        .claims;
+
+        if !matches!(claims.token_type, TokenType::Refresh) {
+            return Err(anyhow::anyhow!("无效的刷新令牌"));
+        }
+
+        let legacy_id = || Self::hash_token(token);
+        Ok(RefreshTokenClaims {
+            jti: claims.jti.unwrap_or_else(legacy_id),
+            family: claims.family.unwrap_or_else(legacy_id),
+            user_id: claims.sub,
+            exp: claims.exp,
+        })
+    }
+
     /// 从 token 中提取 user_id
     pub fn extract_user_id(token: &str) -> Result<String> {
         let parts: Vec<&str> = token.split('.').collect();
@@ -68,6 +115,11 @@ impl TokenService {
         Ok(claims.sub)
     }
 
+    /// token 对应的会话标识（旧版本签发的 token 没有 jti，使用 token 哈希）
+    pub fn session_id(token: &str, jti: Option<&str>) -> String {
+        jti.map(str::to_string).unwrap_or_else(|| Self::hash_token(token))
+    }
+
     /// 生成 token 哈希（用于存储到数据库或黑名单）
     pub fn hash_token(token: &str) -> String {
         let mut hasher = Sha256::new();
@@ -88,4 +140,22 @@ struct Claims {
     sub: String,    // user_id
     exp: usize,     // 过期时间
     token_type: TokenType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    iat: Option<usize>,      // 签发时间
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    jti: Option<String>,     // 令牌 ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    family: Option<String>,  // 令牌族 ID（仅 refresh token）
+}
+
+/// refresh token 中与轮换相关的声明
+#[derive(Debug, Clone)]
+pub struct RefreshTokenClaims {
+    pub user_id: String,
+    /// 令牌 ID（每次轮换都不同）
+    pub jti: String,
+    /// 令牌族 ID（登录 / 注册时生成，轮换时保持不变）
+    pub family: String,
+    /// 过期时间戳（秒）
+    pub exp: usize,
 }
@@ -0,0 +1,160 @@
+use axum::Router;
+use std::future::{Future, IntoFuture};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+/// 等待停止信号（Ctrl+C / SIGINT，Unix 下还包括 SIGTERM）
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("监听 Ctrl+C 信号失败: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("监听 SIGTERM 信号失败: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => tracing::info!("收到 SIGINT"),
+        _ = terminate => tracing::info!("收到 SIGTERM"),
+    }
+}
+
+/// 启动服务器，收到 `signal` 后优雅停止
+///
+/// 停止时不再接受新连接，并等待进行中的请求（如同步事务）完成；
+/// 超过 `drain_timeout` 仍未完成的请求会被中断
+pub async fn serve_with_graceful_shutdown<S>(
+    listener: TcpListener,
+    app: Router,
+    signal: S,
+    drain_timeout: Duration,
+) -> anyhow::Result<()>
+where
+    S: Future<Output = ()>,
+{
+    let (stop_tx, stop_rx) = tokio::sync::oneshot::channel::<()>();
+
+    // 提供客户端地址，供限流中间件按 IP 计数
+    let server = axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(async {
+        let _ = stop_rx.await;
+    })
+    .into_future();
+    let mut server = std::pin::pin!(server);
+
+    tokio::select! {
+        result = &mut server => return Ok(result?),
+        _ = signal => {}
+    }
+
+    tracing::info!(
+        "停止接受新连接，等待进行中的请求完成（最长 {} 秒）",
+        drain_timeout.as_secs()
+    );
+    let _ = stop_tx.send(());
+
+    match tokio::time::timeout(drain_timeout, server).await {
+        Ok(result) => result?,
+        Err(_) => tracing::warn!("等待进行中的请求超时，强制停止"),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::get;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    async fn get_slow(addr: SocketAddr) -> String {
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /slow HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    fn slow_app(delay: Duration) -> Router {
+        Router::new().route(
+            "/slow",
+            get(move || async move {
+                tokio::time::sleep(delay).await;
+                "done"
+            }),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_in_flight_request_completes_during_shutdown() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (signal_tx, signal_rx) = tokio::sync::oneshot::channel::<()>();
+
+        let server = tokio::spawn(serve_with_graceful_shutdown(
+            listener,
+            slow_app(Duration::from_millis(300)),
+            async {
+                let _ = signal_rx.await;
+            },
+            Duration::from_secs(5),
+        ));
+
+        let request = tokio::spawn(get_slow(addr));
+        // 请求进行中时发出停止信号
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        signal_tx.send(()).unwrap();
+
+        let response = request.await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+        assert!(response.ends_with("done"));
+
+        server.await.unwrap().unwrap();
+        assert!(tokio::net::TcpStream::connect(addr).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_gives_up_after_drain_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (signal_tx, signal_rx) = tokio::sync::oneshot::channel::<()>();
+
+        let server = tokio::spawn(serve_with_graceful_shutdown(
+            listener,
+            slow_app(Duration::from_secs(60)),
+            async {
+                let _ = signal_rx.await;
+            },
+            Duration::from_millis(100),
+        ));
+
+        let _request = tokio::spawn(get_slow(addr));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        signal_tx.send(()).unwrap();
+
+        let result = tokio::time::timeout(Duration::from_secs(5), server).await;
+        assert!(result.is_ok(), "服务器应在超时后停止");
+    }
+}
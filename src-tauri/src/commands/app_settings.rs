@@ -77,3 +77,78 @@ pub async fn get_default_server_url(
             url
         })
 }
+
+/// 获取笔记内容长度上限（字符数，0 表示不限制）
+///
+/// 编辑器可据此在内容接近上限时提前提示
+#[tauri::command]
+pub async fn get_max_note_length(
+    service: AppSettingsSvc<'_>,
+) -> Result<usize, String> {
+    service.get_max_note_length()
+        .map_err(|e| {
+            log::error!("[commands/app_settings.rs::get_max_note_length] 获取失败: {}", e);
+            e.to_string()
+        })
+}
+
+/// 获取标签调色板
+///
+/// 新建标签未指定颜色时，会从调色板中依次分配
+#[tauri::command]
+pub async fn get_tag_palette(
+    service: AppSettingsSvc<'_>,
+) -> Result<Vec<String>, String> {
+    service.get_tag_palette()
+        .map_err(|e| {
+            log::error!("[commands/app_settings.rs::get_tag_palette] 获取失败: {}", e);
+            e.to_string()
+        })
+}
+
+/// 设置标签调色板
+///
+/// 每项必须是 `#RGB` 或 `#RRGGBB`，传入空列表表示不自动分配颜色
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// await invoke('set_tag_palette', { palette: ['#ef4444', '#3b82f6', '#22c55e'] });
+/// ```
+#[tauri::command]
+pub async fn set_tag_palette(
+    palette: Vec<String>,
+    service: AppSettingsSvc<'_>,
+) -> Result<Vec<String>, String> {
+    log::info!("[commands/app_settings.rs::set_tag_palette] 设置标签调色板: count={}", palette.len());
+
+    service.set_tag_palette(palette)
+        .map_err(|e| {
+            log::error!("[commands/app_settings.rs::set_tag_palette] 设置失败: {}", e);
+            e.to_string()
+        })
+}
+
+/// 设置自动同步间隔（分钟）
+///
+/// 范围 1 到 1440 分钟；自动同步连续失败时实际间隔会在此基础上退避，
+/// 当前生效的间隔可通过 `get_sync_status` 查看
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// await invoke('set_sync_interval', { minutes: 15 });
+/// ```
+#[tauri::command]
+pub async fn set_sync_interval(
+    minutes: i32,
+    service: AppSettingsSvc<'_>,
+) -> Result<i32, String> {
+    log::info!("[commands/app_settings.rs::set_sync_interval] 设置同步间隔: {} 分钟", minutes);
+
+    service.set_sync_interval(minutes)
+        .map_err(|e| {
+            log::error!("[commands/app_settings.rs::set_sync_interval] 设置失败: {}", e);
+            e.to_string()
+        })
+}
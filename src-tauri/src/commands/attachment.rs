@@ -0,0 +1,104 @@
+use crate::services::AttachmentService;
+use crate::models::{Attachment, AddAttachmentRequest, AttachmentListItem};
+use tauri::State;
+
+/// Attachment service 类型别名
+type AttachmentSvc<'a> = State<'a, AttachmentService>;
+
+/// 为笔记添加附件
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const attachment = await invoke('add_attachment', {
+///   req: { noteId: 'xxx', filename: 'image.png', mimeType: 'image/png', data: base64Data },
+/// });
+/// ```
+#[tauri::command]
+pub async fn add_attachment(
+    req: AddAttachmentRequest,
+    service: AttachmentSvc<'_>,
+) -> std::result::Result<Attachment, String> {
+    let note_id = req.note_id.clone();
+    log::info!("[commands/attachment.rs::add_attachment] 添加附件: note_id={}, filename={}", note_id, req.filename);
+
+    service.add_attachment(req)
+        .map_err(|e| {
+            log::error!("[commands/attachment.rs::add_attachment] 添加失败: note_id={}, error={}", note_id, e);
+            e.to_string()
+        })
+        .inspect(|attachment| {
+            log::info!(
+                "[commands/attachment.rs::add_attachment] 添加成功: id={}, size_bytes={}",
+                attachment.id,
+                attachment.size_bytes
+            );
+        })
+}
+
+/// 列出笔记的附件（不含文件数据）
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const attachments = await invoke('list_attachments', { noteId: 'xxx' });
+/// ```
+#[tauri::command]
+pub async fn list_attachments(
+    note_id: String,
+    service: AttachmentSvc<'_>,
+) -> std::result::Result<Vec<AttachmentListItem>, String> {
+    log::debug!("[commands/attachment.rs::list_attachments] 列出附件: note_id={}", note_id);
+
+    service.list_attachments(&note_id)
+        .map_err(|e| {
+            log::error!("[commands/attachment.rs::list_attachments] 列出失败: note_id={}, error={}", note_id, e);
+            e.to_string()
+        })
+}
+
+/// 获取附件（含 Base64 编码的文件数据）
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const attachment = await invoke('get_attachment', { attachmentId: 'xxx' });
+/// const src = `data:${attachment.mimeType};base64,${attachment.data}`;
+/// ```
+#[tauri::command]
+pub async fn get_attachment(
+    attachment_id: String,
+    service: AttachmentSvc<'_>,
+) -> std::result::Result<Attachment, String> {
+    log::debug!("[commands/attachment.rs::get_attachment] 获取附件: attachment_id={}", attachment_id);
+
+    service.get_attachment(&attachment_id)
+        .map_err(|e| {
+            log::error!("[commands/attachment.rs::get_attachment] 获取失败: attachment_id={}, error={}", attachment_id, e);
+            e.to_string()
+        })
+}
+
+/// 删除附件
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// await invoke('delete_attachment', { attachmentId: 'xxx' });
+/// ```
+#[tauri::command]
+pub async fn delete_attachment(
+    attachment_id: String,
+    service: AttachmentSvc<'_>,
+) -> std::result::Result<(), String> {
+    log::info!("[commands/attachment.rs::delete_attachment] 删除附件: attachment_id={}", attachment_id);
+
+    service.delete_attachment(&attachment_id)
+        .map_err(|e| {
+            log::error!("[commands/attachment.rs::delete_attachment] 删除失败: attachment_id={}, error={}", attachment_id, e);
+            e.to_string()
+        })
+        .inspect(|_| {
+            log::info!("[commands/attachment.rs::delete_attachment] 删除成功: attachment_id={}", attachment_id);
+        })
+}
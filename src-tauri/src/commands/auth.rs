@@ -1,10 +1,11 @@
-use crate::services::{AuthService, AutoSyncService};
-use crate::models::{LoginRequest, RegisterRequest, AuthResponse, User, AccountWithProfile};
+use crate::services::{AuthService, AutoSyncService, SyncService, auth_service::{ChangePasswordResult, LogoutAllResult, LogoutResult}};
+use crate::models::{LoginRequest, RegisterRequest, AuthResponse, User, AccountWithProfile, Device};
 use tauri::State;
 
 /// Auth service 类型别名
 type AuthSvc<'a> = State<'a, AuthService>;
 type AutoSyncSvc<'a> = State<'a, AutoSyncService>;
+type SyncSvc<'a> = State<'a, SyncService>;
 
 /// 用户登录（成功后自动启动自动同步）
 #[tauri::command]
@@ -12,6 +13,7 @@ pub async fn login(
     req: LoginRequest,
     auth_service: AuthSvc<'_>,
     auto_sync: AutoSyncSvc<'_>,
+    sync_service: SyncSvc<'_>,
 ) -> std::result::Result<AuthResponse, String> {
     log::info!("[commands/auth.rs::login] 收到登录请求: email={}, server_url={}", req.email, req.server_url);
 
@@ -30,6 +32,11 @@ pub async fn login(
         log::warn!("[commands/auth.rs::login] 启动自动同步服务失败: {}", e);
     }
 
+    // 刷新服务器限制缓存（失败不影响登录）
+    if let Err(e) = sync_service.fetch_server_limits(true).await {
+        log::warn!("[commands/auth.rs::login] 获取服务器限制失败: {}", e);
+    }
+
     Ok(result)
 }
 
@@ -53,6 +60,8 @@ pub async fn register(
 }
 
 /// 用户登出（先停止自动同步服务）
+///
+/// 会尝试通知服务器吊销 token，吊销失败不影响本地登出
 #[tauri::command]
 pub async fn logout(
     service: AuthSvc<'_>,
@@ -64,13 +73,151 @@ pub async fn logout(
     log::info!("[commands/auth.rs::logout] 停止自动同步服务");
     auto_sync.stop().await;
 
-    service.logout()
+    service.logout_with_server()
+        .await
         .map_err(|e| {
             log::error!("[commands/auth.rs::logout] 登出失败: {}", e);
             e.to_string()
         })
-        .map(|_| {
-            log::info!("[commands/auth.rs::logout] 登出成功");
+        .map(|result| {
+            log::info!("[commands/auth.rs::logout] 登出成功: server_revoked={}", result.server_revoked);
+        })
+}
+
+/// 用户登出并返回服务器吊销结果
+///
+/// 与 `logout` 相同，但会返回服务器是否成功吊销 token，
+/// 便于前端提示"已在本地登出，但服务器端 token 仍有效直至过期"
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const result = await invoke('logout_with_server');
+/// if (!result.serverRevoked) {
+///   console.warn('服务器吊销失败:', result.serverError);
+/// }
+/// ```
+#[tauri::command]
+pub async fn logout_with_server(
+    service: AuthSvc<'_>,
+    auto_sync: AutoSyncSvc<'_>,
+) -> std::result::Result<LogoutResult, String> {
+    log::info!("[commands/auth.rs::logout_with_server] 收到登出请求");
+
+    auto_sync.stop().await;
+
+    service.logout_with_server()
+        .await
+        .map_err(|e| {
+            log::error!("[commands/auth.rs::logout_with_server] 登出失败: {}", e);
+            e.to_string()
+        })
+        .inspect(|result| {
+            log::info!(
+                "[commands/auth.rs::logout_with_server] 登出成功: server_revoked={}",
+                result.server_revoked
+            );
+        })
+}
+
+/// 登出所有设备
+///
+/// 吊销该账号在所有设备上的登录状态。`keepCurrentDevice` 为 false 时当前设备也会登出
+/// （停止自动同步服务并清除本地登录状态）；服务器请求失败时返回错误，本地登录状态保持不变
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const result = await invoke('logout_all_devices', { keepCurrentDevice: true });
+/// console.log(`已清除 ${result.revokedRefreshTokens} 个设备的登录状态`);
+/// ```
+#[tauri::command]
+pub async fn logout_all_devices(
+    keep_current_device: bool,
+    service: AuthSvc<'_>,
+    auto_sync: AutoSyncSvc<'_>,
+) -> std::result::Result<LogoutAllResult, String> {
+    log::info!("[commands/auth.rs::logout_all_devices] 收到请求: keep_current_device={}", keep_current_device);
+
+    let result = service.logout_all_devices(keep_current_device)
+        .await
+        .map_err(|e| {
+            log::error!("[commands/auth.rs::logout_all_devices] 登出所有设备失败: {}", e);
+            e.to_string()
+        })?;
+
+    if !keep_current_device {
+        auto_sync.stop().await;
+        service.logout().map_err(|e| {
+            log::error!("[commands/auth.rs::logout_all_devices] 本地登出失败: {}", e);
+            e.to_string()
+        })?;
+    }
+
+    log::info!(
+        "[commands/auth.rs::logout_all_devices] 登出所有设备成功: revoked_refresh_tokens={}",
+        result.revoked_refresh_tokens
+    );
+    Ok(result)
+}
+
+/// 获取当前账号已登录的设备列表
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const devices = await invoke('list_devices');
+/// ```
+#[tauri::command]
+pub async fn list_devices(
+    service: AuthSvc<'_>,
+) -> std::result::Result<Vec<Device>, String> {
+    log::info!("[commands/auth.rs::list_devices] 获取设备列表");
+
+    service.list_devices()
+        .await
+        .map_err(|e| {
+            log::error!("[commands/auth.rs::list_devices] 获取设备列表失败: {}", e);
+            e.to_string()
+        })
+        .inspect(|devices| {
+            log::info!("[commands/auth.rs::list_devices] 获取成功: count={}", devices.len());
+        })
+}
+
+/// 更新设备（重命名、标记受信任）
+///
+/// 自定义名称优先于服务器自动生成的名称；`name` 传空字符串时清除自定义名称，
+/// 未传的字段保持不变
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const device = await invoke('update_device', { deviceId, name: '工作电脑', trusted: true });
+/// ```
+#[tauri::command]
+pub async fn update_device(
+    device_id: String,
+    name: Option<String>,
+    trusted: Option<bool>,
+    service: AuthSvc<'_>,
+) -> std::result::Result<Device, String> {
+    log::info!(
+        "[commands/auth.rs::update_device] 更新设备: device_id={}, name={:?}, trusted={:?}",
+        device_id, name, trusted
+    );
+
+    service.update_device(&device_id, name, trusted)
+        .await
+        .map_err(|e| {
+            log::error!("[commands/auth.rs::update_device] 更新设备失败: {}", e);
+            e.to_string()
+        })
+        .inspect(|device| {
+            log::info!(
+                "[commands/auth.rs::update_device] 更新成功: device_name={}, trusted={}",
+                device.device_name, device.trusted
+            );
         })
 }
 
@@ -196,6 +343,63 @@ pub async fn refresh_access_token(
         })
 }
 
+/// 校验本地账号与服务器账号是否一致
+///
+/// 不一致时返回错误，前端应提示用户重新登录
+#[tauri::command]
+pub async fn validate_session(
+    service: AuthSvc<'_>,
+) -> std::result::Result<User, String> {
+    log::info!("[commands/auth.rs::validate_session] 校验会话");
+
+    service.validate_session()
+        .await
+        .map_err(|e| {
+            log::error!("[commands/auth.rs::validate_session] 校验失败: {}", e);
+            e.to_string()
+        })
+        .inspect(|user| {
+            log::info!("[commands/auth.rs::validate_session] 校验成功: user_id={}", user.id);
+        })
+}
+
+/// 修改密码
+///
+/// 需要验证原密码，新密码需满足服务器的密码策略。`logoutOtherDevices` 为 true 时
+/// 其他设备的登录状态同时失效，当前设备保持登录
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const result = await invoke('change_password', {
+///   oldPassword: 'old-password',
+///   newPassword: 'new-password1',
+///   logoutOtherDevices: true,
+/// });
+/// ```
+#[tauri::command]
+pub async fn change_password(
+    old_password: String,
+    new_password: String,
+    logout_other_devices: bool,
+    service: AuthSvc<'_>,
+) -> std::result::Result<ChangePasswordResult, String> {
+    log::info!("[commands/auth.rs::change_password] 修改密码请求: logout_other_devices={}", logout_other_devices);
+
+    service.change_password(old_password, new_password, logout_other_devices)
+        .await
+        .map_err(|e| {
+            log::error!("[commands/auth.rs::change_password] 修改失败: {}", e);
+            e.to_string()
+        })
+        .inspect(|result| {
+            log::info!(
+                "[commands/auth.rs::change_password] 修改成功: revoked_refresh_tokens={}",
+                result.revoked_refresh_tokens
+            );
+        })
+}
+
 /// 删除账号（需要密码验证）
 #[tauri::command]
 pub async fn delete_account(
@@ -0,0 +1,45 @@
+use crate::services::{CleanupService, cleanup_service::TrashPurgeResult};
+use tauri::State;
+
+/// Cleanup service 类型别名
+type CleanupSvc<'a> = State<'a, CleanupService>;
+
+/// 永久删除回收站中早于指定时间的笔记
+///
+/// 只清理指定工作空间中 `deleted_at < before_timestamp` 的笔记，较新的删除仍可恢复
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const result = await invoke('purge_trash_before', {
+///   workspaceId: 'xxx',
+///   beforeTimestamp: Math.floor(Date.now() / 1000) - 7 * 86400,
+/// });
+/// console.log(`已删除 ${result.notes} 篇笔记`);
+/// ```
+#[tauri::command]
+pub async fn purge_trash_before(
+    workspace_id: String,
+    before_timestamp: i64,
+    service: CleanupSvc<'_>,
+) -> std::result::Result<TrashPurgeResult, String> {
+    log::info!(
+        "[commands/cleanup.rs::purge_trash_before] 清理回收站: workspace_id={}, before={}",
+        workspace_id,
+        before_timestamp
+    );
+
+    service
+        .purge_trash_before(&workspace_id, before_timestamp)
+        .map_err(|e| {
+            log::error!("[commands/cleanup.rs::purge_trash_before] 清理失败: {}", e);
+            e.to_string()
+        })
+        .inspect(|result| {
+            log::info!(
+                "[commands/cleanup.rs::purge_trash_before] 清理成功: notes={}, note_tags={}",
+                result.notes,
+                result.note_tags
+            );
+        })
+}
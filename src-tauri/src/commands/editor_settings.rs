@@ -40,3 +40,131 @@ pub async fn update_editor_settings(
             settings
         })
 }
+
+/// 获取所有编辑器配置
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const profiles = await invoke<EditorSettings[]>('list_editor_profiles');
+/// const active = profiles.find(p => p.isActive);
+/// ```
+#[tauri::command]
+pub async fn list_editor_profiles(
+    service: EditorSettingsSvc<'_>,
+) -> std::result::Result<Vec<EditorSettings>, String> {
+    log::debug!("[commands/editor_settings.rs::list_editor_profiles] 获取编辑器配置列表");
+
+    service.list_profiles()
+        .map_err(|e| {
+            log::error!("[commands/editor_settings.rs::list_editor_profiles] 获取失败: {}", e);
+            e.to_string()
+        })
+}
+
+/// 以当前编辑器设置新建命名配置
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const profile = await invoke<EditorSettings>('create_editor_profile', { name: '编程' });
+/// ```
+#[tauri::command]
+pub async fn create_editor_profile(
+    name: String,
+    service: EditorSettingsSvc<'_>,
+) -> std::result::Result<EditorSettings, String> {
+    log::info!("[commands/editor_settings.rs::create_editor_profile] 创建编辑器配置: name={}", name);
+
+    service.create_profile(&name)
+        .map_err(|e| {
+            log::error!("[commands/editor_settings.rs::create_editor_profile] 创建失败: name={}, error={}", name, e);
+            e.to_string()
+        })
+        .inspect(|profile| {
+            log::info!("[commands/editor_settings.rs::create_editor_profile] 创建成功: id={}", profile.id);
+        })
+}
+
+/// 删除编辑器配置（默认配置不能删除）
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// await invoke('delete_editor_profile', { id: 2 });
+/// ```
+#[tauri::command]
+pub async fn delete_editor_profile(
+    id: i32,
+    service: EditorSettingsSvc<'_>,
+) -> std::result::Result<(), String> {
+    log::info!("[commands/editor_settings.rs::delete_editor_profile] 删除编辑器配置: id={}", id);
+
+    service.delete_profile(id)
+        .map_err(|e| {
+            log::error!("[commands/editor_settings.rs::delete_editor_profile] 删除失败: id={}, error={}", id, e);
+            e.to_string()
+        })
+}
+
+/// 切换到指定的编辑器配置，返回切换后的设置
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const settings = await invoke<EditorSettings>('activate_editor_profile', { id: 2 });
+/// ```
+#[tauri::command]
+pub async fn activate_editor_profile(
+    id: i32,
+    service: EditorSettingsSvc<'_>,
+) -> std::result::Result<EditorSettings, String> {
+    log::info!("[commands/editor_settings.rs::activate_editor_profile] 切换编辑器配置: id={}", id);
+
+    service.activate_profile(id)
+        .map_err(|e| {
+            log::error!("[commands/editor_settings.rs::activate_editor_profile] 切换失败: id={}, error={}", id, e);
+            e.to_string()
+        })
+}
+
+/// 导出当前编辑器设置为 JSON 字符串
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const json = await invoke<string>('export_editor_settings');
+/// ```
+#[tauri::command]
+pub async fn export_editor_settings(
+    service: EditorSettingsSvc<'_>,
+) -> std::result::Result<String, String> {
+    log::info!("[commands/editor_settings.rs::export_editor_settings] 导出编辑器设置");
+
+    service.export_settings()
+        .map_err(|e| {
+            log::error!("[commands/editor_settings.rs::export_editor_settings] 导出失败: {}", e);
+            e.to_string()
+        })
+}
+
+/// 导入编辑器设置（应用到当前配置），包含未知或无效字段时拒绝导入
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const settings = await invoke<EditorSettings>('import_editor_settings', { jsonString: json });
+/// ```
+#[tauri::command]
+pub async fn import_editor_settings(
+    json_string: String,
+    service: EditorSettingsSvc<'_>,
+) -> std::result::Result<EditorSettings, String> {
+    log::info!("[commands/editor_settings.rs::import_editor_settings] 导入编辑器设置: json_length={}", json_string.len());
+
+    service.import_settings(&json_string)
+        .map_err(|e| {
+            log::error!("[commands/editor_settings.rs::import_editor_settings] 导入失败: {}", e);
+            e.to_string()
+        })
+}
@@ -1,5 +1,6 @@
 use crate::services::FolderService;
 use crate::models::{Folder, CreateFolderRequest, UpdateFolderRequest, MoveFolderRequest};
+use std::collections::HashMap;
 use tauri::State;
 
 /// Folder service 类型别名
@@ -77,6 +78,33 @@ pub async fn delete_folder(
         })
 }
 
+/// 从回收站恢复文件夹
+///
+/// 连同与文件夹一起被删除的子文件夹和笔记一并恢复；
+/// 删除文件夹之前就已单独删除的笔记仍保留在回收站
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const folder = await invoke('restore_folder', { id: 'folder-id' });
+/// ```
+#[tauri::command]
+pub async fn restore_folder(
+    id: String,
+    service: FolderSvc<'_>,
+) -> std::result::Result<Folder, String> {
+    log::info!("[commands/folders.rs::restore_folder] 恢复文件夹: id={}", id);
+
+    service.restore_folder(&id)
+        .map_err(|e| {
+            log::error!("[commands/folders.rs::restore_folder] 恢复失败: id={}, error={}", id, e);
+            e.to_string()
+        })
+        .inspect(|folder| {
+            log::info!("[commands/folders.rs::restore_folder] 恢复成功: id={}", folder.id);
+        })
+}
+
 /// 获取所有文件夹
 #[tauri::command]
 pub async fn list_folders(
@@ -152,3 +180,39 @@ pub async fn permanently_delete_folder(
             log::info!("[commands/folders.rs::permanently_delete_folder] 删除成功: id={}", id);
         })
 }
+
+/// 获取工作空间内每个文件夹的最后活跃时间
+///
+/// 返回 `folderId -> 时间戳` 映射，没有笔记的文件夹使用自身的更新时间
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const lastModified = await invoke('get_folder_last_modified', {
+///   workspaceId: 'xxx',
+///   includeDescendants: true,
+/// });
+/// folders.sort((a, b) => lastModified[b.id] - lastModified[a.id]);
+/// ```
+#[tauri::command]
+pub async fn get_folder_last_modified(
+    workspace_id: String,
+    include_descendants: Option<bool>,
+    service: FolderSvc<'_>,
+) -> std::result::Result<HashMap<String, i64>, String> {
+    let include_descendants = include_descendants.unwrap_or(false);
+    log::debug!(
+        "[commands/folders.rs::get_folder_last_modified] 获取文件夹活跃时间: workspace_id={}, include_descendants={}",
+        workspace_id,
+        include_descendants
+    );
+
+    service.get_folder_last_modified(&workspace_id, include_descendants)
+        .map_err(|e| {
+            log::error!("[commands/folders.rs::get_folder_last_modified] 获取失败: {}", e);
+            e.to_string()
+        })
+        .inspect(|map| {
+            log::debug!("[commands/folders.rs::get_folder_last_modified] 获取成功: count={}", map.len());
+        })
+}
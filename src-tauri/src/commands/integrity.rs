@@ -0,0 +1,180 @@
+use crate::services::{IntegrityService, integrity_service::{CrossWorkspaceFolderParent, FolderCycle, InvalidContentNote, WorkspaceFolderMismatch}};
+use tauri::State;
+
+/// Integrity service 类型别名
+type IntegritySvc<'a> = State<'a, IntegrityService>;
+
+/// 查找 workspace_id 与所在文件夹不一致的笔记
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const mismatches = await invoke('find_workspace_folder_mismatches');
+/// ```
+#[tauri::command]
+pub async fn find_workspace_folder_mismatches(
+    service: IntegritySvc<'_>,
+) -> std::result::Result<Vec<WorkspaceFolderMismatch>, String> {
+    service
+        .find_workspace_folder_mismatches()
+        .map_err(|e| {
+            log::error!("[commands/integrity.rs::find_workspace_folder_mismatches] 检测失败: {}", e);
+            e.to_string()
+        })
+}
+
+/// 修复 workspace_id 与所在文件夹不一致的笔记
+///
+/// `moveToFolderWorkspace` 为 true 时将笔记移到文件夹所在工作空间，否则清空笔记的文件夹
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const repaired = await invoke('repair_workspace_folder_mismatches', {
+///   moveToFolderWorkspace: true,
+/// });
+/// ```
+#[tauri::command]
+pub async fn repair_workspace_folder_mismatches(
+    move_to_folder_workspace: bool,
+    service: IntegritySvc<'_>,
+) -> std::result::Result<usize, String> {
+    log::info!(
+        "[commands/integrity.rs::repair_workspace_folder_mismatches] 修复: move_to_folder_workspace={}",
+        move_to_folder_workspace
+    );
+
+    service
+        .repair_workspace_folder_mismatches(move_to_folder_workspace)
+        .map_err(|e| {
+            log::error!("[commands/integrity.rs::repair_workspace_folder_mismatches] 修复失败: {}", e);
+            e.to_string()
+        })
+}
+
+/// 查找父文件夹属于另一个工作空间的文件夹
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const folders = await invoke('find_cross_workspace_folder_parents');
+/// ```
+#[tauri::command]
+pub async fn find_cross_workspace_folder_parents(
+    service: IntegritySvc<'_>,
+) -> std::result::Result<Vec<CrossWorkspaceFolderParent>, String> {
+    service
+        .find_cross_workspace_folder_parents()
+        .map_err(|e| {
+            log::error!("[commands/integrity.rs::find_cross_workspace_folder_parents] 检测失败: {}", e);
+            e.to_string()
+        })
+}
+
+/// 修复父文件夹属于另一个工作空间的文件夹
+///
+/// `moveSubtree` 为 true 时将整个子树（含笔记）移到父文件夹所在工作空间，否则将文件夹设为根文件夹
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const repaired = await invoke('repair_cross_workspace_folder_parents', { moveSubtree: false });
+/// ```
+#[tauri::command]
+pub async fn repair_cross_workspace_folder_parents(
+    move_subtree: bool,
+    service: IntegritySvc<'_>,
+) -> std::result::Result<usize, String> {
+    log::info!(
+        "[commands/integrity.rs::repair_cross_workspace_folder_parents] 修复: move_subtree={}",
+        move_subtree
+    );
+
+    service
+        .repair_cross_workspace_folder_parents(move_subtree)
+        .map_err(|e| {
+            log::error!("[commands/integrity.rs::repair_cross_workspace_folder_parents] 修复失败: {}", e);
+            e.to_string()
+        })
+}
+
+/// 查找内容损坏的笔记
+///
+/// 检测无效 UTF-8、替换字符和控制字符，并返回每篇笔记第一个问题的字节偏移
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const notes = await invoke('find_invalid_content_notes', { workspaceId: 'xxx' });
+/// ```
+#[tauri::command]
+pub async fn find_invalid_content_notes(
+    workspace_id: String,
+    service: IntegritySvc<'_>,
+) -> std::result::Result<Vec<InvalidContentNote>, String> {
+    service
+        .find_invalid_content(&workspace_id)
+        .map_err(|e| {
+            log::error!("[commands/integrity.rs::find_invalid_content_notes] 检测失败: {}", e);
+            e.to_string()
+        })
+}
+
+/// 清理损坏的笔记内容（移除替换字符和控制字符）
+#[tauri::command]
+pub async fn repair_invalid_content_notes(
+    workspace_id: String,
+    service: IntegritySvc<'_>,
+) -> std::result::Result<usize, String> {
+    log::info!("[commands/integrity.rs::repair_invalid_content_notes] 清理: workspace_id={}", workspace_id);
+
+    service
+        .repair_invalid_content(&workspace_id)
+        .map_err(|e| {
+            log::error!("[commands/integrity.rs::repair_invalid_content_notes] 清理失败: {}", e);
+            e.to_string()
+        })
+}
+
+/// 查找 parent_id 形成循环的文件夹
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const cycles = await invoke('find_folder_cycles', { workspaceId: 'xxx' });
+/// cycles.forEach(c => console.log(c.folderNames.join(' → ')));
+/// ```
+#[tauri::command]
+pub async fn find_folder_cycles(
+    workspace_id: String,
+    service: IntegritySvc<'_>,
+) -> std::result::Result<Vec<FolderCycle>, String> {
+    service
+        .find_folder_cycles(&workspace_id)
+        .map_err(|e| {
+            log::error!("[commands/integrity.rs::find_folder_cycles] 检测失败: {}", e);
+            e.to_string()
+        })
+}
+
+/// 修复文件夹循环引用（将每个循环中最近修改的文件夹设为根文件夹）
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const repaired = await invoke('repair_folder_cycles', { workspaceId: 'xxx' });
+/// ```
+#[tauri::command]
+pub async fn repair_folder_cycles(
+    workspace_id: String,
+    service: IntegritySvc<'_>,
+) -> std::result::Result<usize, String> {
+    log::info!("[commands/integrity.rs::repair_folder_cycles] 修复: workspace_id={}", workspace_id);
+
+    service
+        .repair_folder_cycles(&workspace_id)
+        .map_err(|e| {
+            log::error!("[commands/integrity.rs::repair_folder_cycles] 修复失败: {}", e);
+            e.to_string()
+        })
+}
@@ -1,5 +1,5 @@
 use crate::services::KeybindingService;
-use crate::models::{KeyCombination, KeybindingPreset};
+use crate::models::{KeyCombination, KeybindingPreset, KeybindingSaveResult};
 use tauri::State;
 use std::collections::HashMap;
 
@@ -25,39 +25,63 @@ pub async fn load_keybindings(
 }
 
 /// 保存快捷键配置
+///
+/// 多个命令绑定同一快捷键组合时不保存，返回 `saved: false` 和冲突列表；
+/// `overwrite` 为 true 时忽略冲突直接保存
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const result = await invoke('save_keybindings', { keybindings, presets });
+/// if (!result.saved) {
+///   result.conflicts.forEach(c => console.warn(c.actions.join(' / ')));
+/// }
+/// ```
 #[tauri::command]
 pub async fn save_keybindings(
     keybindings: HashMap<String, KeyCombination>,
     presets: Vec<KeybindingPreset>,
+    overwrite: Option<bool>,
     service: KeybindingSvc<'_>,
-) -> std::result::Result<(), String> {
+) -> std::result::Result<KeybindingSaveResult, String> {
     log::info!("[commands/keybindings.rs::save_keybindings] 保存快捷键配置: count={}", keybindings.len());
 
-    service.save_keybindings(keybindings, presets)
+    service.save_keybindings(keybindings, presets, overwrite.unwrap_or(false))
         .map_err(|e| {
             log::error!("[commands/keybindings.rs::save_keybindings] 保存失败: {}", e);
             e.to_string()
         })
-        .map(|_| {
-            log::info!("[commands/keybindings.rs::save_keybindings] 保存成功");
+        .inspect(|result| {
+            log::info!(
+                "[commands/keybindings.rs::save_keybindings] 处理完成: saved={}, conflicts={}",
+                result.saved,
+                result.conflicts.len()
+            );
         })
 }
 
 /// 导入快捷键配置
+///
+/// 冲突处理与 `save_keybindings` 相同
 #[tauri::command]
 pub async fn import_keybindings(
     json_string: String,
+    overwrite: Option<bool>,
     service: KeybindingSvc<'_>,
-) -> std::result::Result<(), String> {
+) -> std::result::Result<KeybindingSaveResult, String> {
     log::info!("[commands/keybindings.rs::import_keybindings] 导入快捷键配置: json_length={}", json_string.len());
 
-    service.import_keybindings(&json_string)
+    service.import_keybindings(&json_string, overwrite.unwrap_or(false))
         .map_err(|e| {
             log::error!("[commands/keybindings.rs::import_keybindings] 导入失败: {}", e);
             e.to_string()
         })
-        .map(|_| {
-            log::info!("[commands/keybindings.rs::import_keybindings] 导入成功");
+        .inspect(|result| {
+            log::info!(
+                "[commands/keybindings.rs::import_keybindings] 处理完成: saved={}, conflicts={}",
+                result.saved,
+                result.conflicts.len()
+            );
         })
 }
 
@@ -0,0 +1,36 @@
+use crate::services::{MigrationService, migration_service::SchemaVersionInfo};
+use tauri::State;
+
+/// Migration service 类型别名
+type MigrationSvc<'a> = State<'a, MigrationService>;
+
+/// 获取数据库 schema 版本
+///
+/// 返回当前版本、最新版本以及是否存在未应用的迁移
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const info = await invoke('get_schema_version');
+/// if (info.needsMigration) {
+///   console.warn(`数据库版本落后: ${info.currentVersion} < ${info.latestVersion}`);
+/// }
+/// ```
+#[tauri::command]
+pub async fn get_schema_version(
+    service: MigrationSvc<'_>,
+) -> std::result::Result<SchemaVersionInfo, String> {
+    service
+        .get_schema_version()
+        .map_err(|e| {
+            log::error!("[commands/migration.rs::get_schema_version] 获取失败: {}", e);
+            e.to_string()
+        })
+        .inspect(|info| {
+            log::info!(
+                "[commands/migration.rs::get_schema_version] current={}, latest={}",
+                info.current_version,
+                info.latest_version
+            );
+        })
+}
@@ -3,26 +3,36 @@ pub mod notes;
 pub mod folders;
 pub mod editor_settings;
 pub mod tag;
+pub mod cleanup;
+pub mod migration;
+pub mod integrity;
 // ===== 云端同步相关命令 =====
 pub mod sync;
 pub mod auth;
 pub mod snapshot;
+pub mod attachment;
 pub mod profile;
 pub mod app_settings;
 pub mod workspaces;
+pub mod support;
 
 pub use keybindings::*;
 pub use notes::*;
 pub use folders::*;
 pub use editor_settings::*;
 pub use tag::*;
+pub use cleanup::*;
+pub use migration::*;
+pub use integrity::*;
 // ===== 云端同步命令导出 =====
 pub use sync::*;
 pub use auth::*;
 pub use snapshot::*;
+pub use attachment::*;
 pub use profile::*;
 pub use app_settings::*;
 pub use workspaces::*;
+pub use support::*;
 
 // 兼容性命令（已废弃，保留兼容性）
 #[tauri::command]
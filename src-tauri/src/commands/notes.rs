@@ -1,5 +1,6 @@
-use crate::services::NoteService;
-use crate::models::{Note, CreateNoteRequest, UpdateNoteRequest, MoveNotesRequest};
+use crate::services::{NoteService, note_service::{ImportSummary, RenumberedNote, UndoMoveResult, WorkspaceExportResult, WritingStats}};
+use crate::models::{Note, CreateNoteRequest, UpdateNoteRequest, MoveNotesRequest, RankedNote, NoteCursor, NoteList};
+use std::collections::BTreeMap;
 use tauri::State;
 
 /// Note service 类型别名
@@ -141,21 +142,80 @@ pub async fn restore_notes(
         })
 }
 
-/// 获取所有笔记
+/// 获取笔记列表
+///
+/// 未指定 `limit` 时返回全部笔记数组（兼容旧版调用）；指定 `limit` 时按更新时间倒序分页，
+/// 返回 `{ notes, nextCursor }`，将 `nextCursor` 原样传回即可获取下一页
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const all = await invoke('list_notes');
+///
+/// let page = await invoke('list_notes', { limit: 100 });
+/// while (page.nextCursor) {
+///   page = await invoke('list_notes', { limit: 100, cursor: page.nextCursor });
+/// }
+/// ```
 #[tauri::command]
 pub async fn list_notes(
+    limit: Option<u32>,
+    cursor: Option<NoteCursor>,
     service: NoteSvc<'_>,
-) -> std::result::Result<Vec<Note>, String> {
-    log::debug!("[commands/notes.rs::list_notes] 获取笔记列表");
+) -> std::result::Result<NoteList, String> {
+    log::debug!("[commands/notes.rs::list_notes] 获取笔记列表: limit={:?}", limit);
+
+    let result = match limit {
+        Some(limit) => service.list_notes_page(limit, cursor.as_ref()).map(NoteList::Page),
+        None => service.list_all_notes().map(NoteList::All),
+    };
 
-    service.list_all_notes()
+    result
         .map_err(|e| {
             log::error!("[commands/notes.rs::list_notes] 获取失败: {}", e);
             e.to_string()
         })
-        .map(|notes| {
-            log::debug!("[commands/notes.rs::list_notes] 获取成功: count={}", notes.len());
-            notes
+        .inspect(|list| {
+            let count = match list {
+                NoteList::All(notes) => notes.len(),
+                NoteList::Page(page) => page.notes.len(),
+            };
+            log::debug!("[commands/notes.rs::list_notes] 获取成功: count={}", count);
+        })
+}
+
+/// 分页获取收藏的笔记（跨文件夹，按更新时间倒序）
+///
+/// `workspaceId` 省略时使用当前工作空间，`limit` 默认 50，`offset` 默认 0
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const favorites = await invoke<Note[]>('list_favorite_notes', { limit: 20, offset: 0 });
+/// ```
+#[tauri::command]
+pub async fn list_favorite_notes(
+    workspace_id: Option<String>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+    service: NoteSvc<'_>,
+) -> std::result::Result<Vec<Note>, String> {
+    let limit = limit.unwrap_or(50);
+    let offset = offset.unwrap_or(0);
+    log::debug!(
+        "[commands/notes.rs::list_favorite_notes] 获取收藏笔记: workspace_id={:?}, limit={}, offset={}",
+        workspace_id,
+        limit,
+        offset
+    );
+
+    service.list_favorite_notes(workspace_id.as_deref(), limit, offset)
+        .map_err(|e| {
+            log::error!("[commands/notes.rs::list_favorite_notes] 获取失败: {}", e);
+            e.to_string()
+        })
+        .inspect(|notes| {
+            log::debug!("[commands/notes.rs::list_favorite_notes] 获取成功: count={}", notes.len());
         })
 }
 
@@ -207,6 +267,242 @@ pub async fn search_notes(
         })
 }
 
+/// 按相关度全文搜索笔记
+///
+/// 结果按相关度排序，标题命中优先；`titleHighlight` 和 `snippet` 中的命中词以 `<mark>` 包裹。
+/// `workspaceId` 省略时搜索当前工作空间，`limit` 默认 50
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const results = await invoke('search_notes_fts', { query: 'rust async', limit: 20, offset: 0 });
+/// results.forEach(r => console.log(r.note.id, r.titleHighlight, r.snippet));
+/// ```
+#[tauri::command]
+pub async fn search_notes_fts(
+    query: String,
+    workspace_id: Option<String>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+    service: NoteSvc<'_>,
+) -> std::result::Result<Vec<RankedNote>, String> {
+    log::debug!("[commands/notes.rs::search_notes_fts] 搜索笔记: query={}", query);
+
+    service.search_notes_ranked(&query, workspace_id.as_deref(), limit.unwrap_or(50), offset.unwrap_or(0))
+        .map_err(|e| {
+            log::error!("[commands/notes.rs::search_notes_fts] 搜索失败: query={}, error={}", query, e);
+            e.to_string()
+        })
+        .inspect(|notes| {
+            log::debug!("[commands/notes.rs::search_notes_fts] 搜索成功: query={}, count={}", query, notes.len());
+        })
+}
+
+/// 导出笔记为 Markdown（带 YAML front-matter）
+///
+/// front-matter 包含 title、created_at、updated_at、tags 和 folder（文件夹路径，从根到所在文件夹）
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const markdown = await invoke('export_note_markdown', { noteId: 'note-123' });
+/// ```
+#[tauri::command]
+pub async fn export_note_markdown(
+    note_id: String,
+    service: NoteSvc<'_>,
+) -> std::result::Result<String, String> {
+    log::info!("[commands/notes.rs::export_note_markdown] 导出笔记: note_id={}", note_id);
+
+    service.export_note_markdown(&note_id)
+        .map_err(|e| {
+            log::error!("[commands/notes.rs::export_note_markdown] 导出失败: note_id={}, error={}", note_id, e);
+            e.to_string()
+        })
+}
+
+/// 批量导出笔记为 Markdown
+///
+/// 返回 文件名 → 文件内容，文件名由标题生成，重名时追加 ` (2)` 等序号
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const files = await invoke('export_notes_markdown', { noteIds: ['id1', 'id2'] });
+/// Object.entries(files).forEach(([name, content]) => console.log(name, content.length));
+/// ```
+#[tauri::command]
+pub async fn export_notes_markdown(
+    note_ids: Vec<String>,
+    service: NoteSvc<'_>,
+) -> std::result::Result<BTreeMap<String, String>, String> {
+    log::info!("[commands/notes.rs::export_notes_markdown] 批量导出笔记: count={}", note_ids.len());
+
+    service.export_notes_markdown(&note_ids)
+        .map_err(|e| {
+            log::error!("[commands/notes.rs::export_notes_markdown] 批量导出失败: {}", e);
+            e.to_string()
+        })
+}
+
+/// 导出整个工作空间为 zip 归档
+///
+/// 文件夹树保存为目录，每篇笔记为带 front-matter 的 `.md` 文件，根目录包含 `manifest.json`
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const result = await invoke('export_workspace', { workspaceId: 'ws-1', destPath: '/path/to/backup.zip' });
+/// console.log(result.noteCount, result.folderCount);
+/// ```
+#[tauri::command]
+pub async fn export_workspace(
+    workspace_id: String,
+    dest_path: String,
+    service: NoteSvc<'_>,
+) -> std::result::Result<WorkspaceExportResult, String> {
+    log::info!("[commands/notes.rs::export_workspace] 导出工作空间: workspace_id={}, path={}", workspace_id, dest_path);
+
+    service.export_workspace(&workspace_id, &dest_path)
+        .map_err(|e| {
+            log::error!("[commands/notes.rs::export_workspace] 导出失败: workspace_id={}, error={}", workspace_id, e);
+            e.to_string()
+        })
+}
+
+/// 从目录或 zip 归档导入 Markdown 笔记到指定工作空间
+///
+/// 按目录结构重建文件夹，按 front-matter 中的 `tags` 关联标签；已存在相同笔记时跳过，可重复导入
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const summary = await invoke('import_markdown_archive', { path: '/path/to/backup.zip', workspaceId: 'ws-1' });
+/// console.log(summary.notesCreated, summary.foldersCreated, summary.tagsCreated, summary.notesSkipped);
+/// ```
+#[tauri::command]
+pub async fn import_markdown_archive(
+    path: String,
+    workspace_id: String,
+    service: NoteSvc<'_>,
+) -> std::result::Result<ImportSummary, String> {
+    log::info!("[commands/notes.rs::import_markdown_archive] 导入 Markdown: path={}, workspace_id={}", path, workspace_id);
+
+    service.import_markdown_archive(&path, &workspace_id)
+        .map_err(|e| {
+            log::error!("[commands/notes.rs::import_markdown_archive] 导入失败: path={}, error={}", path, e);
+            e.to_string()
+        })
+}
+
+/// 跨工作空间搜索笔记
+///
+/// 搜索当前账号的所有工作空间，默认排除私密笔记
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const notes = await invoke('search_global', { query: 'rust' });
+/// const all = await invoke('search_global', { query: 'rust', includePrivate: true });
+/// ```
+#[tauri::command]
+pub async fn search_global(
+    query: String,
+    include_private: Option<bool>,
+    service: NoteSvc<'_>,
+) -> std::result::Result<Vec<Note>, String> {
+    let include_private = include_private.unwrap_or(false);
+    log::debug!(
+        "[commands/notes.rs::search_global] 全局搜索: query={}, include_private={}",
+        query,
+        include_private
+    );
+
+    service.search_global(&query, include_private)
+        .map_err(|e| {
+            log::error!("[commands/notes.rs::search_global] 搜索失败: query={}, error={}", query, e);
+            e.to_string()
+        })
+        .inspect(|notes| {
+            log::debug!("[commands/notes.rs::search_global] 搜索成功: query={}, count={}", query, notes.len());
+        })
+}
+
+/// 设置笔记是否私密
+///
+/// 私密笔记不会出现在全局搜索中，在所属工作空间内仍可搜索
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const note = await invoke('set_note_private', { noteId: 'xxx', private: true });
+/// ```
+#[tauri::command]
+pub async fn set_note_private(
+    note_id: String,
+    private: bool,
+    service: NoteSvc<'_>,
+) -> std::result::Result<Note, String> {
+    log::info!("[commands/notes.rs::set_note_private] 设置私密: note_id={}, private={}", note_id, private);
+
+    service.set_private(&note_id, private)
+        .map_err(|e| {
+            log::error!("[commands/notes.rs::set_note_private] 设置失败: note_id={}, error={}", note_id, e);
+            e.to_string()
+        })
+}
+
+/// 移动单篇笔记到文件夹
+///
+/// 目标文件夹必须存在、未删除且与笔记在同一工作空间，`targetFolderId` 为 null 时移动到根目录
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const note = await invoke('move_note', { noteId: 'note-id', targetFolderId: 'folder-id' });
+/// ```
+#[tauri::command]
+pub async fn move_note(
+    note_id: String,
+    target_folder_id: Option<String>,
+    service: NoteSvc<'_>,
+) -> std::result::Result<Note, String> {
+    log::info!(
+        "[commands/notes.rs::move_note] 移动笔记: note_id={}, target_folder_id={}",
+        note_id,
+        target_folder_id.as_deref().unwrap_or("root")
+    );
+
+    service.move_note(&note_id, target_folder_id.as_deref())
+        .map_err(|e| {
+            log::error!("[commands/notes.rs::move_note] 移动失败: note_id={}, error={}", note_id, e);
+            e.to_string()
+        })
+}
+
+/// 复制笔记
+///
+/// 生成新 ID 的副本，标题追加"(副本)"，置顶和收藏状态不复制，标签关联一并复制
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const copy = await invoke('duplicate_note', { noteId: 'note-id' });
+/// ```
+#[tauri::command]
+pub async fn duplicate_note(
+    note_id: String,
+    service: NoteSvc<'_>,
+) -> std::result::Result<Note, String> {
+    log::info!("[commands/notes.rs::duplicate_note] 复制笔记: note_id={}", note_id);
+
+    service.duplicate_note(&note_id)
+        .map_err(|e| {
+            log::error!("[commands/notes.rs::duplicate_note] 复制失败: note_id={}, error={}", note_id, e);
+            e.to_string()
+        })
+}
+
 /// 批量移动笔记到文件夹
 #[tauri::command]
 pub async fn move_notes_to_folder(
@@ -227,6 +523,99 @@ pub async fn move_notes_to_folder(
         })
 }
 
+/// 批量移动笔记到文件夹（可撤销）
+///
+/// 移动前记录每篇笔记原来的文件夹，可通过 `undo_last_move` 撤销
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// await invoke('move_notes_to_folder_with_undo', { req: { noteIds: ['a', 'b'], folderId: 'xxx' } });
+/// ```
+#[tauri::command]
+pub async fn move_notes_to_folder_with_undo(
+    req: MoveNotesRequest,
+    service: NoteSvc<'_>,
+) -> std::result::Result<Vec<Note>, String> {
+    log::info!(
+        "[commands/notes.rs::move_notes_to_folder_with_undo] 批量移动笔记: note_count={}, folder_id={}",
+        req.note_ids.len(),
+        req.folder_id.as_deref().unwrap_or("root")
+    );
+
+    service.move_notes_to_folder_with_undo(req)
+        .map_err(|e| {
+            log::error!("[commands/notes.rs::move_notes_to_folder_with_undo] 移动失败: {}", e);
+            e.to_string()
+        })
+        .inspect(|notes| {
+            log::info!("[commands/notes.rs::move_notes_to_folder_with_undo] 移动成功: count={}", notes.len());
+        })
+}
+
+/// 撤销最近一次批量移动
+///
+/// 笔记恢复到移动前的文件夹（原文件夹已删除时移到根目录）
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const result = await invoke('undo_last_move');
+/// console.log(`已恢复 ${result.restored} 篇笔记`);
+/// ```
+#[tauri::command]
+pub async fn undo_last_move(
+    service: NoteSvc<'_>,
+) -> std::result::Result<UndoMoveResult, String> {
+    log::info!("[commands/notes.rs::undo_last_move] 撤销批量移动");
+
+    service.undo_last_move()
+        .map_err(|e| {
+            log::error!("[commands/notes.rs::undo_last_move] 撤销失败: {}", e);
+            e.to_string()
+        })
+        .inspect(|result| {
+            log::info!(
+                "[commands/notes.rs::undo_last_move] 撤销成功: operation_id={}, restored={}",
+                result.operation_id,
+                result.restored
+            );
+        })
+}
+
+/// 获取工作空间最近 N 天的写作统计
+///
+/// 包含每日新建笔记数、每日新增字数（基于快照计算）和最常编辑的笔记，`days` 默认 30
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const stats = await invoke('get_writing_stats', { workspaceId: 'xxx', days: 30 });
+/// console.log(`${stats.totalWordsWritten} 字`, stats.daily);
+/// ```
+#[tauri::command]
+pub async fn get_writing_stats(
+    workspace_id: String,
+    days: Option<i64>,
+    service: NoteSvc<'_>,
+) -> std::result::Result<WritingStats, String> {
+    let days = days.unwrap_or(30);
+    log::debug!("[commands/notes.rs::get_writing_stats] 获取写作统计: workspace_id={}, days={}", workspace_id, days);
+
+    service.writing_stats(&workspace_id, days)
+        .map_err(|e| {
+            log::error!("[commands/notes.rs::get_writing_stats] 获取失败: {}", e);
+            e.to_string()
+        })
+        .inspect(|stats| {
+            log::debug!(
+                "[commands/notes.rs::get_writing_stats] 获取成功: notes_created={}, words_written={}",
+                stats.total_notes_created,
+                stats.total_words_written
+            );
+        })
+}
+
 /// 永久删除笔记（硬删除）
 #[tauri::command]
 pub async fn permanently_delete_note(
@@ -292,3 +681,174 @@ pub async fn get_notes_count(
             count
         })
 }
+
+/// 按当前摘要长度设置重新生成所有笔记的摘要
+///
+/// 修改 `excerpt_length` 设置后调用
+#[tauri::command]
+pub async fn regenerate_excerpts(
+    service: NoteSvc<'_>,
+) -> std::result::Result<usize, String> {
+    log::info!("[commands/notes.rs::regenerate_excerpts] 重新生成摘要");
+
+    service.regenerate_excerpts()
+        .map_err(|e| {
+            log::error!("[commands/notes.rs::regenerate_excerpts] 生成失败: {}", e);
+            e.to_string()
+        })
+        .inspect(|count| {
+            log::info!("[commands/notes.rs::regenerate_excerpts] 生成成功: count={}", count);
+        })
+}
+
+/// 重新生成笔记的 Markdown 缓存
+///
+/// Markdown 渲染规则变化后调用
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const note = await invoke('regenerate_markdown_cache', { noteId: 'xxx' });
+/// ```
+#[tauri::command]
+pub async fn regenerate_markdown_cache(
+    note_id: String,
+    service: NoteSvc<'_>,
+) -> std::result::Result<Note, String> {
+    log::info!("[commands/notes.rs::regenerate_markdown_cache] 重新生成 Markdown 缓存: note_id={}", note_id);
+
+    service.regenerate_markdown_cache(&note_id)
+        .map_err(|e| {
+            log::error!("[commands/notes.rs::regenerate_markdown_cache] 生成失败: note_id={}, error={}", note_id, e);
+            e.to_string()
+        })
+}
+
+/// 重新生成工作空间中所有笔记的 Markdown 缓存
+///
+/// 未指定 `workspaceId` 时使用当前工作空间
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const count = await invoke<number>('regenerate_all_markdown_caches', { workspaceId: null });
+/// ```
+#[tauri::command]
+pub async fn regenerate_all_markdown_caches(
+    workspace_id: Option<String>,
+    service: NoteSvc<'_>,
+) -> std::result::Result<usize, String> {
+    log::info!("[commands/notes.rs::regenerate_all_markdown_caches] 重新生成 Markdown 缓存: workspace_id={:?}", workspace_id);
+
+    service.regenerate_all_markdown_caches(workspace_id.as_deref())
+        .map_err(|e| {
+            log::error!("[commands/notes.rs::regenerate_all_markdown_caches] 生成失败: {}", e);
+            e.to_string()
+        })
+        .inspect(|count| {
+            log::info!("[commands/notes.rs::regenerate_all_markdown_caches] 生成成功: count={}", count);
+        })
+}
+
+/// 为文件夹中的笔记标题添加顺序编号
+///
+/// 已有编号会被替换而不是叠加；`dryRun` 为 true 时只返回拟修改的标题
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const preview = await invoke('renumber_folder_notes', {
+///   folderId: 'xxx',
+///   prefix: '',
+///   dryRun: true,
+/// });
+/// ```
+#[tauri::command]
+pub async fn renumber_folder_notes(
+    folder_id: String,
+    prefix: Option<String>,
+    dry_run: bool,
+    service: NoteSvc<'_>,
+) -> std::result::Result<Vec<RenumberedNote>, String> {
+    log::info!(
+        "[commands/notes.rs::renumber_folder_notes] 重新编号: folder_id={}, dry_run={}",
+        folder_id,
+        dry_run
+    );
+
+    service.renumber_folder(&folder_id, prefix.as_deref().unwrap_or(""), dry_run)
+        .map_err(|e| {
+            log::error!("[commands/notes.rs::renumber_folder_notes] 重新编号失败: {}", e);
+            e.to_string()
+        })
+        .inspect(|changes| {
+            log::info!("[commands/notes.rs::renumber_folder_notes] 重新编号成功: changed={}", changes.len());
+        })
+}
+
+/// 批量收藏/取消收藏笔记
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const changed = await invoke('set_favorite_batch', { noteIds: ['id1', 'id2'], favorite: true });
+/// ```
+#[tauri::command]
+pub async fn set_favorite_batch(
+    note_ids: Vec<String>,
+    favorite: bool,
+    service: NoteSvc<'_>,
+) -> std::result::Result<usize, String> {
+    log::info!("[commands/notes.rs::set_favorite_batch] 批量收藏: count={}, favorite={}", note_ids.len(), favorite);
+
+    service.set_favorite_batch(note_ids, favorite)
+        .map_err(|e| {
+            log::error!("[commands/notes.rs::set_favorite_batch] 批量收藏失败: {}", e);
+            e.to_string()
+        })
+}
+
+/// 批量置顶/取消置顶笔记
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const changed = await invoke('set_pinned_batch', { noteIds: ['id1', 'id2'], pinned: true });
+/// ```
+#[tauri::command]
+pub async fn set_pinned_batch(
+    note_ids: Vec<String>,
+    pinned: bool,
+    service: NoteSvc<'_>,
+) -> std::result::Result<usize, String> {
+    log::info!("[commands/notes.rs::set_pinned_batch] 批量置顶: count={}, pinned={}", note_ids.len(), pinned);
+
+    service.set_pinned_batch(note_ids, pinned)
+        .map_err(|e| {
+            log::error!("[commands/notes.rs::set_pinned_batch] 批量置顶失败: {}", e);
+            e.to_string()
+        })
+}
+
+/// 调整置顶笔记的顺序
+///
+/// 按 `noteIds` 的顺序排到置顶区最前面，未列出的置顶笔记保持原有相对顺序
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// await invoke('set_pin_order', { noteIds: ['id2', 'id1'] });
+/// ```
+#[tauri::command]
+pub async fn set_pin_order(
+    note_ids: Vec<String>,
+    service: NoteSvc<'_>,
+) -> std::result::Result<(), String> {
+    log::info!("[commands/notes.rs::set_pin_order] 调整置顶顺序: count={}", note_ids.len());
+
+    service.set_pin_order(note_ids)
+        .map_err(|e| {
+            log::error!("[commands/notes.rs::set_pin_order] 调整失败: {}", e);
+            e.to_string()
+        })
+}
@@ -1,4 +1,4 @@
-use crate::services::SnapshotService;
+use crate::services::{SnapshotService, snapshot_service::NoteArchiveResult};
 use crate::models::{NoteSnapshot, CreateSnapshotRequest, SnapshotListItem};
 use tauri::State;
 
@@ -95,3 +95,30 @@ pub async fn restore_from_snapshot(
             snapshot
         })
 }
+
+/// 导出笔记归档（zip，包含当前内容、全部快照及 manifest.json）
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const path = await save({ defaultPath: `${note.title}.zip` });
+/// const result = await invoke('export_note_archive', { noteId: note.id, path });
+/// console.log(`已导出 ${result.snapshotCount} 个快照`);
+/// ```
+#[tauri::command]
+pub async fn export_note_archive(
+    note_id: String,
+    path: String,
+    service: SnapshotSvc<'_>,
+) -> std::result::Result<NoteArchiveResult, String> {
+    log::info!("[commands/snapshot.rs::export_note_archive] 导出笔记归档: note_id={}, path={}", note_id, path);
+
+    service.export_note_archive(&note_id, &path)
+        .map_err(|e| {
+            log::error!("[commands/snapshot.rs::export_note_archive] 导出失败: note_id={}, error={}", note_id, e);
+            e.to_string()
+        })
+        .inspect(|result| {
+            log::info!("[commands/snapshot.rs::export_note_archive] 导出成功: note_id={}, snapshots={}", note_id, result.snapshot_count);
+        })
+}
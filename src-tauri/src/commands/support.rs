@@ -0,0 +1,39 @@
+use crate::services::{SupportBundleService, support_bundle_service::SupportBundleResult};
+use std::path::Path;
+use tauri::State;
+
+/// Support bundle service 类型别名
+type SupportBundleSvc<'a> = State<'a, SupportBundleService>;
+
+/// 导出支持包（用于附加到问题反馈）
+///
+/// 包含应用设置、编辑器设置、快捷键、schema 版本、同步状态与死信、待同步数量统计、
+/// 数据目录信息和最近的日志。所有文件中的令牌、密码、邮箱和家目录路径都会被脱敏
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const result = await invoke('export_support_bundle', { path: '/home/user/support.zip' });
+/// console.log(`已导出 ${result.files.length} 个文件，脱敏 ${result.redactions} 处`);
+/// ```
+#[tauri::command]
+pub async fn export_support_bundle(
+    path: String,
+    service: SupportBundleSvc<'_>,
+) -> std::result::Result<SupportBundleResult, String> {
+    log::info!("[commands/support.rs::export_support_bundle] 导出支持包: path={}", path);
+
+    service
+        .export(Path::new(&path))
+        .map_err(|e| {
+            log::error!("[commands/support.rs::export_support_bundle] 导出失败: {}", e);
+            e.to_string()
+        })
+        .inspect(|result| {
+            log::info!(
+                "[commands/support.rs::export_support_bundle] 导出成功: files={}, redactions={}",
+                result.files.len(),
+                result.redactions
+            );
+        })
+}
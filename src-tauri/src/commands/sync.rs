@@ -1,13 +1,17 @@
-use crate::services::{SyncService, SingleSyncService, AutoSyncService};
-use crate::models::{SyncReport, SyncStatus};
+use crate::services::{SyncService, SingleSyncService, AutoSyncService, SyncHistoryService, auto_sync_service::PowerSyncState, sync_service::NoteServerDiff};
+use crate::models::{SyncReport, SyncStatus, SyncHistoryFilter, SyncHistoryPage, DeviceSyncSummary, FlappingItem, PendingChange, LocalOnlyItem, FolderRebuildReport, SyncQueueEntry, ServerLimits, PendingConflict, Note};
 use tauri::State;
 
 /// Sync service 类型别名
 type SyncSvc<'a> = State<'a, SyncService>;
 type SingleSyncSvc<'a> = State<'a, SingleSyncService>;
 type AutoSyncSvc<'a> = State<'a, AutoSyncService>;
+type SyncHistorySvc<'a> = State<'a, SyncHistoryService>;
 
 /// 手动触发同步（带互斥机制）
+///
+/// 返回报告中的 `timing` 字段包含各阶段耗时（构建请求 / 网络往返 / 应用响应 / 清理脏标记），
+/// 可用于判断同步缓慢的瓶颈
 #[tauri::command]
 pub async fn sync_now(
     sync_service: SyncSvc<'_>,
@@ -42,25 +46,116 @@ pub async fn sync_now(
     })
 }
 
+/// 只同步收藏和置顶的笔记（按流量计费 / 网络较差时使用）
+///
+/// 只推送收藏或置顶的脏笔记及其标签，其他未同步的修改保留到下次完整同步
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const report = await invoke('sync_priority_notes');
+/// console.log(`已推送 ${report.pushedNotes} 篇重要笔记`);
+/// ```
+#[tauri::command]
+pub async fn sync_priority_notes(
+    sync_service: SyncSvc<'_>,
+    auto_sync: AutoSyncSvc<'_>,
+) -> std::result::Result<SyncReport, String> {
+    log::info!("[commands/sync.rs::sync_priority_notes] 开始优先同步");
+
+    // 与手动同步共用互斥标记，避免与自动同步同时推送
+    auto_sync.begin_manual_sync().await;
+
+    let result = sync_service.sync_priority_notes()
+        .await
+        .map_err(|e| {
+            log::error!("[commands/sync.rs::sync_priority_notes] 同步失败: {}", e);
+            e.to_string()
+        });
+
+    auto_sync.end_manual_sync().await;
+
+    result.inspect(|report| {
+        log::info!(
+            "[commands/sync.rs::sync_priority_notes] 同步成功: pushed_notes={}, pushed_tags={}",
+            report.pushed_notes,
+            report.pushed_tags
+        );
+    })
+}
+
+/// 同步指定工作空间（无需先切换到该工作空间）
+///
+/// 只推送该工作空间的未同步修改，并拉取该工作空间在服务器上的更新。
+/// 工作空间必须属于当前登录用户
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const report = await invoke('sync_workspace', { workspaceId: 'ws-123' });
+/// console.log(`推送 ${report.pushedNotes} 篇，拉取 ${report.pulledNotes} 篇`);
+/// ```
+#[tauri::command]
+pub async fn sync_workspace(
+    workspace_id: String,
+    sync_service: SyncSvc<'_>,
+    auto_sync: AutoSyncSvc<'_>,
+) -> std::result::Result<SyncReport, String> {
+    log::info!("[commands/sync.rs::sync_workspace] 开始同步工作空间: {}", workspace_id);
+
+    // 与手动同步共用互斥标记，避免与自动同步同时推送
+    auto_sync.begin_manual_sync().await;
+
+    let result = sync_service.sync_workspace(&workspace_id)
+        .await
+        .map_err(|e| {
+            log::error!("[commands/sync.rs::sync_workspace] 同步失败: workspace_id={}, error={}", workspace_id, e);
+            e.to_string()
+        });
+
+    auto_sync.end_manual_sync().await;
+
+    result.inspect(|report| {
+        log::info!(
+            "[commands/sync.rs::sync_workspace] 同步成功: pushed_notes={}, pulled_notes={}, conflicts={}",
+            report.pushed_notes,
+            report.pulled_notes,
+            report.conflict_count
+        );
+    })
+}
+
 /// 获取同步状态
 #[tauri::command]
 pub async fn get_sync_status(
     service: SyncSvc<'_>,
+    auto_sync: AutoSyncSvc<'_>,
 ) -> std::result::Result<SyncStatus, String> {
     log::debug!("[commands/sync.rs::get_sync_status] 获取同步状态");
 
-    service.get_sync_status()
+    let mut status = service.get_sync_status()
         .map_err(|e| {
             log::error!("[commands/sync.rs::get_sync_status] 获取失败: {}", e);
             e.to_string()
-        })
-        .map(|status| {
-            log::debug!(
-                "[commands/sync.rs::get_sync_status] 同步状态: last_sync_at={}",
-                status.last_sync_at.map(|t| t.to_string()).unwrap_or_else(|| "None".to_string())
-            );
-            status
-        })
+        })?;
+
+    // 补充自动同步的实际间隔（连续失败时已退避）
+    status.consecutive_failures = auto_sync.consecutive_failures().await;
+    status.effective_interval_secs = match auto_sync.effective_interval_secs().await {
+        Ok(secs) => Some(secs),
+        Err(e) => {
+            log::warn!("[commands/sync.rs::get_sync_status] 获取同步间隔失败: {}", e);
+            None
+        }
+    };
+
+    log::debug!(
+        "[commands/sync.rs::get_sync_status] 同步状态: last_sync_at={}, effective_interval_secs={:?}",
+        status.last_sync_at.map(|t| t.to_string()).unwrap_or_else(|| "None".to_string()),
+        status.effective_interval_secs
+    );
+
+    Ok(status)
 }
 
 /// 同步单个笔记（包含其标签和快照）
@@ -71,7 +166,7 @@ pub async fn sync_single_note(
 ) -> std::result::Result<SyncReport, String> {
     log::info!("[commands/sync.rs::sync_single_note] 同步单个笔记: {}", note_id);
 
-    service.sync_single_note(&note_id)
+    service.sync_or_enqueue("note", &note_id)
         .await
         .map_err(|e| {
             log::error!("[commands/sync.rs::sync_single_note] 同步失败: {}", e);
@@ -96,7 +191,7 @@ pub async fn sync_single_tag(
 ) -> std::result::Result<SyncReport, String> {
     log::info!("[commands/sync.rs::sync_single_tag] 同步单个标签: {}", tag_id);
 
-    service.sync_single_tag(&tag_id)
+    service.sync_or_enqueue("tag", &tag_id)
         .await
         .map_err(|e| {
             log::error!("[commands/sync.rs::sync_single_tag] 同步失败: {}", e);
@@ -121,7 +216,7 @@ pub async fn sync_single_snapshot(
 ) -> std::result::Result<SyncReport, String> {
     log::info!("[commands/sync.rs::sync_single_snapshot] 同步单个快照: {}", snapshot_id);
 
-    service.sync_single_snapshot(&snapshot_id)
+    service.sync_or_enqueue("snapshot", &snapshot_id)
         .await
         .map_err(|e| {
             log::error!("[commands/sync.rs::sync_single_snapshot] 同步失败: {}", e);
@@ -146,7 +241,7 @@ pub async fn sync_single_folder(
 ) -> std::result::Result<SyncReport, String> {
     log::info!("[commands/sync.rs::sync_single_folder] 同步单个文件夹: {}", folder_id);
 
-    service.sync_single_folder(&folder_id)
+    service.sync_or_enqueue("folder", &folder_id)
         .await
         .map_err(|e| {
             log::error!("[commands/sync.rs::sync_single_folder] 同步失败: {}", e);
@@ -164,3 +259,342 @@ pub async fn sync_single_folder(
             report
         })
 }
+
+/// 按条件分页查询同步历史
+///
+/// 可只查看有冲突或失败的同步，便于排查反复出现的问题。
+/// 默认返回最近 50 条，将结果中的 `nextCursor` 作为 `before` 查询下一页
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const page = await invoke('list_sync_history_filtered', {
+///   filter: { outcome: 'conflicts', from: weekAgo, limit: 50 },
+/// });
+/// const next = await invoke('list_sync_history_filtered', {
+///   filter: { outcome: 'conflicts', from: weekAgo, limit: 50, before: page.nextCursor },
+/// });
+/// ```
+#[tauri::command]
+pub async fn list_sync_history_filtered(
+    filter: SyncHistoryFilter,
+    service: SyncHistorySvc<'_>,
+) -> std::result::Result<SyncHistoryPage, String> {
+    log::info!("[commands/sync.rs::list_sync_history_filtered] 查询同步历史: filter={:?}", filter);
+
+    service.list(filter)
+        .await
+        .map_err(|e| {
+            log::error!("[commands/sync.rs::list_sync_history_filtered] 查询失败: {}", e);
+            e.to_string()
+        })
+        .inspect(|page| {
+            log::info!("[commands/sync.rs::list_sync_history_filtered] 查询成功: count={}", page.entries.len());
+        })
+}
+
+/// 按设备汇总同步历史
+///
+/// 返回每台设备的同步次数和推送/拉取总数，便于查看变更来自哪台设备
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const summaries = await invoke('get_sync_device_summary');
+/// ```
+#[tauri::command]
+pub async fn get_sync_device_summary(
+    service: SyncHistorySvc<'_>,
+) -> std::result::Result<Vec<DeviceSyncSummary>, String> {
+    log::info!("[commands/sync.rs::get_sync_device_summary] 查询设备同步统计");
+
+    service.summarize_by_device()
+        .await
+        .map_err(|e| {
+            log::error!("[commands/sync.rs::get_sync_device_summary] 查询失败: {}", e);
+            e.to_string()
+        })
+        .inspect(|summaries| {
+            log::info!("[commands/sync.rs::get_sync_device_summary] 查询成功: devices={}", summaries.len());
+        })
+}
+
+/// 检测反复删除/复活的实体
+///
+/// 返回最近 7 天内 is_deleted 反复变化的笔记、文件夹和标签，附带每次变化的时间线，
+/// 用于排查多设备同步导致的"删了又回来"问题
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const items = await invoke('detect_flapping_items', { workspaceId: 'xxx' });
+/// ```
+#[tauri::command]
+pub async fn detect_flapping_items(
+    workspace_id: String,
+    service: SyncSvc<'_>,
+) -> std::result::Result<Vec<FlappingItem>, String> {
+    log::info!("[commands/sync.rs::detect_flapping_items] 检测反复删除: workspace_id={}", workspace_id);
+
+    service.detect_flapping(&workspace_id)
+        .map_err(|e| {
+            log::error!("[commands/sync.rs::detect_flapping_items] 检测失败: {}", e);
+            e.to_string()
+        })
+}
+
+/// 列出待同步的变更
+///
+/// 在执行 `full_sync` 前预览将要推送的内容，每项包含类型、标题和变更类型（new / edited / deleted）
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const changes = await invoke('list_pending_changes');
+/// ```
+#[tauri::command]
+pub async fn list_pending_changes(
+    service: SyncSvc<'_>,
+) -> std::result::Result<Vec<PendingChange>, String> {
+    service.list_pending_changes()
+        .map_err(|e| {
+            log::error!("[commands/sync.rs::list_pending_changes] 查询失败: {}", e);
+            e.to_string()
+        })
+}
+
+/// 列出仅存在于本地的笔记（从未被服务器确认）
+///
+/// 返回 `last_synced_at` 为空或 `server_ver = 0` 的笔记，即使它们已不再是脏数据；
+/// `includeFoldersTags` 为 true 时同时返回文件夹和标签
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const items = await invoke('list_local_only_notes', {
+///   workspaceId,
+///   includeFoldersTags: true,
+/// });
+/// ```
+#[tauri::command]
+pub async fn list_local_only_notes(
+    workspace_id: String,
+    include_folders_tags: Option<bool>,
+    service: SyncSvc<'_>,
+) -> std::result::Result<Vec<LocalOnlyItem>, String> {
+    service.list_local_only(&workspace_id, include_folders_tags.unwrap_or(false))
+        .map_err(|e| {
+            log::error!("[commands/sync.rs::list_local_only_notes] 查询失败: {}", e);
+            e.to_string()
+        })
+}
+
+/// 从服务器重建工作空间的文件夹（本地文件夹表损坏时的恢复工具）
+///
+/// 忽略本地版本，以服务器数据覆盖本地文件夹并按 folder_id 重新挂接笔记，
+/// 找不到文件夹的笔记会移到根目录。必须传入 `confirm: true` 才会执行
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// if (await ask('将以服务器数据覆盖本地文件夹，未同步的文件夹修改会丢失，是否继续？')) {
+///   const report = await invoke('rebuild_folders_from_server', { workspaceId, confirm: true });
+///   console.log(`恢复 ${report.serverFolders} 个文件夹，${report.orphanedNotes} 篇笔记移到根目录`);
+/// }
+/// ```
+#[tauri::command]
+pub async fn rebuild_folders_from_server(
+    workspace_id: String,
+    confirm: bool,
+    service: SyncSvc<'_>,
+) -> std::result::Result<FolderRebuildReport, String> {
+    log::info!("[commands/sync.rs::rebuild_folders_from_server] 从服务器重建文件夹: workspace_id={}", workspace_id);
+
+    service.rebuild_folders_from_server(&workspace_id, confirm)
+        .await
+        .map_err(|e| {
+            log::error!("[commands/sync.rs::rebuild_folders_from_server] 重建失败: {}", e);
+            e.to_string()
+        })
+}
+
+/// 对比笔记的本地版本与服务器版本
+///
+/// 返回以服务器版本为基准的逐行差异（`added` 为本地新增，`removed` 为仅服务器存在），
+/// 便于用户在解决冲突前查看。笔记从未同步到服务器时，所有行均为新增
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const diff = await invoke('diff_note_against_server', { noteId });
+/// for (const line of diff.lines) {
+///   const sign = line.kind === 'added' ? '+' : line.kind === 'removed' ? '-' : ' ';
+///   console.log(sign + line.content);
+/// }
+/// ```
+#[tauri::command]
+pub async fn diff_note_against_server(
+    note_id: String,
+    service: SyncSvc<'_>,
+) -> std::result::Result<NoteServerDiff, String> {
+    service.diff_against_server(&note_id)
+        .await
+        .map_err(|e| {
+            log::error!("[commands/sync.rs::diff_note_against_server] 对比失败: note_id={}, error={}", note_id, e);
+            e.to_string()
+        })
+}
+
+/// 列出死信队列中的单项同步
+///
+/// 单个笔记 / 标签 / 快照 / 文件夹同步失败后会自动重试，达到 `maxSyncRetries`
+/// 次（或遇到不可重试的错误）后进入死信队列，需要用户手动处理
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const entries = await invoke('list_dead_letter_syncs');
+/// entries.forEach(e => console.log(e.entityType, e.entityId, e.lastError));
+/// ```
+#[tauri::command]
+pub async fn list_dead_letter_syncs(
+    service: SingleSyncSvc<'_>,
+) -> std::result::Result<Vec<SyncQueueEntry>, String> {
+    service.list_dead_letters()
+        .map_err(|e| {
+            log::error!("[commands/sync.rs::list_dead_letter_syncs] 查询失败: {}", e);
+            e.to_string()
+        })
+}
+
+/// 手动重试死信队列中的单项同步
+///
+/// 重试次数清零后立即同步一次，失败时重新进入自动重试流程
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const report = await invoke('retry_dead_letter', { id: entry.id });
+/// ```
+#[tauri::command]
+pub async fn retry_dead_letter(
+    id: i64,
+    service: SingleSyncSvc<'_>,
+) -> std::result::Result<SyncReport, String> {
+    log::info!("[commands/sync.rs::retry_dead_letter] 重试死信条目: id={}", id);
+
+    service.retry_dead_letter(id)
+        .await
+        .map_err(|e| {
+            log::error!("[commands/sync.rs::retry_dead_letter] 重试失败: id={}, error={}", id, e);
+            e.to_string()
+        })
+}
+
+/// 获取电源感知的自动同步状态
+///
+/// 返回电池电量、是否使用电池供电、网络是否按流量计费，以及根据
+/// `pauseSyncOnLowBattery` / `pauseSyncOnMetered` 设置自动同步当前是否会被推迟。
+/// 平台不支持的字段为 null，此时不会推迟同步
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const state = await invoke('get_power_sync_state');
+/// if (!state.syncAllowed) {
+///   console.log('自动同步已推迟:', state.pauseReason);
+/// }
+/// ```
+#[tauri::command]
+pub async fn get_power_sync_state(
+    auto_sync: AutoSyncSvc<'_>,
+) -> std::result::Result<PowerSyncState, String> {
+    auto_sync.get_power_sync_state()
+        .map_err(|e| {
+            log::error!("[commands/sync.rs::get_power_sync_state] 获取失败: {}", e);
+            e.to_string()
+        })
+}
+
+/// 获取服务器限制（笔记长度、快照数量等）
+///
+/// 默认使用 1 小时内的缓存，`forceRefresh` 为 true 时重新请求服务器
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const limits = await invoke('get_server_limits', { forceRefresh: false });
+/// if (limits.maxNoteLength > 0 && content.length > limits.maxNoteLength) {
+///   // 提示用户
+/// }
+/// ```
+#[tauri::command]
+pub async fn get_server_limits(
+    force_refresh: Option<bool>,
+    service: SyncSvc<'_>,
+) -> std::result::Result<ServerLimits, String> {
+    service.fetch_server_limits(force_refresh.unwrap_or(false))
+        .await
+        .map_err(|e| {
+            log::error!("[commands/sync.rs::get_server_limits] 获取失败: {}", e);
+            e.to_string()
+        })
+}
+
+/// 获取未处理的笔记冲突
+///
+/// 每条冲突同时包含本地版本和服务器版本的内容，可用于并排对比或合并
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const conflicts = await invoke('get_pending_conflicts');
+/// for (const c of conflicts) {
+///   showDiff(c.localContent, c.serverContent);
+/// }
+/// ```
+#[tauri::command]
+pub async fn get_pending_conflicts(
+    service: SyncSvc<'_>,
+) -> std::result::Result<Vec<PendingConflict>, String> {
+    service.get_pending_conflicts()
+        .map_err(|e| {
+            log::error!("[commands/sync.rs::get_pending_conflicts] 获取失败: {}", e);
+            e.to_string()
+        })
+}
+
+/// 手动合并解决笔记冲突
+///
+/// 写入合并后的内容并删除冲突副本，随后立即同步该笔记以推送合并结果。
+/// 推送失败时笔记保持为待同步状态，由重试队列或下次同步处理
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const note = await invoke('resolve_note_conflict', {
+///   noteId: conflict.noteId,
+///   mergedContent: editor.getValue(),
+/// });
+/// ```
+#[tauri::command]
+pub async fn resolve_note_conflict(
+    note_id: String,
+    merged_content: String,
+    service: SyncSvc<'_>,
+    single_sync: SingleSyncSvc<'_>,
+) -> std::result::Result<Note, String> {
+    log::info!("[commands/sync.rs::resolve_note_conflict] 解决笔记冲突: {}", note_id);
+
+    let note = service.resolve_note_conflict(&note_id, &merged_content)
+        .map_err(|e| {
+            log::error!("[commands/sync.rs::resolve_note_conflict] 写入合并结果失败: {}", e);
+            e.to_string()
+        })?;
+
+    if let Err(e) = single_sync.sync_or_enqueue("note", &note_id).await {
+        log::warn!("[commands/sync.rs::resolve_note_conflict] 推送合并结果失败，稍后重试: {}", e);
+    }
+
+    Ok(note)
+}
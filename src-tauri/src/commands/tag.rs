@@ -1,5 +1,5 @@
 use crate::services::TagService;
-use crate::models::{Tag, CreateTagRequest, UpdateTagRequest, NoteTagRequest};
+use crate::models::{Tag, TagColorInconsistency, SimilarTagGroup, TagMergeResult, CreateTagRequest, UpdateTagRequest, NoteTagRequest};
 use tauri::State;
 
 type TagSvc<'a> = State<'a, TagService>;
@@ -207,3 +207,120 @@ pub async fn permanently_delete_tags(
             count
         })
 }
+
+/// 查找跨工作空间颜色不一致的同名标签
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const groups = await invoke('find_tag_color_inconsistencies');
+/// groups.forEach(g => console.log(g.name, g.tags.map(t => t.color)));
+/// ```
+#[tauri::command]
+pub async fn find_tag_color_inconsistencies(
+    service: TagSvc<'_>,
+) -> std::result::Result<Vec<TagColorInconsistency>, String> {
+    log::info!("[commands/tag.rs::find_tag_color_inconsistencies] 查找颜色不一致的标签");
+
+    service.find_color_inconsistencies()
+        .map_err(|e| {
+            log::error!("[commands/tag.rs::find_tag_color_inconsistencies] 查找失败: {}", e);
+            e.to_string()
+        })
+        .inspect(|groups| {
+            log::info!("[commands/tag.rs::find_tag_color_inconsistencies] 查找成功: count={}", groups.len());
+        })
+}
+
+/// 统一同名标签的颜色（跨所有工作空间）
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const count = await invoke('normalize_tag_colors', { name: '工作', color: '#3b82f6' });
+/// ```
+#[tauri::command]
+pub async fn normalize_tag_colors(
+    name: String,
+    color: String,
+    service: TagSvc<'_>,
+) -> std::result::Result<usize, String> {
+    log::info!("[commands/tag.rs::normalize_tag_colors] 统一标签颜色: name={}, color={}", name, color);
+
+    service.normalize_tag_colors(&name, &color)
+        .map_err(|e| {
+            log::error!("[commands/tag.rs::normalize_tag_colors] 统一失败: name={}, error={}", name, e);
+            e.to_string()
+        })
+        .inspect(|count| {
+            log::info!("[commands/tag.rs::normalize_tag_colors] 统一成功: count={}", count);
+        })
+}
+
+/// 查找工作空间内名称相似的标签（如 "工作"/"工作 "、"Work"/"work"）
+///
+/// 每组给出建议保留的标签 `suggestedTargetId`，`threshold` 默认 0.8
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const groups = await invoke('find_similar_tags', { workspaceId: 'xxx', threshold: 0.8 });
+/// groups.forEach(g => console.log(g.suggestedTargetId, g.tags.map(t => t.tag.name)));
+/// ```
+#[tauri::command]
+pub async fn find_similar_tags(
+    workspace_id: String,
+    threshold: Option<f64>,
+    service: TagSvc<'_>,
+) -> std::result::Result<Vec<SimilarTagGroup>, String> {
+    log::info!(
+        "[commands/tag.rs::find_similar_tags] 查找相似标签: workspace_id={}, threshold={:?}",
+        workspace_id,
+        threshold
+    );
+
+    service.find_similar_tags(&workspace_id, threshold)
+        .map_err(|e| {
+            log::error!("[commands/tag.rs::find_similar_tags] 查找失败: {}", e);
+            e.to_string()
+        })
+        .inspect(|groups| {
+            log::info!("[commands/tag.rs::find_similar_tags] 查找成功: groups={}", groups.len());
+        })
+}
+
+/// 合并标签（如 "todo" 合并到 "TODO"）
+///
+/// 源标签的笔记关联转移到目标标签后软删除源标签，返回转移的关联数量
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const result = await invoke('merge_tags', { sourceTagId: 'tag-a', targetTagId: 'tag-b' });
+/// console.log(result.movedRelations, result.alreadyTagged);
+/// ```
+#[tauri::command]
+pub async fn merge_tags(
+    source_tag_id: String,
+    target_tag_id: String,
+    service: TagSvc<'_>,
+) -> std::result::Result<TagMergeResult, String> {
+    log::info!(
+        "[commands/tag.rs::merge_tags] 合并标签: source_tag_id={}, target_tag_id={}",
+        source_tag_id,
+        target_tag_id
+    );
+
+    service.merge_tags(&source_tag_id, &target_tag_id)
+        .map_err(|e| {
+            log::error!("[commands/tag.rs::merge_tags] 合并失败: source_tag_id={}, error={}", source_tag_id, e);
+            e.to_string()
+        })
+        .inspect(|result| {
+            log::info!(
+                "[commands/tag.rs::merge_tags] 合并成功: moved={}, already_tagged={}",
+                result.moved_relations,
+                result.already_tagged
+            );
+        })
+}
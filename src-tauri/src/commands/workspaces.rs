@@ -1,5 +1,7 @@
-use crate::models::{Workspace, CreateWorkspaceRequest, UpdateWorkspaceRequest};
-use crate::services::{WorkspaceService, AutoSyncService, workspace_service::MigrateResult};
+use crate::models::{Workspace, WorkspaceCheckpoint, CreateWorkspaceRequest, UpdateWorkspaceRequest, StorageStats};
+use crate::services::{WorkspaceService, AutoSyncService, workspace_service::{MigrateResult, OrphanMigrationPreview, BundleExportResult, CheckpointRestoreResult, WorkspaceDuplicateResult}};
+use std::fs::File;
+use std::io::BufWriter;
 use tauri::State;
 
 /// Workspace service 类型别名
@@ -174,4 +176,292 @@ pub async fn migrate_orphan_data_to_workspace(
         })
 }
 
+/// 预览孤立数据迁移（只读）
+///
+/// 列出 workspace_id 为 NULL 的笔记、文件夹、标签，以及快照和标签关联数量
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const preview = await invoke('preview_orphan_migration');
+/// console.log(`${preview.notes.length} 篇笔记待迁移`);
+/// ```
+#[tauri::command]
+pub async fn preview_orphan_migration(
+    service: WorkspaceSvc<'_>,
+) -> std::result::Result<OrphanMigrationPreview, String> {
+    log::info!("[commands/workspaces.rs::preview_orphan_migration] 预览孤立数据迁移");
+
+    service
+        .preview_orphan_migration()
+        .map_err(|e| {
+            log::error!("[commands/workspaces.rs::preview_orphan_migration] 预览失败: {}", e);
+            e.to_string()
+        })
+        .inspect(|preview| {
+            log::info!(
+                "[commands/workspaces.rs::preview_orphan_migration] 预览成功: notes={}, folders={}, tags={}, snapshots={}",
+                preview.notes.len(),
+                preview.folders.len(),
+                preview.tags.len(),
+                preview.snapshots
+            );
+        })
+}
+
+/// 迁移孤立数据到指定工作空间（支持试运行）
+///
+/// 目标工作空间必须属于当前账号；`dryRun` 为 true 时只返回将要迁移的数量，省略时直接迁移。
+/// 登录时会自动把孤立数据迁移到默认工作空间，此命令用于迁移到其他工作空间或重试失败的迁移
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const plan = await invoke('migrate_orphan_data', { workspaceId: 'xxx', dryRun: true });
+/// const result = await invoke('migrate_orphan_data', { workspaceId: 'xxx' });
+/// console.log(`已迁移 ${result.notes} 篇笔记`);
+/// ```
+#[tauri::command]
+pub async fn migrate_orphan_data(
+    workspace_id: String,
+    dry_run: Option<bool>,
+    service: WorkspaceSvc<'_>,
+) -> std::result::Result<MigrateResult, String> {
+    let dry_run = dry_run.unwrap_or(false);
+    log::info!(
+        "[commands/workspaces.rs::migrate_orphan_data] 迁移孤立数据: workspace_id={}, dry_run={}",
+        workspace_id,
+        dry_run
+    );
+
+    service
+        .migrate_orphan_data(&workspace_id, dry_run)
+        .map_err(|e| {
+            log::error!("[commands/workspaces.rs::migrate_orphan_data] 迁移失败: {}", e);
+            e.to_string()
+        })
+        .inspect(|result| {
+            log::info!(
+                "[commands/workspaces.rs::migrate_orphan_data] 完成: dry_run={}, notes={}, folders={}, tags={}, snapshots={}",
+                dry_run,
+                result.notes,
+                result.folders,
+                result.tags,
+                result.snapshots
+            );
+        })
+}
+
+/// 流式导出工作空间到文件
+///
+/// 笔记按页写入，不会一次性加载到内存，适合大型工作空间
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const result = await invoke('export_workspace_bundle_streaming', {
+///   workspaceId: 'xxx',
+///   path: '/home/user/backup.json',
+/// });
+/// console.log(`已导出 ${result.notes} 篇笔记`);
+/// ```
+#[tauri::command]
+pub async fn export_workspace_bundle_streaming(
+    workspace_id: String,
+    path: String,
+    service: WorkspaceSvc<'_>,
+) -> std::result::Result<BundleExportResult, String> {
+    log::info!(
+        "[commands/workspaces.rs::export_workspace_bundle_streaming] 导出工作空间: workspace_id={}, path={}",
+        workspace_id,
+        path
+    );
+
+    let file = File::create(&path).map_err(|e| {
+        log::error!("[commands/workspaces.rs::export_workspace_bundle_streaming] 创建文件失败: {}", e);
+        format!("创建导出文件失败: {}", e)
+    })?;
+
+    service
+        .export_bundle_streaming(&workspace_id, BufWriter::new(file))
+        .map_err(|e| {
+            log::error!("[commands/workspaces.rs::export_workspace_bundle_streaming] 导出失败: {}", e);
+            e.to_string()
+        })
+        .inspect(|result| {
+            log::info!(
+                "[commands/workspaces.rs::export_workspace_bundle_streaming] 导出成功: notes={}",
+                result.notes
+            );
+        })
+}
+
+/// 创建工作空间检查点
+///
+/// 保存工作空间全部笔记、文件夹、标签的压缩副本；
+/// 每个工作空间最多保留 10 个检查点，总大小不超过 100MB，超出时自动删除最旧的
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const checkpoint = await invoke('create_workspace_checkpoint', {
+///   workspaceId: 'xxx',
+///   name: '批量整理前',
+/// });
+/// ```
+#[tauri::command]
+pub async fn create_workspace_checkpoint(
+    workspace_id: String,
+    name: String,
+    service: WorkspaceSvc<'_>,
+) -> std::result::Result<WorkspaceCheckpoint, String> {
+    log::info!(
+        "[commands/workspaces.rs::create_workspace_checkpoint] 创建检查点: workspace_id={}, name={}",
+        workspace_id,
+        name
+    );
 
+    service
+        .create_checkpoint(&workspace_id, &name)
+        .map_err(|e| {
+            log::error!("[commands/workspaces.rs::create_workspace_checkpoint] 创建失败: {}", e);
+            e.to_string()
+        })
+        .inspect(|checkpoint| {
+            log::info!(
+                "[commands/workspaces.rs::create_workspace_checkpoint] 创建成功: id={}, size={}",
+                checkpoint.id,
+                checkpoint.size_bytes
+            );
+        })
+}
+
+/// 列出工作空间的检查点（最新的在前）
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const checkpoints = await invoke('list_checkpoints', { workspaceId: 'xxx' });
+/// ```
+#[tauri::command]
+pub async fn list_checkpoints(
+    workspace_id: String,
+    service: WorkspaceSvc<'_>,
+) -> std::result::Result<Vec<WorkspaceCheckpoint>, String> {
+    log::info!("[commands/workspaces.rs::list_checkpoints] 列出检查点: workspace_id={}", workspace_id);
+
+    service
+        .list_checkpoints(&workspace_id)
+        .map_err(|e| {
+            log::error!("[commands/workspaces.rs::list_checkpoints] 列出失败: {}", e);
+            e.to_string()
+        })
+}
+
+/// 将工作空间恢复到检查点
+///
+/// 恢复前会自动为当前状态创建检查点（`backupCheckpointId`），
+/// 恢复产生的变化会在下次同步时推送到服务器
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const result = await invoke('restore_checkpoint', { checkpointId: 'xxx' });
+/// console.log(`已恢复，恢复前的状态保存在 ${result.backupCheckpointId}`);
+/// ```
+#[tauri::command]
+pub async fn restore_checkpoint(
+    checkpoint_id: String,
+    service: WorkspaceSvc<'_>,
+) -> std::result::Result<CheckpointRestoreResult, String> {
+    log::info!("[commands/workspaces.rs::restore_checkpoint] 恢复检查点: checkpoint_id={}", checkpoint_id);
+
+    service
+        .restore_checkpoint(&checkpoint_id)
+        .map_err(|e| {
+            log::error!("[commands/workspaces.rs::restore_checkpoint] 恢复失败: {}", e);
+            e.to_string()
+        })
+        .inspect(|result| {
+            log::info!(
+                "[commands/workspaces.rs::restore_checkpoint] 恢复成功: notes={}, backup_id={}",
+                result.notes,
+                result.backup_checkpoint_id
+            );
+        })
+}
+
+/// 复制工作空间（用作模板）
+///
+/// 复制文件夹层级和标签，`includeNotes` 为 true 时同时复制笔记；
+/// 复制出的数据会在下次同步时推送到服务器
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const result = await invoke('duplicate_workspace', {
+///   workspaceId: 'xxx',
+///   newName: '项目模板',
+///   includeNotes: false,
+/// });
+/// console.log(`已复制 ${result.folders} 个文件夹、${result.tags} 个标签`);
+/// ```
+#[tauri::command]
+pub async fn duplicate_workspace(
+    workspace_id: String,
+    new_name: String,
+    include_notes: bool,
+    service: WorkspaceSvc<'_>,
+) -> std::result::Result<WorkspaceDuplicateResult, String> {
+    log::info!(
+        "[commands/workspaces.rs::duplicate_workspace] 复制工作空间: workspace_id={}, include_notes={}",
+        workspace_id,
+        include_notes
+    );
+
+    service
+        .duplicate_workspace(&workspace_id, &new_name, include_notes)
+        .map_err(|e| {
+            log::error!("[commands/workspaces.rs::duplicate_workspace] 复制失败: {}", e);
+            e.to_string()
+        })
+        .inspect(|result| {
+            log::info!(
+                "[commands/workspaces.rs::duplicate_workspace] 复制成功: id={}, folders={}, tags={}, notes={}",
+                result.workspace.id,
+                result.folders,
+                result.tags,
+                result.notes
+            );
+        })
+}
+
+/// 获取存储统计（各工作空间的笔记、文件夹、标签、快照和回收站数量，以及近似大小）
+///
+/// ## 使用示例
+///
+/// ```typescript
+/// const stats = await invoke('get_storage_stats');
+/// console.log(`共 ${stats.total.notes} 篇笔记，约 ${stats.total.approxBytes} 字节`);
+/// ```
+#[tauri::command]
+pub async fn get_storage_stats(
+    service: WorkspaceSvc<'_>,
+) -> std::result::Result<StorageStats, String> {
+    log::info!("[commands/workspaces.rs::get_storage_stats] 获取存储统计");
+
+    service
+        .get_storage_stats()
+        .map_err(|e| {
+            log::error!("[commands/workspaces.rs::get_storage_stats] 获取失败: {}", e);
+            e.to_string()
+        })
+        .inspect(|stats| {
+            log::info!(
+                "[commands/workspaces.rs::get_storage_stats] 获取成功: workspaces={}, notes={}, approx_bytes={}",
+                stats.workspaces.len(),
+                stats.total.notes,
+                stats.total.approx_bytes
+            );
+        })
+}
@@ -25,3 +25,26 @@ pub fn init_db_pool(db_path: &str) -> Result<DbPool> {
     log::info!("Database initialized at: {}", db_path);
     Ok(pool)
 }
+
+/// 创建测试用的内存数据库（已初始化 schema 并执行全部迁移）
+///
+/// 每次调用创建一个独立的共享缓存内存数据库，池中的连接访问同一份数据，
+/// 服务内部嵌套获取连接时不会等待超时
+#[cfg(test)]
+pub fn memory_pool() -> DbPool {
+    use r2d2_sqlite::rusqlite::OpenFlags;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+    let uri = format!("file:memory_pool_{}?mode=memory&cache=shared", NEXT_ID.fetch_add(1, Ordering::Relaxed));
+    let manager = SqliteConnectionManager::file(uri).with_flags(
+        OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE | OpenFlags::SQLITE_OPEN_URI,
+    );
+    let pool = Pool::builder()
+        .max_size(4)
+        .build(manager)
+        .expect("Failed to create memory pool");
+    schema::init_schema(&pool.get().unwrap()).unwrap();
+    crate::services::MigrationService::new(pool.clone()).migrate_to_latest().unwrap();
+    pool
+}
@@ -3,3 +3,5 @@ pub mod schema;
 pub mod repositories;
 
 pub use connection::{DbPool, init_db_pool};
+#[cfg(test)]
+pub use connection::memory_pool;
@@ -0,0 +1,142 @@
+use crate::models::{Attachment, AttachmentListItem, ServerLimits};
+use crate::models::error::{Result, AppError};
+use crate::database::DbPool;
+use r2d2_sqlite::rusqlite::{self as rusqlite, params, OptionalExtension, Row};
+
+/// 附件数据访问层
+#[derive(Clone)]
+pub struct AttachmentRepository {
+    pool: DbPool,
+}
+
+impl AttachmentRepository {
+    /// 统一的 SQL 查询字段列表（顺序与 from_row 一致）
+    const SELECT_FIELDS: &'static str =
+        "id, note_id, workspace_id, filename, mime_type, size_bytes, data, created_at, updated_at,
+         is_deleted, deleted_at, server_ver, is_dirty, last_synced_at";
+
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    fn from_row(row: &Row) -> rusqlite::Result<Attachment> {
+        Ok(Attachment {
+            id: row.get(0)?,
+            note_id: row.get(1)?,
+            workspace_id: row.get(2)?,
+            filename: row.get(3)?,
+            mime_type: row.get(4)?,
+            size_bytes: row.get(5)?,
+            data: row.get(6)?,
+            created_at: row.get(7)?,
+            updated_at: row.get(8)?,
+            is_deleted: row.get(9)?,
+            deleted_at: row.get(10)?,
+            server_ver: row.get(11)?,
+            is_dirty: row.get(12)?,
+            last_synced_at: row.get(13)?,
+        })
+    }
+
+    /// 创建附件
+    pub fn create(&self, attachment: &Attachment) -> Result<Attachment> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "INSERT INTO attachments (id, note_id, workspace_id, filename, mime_type, size_bytes, data,
+                                      created_at, updated_at, is_deleted, deleted_at, server_ver, is_dirty, last_synced_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            params![
+                &attachment.id,
+                &attachment.note_id,
+                &attachment.workspace_id,
+                &attachment.filename,
+                &attachment.mime_type,
+                attachment.size_bytes,
+                &attachment.data,
+                attachment.created_at,
+                attachment.updated_at,
+                attachment.is_deleted,
+                attachment.deleted_at,
+                attachment.server_ver,
+                attachment.is_dirty,
+                attachment.last_synced_at,
+            ],
+        )?;
+
+        Ok(attachment.clone())
+    }
+
+    /// 根据 ID 获取附件（不含已删除的附件）
+    pub fn find_by_id(&self, id: &str) -> Result<Option<Attachment>> {
+        let conn = self.pool.get()?;
+        conn.query_row(
+            &format!("SELECT {} FROM attachments WHERE id = ?1 AND is_deleted = 0", Self::SELECT_FIELDS),
+            params![id],
+            Self::from_row,
+        ).optional().map_err(AppError::Database)
+    }
+
+    /// 列出笔记的附件（不含文件数据和已删除的附件，按创建时间排序）
+    pub fn find_by_note(&self, note_id: &str) -> Result<Vec<AttachmentListItem>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, note_id, filename, mime_type, size_bytes, created_at
+             FROM attachments
+             WHERE note_id = ?1 AND is_deleted = 0
+             ORDER BY created_at ASC, rowid ASC"
+        )?;
+
+        let attachments = stmt.query_map(params![note_id], |row| {
+            Ok(AttachmentListItem {
+                id: row.get(0)?,
+                note_id: row.get(1)?,
+                filename: row.get(2)?,
+                mime_type: row.get(3)?,
+                size_bytes: row.get(4)?,
+                created_at: row.get(5)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(attachments)
+    }
+
+    /// 软删除附件（标记为脏数据，下次同步时通知服务器）
+    ///
+    /// ## 返回
+    ///
+    /// 附件存在且未删除时返回 true
+    pub fn soft_delete(&self, id: &str) -> Result<bool> {
+        let conn = self.pool.get()?;
+        let now = chrono::Utc::now().timestamp();
+        let updated = conn.execute(
+            "UPDATE attachments SET is_deleted = 1, deleted_at = ?1, updated_at = ?1, is_dirty = 1
+             WHERE id = ?2 AND is_deleted = 0",
+            params![now, id],
+        )?;
+
+        Ok(updated > 0)
+    }
+
+    /// 获取笔记所属的工作空间 ID（笔记不存在或已删除时返回 None）
+    pub fn find_note_workspace_id(&self, note_id: &str) -> Result<Option<Option<String>>> {
+        let conn = self.pool.get()?;
+        conn.query_row(
+            "SELECT workspace_id FROM notes WHERE id = ?1 AND is_deleted = 0",
+            params![note_id],
+            |row| row.get(0),
+        ).optional().map_err(AppError::Database)
+    }
+
+    /// 读取缓存的服务器限制（未缓存或解析失败时返回 None）
+    pub fn find_cached_server_limits(&self) -> Result<Option<ServerLimits>> {
+        let conn = self.pool.get()?;
+        let json: Option<String> = conn.query_row(
+            "SELECT value FROM settings WHERE key = 'server_limits'",
+            [],
+            |row| row.get(0),
+        ).optional()?;
+
+        Ok(json.and_then(|json| serde_json::from_str(&json).ok()))
+    }
+}
@@ -1,4 +1,4 @@
-use crate::models::{EditorSettings, UpdateEditorSettingsRequest, error::{Result, AppError}};
+use crate::models::{EditorSettings, UpdateEditorSettingsRequest, DEFAULT_EDITOR_PROFILE_ID, error::{Result, AppError}};
 use crate::database::DbPool;
 use r2d2_sqlite::rusqlite::{self as rusqlite, Row, params};
 
@@ -7,59 +7,103 @@ pub struct EditorSettingsRepository {
 }
 
 impl EditorSettingsRepository {
+    const SELECT_FIELDS: &'static str = "id, name, is_active, content_font_family, content_font_size, content_font_weight,
+                    content_line_height, heading_font_family, heading_font_weight,
+                    code_font_family, code_font_size, markdown_preview_style, updated_at";
+
     pub fn new(pool: DbPool) -> Self {
         Self { pool }
     }
 
-    /// 获取编辑器设置（单例模式，id = 1）
-    pub fn find_by_id(&self) -> Result<Option<EditorSettings>> {
-        let conn = self.pool.get()?;
-        let mut stmt = conn.prepare(
-            "SELECT id, content_font_family, content_font_size, content_font_weight,
-                    content_line_height, heading_font_family, heading_font_weight,
-                    code_font_family, code_font_size, markdown_preview_style, updated_at
-             FROM editor_settings WHERE id = 1"
-        )?;
+    fn from_row(row: &Row) -> rusqlite::Result<EditorSettings> {
+        Ok(EditorSettings {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            is_active: row.get(2)?,
+            content_font_family: row.get(3)?,
+            content_font_size: row.get(4)?,
+            content_font_weight: row.get(5)?,
+            content_line_height: row.get(6)?,
+            heading_font_family: row.get(7)?,
+            heading_font_weight: row.get(8)?,
+            code_font_family: row.get(9)?,
+            code_font_size: row.get(10)?,
+            markdown_preview_style: row.get(11)?,
+            updated_at: row.get(12)?,
+        })
+    }
 
-        let result = stmt.query_row([], |row: &Row| {
-            Ok(EditorSettings {
-                id: row.get(0)?,
-                content_font_family: row.get(1)?,
-                content_font_size: row.get(2)?,
-                content_font_weight: row.get(3)?,
-                content_line_height: row.get(4)?,
-                heading_font_family: row.get(5)?,
-                heading_font_weight: row.get(6)?,
-                code_font_family: row.get(7)?,
-                code_font_size: row.get(8)?,
-                markdown_preview_style: row.get(9)?,
-                updated_at: row.get(10)?,
-            })
-        });
+    /// 获取当前激活的编辑器配置
+    ///
+    /// 默认配置不存在时自动创建；没有激活的配置时激活默认配置
+    pub fn find_active(&self) -> Result<Option<EditorSettings>> {
+        let conn = self.pool.get()?;
+        let result = conn.query_row(
+            &format!("SELECT {} FROM editor_settings WHERE is_active = 1 ORDER BY id LIMIT 1", Self::SELECT_FIELDS),
+            [],
+            Self::from_row,
+        );
 
         match result {
             Ok(settings) => Ok(Some(settings)),
             Err(rusqlite::Error::QueryReturnedNoRows) => {
-                // 如果不存在，创建默认设置
-                let default_settings = EditorSettings::default();
-                self.create(&default_settings)?;
-                Ok(Some(default_settings))
+                drop(conn);
+                match self.find_profile(DEFAULT_EDITOR_PROFILE_ID)? {
+                    Some(_) => self.activate(DEFAULT_EDITOR_PROFILE_ID).map(Some),
+                    None => {
+                        // 如果不存在，创建默认设置
+                        let default_settings = EditorSettings::default();
+                        self.create(&default_settings)?;
+                        Ok(Some(default_settings))
+                    }
+                }
             }
             Err(e) => Err(AppError::Database(e)),
         }
     }
 
+    /// 根据 ID 获取编辑器配置
+    pub fn find_profile(&self, id: i32) -> Result<Option<EditorSettings>> {
+        let conn = self.pool.get()?;
+        let result = conn.query_row(
+            &format!("SELECT {} FROM editor_settings WHERE id = ?1", Self::SELECT_FIELDS),
+            params![id],
+            Self::from_row,
+        );
+
+        match result {
+            Ok(settings) => Ok(Some(settings)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(AppError::Database(e)),
+        }
+    }
+
+    /// 获取所有编辑器配置（默认配置在前，其余按创建顺序）
+    pub fn list_profiles(&self) -> Result<Vec<EditorSettings>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            &format!("SELECT {} FROM editor_settings ORDER BY id", Self::SELECT_FIELDS)
+        )?;
+
+        let profiles = stmt.query_map([], Self::from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(profiles)
+    }
+
     /// 创建编辑器设置
     fn create(&self, settings: &EditorSettings) -> Result<EditorSettings> {
         let conn = self.pool.get()?;
         conn.execute(
             "INSERT OR REPLACE INTO editor_settings (
-                id, content_font_family, content_font_size, content_font_weight,
+                id, name, is_active, content_font_family, content_font_size, content_font_weight,
                 content_line_height, heading_font_family, heading_font_weight,
                 code_font_family, code_font_size, markdown_preview_style, updated_at
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
             params![
                 settings.id,
+                &settings.name,
+                settings.is_active,
                 &settings.content_font_family,
                 settings.content_font_size,
                 settings.content_font_weight,
@@ -76,14 +120,93 @@ impl EditorSettingsRepository {
         Ok(settings.clone())
     }
 
+    /// 以 `base` 的各项设置新建一个命名配置（新配置不激活）
+    pub fn create_profile(&self, name: &str, base: &EditorSettings) -> Result<EditorSettings> {
+        let conn = self.pool.get()?;
+        let now = chrono::Utc::now().timestamp();
+        conn.execute(
+            "INSERT INTO editor_settings (
+                name, is_active, content_font_family, content_font_size, content_font_weight,
+                content_line_height, heading_font_family, heading_font_weight,
+                code_font_family, code_font_size, markdown_preview_style, updated_at
+            ) VALUES (?1, 0, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                name,
+                &base.content_font_family,
+                base.content_font_size,
+                base.content_font_weight,
+                base.content_line_height,
+                &base.heading_font_family,
+                base.heading_font_weight,
+                &base.code_font_family,
+                base.code_font_size,
+                &base.markdown_preview_style,
+                now,
+            ],
+        )?;
+
+        Ok(EditorSettings {
+            id: conn.last_insert_rowid() as i32,
+            name: name.to_string(),
+            is_active: false,
+            updated_at: now,
+            ..base.clone()
+        })
+    }
+
+    /// 删除编辑器配置
+    ///
+    /// 删除的是当前激活的配置时，改为激活默认配置
+    ///
+    /// ## 返回
+    ///
+    /// 配置存在并被删除时返回 true
+    pub fn delete_profile(&self, id: i32) -> Result<bool> {
+        let conn = self.pool.get()?;
+        let tx = conn.unchecked_transaction()?;
+
+        let deleted = tx.execute("DELETE FROM editor_settings WHERE id = ?1", params![id])?;
+        tx.execute(
+            "UPDATE editor_settings SET is_active = 1
+             WHERE id = ?1 AND NOT EXISTS (SELECT 1 FROM editor_settings WHERE is_active = 1)",
+            params![DEFAULT_EDITOR_PROFILE_ID],
+        )?;
+
+        tx.commit()?;
+        Ok(deleted > 0)
+    }
+
+    /// 激活编辑器配置（其余配置取消激活）
+    pub fn activate(&self, id: i32) -> Result<EditorSettings> {
+        let mut profile = self.find_profile(id)?
+            .ok_or_else(|| AppError::NotFound(format!("编辑器配置 {}", id)))?;
+
+        let conn = self.pool.get()?;
+        conn.execute(
+            "UPDATE editor_settings SET is_active = (id = ?1)",
+            params![id],
+        )?;
+
+        profile.is_active = true;
+        Ok(profile)
+    }
+
     /// 更新编辑器设置
+    ///
+    /// `req.profile_id` 为空时更新当前激活的配置
     pub fn update(&self, req: &UpdateEditorSettingsRequest) -> Result<EditorSettings> {
         // 获取当前设置
-        let current = self.find_by_id()?.ok_or(AppError::Internal("Editor settings not found".to_string()))?;
+        let current = match req.profile_id {
+            Some(id) => self.find_profile(id)?
+                .ok_or_else(|| AppError::NotFound(format!("编辑器配置 {}", id)))?,
+            None => self.find_active()?.ok_or(AppError::Internal("Editor settings not found".to_string()))?,
+        };
 
         // 构建更新后的设置
         let updated = EditorSettings {
-            id: 1,
+            id: current.id,
+            name: current.name,
+            is_active: current.is_active,
             content_font_family: req.content_font_family.clone().unwrap_or(current.content_font_family),
             content_font_size: req.content_font_size.unwrap_or(current.content_font_size),
             content_font_weight: req.content_font_weight.unwrap_or(current.content_font_weight),
@@ -109,7 +232,7 @@ impl EditorSettingsRepository {
                 code_font_size = ?8,
                 markdown_preview_style = ?9,
                 updated_at = ?10
-            WHERE id = 1",
+            WHERE id = ?11",
             params![
                 &updated.content_font_family,
                 updated.content_font_size,
@@ -121,6 +244,7 @@ impl EditorSettingsRepository {
                 updated.code_font_size,
                 &updated.markdown_preview_style,
                 updated.updated_at,
+                updated.id,
             ],
         )?;
 
@@ -2,6 +2,7 @@ use crate::models::Folder;
 use crate::database::DbPool;
 use crate::models::error::{Result, AppError};
 use r2d2_sqlite::rusqlite::params;
+use std::collections::HashMap;
 
 /// 文件夹树节点（用于构建树形结构）
 #[derive(Debug, Clone)]
@@ -25,6 +26,12 @@ impl FolderRepository {
         "id, name, parent_id, icon, color, sort_order, workspace_id, created_at, updated_at,
          is_deleted, deleted_at, server_ver, is_dirty, last_synced_at";
 
+    /// 级联删除的时间窗口（秒）
+    ///
+    /// 删除文件夹时子文件夹和笔记使用同一个 `deleted_at`；同步回来的数据可能相差几秒，
+    /// 恢复时将该窗口内删除的数据视为同一次删除
+    const CASCADE_DELETE_WINDOW_SECS: i64 = 2;
+
     /// 创建新的 FolderRepository 实例
     pub fn new(pool: DbPool) -> Self {
         Self { pool }
@@ -168,38 +175,78 @@ impl FolderRepository {
         }
     }
 
-    /// 恢复已删除的文件夹
+    /// 恢复已删除的文件夹（连同一起被级联删除的子文件夹和笔记）
+    ///
+    /// 只恢复 `deleted_at` 与该文件夹删除时间相差不超过 `CASCADE_DELETE_WINDOW_SECS` 的
+    /// 子文件夹和笔记，即与文件夹同一次删除的数据；用户在此之前单独删除的笔记仍留在回收站。
+    /// 恢复的数据都会标记为需要同步
     pub fn restore(&self, id: &str) -> Result<Folder> {
+        let mut folder = self.find_by_id_include_deleted(id)?
+            .ok_or_else(|| AppError::NotFound(format!("文件夹 {} 未找到", id)))?;
+
+        if !folder.is_deleted {
+            return Ok(folder);
+        }
+
         let conn = self.pool.get()?;
         let now = chrono::Utc::now().timestamp();
+        let deleted_at = folder.deleted_at.unwrap_or(0);
+        let window_start = deleted_at - Self::CASCADE_DELETE_WINDOW_SECS;
+        let window_end = deleted_at + Self::CASCADE_DELETE_WINDOW_SECS;
 
-        // 先获取文件夹信息
-        let folder = self.find_by_id_include_deleted(id)?;
+        // 同一次删除的文件夹树（子文件夹需在删除窗口内，单独删除过的子文件夹及其内容不恢复）；
+        // 使用 UNION 去重，parent_id 意外成环时递归也会结束
+        const RESTORE_TREE: &str = "WITH RECURSIVE folder_tree AS (
+                SELECT id FROM folders WHERE id = ?1
+                UNION
+                SELECT f.id FROM folders f
+                INNER JOIN folder_tree ft ON f.parent_id = ft.id
+                WHERE f.is_deleted = 1 AND f.deleted_at BETWEEN ?2 AND ?3
+            )";
+
+        let tx = conn.unchecked_transaction()?;
+
+        // 1. 先恢复笔记（恢复文件夹后树查询的 is_deleted 条件将不再成立）
+        let restored_notes = tx.execute(
+            &format!(
+                "{RESTORE_TREE}
+                UPDATE notes SET is_deleted = 0, deleted_at = NULL, updated_at = ?4, is_dirty = 1
+                WHERE folder_id IN folder_tree AND is_deleted = 1 AND deleted_at BETWEEN ?2 AND ?3"
+            ),
+            params![id, window_start, window_end, now],
+        )?;
 
-        // 更新为未删除状态
-        conn.execute(
-            "UPDATE folders SET is_deleted = 0, deleted_at = NULL, updated_at = ?, is_dirty = 1 WHERE id = ?",
-            params![now, id],
+        // 2. 恢复文件夹树
+        let restored_folders = tx.execute(
+            &format!(
+                "{RESTORE_TREE}
+                UPDATE folders SET is_deleted = 0, deleted_at = NULL, updated_at = ?4, is_dirty = 1
+                WHERE id IN folder_tree"
+            ),
+            params![id, window_start, window_end, now],
         )?;
 
-        let mut restored_folder = folder.unwrap();
-        restored_folder.is_deleted = false;
-        restored_folder.deleted_at = None;
-        restored_folder.updated_at = now;
-        restored_folder.is_dirty = true;
+        tx.commit()?;
 
-        log::info!("Folder restored: {}", id);
-        Ok(restored_folder)
+        folder.is_deleted = false;
+        folder.deleted_at = None;
+        folder.updated_at = now;
+        folder.is_dirty = true;
+
+        log::info!(
+            "Folder restored: id={}, folders_restored={}, notes_restored={}",
+            id,
+            restored_folders,
+            restored_notes
+        );
+        Ok(folder)
     }
 
     /// 根据 ID 查找文件夹（包括已删除的）
-    fn find_by_id_include_deleted(&self, id: &str) -> Result<Option<Folder>> {
+    pub fn find_by_id_include_deleted(&self, id: &str) -> Result<Option<Folder>> {
         let conn = self.pool.get()?;
         let mut stmt = conn.prepare(
-            "SELECT id, name, parent_id, icon, color, sort_order, created_at, updated_at,
-                    is_deleted, deleted_at, server_ver, is_dirty, last_synced_at
-             FROM folders
-             WHERE id = ?"
+            &format!("SELECT {} FROM folders WHERE id = ?", Self::SELECT_FIELDS)
         )?;
 
         let folder = stmt.query_row(params![id], |row| {
@@ -252,12 +299,12 @@ impl FolderRepository {
         let conn = self.pool.get()?;
         conn.execute(
             "UPDATE folders
-             SET name = ?, parent_id = ?, icon = ?, color = ?, sort_order = ?, updated_at = ?,
-                 is_dirty = ?
+             SET name = ?, parent_id = ?, icon = ?, color = ?, sort_order = ?, workspace_id = ?,
+                 updated_at = ?, is_dirty = ?
              WHERE id = ? AND is_deleted = 0",
             params![
                 folder.name, folder.parent_id, folder.icon, folder.color,
-                folder.sort_order, folder.updated_at, folder.is_dirty as i32, folder.id
+                folder.sort_order, folder.workspace_id, folder.updated_at, folder.is_dirty as i32, folder.id
             ],
         )?;
 
@@ -334,6 +381,43 @@ impl FolderRepository {
         Ok(())
     }
 
+    /// 查询工作空间内每个文件夹的最后修改时间
+    ///
+    /// 取文件夹内未删除笔记的最大 `updated_at`；`include_descendants` 为 true 时
+    /// 同时统计所有子文件夹中的笔记。没有笔记的文件夹返回自身的 `updated_at`。
+    ///
+    /// ## 返回
+    ///
+    /// `folder_id -> updated_at` 映射（单次查询）
+    pub fn last_modified_map(&self, workspace_id: &str, include_descendants: bool) -> Result<HashMap<String, i64>> {
+        let conn = self.pool.get()?;
+
+        let mut stmt = conn.prepare(
+            "WITH RECURSIVE folder_tree(ancestor_id, folder_id) AS (
+                -- 每个文件夹都包含自身
+                SELECT id, id FROM folders WHERE workspace_id = ?1 AND is_deleted = 0
+                -- UNION 去重：parent_id 意外成环时递归也会结束
+                UNION
+                -- 需要统计子孙文件夹时递归展开
+                SELECT ft.ancestor_id, f.id FROM folders f
+                INNER JOIN folder_tree ft ON f.parent_id = ft.folder_id
+                WHERE ?2 = 1 AND f.is_deleted = 0
+            )
+            SELECT f.id, COALESCE(MAX(n.updated_at), f.updated_at)
+            FROM folders f
+            INNER JOIN folder_tree ft ON ft.ancestor_id = f.id
+            LEFT JOIN notes n ON n.folder_id = ft.folder_id AND n.is_deleted = 0
+            GROUP BY f.id"
+        )?;
+
+        let map = stmt.query_map(params![workspace_id, include_descendants], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })?
+        .collect::<std::result::Result<HashMap<_, _>, _>>()?;
+
+        Ok(map)
+    }
+
     /// 查找子文件夹
     pub fn find_children(&self, parent_id: Option<&str>) -> Result<Vec<Folder>> {
         let conn = self.pool.get()?;
@@ -361,37 +445,30 @@ impl FolderRepository {
 
     /// 构建文件夹树
     pub fn find_tree(&self) -> Result<Vec<FolderNode>> {
-        let all_folders = self.find_all()?;
-        let mut folder_map: std::collections::HashMap<String, FolderNode> = std::collections::HashMap::new();
-
-        // 初始化所有节点
-        for folder in all_folders {
-            folder_map.insert(folder.id.clone(), FolderNode {
-                folder,
-                children: Vec::new(),
-            });
-        }
+        Ok(build_tree(self.find_all()?))
+    }
 
-        // 收集所有父节点关系
-        let mut child_parent_relations: Vec<(String, Option<String>)> = Vec::new();
-        for (id, node) in folder_map.iter() {
-            child_parent_relations.push((id.clone(), node.folder.parent_id.clone()));
-        }
+    /// 获取指定工作空间的所有文件夹（不含已删除）
+    pub fn find_by_workspace(&self, workspace_id: &str) -> Result<Vec<Folder>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}
+             FROM folders
+             WHERE is_deleted = 0 AND workspace_id = ?
+             ORDER BY sort_order ASC, created_at ASC",
+            Self::SELECT_FIELDS
+        ))?;
 
-        // 构建树形结构
-        let mut roots = Vec::new();
-        for (child_id, parent_id) in child_parent_relations {
-            let child_node = folder_map.get(&child_id).cloned().unwrap();
-            if let Some(pid) = parent_id {
-                if let Some(parent_node) = folder_map.get_mut(&pid) {
-                    parent_node.children.push(child_node);
-                }
-            } else {
-                roots.push(child_node);
-            }
-        }
+        let folders = stmt.query_map(params![workspace_id], |row| self.row_to_folder(row))?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(AppError::Database)?;
 
-        Ok(roots)
+        Ok(folders)
+    }
+
+    /// 获取指定工作空间的文件夹树
+    pub fn find_tree_by_workspace(&self, workspace_id: &str) -> Result<Vec<FolderNode>> {
+        Ok(build_tree(self.find_by_workspace(workspace_id)?))
     }
 
     /// 获取文件夹路径（从根到当前文件夹）
@@ -521,44 +598,211 @@ impl FolderRepository {
         Ok(())
     }
 
-    /// 清理超过指定天数的软删除文件夹
+    /// 清理指定工作空间中超过指定天数的软删除文件夹
     ///
     /// ## 参数
     ///
+    /// - `workspace_id`: 工作空间 ID（`None` 表示未归属工作空间的数据）
     /// - `days`: 软删除后的保留天数（如 30 天）
     ///
     /// ## 返回
     ///
     /// 返回清理的文件夹数量
-    pub fn purge_old_deleted_folders(&self, days: i64) -> Result<i64> {
+    pub fn purge_old_deleted_folders(&self, workspace_id: Option<&str>, days: i64) -> Result<i64> {
         let conn = self.pool.get()?;
         let cutoff_time = chrono::Utc::now().timestamp() - (days * 86400);
 
         // 先删除这些文件夹下的所有笔记
         let notes_affected = conn.execute(
             "WITH RECURSIVE folder_tree AS (
-                SELECT id FROM folders WHERE is_deleted = 1 AND deleted_at < ?
+                SELECT id FROM folders WHERE is_deleted = 1 AND workspace_id IS ? AND deleted_at < ?
                 UNION ALL
                 SELECT f.id FROM folders f
                 INNER JOIN folder_tree ft ON f.parent_id = ft.id
             )
             DELETE FROM notes WHERE folder_id IN folder_tree",
-            params![cutoff_time],
+            params![workspace_id, cutoff_time],
         ).map_err(AppError::Database)?;
 
         // 再删除文件夹
         let folders_affected = conn.execute(
             "WITH RECURSIVE folder_tree AS (
-                SELECT id FROM folders WHERE is_deleted = 1 AND deleted_at < ?
+                SELECT id FROM folders WHERE is_deleted = 1 AND workspace_id IS ? AND deleted_at < ?
                 UNION ALL
                 SELECT f.id FROM folders f
                 INNER JOIN folder_tree ft ON f.parent_id = ft.id
             )
             DELETE FROM folders WHERE id IN folder_tree",
-            params![cutoff_time],
+            params![workspace_id, cutoff_time],
         ).map_err(AppError::Database)?;
 
-        log::info!("[FolderRepository] 清理旧文件夹: days={}, folders={}, notes={}", days, folders_affected, notes_affected);
+        log::info!("[FolderRepository] 清理旧文件夹: workspace_id={:?}, days={}, folders={}, notes={}", workspace_id, days, folders_affected, notes_affected);
         Ok(folders_affected as i64)
     }
 }
+
+/// 由平铺的文件夹列表构建树
+///
+/// 保持列表中的顺序；父文件夹不在列表中的文件夹（及其子树）不会出现在结果中
+fn build_tree(folders: Vec<Folder>) -> Vec<FolderNode> {
+    let mut children_of: HashMap<Option<String>, Vec<Folder>> = HashMap::new();
+    for folder in folders {
+        children_of.entry(folder.parent_id.clone()).or_default().push(folder);
+    }
+
+    fn attach(parent_id: Option<String>, children_of: &mut HashMap<Option<String>, Vec<Folder>>) -> Vec<FolderNode> {
+        children_of.remove(&parent_id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|folder| {
+                let children = attach(Some(folder.id.clone()), children_of);
+                FolderNode { folder, children }
+            })
+            .collect()
+    }
+
+    attach(None, &mut children_of)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory_repo() -> FolderRepository {
+        let pool = crate::database::memory_pool();
+        FolderRepository::new(pool)
+    }
+
+    #[test]
+    fn test_workspace_id_round_trip() {
+        let repo = memory_repo();
+
+        let mut folder = Folder::new("工作".to_string(), None, None, None, Some("workspace-a".to_string()));
+        repo.create(&folder).unwrap();
+        let loaded = repo.find_by_id(&folder.id).unwrap().unwrap();
+        assert_eq!(loaded.workspace_id.as_deref(), Some("workspace-a"));
+
+        folder.name = "项目".to_string();
+        folder.workspace_id = Some("workspace-b".to_string());
+        repo.update(&folder).unwrap();
+        let loaded = repo.find_by_id(&folder.id).unwrap().unwrap();
+        assert_eq!(loaded.workspace_id.as_deref(), Some("workspace-b"));
+
+        repo.delete(&folder.id).unwrap();
+        let restored = repo.restore(&folder.id).unwrap();
+        assert_eq!(restored.workspace_id.as_deref(), Some("workspace-b"));
+    }
+
+    fn insert_note(repo: &FolderRepository, id: &str, folder_id: &str) {
+        repo.pool.get().unwrap().execute(
+            "INSERT INTO notes (id, title, content, folder_id, created_at, updated_at) VALUES (?1, 't', 'c', ?2, 0, 0)",
+            params![id, folder_id],
+        ).unwrap();
+    }
+
+    fn note_state(repo: &FolderRepository, id: &str) -> (bool, bool) {
+        repo.pool.get().unwrap().query_row(
+            "SELECT is_deleted, is_dirty FROM notes WHERE id = ?",
+            [id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).unwrap()
+    }
+
+    #[test]
+    fn test_restore_brings_back_cascade_deleted_notes_only() {
+        let repo = memory_repo();
+
+        let parent = Folder::new("父".to_string(), None, None, None, None);
+        repo.create(&parent).unwrap();
+        let child = Folder::new("子".to_string(), Some(parent.id.clone()), None, None, None);
+        repo.create(&child).unwrap();
+
+        insert_note(&repo, "in-parent", &parent.id);
+        insert_note(&repo, "in-child", &child.id);
+        insert_note(&repo, "deleted-earlier", &parent.id);
+
+        // 用户先单独删除了一篇笔记
+        repo.pool.get().unwrap().execute(
+            "UPDATE notes SET is_deleted = 1, deleted_at = ? WHERE id = 'deleted-earlier'",
+            [chrono::Utc::now().timestamp() - 3600],
+        ).unwrap();
+
+        repo.delete(&parent.id).unwrap();
+        assert!(note_state(&repo, "in-parent").0);
+        assert!(note_state(&repo, "in-child").0);
+
+        let restored = repo.restore(&parent.id).unwrap();
+        assert!(!restored.is_deleted);
+
+        assert!(repo.find_by_id(&child.id).unwrap().is_some(), "child folder should be restored");
+        assert_eq!(note_state(&repo, "in-parent"), (false, true));
+        assert_eq!(note_state(&repo, "in-child"), (false, true));
+        assert!(note_state(&repo, "deleted-earlier").0, "independently deleted note stays in trash");
+    }
+
+    /// 创建 ws-1 中互为父子的两个文件夹（同步异常可能产生），返回它们的 ID
+    fn create_folder_cycle(repo: &FolderRepository) -> (String, String) {
+        let ws = Some("ws-1".to_string());
+        let a = Folder::new("A".to_string(), None, None, None, ws.clone());
+        let b = Folder::new("B".to_string(), Some(a.id.clone()), None, None, ws);
+        repo.create(&a).unwrap();
+        repo.create(&b).unwrap();
+        repo.pool.get().unwrap().execute(
+            "UPDATE folders SET parent_id = ?1 WHERE id = ?2",
+            params![b.id, a.id],
+        ).unwrap();
+        (a.id, b.id)
+    }
+
+    #[test]
+    fn test_last_modified_map_terminates_on_folder_cycle() {
+        let repo = memory_repo();
+        let (a, b) = create_folder_cycle(&repo);
+        insert_note(&repo, "in-b", &b);
+        repo.pool.get().unwrap().execute("UPDATE notes SET updated_at = 500 WHERE id = 'in-b'", []).unwrap();
+
+        let map = repo.last_modified_map("ws-1", true).unwrap();
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map[&a], 500);
+        assert_eq!(map[&b], 500);
+    }
+
+    #[test]
+    fn test_restore_terminates_on_folder_cycle() {
+        let repo = memory_repo();
+        let (a, b) = create_folder_cycle(&repo);
+        insert_note(&repo, "in-b", &b);
+        let now = chrono::Utc::now().timestamp();
+        repo.pool.get().unwrap().execute_batch(&format!(
+            "UPDATE folders SET is_deleted = 1, deleted_at = {now};
+             UPDATE notes SET is_deleted = 1, deleted_at = {now};"
+        )).unwrap();
+
+        repo.restore(&a).unwrap();
+
+        assert!(repo.find_by_id(&b).unwrap().is_some());
+        assert_eq!(note_state(&repo, "in-b"), (false, true));
+    }
+
+    #[test]
+    fn test_find_tree_by_workspace_keeps_nested_folders() {
+        let repo = memory_repo();
+        let ws = Some("workspace-a".to_string());
+
+        let work = Folder::new("工作".to_string(), None, None, None, ws.clone());
+        let project = Folder::new("项目".to_string(), Some(work.id.clone()), None, None, ws.clone());
+        let year = Folder::new("2024".to_string(), Some(project.id.clone()), None, None, ws.clone());
+        let other = Folder::new("其他".to_string(), None, None, None, Some("workspace-b".to_string()));
+        for folder in [&work, &project, &year, &other] {
+            repo.create(folder).unwrap();
+        }
+
+        let tree = repo.find_tree_by_workspace("workspace-a").unwrap();
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree[0].folder.name, "工作");
+        assert_eq!(tree[0].children[0].folder.name, "项目");
+        assert_eq!(tree[0].children[0].children[0].folder.name, "2024");
+        assert!(tree[0].children[0].children[0].children.is_empty());
+    }
+}
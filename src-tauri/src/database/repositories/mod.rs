@@ -6,6 +6,7 @@ pub mod tag_repository;
 pub mod user_profile_repository;
 pub mod snapshot_repository;
 pub mod workspace_repository;
+pub mod attachment_repository;
 
 pub use note_repository::NoteRepository;
 pub use folder_repository::FolderRepository;
@@ -17,3 +18,4 @@ pub use user_profile_repository::UserProfileRepository;
 #[allow(unused_imports)]
 pub use snapshot_repository::SnapshotRepository;
 pub use workspace_repository::WorkspaceRepository;
+pub use attachment_repository::AttachmentRepository;
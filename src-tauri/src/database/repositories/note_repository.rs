@@ -1,7 +1,17 @@
 use crate::database::DbPool;
 use crate::models::error::{AppError, Result};
-use crate::models::Note;
-use r2d2_sqlite::rusqlite::params;
+use crate::models::{Note, NoteCursor, NotePage, RankedNote};
+use r2d2_sqlite::rusqlite::{self, params, params_from_iter, OptionalExtension};
+
+/// 笔记活动记录（用于写作统计）
+#[derive(Debug, Clone)]
+pub struct NoteActivity {
+    pub id: String,
+    pub title: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub word_count: u32,
+}
 
 /// 笔记数据访问层
 ///
@@ -12,6 +22,10 @@ pub struct NoteRepository {
 }
 
 impl NoteRepository {
+    /// 新置顶笔记的排序值（比现有置顶笔记都小，排在最前）
+    const NEXT_PIN_SORT_ORDER: &'static str =
+        "SELECT COALESCE(MIN(pin_sort_order), 0) - 1 FROM notes WHERE is_pinned = 1";
+
     /// 创建新的 NoteRepository 实例
     pub fn new(pool: DbPool) -> Self {
         Self { pool }
@@ -54,7 +68,7 @@ impl NoteRepository {
             "SELECT id, title, content, excerpt, markdown_cache, workspace_id, folder_id, is_favorite,
                     is_deleted, is_pinned, author, created_at, updated_at, deleted_at,
                     word_count, read_time_minutes,
-                    server_ver, is_dirty, last_synced_at
+                    server_ver, is_dirty, last_synced_at, is_private
              FROM notes
              WHERE id = ? AND is_deleted = 0",
         )?;
@@ -71,6 +85,7 @@ impl NoteRepository {
                 is_favorite: row.get(7)?,
                 is_deleted: row.get(8)?,
                 is_pinned: row.get(9)?,
+                is_private: row.get(19)?,
                 author: row.get(10)?,
                 created_at: row.get(11)?,
                 updated_at: row.get(12)?,
@@ -91,6 +106,8 @@ impl NoteRepository {
     }
 
     /// 查找所有笔记（仅当前工作空间）
+    ///
+    /// 置顶笔记在前（按 `pin_sort_order` 升序，未设置排序的排在最后），其余按更新时间倒序
     pub fn find_all(&self) -> Result<Vec<Note>> {
         let workspace_id = self.get_current_workspace_id()?;
 
@@ -99,10 +116,10 @@ impl NoteRepository {
             "SELECT id, title, content, excerpt, markdown_cache, workspace_id, folder_id, is_favorite,
                     is_deleted, is_pinned, author, created_at, updated_at, deleted_at,
                     word_count, read_time_minutes,
-                    server_ver, is_dirty, last_synced_at
+                    server_ver, is_dirty, last_synced_at, is_private
              FROM notes
              WHERE is_deleted = 0 AND (workspace_id = ? OR workspace_id IS NULL)
-             ORDER BY updated_at DESC",
+             ORDER BY is_pinned DESC, pin_sort_order IS NULL, pin_sort_order ASC, updated_at DESC, id DESC",
         )?;
 
         let notes = stmt
@@ -118,6 +135,7 @@ impl NoteRepository {
                     is_favorite: row.get(7)?,
                     is_deleted: row.get(8)?,
                     is_pinned: row.get(9)?,
+                    is_private: row.get(19)?,
                     author: row.get(10)?,
                     created_at: row.get(11)?,
                     updated_at: row.get(12)?,
@@ -135,6 +153,116 @@ impl NoteRepository {
         Ok(notes)
     }
 
+    /// 分页查找笔记（仅当前工作空间）
+    ///
+    /// 排序与 [`find_all`](Self::find_all) 相同，再以 id DESC 保证顺序唯一；使用键集游标而不是 OFFSET，
+    /// 翻页期间其他笔记被修改时不会导致已返回的笔记重复出现。
+    /// 被修改的笔记会移到游标之前，因此带游标查询时同时返回读取上一页之后修改过的笔记（`changed`），
+    /// 尚未加载的笔记也不会遗漏
+    pub fn find_page(&self, limit: u32, cursor: Option<&NoteCursor>) -> Result<NotePage> {
+        let workspace_id = self.get_current_workspace_id()?;
+        let now = chrono::Utc::now().timestamp();
+
+        let conn = self.pool.get()?;
+        // 排序键 (未置顶, 无置顶顺序, 置顶顺序, -updated_at) 升序、id 降序，游标之后的笔记排序键更大
+        let mut stmt = conn.prepare(
+            "SELECT id, title, content, excerpt, markdown_cache, workspace_id, folder_id, is_favorite,
+                    is_deleted, is_pinned, author, created_at, updated_at, deleted_at,
+                    word_count, read_time_minutes,
+                    server_ver, is_dirty, last_synced_at, is_private, pin_sort_order
+             FROM notes
+             WHERE is_deleted = 0 AND (workspace_id = ?1 OR workspace_id IS NULL)
+               AND (?2 IS NULL
+                    OR (1 - is_pinned, pin_sort_order IS NULL, COALESCE(pin_sort_order, 0), -updated_at)
+                       > (1 - ?4, ?5 IS NULL, COALESCE(?5, 0), -?2)
+                    OR ((1 - is_pinned, pin_sort_order IS NULL, COALESCE(pin_sort_order, 0), -updated_at)
+                        = (1 - ?4, ?5 IS NULL, COALESCE(?5, 0), -?2) AND id < ?3))
+             ORDER BY is_pinned DESC, pin_sort_order IS NULL, pin_sort_order ASC, updated_at DESC, id DESC
+             LIMIT ?6",
+        )?;
+
+        // 多取一条用于判断是否还有下一页
+        let mut rows = stmt
+            .query_map(
+                params![
+                    workspace_id,
+                    cursor.map(|c| c.updated_at),
+                    cursor.map(|c| c.id.as_str()),
+                    cursor.map(|c| c.is_pinned),
+                    cursor.and_then(|c| c.pin_sort_order),
+                    limit as i64 + 1,
+                ],
+                |row| Ok((note_from_row(row)?, row.get::<_, Option<i64>>(20)?)),
+            )?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(AppError::Database)?;
+
+        let next_cursor = if rows.len() > limit as usize {
+            rows.truncate(limit as usize);
+            rows.last().map(|(note, pin_sort_order)| NoteCursor {
+                updated_at: note.updated_at,
+                id: note.id.clone(),
+                is_pinned: note.is_pinned,
+                pin_sort_order: *pin_sort_order,
+                since: now,
+            })
+        } else {
+            None
+        };
+
+        let changed = match cursor {
+            Some(cursor) => {
+                let mut stmt = conn.prepare(
+                    "SELECT id, title, content, excerpt, markdown_cache, workspace_id, folder_id, is_favorite,
+                            is_deleted, is_pinned, author, created_at, updated_at, deleted_at,
+                            word_count, read_time_minutes,
+                            server_ver, is_dirty, last_synced_at, is_private
+                     FROM notes
+                     WHERE is_deleted = 0 AND (workspace_id = ?1 OR workspace_id IS NULL) AND updated_at >= ?2
+                     ORDER BY is_pinned DESC, pin_sort_order IS NULL, pin_sort_order ASC, updated_at DESC, id DESC",
+                )?;
+                let changed = stmt
+                    .query_map(params![workspace_id, cursor.since], note_from_row)?
+                    .collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(AppError::Database)?;
+                changed
+            }
+            None => Vec::new(),
+        };
+
+        let notes = rows.into_iter().map(|(note, _)| note).collect();
+        Ok(NotePage { notes, next_cursor, changed })
+    }
+
+    /// 分页查找收藏的笔记（不含已删除），按更新时间倒序
+    ///
+    /// `workspace_id` 为 `None` 时使用当前工作空间
+    pub fn find_favorites(&self, workspace_id: Option<&str>, limit: u32, offset: u32) -> Result<Vec<Note>> {
+        let workspace_id = match workspace_id {
+            Some(id) => Some(id.to_string()),
+            None => self.get_current_workspace_id()?,
+        };
+
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, title, content, excerpt, markdown_cache, workspace_id, folder_id, is_favorite,
+                    is_deleted, is_pinned, author, created_at, updated_at, deleted_at,
+                    word_count, read_time_minutes,
+                    server_ver, is_dirty, last_synced_at, is_private
+             FROM notes
+             WHERE is_deleted = 0 AND is_favorite = 1 AND (workspace_id = ?1 OR workspace_id IS NULL)
+             ORDER BY updated_at DESC, id DESC
+             LIMIT ?2 OFFSET ?3",
+        )?;
+
+        let notes = stmt
+            .query_map(params![workspace_id, limit, offset], note_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(AppError::Database)?;
+
+        Ok(notes)
+    }
+
     /// 查找所有已删除的笔记（回收站）
     ///
     /// ## 查询条件
@@ -153,7 +281,7 @@ impl NoteRepository {
             "SELECT id, title, content, excerpt, markdown_cache, workspace_id, folder_id, is_favorite,
                     is_deleted, is_pinned, author, created_at, updated_at, deleted_at,
                     word_count, read_time_minutes,
-                    server_ver, is_dirty, last_synced_at
+                    server_ver, is_dirty, last_synced_at, is_private
              FROM notes
              WHERE is_deleted = 1 AND (workspace_id = ? OR workspace_id IS NULL)
              ORDER BY deleted_at DESC",
@@ -172,6 +300,7 @@ impl NoteRepository {
                     is_favorite: row.get(7)?,
                     is_deleted: row.get(8)?,
                     is_pinned: row.get(9)?,
+                    is_private: row.get(19)?,
                     author: row.get(10)?,
                     created_at: row.get(11)?,
                     updated_at: row.get(12)?,
@@ -190,16 +319,21 @@ impl NoteRepository {
         Ok(notes)
     }
 
-    /// 创建新笔记
+    /// 创建新笔记（归属当前工作空间）
     pub fn create(&self, note: &Note) -> Result<Note> {
         let workspace_id = self.get_current_workspace_id()?;
+        self.create_in_workspace(note, workspace_id.as_deref())
+    }
+
+    /// 在指定工作空间中创建笔记
+    pub fn create_in_workspace(&self, note: &Note, workspace_id: Option<&str>) -> Result<Note> {
         let conn = self.pool.get()?;
         conn.execute(
             "INSERT INTO notes (id, title, content, excerpt, markdown_cache, workspace_id, folder_id,
                               is_favorite, is_deleted, is_pinned, author,
                               created_at, updated_at, deleted_at, word_count, read_time_minutes,
-                              server_ver, is_dirty, last_synced_at)
-             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                              server_ver, is_dirty, last_synced_at, is_private)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
             params![
                 note.id,
                 note.title,
@@ -219,7 +353,8 @@ impl NoteRepository {
                 note.read_time_minutes,
                 note.server_ver,
                 note.is_dirty as i32,
-                note.last_synced_at
+                note.last_synced_at,
+                note.is_private as i32
             ],
         )?;
 
@@ -227,20 +362,47 @@ impl NoteRepository {
         Ok(note.clone())
     }
 
+    /// 工作空间的指定文件夹中是否已有标题和内容都相同的笔记（不含已删除）
+    pub fn exists_with_content(
+        &self,
+        workspace_id: &str,
+        folder_id: Option<&str>,
+        title: &str,
+        content: &str,
+    ) -> Result<bool> {
+        let conn = self.pool.get()?;
+        let exists = conn.query_row(
+            "SELECT EXISTS(
+                SELECT 1 FROM notes
+                WHERE workspace_id = ?1 AND folder_id IS ?2 AND title = ?3 AND content = ?4 AND is_deleted = 0
+             )",
+            params![workspace_id, folder_id, title, content],
+            |row| row.get(0),
+        )?;
+
+        Ok(exists)
+    }
+
     /// 更新笔记
     pub fn update(&self, note: &Note) -> Result<Note> {
         let conn = self.pool.get()?;
         conn.execute(
-            "UPDATE notes
-             SET title = ?, content = ?, excerpt = ?, folder_id = ?,
-                 is_favorite = ?, is_pinned = ?, author = ?,
-                 updated_at = ?, word_count = ?, read_time_minutes = ?,
-                 is_dirty = ?
-             WHERE id = ?",
+            &format!(
+                "UPDATE notes
+                 SET title = ?, content = ?, excerpt = ?, markdown_cache = ?, folder_id = ?,
+                     is_favorite = ?, is_pinned = ?, author = ?,
+                     updated_at = ?, word_count = ?, read_time_minutes = ?,
+                     is_dirty = ?,
+                     pinned_at = CASE WHEN ? = 0 THEN NULL WHEN is_pinned = 1 THEN pinned_at ELSE ? END,
+                     pin_sort_order = CASE WHEN ? = 0 THEN NULL WHEN is_pinned = 1 THEN pin_sort_order ELSE ({}) END
+                 WHERE id = ?",
+                Self::NEXT_PIN_SORT_ORDER
+            ),
             params![
                 note.title,
                 note.content,
                 note.excerpt,
+                note.markdown_cache,
                 note.folder_id,
                 note.is_favorite as i32,
                 note.is_pinned as i32,
@@ -249,6 +411,9 @@ impl NoteRepository {
                 note.word_count,
                 note.read_time_minutes,
                 note.is_dirty as i32,
+                note.is_pinned as i32,
+                note.updated_at,
+                note.is_pinned as i32,
                 note.id
             ],
         )?;
@@ -311,7 +476,7 @@ impl NoteRepository {
             "SELECT n.id, n.title, n.content, n.excerpt, n.markdown_cache, n.workspace_id, n.folder_id, n.is_favorite,
                     n.is_deleted, n.is_pinned, n.author, n.created_at, n.updated_at, n.deleted_at,
                     n.word_count, n.read_time_minutes,
-                    n.server_ver, n.is_dirty, n.last_synced_at
+                    n.server_ver, n.is_dirty, n.last_synced_at, n.is_private
              FROM notes n
              JOIN notes_fts f ON n.id = f.note_id
              WHERE notes_fts MATCH ? AND n.is_deleted = 0 AND (n.workspace_id = ? OR n.workspace_id IS NULL)
@@ -332,6 +497,7 @@ impl NoteRepository {
                     is_favorite: row.get(7)?,
                     is_deleted: row.get(8)?,
                     is_pinned: row.get(9)?,
+                    is_private: row.get(19)?,
                     author: row.get(10)?,
                     created_at: row.get(11)?,
                     updated_at: row.get(12)?,
@@ -350,6 +516,213 @@ impl NoteRepository {
         Ok(notes)
     }
 
+    /// 按相关度全文搜索笔记
+    ///
+    /// 多个关键词之间为“且”关系，每个关键词按前缀匹配；按 bm25 排序，标题命中权重高于内容。
+    /// `workspace_id` 为 None 时搜索当前工作空间。FTS5 不可用时回退到 LIKE 匹配
+    pub fn search_notes_ranked(
+        &self,
+        query: &str,
+        workspace_id: Option<&str>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<RankedNote>> {
+        let terms: Vec<&str> = query.split_whitespace().collect();
+        if terms.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let workspace_id = match workspace_id {
+            Some(id) => Some(id.to_string()),
+            None => self.get_current_workspace_id()?,
+        };
+
+        let conn = self.pool.get()?;
+        // 每个关键词作为带引号的短语，避免用户输入被解析为 FTS 语法
+        let match_query = terms.iter()
+            .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let mut stmt = match conn.prepare(
+            "SELECT n.id, n.title, n.content, n.excerpt, n.markdown_cache, n.workspace_id, n.folder_id, n.is_favorite,
+                    n.is_deleted, n.is_pinned, n.author, n.created_at, n.updated_at, n.deleted_at,
+                    n.word_count, n.read_time_minutes,
+                    n.server_ver, n.is_dirty, n.last_synced_at, n.is_private,
+                    bm25(notes_fts, 0.0, 10.0, 1.0) AS rank,
+                    highlight(notes_fts, 1, '<mark>', '</mark>'),
+                    snippet(notes_fts, 2, '<mark>', '</mark>', '…', 16)
+             FROM notes_fts
+             JOIN notes n ON n.id = notes_fts.note_id
+             WHERE notes_fts MATCH ?1 AND n.is_deleted = 0 AND (n.workspace_id = ?2 OR n.workspace_id IS NULL)
+             ORDER BY rank
+             LIMIT ?3 OFFSET ?4"
+        ) {
+            Ok(stmt) => stmt,
+            Err(e) => {
+                log::warn!("FTS5 unavailable, falling back to LIKE search: {}", e);
+                return Self::search_notes_like(&conn, &terms, workspace_id.as_deref(), limit, offset);
+            }
+        };
+
+        let notes = stmt
+            .query_map(params![match_query, workspace_id, limit, offset], |row| {
+                Ok(RankedNote {
+                    note: note_from_row(row)?,
+                    rank: row.get(20)?,
+                    title_highlight: row.get(21)?,
+                    snippet: row.get(22)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(AppError::Database)?;
+
+        log::debug!("Ranked search completed: {} results", notes.len());
+        Ok(notes)
+    }
+
+    /// LIKE 匹配搜索（FTS5 不可用时使用）
+    ///
+    /// 按标题和内容中关键词出现次数排序，标题命中计 10 次；不生成高亮，片段使用摘要
+    fn search_notes_like(
+        conn: &rusqlite::Connection,
+        terms: &[&str],
+        workspace_id: Option<&str>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<RankedNote>> {
+        let mut sql = String::from(
+            "SELECT n.id, n.title, n.content, n.excerpt, n.markdown_cache, n.workspace_id, n.folder_id, n.is_favorite,
+                    n.is_deleted, n.is_pinned, n.author, n.created_at, n.updated_at, n.deleted_at,
+                    n.word_count, n.read_time_minutes,
+                    n.server_ver, n.is_dirty, n.last_synced_at, n.is_private
+             FROM notes n
+             WHERE n.is_deleted = 0 AND (n.workspace_id = ?1 OR n.workspace_id IS NULL)"
+        );
+        let mut values = vec![workspace_id.map(str::to_string)];
+        for term in terms {
+            let index = values.len() + 1;
+            sql.push_str(&format!(
+                " AND (n.title LIKE ?{0} ESCAPE '\\' OR n.content LIKE ?{0} ESCAPE '\\')",
+                index
+            ));
+            let escaped = term.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+            values.push(Some(format!("%{}%", escaped)));
+        }
+
+        let mut stmt = conn.prepare(&sql)?;
+        let notes = stmt
+            .query_map(params_from_iter(values), note_from_row)?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(AppError::Database)?;
+
+        let terms: Vec<String> = terms.iter().map(|t| t.to_lowercase()).collect();
+        let mut ranked: Vec<(usize, Note)> = notes.into_iter()
+            .map(|note| {
+                let title = note.title.to_lowercase();
+                let content = note.content.to_lowercase();
+                let hits = terms.iter()
+                    .map(|t| title.matches(t.as_str()).count() * 10 + content.matches(t.as_str()).count())
+                    .sum();
+                (hits, note)
+            })
+            .collect();
+        ranked.sort_by(|(a_hits, a), (b_hits, b)| {
+            b_hits.cmp(a_hits).then(b.updated_at.cmp(&a.updated_at))
+        });
+
+        let notes: Vec<RankedNote> = ranked.into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .map(|(hits, note)| RankedNote {
+                rank: -(hits as f64),
+                title_highlight: note.title.clone(),
+                // 短笔记没有摘要，直接使用全文
+                snippet: note.excerpt.clone().unwrap_or_else(|| Note::plain_text(&note.content)),
+                note,
+            })
+            .collect();
+
+        log::debug!("LIKE search completed: {} results", notes.len());
+        Ok(notes)
+    }
+
+    /// 跨工作空间全文搜索笔记
+    ///
+    /// 搜索当前用户的所有工作空间；`include_private` 为 false 时排除私密笔记
+    pub fn search_global(&self, query: &str, include_private: bool) -> Result<Vec<Note>> {
+        let conn = self.pool.get()?;
+        let search_query = format!("{}*", query); // FTS5 前缀搜索
+
+        let mut stmt = conn.prepare(
+            "SELECT n.id, n.title, n.content, n.excerpt, n.markdown_cache, n.workspace_id, n.folder_id, n.is_favorite,
+                    n.is_deleted, n.is_pinned, n.author, n.created_at, n.updated_at, n.deleted_at,
+                    n.word_count, n.read_time_minutes,
+                    n.server_ver, n.is_dirty, n.last_synced_at, n.is_private
+             FROM notes n
+             JOIN notes_fts f ON n.id = f.note_id
+             WHERE notes_fts MATCH ?1 AND n.is_deleted = 0
+               AND (?2 = 1 OR n.is_private = 0)
+               AND (n.workspace_id IS NULL OR n.workspace_id IN (
+                    SELECT w.id FROM workspaces w
+                    INNER JOIN user_auth u ON u.user_id = w.user_id
+                    WHERE u.is_current = 1 AND w.is_deleted = 0
+               ))
+             ORDER BY n.updated_at DESC
+             LIMIT 50"
+        )?;
+
+        let notes = stmt
+            .query_map(params![search_query, include_private], |row| {
+                Ok(Note {
+                    id: row.get(0)?,
+                    title: row.get(1)?,
+                    content: row.get(2)?,
+                    excerpt: row.get(3)?,
+                    markdown_cache: row.get(4)?,
+                    workspace_id: row.get(5)?,
+                    folder_id: row.get(6)?,
+                    is_favorite: row.get(7)?,
+                    is_deleted: row.get(8)?,
+                    is_pinned: row.get(9)?,
+                    is_private: row.get(19)?,
+                    author: row.get(10)?,
+                    created_at: row.get(11)?,
+                    updated_at: row.get(12)?,
+                    deleted_at: row.get(13)?,
+                    word_count: row.get(14)?,
+                    read_time_minutes: row.get(15)?,
+                    server_ver: row.get(16)?,
+                    is_dirty: row.get(17)?,
+                    last_synced_at: row.get(18)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(AppError::Database)?;
+
+        log::debug!("Global search completed: {} results (include_private={})", notes.len(), include_private);
+        Ok(notes)
+    }
+
+    /// 设置笔记的私密状态
+    ///
+    /// ## 返回
+    ///
+    /// 笔记不存在或已删除时返回 false
+    pub fn set_private(&self, id: &str, private: bool) -> Result<bool> {
+        let conn = self.pool.get()?;
+        let now = chrono::Utc::now().timestamp();
+
+        let rows_affected = conn.execute(
+            "UPDATE notes SET is_private = ?, updated_at = ?, is_dirty = 1
+             WHERE id = ? AND is_deleted = 0",
+            params![private as i32, now, id],
+        ).map_err(AppError::Database)?;
+
+        log::debug!("Note private flag updated: id={}, private={}", id, private);
+        Ok(rows_affected > 0)
+    }
+
     /// 统计笔记数量（不包括软删除的笔记）
     ///
     /// ## 返回
@@ -425,25 +798,610 @@ impl NoteRepository {
         Ok(rows_affected as i64)
     }
 
-    /// 清理超过指定天数的软删除笔记
+    /// 批量设置收藏状态
+    ///
+    /// 只更新状态实际变化的笔记，单条 UPDATE 语句保证原子性
+    ///
+    /// ## 返回
+    ///
+    /// 返回状态发生变化的笔记数量
+    pub fn set_favorite_batch(&self, ids: &[String], favorite: bool) -> Result<usize> {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+
+        let conn = self.pool.get()?;
+        let now = chrono::Utc::now().timestamp();
+        let favorite = favorite as i32;
+
+        let sql = format!(
+            "UPDATE notes SET is_favorite = ?, updated_at = ?, is_dirty = 1
+             WHERE is_deleted = 0 AND is_favorite != ? AND id IN ({})",
+            ids.iter().map(|_| "?").collect::<Vec<_>>().join(",")
+        );
+
+        let mut params: Vec<&dyn r2d2_sqlite::rusqlite::ToSql> = vec![&favorite, &now, &favorite];
+        params.extend(ids.iter().map(|s| s as &dyn r2d2_sqlite::rusqlite::ToSql));
+
+        let rows_affected = conn.execute(&sql, params.as_slice())
+            .map_err(AppError::Database)?;
+
+        log::info!("[NoteRepository] 批量设置收藏: favorite={}, count={}", favorite, rows_affected);
+        Ok(rows_affected)
+    }
+
+    /// 批量设置置顶状态
+    ///
+    /// 置顶时记录 `pinned_at`，取消置顶时清空
+    ///
+    /// ## 返回
+    ///
+    /// 返回状态发生变化的笔记数量
+    pub fn set_pinned_batch(&self, ids: &[String], pinned: bool) -> Result<usize> {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+
+        let conn = self.pool.get()?;
+        let now = chrono::Utc::now().timestamp();
+        let pinned_at = if pinned { Some(now) } else { None };
+        let pinned = pinned as i32;
+
+        let sql = format!(
+            "UPDATE notes SET is_pinned = ?, pinned_at = ?, updated_at = ?, is_dirty = 1,
+                 pin_sort_order = CASE WHEN ? = 1 THEN ({}) END
+             WHERE is_deleted = 0 AND is_pinned != ? AND id IN ({})",
+            Self::NEXT_PIN_SORT_ORDER,
+            ids.iter().map(|_| "?").collect::<Vec<_>>().join(",")
+        );
+
+        let mut params: Vec<&dyn r2d2_sqlite::rusqlite::ToSql> = vec![&pinned, &pinned_at, &now, &pinned, &pinned];
+        params.extend(ids.iter().map(|s| s as &dyn r2d2_sqlite::rusqlite::ToSql));
+
+        let rows_affected = conn.execute(&sql, params.as_slice())
+            .map_err(AppError::Database)?;
+
+        log::info!("[NoteRepository] 批量设置置顶: pinned={}, count={}", pinned, rows_affected);
+        Ok(rows_affected)
+    }
+
+    /// 调整置顶笔记的顺序
+    ///
+    /// 按给定顺序把笔记排到所有置顶笔记的最前面，未列出的置顶笔记保持原有相对顺序。
+    /// 排序只保存在本地，不标记为需要同步
+    pub fn set_pin_order(&self, ids: &[String]) -> Result<()> {
+        let conn = self.pool.get()?;
+        let tx = conn.unchecked_transaction().map_err(AppError::Database)?;
+
+        let top: i64 = tx.query_row(
+            "SELECT COALESCE(MIN(pin_sort_order), 0) FROM notes WHERE is_pinned = 1",
+            [],
+            |row| row.get(0),
+        )?;
+        let base = top - ids.len() as i64;
+
+        for (index, id) in ids.iter().enumerate() {
+            let updated = tx.execute(
+                "UPDATE notes SET pin_sort_order = ?1 WHERE id = ?2 AND is_pinned = 1 AND is_deleted = 0",
+                params![base + index as i64, id],
+            )?;
+            if updated == 0 {
+                return Err(AppError::InvalidOperation(format!("笔记 {} 不存在或未置顶", id)));
+            }
+        }
+
+        tx.commit().map_err(AppError::Database)?;
+        log::info!("[NoteRepository] 调整置顶顺序: count={}", ids.len());
+        Ok(())
+    }
+
+    /// 清理指定工作空间中超过指定天数的软删除笔记
     ///
     /// ## 参数
     ///
+    /// - `workspace_id`: 工作空间 ID（`None` 表示未归属工作空间的数据）
     /// - `days`: 软删除后的保留天数（如 30 天）
     ///
     /// ## 返回
     ///
     /// 返回清理的笔记数量
-    pub fn purge_old_deleted_notes(&self, days: i64) -> Result<i64> {
+    pub fn purge_old_deleted_notes(&self, workspace_id: Option<&str>, days: i64) -> Result<i64> {
         let conn = self.pool.get()?;
         let cutoff_time = chrono::Utc::now().timestamp() - (days * 86400);
 
         let rows_affected = conn.execute(
-            "DELETE FROM notes WHERE is_deleted = 1 AND deleted_at < ?",
-            params![cutoff_time],
+            "DELETE FROM notes WHERE is_deleted = 1 AND workspace_id IS ? AND deleted_at < ?",
+            params![workspace_id, cutoff_time],
         ).map_err(AppError::Database)?;
 
-        log::info!("[NoteRepository] 清理旧笔记: days={}, count={}", days, rows_affected);
+        log::info!("[NoteRepository] 清理旧笔记: workspace_id={:?}, days={}, count={}", workspace_id, days, rows_affected);
         Ok(rows_affected as i64)
     }
+
+    /// 获取所有未删除笔记的 ID 和内容（用于重新生成摘要）
+    pub fn find_all_contents(&self) -> Result<Vec<(String, String)>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, content FROM notes WHERE is_deleted = 0",
+        )?;
+
+        let contents = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(AppError::Database)?;
+
+        Ok(contents)
+    }
+
+    /// 批量更新笔记摘要
+    ///
+    /// 摘要只是内容的派生展示数据，不修改 `updated_at`，也不标记为需要同步
+    pub fn update_excerpts(&self, excerpts: &[(String, Option<String>)]) -> Result<usize> {
+        let conn = self.pool.get()?;
+        let tx = conn.unchecked_transaction().map_err(AppError::Database)?;
+
+        let mut updated = 0;
+        for (id, excerpt) in excerpts {
+            updated += tx.execute(
+                "UPDATE notes SET excerpt = ? WHERE id = ?",
+                params![excerpt, id],
+            ).map_err(AppError::Database)?;
+        }
+
+        tx.commit().map_err(AppError::Database)?;
+
+        log::info!("[NoteRepository] 批量更新摘要: count={}", updated);
+        Ok(updated)
+    }
+
+    /// 获取工作空间中未删除笔记的 ID 和内容（用于重新生成 Markdown 缓存）
+    ///
+    /// `workspace_id` 为 `None` 时使用当前工作空间
+    pub fn find_contents_by_workspace(&self, workspace_id: Option<&str>) -> Result<Vec<(String, String)>> {
+        let workspace_id = match workspace_id {
+            Some(id) => Some(id.to_string()),
+            None => self.get_current_workspace_id()?,
+        };
+
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, content FROM notes
+             WHERE is_deleted = 0 AND (workspace_id = ?1 OR workspace_id IS NULL)",
+        )?;
+
+        let contents = stmt
+            .query_map(params![workspace_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(AppError::Database)?;
+
+        Ok(contents)
+    }
+
+    /// 批量更新笔记的 Markdown 缓存
+    ///
+    /// 与摘要一样只是派生数据，不修改 `updated_at`，也不标记为需要同步
+    pub fn update_markdown_caches(&self, caches: &[(String, Option<String>)]) -> Result<usize> {
+        let conn = self.pool.get()?;
+        let tx = conn.unchecked_transaction().map_err(AppError::Database)?;
+
+        let mut updated = 0;
+        for (id, markdown_cache) in caches {
+            updated += tx.execute(
+                "UPDATE notes SET markdown_cache = ? WHERE id = ?",
+                params![markdown_cache, id],
+            ).map_err(AppError::Database)?;
+        }
+
+        tx.commit().map_err(AppError::Database)?;
+
+        log::info!("[NoteRepository] 批量更新 Markdown 缓存: count={}", updated);
+        Ok(updated)
+    }
+
+    /// 获取文件夹中未删除笔记的 ID 和标题（按创建时间升序）
+    pub fn find_titles_by_folder(&self, folder_id: &str) -> Result<Vec<(String, String)>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, title FROM notes
+             WHERE folder_id = ? AND is_deleted = 0
+             ORDER BY created_at ASC, id ASC",
+        )?;
+
+        let titles = stmt
+            .query_map(params![folder_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(AppError::Database)?;
+
+        Ok(titles)
+    }
+
+    /// 批量修改笔记标题，修改前为每篇笔记创建快照
+    ///
+    /// 快照和标题修改在同一事务中完成，修改后的笔记标记为需要同步
+    pub fn rename_with_snapshots(&self, titles: &[(String, String)], snapshot_name: &str) -> Result<usize> {
+        let conn = self.pool.get()?;
+        let now = chrono::Utc::now().timestamp();
+        let tx = conn.unchecked_transaction().map_err(AppError::Database)?;
+
+        let mut renamed = 0;
+        for (id, title) in titles {
+            tx.execute(
+                "INSERT INTO note_snapshots (id, note_id, title, content, snapshot_name, created_at, workspace_id, server_ver, is_dirty)
+                 SELECT ?, id, title, content, ?, ?, workspace_id, 1, 1 FROM notes WHERE id = ?",
+                params![uuid::Uuid::new_v4().to_string(), snapshot_name, now, id],
+            ).map_err(AppError::Database)?;
+
+            renamed += tx.execute(
+                "UPDATE notes SET title = ?, updated_at = ?, is_dirty = 1 WHERE id = ?",
+                params![title, now, id],
+            ).map_err(AppError::Database)?;
+        }
+
+        tx.commit().map_err(AppError::Database)?;
+
+        log::info!("[NoteRepository] 批量重命名笔记: count={}", renamed);
+        Ok(renamed)
+    }
+
+    /// 批量移动笔记并记录撤销信息
+    ///
+    /// 在同一事务中把每篇笔记原来的 folder_id 写入 `undo_log`，然后执行移动。
+    /// `undo_log` 中只保留最近 `keep_operations` 次移动操作。
+    ///
+    /// ## 返回
+    ///
+    /// 本次操作的 operation_id
+    pub fn move_with_undo(&self, ids: &[String], folder_id: Option<&str>, keep_operations: usize) -> Result<String> {
+        let conn = self.pool.get()?;
+        let now = chrono::Utc::now().timestamp();
+        let operation_id = uuid::Uuid::new_v4().to_string();
+        let tx = conn.unchecked_transaction().map_err(AppError::Database)?;
+
+        let mut moved = 0;
+        for id in ids {
+            tx.execute(
+                "INSERT INTO undo_log (operation_id, operation_type, entity_id, previous_value, created_at)
+                 SELECT ?, 'move_notes', id, folder_id, ? FROM notes WHERE id = ? AND is_deleted = 0",
+                params![operation_id, now, id],
+            ).map_err(AppError::Database)?;
+
+            moved += tx.execute(
+                "UPDATE notes SET folder_id = ?, updated_at = ?, is_dirty = 1 WHERE id = ? AND is_deleted = 0",
+                params![folder_id, now, id],
+            ).map_err(AppError::Database)?;
+        }
+
+        // 只保留最近的若干次操作
+        tx.execute(
+            "DELETE FROM undo_log
+             WHERE operation_type = 'move_notes' AND operation_id NOT IN (
+                SELECT operation_id FROM undo_log
+                WHERE operation_type = 'move_notes'
+                GROUP BY operation_id
+                ORDER BY MAX(id) DESC
+                LIMIT ?
+             )",
+            params![keep_operations as i64],
+        ).map_err(AppError::Database)?;
+
+        tx.commit().map_err(AppError::Database)?;
+
+        log::info!("[NoteRepository] 批量移动笔记（可撤销）: operation_id={}, count={}", operation_id, moved);
+        Ok(operation_id)
+    }
+
+    /// 撤销最近一次批量移动
+    ///
+    /// 笔记恢复到移动前的文件夹；如果原文件夹已被删除，则移到根目录。
+    /// 撤销后该操作的记录会被删除。
+    ///
+    /// ## 返回
+    ///
+    /// `(operation_id, 恢复的笔记数量)`，没有可撤销的操作时返回 None
+    pub fn undo_last_move(&self) -> Result<Option<(String, usize)>> {
+        let conn = self.pool.get()?;
+        let now = chrono::Utc::now().timestamp();
+        let tx = conn.unchecked_transaction().map_err(AppError::Database)?;
+
+        let operation_id: Option<String> = tx.query_row(
+            "SELECT operation_id FROM undo_log WHERE operation_type = 'move_notes' ORDER BY id DESC LIMIT 1",
+            [],
+            |row| row.get(0),
+        ).optional().map_err(AppError::Database)?;
+
+        let operation_id = match operation_id {
+            Some(id) => id,
+            None => return Ok(None),
+        };
+
+        let restored = tx.execute(
+            "UPDATE notes SET
+                folder_id = (
+                    SELECT CASE WHEN EXISTS (
+                        SELECT 1 FROM folders f WHERE f.id = u.previous_value AND f.is_deleted = 0
+                    ) THEN u.previous_value ELSE NULL END
+                    FROM undo_log u
+                    WHERE u.operation_id = ?1 AND u.entity_id = notes.id
+                ),
+                updated_at = ?2,
+                is_dirty = 1
+             WHERE is_deleted = 0 AND id IN (SELECT entity_id FROM undo_log WHERE operation_id = ?1)",
+            params![operation_id, now],
+        ).map_err(AppError::Database)?;
+
+        tx.execute(
+            "DELETE FROM undo_log WHERE operation_id = ?",
+            params![operation_id],
+        ).map_err(AppError::Database)?;
+
+        tx.commit().map_err(AppError::Database)?;
+
+        log::info!("[NoteRepository] 撤销批量移动: operation_id={}, restored={}", operation_id, restored);
+        Ok(Some((operation_id, restored)))
+    }
+
+    /// 查询时间窗口内有活动（创建或修改）的笔记
+    pub fn find_active_since(&self, workspace_id: &str, since: i64) -> Result<Vec<NoteActivity>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, title, created_at, updated_at, word_count
+             FROM notes
+             WHERE workspace_id = ?1 AND is_deleted = 0 AND (created_at >= ?2 OR updated_at >= ?2)"
+        )?;
+
+        let notes = stmt.query_map(params![workspace_id, since], |row| {
+            Ok(NoteActivity {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                created_at: row.get(2)?,
+                updated_at: row.get(3)?,
+                word_count: row.get(4)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(notes)
+    }
+
+    /// 查询时间窗口内有活动的笔记的快照（用于计算字数变化）
+    ///
+    /// 除窗口内的快照外，还包含每篇笔记在窗口开始前的最后一个快照作为基线
+    ///
+    /// ## 返回
+    ///
+    /// `(note_id, created_at, content)` 列表，按笔记和时间排序
+    pub fn find_snapshots_for_stats(&self, workspace_id: &str, since: i64) -> Result<Vec<(String, i64, String)>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT s.note_id, s.created_at, s.content
+             FROM note_snapshots s
+             INNER JOIN notes n ON n.id = s.note_id
+             WHERE n.workspace_id = ?1 AND n.is_deleted = 0
+               AND (n.created_at >= ?2 OR n.updated_at >= ?2)
+               AND s.created_at >= COALESCE(
+                    (SELECT MAX(s2.created_at) FROM note_snapshots s2
+                     WHERE s2.note_id = s.note_id AND s2.created_at < ?2),
+                    ?2
+               )
+             ORDER BY s.note_id, s.created_at"
+        )?;
+
+        let snapshots = stmt.query_map(params![workspace_id, since], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(snapshots)
+    }
+
+    /// 逐条读取工作空间内的笔记（不含已删除），按创建时间排序
+    ///
+    /// 每读到一条就交给 `f` 处理，不会把整个工作空间的笔记内容同时加载到内存。
+    /// 读取期间占用一个数据库连接，`f` 中不要再从同一连接池获取连接
+    ///
+    /// ## 返回
+    ///
+    /// 返回处理的笔记数量
+    pub fn for_each_in_workspace<F>(&self, workspace_id: &str, mut f: F) -> Result<usize>
+    where
+        F: FnMut(Note) -> Result<()>,
+    {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, title, content, excerpt, markdown_cache, workspace_id, folder_id, is_favorite,
+                    is_deleted, is_pinned, author, created_at, updated_at, deleted_at,
+                    word_count, read_time_minutes,
+                    server_ver, is_dirty, last_synced_at, is_private
+             FROM notes
+             WHERE workspace_id = ?1 AND is_deleted = 0
+             ORDER BY created_at ASC, id ASC",
+        )?;
+
+        let mut count = 0;
+        for note in stmt.query_map(params![workspace_id], note_from_row)? {
+            f(note?)?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+}
+
+/// 按标准列顺序（id ... is_private）读取笔记
+fn note_from_row(row: &rusqlite::Row) -> rusqlite::Result<Note> {
+    Ok(Note {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        content: row.get(2)?,
+        excerpt: row.get(3)?,
+        markdown_cache: row.get(4)?,
+        workspace_id: row.get(5)?,
+        folder_id: row.get(6)?,
+        is_favorite: row.get(7)?,
+        is_deleted: row.get(8)?,
+        is_pinned: row.get(9)?,
+        is_private: row.get(19)?,
+        author: row.get(10)?,
+        created_at: row.get(11)?,
+        updated_at: row.get(12)?,
+        deleted_at: row.get(13)?,
+        word_count: row.get(14)?,
+        read_time_minutes: row.get(15)?,
+        server_ver: row.get(16)?,
+        is_dirty: row.get(17)?,
+        last_synced_at: row.get(18)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory_repo() -> NoteRepository {
+        let pool = crate::database::memory_pool();
+
+        let conn = pool.get().unwrap();
+        for (id, title, content, is_deleted) in [
+            ("guide", "Rust async guide", "Writing async Rust with tokio: async functions, async blocks and await.", 0),
+            ("mention", "Weekly notes", "Groceries, meetings, a short mention of rust and async, then more errands and chores.", 0),
+            ("rust-only", "Rust ownership", "Borrowing and lifetimes in rust.", 0),
+            ("deleted", "Rust async deep dive", "async rust async rust async rust", 1),
+        ] {
+            conn.execute(
+                "INSERT INTO notes (id, title, content, workspace_id, is_deleted, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, 'workspace-1', ?4, 0, 0)",
+                params![id, title, content, is_deleted],
+            ).unwrap();
+        }
+        drop(conn);
+
+        NoteRepository::new(pool)
+    }
+
+    /// 按 updated_at 从新到旧依次为 n1..n5
+    fn paged_repo() -> NoteRepository {
+        let pool = crate::database::memory_pool();
+
+        let conn = pool.get().unwrap();
+        for i in 1..=5 {
+            conn.execute(
+                "INSERT INTO notes (id, title, content, created_at, updated_at) VALUES (?1, ?1, '', 0, ?2)",
+                params![format!("n{}", i), 10 - i],
+            ).unwrap();
+        }
+        drop(conn);
+
+        NoteRepository::new(pool)
+    }
+
+    /// 模拟编辑笔记：更新时间设为当前时间
+    fn touch(repo: &NoteRepository, id: &str) {
+        repo.pool.get().unwrap()
+            .execute("UPDATE notes SET updated_at = ? WHERE id = ?", params![chrono::Utc::now().timestamp(), id])
+            .unwrap();
+    }
+
+    /// 读完剩余的页，返回翻页期间报告为已修改的笔记 ID
+    fn collect_remaining(repo: &NoteRepository, mut cursor: Option<NoteCursor>, seen: &mut Vec<String>) -> Vec<String> {
+        let mut changed = Vec::new();
+        while let Some(c) = cursor {
+            let page = repo.find_page(2, Some(&c)).unwrap();
+            seen.extend(page.notes.into_iter().map(|n| n.id));
+            changed.extend(page.changed.into_iter().map(|n| n.id));
+            cursor = page.next_cursor;
+        }
+        changed
+    }
+
+    #[test]
+    fn test_find_page_walks_all_notes() {
+        let repo = paged_repo();
+
+        let first = repo.find_page(2, None).unwrap();
+        let mut seen: Vec<_> = first.notes.into_iter().map(|n| n.id).collect();
+        assert_eq!(seen, vec!["n1", "n2"]);
+        collect_remaining(&repo, first.next_cursor, &mut seen);
+
+        assert_eq!(seen, vec!["n1", "n2", "n3", "n4", "n5"]);
+    }
+
+    #[test]
+    fn test_find_page_lists_pinned_notes_first_like_find_all() {
+        let repo = paged_repo();
+        repo.pool.get().unwrap().execute_batch(
+            "UPDATE notes SET is_pinned = 1, pin_sort_order = -1 WHERE id = 'n3';
+             UPDATE notes SET is_pinned = 1, pin_sort_order = -2 WHERE id = 'n5';
+             UPDATE notes SET is_pinned = 1, pin_sort_order = NULL WHERE id = 'n4';"
+        ).unwrap();
+
+        let first = repo.find_page(2, None).unwrap();
+        let mut seen: Vec<_> = first.notes.into_iter().map(|n| n.id).collect();
+        collect_remaining(&repo, first.next_cursor, &mut seen);
+
+        assert_eq!(seen, vec!["n5", "n3", "n4", "n1", "n2"]);
+        let all: Vec<_> = repo.find_all().unwrap().into_iter().map(|n| n.id).collect();
+        assert_eq!(seen, all);
+    }
+
+    #[test]
+    fn test_find_page_stable_when_fetched_note_is_updated() {
+        let repo = paged_repo();
+
+        let first = repo.find_page(2, None).unwrap();
+        let mut seen: Vec<_> = first.notes.into_iter().map(|n| n.id).collect();
+        touch(&repo, "n2");
+        let changed = collect_remaining(&repo, first.next_cursor, &mut seen);
+
+        assert_eq!(seen, vec!["n1", "n2", "n3", "n4", "n5"]);
+        assert!(changed.contains(&"n2".to_string()));
+    }
+
+    #[test]
+    fn test_find_page_reports_unfetched_note_that_moved_ahead() {
+        let repo = paged_repo();
+
+        let first = repo.find_page(2, None).unwrap();
+        let mut seen: Vec<_> = first.notes.into_iter().map(|n| n.id).collect();
+        // n4 移到最前面：OFFSET 分页会让 n2 在第二页重复出现，只按游标翻页又会漏掉 n4
+        touch(&repo, "n4");
+        let changed = collect_remaining(&repo, first.next_cursor, &mut seen);
+
+        assert_eq!(seen, vec!["n1", "n2", "n3", "n5"]);
+        assert!(changed.contains(&"n4".to_string()), "edited note must not be lost: {:?}", changed);
+    }
+
+    #[test]
+    fn test_ranked_search_orders_by_relevance() {
+        let repo = memory_repo();
+
+        let results = repo.search_notes_ranked("rust async", Some("workspace-1"), 10, 0).unwrap();
+        let ids: Vec<_> = results.iter().map(|r| r.note.id.as_str()).collect();
+        assert_eq!(ids, vec!["guide", "mention"]);
+        assert!(results[0].title_highlight.contains("<mark>Rust</mark>"));
+        assert!(results[0].snippet.contains("<mark>"));
+    }
+
+    #[test]
+    fn test_ranked_search_excludes_deleted_notes() {
+        let repo = memory_repo();
+
+        let results = repo.search_notes_ranked("deep dive", Some("workspace-1"), 10, 0).unwrap();
+        assert!(results.is_empty());
+
+        let results = repo.search_notes_ranked("rust", Some("workspace-1"), 10, 0).unwrap();
+        assert!(results.iter().all(|r| r.note.id != "deleted"));
+        assert_eq!(results.len(), 3);
+    }
+
+    #[test]
+    fn test_ranked_search_falls_back_to_like() {
+        let repo = memory_repo();
+        repo.pool.get().unwrap().execute_batch(
+            "DROP TRIGGER notes_ai; DROP TRIGGER notes_ad; DROP TRIGGER notes_au; DROP TABLE notes_fts;"
+        ).unwrap();
+
+        let results = repo.search_notes_ranked("rust async", Some("workspace-1"), 10, 0).unwrap();
+        let ids: Vec<_> = results.iter().map(|r| r.note.id.as_str()).collect();
+        assert_eq!(ids, vec!["guide", "mention"]);
+    }
 }
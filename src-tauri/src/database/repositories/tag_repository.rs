@@ -1,6 +1,7 @@
-use crate::models::{Tag, CreateTagRequest, UpdateTagRequest, NoteTagRequest, error::{Result, AppError}};
+use crate::models::{Tag, TagMergeResult, CreateTagRequest, UpdateTagRequest, NoteTagRequest, error::{Result, AppError}};
 use crate::database::DbPool;
 use r2d2_sqlite::rusqlite::{self as rusqlite, params};
+use std::collections::HashMap;
 
 #[derive(Clone)]
 pub struct TagRepository {
@@ -8,12 +9,20 @@ pub struct TagRepository {
 }
 
 impl TagRepository {
+    /// 当前用户所有工作空间的过滤条件（用于跨工作空间查询）
+    const CURRENT_USER_WORKSPACES: &'static str =
+        "workspace_id IN (
+            SELECT w.id FROM workspaces w
+            INNER JOIN user_auth u ON u.user_id = w.user_id
+            WHERE u.is_current = 1 AND w.is_deleted = 0
+        )";
+
     pub fn new(pool: DbPool) -> Self {
         Self { pool }
     }
 
     /// 获取当前工作空间 ID（基于当前用户的 is_current 标记）
-    fn get_current_workspace_id(&self) -> Result<Option<String>> {
+    pub fn get_current_workspace_id(&self) -> Result<Option<String>> {
         let conn = self.pool.get()?;
 
         // 获取当前用户 ID
@@ -104,6 +113,62 @@ impl TagRepository {
         }
     }
 
+    /// 在工作空间内按名称查找未删除的标签（忽略大小写）
+    pub fn find_by_name_in_workspace(&self, name: &str, workspace_id: Option<&str>) -> Result<Option<Tag>> {
+        let conn = self.pool.get()?;
+        let result = conn.query_row(
+            "SELECT id, name, color, workspace_id, created_at, updated_at, is_deleted, deleted_at, server_ver, is_dirty, last_synced_at
+             FROM tags WHERE name = ?1 COLLATE NOCASE AND workspace_id IS ?2 AND is_deleted = 0
+             LIMIT 1",
+            params![name, workspace_id],
+            |row| Ok(Tag {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                color: row.get(2)?,
+                workspace_id: row.get(3)?,
+                created_at: row.get(4)?,
+                updated_at: row.get(5)?,
+                is_deleted: row.get(6)?,
+                deleted_at: row.get(7)?,
+                server_ver: row.get(8)?,
+                is_dirty: row.get(9)?,
+                last_synced_at: row.get(10)?,
+            }),
+        );
+
+        match result {
+            Ok(tag) => Ok(Some(tag)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(AppError::Database(e)),
+        }
+    }
+
+    /// 获取工作空间内每篇笔记的标签名（按名称排序）
+    ///
+    /// 返回 笔记 ID → 标签名列表，用于批量导出
+    pub fn find_tag_names_by_workspace(&self, workspace_id: &str) -> Result<HashMap<String, Vec<String>>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT nt.note_id, t.name
+             FROM note_tags nt
+             INNER JOIN tags t ON t.id = nt.tag_id AND t.is_deleted = 0
+             INNER JOIN notes n ON n.id = nt.note_id
+             WHERE n.workspace_id = ?1 AND nt.is_deleted = 0
+             ORDER BY t.name"
+        )?;
+
+        let mut names: HashMap<String, Vec<String>> = HashMap::new();
+        let rows = stmt.query_map(params![workspace_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        for row in rows {
+            let (note_id, name) = row?;
+            names.entry(note_id).or_default().push(name);
+        }
+
+        Ok(names)
+    }
+
     /// 获取笔记的所有标签
     pub fn find_by_note_id(&self, note_id: &str) -> Result<Vec<Tag>> {
         let conn = self.pool.get()?;
@@ -188,6 +253,77 @@ impl TagRepository {
         })
     }
 
+    /// 按名称查找标签，不存在时在指定工作空间中创建
+    ///
+    /// 只在指定工作空间中查找，已软删除的同名标签会被恢复
+    ///
+    /// ## 返回
+    ///
+    /// 返回 (标签, 是否新建)
+    pub fn find_or_create_by_name(&self, name: &str, workspace_id: &str) -> Result<(Tag, bool)> {
+        let conn = self.pool.get()?;
+        let existing = conn.query_row(
+            "SELECT id, name, color, workspace_id, created_at, updated_at, is_deleted, deleted_at, server_ver, is_dirty, last_synced_at
+             FROM tags WHERE name = ?1 AND workspace_id = ?2
+             ORDER BY is_deleted
+             LIMIT 1",
+            params![name, workspace_id],
+            |row| Ok(Tag {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                color: row.get(2)?,
+                workspace_id: row.get(3)?,
+                created_at: row.get(4)?,
+                updated_at: row.get(5)?,
+                is_deleted: row.get(6)?,
+                deleted_at: row.get(7)?,
+                server_ver: row.get(8)?,
+                is_dirty: row.get(9)?,
+                last_synced_at: row.get(10)?,
+            }),
+        );
+
+        let now = chrono::Utc::now().timestamp();
+        match existing {
+            Ok(mut tag) => {
+                if tag.is_deleted {
+                    conn.execute(
+                        "UPDATE tags SET is_deleted = 0, deleted_at = NULL, updated_at = ?1, is_dirty = 1 WHERE id = ?2",
+                        params![now, &tag.id],
+                    )?;
+                    tag.is_deleted = false;
+                    tag.deleted_at = None;
+                    tag.updated_at = now;
+                    tag.is_dirty = true;
+                }
+                Ok((tag, false))
+            }
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                let id = uuid::Uuid::new_v4().to_string();
+                conn.execute(
+                    "INSERT INTO tags (id, name, color, workspace_id, created_at, updated_at, is_deleted, deleted_at, server_ver, is_dirty, last_synced_at)
+                     VALUES (?1, ?2, NULL, ?3, ?4, ?5, 0, NULL, 1, 1, NULL)",
+                    params![&id, name, workspace_id, now, now],
+                )?;
+
+                Ok((Tag {
+                    id,
+                    name: name.to_string(),
+                    color: None,
+                    workspace_id: Some(workspace_id.to_string()),
+                    created_at: now,
+                    updated_at: now,
+                    is_deleted: false,
+                    deleted_at: None,
+                    server_ver: 1,
+                    is_dirty: true,
+                    last_synced_at: None,
+                }, true))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
     /// 更新标签
     pub fn update(&self, id: &str, req: &UpdateTagRequest) -> Result<Tag> {
         let current = self.find_by_id(id)?
@@ -232,33 +368,99 @@ impl TagRepository {
 
         // 同时软删除所有关联的 note_tags
         conn.execute(
-            "UPDATE note_tags SET is_deleted = 1, deleted_at = ? WHERE tag_id = ?",
+            "UPDATE note_tags SET is_deleted = 1, deleted_at = ?, is_dirty = 1 WHERE tag_id = ? AND is_deleted = 0",
             params![now, id],
         )?;
 
         Ok(())
     }
 
+    /// 将源标签合并到目标标签
+    ///
+    /// 源标签的笔记关联转移到目标标签（笔记已带目标标签时不重复添加），
+    /// 然后软删除源标签及其关联，所有变更的记录都标记为需要同步
+    pub fn merge_into(&self, source_id: &str, target_id: &str) -> Result<TagMergeResult> {
+        let conn = self.pool.get()?;
+        let now = chrono::Utc::now().timestamp();
+        let tx = conn.unchecked_transaction()?;
+
+        let source_relations = tx.query_row(
+            "SELECT COUNT(*) FROM note_tags WHERE tag_id = ?1 AND is_deleted = 0",
+            params![source_id],
+            |row| row.get::<_, i64>(0),
+        )? as usize;
+
+        // 1. 为源标签的笔记添加目标标签（已软删除的关联重新启用，已有的关联保持不变）
+        let moved_relations = tx.execute(
+            "INSERT INTO note_tags (note_id, tag_id, workspace_id, created_at, is_dirty)
+             SELECT note_id, ?2, workspace_id, ?3, 1 FROM note_tags
+             WHERE tag_id = ?1 AND is_deleted = 0
+             ON CONFLICT(note_id, tag_id) DO UPDATE SET
+                is_deleted = 0, deleted_at = NULL, is_dirty = 1
+             WHERE note_tags.is_deleted = 1",
+            params![source_id, target_id, now],
+        )?;
+
+        // 2. 软删除源标签的关联
+        tx.execute(
+            "UPDATE note_tags SET is_deleted = 1, deleted_at = ?1, is_dirty = 1 WHERE tag_id = ?2 AND is_deleted = 0",
+            params![now, source_id],
+        )?;
+
+        // 3. 软删除源标签
+        tx.execute(
+            "UPDATE tags SET is_deleted = 1, deleted_at = ?1, is_dirty = 1 WHERE id = ?2",
+            params![now, source_id],
+        )?;
+
+        tx.commit()?;
+
+        log::info!(
+            "Tag merged: source={}, target={}, moved={}, already_tagged={}",
+            source_id,
+            target_id,
+            moved_relations,
+            source_relations - moved_relations
+        );
+        Ok(TagMergeResult {
+            source_tag_id: source_id.to_string(),
+            target_tag_id: target_id.to_string(),
+            moved_relations,
+            already_tagged: source_relations - moved_relations,
+        })
+    }
+
     /// 为笔记添加标签
     pub fn add_tag_to_note(&self, req: &NoteTagRequest) -> Result<()> {
+        let workspace_id = self.get_current_workspace_id()?;
+        self.add_tag_to_note_in_workspace(req, workspace_id.as_deref())
+    }
+
+    /// 为笔记添加标签（关联记录归属指定工作空间）
+    pub fn add_tag_to_note_in_workspace(&self, req: &NoteTagRequest, workspace_id: Option<&str>) -> Result<()> {
         let conn = self.pool.get()?;
         let now = chrono::Utc::now().timestamp();
-        let workspace_id = self.get_current_workspace_id()?;
 
+        // 已软删除的关联重新启用
         conn.execute(
-            "INSERT OR IGNORE INTO note_tags (note_id, tag_id, workspace_id, created_at)
-             VALUES (?1, ?2, ?3, ?4)",
+            "INSERT INTO note_tags (note_id, tag_id, workspace_id, created_at, is_dirty)
+             VALUES (?1, ?2, ?3, ?4, 1)
+             ON CONFLICT(note_id, tag_id) DO UPDATE SET
+                is_deleted = 0, deleted_at = NULL, is_dirty = 1
+             WHERE note_tags.is_deleted = 1",
             params![&req.note_id, &req.tag_id, workspace_id, now],
         )?;
         Ok(())
     }
 
-    /// 从笔记移除标签
+    /// 从笔记移除标签（软删除，以便同步到服务器）
     pub fn remove_tag_from_note(&self, note_id: &str, tag_id: &str) -> Result<()> {
         let conn = self.pool.get()?;
+        let now = chrono::Utc::now().timestamp();
         conn.execute(
-            "DELETE FROM note_tags WHERE note_id = ?1 AND tag_id = ?2",
-            params![note_id, tag_id],
+            "UPDATE note_tags SET is_deleted = 1, deleted_at = ?1, is_dirty = 1
+             WHERE note_id = ?2 AND tag_id = ?3 AND is_deleted = 0",
+            params![now, note_id, tag_id],
         )?;
         Ok(())
     }
@@ -267,19 +469,26 @@ impl TagRepository {
     pub fn set_note_tags(&self, note_id: &str, tag_ids: &[String]) -> Result<()> {
         let conn = self.pool.get()?;
         let workspace_id = self.get_current_workspace_id()?;
+        let now = chrono::Utc::now().timestamp();
+        let tag_ids_json = serde_json::to_string(tag_ids)
+            .map_err(|e| AppError::Internal(format!("序列化标签 ID 失败: {}", e)))?;
 
-        // 先删除现有标签
+        // 软删除不在新列表中的标签
         conn.execute(
-            "DELETE FROM note_tags WHERE note_id = ?1",
-            params![note_id],
+            "UPDATE note_tags SET is_deleted = 1, deleted_at = ?1, is_dirty = 1
+             WHERE note_id = ?2 AND is_deleted = 0
+               AND tag_id NOT IN (SELECT value FROM json_each(?3))",
+            params![now, note_id, tag_ids_json],
         )?;
 
-        // 添加新标签
-        let now = chrono::Utc::now().timestamp();
+        // 添加新标签（已软删除的关联重新启用）
         for tag_id in tag_ids {
             conn.execute(
-                "INSERT INTO note_tags (note_id, tag_id, workspace_id, created_at)
-                 VALUES (?1, ?2, ?3, ?4)",
+                "INSERT INTO note_tags (note_id, tag_id, workspace_id, created_at, is_dirty)
+                 VALUES (?1, ?2, ?3, ?4, 1)
+                 ON CONFLICT(note_id, tag_id) DO UPDATE SET
+                    is_deleted = 0, deleted_at = NULL, is_dirty = 1
+                 WHERE note_tags.is_deleted = 1",
                 params![note_id, tag_id, workspace_id, now],
             )?;
         }
@@ -335,25 +544,120 @@ impl TagRepository {
         Ok(rows_affected as i64)
     }
 
-    /// 清理超过指定天数的软删除标签
+    /// 清理指定工作空间中超过指定天数的软删除标签
     ///
     /// ## 参数
     ///
+    /// - `workspace_id`: 工作空间 ID（`None` 表示未归属工作空间的数据）
     /// - `days`: 软删除后的保留天数（如 30 天）
     ///
     /// ## 返回
     ///
     /// 返回清理的标签数量
-    pub fn purge_old_deleted_tags(&self, days: i64) -> Result<i64> {
+    pub fn purge_old_deleted_tags(&self, workspace_id: Option<&str>, days: i64) -> Result<i64> {
         let conn = self.pool.get()?;
         let cutoff_time = chrono::Utc::now().timestamp() - (days * 86400);
 
         let rows_affected = conn.execute(
-            "DELETE FROM tags WHERE is_deleted = 1 AND deleted_at < ?",
-            params![cutoff_time],
+            "DELETE FROM tags WHERE is_deleted = 1 AND workspace_id IS ? AND deleted_at < ?",
+            params![workspace_id, cutoff_time],
         ).map_err(AppError::Database)?;
 
-        log::info!("[TagRepository] 清理旧标签: days={}, count={}", days, rows_affected);
+        log::info!("[TagRepository] 清理旧标签: workspace_id={:?}, days={}, count={}", workspace_id, days, rows_affected);
         Ok(rows_affected as i64)
     }
+
+    /// 查找在当前用户的多个工作空间中同名但颜色不同的标签
+    ///
+    /// 按名称、工作空间排序返回这些标签
+    pub fn find_color_inconsistent_tags(&self) -> Result<Vec<Tag>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, name, color, workspace_id, created_at, updated_at, is_deleted, deleted_at, server_ver, is_dirty, last_synced_at
+             FROM tags
+             WHERE is_deleted = 0 AND {scope} AND name IN (
+                SELECT name FROM tags
+                WHERE is_deleted = 0 AND {scope}
+                GROUP BY name
+                HAVING COUNT(DISTINCT COALESCE(color, '')) > 1
+             )
+             ORDER BY name, workspace_id",
+            scope = Self::CURRENT_USER_WORKSPACES
+        ))?;
+
+        let tags = stmt.query_map([], |row| {
+            Ok(Tag {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                color: row.get(2)?,
+                workspace_id: row.get(3)?,
+                created_at: row.get(4)?,
+                updated_at: row.get(5)?,
+                is_deleted: row.get(6)?,
+                deleted_at: row.get(7)?,
+                server_ver: row.get(8)?,
+                is_dirty: row.get(9)?,
+                last_synced_at: row.get(10)?,
+            })
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(tags)
+    }
+
+    /// 将当前用户所有工作空间中指定名称的标签设为同一颜色
+    ///
+    /// 只更新颜色不同的标签，并标记为需要同步
+    pub fn set_color_by_name(&self, name: &str, color: &str) -> Result<usize> {
+        let conn = self.pool.get()?;
+        let now = chrono::Utc::now().timestamp();
+
+        let updated = conn.execute(
+            &format!(
+                "UPDATE tags SET color = ?1, updated_at = ?2, is_dirty = 1
+                 WHERE name = ?3 AND is_deleted = 0 AND color IS NOT ?1 AND {}",
+                Self::CURRENT_USER_WORKSPACES
+            ),
+            params![color, now, name],
+        ).map_err(AppError::Database)?;
+
+        log::info!("[TagRepository] 统一标签颜色: name={}, color={}, count={}", name, color, updated);
+        Ok(updated)
+    }
+
+    /// 查询工作空间内所有未删除的标签及其关联的笔记数量
+    pub fn find_with_note_counts(&self, workspace_id: &str) -> Result<Vec<(Tag, i64)>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT t.id, t.name, t.color, t.workspace_id, t.created_at, t.updated_at, t.is_deleted, t.deleted_at, t.server_ver, t.is_dirty, t.last_synced_at,
+                    (SELECT COUNT(*) FROM note_tags nt
+                     INNER JOIN notes n ON n.id = nt.note_id
+                     WHERE nt.tag_id = t.id AND nt.is_deleted = 0 AND n.is_deleted = 0)
+             FROM tags t
+             WHERE t.is_deleted = 0 AND t.workspace_id = ?
+             ORDER BY t.created_at ASC"
+        )?;
+
+        let tags = stmt.query_map(params![workspace_id], |row| {
+            Ok((
+                Tag {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    color: row.get(2)?,
+                    workspace_id: row.get(3)?,
+                    created_at: row.get(4)?,
+                    updated_at: row.get(5)?,
+                    is_deleted: row.get(6)?,
+                    deleted_at: row.get(7)?,
+                    server_ver: row.get(8)?,
+                    is_dirty: row.get(9)?,
+                    last_synced_at: row.get(10)?,
+                },
+                row.get(11)?,
+            ))
+        })?
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(tags)
+    }
 }
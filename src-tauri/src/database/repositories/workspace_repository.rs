@@ -1,7 +1,9 @@
-use crate::models::Workspace;
+use crate::models::{Note, Workspace, WorkspaceCheckpoint, StorageCounts, WorkspaceStorageStats};
 use crate::database::DbPool;
 use crate::models::error::{Result, AppError};
-use r2d2_sqlite::rusqlite::params;
+use r2d2_sqlite::rusqlite::{params, OptionalExtension};
+use r2d2_sqlite::rusqlite::types::{Value, ValueRef};
+use serde_json::Value as JsonValue;
 
 /// 工作空间数据访问层
 ///
@@ -265,14 +267,23 @@ impl WorkspaceRepository {
                     )
                     .ok();
 
-                if let Some(default_id) = default_workspace {
-                    // 切换到默认工作空间
+                // 没有默认工作空间时切换到排在最前面的工作空间
+                let next_workspace = default_workspace.or_else(|| {
+                    conn.query_row(
+                        "SELECT id FROM workspaces WHERE user_id = ? AND is_deleted = 0
+                         ORDER BY sort_order ASC, created_at ASC LIMIT 1",
+                        params![uid],
+                        |row| row.get(0),
+                    )
+                    .ok()
+                });
+
+                if let Some(next_id) = next_workspace {
                     conn.execute(
                         "UPDATE workspaces SET is_current = 1 WHERE id = ?",
-                        params![&default_id],
+                        params![&next_id],
                     ).map_err(AppError::Database)?;
                 }
-                // 如果没有默认工作空间，则不做任何操作（用户下次需要手动选择）
             }
         }
 
@@ -320,6 +331,19 @@ impl WorkspaceRepository {
         })
     }
 
+    /// 统计用户未删除的工作空间数量
+    pub fn count_active_by_user_id(&self, user_id: &str) -> Result<usize> {
+        let conn = self.pool.get()?;
+
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM workspaces WHERE user_id = ? AND is_deleted = 0",
+            params![user_id],
+            |row| row.get(0),
+        ).map_err(AppError::Database)?;
+
+        Ok(count as usize)
+    }
+
     /// 获取最大排序值
     pub fn get_max_sort_order(&self, user_id: &str) -> Result<i32> {
         let conn = self.pool.get()?;
@@ -480,5 +504,514 @@ impl WorkspaceRepository {
             snapshots,
         })
     }
+
+    /// 查询所有孤立数据（workspace_id = NULL），只读
+    pub fn find_orphan_data(&self) -> Result<crate::services::workspace_service::OrphanMigrationPreview> {
+        use crate::services::workspace_service::{OrphanItem, OrphanMigrationPreview};
+
+        let conn = self.pool.get()?;
+
+        let find_items = |sql: &str| -> Result<Vec<OrphanItem>> {
+            let mut stmt = conn.prepare(sql)?;
+            let items = stmt.query_map([], |row| {
+                Ok(OrphanItem {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+            Ok(items)
+        };
+
+        let notes = find_items("SELECT id, title FROM notes WHERE workspace_id IS NULL ORDER BY updated_at DESC")?;
+        let folders = find_items("SELECT id, name FROM folders WHERE workspace_id IS NULL ORDER BY name")?;
+        let tags = find_items("SELECT id, name FROM tags WHERE workspace_id IS NULL ORDER BY name")?;
+
+        let snapshots: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM note_snapshots WHERE workspace_id IS NULL",
+            [],
+            |row| row.get(0),
+        )?;
+        let note_tags: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM note_tags WHERE workspace_id IS NULL",
+            [],
+            |row| row.get(0),
+        )?;
+
+        Ok(OrphanMigrationPreview {
+            notes,
+            folders,
+            tags,
+            snapshots: snapshots as usize,
+            note_tags: note_tags as usize,
+        })
+    }
+
+    /// 分页查询工作空间中的笔记（按 id 键集分页）
+    ///
+    /// 使用 `id > after_id` 而不是 OFFSET，页数很多时也不会越查越慢
+    pub fn find_notes_page(&self, workspace_id: &str, after_id: Option<&str>, limit: usize) -> Result<Vec<Note>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, title, content, excerpt, markdown_cache, workspace_id, folder_id, is_favorite,
+                    is_deleted, is_pinned, author, created_at, updated_at, deleted_at,
+                    word_count, read_time_minutes,
+                    server_ver, is_dirty, last_synced_at, is_private
+             FROM notes
+             WHERE workspace_id = ?1 AND is_deleted = 0 AND id > ?2
+             ORDER BY id
+             LIMIT ?3",
+        ).map_err(AppError::Database)?;
+
+        let notes = stmt.query_map(params![workspace_id, after_id.unwrap_or(""), limit as i64], |row| {
+            Ok(Note {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                content: row.get(2)?,
+                excerpt: row.get(3)?,
+                markdown_cache: row.get(4)?,
+                workspace_id: row.get(5)?,
+                folder_id: row.get(6)?,
+                is_favorite: row.get(7)?,
+                is_deleted: row.get(8)?,
+                is_pinned: row.get(9)?,
+                is_private: row.get(19)?,
+                author: row.get(10)?,
+                created_at: row.get(11)?,
+                updated_at: row.get(12)?,
+                deleted_at: row.get(13)?,
+                word_count: row.get(14)?,
+                read_time_minutes: row.get(15)?,
+                server_ver: row.get(16)?,
+                is_dirty: row.get(17)?,
+                last_synced_at: row.get(18)?,
+            })
+        }).map_err(AppError::Database)?
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(AppError::Database)?;
+
+        Ok(notes)
+    }
+
+    /// 检查点包含的表及其主键列（按恢复时的写入顺序排列）
+    pub const CHECKPOINT_TABLES: &'static [(&'static str, &'static [&'static str])] = &[
+        ("folders", &["id"]),
+        ("tags", &["id"]),
+        ("notes", &["id"]),
+        ("note_tags", &["note_id", "tag_id"]),
+    ];
+
+    /// 检查点元数据字段（不含 data）
+    const CHECKPOINT_FIELDS: &'static str =
+        "id, workspace_id, name, note_count, folder_count, tag_count, size_bytes, created_at";
+
+    /// 导出工作空间的全部笔记、文件夹、标签和标签关联（含已软删除的数据）
+    ///
+    /// 每张表导出为按列名组织的 JSON 对象数组，列集合随 schema 变化自动适配
+    pub fn dump_checkpoint_tables(&self, workspace_id: &str) -> Result<serde_json::Map<String, JsonValue>> {
+        let conn = self.pool.get()?;
+        let mut tables = serde_json::Map::new();
+
+        for (table, _) in Self::CHECKPOINT_TABLES {
+            let mut stmt = conn.prepare(&format!("SELECT * FROM {} WHERE workspace_id = ?1", table))
+                .map_err(AppError::Database)?;
+            let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+
+            let rows = stmt.query_map(params![workspace_id], |row| {
+                let mut object = serde_json::Map::new();
+                for (i, column) in columns.iter().enumerate() {
+                    object.insert(column.clone(), sql_to_json(row.get_ref(i)?));
+                }
+                Ok(JsonValue::Object(object))
+            }).map_err(AppError::Database)?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(AppError::Database)?;
+
+            tables.insert(table.to_string(), JsonValue::Array(rows));
+        }
+
+        Ok(tables)
+    }
+
+    /// 将工作空间恢复为检查点中的数据（单个事务）
+    ///
+    /// - 检查点中不存在的数据：软删除并标记为需要同步
+    /// - 检查点中存在的数据：写回检查点中的值，只有内容发生变化的行才会标记为需要同步；
+    ///   保留当前的 server_ver / last_synced_at，避免下次同步被误判为冲突
+    pub fn restore_checkpoint_tables(&self, workspace_id: &str, tables: &serde_json::Map<String, JsonValue>) -> Result<()> {
+        let conn = self.pool.get()?;
+        let tx = conn.unchecked_transaction().map_err(AppError::Database)?;
+        // folders.parent_id、notes.folder_id 等外键在提交时再检查，写入顺序不受父子关系限制
+        tx.execute_batch("PRAGMA defer_foreign_keys = ON").map_err(AppError::Database)?;
+
+        let now = chrono::Utc::now().timestamp();
+
+        for (table, key_columns) in Self::CHECKPOINT_TABLES {
+            let existing_columns: Vec<String> = {
+                let mut stmt = tx.prepare(&format!("SELECT name FROM pragma_table_info('{}')", table))
+                    .map_err(AppError::Database)?;
+                let columns = stmt.query_map([], |row| row.get(0))
+                    .map_err(AppError::Database)?
+                    .collect::<std::result::Result<Vec<String>, _>>()
+                    .map_err(AppError::Database)?;
+                columns
+            };
+            let has_dirty = existing_columns.iter().any(|c| c == "is_dirty");
+            let has_updated_at = existing_columns.iter().any(|c| c == "updated_at");
+            let rows: Vec<&serde_json::Map<String, JsonValue>> = tables.get(*table)
+                .and_then(JsonValue::as_array)
+                .map(|rows| rows.iter().filter_map(JsonValue::as_object).collect())
+                .unwrap_or_default();
+
+            // 1. 软删除检查点中不存在的数据
+            let key_expr = key_columns.join(" || char(31) || ");
+            let keys: Vec<String> = rows.iter()
+                .filter_map(|row| {
+                    key_columns.iter()
+                        .map(|column| row.get(*column).and_then(JsonValue::as_str))
+                        .collect::<Option<Vec<_>>>()
+                        .map(|parts| parts.join("\u{1f}"))
+                })
+                .collect();
+            let keys_json = serde_json::to_string(&keys)
+                .map_err(|e| AppError::Internal(format!("序列化主键失败: {}", e)))?;
+
+            tx.execute(
+                &format!(
+                    "UPDATE {} SET is_deleted = 1, deleted_at = ?1{}{}
+                     WHERE workspace_id = ?2 AND is_deleted = 0
+                       AND ({}) NOT IN (SELECT value FROM json_each(?3))",
+                    table,
+                    if has_updated_at { ", updated_at = ?1" } else { "" },
+                    if has_dirty { ", is_dirty = 1" } else { "" },
+                    key_expr
+                ),
+                params![now, workspace_id, keys_json],
+            ).map_err(AppError::Database)?;
+
+            // 2. 写回检查点中的数据
+            for row in rows {
+                let columns: Vec<&str> = row.keys()
+                    .map(String::as_str)
+                    .filter(|c| *c != "is_dirty" && existing_columns.iter().any(|e| e == c))
+                    .collect();
+
+                let mut values: Vec<Value> = columns.iter()
+                    .map(|c| if *c == "workspace_id" { Value::Text(workspace_id.to_string()) } else { json_to_sql(&row[*c]) })
+                    .collect();
+                let mut insert_columns = columns.clone();
+                if has_dirty {
+                    insert_columns.push("is_dirty");
+                    values.push(Value::Integer(1));
+                }
+
+                let update_columns: Vec<&str> = columns.iter()
+                    .copied()
+                    .filter(|c| !key_columns.contains(c) && *c != "server_ver" && *c != "last_synced_at")
+                    .collect();
+
+                let on_conflict = if update_columns.is_empty() {
+                    "DO NOTHING".to_string()
+                } else {
+                    let mut assignments: Vec<String> = update_columns.iter()
+                        .map(|c| format!("{c} = excluded.{c}"))
+                        .collect();
+                    if has_dirty {
+                        assignments.push("is_dirty = 1".to_string());
+                    }
+                    let changed: Vec<String> = update_columns.iter()
+                        .map(|c| format!("{table}.{c} IS NOT excluded.{c}"))
+                        .collect();
+                    format!("DO UPDATE SET {} WHERE {}", assignments.join(", "), changed.join(" OR "))
+                };
+
+                let placeholders: Vec<String> = (1..=insert_columns.len()).map(|i| format!("?{}", i)).collect();
+                tx.execute(
+                    &format!(
+                        "INSERT INTO {} ({}) VALUES ({}) ON CONFLICT({}) {}",
+                        table,
+                        insert_columns.join(", "),
+                        placeholders.join(", "),
+                        key_columns.join(", "),
+                        on_conflict
+                    ),
+                    r2d2_sqlite::rusqlite::params_from_iter(values),
+                ).map_err(AppError::Database)?;
+            }
+        }
+
+        tx.commit().map_err(AppError::Database)?;
+        Ok(())
+    }
+
+    /// 复制工作空间的文件夹、标签以及（可选）笔记和标签关联到另一个工作空间（单个事务）
+    ///
+    /// 只复制未删除的数据，所有行使用新 id，parent_id / folder_id / 标签关联映射到新 id；
+    /// 复制出的数据都标记为需要同步。返回 (文件夹数, 标签数, 笔记数)
+    pub fn duplicate_workspace_contents(
+        &self,
+        source_workspace_id: &str,
+        target_workspace_id: &str,
+        include_notes: bool,
+    ) -> Result<(usize, usize, usize)> {
+        let conn = self.pool.get()?;
+        let tx = conn.unchecked_transaction().map_err(AppError::Database)?;
+        tx.execute_batch(
+            "CREATE TEMP TABLE IF NOT EXISTS duplicate_id_map (old_id TEXT PRIMARY KEY, new_id TEXT NOT NULL);
+             DELETE FROM duplicate_id_map;"
+        ).map_err(AppError::Database)?;
+
+        let now = chrono::Utc::now().timestamp();
+        let map = |column: &str| format!("(SELECT new_id FROM duplicate_id_map WHERE old_id = {})", column);
+
+        let mut tables = vec!["folders", "tags"];
+        if include_notes {
+            tables.push("notes");
+        }
+
+        let mut counts = Vec::with_capacity(tables.len());
+        for table in &tables {
+            // 1. 为每一行分配新 id
+            let ids: Vec<String> = {
+                let mut stmt = tx.prepare(&format!("SELECT id FROM {} WHERE workspace_id = ?1 AND is_deleted = 0", table))
+                    .map_err(AppError::Database)?;
+                let ids = stmt.query_map(params![source_workspace_id], |row| row.get(0))
+                    .map_err(AppError::Database)?
+                    .collect::<std::result::Result<Vec<String>, _>>()
+                    .map_err(AppError::Database)?;
+                ids
+            };
+            for id in &ids {
+                tx.execute(
+                    "INSERT INTO duplicate_id_map (old_id, new_id) VALUES (?1, ?2)",
+                    params![id, uuid::Uuid::new_v4().to_string()],
+                ).map_err(AppError::Database)?;
+            }
+
+            // 2. 按新 id 复制（父文件夹已删除时复制到根目录，所属文件夹已删除时笔记不属于任何文件夹）
+            let copied = tx.execute(
+                &Self::copy_rows_sql(&tx, table, |column| match column {
+                    "id" | "parent_id" | "folder_id" => Some(map(&format!("{}.{}", table, column))),
+                    _ => None,
+                })?,
+                params![target_workspace_id, now, source_workspace_id],
+            ).map_err(AppError::Database)?;
+            counts.push(copied);
+        }
+
+        if include_notes {
+            tx.execute(
+                &format!(
+                    "{} AND note_id IN (SELECT old_id FROM duplicate_id_map) AND tag_id IN (SELECT old_id FROM duplicate_id_map)",
+                    Self::copy_rows_sql(&tx, "note_tags", |column| match column {
+                        "note_id" | "tag_id" => Some(map(&format!("note_tags.{}", column))),
+                        _ => None,
+                    })?
+                ),
+                params![target_workspace_id, now, source_workspace_id],
+            ).map_err(AppError::Database)?;
+        }
+
+        tx.execute("DELETE FROM duplicate_id_map", []).map_err(AppError::Database)?;
+        tx.commit().map_err(AppError::Database)?;
+
+        Ok((counts[0], counts[1], counts.get(2).copied().unwrap_or(0)))
+    }
+
+    /// 生成 `INSERT INTO table SELECT ... FROM table` 语句（列集合随 schema 变化自动适配）
+    ///
+    /// 绑定参数：?1 目标工作空间，?2 当前时间，?3 源工作空间。
+    /// `remap` 返回需要替换的列表达式；同步字段重置为未同步状态
+    fn copy_rows_sql(
+        conn: &r2d2_sqlite::rusqlite::Connection,
+        table: &str,
+        remap: impl Fn(&str) -> Option<String>,
+    ) -> Result<String> {
+        let mut stmt = conn.prepare(&format!("SELECT name FROM pragma_table_info('{}')", table))
+            .map_err(AppError::Database)?;
+        let columns = stmt.query_map([], |row| row.get(0))
+            .map_err(AppError::Database)?
+            .collect::<std::result::Result<Vec<String>, _>>()
+            .map_err(AppError::Database)?;
+
+        let values: Vec<String> = columns.iter()
+            .map(|column| remap(column).unwrap_or_else(|| match column.as_str() {
+                "workspace_id" => "?1".to_string(),
+                "created_at" | "updated_at" => "?2".to_string(),
+                "is_dirty" => "1".to_string(),
+                "server_ver" => "0".to_string(),
+                "last_synced_at" | "deleted_at" => "NULL".to_string(),
+                _ => column.clone(),
+            }))
+            .collect();
+
+        Ok(format!(
+            "INSERT INTO {table} ({}) SELECT {} FROM {table} WHERE workspace_id = ?3 AND is_deleted = 0",
+            columns.join(", "),
+            values.join(", ")
+        ))
+    }
+
+    /// 保存检查点
+    pub fn insert_checkpoint(&self, checkpoint: &WorkspaceCheckpoint, data: &[u8]) -> Result<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "INSERT INTO workspace_checkpoints
+             (id, workspace_id, name, note_count, folder_count, tag_count, size_bytes, created_at, data)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                checkpoint.id,
+                checkpoint.workspace_id,
+                checkpoint.name,
+                checkpoint.note_count as i64,
+                checkpoint.folder_count as i64,
+                checkpoint.tag_count as i64,
+                checkpoint.size_bytes,
+                checkpoint.created_at,
+                data,
+            ],
+        ).map_err(AppError::Database)?;
+        Ok(())
+    }
+
+    /// 列出工作空间的检查点（最新的在前）
+    pub fn find_checkpoints(&self, workspace_id: &str) -> Result<Vec<WorkspaceCheckpoint>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM workspace_checkpoints WHERE workspace_id = ?1 ORDER BY created_at DESC, rowid DESC",
+            Self::CHECKPOINT_FIELDS
+        )).map_err(AppError::Database)?;
+
+        let checkpoints = stmt.query_map(params![workspace_id], Self::row_to_checkpoint)
+            .map_err(AppError::Database)?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(AppError::Database)?;
+
+        Ok(checkpoints)
+    }
+
+    /// 根据 ID 获取检查点及其压缩数据
+    pub fn find_checkpoint(&self, id: &str) -> Result<Option<(WorkspaceCheckpoint, Vec<u8>)>> {
+        let conn = self.pool.get()?;
+        conn.query_row(
+            &format!("SELECT {}, data FROM workspace_checkpoints WHERE id = ?1", Self::CHECKPOINT_FIELDS),
+            params![id],
+            |row| Ok((Self::row_to_checkpoint(row)?, row.get(8)?)),
+        ).optional().map_err(AppError::Database)
+    }
+
+    /// 清理超出数量或总大小上限的旧检查点（从最新的开始保留，至少保留一个）
+    ///
+    /// 返回删除的数量
+    pub fn prune_checkpoints(&self, workspace_id: &str, max_count: usize, max_total_bytes: i64) -> Result<usize> {
+        let checkpoints = self.find_checkpoints(workspace_id)?;
+
+        let mut kept = 0usize;
+        let mut total_bytes = 0i64;
+        let mut expired = Vec::new();
+        for checkpoint in checkpoints {
+            if kept == 0 || (kept < max_count && total_bytes + checkpoint.size_bytes <= max_total_bytes) {
+                kept += 1;
+                total_bytes += checkpoint.size_bytes;
+            } else {
+                expired.push(checkpoint.id);
+            }
+        }
+
+        if expired.is_empty() {
+            return Ok(0);
+        }
+
+        let conn = self.pool.get()?;
+        let ids_json = serde_json::to_string(&expired)
+            .map_err(|e| AppError::Internal(format!("序列化检查点 ID 失败: {}", e)))?;
+        let deleted = conn.execute(
+            "DELETE FROM workspace_checkpoints WHERE id IN (SELECT value FROM json_each(?1))",
+            params![ids_json],
+        ).map_err(AppError::Database)?;
+
+        Ok(deleted)
+    }
+
+    /// 统计用户各工作空间的存储使用情况（按 sort_order 排序，不含已删除的工作空间）
+    ///
+    /// 数量按 is_deleted 区分正常和回收站；近似大小为笔记（含回收站）和快照内容的字节数之和
+    pub fn storage_stats(&self, user_id: &str) -> Result<Vec<WorkspaceStorageStats>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT w.id, w.name,
+                    (SELECT COUNT(*) FROM notes WHERE workspace_id = w.id AND is_deleted = 0),
+                    (SELECT COUNT(*) FROM folders WHERE workspace_id = w.id AND is_deleted = 0),
+                    (SELECT COUNT(*) FROM tags WHERE workspace_id = w.id AND is_deleted = 0),
+                    (SELECT COUNT(*) FROM note_snapshots WHERE workspace_id = w.id),
+                    (SELECT COUNT(*) FROM notes WHERE workspace_id = w.id AND is_deleted = 1),
+                    (SELECT COUNT(*) FROM folders WHERE workspace_id = w.id AND is_deleted = 1),
+                    (SELECT COUNT(*) FROM tags WHERE workspace_id = w.id AND is_deleted = 1),
+                    (SELECT COALESCE(SUM(LENGTH(CAST(content AS BLOB))), 0) FROM notes WHERE workspace_id = w.id)
+                        + (SELECT COALESCE(SUM(LENGTH(CAST(content AS BLOB))), 0) FROM note_snapshots WHERE workspace_id = w.id)
+             FROM workspaces w
+             WHERE w.user_id = ?1 AND w.is_deleted = 0
+             ORDER BY w.sort_order ASC, w.created_at ASC"
+        ).map_err(AppError::Database)?;
+
+        let count = |row: &r2d2_sqlite::rusqlite::Row, idx: usize| row.get::<_, i64>(idx).map(|n| n as usize);
+        let stats = stmt.query_map(params![user_id], |row| {
+            Ok(WorkspaceStorageStats {
+                workspace_id: row.get(0)?,
+                workspace_name: row.get(1)?,
+                counts: StorageCounts {
+                    notes: count(row, 2)?,
+                    folders: count(row, 3)?,
+                    tags: count(row, 4)?,
+                    snapshots: count(row, 5)?,
+                    trashed_notes: count(row, 6)?,
+                    trashed_folders: count(row, 7)?,
+                    trashed_tags: count(row, 8)?,
+                    approx_bytes: row.get(9)?,
+                },
+            })
+        })
+        .map_err(AppError::Database)?
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(AppError::Database)?;
+
+        Ok(stats)
+    }
+
+    fn row_to_checkpoint(row: &r2d2_sqlite::rusqlite::Row) -> r2d2_sqlite::rusqlite::Result<WorkspaceCheckpoint> {
+        Ok(WorkspaceCheckpoint {
+            id: row.get(0)?,
+            workspace_id: row.get(1)?,
+            name: row.get(2)?,
+            note_count: row.get::<_, i64>(3)? as usize,
+            folder_count: row.get::<_, i64>(4)? as usize,
+            tag_count: row.get::<_, i64>(5)? as usize,
+            size_bytes: row.get(6)?,
+            created_at: row.get(7)?,
+        })
+    }
+}
+
+/// SQLite 值转 JSON（检查点涉及的表没有 BLOB 列）
+fn sql_to_json(value: ValueRef) -> JsonValue {
+    match value {
+        ValueRef::Null | ValueRef::Blob(_) => JsonValue::Null,
+        ValueRef::Integer(i) => JsonValue::from(i),
+        ValueRef::Real(f) => serde_json::Number::from_f64(f).map(JsonValue::Number).unwrap_or(JsonValue::Null),
+        ValueRef::Text(t) => JsonValue::String(String::from_utf8_lossy(t).into_owned()),
+    }
 }
 
+/// JSON 值转 SQLite 值
+fn json_to_sql(value: &JsonValue) -> Value {
+    match value {
+        JsonValue::Null => Value::Null,
+        JsonValue::Bool(b) => Value::Integer(*b as i64),
+        JsonValue::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => n.as_f64().map(Value::Real).unwrap_or(Value::Null),
+        },
+        JsonValue::String(s) => Value::Text(s.clone()),
+        other => Value::Text(other.to_string()),
+    }
+}
@@ -17,6 +17,9 @@ pub fn init_schema(conn: &Connection) -> Result<()> {
             is_favorite BOOLEAN DEFAULT 0,
             is_deleted BOOLEAN DEFAULT 0,
             is_pinned BOOLEAN DEFAULT 0,
+            pinned_at INTEGER,
+            pin_sort_order INTEGER,
+            is_private BOOLEAN DEFAULT 0,
             author TEXT,
             created_at INTEGER NOT NULL,
             updated_at INTEGER NOT NULL,
@@ -51,7 +54,7 @@ pub fn init_schema(conn: &Connection) -> Result<()> {
 
         CREATE TABLE IF NOT EXISTS tags (
             id TEXT PRIMARY KEY,
-            name TEXT NOT NULL UNIQUE,
+            name TEXT NOT NULL,
             color TEXT,
             workspace_id TEXT,
             created_at INTEGER NOT NULL,
@@ -71,6 +74,7 @@ pub fn init_schema(conn: &Connection) -> Result<()> {
             created_at INTEGER NOT NULL,
             is_deleted BOOLEAN DEFAULT 0,
             deleted_at INTEGER,
+            is_dirty BOOLEAN DEFAULT 1,
             PRIMARY KEY (note_id, tag_id),
             FOREIGN KEY (note_id) REFERENCES notes(id) ON DELETE CASCADE,
             FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE
@@ -107,6 +111,7 @@ pub fn init_schema(conn: &Connection) -> Result<()> {
         CREATE INDEX IF NOT EXISTS idx_folders_is_deleted ON folders(is_deleted);
         CREATE INDEX IF NOT EXISTS idx_tags_workspace_id ON tags(workspace_id);
         CREATE INDEX IF NOT EXISTS idx_tags_is_deleted ON tags(is_deleted);
+        CREATE INDEX IF NOT EXISTS idx_tags_workspace_name ON tags(workspace_id, name);
         CREATE INDEX IF NOT EXISTS idx_note_tags_note_id ON note_tags(note_id);
         CREATE INDEX IF NOT EXISTS idx_note_tags_tag_id ON note_tags(tag_id);
         CREATE INDEX IF NOT EXISTS idx_note_tags_workspace_id ON note_tags(workspace_id);
@@ -114,6 +119,8 @@ pub fn init_schema(conn: &Connection) -> Result<()> {
 
         CREATE TABLE IF NOT EXISTS editor_settings (
             id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL DEFAULT '默认',
+            is_active BOOLEAN NOT NULL DEFAULT 0,
             content_font_family TEXT NOT NULL DEFAULT 'Inter, Avenir, Helvetica, Arial, sans-serif',
             content_font_size INTEGER NOT NULL DEFAULT 16,
             content_font_weight INTEGER NOT NULL DEFAULT 400,
@@ -230,6 +237,21 @@ pub fn init_schema(conn: &Connection) -> Result<()> {
             sync_interval_minutes INTEGER DEFAULT 5,
             theme TEXT DEFAULT 'system',
             language TEXT DEFAULT 'zh-CN',
+            excerpt_length INTEGER DEFAULT 200,
+            max_note_length INTEGER DEFAULT 0,
+            conflict_strategies TEXT,
+            tag_palette TEXT,
+            max_synced_snapshot_bytes INTEGER DEFAULT 0,
+            pause_sync_on_low_battery BOOLEAN DEFAULT 0,
+            low_battery_threshold INTEGER DEFAULT 20,
+            pause_sync_on_metered BOOLEAN DEFAULT 0,
+            max_sync_retries INTEGER DEFAULT 5,
+            sync_request_max_attempts INTEGER DEFAULT 3,
+            sync_retry_base_delay_ms INTEGER DEFAULT 500,
+            sync_compression_threshold_bytes INTEGER DEFAULT 32768,
+            token_refresh_window_minutes INTEGER DEFAULT 10,
+            trash_retention_days INTEGER DEFAULT 30,
+            workspace_trash_retention TEXT,
             updated_at INTEGER NOT NULL
         );
 
@@ -250,6 +272,33 @@ pub fn init_schema(conn: &Connection) -> Result<()> {
     "
     )?;
 
+    // 记录已执行的迁移版本（由 MigrationService 维护）
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            description TEXT NOT NULL,
+            applied_at INTEGER NOT NULL
+        );"
+    )?;
+
     log::info!("Database schema initialized successfully");
     Ok(())
 }
+
+/// 如果表中不存在指定列，则添加该列
+///
+/// `CREATE TABLE IF NOT EXISTS` 不会修改已存在的表，新增列需要通过迁移单独处理
+pub fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|name| name.ok())
+        .any(|name| name == column);
+
+    if !exists {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+        log::info!("Added column {}.{}", table, column);
+    }
+
+    Ok(())
+}
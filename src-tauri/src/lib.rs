@@ -6,10 +6,10 @@ mod services;
 
 use database::init_db_pool;
 use database::repositories::{
-    EditorSettingsRepository, FolderRepository, KeybindingRepository, NoteRepository,
+    AttachmentRepository, EditorSettingsRepository, FolderRepository, KeybindingRepository, NoteRepository,
     TagRepository, UserProfileRepository, WorkspaceRepository,
 };
-use services::{AppSettingsService, AuthService, AutoSyncService, CleanupService, SnapshotService, SyncService, SingleSyncService, UserProfileService, WorkspaceService};
+use services::{AppSettingsService, AttachmentService, AuthService, AutoSyncService, CleanupService, CryptoService, IntegrityService, MigrationService, SnapshotService, SupportBundleService, SyncHistoryService, SyncService, SingleSyncService, UserProfileService, WorkspaceService};
 use services::{EditorSettingsService, FolderService, KeybindingService, NoteService, TagService};
 use tauri::Manager;
 
@@ -65,12 +65,25 @@ pub fn run() {
             let pool =
                 init_db_pool(db_path.to_str().unwrap()).expect("Failed to initialize database");
 
+            // 加载本机安装密钥（token 加密使用，失败时退回不含安装密钥的加密方案）
+            if let Err(e) = CryptoService::init_install_secret(&app_data_dir) {
+                log::warn!("Failed to initialize install secret: {}", e);
+            }
+
+            // 执行数据库迁移（必须在其他服务使用数据库之前）
+            let migration_service = MigrationService::new(pool.clone());
+            migration_service.migrate_to_latest().expect("Failed to run database migrations");
+
             // 初始化仓库（先创建所有仓库）
             let note_repo = NoteRepository::new(pool.clone());
             let folder_repo = FolderRepository::new(pool.clone());
+            let tag_repo = TagRepository::new(pool.clone());
+
+            // 应用设置服务（NoteService 需要读取摘要长度）
+            let app_settings_service = AppSettingsService::new(pool.clone());
 
-            // 初始化服务（NoteService 需要 FolderRepository）
-            let note_service = NoteService::new(note_repo, folder_repo.clone());
+            // 初始化服务（NoteService 需要 FolderRepository、TagRepository 和 AppSettingsService）
+            let note_service = NoteService::new(note_repo, folder_repo.clone(), tag_repo.clone(), app_settings_service.clone());
             let folder_service = FolderService::new(folder_repo);
 
             // 初始化快捷键服务（使用文件存储）
@@ -83,19 +96,32 @@ pub fn run() {
             let editor_settings_service = EditorSettingsService::new(editor_settings_repo);
 
             // 初始化标签服务
-            let tag_repo = TagRepository::new(pool.clone());
-            let tag_service = TagService::new(tag_repo);
+            let tag_service = TagService::new(tag_repo, app_settings_service.clone());
 
             // ===== 初始化云端同步相关服务 =====
-            // 同步服务需要直接使用连接池
-            let sync_service = SyncService::new(pool.clone());
+            // 同步服务需要直接使用连接池，同步事件通过 AppHandle 发送给前端
+            let sync_service = SyncService::new(pool.clone())
+                .with_event_sink(std::sync::Arc::new(app.handle().clone()));
 
             // 单个同步服务（需要 SyncService）
             let single_sync_service = SingleSyncService::new(pool.clone(), sync_service.clone());
 
-            // 自动同步服务（需要 SyncService 和 AppSettingsService）
-            let app_settings_service = AppSettingsService::new(pool.clone());
-            let auto_sync_service = AutoSyncService::new(sync_service.clone(), app_settings_service.clone());
+            // 同步历史服务（从服务器查询）
+            let sync_history_service = SyncHistoryService::new(pool.clone());
+
+            // 认证服务
+            let auth_service = AuthService::new(pool.clone());
+            if let Err(e) = auth_service.rotate_token_encryption() {
+                log::warn!("Failed to rotate token encryption: {}", e);
+            }
+
+            // 自动同步服务（需要 SyncService、SingleSyncService、AppSettingsService 和 AuthService）
+            let auto_sync_service = AutoSyncService::new(
+                sync_service.clone(),
+                single_sync_service.clone(),
+                app_settings_service.clone(),
+                auth_service.clone(),
+            );
 
             // 自动清理服务（需要 NoteService、FolderService、TagService、DbPool）
             let cleanup_service = CleanupService::new(
@@ -105,12 +131,15 @@ pub fn run() {
                 pool.clone(),
             );
 
-            // 认证服务
-            let auth_service = AuthService::new(pool.clone());
+            // 数据完整性服务
+            let integrity_service = IntegrityService::new(pool.clone());
 
             // 快照服务
             let snapshot_service = SnapshotService::new(pool.clone());
 
+            // 附件服务
+            let attachment_service = AttachmentService::new(AttachmentRepository::new(pool.clone()));
+
             // 用户资料服务
             let user_profile_repo = UserProfileRepository::new(pool.clone());
             let user_profile_service = UserProfileService::new(user_profile_repo, pool.clone());
@@ -119,21 +148,34 @@ pub fn run() {
             let workspace_repo = WorkspaceRepository::new(pool.clone());
             let workspace_service = WorkspaceService::new(workspace_repo);
 
+            // 支持包服务（需要读取数据目录中的快捷键配置和日志）
+            let support_bundle_service = SupportBundleService::new(
+                pool.clone(),
+                app_data_dir.clone(),
+                app_settings_service.clone(),
+                sync_service.clone(),
+            );
+
             // 注册服务到 Tauri 状态
             app.manage(note_service);
             app.manage(folder_service);
             app.manage(keybinding_service);
             app.manage(editor_settings_service);
             app.manage(tag_service);
+            app.manage(migration_service);
+            app.manage(integrity_service);
             // ===== 云端同步服务 =====
             app.manage(sync_service);
             app.manage(single_sync_service);
+            app.manage(sync_history_service);
             app.manage(auto_sync_service.clone()); // 克隆以便后续使用
             app.manage(app_settings_service);
             app.manage(auth_service.clone()); // 克隆以便后续使用
             app.manage(snapshot_service);
+            app.manage(attachment_service);
             app.manage(user_profile_service);
             app.manage(workspace_service);
+            app.manage(support_bundle_service);
             // ===== 自动清理服务 =====
             app.manage(cleanup_service.clone()); // 克隆以便后续使用
 
@@ -204,20 +246,52 @@ pub fn run() {
             commands::restore_notes,
             commands::list_notes,
             commands::list_deleted_notes,
+            commands::list_favorite_notes,
             commands::search_notes,
+            commands::search_notes_fts,
+            commands::export_note_markdown,
+            commands::export_notes_markdown,
+            commands::export_workspace,
+            commands::import_markdown_archive,
+            commands::search_global,
+            commands::move_note,
+            commands::duplicate_note,
             commands::move_notes_to_folder,
+            commands::move_notes_to_folder_with_undo,
+            commands::undo_last_move,
+            commands::get_writing_stats,
             commands::get_notes_count,
+            commands::regenerate_excerpts,
+            commands::regenerate_markdown_cache,
+            commands::regenerate_all_markdown_caches,
+            commands::renumber_folder_notes,
+            commands::set_favorite_batch,
+            commands::set_pinned_batch,
+            commands::set_pin_order,
+            commands::set_note_private,
             commands::permanently_delete_note,
             commands::permanently_delete_notes,
+            commands::purge_trash_before,
+            commands::get_schema_version,
+            commands::find_workspace_folder_mismatches,
+            commands::repair_workspace_folder_mismatches,
+            commands::find_cross_workspace_folder_parents,
+            commands::repair_cross_workspace_folder_parents,
+            commands::find_invalid_content_notes,
+            commands::repair_invalid_content_notes,
+            commands::find_folder_cycles,
+            commands::repair_folder_cycles,
             // 文件夹命令
             commands::create_folder,
             commands::get_folder,
             commands::update_folder,
             commands::delete_folder,
+            commands::restore_folder,
             commands::list_folders,
             commands::move_folder,
             commands::get_folder_path,
             commands::permanently_delete_folder,
+            commands::get_folder_last_modified,
             // 快捷键命令
             commands::load_keybindings,
             commands::save_keybindings,
@@ -226,6 +300,12 @@ pub fn run() {
             // 编辑器设置命令
             commands::get_editor_settings,
             commands::update_editor_settings,
+            commands::list_editor_profiles,
+            commands::create_editor_profile,
+            commands::delete_editor_profile,
+            commands::activate_editor_profile,
+            commands::export_editor_settings,
+            commands::import_editor_settings,
             // 标签命令
             commands::get_all_tags,
             commands::get_tag,
@@ -238,6 +318,10 @@ pub fn run() {
             commands::set_note_tags,
             commands::permanently_delete_tag,
             commands::permanently_delete_tags,
+            commands::find_tag_color_inconsistencies,
+            commands::normalize_tag_colors,
+            commands::find_similar_tags,
+            commands::merge_tags,
             // ===== 工作空间命令 =====
             commands::list_workspaces,
             commands::create_workspace,
@@ -246,27 +330,64 @@ pub fn run() {
             commands::set_default_workspace,
             commands::get_current_workspace,
             commands::switch_workspace,
+            commands::export_workspace_bundle_streaming,
+            commands::create_workspace_checkpoint,
+            commands::list_checkpoints,
+            commands::restore_checkpoint,
+            commands::duplicate_workspace,
+            commands::preview_orphan_migration,
+            commands::migrate_orphan_data,
+            commands::get_storage_stats,
+            // 支持包命令
+            commands::export_support_bundle,
             // ===== 云端同步命令 =====
             commands::sync_now,
+            commands::sync_priority_notes,
+            commands::sync_workspace,
             commands::get_sync_status,
             commands::sync_single_note,
             commands::sync_single_tag,
             commands::sync_single_snapshot,
             commands::sync_single_folder,
+            commands::list_sync_history_filtered,
+            commands::get_sync_device_summary,
+            commands::detect_flapping_items,
+            commands::list_pending_changes,
+            commands::list_local_only_notes,
+            commands::get_power_sync_state,
+            commands::rebuild_folders_from_server,
+            commands::diff_note_against_server,
+            commands::list_dead_letter_syncs,
+            commands::retry_dead_letter,
+            commands::get_server_limits,
+            commands::get_pending_conflicts,
+            commands::resolve_note_conflict,
             commands::login,
             commands::register,
             commands::logout,
+            commands::logout_with_server,
+            commands::logout_all_devices,
+            commands::change_password,
+            commands::list_devices,
+            commands::update_device,
             commands::get_current_user,
             commands::is_authenticated,
             commands::list_accounts,
             commands::switch_account,
             commands::remove_account,
             commands::refresh_access_token,
+            commands::validate_session,
             commands::create_snapshot,
             commands::list_snapshots,
             commands::get_snapshot,
             commands::delete_snapshot,
             commands::restore_from_snapshot,
+            commands::export_note_archive,
+            // 附件命令
+            commands::add_attachment,
+            commands::list_attachments,
+            commands::get_attachment,
+            commands::delete_attachment,
             // 用户资料命令
             commands::get_user_profile,
             commands::update_user_profile,
@@ -275,6 +396,10 @@ pub fn run() {
             commands::get_app_settings,
             commands::update_app_settings,
             commands::reset_app_settings,
+            commands::get_max_note_length,
+            commands::get_tag_palette,
+            commands::set_tag_palette,
+            commands::set_sync_interval,
             commands::get_default_server_url,
             // 兼容性命令（已废弃，保留兼容性）
             commands::note_generate_id,
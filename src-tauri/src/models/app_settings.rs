@@ -1,4 +1,61 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use super::sync::ConflictStrategies;
+
+/// 默认摘要长度（字符数）
+pub const DEFAULT_EXCERPT_LENGTH: i32 = 200;
+
+/// 摘要长度允许范围（字符数）
+pub const MIN_EXCERPT_LENGTH: i32 = 20;
+pub const MAX_EXCERPT_LENGTH: i32 = 1000;
+
+/// 自动同步间隔允许范围（分钟）
+pub const MIN_SYNC_INTERVAL_MINUTES: i32 = 1;
+pub const MAX_SYNC_INTERVAL_MINUTES: i32 = 1440;
+
+/// 默认笔记内容长度上限（字符数，0 表示不限制）
+pub const DEFAULT_MAX_NOTE_LENGTH: i32 = 0;
+
+/// 默认同步快照大小上限（字节，0 表示不限制）
+pub const DEFAULT_MAX_SYNCED_SNAPSHOT_BYTES: i64 = 0;
+
+/// 默认低电量阈值（百分比，电量不高于该值且使用电池供电时暂停自动同步）
+pub const DEFAULT_LOW_BATTERY_THRESHOLD: i32 = 20;
+
+/// 默认单项同步最大重试次数（超过后进入死信队列）
+pub const DEFAULT_MAX_SYNC_RETRIES: i32 = 5;
+
+/// 默认同步请求最大尝试次数（连接失败、超时或 5xx 时重试）
+pub const DEFAULT_SYNC_REQUEST_MAX_ATTEMPTS: i32 = 3;
+
+/// 默认同步请求重试基础间隔（毫秒，每次重试翻倍）
+pub const DEFAULT_SYNC_RETRY_BASE_DELAY_MS: i32 = 500;
+
+/// 默认同步请求压缩阈值（字节，请求体达到该大小时使用 gzip 压缩，0 表示不压缩）
+pub const DEFAULT_SYNC_COMPRESSION_THRESHOLD_BYTES: i64 = 32 * 1024;
+
+/// 默认 token 主动刷新窗口（分钟，access token 剩余有效期不超过该值时在自动同步前刷新，0 表示不主动刷新）
+pub const DEFAULT_TOKEN_REFRESH_WINDOW_MINUTES: i32 = 10;
+
+/// 默认回收站保留天数（超过后自动永久删除，0 表示不自动清理）
+pub const DEFAULT_TRASH_RETENTION_DAYS: i32 = 30;
+
+/// 回收站保留天数上限
+pub const MAX_TRASH_RETENTION_DAYS: i32 = 3650;
+
+/// 默认标签调色板（新建标签未指定颜色时依次使用）
+pub const DEFAULT_TAG_PALETTE: &[&str] = &[
+    "#ef4444", "#f97316", "#eab308", "#22c55e",
+    "#14b8a6", "#3b82f6", "#8b5cf6", "#ec4899",
+];
+
+/// 是否为合法的十六进制颜色（`#RGB` 或 `#RRGGBB`）
+pub fn is_valid_hex_color(color: &str) -> bool {
+    match color.strip_prefix('#') {
+        Some(hex) => matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit()),
+        None => false,
+    }
+}
 
 /// 应用设置模型（全局配置）
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -9,9 +66,34 @@ pub struct AppSettings {
     pub sync_interval_minutes: i32,
     pub theme: String,
     pub language: String,
+    pub excerpt_length: i32,
+    pub max_note_length: i32,  // 笔记内容长度上限（字符数，0 表示不限制）
+    pub conflict_strategies: ConflictStrategies,  // 按实体类型的冲突解决策略
+    pub tag_palette: Vec<String>,  // 标签调色板（空列表表示不自动分配颜色）
+    pub max_synced_snapshot_bytes: i64,  // 参与同步的快照内容大小上限（字节，0 表示不限制）
+    pub pause_sync_on_low_battery: bool,  // 低电量且未接电源时暂停自动同步
+    pub low_battery_threshold: i32,  // 低电量阈值（百分比）
+    pub pause_sync_on_metered: bool,  // 按流量计费网络下暂停自动同步
+    pub max_sync_retries: i32,  // 单项同步失败后的最大自动重试次数
+    pub sync_request_max_attempts: i32,  // 同步请求最大尝试次数（含首次请求）
+    pub sync_retry_base_delay_ms: i32,  // 同步请求重试基础间隔（毫秒）
+    pub sync_compression_threshold_bytes: i64,  // 同步请求体 gzip 压缩阈值（字节，0 表示不压缩）
+    pub token_refresh_window_minutes: i32,  // 自动同步前主动刷新 token 的窗口（分钟，0 表示不主动刷新）
+    pub trash_retention_days: i32,  // 回收站默认保留天数（0 表示不自动清理）
+    pub workspace_trash_retention: HashMap<String, i32>,  // 按工作空间覆盖的回收站保留天数（workspace_id -> 天数）
     pub updated_at: i64,
 }
 
+impl AppSettings {
+    /// 指定工作空间的回收站保留天数（未单独设置时使用默认值，0 表示不自动清理）
+    pub fn trash_retention_days_for(&self, workspace_id: Option<&str>) -> i32 {
+        workspace_id
+            .and_then(|id| self.workspace_trash_retention.get(id))
+            .copied()
+            .unwrap_or(self.trash_retention_days)
+    }
+}
+
 /// 更新应用设置请求
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdateAppSettings {
@@ -20,6 +102,21 @@ pub struct UpdateAppSettings {
     pub sync_interval_minutes: Option<i32>,
     pub theme: Option<String>,
     pub language: Option<String>,
+    pub excerpt_length: Option<i32>,
+    pub max_note_length: Option<i32>,
+    pub conflict_strategies: Option<ConflictStrategies>,
+    pub tag_palette: Option<Vec<String>>,
+    pub max_synced_snapshot_bytes: Option<i64>,
+    pub pause_sync_on_low_battery: Option<bool>,
+    pub low_battery_threshold: Option<i32>,
+    pub pause_sync_on_metered: Option<bool>,
+    pub max_sync_retries: Option<i32>,
+    pub sync_request_max_attempts: Option<i32>,
+    pub sync_retry_base_delay_ms: Option<i32>,
+    pub sync_compression_threshold_bytes: Option<i64>,
+    pub token_refresh_window_minutes: Option<i32>,
+    pub trash_retention_days: Option<i32>,
+    pub workspace_trash_retention: Option<HashMap<String, i32>>,
 }
 
 impl Default for AppSettings {
@@ -32,6 +129,21 @@ impl Default for AppSettings {
             sync_interval_minutes: 5,
             theme: "system".to_string(),
             language: "zh-CN".to_string(),
+            excerpt_length: DEFAULT_EXCERPT_LENGTH,
+            max_note_length: DEFAULT_MAX_NOTE_LENGTH,
+            conflict_strategies: ConflictStrategies::default(),
+            tag_palette: DEFAULT_TAG_PALETTE.iter().map(|c| c.to_string()).collect(),
+            max_synced_snapshot_bytes: DEFAULT_MAX_SYNCED_SNAPSHOT_BYTES,
+            pause_sync_on_low_battery: false,
+            low_battery_threshold: DEFAULT_LOW_BATTERY_THRESHOLD,
+            pause_sync_on_metered: false,
+            max_sync_retries: DEFAULT_MAX_SYNC_RETRIES,
+            sync_request_max_attempts: DEFAULT_SYNC_REQUEST_MAX_ATTEMPTS,
+            sync_retry_base_delay_ms: DEFAULT_SYNC_RETRY_BASE_DELAY_MS,
+            sync_compression_threshold_bytes: DEFAULT_SYNC_COMPRESSION_THRESHOLD_BYTES,
+            token_refresh_window_minutes: DEFAULT_TOKEN_REFRESH_WINDOW_MINUTES,
+            trash_retention_days: DEFAULT_TRASH_RETENTION_DAYS,
+            workspace_trash_retention: HashMap::new(),
             updated_at: now,
         }
     }
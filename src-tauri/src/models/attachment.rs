@@ -0,0 +1,49 @@
+use serde::{Serialize, Deserialize};
+
+/// 笔记附件（粘贴的图片等，数据以 Base64 编码存储）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    pub id: String,  // 附件唯一标识（UUID）
+    pub note_id: String,  // 所属笔记 ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace_id: Option<String>,  // 所属工作空间 ID（与笔记一致）
+    pub filename: String,  // 文件名
+    pub mime_type: String,  // MIME 类型（如 image/png）
+    pub size_bytes: i64,  // 解码后的大小（字节）
+    pub data: String,  // 文件数据（Base64 编码）
+    pub created_at: i64,  // 创建时间（Unix 时间戳，秒）
+    pub updated_at: i64,  // 更新时间（Unix 时间戳，秒）
+    #[serde(default)]
+    pub is_deleted: bool,  // 是否已删除（软删除）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<i64>,  // 删除时间（Unix 时间戳，秒）
+    #[serde(default)]
+    pub server_ver: i32,  // 服务器版本号
+    #[serde(default)]
+    pub is_dirty: bool,  // 是否需要同步
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_synced_at: Option<i64>,  // 最后同步时间（Unix 时间戳，秒）
+}
+
+/// 添加附件请求
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddAttachmentRequest {
+    pub note_id: String,  // 所属笔记 ID
+    pub filename: String,  // 文件名
+    pub mime_type: String,  // MIME 类型
+    pub data: String,  // 文件数据（Base64 编码）
+}
+
+/// 附件列表项（不含文件数据）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachmentListItem {
+    pub id: String,  // 附件 ID
+    pub note_id: String,  // 所属笔记 ID
+    pub filename: String,  // 文件名
+    pub mime_type: String,  // MIME 类型
+    pub size_bytes: i64,  // 大小（字节）
+    pub created_at: i64,  // 创建时间（Unix 时间戳，秒）
+}
@@ -57,6 +57,24 @@ pub struct AccountWithProfile {
     pub profile: Option<UserProfile>,  // 用户资料
 }
 
+/// 已登录设备（从服务器返回）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Device {
+    pub id: String,  // 设备 ID
+    pub device_name: String,  // 展示名称（已设置自定义名称时为自定义名称）
+    #[serde(default)]
+    pub custom_name: Option<String>,  // 用户自定义名称
+    pub device_type: String,  // 设备类型（desktop/mobile/tablet）
+    #[serde(default)]
+    pub trusted: bool,  // 是否为受信任设备
+    pub last_seen_at: i64,  // 最后活跃时间（Unix 时间戳，秒）
+    #[serde(default)]
+    pub last_heartbeat_at: Option<i64>,  // 最近心跳时间（Unix 时间戳，秒）
+    pub created_at: i64,  // 首次登录时间（Unix 时间戳，秒）
+    #[serde(default)]
+    pub online: bool,  // 是否在线（最近心跳在服务器配置的在线阈值内）
+}
+
 /// Token 刷新请求
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RefreshTokenRequest {
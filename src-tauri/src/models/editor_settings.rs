@@ -1,10 +1,18 @@
 use serde::{Serialize, Deserialize};
 
-/// 编辑器设置模型
+/// 默认编辑器配置的 ID（升级前的单例设置，不能删除）
+pub const DEFAULT_EDITOR_PROFILE_ID: i32 = 1;
+
+/// 默认编辑器配置的名称
+pub const DEFAULT_EDITOR_PROFILE_NAME: &str = "默认";
+
+/// 编辑器设置模型（每条记录是一个命名配置，同一时间只有一个处于激活状态）
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct EditorSettings {
-    pub id: i32,  // 设置 ID（固定为 1，单例模式）
+    pub id: i32,  // 配置 ID（1 为默认配置）
+    pub name: String,  // 配置名称（如 "写作"、"编程"）
+    pub is_active: bool,  // 是否为当前使用的配置
     // ===== 内容字体设置 =====
     pub content_font_family: String,  // 正文字体族
     pub content_font_size: i32,  // 正文字体大小（px）
@@ -26,6 +34,8 @@ pub struct EditorSettings {
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct UpdateEditorSettingsRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile_id: Option<i32>,  // 要更新的配置 ID（为空时更新当前激活的配置）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub content_font_family: Option<String>,  // 新正文字体族
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -46,10 +56,65 @@ pub struct UpdateEditorSettingsRequest {
     pub markdown_preview_style: Option<String>,  // 新 Markdown 预览样式
 }
 
+/// 编辑器设置导出格式的版本
+pub const EDITOR_SETTINGS_EXPORT_VERSION: &str = "1.0";
+
+/// 编辑器设置导出文件（用于在用户之间分享配置）
+///
+/// 导入时拒绝未知字段，避免拼错的字段名被静默忽略
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct EditorSettingsExport {
+    pub version: String,  // 导出格式版本
+    pub settings: ExportedEditorSettings,  // 编辑器设置（不含配置 ID、名称等本地信息）
+}
+
+/// 导出文件中的编辑器设置项（导入时可以只包含部分字段）
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ExportedEditorSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_font_family: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_font_size: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_font_weight: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_line_height: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heading_font_family: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub heading_font_weight: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_font_family: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code_font_size: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub markdown_preview_style: Option<String>,
+}
+
+impl From<EditorSettings> for ExportedEditorSettings {
+    fn from(settings: EditorSettings) -> Self {
+        Self {
+            content_font_family: Some(settings.content_font_family),
+            content_font_size: Some(settings.content_font_size),
+            content_font_weight: Some(settings.content_font_weight),
+            content_line_height: Some(settings.content_line_height),
+            heading_font_family: Some(settings.heading_font_family),
+            heading_font_weight: Some(settings.heading_font_weight),
+            code_font_family: Some(settings.code_font_family),
+            code_font_size: Some(settings.code_font_size),
+            markdown_preview_style: Some(settings.markdown_preview_style),
+        }
+    }
+}
+
 impl Default for EditorSettings {
     fn default() -> Self {
         Self {
-            id: 1,
+            id: DEFAULT_EDITOR_PROFILE_ID,
+            name: DEFAULT_EDITOR_PROFILE_NAME.to_string(),
+            is_active: true,
             content_font_family: "Inter, Avenir, Helvetica, Arial, sans-serif".to_string(),
             content_font_size: 16,
             content_font_weight: 400,
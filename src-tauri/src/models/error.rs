@@ -48,9 +48,15 @@ pub enum AppError {
     #[error("无效操作: {0}")]
     InvalidOperation(String),
 
+    #[error("不能跨工作空间移动: {0}")]
+    CrossWorkspaceMove(String),
+
     #[error("无效输入: {0}")]
     InvalidInput(String),
 
+    #[error("校验失败: {0}")]
+    Validation(String),
+
     #[error("内部错误: {0}")]
     Internal(String),
 }
@@ -2,7 +2,7 @@ use serde::{Serialize, Deserialize};
 use std::collections::HashMap;
 
 /// 快捷键组合
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 #[serde(rename_all = "camelCase")]
 pub struct KeyCombination {
     pub ctrl: bool,  // 是否按下 Ctrl 键
@@ -30,46 +30,75 @@ pub struct KeybindingsData {
     pub presets: Vec<KeybindingPreset>,  // 可用的预设列表
 }
 
-/// 获取默认快捷键配置
+/// 快捷键冲突（多个命令绑定了同一快捷键组合）
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct KeybindingConflict {
+    pub combination: KeyCombination,  // 冲突的快捷键组合
+    pub actions: Vec<String>,  // 绑定了该组合的命令 ID（按名称排序）
+}
+
+/// 保存/导入快捷键的结果
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct KeybindingSaveResult {
+    pub saved: bool,  // 是否已保存（存在冲突且未要求覆盖时为 false）
+    pub conflicts: Vec<KeybindingConflict>,  // 检测到的冲突
+}
+
+/// 运行平台（决定默认快捷键使用 Cmd 还是 Ctrl）
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Platform {
+    MacOs,
+    Windows,
+    Linux,
+}
+
+impl Platform {
+    /// 当前编译目标平台
+    pub fn current() -> Self {
+        if cfg!(target_os = "macos") {
+            Platform::MacOs
+        } else if cfg!(target_os = "windows") {
+            Platform::Windows
+        } else {
+            Platform::Linux
+        }
+    }
+}
+
+impl KeyCombination {
+    /// 主修饰键 + 按键（macOS 上为 Cmd，其他平台为 Ctrl）
+    pub fn primary(platform: Platform, key: &str) -> Self {
+        let mac = platform == Platform::MacOs;
+        Self { ctrl: !mac, alt: false, shift: false, meta: mac, key: key.to_string() }
+    }
+}
+
+/// 获取当前平台的默认快捷键配置
 pub fn get_default_keybindings() -> KeybindingsData {
+    get_default_keybindings_for_platform(Platform::current())
+}
+
+/// 获取指定平台的默认快捷键配置
+pub fn get_default_keybindings_for_platform(platform: Platform) -> KeybindingsData {
     let mut keybindings = HashMap::new();
 
     // 全局快捷键
-    keybindings.insert("global.newNote".to_string(), KeyCombination {
-        ctrl: true, alt: false, shift: false, meta: false, key: "KeyN".to_string(),
-    });
-    keybindings.insert("global.openSearch".to_string(), KeyCombination {
-        ctrl: true, alt: false, shift: false, meta: false, key: "KeyK".to_string(),
-    });
-    keybindings.insert("global.openSettings".to_string(), KeyCombination {
-        ctrl: true, alt: false, shift: false, meta: false, key: "Comma".to_string(),
-    });
-    keybindings.insert("global.toggleSidebar".to_string(), KeyCombination {
-        ctrl: true, alt: false, shift: false, meta: false, key: "KeyB".to_string(),
-    });
+    keybindings.insert("global.newNote".to_string(), KeyCombination::primary(platform, "KeyN"));
+    keybindings.insert("global.openSearch".to_string(), KeyCombination::primary(platform, "KeyK"));
+    keybindings.insert("global.openSettings".to_string(), KeyCombination::primary(platform, "Comma"));
+    keybindings.insert("global.toggleSidebar".to_string(), KeyCombination::primary(platform, "KeyB"));
 
     // 笔记编辑器快捷键
-    keybindings.insert("note.save".to_string(), KeyCombination {
-        ctrl: true, alt: false, shift: false, meta: false, key: "KeyS".to_string(),
-    });
-    keybindings.insert("note.find".to_string(), KeyCombination {
-        ctrl: true, alt: false, shift: false, meta: false, key: "KeyF".to_string(),
-    });
-    keybindings.insert("note.closeTab".to_string(), KeyCombination {
-        ctrl: true, alt: false, shift: false, meta: false, key: "KeyW".to_string(),
-    });
-    keybindings.insert("note.togglePreview".to_string(), KeyCombination {
-        ctrl: true, alt: false, shift: false, meta: false, key: "KeyD".to_string(),
-    });
-    keybindings.insert("note.zoomIn".to_string(), KeyCombination {
-        ctrl: true, alt: false, shift: false, meta: false, key: "Equal".to_string(),
-    });
-    keybindings.insert("note.zoomOut".to_string(), KeyCombination {
-        ctrl: true, alt: false, shift: false, meta: false, key: "Minus".to_string(),
-    });
-    keybindings.insert("note.zoomReset".to_string(), KeyCombination {
-        ctrl: true, alt: false, shift: false, meta: false, key: "Digit0".to_string(),
-    });
+    keybindings.insert("note.save".to_string(), KeyCombination::primary(platform, "KeyS"));
+    keybindings.insert("note.find".to_string(), KeyCombination::primary(platform, "KeyF"));
+    keybindings.insert("note.closeTab".to_string(), KeyCombination::primary(platform, "KeyW"));
+    keybindings.insert("note.togglePreview".to_string(), KeyCombination::primary(platform, "KeyD"));
+    keybindings.insert("note.zoomIn".to_string(), KeyCombination::primary(platform, "Equal"));
+    keybindings.insert("note.zoomOut".to_string(), KeyCombination::primary(platform, "Minus"));
+    keybindings.insert("note.zoomReset".to_string(), KeyCombination::primary(platform, "Digit0"));
 
     let presets = vec![
         KeybindingPreset {
@@ -85,3 +114,36 @@ pub fn get_default_keybindings() -> KeybindingsData {
         presets,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_keybindings_use_cmd_on_macos() {
+        let data = get_default_keybindings_for_platform(Platform::MacOs);
+
+        assert!(data.keybindings.values().all(|c| c.meta && !c.ctrl));
+        assert_eq!(data.keybindings["note.save"], KeyCombination::primary(Platform::MacOs, "KeyS"));
+    }
+
+    #[test]
+    fn test_default_keybindings_use_ctrl_elsewhere() {
+        for platform in [Platform::Windows, Platform::Linux] {
+            let data = get_default_keybindings_for_platform(platform);
+            assert!(data.keybindings.values().all(|c| c.ctrl && !c.meta));
+        }
+    }
+
+    #[test]
+    #[cfg(target_os = "macos")]
+    fn test_current_platform_defaults_use_cmd() {
+        assert!(get_default_keybindings().keybindings["note.save"].meta);
+    }
+
+    #[test]
+    #[cfg(not(target_os = "macos"))]
+    fn test_current_platform_defaults_use_ctrl() {
+        assert!(get_default_keybindings().keybindings["note.save"].ctrl);
+    }
+}
@@ -8,22 +8,24 @@ pub mod workspace;
 // ===== 云端同步相关模型 =====
 pub mod sync;
 pub mod snapshot;
+pub mod attachment;
 pub mod auth;
 pub mod user_profile;
 pub mod app_settings;
 
 #[allow(unused_imports)]
 pub use error::{AppError, Result};
-pub use note::{Note, CreateNoteRequest, UpdateNoteRequest};
+pub use note::{Note, CreateNoteRequest, UpdateNoteRequest, RankedNote, NoteCursor, NotePage, NoteList};
 pub use folder::{Folder, CreateFolderRequest, UpdateFolderRequest, MoveFolderRequest, MoveNotesRequest};
-pub use keybinding::{KeyCombination, KeybindingPreset, KeybindingsData, get_default_keybindings};
-pub use editor_settings::{EditorSettings, UpdateEditorSettingsRequest};
-pub use tag::{Tag, CreateTagRequest, UpdateTagRequest, NoteTagRequest};
-pub use workspace::{Workspace, CreateWorkspaceRequest, UpdateWorkspaceRequest};
+pub use keybinding::{KeyCombination, KeybindingPreset, KeybindingsData, KeybindingConflict, KeybindingSaveResult, get_default_keybindings};
+pub use editor_settings::{EditorSettings, UpdateEditorSettingsRequest, EditorSettingsExport, ExportedEditorSettings, DEFAULT_EDITOR_PROFILE_ID};
+pub use tag::{Tag, TagColorInconsistency, SimilarTag, SimilarTagGroup, TagMergeResult, CreateTagRequest, UpdateTagRequest, NoteTagRequest};
+pub use workspace::{Workspace, WorkspaceCheckpoint, CreateWorkspaceRequest, UpdateWorkspaceRequest, StorageCounts, WorkspaceStorageStats, StorageStats};
 // ===== 云端同步相关导出 =====
-pub use sync::{SyncRequest, SyncResponse, ConflictInfo, SyncStatus, SyncReport, SyncType, NoteTagRelation, ConflictStrategy, ServerWorkspace};
+pub use sync::{SyncRequest, SyncResponse, ConflictInfo, SyncStatus, SyncReport, SyncTiming, SyncType, NoteTagRelation, ConflictStrategy, ServerWorkspace, SyncHistoryFilter, SyncHistoryPage, DeviceSyncSummary, DeleteEvent, FlappingItem, ConflictStrategies, PendingChange, PendingChangeKind, LocalOnlyItem, FolderRebuildReport, SyncQueueEntry, ServerLimits, PendingConflict, DEFAULT_MAX_SNAPSHOTS_PER_NOTE, DEFAULT_MAX_ATTACHMENT_BYTES};
 pub use snapshot::{NoteSnapshot, CreateSnapshotRequest, SnapshotListItem};
-pub use auth::{LoginRequest, RegisterRequest, AuthResponse, User, AccountWithProfile};
+pub use attachment::{Attachment, AddAttachmentRequest, AttachmentListItem};
+pub use auth::{LoginRequest, RegisterRequest, AuthResponse, User, AccountWithProfile, Device};
 // CreateProfileRequest 是预留功能（用户注册时创建资料）
 #[allow(unused_imports)]
 pub use user_profile::{UserProfile, CreateProfileRequest, UpdateProfileRequest};
@@ -1,5 +1,6 @@
 use serde::{Serialize, Deserialize};
 use uuid::Uuid;
+use super::app_settings::DEFAULT_EXCERPT_LENGTH;
 
 /// 笔记模型
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -12,7 +13,7 @@ pub struct Note {
 
     // ===== 摘要与缓存 =====
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub excerpt: Option<String>,  // 内容摘要（前 200 字符）
+    pub excerpt: Option<String>,  // 内容摘要（去除 Markdown 标记后的前 N 个字符，默认 200）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub markdown_cache: Option<String>,  // Markdown 缓存（用于导出/兼容）
 
@@ -25,6 +26,8 @@ pub struct Note {
     pub is_favorite: bool,  // 是否收藏
     #[serde(default)]
     pub is_pinned: bool,  // 是否置顶
+    #[serde(default)]
+    pub is_private: bool,  // 是否私密（不出现在跨工作空间的全局搜索中）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub author: Option<String>,  // 作者
 
@@ -67,14 +70,15 @@ impl Note {
         Self {
             id: Uuid::new_v4().to_string(),
             title,
-            excerpt: Self::generate_excerpt(&content),
-            markdown_cache: None,
+            excerpt: Self::generate_excerpt(&content, DEFAULT_EXCERPT_LENGTH as usize),
+            markdown_cache: Self::generate_markdown(&content),
             content,
             workspace_id: None,  // 将由 Service 层设置
             folder_id,
             is_favorite: false,
             is_deleted: false,
             is_pinned: false,
+            is_private: false,
             author: None,
             created_at: now,
             updated_at: now,
@@ -89,14 +93,20 @@ impl Note {
 
     /// 更新笔记内容并重新计算衍生值
     ///
-    /// 应该在更新 content 时调用此方法，确保 excerpt、word_count、read_time_minutes 保持同步
+    /// 应该在更新 content 时调用此方法，确保 excerpt、markdown_cache、word_count、read_time_minutes 保持同步
     pub fn update_content(&mut self, content: String) {
         self.content = content;
-        self.excerpt = Self::generate_excerpt(&self.content);
+        self.excerpt = Self::generate_excerpt(&self.content, DEFAULT_EXCERPT_LENGTH as usize);
+        self.markdown_cache = Self::generate_markdown(&self.content);
         self.word_count = Self::count_words(&self.content);
         self.read_time_minutes = Self::calculate_read_time(self.word_count);
     }
 
+    /// 按指定长度重新生成摘要
+    pub fn refresh_excerpt(&mut self, max_chars: usize) {
+        self.excerpt = Self::generate_excerpt(&self.content, max_chars);
+    }
+
     /// 创建冲突副本（用于同步冲突解决）
     pub fn conflict_copy(&self, suffix: &str) -> Self {
         let now = chrono::Utc::now().timestamp();
@@ -112,6 +122,7 @@ impl Note {
             is_favorite: self.is_favorite,
             is_deleted: false,
             is_pinned: self.is_pinned,
+            is_private: self.is_private,
             author: self.author.clone(),
             created_at: now,
             updated_at: now,
@@ -124,18 +135,176 @@ impl Note {
         }
     }
 
-    /// 生成摘要（前 200 个字符）
-    fn generate_excerpt(content: &str) -> Option<String> {
-        let chars: Vec<char> = content.chars().collect();
-        if chars.len() <= 200 {
+    /// 生成摘要（去除 Markdown 标记后的前 `max_chars` 个字符）
+    ///
+    /// 按字符截断而不是按字节，中文等多字节内容不会被截断在字符中间；
+    /// 文本不超过 `max_chars` 时不需要摘要，返回 None
+    pub fn generate_excerpt(content: &str, max_chars: usize) -> Option<String> {
+        let text = Self::plain_text(content);
+        if text.chars().count() <= max_chars {
             None
         } else {
-            Some(chars[..200].iter().collect())
+            Some(text.chars().take(max_chars).collect())
         }
     }
 
+    /// 笔记的纯文本（Tiptap 文档提取文本，Markdown 去除标记），连续空白合并为单个空格
+    pub(crate) fn plain_text(content: &str) -> String {
+        match Self::extract_tiptap_text(content) {
+            Some(text) => text.split_whitespace().collect::<Vec<_>>().join(" "),
+            None => Self::strip_markdown(content),
+        }
+    }
+
+    /// 生成 Markdown 缓存
+    ///
+    /// Tiptap JSON 内容转换为 Markdown，其他内容本身即为 Markdown，原样返回；空内容返回 None
+    pub fn generate_markdown(content: &str) -> Option<String> {
+        if content.trim().is_empty() {
+            return None;
+        }
+
+        match Self::parse_tiptap_doc(content) {
+            Some(doc) => {
+                let children = doc.get("content").and_then(|c| c.as_array()).map(Vec::as_slice).unwrap_or(&[]);
+                let markdown = render_markdown_blocks(children);
+                (!markdown.trim().is_empty()).then_some(markdown)
+            }
+            None => Some(content.to_string()),
+        }
+    }
+
+    /// 解析 Tiptap JSON 文档（非 Tiptap 文档返回 None）
+    fn parse_tiptap_doc(content: &str) -> Option<serde_json::Value> {
+        if !content.trim_start().starts_with('{') {
+            return None;
+        }
+        let doc: serde_json::Value = serde_json::from_str(content).ok()?;
+        (doc.get("type").and_then(|t| t.as_str()) == Some("doc")).then_some(doc)
+    }
+
+    /// 从 Tiptap JSON 内容中提取纯文本（非 Tiptap 文档返回 None）
+    fn extract_tiptap_text(content: &str) -> Option<String> {
+        // 行内文本直接拼接，块级节点之间用空格分隔
+        fn collect(node: &serde_json::Value, out: &mut String) {
+            if let Some(text) = node.get("text").and_then(|t| t.as_str()) {
+                out.push_str(text);
+            }
+            if let Some(children) = node.get("content").and_then(|c| c.as_array()) {
+                for child in children {
+                    collect(child, out);
+                }
+                out.push(' ');
+            }
+        }
+
+        let doc = Self::parse_tiptap_doc(content)?;
+
+        let mut text = String::new();
+        collect(&doc, &mut text);
+        Some(text)
+    }
+
+    /// 去除常见的 Markdown 标记，并将连续空白合并为单个空格
+    fn strip_markdown(content: &str) -> String {
+        let mut lines = Vec::new();
+        let mut in_code_block = false;
+
+        for line in content.lines() {
+            let trimmed = line.trim();
+
+            // 代码块围栏本身不计入摘要
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                in_code_block = !in_code_block;
+                continue;
+            }
+            if in_code_block {
+                lines.push(trimmed.to_string());
+                continue;
+            }
+
+            // 去除行首的标题、引用、列表标记
+            let mut text = trimmed.trim_start_matches('#').trim_start();
+            while let Some(rest) = text.strip_prefix('>') {
+                text = rest.trim_start();
+            }
+            for marker in ["- [ ] ", "- [x] ", "- ", "* ", "+ "] {
+                if let Some(rest) = text.strip_prefix(marker) {
+                    text = rest;
+                    break;
+                }
+            }
+
+            // 分隔线
+            if !text.is_empty() && text.chars().all(|c| c == '-' || c == '*' || c == '_') && text.len() >= 3 {
+                continue;
+            }
+
+            lines.push(Self::strip_inline_markdown(text));
+        }
+
+        lines.join(" ").split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    /// 去除行内 Markdown 标记：强调、行内代码、链接和图片
+    ///
+    /// 只去除成对的强调和代码标记，`snake_case_name`、`a*b` 这类文本中的符号保留
+    fn strip_inline_markdown(text: &str) -> String {
+        let chars: Vec<char> = text.chars().collect();
+        let mut result = String::with_capacity(text.len());
+        let mut i = 0;
+
+        while i < chars.len() {
+            match chars[i] {
+                delimiter @ ('*' | '_' | '`' | '~') => {
+                    let len = delimiter_run(&chars, i);
+                    match closing_delimiter(&chars, i, len) {
+                        Some(close) => {
+                            let inner = &chars[i + len..close];
+                            if delimiter == '`' {
+                                // 行内代码的内容原样保留
+                                result.extend(inner);
+                            } else {
+                                result.push_str(&Self::strip_inline_markdown(&inner.iter().collect::<String>()));
+                            }
+                            i = close + len;
+                        }
+                        None => {
+                            result.extend(&chars[i..i + len]);
+                            i += len;
+                        }
+                    }
+                }
+                '!' if chars.get(i + 1) == Some(&'[') => {
+                    i += 1;
+                }
+                '[' => {
+                    // [文本](链接) -> 文本
+                    if let Some(close) = chars[i + 1..].iter().position(|&c| c == ']') {
+                        let close = i + 1 + close;
+                        if chars.get(close + 1) == Some(&'(') {
+                            if let Some(end) = chars[close + 2..].iter().position(|&c| c == ')') {
+                                result.extend(&chars[i + 1..close]);
+                                i = close + 2 + end + 1;
+                                continue;
+                            }
+                        }
+                    }
+                    result.push('[');
+                    i += 1;
+                }
+                c => {
+                    result.push(c);
+                    i += 1;
+                }
+            }
+        }
+
+        result
+    }
+
     /// 计算字数（按空白字符分割）
-    fn count_words(content: &str) -> u32 {
+    pub(crate) fn count_words(content: &str) -> u32 {
         content.split_whitespace().count() as u32
     }
 
@@ -145,6 +314,247 @@ impl Note {
     }
 }
 
+/// 从 `start` 开始连续相同标记字符的个数
+fn delimiter_run(chars: &[char], start: usize) -> usize {
+    chars[start..].iter().take_while(|&&c| c == chars[start]).count()
+}
+
+/// 查找与 `start` 处长度为 `len` 的开始标记配对的结束标记位置
+///
+/// 强调标记紧贴内容（开始标记后、结束标记前不是空白）；`_` 不能出现在单词内部，
+/// 以免把 `snake_case_name` 当作强调；行内代码只要求结束标记长度相同
+fn closing_delimiter(chars: &[char], start: usize, len: usize) -> Option<usize> {
+    let delimiter = chars[start];
+    let is_code = delimiter == '`';
+    let is_word = |index: Option<usize>| index.and_then(|i| chars.get(i)).is_some_and(|c| c.is_alphanumeric());
+
+    let first = *chars.get(start + len)?;
+    if !is_code && first.is_whitespace() {
+        return None;
+    }
+    if delimiter == '_' && is_word(start.checked_sub(1)) {
+        return None;
+    }
+
+    let mut i = start + len + 1;
+    while i < chars.len() {
+        // 行内代码优先于强调，代码中的符号不参与配对
+        if !is_code && chars[i] == '`' {
+            let run = delimiter_run(chars, i);
+            i = closing_delimiter(chars, i, run).map_or(i + run, |close| close + run);
+            continue;
+        }
+        if chars[i] != delimiter {
+            i += 1;
+            continue;
+        }
+        let run = delimiter_run(chars, i);
+        let closes = run == len
+            && (is_code || !chars[i - 1].is_whitespace())
+            && !(delimiter == '_' && is_word(Some(i + run)));
+        if closes {
+            return Some(i);
+        }
+        i += run;
+    }
+    None
+}
+
+/// 将 Tiptap 块级节点渲染为 Markdown（块之间空一行）
+fn render_markdown_blocks(nodes: &[serde_json::Value]) -> String {
+    nodes.iter()
+        .map(render_markdown_block)
+        .filter(|block| !block.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn render_markdown_block(node: &serde_json::Value) -> String {
+    let children = node_children(node);
+    match node_type(node) {
+        "paragraph" => render_markdown_inline(children),
+        "heading" => {
+            let level = node_attr(node, "level").and_then(|l| l.as_u64()).unwrap_or(1).clamp(1, 6) as usize;
+            format!("{} {}", "#".repeat(level), render_markdown_inline(children))
+        }
+        "blockquote" => render_markdown_blocks(children)
+            .lines()
+            .map(|line| if line.is_empty() { ">".to_string() } else { format!("> {}", line) })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        "codeBlock" => {
+            let language = node_attr(node, "language").and_then(|l| l.as_str()).unwrap_or("");
+            let code: String = children.iter()
+                .filter_map(|child| child.get("text").and_then(|t| t.as_str()))
+                .collect();
+            format!("```{}\n{}\n```", language, code)
+        }
+        "horizontalRule" => "---".to_string(),
+        "bulletList" => render_markdown_list(children, |_, _| "- ".to_string()),
+        "orderedList" => {
+            let start = node_attr(node, "start").and_then(|s| s.as_u64()).unwrap_or(1);
+            render_markdown_list(children, |index, _| format!("{}. ", start + index as u64))
+        }
+        "taskList" => render_markdown_list(children, |_, item| {
+            let checked = node_attr(item, "checked").and_then(|c| c.as_bool()).unwrap_or(false);
+            if checked { "- [x] ".to_string() } else { "- [ ] ".to_string() }
+        }),
+        "table" => render_markdown_table(children),
+        "image" => render_markdown_image(node),
+        _ if children.iter().any(|child| node_type(child) == "text") => render_markdown_inline(children),
+        _ => render_markdown_blocks(children),
+    }
+}
+
+/// 渲染列表，`marker` 根据序号和列表项生成标记；列表项的后续行按标记宽度缩进
+fn render_markdown_list(items: &[serde_json::Value], marker: impl Fn(usize, &serde_json::Value) -> String) -> String {
+    items.iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let marker = marker(index, item);
+            let indent = " ".repeat(marker.chars().count());
+            let body = node_children(item).iter()
+                .map(render_markdown_block)
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            body.lines()
+                .enumerate()
+                .map(|(i, line)| match (i, line.is_empty()) {
+                    (0, _) => format!("{}{}", marker, line),
+                    (_, true) => String::new(),
+                    _ => format!("{}{}", indent, line),
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 渲染表格，第一行作为表头
+fn render_markdown_table(rows: &[serde_json::Value]) -> String {
+    let rows: Vec<Vec<String>> = rows.iter()
+        .map(|row| node_children(row).iter()
+            .map(|cell| {
+                let text: Vec<String> = node_children(cell).iter().map(render_markdown_block).collect();
+                text.join(" ").replace('|', "\\|").replace('\n', " ")
+            })
+            .collect())
+        .collect();
+    let Some(header) = rows.first() else {
+        return String::new();
+    };
+
+    let mut lines = vec![
+        format!("| {} |", header.join(" | ")),
+        format!("|{}|", vec![" --- "; header.len()].join("|")),
+    ];
+    lines.extend(rows[1..].iter().map(|row| format!("| {} |", row.join(" | "))));
+    lines.join("\n")
+}
+
+/// 渲染行内节点（文本、换行、图片），按标记包裹强调、删除线、行内代码和链接
+fn render_markdown_inline(nodes: &[serde_json::Value]) -> String {
+    let mut result = String::new();
+    for node in nodes {
+        match node_type(node) {
+            "text" => {
+                let mut text = node.get("text").and_then(|t| t.as_str()).unwrap_or("").to_string();
+                let marks = node.get("marks").and_then(|m| m.as_array()).map(Vec::as_slice).unwrap_or(&[]);
+                let has_mark = |name: &str| marks.iter().any(|mark| node_type(mark) == name);
+
+                if has_mark("code") {
+                    text = format!("`{}`", text);
+                }
+                if has_mark("bold") {
+                    text = format!("**{}**", text);
+                }
+                if has_mark("italic") {
+                    text = format!("*{}*", text);
+                }
+                if has_mark("strike") {
+                    text = format!("~~{}~~", text);
+                }
+                if let Some(href) = marks.iter()
+                    .find(|mark| node_type(mark) == "link")
+                    .and_then(|mark| node_attr(mark, "href"))
+                    .and_then(|href| href.as_str())
+                {
+                    text = format!("[{}]({})", text, href);
+                }
+                result.push_str(&text);
+            }
+            "hardBreak" => result.push_str("  \n"),
+            "image" => result.push_str(&render_markdown_image(node)),
+            _ => result.push_str(&render_markdown_inline(node_children(node))),
+        }
+    }
+    result
+}
+
+fn render_markdown_image(node: &serde_json::Value) -> String {
+    let src = node_attr(node, "src").and_then(|s| s.as_str()).unwrap_or("");
+    let alt = node_attr(node, "alt").and_then(|a| a.as_str()).unwrap_or("");
+    format!("![{}]({})", alt, src)
+}
+
+fn node_type(node: &serde_json::Value) -> &str {
+    node.get("type").and_then(|t| t.as_str()).unwrap_or("")
+}
+
+fn node_attr<'a>(node: &'a serde_json::Value, name: &str) -> Option<&'a serde_json::Value> {
+    node.get("attrs").and_then(|attrs| attrs.get(name))
+}
+
+fn node_children(node: &serde_json::Value) -> &[serde_json::Value] {
+    node.get("content").and_then(|c| c.as_array()).map(Vec::as_slice).unwrap_or(&[])
+}
+
+/// 笔记列表分页游标（置顶笔记在前，按 pin_sort_order、updated_at DESC, id DESC 排序的键集游标）
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteCursor {
+    pub updated_at: i64,  // 上一页最后一条笔记的更新时间
+    pub id: String,  // 上一页最后一条笔记的 ID
+    #[serde(default)]
+    pub is_pinned: bool,  // 上一页最后一条笔记是否置顶
+    #[serde(default)]
+    pub pin_sort_order: Option<i64>,  // 上一页最后一条笔记的置顶顺序
+    #[serde(default)]
+    pub since: i64,  // 读取上一页的时间，之后修改的笔记通过 NotePage.changed 返回
+}
+
+/// 笔记列表分页结果
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct NotePage {
+    pub notes: Vec<Note>,  // 当前页笔记
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<NoteCursor>,  // 下一页游标（没有更多数据时为空）
+    /// 读取上一页之后被修改的笔记（已移到游标之前，不会出现在后续页中，前端按 ID 合并到列表）
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub changed: Vec<Note>,
+}
+
+/// 笔记列表（未指定 limit 时为完整列表，兼容旧版前端）
+#[derive(Serialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum NoteList {
+    All(Vec<Note>),
+    Page(NotePage),
+}
+
+/// 按相关度排序的搜索结果
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RankedNote {
+    pub note: Note,  // 笔记
+    pub rank: f64,  // 相关度（越小越相关）
+    pub title_highlight: String,  // 标题，命中词以 <mark> 包裹
+    pub snippet: String,  // 内容片段，命中词以 <mark> 包裹
+}
+
 /// 创建笔记请求
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -173,3 +583,90 @@ pub struct UpdateNoteRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub author: Option<String>,  // 作者
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_excerpt_strips_markdown() {
+        let content = "# 标题\n\n这是**粗体**和[链接](https://example.com)以及`code`。\n\n- 列表项";
+        let excerpt = Note::generate_excerpt(content, 20).expect("excerpt should exist");
+
+        assert_eq!(excerpt, "标题 这是粗体和链接以及code。 列表");
+    }
+
+    #[test]
+    fn test_excerpt_is_none_when_text_fits() {
+        assert_eq!(Note::generate_excerpt("# 短笔记", 200), None);
+        assert_eq!(Note::generate_excerpt("", 200), None);
+    }
+
+    #[test]
+    fn test_strip_inline_markdown_keeps_unpaired_symbols() {
+        assert_eq!(Note::strip_inline_markdown("snake_case_name 和 a*b"), "snake_case_name 和 a*b");
+        assert_eq!(Note::strip_inline_markdown("2 * 3 * 4"), "2 * 3 * 4");
+        assert_eq!(Note::strip_inline_markdown("**粗体** _斜体_ ~~删除~~"), "粗体 斜体 删除");
+        assert_eq!(Note::strip_inline_markdown("**粗*斜*体** 与 `a_b*c`"), "粗斜体 与 a_b*c");
+        assert_eq!(Note::strip_inline_markdown("*强调 `x*` 结束*"), "强调 x* 结束");
+    }
+
+    #[test]
+    fn test_excerpt_respects_length_for_cjk() {
+        let content = "中文内容测试".repeat(50);
+        let excerpt = Note::generate_excerpt(&content, 10).expect("excerpt should exist");
+
+        assert_eq!(excerpt.chars().count(), 10);
+        assert_eq!(excerpt, "中文内容测试中文内容");
+    }
+
+    #[test]
+    fn test_excerpt_from_tiptap_json() {
+        let content = r#"{"type":"doc","content":[{"type":"paragraph","content":[{"type":"text","text":"你好"},{"type":"text","marks":[{"type":"bold"}],"text":"世界"}]}]}"#;
+
+        assert_eq!(Note::generate_excerpt(content, 2).as_deref(), Some("你好"));
+        assert_eq!(Note::generate_excerpt(content, 200), None);
+    }
+
+    #[test]
+    fn test_markdown_from_tiptap_json() {
+        let content = r#"{"type":"doc","content":[
+            {"type":"heading","attrs":{"level":2},"content":[{"type":"text","text":"标题"}]},
+            {"type":"paragraph","content":[
+                {"type":"text","text":"普通"},
+                {"type":"text","marks":[{"type":"bold"}],"text":"粗体"},
+                {"type":"text","marks":[{"type":"link","attrs":{"href":"https://example.com"}}],"text":"链接"}
+            ]},
+            {"type":"bulletList","content":[
+                {"type":"listItem","content":[{"type":"paragraph","content":[{"type":"text","text":"一"}]}]},
+                {"type":"listItem","content":[{"type":"paragraph","content":[{"type":"text","text":"二"}]}]}
+            ]},
+            {"type":"taskList","content":[
+                {"type":"taskItem","attrs":{"checked":true},"content":[{"type":"paragraph","content":[{"type":"text","text":"完成"}]}]}
+            ]},
+            {"type":"codeBlock","attrs":{"language":"rust"},"content":[{"type":"text","text":"fn main() {}"}]}
+        ]}"#;
+
+        assert_eq!(
+            Note::generate_markdown(content).as_deref(),
+            Some("## 标题\n\n普通**粗体**[链接](https://example.com)\n\n- 一\n- 二\n\n- [x] 完成\n\n```rust\nfn main() {}\n```")
+        );
+    }
+
+    #[test]
+    fn test_markdown_keeps_plain_markdown_content() {
+        assert_eq!(Note::generate_markdown("# 标题").as_deref(), Some("# 标题"));
+        assert_eq!(Note::generate_markdown("  "), None);
+        assert_eq!(Note::generate_markdown(r#"{"type":"doc","content":[]}"#), None);
+    }
+
+    #[test]
+    fn test_update_content_rebuilds_markdown_cache() {
+        let mut note = Note::new("t".to_string(), "旧内容".to_string(), None);
+        note.markdown_cache = Some("过期的缓存".to_string());
+
+        note.update_content(r#"{"type":"doc","content":[{"type":"paragraph","content":[{"type":"text","text":"新内容"}]}]}"#.to_string());
+
+        assert_eq!(note.markdown_cache.as_deref(), Some("新内容"));
+    }
+}
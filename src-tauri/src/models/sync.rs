@@ -3,6 +3,7 @@ use crate::models::Note;
 use crate::models::Folder;
 use crate::models::Tag;
 use crate::models::NoteSnapshot;
+use crate::models::Attachment;
 use crate::models::Workspace;
 
 /// 同步类型枚举
@@ -40,6 +41,8 @@ pub struct ServerNote {
     #[serde(default)]
     pub is_pinned: bool,
     #[serde(default)]
+    pub is_private: bool,
+    #[serde(default)]
     pub author: Option<String>,
     #[serde(default)]
     pub word_count: i32,
@@ -65,6 +68,7 @@ impl From<Note> for ServerNote {
             markdown_cache: note.markdown_cache,
             is_favorite: note.is_favorite,
             is_pinned: note.is_pinned,
+            is_private: note.is_private,
             author: note.author,
             word_count: note.word_count as i32,
             read_time_minutes: note.read_time_minutes as i32,
@@ -90,6 +94,7 @@ impl From<ServerNote> for Note {
             workspace_id: None,
             is_favorite: note.is_favorite,
             is_pinned: note.is_pinned,
+            is_private: note.is_private,
             author: note.author,
             word_count: note.word_count as u32,
             read_time_minutes: note.read_time_minutes as u32,
@@ -254,6 +259,70 @@ impl From<ServerNoteSnapshot> for NoteSnapshot {
     }
 }
 
+/// 服务器附件（用于与服务器通信，snake_case）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ServerAttachment {
+    pub id: String,
+    pub note_id: String,
+    #[serde(default)]
+    pub user_id: String,  // 客户端推送时为空，由服务器填充
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace_id: Option<String>,
+    pub filename: String,
+    pub mime_type: String,
+    pub size_bytes: i64,
+    pub data: String,  // Base64 编码
+    pub created_at: i64,
+    pub updated_at: i64,
+    #[serde(default)]
+    pub is_deleted: bool,
+    pub deleted_at: Option<i64>,
+    #[serde(default)]
+    pub server_ver: i32,
+}
+
+impl From<Attachment> for ServerAttachment {
+    fn from(attachment: Attachment) -> Self {
+        ServerAttachment {
+            id: attachment.id,
+            note_id: attachment.note_id,
+            user_id: String::new(),
+            workspace_id: attachment.workspace_id,
+            filename: attachment.filename,
+            mime_type: attachment.mime_type,
+            size_bytes: attachment.size_bytes,
+            data: attachment.data,
+            created_at: attachment.created_at,
+            updated_at: attachment.updated_at,
+            is_deleted: attachment.is_deleted,
+            deleted_at: attachment.deleted_at,
+            server_ver: attachment.server_ver,
+        }
+    }
+}
+
+impl From<ServerAttachment> for Attachment {
+    fn from(attachment: ServerAttachment) -> Self {
+        Attachment {
+            id: attachment.id,
+            note_id: attachment.note_id,
+            workspace_id: attachment.workspace_id,
+            filename: attachment.filename,
+            mime_type: attachment.mime_type,
+            size_bytes: attachment.size_bytes,
+            data: attachment.data,
+            created_at: attachment.created_at,
+            updated_at: attachment.updated_at,
+            is_deleted: attachment.is_deleted,
+            deleted_at: attachment.deleted_at,
+            server_ver: attachment.server_ver,
+            // ✅ 客户端本地管理这些字段
+            is_dirty: false,
+            last_synced_at: Some(chrono::Utc::now().timestamp()),
+        }
+    }
+}
+
 /// 服务器工作空间（用于与服务器通信，snake_case）
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ServerWorkspace {
@@ -336,6 +405,10 @@ pub struct ServerNoteTagRelation {
     pub tag_id: String,
     pub user_id: String,
     pub created_at: i64,
+    #[serde(default)]
+    pub is_deleted: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<i64>,
 }
 
 impl From<NoteTagRelation> for ServerNoteTagRelation {
@@ -345,7 +418,8 @@ impl From<NoteTagRelation> for ServerNoteTagRelation {
             tag_id: rel.tag_id,
             user_id: rel.user_id,
             created_at: rel.created_at,
-            // is_deleted 和 deleted_at 不发送到服务器（服务器有自己的删除管理）
+            is_deleted: rel.is_deleted,
+            deleted_at: rel.deleted_at,
         }
     }
 }
@@ -357,8 +431,8 @@ impl From<ServerNoteTagRelation> for NoteTagRelation {
             tag_id: rel.tag_id,
             user_id: rel.user_id,
             created_at: rel.created_at,
-            is_deleted: false,
-            deleted_at: None,
+            is_deleted: rel.is_deleted,
+            deleted_at: rel.deleted_at,
         }
     }
 }
@@ -379,13 +453,27 @@ pub struct SyncRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub note_tags: Option<Vec<ServerNoteTagRelation>>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub attachments: Option<Vec<ServerAttachment>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub last_sync_at: Option<i64>,
+    /// 上次同步返回的变更游标（提供时服务器忽略 last_sync_at）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<i64>,
+    /// 目标工作空间 ID（为空时服务器使用用户的默认工作空间）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace_id: Option<String>,
     /// 冲突解决策略（默认：创建冲突副本）
     #[serde(default)]
     pub conflict_resolution: ConflictStrategy,
+    /// 按实体类型覆盖的冲突解决策略（未设置的类型使用 conflict_resolution）
+    #[serde(default)]
+    pub conflict_strategies: ConflictStrategies,
     /// 设备ID（用于操作锁）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub device_id: Option<String>,
+    /// 幂等键（重试同一次同步时保持不变，服务器返回首次处理的结果而不是重复写入）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>,
 }
 
 /// 同步响应（使用 snake_case 版本的结构体）
@@ -394,6 +482,9 @@ pub struct SyncResponse {
     pub status: String,
     pub server_time: i64,
     pub last_sync_at: i64,
+    /// 变更游标（旧版本服务器不返回）
+    #[serde(default)]
+    pub cursor: Option<i64>,
 
     pub upserted_workspaces: Vec<ServerWorkspace>,
     pub upserted_notes: Vec<ServerNote>,
@@ -401,6 +492,9 @@ pub struct SyncResponse {
     pub upserted_tags: Vec<ServerTag>,
     pub upserted_snapshots: Vec<ServerNoteSnapshot>,
     pub upserted_note_tags: Vec<ServerNoteTagRelation>,
+    /// 附件（旧版本服务器不返回）
+    #[serde(default)]
+    pub upserted_attachments: Vec<ServerAttachment>,
 
     #[serde(default)]
     pub deleted_workspace_ids: Vec<String>,
@@ -410,6 +504,8 @@ pub struct SyncResponse {
     pub deleted_folder_ids: Vec<String>,
     #[serde(default)]
     pub deleted_tag_ids: Vec<String>,
+    #[serde(default)]
+    pub deleted_attachment_ids: Vec<String>,
 
     // 推送统计（服务器确认实际更新的数量）
     pub pushed_workspaces: usize,
@@ -418,6 +514,8 @@ pub struct SyncResponse {
     pub pushed_tags: usize,
     pub pushed_snapshots: usize,
     pub pushed_note_tags: usize,
+    #[serde(default)]
+    pub pushed_attachments: usize,
     pub pushed_total: usize,  // 推送总数
 
     // 拉取统计（服务器端真正的新数据）
@@ -427,6 +525,8 @@ pub struct SyncResponse {
     pub pulled_tags: usize,
     pub pulled_snapshots: usize,
     pub pulled_note_tags: usize,
+    #[serde(default)]
+    pub pulled_attachments: usize,
     pub pulled_total: usize,  // 拉取总数
 
     #[serde(default)]
@@ -455,6 +555,15 @@ pub struct ConflictInfo {
     pub local_version: i32,
     pub server_version: i32,
     pub title: String,
+    // 以下字段仅笔记冲突时由服务器返回，用于展示双方差异
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub local_content: Option<String>,  // 本地推送的内容
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_content: Option<String>,  // 服务器当前内容
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub local_updated_at: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_updated_at: Option<i64>,
 }
 
 /// 同步状态
@@ -466,6 +575,23 @@ pub struct SyncStatus {
     pub conflict_count: i32,  // 冲突数量
     #[serde(skip_serializing_if = "Option::is_none")]
     pub last_error: Option<String>,  // 最后一次错误信息
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effective_interval_secs: Option<i64>,  // 自动同步当前生效间隔（秒，含连续失败退避）
+    #[serde(default)]
+    pub consecutive_failures: u32,  // 自动同步连续失败次数
+}
+
+/// 完整同步各阶段耗时（毫秒）
+///
+/// 用于判断同步缓慢的瓶颈在网络还是本地数据库读写
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncTiming {
+    pub build_request_ms: u64,   // 构建同步请求（读取本地数据）
+    pub network_ms: u64,         // 网络往返（发送请求到解析完响应）
+    pub apply_response_ms: u64,  // 应用服务器响应（写入本地数据库）
+    pub clear_markers_ms: u64,   // 清理脏标记
+    pub total_ms: u64,           // 总耗时（含会话校验、更新同步状态）
 }
 
 /// 同步结果报告
@@ -481,6 +607,8 @@ pub struct SyncReport {
     pub pushed_tags: usize,
     pub pushed_snapshots: usize,
     pub pushed_note_tags: usize,
+    #[serde(default)]
+    pub pushed_attachments: usize,
 
     // 从服务器拉取的详细统计
     pub pulled_workspaces: usize,
@@ -489,16 +617,24 @@ pub struct SyncReport {
     pub pulled_tags: usize,
     pub pulled_snapshots: usize,
     pub pulled_note_tags: usize,
+    #[serde(default)]
+    pub pulled_attachments: usize,
 
     // 删除的数据统计
     pub deleted_workspaces: usize,
     pub deleted_notes: usize,
     pub deleted_folders: usize,
     pub deleted_tags: usize,
+    #[serde(default)]
+    pub deleted_attachments: usize,
 
     pub conflict_count: usize,  // 冲突数量
+    #[serde(default)]
+    pub skipped_snapshots: usize,  // 因超过大小上限而未同步的快照数量（保留为本地快照）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,  // 错误信息（如果有）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing: Option<SyncTiming>,  // 各阶段耗时（仅完整同步）
 
     // 兼容旧版本的汇总字段
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -510,15 +646,207 @@ pub struct SyncReport {
 impl SyncReport {
     /// 获取总推送数量（兼容旧版本）
     pub fn total_pushed(&self) -> usize {
-        self.pushed_workspaces + self.pushed_notes + self.pushed_folders + self.pushed_tags + self.pushed_snapshots + self.pushed_note_tags
+        self.pushed_workspaces + self.pushed_notes + self.pushed_folders + self.pushed_tags + self.pushed_snapshots + self.pushed_note_tags + self.pushed_attachments
     }
 
     /// 获取总拉取数量（兼容旧版本）
     pub fn total_pulled(&self) -> usize {
-        self.pulled_workspaces + self.pulled_notes + self.pulled_folders + self.pulled_tags + self.pulled_snapshots + self.pulled_note_tags
+        self.pulled_workspaces + self.pulled_notes + self.pulled_folders + self.pulled_tags + self.pulled_snapshots + self.pulled_note_tags + self.pulled_attachments
+    }
+}
+
+/// 同步历史记录（从服务器读取 snake_case，返回前端 camelCase）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "snake_case"))]
+pub struct SyncHistoryEntry {
+    pub id: String,
+    pub user_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_id: Option<String>,  // 执行同步的设备（旧记录为空）
+    pub sync_type: String,  // 同步类型（push, pull, full, sync）
+    pub pushed_count: i32,  // 推送数量
+    pub pulled_count: i32,  // 拉取数量
+    pub conflict_count: i32,  // 冲突数量
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,  // 错误信息（同步失败时）
+    pub duration_ms: i64,  // 耗时（毫秒）
+    pub created_at: i64,  // 同步时间（Unix 时间戳，秒）
+}
+
+/// 同步历史分页结果（从服务器读取 snake_case，返回前端 camelCase）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "snake_case"))]
+pub struct SyncHistoryPage {
+    pub entries: Vec<SyncHistoryEntry>,
+    pub next_cursor: Option<String>,  // 下一页游标（为空表示没有更多记录）
+}
+
+/// 按设备汇总的同步统计（从服务器读取 snake_case，返回前端 camelCase）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "snake_case"))]
+pub struct DeviceSyncSummary {
+    pub device_id: Option<String>,  // 设备 ID（为空表示未记录设备的旧记录）
+    pub sync_count: i64,  // 同步次数
+    pub pushed_total: i64,  // 推送总数
+    pub pulled_total: i64,  // 拉取总数
+    pub conflict_total: i64,  // 冲突总数
+    pub last_sync_at: i64,  // 最近同步时间（Unix 时间戳，秒）
+}
+
+/// 同步历史结果过滤
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SyncHistoryOutcome {
+    #[default]
+    All,  // 全部记录
+    Conflicts,  // 仅包含冲突的同步
+    Errors,  // 仅失败的同步
+}
+
+impl SyncHistoryOutcome {
+    /// 服务器查询参数值
+    pub fn as_query_value(&self) -> &'static str {
+        match self {
+            SyncHistoryOutcome::All => "all",
+            SyncHistoryOutcome::Conflicts => "conflicts",
+            SyncHistoryOutcome::Errors => "errors",
+        }
     }
 }
 
+/// 同步历史查询条件
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncHistoryFilter {
+    #[serde(default)]
+    pub outcome: SyncHistoryOutcome,  // 结果过滤
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sync_type: Option<String>,  // 同步类型过滤
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<i64>,  // 起始时间（Unix 时间戳，秒）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<i64>,  // 结束时间（Unix 时间戳，秒）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<String>,  // 分页游标（上一页返回的 nextCursor）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,  // 每页条数（默认 50，服务器上限 100）
+}
+
+/// is_deleted 变化记录（来自本地 delete_events 表）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteEvent {
+    pub is_deleted: bool,  // 变化后的状态（true = 删除，false = 恢复）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_ver: Option<i32>,  // 变化时的服务器版本号
+    pub occurred_at: i64,  // 发生时间（Unix 时间戳，秒）
+}
+
+/// 反复删除/恢复的实体
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FlappingItem {
+    pub entity_type: String,  // note / folder / tag
+    pub entity_id: String,
+    pub toggle_count: usize,  // 窗口内 is_deleted 变化次数
+    pub timeline: Vec<DeleteEvent>,  // 按时间升序的变化记录
+}
+
+/// 待同步变更类型
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum PendingChangeKind {
+    New,  // 新建（从未同步过，server_ver = 0）
+    Edited,  // 修改
+    Deleted,  // 删除
+}
+
+/// 待同步变更（同步前预览）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingChange {
+    pub entity_type: String,  // workspace / note / folder / tag / snapshot
+    pub id: String,
+    pub title: String,  // 标题或名称
+    pub change: PendingChangeKind,  // 变更类型
+    pub updated_at: i64,  // 最后修改时间（Unix 时间戳，秒）
+}
+
+/// 仅存在于本地的数据（从未被服务器确认）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalOnlyItem {
+    pub entity_type: String,  // note / folder / tag
+    pub id: String,
+    pub title: String,  // 标题或名称
+    pub is_dirty: bool,  // 是否仍标记为待同步（false 表示曾同步失败但脏标记已被清除）
+    pub updated_at: i64,  // 最后修改时间（Unix 时间戳，秒）
+}
+
+/// 未处理的笔记冲突（冲突副本及其原笔记）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingConflict {
+    pub id: i64,  // 冲突记录 ID
+    pub note_id: String,  // 原笔记 ID
+    pub copy_id: Option<String>,  // 冲突副本 ID（未创建副本时为空）
+    pub title: String,
+    pub local_version: i32,
+    pub server_version: i32,
+    pub local_content: String,  // 本地版本内容（服务器未返回时取冲突副本内容）
+    pub server_content: String,  // 服务器版本内容（服务器未返回时取原笔记当前内容）
+    pub local_updated_at: Option<i64>,
+    pub server_updated_at: Option<i64>,
+    pub original: Option<crate::models::Note>,  // 原笔记当前状态
+    pub copy: Option<crate::models::Note>,  // 冲突副本当前状态
+    pub created_at: i64,  // 冲突发生时间（Unix 时间戳，秒）
+}
+
+/// 单项同步重试队列条目
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncQueueEntry {
+    pub id: i64,
+    pub entity_type: String,  // note / tag / snapshot / folder
+    pub entity_id: String,
+    pub status: String,  // pending / dead_letter
+    pub attempts: u32,  // 已失败次数
+    pub last_error: Option<String>,  // 最近一次失败原因
+    pub next_attempt_at: i64,  // 下次重试时间（Unix 时间戳，秒）
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// 从服务器重建文件夹的结果
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderRebuildReport {
+    pub server_folders: usize,  // 从服务器写入的文件夹数量
+    pub removed_local_folders: usize,  // 删除的仅本地存在的文件夹数量
+    pub relinked_notes: usize,  // folder_id 能在重建后的文件夹中找到的笔记数量
+    pub orphaned_notes: usize,  // 所在文件夹不存在、已移到根目录的笔记数量
+}
+
+/// 服务器限制（从服务器读取 snake_case，返回前端 camelCase）
+/// 默认每篇笔记保留的最大快照数量（与服务器默认值一致，未获取到服务器限制时使用）
+pub const DEFAULT_MAX_SNAPSHOTS_PER_NOTE: i64 = 20;
+
+/// 默认单个附件的最大大小（字节，与服务器默认值一致，未获取到服务器限制时使用）
+pub const DEFAULT_MAX_ATTACHMENT_BYTES: i64 = 10 * 1024 * 1024;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all(serialize = "camelCase", deserialize = "snake_case"))]
+pub struct ServerLimits {
+    pub max_note_length: usize,  // 单篇笔记内容最大字符数（0 表示不限制）
+    pub max_snapshots_per_note: i64,  // 每篇笔记保留的最大快照数量
+    #[serde(default = "default_max_attachment_bytes")]
+    pub max_attachment_bytes: i64,  // 单个附件的最大大小（字节，旧版本服务器不返回）
+}
+
+fn default_max_attachment_bytes() -> i64 {
+    DEFAULT_MAX_ATTACHMENT_BYTES
+}
+
 /// 冲突解决策略
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 #[serde(rename_all = "camelCase")]
@@ -527,4 +855,25 @@ pub enum ConflictStrategy {
     KeepBoth,  // 保留两个版本（创建冲突副本，默认）
     KeepServer,  // 保留服务器版本
     KeepLocal,  // 保留本地版本
+    LastWriteWins,  // 保留修改时间较新的版本（时间相近时保留服务器版本并记录冲突）
+}
+
+/// 按实体类型配置的冲突解决策略
+///
+/// 未设置的实体类型使用 `SyncRequest.conflict_resolution`。
+/// ⚠️ 只有笔记支持 `keepBoth`（创建冲突副本），快照不支持 `lastWriteWins`；为实体指定不支持的策略时
+/// 服务器返回 400，从 `conflict_resolution` 继承来的不支持的策略按 `keepServer` 处理
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictStrategies {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<ConflictStrategy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<ConflictStrategy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub folder: Option<ConflictStrategy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<ConflictStrategy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<ConflictStrategy>,
 }
@@ -26,12 +26,49 @@ pub struct Tag {
     pub last_synced_at: Option<i64>,  // 最后同步时间（Unix 时间戳，秒）
 }
 
+/// 跨工作空间颜色不一致的同名标签
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TagColorInconsistency {
+    pub name: String,  // 标签名称
+    pub tags: Vec<Tag>,  // 各工作空间中的同名标签（颜色不完全相同）
+}
+
+/// 相似标签分组中的单个标签
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SimilarTag {
+    pub tag: Tag,
+    pub note_count: i64,  // 关联的笔记数量
+    pub score: f64,  // 与建议目标标签的相似度（0.0 ~ 1.0）
+}
+
+/// 一组名称相似、建议合并的标签
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SimilarTagGroup {
+    pub suggested_target_id: String,  // 建议保留的标签（关联笔记最多的标签）
+    pub tags: Vec<SimilarTag>,  // 组内所有标签（包含目标标签）
+}
+
+/// 标签合并结果
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TagMergeResult {
+    pub source_tag_id: String,  // 被合并（已软删除）的标签
+    pub target_tag_id: String,  // 保留的标签
+    pub moved_relations: usize,  // 转移到目标标签的笔记关联数
+    pub already_tagged: usize,  // 已同时带有两个标签的笔记数（只移除源标签关联）
+}
+
 /// 创建标签请求
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateTagRequest {
     pub name: String,  // 标签名称
     pub color: Option<String>,  // 标签颜色
+    #[serde(default)]
+    pub merge_on_conflict: bool,  // 工作空间已有同名标签时直接返回该标签（否则报错）
 }
 
 /// 更新标签请求
@@ -40,6 +77,8 @@ pub struct CreateTagRequest {
 pub struct UpdateTagRequest {
     pub name: Option<String>,  // 新标签名称
     pub color: Option<String>,  // 新标签颜色
+    #[serde(default)]
+    pub merge_on_conflict: bool,  // 新名称与工作空间已有标签冲突时合并到该标签（否则报错）
 }
 
 /// 笔记-标签关联请求
@@ -1,6 +1,15 @@
 use serde::{Serialize, Deserialize};
 use uuid::Uuid;
 
+/// 工作空间名称最大长度（字符数，与服务器一致）
+pub const MAX_WORKSPACE_NAME_LENGTH: usize = 100;
+
+/// 工作空间描述最大长度（字符数）
+pub const MAX_WORKSPACE_DESCRIPTION_LENGTH: usize = 500;
+
+/// 工作空间图标最大长度（字符数，图标为 emoji，组合 emoji 由多个字符组成）
+pub const MAX_WORKSPACE_ICON_LENGTH: usize = 16;
+
 /// 工作空间模型
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -45,6 +54,66 @@ pub struct Workspace {
     pub last_synced_at: Option<i64>,  // 最后同步时间（Unix 时间戳，秒）
 }
 
+/// 工作空间检查点（整个工作空间的笔记、文件夹、标签的压缩副本）
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceCheckpoint {
+    pub id: String,
+    pub workspace_id: String,
+    pub name: String,            // 检查点名称
+    pub note_count: usize,       // 包含的笔记数量（不含已删除）
+    pub folder_count: usize,     // 包含的文件夹数量（不含已删除）
+    pub tag_count: usize,        // 包含的标签数量（不含已删除）
+    pub size_bytes: i64,         // 压缩后的大小（字节）
+    pub created_at: i64,         // 创建时间（Unix 时间戳，秒）
+}
+
+/// 存储统计数据（数量不含回收站，回收站单独计数）
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageCounts {
+    pub notes: usize,            // 笔记数量
+    pub folders: usize,          // 文件夹数量
+    pub tags: usize,             // 标签数量
+    pub snapshots: usize,        // 版本快照数量
+    pub trashed_notes: usize,    // 回收站中的笔记数量
+    pub trashed_folders: usize,  // 回收站中的文件夹数量
+    pub trashed_tags: usize,     // 回收站中的标签数量
+    pub approx_bytes: i64,       // 近似大小（笔记和快照内容的字节数之和，含回收站）
+}
+
+impl StorageCounts {
+    /// 累加另一份统计
+    pub fn add(&mut self, other: &StorageCounts) {
+        self.notes += other.notes;
+        self.folders += other.folders;
+        self.tags += other.tags;
+        self.snapshots += other.snapshots;
+        self.trashed_notes += other.trashed_notes;
+        self.trashed_folders += other.trashed_folders;
+        self.trashed_tags += other.trashed_tags;
+        self.approx_bytes += other.approx_bytes;
+    }
+}
+
+/// 单个工作空间的存储统计
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceStorageStats {
+    pub workspace_id: String,
+    pub workspace_name: String,
+    #[serde(flatten)]
+    pub counts: StorageCounts,
+}
+
+/// 存储统计（各工作空间及合计）
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageStats {
+    pub workspaces: Vec<WorkspaceStorageStats>,
+    pub total: StorageCounts,
+}
+
 impl Workspace {
     /// 创建新工作空间（构造函数）
     ///
@@ -1,7 +1,9 @@
-use crate::models::{AppSettings, UpdateAppSettings};
+use crate::models::{AppSettings, ConflictStrategies, ConflictStrategy, UpdateAppSettings};
+use crate::models::app_settings::{MIN_EXCERPT_LENGTH, MAX_EXCERPT_LENGTH, MIN_SYNC_INTERVAL_MINUTES, MAX_SYNC_INTERVAL_MINUTES, DEFAULT_TAG_PALETTE, DEFAULT_LOW_BATTERY_THRESHOLD, DEFAULT_MAX_SYNC_RETRIES, DEFAULT_SYNC_REQUEST_MAX_ATTEMPTS, DEFAULT_SYNC_RETRY_BASE_DELAY_MS, DEFAULT_SYNC_COMPRESSION_THRESHOLD_BYTES, DEFAULT_TOKEN_REFRESH_WINDOW_MINUTES, DEFAULT_TRASH_RETENTION_DAYS, MAX_TRASH_RETENTION_DAYS, is_valid_hex_color};
 use crate::models::error::{Result, AppError};
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
+use r2d2_sqlite::rusqlite::params;
 
 /// 应用设置服务
 ///
@@ -24,7 +26,10 @@ impl AppSettingsService {
 
         let mut stmt = conn.prepare(
             "SELECT id, default_server_url, auto_sync_enabled, sync_interval_minutes,
-                    theme, language, updated_at
+                    theme, language, excerpt_length, max_note_length, conflict_strategies, tag_palette, max_synced_snapshot_bytes,
+                    pause_sync_on_low_battery, low_battery_threshold, pause_sync_on_metered, max_sync_retries,
+                    sync_request_max_attempts, sync_retry_base_delay_ms, sync_compression_threshold_bytes,
+                    token_refresh_window_minutes, trash_retention_days, workspace_trash_retention, updated_at
              FROM app_settings
              WHERE id = 1"
         ).map_err(|e| AppError::DatabaseError(format!("查询应用设置失败: {}", e)))?;
@@ -37,7 +42,28 @@ impl AppSettingsService {
                 sync_interval_minutes: row.get(3)?,
                 theme: row.get(4)?,
                 language: row.get(5)?,
-                updated_at: row.get(6)?,
+                excerpt_length: row.get(6)?,
+                max_note_length: row.get(7)?,
+                conflict_strategies: row.get::<_, Option<String>>(8)?
+                    .and_then(|json| serde_json::from_str(&json).ok())
+                    .unwrap_or_default(),
+                tag_palette: row.get::<_, Option<String>>(9)?
+                    .and_then(|json| serde_json::from_str(&json).ok())
+                    .unwrap_or_else(|| DEFAULT_TAG_PALETTE.iter().map(|c| c.to_string()).collect()),
+                max_synced_snapshot_bytes: row.get::<_, Option<i64>>(10)?.unwrap_or(0),
+                pause_sync_on_low_battery: row.get::<_, Option<bool>>(11)?.unwrap_or(false),
+                low_battery_threshold: row.get::<_, Option<i32>>(12)?.unwrap_or(DEFAULT_LOW_BATTERY_THRESHOLD),
+                pause_sync_on_metered: row.get::<_, Option<bool>>(13)?.unwrap_or(false),
+                max_sync_retries: row.get::<_, Option<i32>>(14)?.unwrap_or(DEFAULT_MAX_SYNC_RETRIES),
+                sync_request_max_attempts: row.get::<_, Option<i32>>(15)?.unwrap_or(DEFAULT_SYNC_REQUEST_MAX_ATTEMPTS),
+                sync_retry_base_delay_ms: row.get::<_, Option<i32>>(16)?.unwrap_or(DEFAULT_SYNC_RETRY_BASE_DELAY_MS),
+                sync_compression_threshold_bytes: row.get::<_, Option<i64>>(17)?.unwrap_or(DEFAULT_SYNC_COMPRESSION_THRESHOLD_BYTES),
+                token_refresh_window_minutes: row.get::<_, Option<i32>>(18)?.unwrap_or(DEFAULT_TOKEN_REFRESH_WINDOW_MINUTES),
+                trash_retention_days: row.get::<_, Option<i32>>(19)?.unwrap_or(DEFAULT_TRASH_RETENTION_DAYS),
+                workspace_trash_retention: row.get::<_, Option<String>>(20)?
+                    .and_then(|json| serde_json::from_str(&json).ok())
+                    .unwrap_or_default(),
+                updated_at: row.get(21)?,
             })
         }).map_err(|e| AppError::DatabaseError(format!("应用设置不存在: {}", e)))?;
 
@@ -52,6 +78,91 @@ impl AppSettingsService {
         // 获取当前设置
         let current = self.get_settings()?;
 
+        if let Some(excerpt_length) = updates.excerpt_length {
+            if !(MIN_EXCERPT_LENGTH..=MAX_EXCERPT_LENGTH).contains(&excerpt_length) {
+                return Err(AppError::InvalidInput(format!(
+                    "摘要长度必须在 {} 到 {} 之间",
+                    MIN_EXCERPT_LENGTH, MAX_EXCERPT_LENGTH
+                )));
+            }
+        }
+
+        if let Some(minutes) = updates.sync_interval_minutes {
+            if !(MIN_SYNC_INTERVAL_MINUTES..=MAX_SYNC_INTERVAL_MINUTES).contains(&minutes) {
+                return Err(AppError::InvalidInput(format!(
+                    "同步间隔必须在 {} 到 {} 分钟之间",
+                    MIN_SYNC_INTERVAL_MINUTES, MAX_SYNC_INTERVAL_MINUTES
+                )));
+            }
+        }
+
+        if let Some(max_note_length) = updates.max_note_length {
+            if max_note_length < 0 {
+                return Err(AppError::InvalidInput("笔记长度上限不能为负数（0 表示不限制）".to_string()));
+            }
+        }
+
+        if let Some(max_bytes) = updates.max_synced_snapshot_bytes {
+            if max_bytes < 0 {
+                return Err(AppError::InvalidInput("快照同步大小上限不能为负数（0 表示不限制）".to_string()));
+            }
+        }
+
+        if let Some(threshold) = updates.low_battery_threshold {
+            if !(0..=100).contains(&threshold) {
+                return Err(AppError::InvalidInput("低电量阈值必须在 0 到 100 之间".to_string()));
+            }
+        }
+
+        if let Some(max_sync_retries) = updates.max_sync_retries {
+            if !(1..=100).contains(&max_sync_retries) {
+                return Err(AppError::InvalidInput("最大重试次数必须在 1 到 100 之间".to_string()));
+            }
+        }
+
+        if let Some(attempts) = updates.sync_request_max_attempts {
+            if !(1..=10).contains(&attempts) {
+                return Err(AppError::InvalidInput("同步请求尝试次数必须在 1 到 10 之间".to_string()));
+            }
+        }
+
+        if let Some(delay_ms) = updates.sync_retry_base_delay_ms {
+            if !(0..=60_000).contains(&delay_ms) {
+                return Err(AppError::InvalidInput("同步重试间隔必须在 0 到 60000 毫秒之间".to_string()));
+            }
+        }
+
+        if let Some(threshold) = updates.sync_compression_threshold_bytes {
+            if threshold < 0 {
+                return Err(AppError::InvalidInput("同步压缩阈值不能为负数（0 表示不压缩）".to_string()));
+            }
+        }
+
+        if let Some(window) = updates.token_refresh_window_minutes {
+            if !(0..=1440).contains(&window) {
+                return Err(AppError::InvalidInput("token 刷新窗口必须在 0 到 1440 分钟之间（0 表示不主动刷新）".to_string()));
+            }
+        }
+
+        if let Some(tag_palette) = &updates.tag_palette {
+            Self::validate_tag_palette(tag_palette)?;
+        }
+
+        if let Some(strategies) = &updates.conflict_strategies {
+            Self::validate_conflict_strategies(strategies)?;
+        }
+
+        let retention_days = updates.trash_retention_days.into_iter()
+            .chain(updates.workspace_trash_retention.iter().flat_map(|m| m.values().copied()));
+        for days in retention_days {
+            if !(0..=MAX_TRASH_RETENTION_DAYS).contains(&days) {
+                return Err(AppError::InvalidInput(format!(
+                    "回收站保留天数必须在 0 到 {} 之间（0 表示不自动清理）",
+                    MAX_TRASH_RETENTION_DAYS
+                )));
+            }
+        }
+
         // 构建更新后的设置
         let updated = AppSettings {
             default_server_url: updates.default_server_url.unwrap_or(current.default_server_url),
@@ -59,6 +170,21 @@ impl AppSettingsService {
             sync_interval_minutes: updates.sync_interval_minutes.unwrap_or(current.sync_interval_minutes),
             theme: updates.theme.unwrap_or(current.theme),
             language: updates.language.unwrap_or(current.language),
+            excerpt_length: updates.excerpt_length.unwrap_or(current.excerpt_length),
+            max_note_length: updates.max_note_length.unwrap_or(current.max_note_length),
+            conflict_strategies: updates.conflict_strategies.unwrap_or(current.conflict_strategies),
+            tag_palette: updates.tag_palette.unwrap_or(current.tag_palette),
+            max_synced_snapshot_bytes: updates.max_synced_snapshot_bytes.unwrap_or(current.max_synced_snapshot_bytes),
+            pause_sync_on_low_battery: updates.pause_sync_on_low_battery.unwrap_or(current.pause_sync_on_low_battery),
+            low_battery_threshold: updates.low_battery_threshold.unwrap_or(current.low_battery_threshold),
+            pause_sync_on_metered: updates.pause_sync_on_metered.unwrap_or(current.pause_sync_on_metered),
+            max_sync_retries: updates.max_sync_retries.unwrap_or(current.max_sync_retries),
+            sync_request_max_attempts: updates.sync_request_max_attempts.unwrap_or(current.sync_request_max_attempts),
+            sync_retry_base_delay_ms: updates.sync_retry_base_delay_ms.unwrap_or(current.sync_retry_base_delay_ms),
+            sync_compression_threshold_bytes: updates.sync_compression_threshold_bytes.unwrap_or(current.sync_compression_threshold_bytes),
+            token_refresh_window_minutes: updates.token_refresh_window_minutes.unwrap_or(current.token_refresh_window_minutes),
+            trash_retention_days: updates.trash_retention_days.unwrap_or(current.trash_retention_days),
+            workspace_trash_retention: updates.workspace_trash_retention.unwrap_or(current.workspace_trash_retention),
             updated_at: chrono::Utc::now().timestamp(),
             id: 1,
         };
@@ -67,16 +193,39 @@ impl AppSettingsService {
         conn.execute(
             "UPDATE app_settings
              SET default_server_url = ?1, auto_sync_enabled = ?2, sync_interval_minutes = ?3,
-                 theme = ?4, language = ?5, updated_at = ?6
+                 theme = ?4, language = ?5, excerpt_length = ?6, max_note_length = ?7,
+                 conflict_strategies = ?8, tag_palette = ?9, max_synced_snapshot_bytes = ?10,
+                 pause_sync_on_low_battery = ?11, low_battery_threshold = ?12, pause_sync_on_metered = ?13,
+                 max_sync_retries = ?14, sync_request_max_attempts = ?15, sync_retry_base_delay_ms = ?16,
+                 sync_compression_threshold_bytes = ?17, token_refresh_window_minutes = ?18,
+                 trash_retention_days = ?19, workspace_trash_retention = ?20, updated_at = ?21
              WHERE id = 1",
-            (
+            params![
                 &updated.default_server_url,
                 updated.auto_sync_enabled,
                 updated.sync_interval_minutes,
                 &updated.theme,
                 &updated.language,
+                updated.excerpt_length,
+                updated.max_note_length,
+                serde_json::to_string(&updated.conflict_strategies)
+                    .map_err(|e| AppError::Internal(format!("序列化冲突策略失败: {}", e)))?,
+                serde_json::to_string(&updated.tag_palette)
+                    .map_err(|e| AppError::Internal(format!("序列化标签调色板失败: {}", e)))?,
+                updated.max_synced_snapshot_bytes,
+                updated.pause_sync_on_low_battery,
+                updated.low_battery_threshold,
+                updated.pause_sync_on_metered,
+                updated.max_sync_retries,
+                updated.sync_request_max_attempts,
+                updated.sync_retry_base_delay_ms,
+                updated.sync_compression_threshold_bytes,
+                updated.token_refresh_window_minutes,
+                updated.trash_retention_days,
+                serde_json::to_string(&updated.workspace_trash_retention)
+                    .map_err(|e| AppError::Internal(format!("序列化回收站保留设置失败: {}", e)))?,
                 updated.updated_at,
-            ),
+            ],
         ).map_err(|e| AppError::DatabaseError(format!("更新应用设置失败: {}", e)))?;
 
         log::info!("应用设置已更新");
@@ -89,6 +238,136 @@ impl AppSettingsService {
         Ok(settings.default_server_url)
     }
 
+    /// 获取笔记摘要长度（字符数）
+    pub fn get_excerpt_length(&self) -> Result<usize> {
+        let settings = self.get_settings()?;
+        Ok(settings.excerpt_length.clamp(MIN_EXCERPT_LENGTH, MAX_EXCERPT_LENGTH) as usize)
+    }
+
+    /// 获取笔记内容长度上限（字符数，0 表示不限制）
+    pub fn get_max_note_length(&self) -> Result<usize> {
+        let settings = self.get_settings()?;
+        Ok(settings.max_note_length.max(0) as usize)
+    }
+
+    /// 获取参与同步的快照大小上限（字节，0 表示不限制）
+    pub fn get_max_synced_snapshot_bytes(&self) -> Result<usize> {
+        let settings = self.get_settings()?;
+        Ok(settings.max_synced_snapshot_bytes.max(0) as usize)
+    }
+
+    /// 获取单项同步最大重试次数
+    pub fn get_max_sync_retries(&self) -> Result<u32> {
+        let settings = self.get_settings()?;
+        Ok(settings.max_sync_retries.max(1) as u32)
+    }
+
+    /// 获取同步请求重试策略（最大尝试次数, 基础间隔毫秒）
+    pub fn get_sync_retry_policy(&self) -> Result<(u32, u64)> {
+        let settings = self.get_settings()?;
+        Ok((
+            settings.sync_request_max_attempts.max(1) as u32,
+            settings.sync_retry_base_delay_ms.max(0) as u64,
+        ))
+    }
+
+    /// 获取同步请求 gzip 压缩阈值（字节，0 表示不压缩）
+    pub fn get_sync_compression_threshold(&self) -> Result<usize> {
+        let settings = self.get_settings()?;
+        Ok(settings.sync_compression_threshold_bytes.max(0) as usize)
+    }
+
+    /// 获取标签调色板
+    pub fn get_tag_palette(&self) -> Result<Vec<String>> {
+        let settings = self.get_settings()?;
+        Ok(settings.tag_palette)
+    }
+
+    /// 设置标签调色板
+    ///
+    /// 每项必须是 `#RGB` 或 `#RRGGBB` 格式；传入空列表表示新建标签时不自动分配颜色
+    pub fn set_tag_palette(&self, palette: Vec<String>) -> Result<Vec<String>> {
+        let updated = self.update_settings(UpdateAppSettings {
+            default_server_url: None,
+            auto_sync_enabled: None,
+            sync_interval_minutes: None,
+            theme: None,
+            language: None,
+            excerpt_length: None,
+            max_note_length: None,
+            conflict_strategies: None,
+            tag_palette: Some(palette),
+            max_synced_snapshot_bytes: None,
+            pause_sync_on_low_battery: None,
+            low_battery_threshold: None,
+            pause_sync_on_metered: None,
+            max_sync_retries: None,
+            sync_request_max_attempts: None,
+            sync_retry_base_delay_ms: None,
+            sync_compression_threshold_bytes: None,
+            token_refresh_window_minutes: None,
+            trash_retention_days: None,
+            workspace_trash_retention: None,
+        })?;
+        Ok(updated.tag_palette)
+    }
+
+    /// 设置自动同步间隔（分钟）
+    pub fn set_sync_interval(&self, minutes: i32) -> Result<i32> {
+        let updated = self.update_settings(UpdateAppSettings {
+            default_server_url: None,
+            auto_sync_enabled: None,
+            sync_interval_minutes: Some(minutes),
+            theme: None,
+            language: None,
+            excerpt_length: None,
+            max_note_length: None,
+            conflict_strategies: None,
+            tag_palette: None,
+            max_synced_snapshot_bytes: None,
+            pause_sync_on_low_battery: None,
+            low_battery_threshold: None,
+            pause_sync_on_metered: None,
+            max_sync_retries: None,
+            sync_request_max_attempts: None,
+            sync_retry_base_delay_ms: None,
+            sync_compression_threshold_bytes: None,
+            token_refresh_window_minutes: None,
+            trash_retention_days: None,
+            workspace_trash_retention: None,
+        })?;
+        Ok(updated.sync_interval_minutes)
+    }
+
+    /// 校验调色板中的颜色格式
+    fn validate_tag_palette(palette: &[String]) -> Result<()> {
+        if let Some(invalid) = palette.iter().find(|c| !is_valid_hex_color(c)) {
+            return Err(AppError::InvalidInput(format!("无效的颜色值: {}（应为 #RGB 或 #RRGGBB）", invalid)));
+        }
+        Ok(())
+    }
+
+    /// 校验按实体类型配置的冲突策略（服务器对实体不支持的策略返回 400）
+    ///
+    /// 只有笔记支持 `keepBoth`（创建冲突副本），快照没有修改时间，不支持 `lastWriteWins`
+    fn validate_conflict_strategies(strategies: &ConflictStrategies) -> Result<()> {
+        let entities = [
+            ("工作空间", &strategies.workspace),
+            ("文件夹", &strategies.folder),
+            ("标签", &strategies.tag),
+            ("快照", &strategies.snapshot),
+        ];
+        for (name, strategy) in entities {
+            if matches!(strategy, Some(ConflictStrategy::KeepBoth)) {
+                return Err(AppError::InvalidInput(format!("{}不支持保留两个版本（只有笔记可以创建冲突副本）", name)));
+            }
+        }
+        if matches!(strategies.snapshot, Some(ConflictStrategy::LastWriteWins)) {
+            return Err(AppError::InvalidInput("快照不支持按修改时间解决冲突".to_string()));
+        }
+        Ok(())
+    }
+
     /// 重置为默认设置
     pub fn reset_to_default(&self) -> Result<AppSettings> {
         let default = AppSettings::default();
@@ -100,16 +379,36 @@ impl AppSettingsService {
         conn.execute(
             "UPDATE app_settings
              SET default_server_url = ?1, auto_sync_enabled = ?2, sync_interval_minutes = ?3,
-                 theme = ?4, language = ?5, updated_at = ?6
+                 theme = ?4, language = ?5, excerpt_length = ?6, max_note_length = ?7,
+                 conflict_strategies = ?8, tag_palette = ?9, max_synced_snapshot_bytes = ?10,
+                 pause_sync_on_low_battery = ?11, low_battery_threshold = ?12, pause_sync_on_metered = ?13,
+                 max_sync_retries = ?14, sync_request_max_attempts = ?15, sync_retry_base_delay_ms = ?16,
+                 sync_compression_threshold_bytes = ?17, token_refresh_window_minutes = ?18,
+                 trash_retention_days = ?19, workspace_trash_retention = ?20, updated_at = ?21
              WHERE id = 1",
-            (
+            params![
                 &default.default_server_url,
                 default.auto_sync_enabled,
                 default.sync_interval_minutes,
                 &default.theme,
                 &default.language,
+                default.excerpt_length,
+                default.max_note_length,
+                Option::<String>::None,
+                Option::<String>::None,
+                default.max_synced_snapshot_bytes,
+                default.pause_sync_on_low_battery,
+                default.low_battery_threshold,
+                default.pause_sync_on_metered,
+                default.max_sync_retries,
+                default.sync_request_max_attempts,
+                default.sync_retry_base_delay_ms,
+                default.sync_compression_threshold_bytes,
+                default.token_refresh_window_minutes,
+                default.trash_retention_days,
+                Option::<String>::None,
                 now,
-            ),
+            ],
         ).map_err(|e| AppError::DatabaseError(format!("重置应用设置失败: {}", e)))?;
 
         log::info!("应用设置已重置为默认值");
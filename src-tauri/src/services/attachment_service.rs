@@ -0,0 +1,169 @@
+use crate::database::repositories::AttachmentRepository;
+use crate::models::{Attachment, AddAttachmentRequest, AttachmentListItem, DEFAULT_MAX_ATTACHMENT_BYTES};
+use crate::models::error::{Result, AppError};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::Utc;
+use uuid::Uuid;
+
+/// 附件服务
+///
+/// 管理笔记附件（粘贴的图片等），附件随笔记同步到云端
+#[derive(Clone)]
+pub struct AttachmentService {
+    repo: AttachmentRepository,
+}
+
+impl AttachmentService {
+    pub fn new(repo: AttachmentRepository) -> Self {
+        Self { repo }
+    }
+
+    /// 单个附件的最大大小（读取缓存的服务器限制，未缓存时使用默认值）
+    fn max_attachment_bytes(&self) -> Result<i64> {
+        Ok(self.repo.find_cached_server_limits()?
+            .map(|limits| limits.max_attachment_bytes)
+            .filter(|max| *max > 0)
+            .unwrap_or(DEFAULT_MAX_ATTACHMENT_BYTES))
+    }
+
+    /// 添加附件
+    ///
+    /// 附件归属到笔记所在的工作空间；超过服务器限制大小的附件会被拒绝，避免同步时被服务器拒绝
+    pub fn add_attachment(&self, req: AddAttachmentRequest) -> Result<Attachment> {
+        let filename = req.filename.trim();
+        if filename.is_empty() {
+            return Err(AppError::Validation("附件文件名不能为空".to_string()));
+        }
+        if req.mime_type.trim().is_empty() {
+            return Err(AppError::Validation("附件类型不能为空".to_string()));
+        }
+
+        let decoded = general_purpose::STANDARD
+            .decode(&req.data)
+            .map_err(|e| AppError::InvalidInput(format!("附件数据不是有效的 Base64: {}", e)))?;
+        let size_bytes = decoded.len() as i64;
+        let max_bytes = self.max_attachment_bytes()?;
+        if size_bytes > max_bytes {
+            return Err(AppError::Validation(format!(
+                "附件「{}」大小 {} 字节，超过上限 {} 字节",
+                filename, size_bytes, max_bytes
+            )));
+        }
+
+        let workspace_id = self.repo.find_note_workspace_id(&req.note_id)?
+            .ok_or_else(|| AppError::NoteNotFound(req.note_id.clone()))?;
+
+        let now = Utc::now().timestamp();
+        let attachment = Attachment {
+            id: Uuid::new_v4().to_string(),
+            note_id: req.note_id,
+            workspace_id,
+            filename: filename.to_string(),
+            mime_type: req.mime_type,
+            size_bytes,
+            data: req.data,
+            created_at: now,
+            updated_at: now,
+            is_deleted: false,
+            deleted_at: None,
+            server_ver: 0,
+            is_dirty: true,
+            last_synced_at: None,
+        };
+        self.repo.create(&attachment)?;
+
+        log::info!("已为笔记 {} 添加附件 {}（{} 字节）", attachment.note_id, attachment.id, size_bytes);
+        Ok(attachment)
+    }
+
+    /// 列出笔记的附件（不含文件数据）
+    pub fn list_attachments(&self, note_id: &str) -> Result<Vec<AttachmentListItem>> {
+        self.repo.find_by_note(note_id)
+    }
+
+    /// 获取附件（含文件数据）
+    pub fn get_attachment(&self, id: &str) -> Result<Attachment> {
+        self.repo.find_by_id(id)?
+            .ok_or_else(|| AppError::NotFound(format!("附件 {}", id)))
+    }
+
+    /// 删除附件（软删除，同步后从其他设备移除）
+    pub fn delete_attachment(&self, id: &str) -> Result<()> {
+        if !self.repo.soft_delete(id)? {
+            return Err(AppError::NotFound(format!("附件 {}", id)));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory_service() -> (AttachmentService, crate::database::DbPool) {
+        let pool = crate::database::memory_pool();
+        pool.get().unwrap().execute_batch(
+            "INSERT INTO notes (id, title, content, workspace_id, created_at, updated_at) VALUES ('note-1', 't', 'c', 'ws-1', 0, 0);"
+        ).unwrap();
+
+        (AttachmentService::new(AttachmentRepository::new(pool.clone())), pool)
+    }
+
+    fn image_request(note_id: &str, bytes: &[u8]) -> AddAttachmentRequest {
+        AddAttachmentRequest {
+            note_id: note_id.to_string(),
+            filename: "image.png".to_string(),
+            mime_type: "image/png".to_string(),
+            data: general_purpose::STANDARD.encode(bytes),
+        }
+    }
+
+    #[test]
+    fn test_add_list_and_delete_attachment() {
+        let (service, _pool) = memory_service();
+
+        let attachment = service.add_attachment(image_request("note-1", b"png-bytes")).unwrap();
+
+        assert_eq!(attachment.workspace_id.as_deref(), Some("ws-1"));
+        assert_eq!(attachment.size_bytes, 9);
+        assert!(attachment.is_dirty);
+        let listed = service.list_attachments("note-1").unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, attachment.id);
+        assert_eq!(service.get_attachment(&attachment.id).unwrap().data, attachment.data);
+
+        service.delete_attachment(&attachment.id).unwrap();
+
+        assert!(service.list_attachments("note-1").unwrap().is_empty());
+        assert!(matches!(service.get_attachment(&attachment.id), Err(AppError::NotFound(_))));
+        assert!(matches!(service.delete_attachment(&attachment.id), Err(AppError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_add_attachment_enforces_server_limit() {
+        let (service, pool) = memory_service();
+        pool.get().unwrap().execute(
+            "INSERT INTO settings (key, value) VALUES ('server_limits', ?1)",
+            [r#"{"max_note_length": 0, "max_snapshots_per_note": 20, "max_attachment_bytes": 4}"#],
+        ).unwrap();
+
+        assert!(service.add_attachment(image_request("note-1", b"1234")).is_ok());
+        assert!(matches!(
+            service.add_attachment(image_request("note-1", b"12345")),
+            Err(AppError::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn test_add_attachment_rejects_invalid_input() {
+        let (service, _pool) = memory_service();
+
+        let mut invalid_data = image_request("note-1", b"x");
+        invalid_data.data = "not base64!".to_string();
+        assert!(matches!(service.add_attachment(invalid_data), Err(AppError::InvalidInput(_))));
+        assert!(matches!(
+            service.add_attachment(image_request("missing", b"x")),
+            Err(AppError::NoteNotFound(_))
+        ));
+    }
+}
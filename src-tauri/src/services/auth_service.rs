@@ -1,14 +1,33 @@
-use crate::models::{LoginRequest, RegisterRequest, AuthResponse, User};
+use crate::models::{LoginRequest, RegisterRequest, AuthResponse, User, Device};
 use crate::models::error::{Result, AppError};
 use crate::services::{AppSettingsService, UserProfileService, CryptoService};
+use crate::services::crypto::KeyScheme;
 use crate::database::repositories::UserProfileRepository;
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
 use uuid::Uuid;
 use reqwest::Client;
 use std::time::Duration;
+use serde::Serialize;
 use serde_json::json;
 use r2d2_sqlite::rusqlite;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// 账号纪元：每次切换账号或登出时递增
+///
+/// 同步开始时记录当前值，应用服务器数据前比对；
+/// 值发生变化说明账号已变更，进行中的同步应立即取消
+static ACCOUNT_EPOCH: AtomicU64 = AtomicU64::new(0);
+
+/// 获取当前账号纪元
+pub fn account_epoch() -> u64 {
+    ACCOUNT_EPOCH.load(Ordering::SeqCst)
+}
+
+/// 标记账号已变更（在修改 user_auth 之前调用）
+fn bump_account_epoch() {
+    ACCOUNT_EPOCH.fetch_add(1, Ordering::SeqCst);
+}
 
 /// 认证服务
 ///
@@ -125,9 +144,11 @@ impl AuthService {
         use crate::database::repositories::WorkspaceRepository;
 
         let workspace_service = WorkspaceService::new(WorkspaceRepository::new(self.pool.clone()));
-        let default_workspace = workspace_service.get_default_workspace(&auth_response.user_id);
+        let mut default_workspace_id = workspace_service.get_default_workspace(&auth_response.user_id)
+            .ok()
+            .map(|workspace| workspace.id);
 
-        if default_workspace.is_err() {
+        if default_workspace_id.is_none() {
             // 默认工作空间不存在，创建一个
             log::info!("[AuthService::login] 默认工作空间不存在，创建中: user_id={}", auth_response.user_id);
 
@@ -153,6 +174,7 @@ impl AuthService {
                     } else {
                         log::info!("[AuthService::login] 默认工作空间已设置为当前空间: workspace_id={}", workspace.id);
                     }
+                    default_workspace_id = Some(workspace.id);
                 }
                 Err(e) => {
                     log::warn!("[AuthService::login] 创建默认工作空间失败（非致命错误）: {}", e);
@@ -162,9 +184,44 @@ impl AuthService {
             log::debug!("[AuthService::login] 默认工作空间已存在: user_id={}", auth_response.user_id);
         }
 
+        // 未登录时创建的数据归入默认工作空间
+        if let Some(workspace_id) = default_workspace_id {
+            self.migrate_orphan_data_on_login(&workspace_id);
+        }
+
         Ok(auth_response)
     }
 
+    /// 将未登录时创建的数据（workspace_id 为 NULL）迁移到默认工作空间
+    ///
+    /// 没有孤立数据时不做任何修改；迁移失败不影响登录，用户可以稍后通过
+    /// `migrate_orphan_data` 命令手动迁移
+    fn migrate_orphan_data_on_login(&self, workspace_id: &str) {
+        use crate::database::repositories::WorkspaceRepository;
+
+        let repo = WorkspaceRepository::new(self.pool.clone());
+        let result = repo.find_orphan_data().and_then(|preview| {
+            if preview.is_empty() {
+                Ok(None)
+            } else {
+                repo.migrate_orphan_data_to_workspace(workspace_id).map(Some)
+            }
+        });
+
+        match result {
+            Ok(Some(migrated)) => log::info!(
+                "[AuthService::login] 已迁移孤立数据到默认工作空间: workspace_id={}, notes={}, folders={}, tags={}, snapshots={}",
+                workspace_id,
+                migrated.notes,
+                migrated.folders,
+                migrated.tags,
+                migrated.snapshots
+            ),
+            Ok(None) => log::debug!("[AuthService::login] 没有需要迁移的孤立数据"),
+            Err(e) => log::warn!("[AuthService::login] 迁移孤立数据失败（非致命错误）: {}", e),
+        }
+    }
+
     /// 用户注册
     pub async fn register(&self, mut req: RegisterRequest) -> Result<AuthResponse> {
         log::info!("[AuthService::register] 开始注册流程: email={}, server_url='{}'", req.email, req.server_url);
@@ -307,6 +364,8 @@ impl AuthService {
     ///
     /// 删除当前登录账号（is_current = 1）
     pub fn logout(&self) -> Result<()> {
+        bump_account_epoch();
+
         let conn = self.pool.get()
             .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
 
@@ -319,6 +378,182 @@ impl AuthService {
         Ok(())
     }
 
+    /// 用户登出（同时通知服务器吊销 token）
+    ///
+    /// 先调用服务器 `/auth/logout` 将当前 access token 加入黑名单并吊销 refresh token，再清除本地登录状态。
+    /// 服务器不可达或吊销失败时仍会清除本地状态，结果中记录吊销是否成功。
+    pub async fn logout_with_server(&self) -> Result<LogoutResult> {
+        let (server_revoked, server_error) = match self.revoke_server_token().await {
+            Ok(()) => (true, None),
+            Err(e) => {
+                log::warn!("[AuthService::logout_with_server] 服务器吊销 token 失败（继续本地登出）: {}", e);
+                (false, Some(e.to_string()))
+            }
+        };
+
+        self.logout()?;
+
+        Ok(LogoutResult { server_revoked, server_error })
+    }
+
+    /// 调用服务器 `/auth/logout` 将当前 access token 加入黑名单，并吊销当前设备的 refresh token
+    async fn revoke_server_token(&self) -> Result<()> {
+        let (server_url, token) = self.get_auth_info()?;
+        let refresh_token = self.get_refresh_token()?;
+        let url = format!("{}/auth/logout", server_url.trim_end_matches('/'));
+
+        log::info!("Revoking token at {}", url);
+
+        // 登出不应长时间阻塞，使用较短的超时
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .json(&serde_json::json!({ "refresh_token": refresh_token }))
+            .timeout(Duration::from_secs(5))
+            .send()
+            .await
+            .map_err(|e| {
+                log::error!("Failed to send logout request: {}", e);
+                AppError::NetworkError(format!("登出请求失败: {}", e))
+            })?;
+
+        let status = response.status();
+
+        if !status.is_success() {
+            let error_msg = response.text().await.unwrap_or_default();
+            log::error!("Server returned error {}: {}", status, error_msg);
+            return Err(AppError::NetworkError(format!("服务器错误 {}: {}", status, error_msg)));
+        }
+
+        log::info!("Token revoked on server");
+        Ok(())
+    }
+
+    /// 登出所有设备
+    ///
+    /// 调用服务器 `/auth/logout-all` 吊销该账号在所有设备上的 token。
+    /// `keep_current_device` 为 false 时当前设备的 token 同样失效，调用方需随后执行本地登出
+    pub async fn logout_all_devices(&self, keep_current_device: bool) -> Result<LogoutAllResult> {
+        #[derive(serde::Deserialize)]
+        struct ServerLogoutAllResponse {
+            revoked_refresh_tokens: u64,
+        }
+
+        let (server_url, token) = self.get_auth_info()?;
+        let refresh_token = self.get_refresh_token()?;
+        let url = format!("{}/auth/logout-all", server_url.trim_end_matches('/'));
+
+        log::info!("Revoking all sessions at {}: keep_current_device={}", url, keep_current_device);
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .json(&json!({
+                "keep_current_device": keep_current_device,
+                "refresh_token": refresh_token,
+            }))
+            .send()
+            .await
+            .map_err(|e| {
+                log::error!("Failed to send logout-all request: {}", e);
+                AppError::NetworkError(format!("登出所有设备请求失败: {}", e))
+            })?;
+
+        let status = response.status();
+
+        if !status.is_success() {
+            let error_msg = response.text().await.unwrap_or_default();
+            log::error!("Server returned error {}: {}", status, error_msg);
+            return Err(AppError::NetworkError(format!("服务器错误 {}: {}", status, error_msg)));
+        }
+
+        let body: ServerLogoutAllResponse = response.json().await
+            .map_err(|e| AppError::NetworkError(format!("解析登出所有设备响应失败: {}", e)))?;
+
+        log::info!("All sessions revoked: revoked_refresh_tokens={}", body.revoked_refresh_tokens);
+        Ok(LogoutAllResult {
+            revoked_refresh_tokens: body.revoked_refresh_tokens,
+            kept_current_device: keep_current_device,
+        })
+    }
+
+    /// 获取当前账号已登录的设备列表
+    pub async fn list_devices(&self) -> Result<Vec<Device>> {
+        let (server_url, token) = self.get_auth_info()?;
+        let url = format!("{}/devices", server_url.trim_end_matches('/'));
+
+        let response = self.client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await
+            .map_err(|e| {
+                log::error!("Failed to send list devices request: {}", e);
+                AppError::NetworkError(format!("获取设备列表请求失败: {}", e))
+            })?;
+
+        let status = response.status();
+
+        if !status.is_success() {
+            let error_msg = response.text().await.unwrap_or_default();
+            log::error!("Server returned error {}: {}", status, error_msg);
+            return Err(AppError::NetworkError(format!("服务器错误 {}: {}", status, error_msg)));
+        }
+
+        response.json().await
+            .map_err(|e| AppError::NetworkError(format!("解析设备列表失败: {}", e)))
+    }
+
+    /// 更新设备（重命名、标记受信任）
+    ///
+    /// `name` 为空字符串时清除自定义名称，恢复使用服务器自动生成的名称；
+    /// 参数为 None 的字段保持不变
+    pub async fn update_device(&self, device_id: &str, name: Option<String>, trusted: Option<bool>) -> Result<Device> {
+        let (server_url, token) = self.get_auth_info()?;
+        let url = format!("{}/devices/{}", server_url.trim_end_matches('/'), device_id);
+
+        log::info!("Updating device at {}: name={:?}, trusted={:?}", url, name, trusted);
+
+        let response = self.client
+            .patch(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .json(&json!({
+                "name": name,
+                "trusted": trusted,
+            }))
+            .send()
+            .await
+            .map_err(|e| {
+                log::error!("Failed to send update device request: {}", e);
+                AppError::NetworkError(format!("更新设备请求失败: {}", e))
+            })?;
+
+        let status = response.status();
+
+        if !status.is_success() {
+            let error_msg = response.text().await.unwrap_or_default();
+            log::error!("Server returned error {}: {}", status, error_msg);
+            return Err(AppError::NetworkError(format!("服务器错误 {}: {}", status, error_msg)));
+        }
+
+        response.json().await
+            .map_err(|e| AppError::NetworkError(format!("解析设备信息失败: {}", e)))
+    }
+
+    /// 获取当前用户的 refresh token
+    fn get_refresh_token(&self) -> Result<String> {
+        let conn = self.pool.get()
+            .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
+
+        let (encrypted_token, device_id): (String, String) = conn.query_row(
+            "SELECT refresh_token_encrypted, device_id FROM user_auth WHERE is_current = 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).map_err(|_| AppError::NotAuthenticated("用户未登录".to_string()))?;
+
+        CryptoService::decrypt_token(&encrypted_token, &device_id)
+    }
+
     /// 获取当前用户的访问 token
     fn get_access_token(&self) -> Result<String> {
         let conn = self.pool.get()
@@ -332,11 +567,8 @@ impl AuthService {
             Ok((row.get(0)?, row.get(1)?))
         }).map_err(|_| AppError::NotAuthenticated("用户未登录".to_string()))?;
 
-        // 使用 device_id 生成加密密钥
-        let key = CryptoService::derive_key_from_device_id(&device_id);
-
-        // 解密 token
-        let token = CryptoService::decrypt_token(&encrypted_token, &key)?;
+        // 使用 device_id 解密 token
+        let token = CryptoService::decrypt_token(&encrypted_token, &device_id)?;
         Ok(token)
     }
 
@@ -467,6 +699,8 @@ impl AuthService {
 
     /// 切换到指定账号
     pub fn switch_account(&self, user_id: &str) -> Result<()> {
+        bump_account_epoch();
+
         let conn = self.pool.get()
             .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
 
@@ -552,8 +786,7 @@ impl AuthService {
             ).map_err(|_| AppError::NotAuthenticated("No user logged in".to_string()))?;
 
         // 2. 使用 device_id 解密 refresh_token
-        let key = CryptoService::derive_key_from_device_id(&device_id);
-        let refresh_token = CryptoService::decrypt_token(&encrypted_refresh_token, &key)?;
+        let refresh_token = CryptoService::decrypt_token(&encrypted_refresh_token, &device_id)?;
 
         // 3. 向服务器发起刷新请求
         let url = format!("{}/auth/refresh", server_url.trim_end_matches('/'));
@@ -613,8 +846,8 @@ impl AuthService {
         let expires_at = now + 7 * 24 * 3600;
 
         // 6. 更新数据库（加密存储新的 token）
-        let encrypted_access = CryptoService::encrypt_token(new_access_token, &key)?;
-        let encrypted_refresh = CryptoService::encrypt_token(new_refresh_token, &key)?;
+        let encrypted_access = CryptoService::encrypt_token(new_access_token, &device_id)?;
+        let encrypted_refresh = CryptoService::encrypt_token(new_refresh_token, &device_id)?;
 
         conn.execute(
             "UPDATE user_auth
@@ -661,8 +894,7 @@ impl AuthService {
         }).map_err(|_| AppError::NotAuthenticated("用户未登录".to_string()))?;
 
         // 使用 device_id 解密 token
-        let key = CryptoService::derive_key_from_device_id(&device_id);
-        let token = CryptoService::decrypt_token(&encrypted_token, &key)?;
+        let token = CryptoService::decrypt_token(&encrypted_token, &device_id)?;
 
         Ok((server_url, token))
     }
@@ -683,12 +915,80 @@ impl AuthService {
         }).map_err(|_| AppError::NotAuthenticated("用户未登录".to_string()))?;
 
         // 使用 device_id 解密 token
-        let key = CryptoService::derive_key_from_device_id(&device_id);
-        let token = CryptoService::decrypt_token(&encrypted_token, &key)?;
+        let token = CryptoService::decrypt_token(&encrypted_token, &device_id)?;
 
         Ok((server_url, token, expires_at))
     }
 
+    /// 将旧方案加密的 token 升级到当前加密方案
+    ///
+    /// 遍历 user_auth 中所有账号，access/refresh token 有任一不是
+    /// `KeyScheme::current()` 时解密后重新加密并原地更新，返回升级的账号数量。
+    /// 无法解密的记录会被跳过（保留原值，等待重新登录覆盖）
+    pub fn rotate_token_encryption(&self) -> Result<usize> {
+        let conn = self.pool.get()
+            .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
+
+        let rows: Vec<(String, String, String, Option<String>)> = {
+            let mut stmt = conn.prepare(
+                "SELECT user_id, device_id, access_token_encrypted, refresh_token_encrypted FROM user_auth"
+            ).map_err(|e| AppError::DatabaseError(format!("查询认证信息失败: {}", e)))?;
+
+            let rows = stmt.query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            }).map_err(|e| AppError::DatabaseError(format!("查询认证信息失败: {}", e)))?;
+
+            rows.collect::<std::result::Result<Vec<_>, _>>()
+                .map_err(|e| AppError::DatabaseError(format!("读取认证信息失败: {}", e)))?
+        };
+
+        let current = KeyScheme::current();
+        let is_current = |encrypted: &str| {
+            matches!(KeyScheme::of(encrypted), Ok((scheme, _)) if scheme == current)
+        };
+
+        let mut rotated = 0;
+        for (user_id, device_id, access, refresh) in rows {
+            if is_current(&access) && refresh.as_deref().is_none_or(is_current) {
+                continue;
+            }
+
+            let reencrypt = |encrypted: &str| -> Result<String> {
+                let token = CryptoService::decrypt_token(encrypted, &device_id)?;
+                CryptoService::encrypt_token(&token, &device_id)
+            };
+
+            let upgraded = reencrypt(&access).and_then(|access| {
+                let refresh = refresh.as_deref().map(reencrypt).transpose()?;
+                Ok((access, refresh))
+            });
+
+            let (new_access, new_refresh) = match upgraded {
+                Ok(tokens) => tokens,
+                Err(e) => {
+                    log::warn!("[AuthService::rotate_token_encryption] 跳过无法解密的账号: user_id={}, error={}", user_id, e);
+                    continue;
+                }
+            };
+
+            conn.execute(
+                "UPDATE user_auth
+                 SET access_token_encrypted = ?1,
+                     refresh_token_encrypted = ?2
+                 WHERE user_id = ?3",
+                rusqlite::params![new_access, new_refresh, user_id],
+            ).map_err(|e| AppError::DatabaseError(format!("更新认证信息失败: {}", e)))?;
+
+            rotated += 1;
+        }
+
+        if rotated > 0 {
+            log::info!("[AuthService::rotate_token_encryption] 已升级 {} 个账号的 token 加密方案", rotated);
+        }
+
+        Ok(rotated)
+    }
+
     // ===== 私有方法 =====
 
     /// 保存用户认证信息（加密，支持多账号）
@@ -706,9 +1006,8 @@ impl AuthService {
 
         // 使用 device_id 加密 token
         log::debug!("[AuthService::save_user_auth] 开始加密 token");
-        let key = CryptoService::derive_key_from_device_id(device_id);
-        let encrypted_access = CryptoService::encrypt_token(access_token, &key)?;
-        let encrypted_refresh = CryptoService::encrypt_token(refresh_token, &key)?;
+        let encrypted_access = CryptoService::encrypt_token(access_token, device_id)?;
+        let encrypted_refresh = CryptoService::encrypt_token(refresh_token, device_id)?;
         log::debug!("[AuthService::save_user_auth] token 加密完成");
 
         let now = chrono::Utc::now().timestamp();
@@ -788,6 +1087,125 @@ impl AuthService {
         }
     }
 
+    /// 校验本地账号与服务器账号是否一致
+    ///
+    /// 调用服务器 `/auth/me`，比较服务器根据 token 识别出的用户与本地 `user_auth` 记录。
+    /// 服务器迁移后本地 token 可能指向一个不再识别该用户的服务器，此时返回错误提示重新登录。
+    pub async fn validate_session(&self) -> Result<User> {
+        let user = self.get_current_user()?;
+        let access_token = self.get_access_token()?;
+
+        let url = format!("{}/auth/me", user.server_url.trim_end_matches('/'));
+
+        log::info!("Validating session at {}", url);
+
+        let response = self.client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", access_token))
+            .send()
+            .await
+            .map_err(|e| {
+                log::error!("Failed to send validate session request: {}", e);
+                AppError::NetworkError(format!("校验会话请求失败: {}", e))
+            })?;
+
+        let status = response.status();
+
+        if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::NOT_FOUND {
+            log::warn!("Server does not recognize current session: status={}", status);
+            return Err(AppError::NotAuthenticated("服务器无法识别当前账号，请重新登录".to_string()));
+        }
+
+        let response_json: serde_json::Value = response.json().await.map_err(|e| {
+            log::error!("Failed to parse response: {}", e);
+            AppError::NetworkError(format!("响应无效: {}", e))
+        })?;
+
+        if !status.is_success() {
+            let error_msg = response_json["error"]
+                .as_str()
+                .unwrap_or("未知错误");
+            log::error!("Server returned error {}: {}", status, error_msg);
+            return Err(AppError::NetworkError(error_msg.to_string()));
+        }
+
+        let server_user_id = response_json["user_id"].as_str().unwrap_or_default();
+        let server_email = response_json["email"].as_str().unwrap_or_default();
+
+        if server_user_id != user.id || server_email != user.email {
+            log::warn!(
+                "Session account mismatch: local=({}, {}), server=({}, {})",
+                user.id, user.email, server_user_id, server_email
+            );
+            return Err(AppError::AuthenticationError(format!(
+                "本地账号 {} 与服务器账号不一致，请重新登录",
+                user.email
+            )));
+        }
+
+        log::info!("Session validated: user_id={}", user.id);
+        Ok(user)
+    }
+
+    /// 修改密码（需要验证原密码）
+    ///
+    /// 调用服务器 `/auth/change-password`；`logout_other_devices` 为 true 时
+    /// 其他设备的登录状态同时失效，当前设备保持登录
+    pub async fn change_password(
+        &self,
+        old_password: String,
+        new_password: String,
+        logout_other_devices: bool,
+    ) -> Result<ChangePasswordResult> {
+        #[derive(serde::Deserialize)]
+        struct ServerChangePasswordResponse {
+            revoked_refresh_tokens: u64,
+        }
+
+        let (server_url, token) = self.get_auth_info()?;
+        let refresh_token = self.get_refresh_token()?;
+        let url = format!("{}/auth/change-password", server_url.trim_end_matches('/'));
+
+        log::info!("Changing password at {}: logout_other_devices={}", url, logout_other_devices);
+
+        let response = self.client
+            .post(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .json(&json!({
+                "old_password": old_password,
+                "new_password": new_password,
+                "logout_other_devices": logout_other_devices,
+                "refresh_token": refresh_token,
+            }))
+            .send()
+            .await
+            .map_err(|e| {
+                log::error!("Failed to send change password request: {}", e);
+                AppError::NetworkError(format!("修改密码请求失败: {}", e))
+            })?;
+
+        let status = response.status();
+
+        if !status.is_success() {
+            let error_msg = response.text().await.unwrap_or_default();
+            log::error!("Server returned error {}: {}", status, error_msg);
+            return Err(match status.as_u16() {
+                401 => AppError::AuthenticationError(error_msg),
+                400 => AppError::InvalidInput(error_msg),
+                _ => AppError::NetworkError(format!("服务器错误 {}: {}", status, error_msg)),
+            });
+        }
+
+        let body: ServerChangePasswordResponse = response.json().await
+            .map_err(|e| AppError::NetworkError(format!("解析修改密码响应失败: {}", e)))?;
+
+        log::info!("Password changed: revoked_refresh_tokens={}", body.revoked_refresh_tokens);
+        Ok(ChangePasswordResult {
+            revoked_refresh_tokens: body.revoked_refresh_tokens,
+            logged_out_other_devices: logout_other_devices,
+        })
+    }
+
     /// 删除账号（需要密码验证）
     pub async fn delete_account(&self, password: String) -> Result<()> {
         // 获取当前用户信息
@@ -830,3 +1248,233 @@ impl AuthService {
         Ok(())
     }
 }
+
+/// 登出结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogoutResult {
+    pub server_revoked: bool,              // 服务器是否已吊销 token
+    pub server_error: Option<String>,      // 吊销失败的原因（本地状态已清除）
+}
+
+/// 登出所有设备结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogoutAllResult {
+    pub revoked_refresh_tokens: u64,       // 服务器清除的 refresh token 数量
+    pub kept_current_device: bool,         // 当前设备是否仍保持登录
+}
+
+/// 修改密码结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangePasswordResult {
+    pub revoked_refresh_tokens: u64,       // 服务器清除的其他设备 refresh token 数量
+    pub logged_out_other_devices: bool,    // 是否已登出其他设备
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::mock_http;
+
+    fn memory_service() -> AuthService {
+        let pool = crate::database::memory_pool();
+        AuthService::new(pool)
+    }
+
+    /// 保存当前登录账号（token 使用当前加密方案）
+    fn login_as(service: &AuthService, server_url: &str) {
+        let device_id = "device-1";
+        let access = CryptoService::encrypt_token("access-token", device_id).unwrap();
+        let refresh = CryptoService::encrypt_token("refresh-token", device_id).unwrap();
+        service.pool.get().unwrap().execute(
+            "INSERT INTO user_auth (user_id, server_url, email, access_token_encrypted, refresh_token_encrypted,
+                                    token_expires_at, device_id, is_current, created_at, updated_at)
+             VALUES ('user-1', ?1, 'a@b.c', ?2, ?3, 0, ?4, 1, 0, 0)",
+            rusqlite::params![server_url, access, refresh, device_id],
+        ).unwrap();
+    }
+
+    /// 模拟设备接口：PATCH 保存自定义名称，GET 返回展示名称
+    fn mock_device_server() -> String {
+        let mut custom_name: Option<String> = None;
+        mock_http::serve(move |request| {
+            if request.method == "PATCH" {
+                let req: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+                if let Some(name) = req["name"].as_str() {
+                    custom_name = Some(name.to_string()).filter(|n| !n.is_empty());
+                }
+            }
+            let device = json!({
+                "id": "device-1",
+                "device_name": custom_name.clone().unwrap_or_else(|| "Linux 电脑".to_string()),
+                "custom_name": custom_name,
+                "device_type": "desktop",
+                "trusted": false,
+                "last_seen_at": 0,
+                "created_at": 0,
+            });
+            let body = if request.method == "PATCH" { device.to_string() } else { json!([device]).to_string() };
+            (200, body)
+        })
+    }
+
+    /// 模拟登录接口：任何请求都返回 user-1 的登录结果
+    fn mock_login_server() -> String {
+        mock_http::serve(|_| {
+            let body = json!({
+                "token": "access-token",
+                "refresh_token": "refresh-token",
+                "user_id": "user-1",
+                "email": "a@b.c",
+                "device_id": "device-1",
+            }).to_string();
+            (200, body)
+        })
+    }
+
+    fn login(service: &AuthService, server_url: &str) {
+        let req = LoginRequest {
+            email: "a@b.c".to_string(),
+            password: "password".to_string(),
+            server_url: server_url.to_string(),
+            device_id: Some("device-1".to_string()),
+        };
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        runtime.block_on(service.login(req)).unwrap();
+    }
+
+    /// 各表中 workspace_id 为 NULL 的行数
+    fn orphan_counts(service: &AuthService) -> Vec<i64> {
+        let conn = service.pool.get().unwrap();
+        ["notes", "folders", "tags", "note_tags", "note_snapshots"].iter()
+            .map(|table| conn.query_row(&format!("SELECT COUNT(*) FROM {} WHERE workspace_id IS NULL", table), [], |row| row.get(0)).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_login_migrates_orphan_data_to_default_workspace() {
+        let service = memory_service();
+        service.pool.get().unwrap().execute_batch(
+            "INSERT INTO folders (id, name, created_at, updated_at) VALUES ('f-1', '离线文件夹', 0, 0);
+             INSERT INTO tags (id, name, created_at, updated_at) VALUES ('t-1', '离线', 0, 0);
+             INSERT INTO notes (id, title, content, folder_id, created_at, updated_at) VALUES
+                 ('n-1', '离线笔记', 'a', 'f-1', 0, 0),
+                 ('n-2', '离线笔记 2', 'b', NULL, 0, 0);
+             INSERT INTO note_tags (note_id, tag_id, created_at) VALUES ('n-1', 't-1', 0);
+             INSERT INTO note_snapshots (id, note_id, title, content, created_at) VALUES ('s-1', 'n-1', '离线笔记', 'a', 0);",
+        ).unwrap();
+
+        login(&service, &mock_login_server());
+
+        assert_eq!(orphan_counts(&service), [0, 0, 0, 0, 0]);
+
+        let conn = service.pool.get().unwrap();
+        let default_workspace_id: String = conn.query_row(
+            "SELECT id FROM workspaces WHERE user_id = 'user-1' AND is_default = 1",
+            [],
+            |row| row.get(0),
+        ).unwrap();
+        let migrated: i64 = conn.query_row(
+            "SELECT (SELECT COUNT(*) FROM notes WHERE workspace_id = ?1)
+                  + (SELECT COUNT(*) FROM folders WHERE workspace_id = ?1)
+                  + (SELECT COUNT(*) FROM tags WHERE workspace_id = ?1)",
+            rusqlite::params![default_workspace_id],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(migrated, 4);
+    }
+
+    #[test]
+    fn test_login_without_orphan_data_keeps_existing_data() {
+        let service = memory_service();
+        service.pool.get().unwrap().execute_batch(
+            "INSERT INTO workspaces (id, user_id, name, is_default, is_current, created_at, updated_at)
+             VALUES ('ws-default', 'user-1', '我的空间', 1, 1, 0, 0), ('ws-other', 'user-2', '其他账号', 1, 0, 0, 0);
+             INSERT INTO notes (id, title, content, workspace_id, created_at, updated_at)
+             VALUES ('n-other', '其他账号的笔记', 'a', 'ws-other', 0, 0);",
+        ).unwrap();
+
+        login(&service, &mock_login_server());
+
+        let workspace_id: String = service.pool.get().unwrap().query_row(
+            "SELECT workspace_id FROM notes WHERE id = 'n-other'",
+            [],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(workspace_id, "ws-other");
+        assert_eq!(orphan_counts(&service), [0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_renamed_device_is_listed_with_custom_name() {
+        let service = memory_service();
+        login_as(&service, &mock_device_server());
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        let device_name = |service: &AuthService| runtime.block_on(service.list_devices()).unwrap()[0].device_name.clone();
+        assert_eq!(device_name(&service), "Linux 电脑");
+
+        let renamed = runtime.block_on(service.update_device("device-1", Some("工作电脑".to_string()), None)).unwrap();
+        assert_eq!(renamed.custom_name.as_deref(), Some("工作电脑"));
+        assert_eq!(device_name(&service), "工作电脑");
+
+        // 清除自定义名称后恢复自动生成的名称
+        runtime.block_on(service.update_device("device-1", Some(String::new()), None)).unwrap();
+        assert_eq!(device_name(&service), "Linux 电脑");
+    }
+
+    #[test]
+    fn test_rotate_token_encryption_upgrades_legacy_row() {
+        let service = memory_service();
+        let device_id = "device-1";
+        {
+            let access = CryptoService::encrypt_token_with(KeyScheme::V1, "access-token", device_id).unwrap();
+            let refresh = CryptoService::encrypt_token_with(KeyScheme::V1, "refresh-token", device_id).unwrap();
+            let conn = service.pool.get().unwrap();
+            conn.execute(
+                "INSERT INTO user_auth (user_id, server_url, email, access_token_encrypted, refresh_token_encrypted,
+                                        token_expires_at, device_id, is_current, created_at, updated_at)
+                 VALUES ('user-1', 'http://localhost', 'a@b.c', ?1, ?2, 0, ?3, 1, 0, 0)",
+                rusqlite::params![access, refresh, device_id],
+            ).unwrap();
+        }
+
+        assert_eq!(service.rotate_token_encryption().unwrap(), 1);
+
+        let (access, refresh): (String, String) = service.pool.get().unwrap().query_row(
+            "SELECT access_token_encrypted, refresh_token_encrypted FROM user_auth WHERE user_id = 'user-1'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).unwrap();
+        assert_eq!(KeyScheme::of(&access).unwrap().0, KeyScheme::current());
+        assert_eq!(KeyScheme::of(&refresh).unwrap().0, KeyScheme::current());
+        assert_eq!(service.get_access_token().unwrap(), "access-token");
+        assert_eq!(service.get_refresh_token().unwrap(), "refresh-token");
+
+        // 已是当前方案的记录不会重复升级
+        assert_eq!(service.rotate_token_encryption().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_logout_with_server_revokes_refresh_token() {
+        let requests = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        let server_url = mock_http::serve(move |request| {
+            recorded.lock().unwrap().push(request.clone());
+            (200, String::new())
+        });
+        let service = memory_service();
+        login_as(&service, &server_url);
+
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        let result = runtime.block_on(service.logout_with_server()).unwrap();
+
+        assert!(result.server_revoked);
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests[0].path, "/auth/logout");
+        let body: serde_json::Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(body["refresh_token"], "refresh-token");
+        assert!(!service.is_authenticated().unwrap());
+    }
+}
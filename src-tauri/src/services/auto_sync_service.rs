@@ -1,18 +1,29 @@
-use crate::services::{SyncService, AppSettingsService};
+use crate::services::{SyncService, SingleSyncService, AppSettingsService, AuthService};
+use crate::services::power_state::{self, PowerState};
+use crate::models::AppSettings;
 use crate::models::error::Result;
+use serde::Serialize;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tokio::time::{interval, Duration};
 
+/// 连续失败退避后的最大同步间隔（秒）
+///
+/// 设置的同步间隔本身超过该值时以设置为准
+const MAX_BACKOFF_INTERVAL_SECS: i64 = 60 * 60;
+
 /// 自动同步服务
 ///
-/// 提供定时自动同步功能，可配置同步间隔
+/// 提供定时自动同步功能，可配置同步间隔；连续失败时按指数退避延长间隔，成功后恢复
 #[derive(Clone)]
 pub struct AutoSyncService {
     sync_service: SyncService,
+    single_sync_service: SingleSyncService,
     app_settings_service: AppSettingsService,
+    auth_service: AuthService,
     is_running: Arc<Mutex<bool>>,
     manual_sync_in_progress: Arc<Mutex<bool>>,
+    backoff: Arc<Mutex<SyncBackoff>>,
     handle: Arc<Mutex<Option<tokio::task::JoinHandle<()>>>>,
 }
 
@@ -20,13 +31,18 @@ impl AutoSyncService {
     /// 创建新的 AutoSyncService 实例
     pub fn new(
         sync_service: SyncService,
+        single_sync_service: SingleSyncService,
         app_settings_service: AppSettingsService,
+        auth_service: AuthService,
     ) -> Self {
         Self {
             sync_service,
+            single_sync_service,
             app_settings_service,
+            auth_service,
             is_running: Arc::new(Mutex::new(false)),
             manual_sync_in_progress: Arc::new(Mutex::new(false)),
+            backoff: Arc::new(Mutex::new(SyncBackoff::default())),
             handle: Arc::new(Mutex::new(None)),
         }
     }
@@ -44,9 +60,12 @@ impl AutoSyncService {
         drop(is_running);
 
         let sync_service = self.sync_service.clone();
+        let single_sync_service = self.single_sync_service.clone();
         let app_settings_service = self.app_settings_service.clone();
+        let auth_service = self.auth_service.clone();
         let is_running = self.is_running.clone();
         let manual_sync_in_progress = self.manual_sync_in_progress.clone();
+        let backoff = self.backoff.clone();
 
         let task = tokio::spawn(async move {
             log::info!("[AutoSyncService] 后台同步任务已启动");
@@ -89,8 +108,21 @@ impl AutoSyncService {
                     continue;
                 }
 
-                // 检查是否到同步时间
-                let sync_interval_seconds = settings.sync_interval_minutes as i64 * 60;
+                // 重试失败的单项同步（有到期条目时才检测电源状态）
+                match single_sync_service.has_due_retries() {
+                    Ok(true) => {
+                        if power_pause_reason(&settings, &power_state::detect()).is_none() {
+                            let max_retries = settings.max_sync_retries.max(1) as u32;
+                            if let Err(e) = single_sync_service.retry_due(max_retries).await {
+                                log::warn!("[AutoSyncService] 重试失败的单项同步出错: {}", e);
+                            }
+                        }
+                    }
+                    Ok(false) => {}
+                    Err(e) => log::warn!("[AutoSyncService] 读取重试队列失败: {}", e),
+                }
+
+                // 检查是否到同步时间（连续失败时间隔按指数退避，从上次尝试算起）
                 let now = chrono::Utc::now().timestamp();
                 let last_sync_at = match sync_service.get_last_sync_at() {
                     Ok(Some(t)) => t,
@@ -101,32 +133,30 @@ impl AutoSyncService {
                     }
                 };
 
-                if last_sync_at + sync_interval_seconds > now {
-                    log::debug!(
-                        "[AutoSyncService] 未到同步时间（上次同步: {}, 间隔: {}秒, 当前: {}）",
-                        last_sync_at,
-                        sync_interval_seconds,
-                        now
-                    );
-                    continue;
-                }
-
-                // 执行自动同步
-                log::info!("[AutoSyncService] 开始执行自动同步");
-                match sync_service.full_sync().await {
-                    Ok(report) => {
-                        log::info!(
-                            "[AutoSyncService] 自动同步成功: pushed_notes={}, pulled_notes={}, pulled_tags={}, conflicts={}",
-                            report.pushed_notes,
-                            report.pulled_notes,
-                            report.pulled_tags,
-                            report.conflict_count
+                {
+                    let backoff = backoff.lock().await;
+                    if !backoff.is_due(settings.sync_interval_minutes, last_sync_at, now) {
+                        log::debug!(
+                            "[AutoSyncService] 未到同步时间（上次同步: {}, 上次尝试: {:?}, 间隔: {}秒, 连续失败: {}, 当前: {}）",
+                            last_sync_at,
+                            backoff.last_attempt_at,
+                            backoff.interval_secs(settings.sync_interval_minutes),
+                            backoff.consecutive_failures,
+                            now
                         );
+                        continue;
                     }
-                    Err(e) => {
-                        log::error!("[AutoSyncService] 自动同步失败: {}", e);
-                    }
                 }
+
+                // 低电量 / 按流量计费网络时推迟本次自动同步（手动同步不受影响）
+                if let Some(reason) = power_pause_reason(&settings, &power_state::detect()) {
+                    log::info!("[AutoSyncService] 推迟自动同步: {}", reason);
+                    continue;
+                }
+
+                // 执行自动同步（token 即将过期时先刷新）
+                let success = run_auto_sync(&sync_service, &auth_service, &settings).await;
+                backoff.lock().await.record(success, now);
             }
 
             log::info!("[AutoSyncService] 后台同步任务已退出");
@@ -178,4 +208,312 @@ impl AutoSyncService {
     pub async fn is_manual_syncing(&self) -> bool {
         *self.manual_sync_in_progress.lock().await
     }
+
+    /// 获取自动同步连续失败次数
+    pub async fn consecutive_failures(&self) -> u32 {
+        self.backoff.lock().await.consecutive_failures
+    }
+
+    /// 获取当前生效的自动同步间隔（秒，含连续失败退避）
+    pub async fn effective_interval_secs(&self) -> Result<i64> {
+        let settings = self.app_settings_service.get_settings()?;
+        Ok(self.backoff.lock().await.interval_secs(settings.sync_interval_minutes))
+    }
+
+    /// 获取电源 / 网络状态及自动同步是否会被推迟
+    ///
+    /// 平台无法提供电池或按流量计费信息时对应字段为空，且不会推迟同步
+    pub fn get_power_sync_state(&self) -> Result<PowerSyncState> {
+        let settings = self.app_settings_service.get_settings()?;
+        let power = power_state::detect();
+        let pause_reason = power_pause_reason(&settings, &power);
+
+        Ok(PowerSyncState {
+            sync_allowed: pause_reason.is_none(),
+            pause_reason,
+            power,
+        })
+    }
+}
+
+/// 电源感知的自动同步状态
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PowerSyncState {
+    pub sync_allowed: bool,  // 自动同步当前是否允许执行
+    pub pause_reason: Option<String>,  // 推迟原因（允许时为空）
+    #[serde(flatten)]
+    pub power: PowerState,  // 电池与网络状态
+}
+
+/// 连续失败后的同步间隔（秒）
+///
+/// 每失败一次间隔翻倍，最多退避到 `MAX_BACKOFF_INTERVAL_SECS`（设置间隔更长时以设置为准）
+fn backoff_interval_secs(interval_minutes: i32, consecutive_failures: u32) -> i64 {
+    let base = i64::from(interval_minutes.max(1)) * 60;
+    let backoff = base.saturating_mul(1i64 << consecutive_failures.min(16));
+    backoff.min(MAX_BACKOFF_INTERVAL_SECS.max(base))
+}
+
+/// 自动同步的退避状态
+///
+/// 失败的同步不会更新上次同步时间，因此退避间隔从上次尝试的时间算起
+#[derive(Debug, Default)]
+struct SyncBackoff {
+    consecutive_failures: u32,
+    last_attempt_at: Option<i64>,
+}
+
+impl SyncBackoff {
+    /// 当前生效的同步间隔（秒）
+    fn interval_secs(&self, interval_minutes: i32) -> i64 {
+        backoff_interval_secs(interval_minutes, self.consecutive_failures)
+    }
+
+    /// 距上次同步或上次尝试（取较晚者）是否已超过同步间隔
+    fn is_due(&self, interval_minutes: i32, last_sync_at: i64, now: i64) -> bool {
+        let since = last_sync_at.max(self.last_attempt_at.unwrap_or(0));
+        since + self.interval_secs(interval_minutes) <= now
+    }
+
+    /// 记录一次自动同步结果：失败时累加连续失败次数，成功时清零
+    fn record(&mut self, success: bool, attempted_at: i64) {
+        self.last_attempt_at = Some(attempted_at);
+        if success {
+            if self.consecutive_failures > 0 {
+                log::info!("[AutoSyncService] 自动同步恢复，重置退避（此前连续失败 {} 次）", self.consecutive_failures);
+            }
+            self.consecutive_failures = 0;
+        } else {
+            self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+            log::warn!("[AutoSyncService] 自动同步连续失败 {} 次，延长同步间隔", self.consecutive_failures);
+        }
+    }
+}
+
+/// 执行一次自动同步，返回是否成功
+///
+/// access token 即将过期时先主动刷新，刷新失败则跳过本次同步，避免必然失败的同步请求
+async fn run_auto_sync(sync_service: &SyncService, auth_service: &AuthService, settings: &AppSettings) -> bool {
+    if let Err(e) = refresh_token_if_expiring(auth_service, settings.token_refresh_window_minutes).await {
+        log::warn!("[AutoSyncService] token 即将过期且刷新失败，跳过本次自动同步: {}", e);
+        return false;
+    }
+
+    log::info!("[AutoSyncService] 开始执行自动同步");
+    match sync_service.full_sync().await {
+        Ok(report) => {
+            log::info!(
+                "[AutoSyncService] 自动同步成功: pushed_notes={}, pulled_notes={}, pulled_tags={}, conflicts={}",
+                report.pushed_notes,
+                report.pulled_notes,
+                report.pulled_tags,
+                report.conflict_count
+            );
+
+            // 顺便刷新服务器限制（缓存未过期时不会发请求）
+            if let Err(e) = sync_service.fetch_server_limits(false).await {
+                log::warn!("[AutoSyncService] 刷新服务器限制失败: {}", e);
+            }
+            true
+        }
+        Err(e) => {
+            log::error!("[AutoSyncService] 自动同步失败: {}", e);
+            false
+        }
+    }
+}
+
+/// access token 剩余有效期不超过刷新窗口时主动刷新
+///
+/// 未登录或过期时间未知时不做处理，由同步请求自身报告错误
+async fn refresh_token_if_expiring(auth_service: &AuthService, window_minutes: i32) -> Result<()> {
+    if window_minutes <= 0 {
+        return Ok(());
+    }
+
+    let expires_at = match auth_service.get_auth_info_with_expires() {
+        Ok((_, _, expires_at)) => expires_at,
+        Err(_) => return Ok(()),
+    };
+
+    let now = chrono::Utc::now().timestamp();
+    if !token_expiring(expires_at, now, window_minutes) {
+        return Ok(());
+    }
+
+    log::info!("[AutoSyncService] access token 将于 {} 秒内过期，主动刷新", expires_at - now);
+    auth_service.refresh_access_token().await?;
+    Ok(())
+}
+
+/// token 是否已过期或将在窗口内过期
+fn token_expiring(expires_at: i64, now: i64, window_minutes: i32) -> bool {
+    expires_at - now <= i64::from(window_minutes) * 60
+}
+
+/// 根据设置和设备状态判断是否应推迟自动同步，返回推迟原因
+///
+/// 状态未知（None）时一律视为允许
+fn power_pause_reason(settings: &AppSettings, power: &PowerState) -> Option<String> {
+    if settings.pause_sync_on_low_battery && power.on_battery == Some(true) {
+        if let Some(percent) = power.battery_percent {
+            if i32::from(percent) <= settings.low_battery_threshold {
+                return Some(format!("电池电量低（{}%）", percent));
+            }
+        }
+    }
+
+    if settings.pause_sync_on_metered && power.metered == Some(true) {
+        return Some("当前网络按流量计费".to_string());
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::CryptoService;
+    use crate::services::mock_http;
+    use std::sync::Mutex as StdMutex;
+
+    fn settings(low_battery: bool, metered: bool) -> AppSettings {
+        AppSettings {
+            pause_sync_on_low_battery: low_battery,
+            pause_sync_on_metered: metered,
+            ..AppSettings::default()
+        }
+    }
+
+    #[test]
+    fn pauses_only_when_discharging_below_threshold() {
+        let low = PowerState { battery_percent: Some(15), on_battery: Some(true), metered: None };
+        let charging = PowerState { on_battery: Some(false), ..low.clone() };
+        let full = PowerState { battery_percent: Some(80), ..low.clone() };
+
+        assert!(power_pause_reason(&settings(true, false), &low).is_some());
+        assert!(power_pause_reason(&settings(true, false), &charging).is_none());
+        assert!(power_pause_reason(&settings(true, false), &full).is_none());
+        assert!(power_pause_reason(&settings(false, false), &low).is_none());
+    }
+
+    #[test]
+    fn unknown_state_never_pauses() {
+        let unknown = PowerState::default();
+        assert!(power_pause_reason(&settings(true, true), &unknown).is_none());
+
+        let metered = PowerState { metered: Some(true), ..PowerState::default() };
+        assert!(power_pause_reason(&settings(false, true), &metered).is_some());
+    }
+
+    #[test]
+    fn token_expiring_within_window() {
+        assert!(token_expiring(1_000 + 5 * 60, 1_000, 10));
+        assert!(token_expiring(900, 1_000, 10));
+        assert!(!token_expiring(1_000 + 30 * 60, 1_000, 10));
+    }
+
+    /// 按每分钟一次的检查模拟后台循环，返回发起同步的时间点
+    ///
+    /// 失败的同步不更新上次同步时间，与实际同步失败时一致
+    fn simulate_attempts(backoff: &mut SyncBackoff, last_sync_at: i64, minutes: i64, succeed: bool) -> Vec<i64> {
+        let mut attempts = Vec::new();
+        for minute in 1..=minutes {
+            let now = last_sync_at + minute * 60;
+            if backoff.is_due(5, last_sync_at, now) {
+                attempts.push(now);
+                backoff.record(succeed, now);
+            }
+        }
+        attempts
+    }
+
+    #[test]
+    fn failures_back_off_until_success() {
+        let mut backoff = SyncBackoff::default();
+        let start = 1_700_000_000;
+
+        // 每次失败后下一次尝试的间隔翻倍：5、10、20、40 分钟
+        let attempts = simulate_attempts(&mut backoff, start, 80, false);
+        let gaps: Vec<i64> = attempts.windows(2).map(|w| (w[1] - w[0]) / 60).collect();
+        assert_eq!(attempts.first(), Some(&(start + 5 * 60)));
+        assert_eq!(gaps, vec![10, 20, 40]);
+        assert_eq!(backoff.consecutive_failures, 4);
+
+        backoff.record(true, start + 80 * 60);
+        assert_eq!(backoff.consecutive_failures, 0);
+        assert_eq!(backoff.interval_secs(5), 5 * 60);
+    }
+
+    #[test]
+    fn stale_last_sync_does_not_bypass_backoff() {
+        let mut backoff = SyncBackoff::default();
+        let now = 1_700_000_000;
+        // 上次成功同步在很久以前，连续失败后仍需按退避间隔等待
+        backoff.record(false, now);
+        backoff.record(false, now);
+
+        assert!(!backoff.is_due(5, 0, now + 60));
+        assert!(!backoff.is_due(5, 0, now + 20 * 60 - 1));
+        assert!(backoff.is_due(5, 0, now + 20 * 60));
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        assert_eq!(backoff_interval_secs(5, 10), MAX_BACKOFF_INTERVAL_SECS);
+        assert_eq!(backoff_interval_secs(5, u32::MAX), MAX_BACKOFF_INTERVAL_SECS);
+        // 设置间隔超过上限时不再延长，也不会缩短
+        assert_eq!(backoff_interval_secs(120, 3), 120 * 60);
+    }
+
+    /// 按请求路径返回响应的 HTTP 服务器，记录收到的请求路径
+    fn mock_server() -> (String, Arc<StdMutex<Vec<String>>>) {
+        let paths = Arc::new(StdMutex::new(Vec::new()));
+        let recorded = paths.clone();
+        let addr = mock_http::serve(move |request| {
+            recorded.lock().unwrap().push(request.path.clone());
+            if request.path == "/auth/refresh" {
+                (200, r#"{"token":"new-access","refresh_token":"new-refresh"}"#.to_string())
+            } else {
+                (400, r#"{"error":"bad request"}"#.to_string())
+            }
+        });
+
+        (addr, paths)
+    }
+
+    #[test]
+    fn refreshes_near_expiry_token_before_sync() {
+        let (server_url, paths) = mock_server();
+
+        let pool = crate::database::memory_pool();
+        {
+            let device_id = "device-1";
+            let access = CryptoService::encrypt_token("old-access", device_id).unwrap();
+            let refresh = CryptoService::encrypt_token("old-refresh", device_id).unwrap();
+            let expires_at = chrono::Utc::now().timestamp() + 60;
+            pool.get().unwrap().execute(
+                "INSERT INTO user_auth (user_id, server_url, email, access_token_encrypted, refresh_token_encrypted,
+                                        token_expires_at, device_id, is_current, created_at, updated_at)
+                 VALUES ('user-1', ?1, 'a@example.com', ?2, ?3, ?4, ?5, 1, 0, 0)",
+                r2d2_sqlite::rusqlite::params![server_url, access, refresh, expires_at, device_id],
+            ).unwrap();
+        }
+
+        let sync_service = SyncService::new(pool.clone());
+        let auth_service = AuthService::new(pool.clone());
+        let settings = AppSettings { token_refresh_window_minutes: 10, ..AppSettings::default() };
+
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        runtime.block_on(run_auto_sync(&sync_service, &auth_service, &settings));
+
+        let paths = paths.lock().unwrap().clone();
+        assert_eq!(paths.first().map(String::as_str), Some("/auth/refresh"));
+        assert!(paths[1..].iter().any(|p| p == "/sync"), "sync should run after refresh: {:?}", paths);
+
+        let (_, token, expires_at) = auth_service.get_auth_info_with_expires().unwrap();
+        assert_eq!(token, "new-access");
+        assert!(expires_at > chrono::Utc::now().timestamp() + 10 * 60);
+    }
 }
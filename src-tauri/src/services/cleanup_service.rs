@@ -1,12 +1,17 @@
-use crate::services::{NoteService, FolderService, TagService};
+use crate::services::{NoteService, FolderService, TagService, AppSettingsService};
 use crate::models::error::{Result, AppError};
 use crate::database::DbPool;
+use r2d2_sqlite::rusqlite::params;
+use serde::Serialize;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
 /// 自动清理服务
 ///
-/// 定期清理超过 30 天的软删除数据
+/// 定期清理超过保留期的软删除数据
+///
+/// 保留天数读取自应用设置（`trash_retention_days`，默认 30 天），
+/// 可按工作空间单独覆盖；保留天数为 0 的工作空间不会自动清理
 ///
 /// ## 执行策略
 ///
@@ -74,7 +79,7 @@ impl CleanupService {
 
         // 执行清理（无论是否实际删除数据，都视为一次成功的检查）
         log::info!("[CleanupService] 开始执行清理任务");
-        let stats = Self::do_cleanup(&self.note_service, &self.folder_service, &self.tag_service)?;
+        let stats = self.do_cleanup()?;
 
         // 更新检查时间（无论实际清理了多少数据）
         self.update_last_cleanup_time(now)?;
@@ -137,17 +142,84 @@ impl CleanupService {
         Ok(())
     }
 
+    /// 永久删除指定工作空间回收站中早于指定时间的笔记
+    ///
+    /// 比全局的保留期更细粒度：只清理 `deleted_at < before_timestamp` 的笔记，
+    /// 较新的删除仍可恢复。笔记及其标签关联在同一事务中删除。
+    pub fn purge_trash_before(&self, workspace_id: &str, before_timestamp: i64) -> Result<TrashPurgeResult> {
+        let conn = self.pool.get()?;
+
+        // 开始事务
+        let tx = conn.unchecked_transaction().map_err(AppError::Database)?;
+
+        // 先删除标签关联（避免依赖外键级联）
+        let note_tags = tx.execute(
+            "DELETE FROM note_tags WHERE note_id IN (
+                SELECT id FROM notes
+                WHERE is_deleted = 1 AND workspace_id = ? AND deleted_at < ?
+            )",
+            params![workspace_id, before_timestamp],
+        ).map_err(AppError::Database)?;
+
+        let notes = tx.execute(
+            "DELETE FROM notes WHERE is_deleted = 1 AND workspace_id = ? AND deleted_at < ?",
+            params![workspace_id, before_timestamp],
+        ).map_err(AppError::Database)?;
+
+        tx.commit().map_err(AppError::Database)?;
+
+        log::info!(
+            "[CleanupService] 清理回收站: workspace_id={}, before={}, notes={}, note_tags={}",
+            workspace_id,
+            before_timestamp,
+            notes,
+            note_tags
+        );
+
+        Ok(TrashPurgeResult { notes, note_tags })
+    }
+
     /// 执行清理的核心逻辑（私有方法）
-    fn do_cleanup(
-        note_service: &NoteService,
-        folder_service: &FolderService,
-        tag_service: &TagService,
-    ) -> Result<CleanupStats> {
-        let notes = note_service.purge_old_deleted_notes()?;
-        let folders = folder_service.purge_old_deleted_folders()?;
-        let tags = tag_service.purge_old_deleted_tags()?;
-
-        Ok(CleanupStats { notes, folders, tags })
+    ///
+    /// 按工作空间分别读取保留天数并清理，保留天数为 0 的工作空间跳过
+    fn do_cleanup(&self) -> Result<CleanupStats> {
+        let settings = AppSettingsService::new(self.pool.clone()).get_settings()?;
+        let mut stats = CleanupStats { notes: 0, folders: 0, tags: 0 };
+
+        for workspace_id in self.workspaces_with_trash()? {
+            let days = settings.trash_retention_days_for(workspace_id.as_deref());
+            if days <= 0 {
+                log::debug!("[CleanupService] 工作空间不自动清理回收站: workspace_id={:?}", workspace_id);
+                continue;
+            }
+
+            let days = i64::from(days);
+            stats.notes += self.note_service.purge_old_deleted_notes(workspace_id.as_deref(), days)?;
+            stats.folders += self.folder_service.purge_old_deleted_folders(workspace_id.as_deref(), days)?;
+            stats.tags += self.tag_service.purge_old_deleted_tags(workspace_id.as_deref(), days)?;
+        }
+
+        Ok(stats)
+    }
+
+    /// 查询回收站中有数据的工作空间（`None` 表示未归属工作空间的数据）
+    fn workspaces_with_trash(&self) -> Result<Vec<Option<String>>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT workspace_id FROM notes WHERE is_deleted = 1
+             UNION
+             SELECT workspace_id FROM folders WHERE is_deleted = 1
+             UNION
+             SELECT workspace_id FROM tags WHERE is_deleted = 1"
+        ).map_err(AppError::Database)?;
+
+        let workspace_ids = stmt
+            .query_map([], |row| row.get(0))
+            .map_err(AppError::Database)?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(AppError::Database)?;
+
+        Ok(workspace_ids)
     }
 }
 
@@ -158,3 +230,119 @@ pub struct CleanupStats {
     pub folders: i64,
     pub tags: i64,
 }
+
+/// 回收站清理结果统计
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrashPurgeResult {
+    pub notes: usize,      // 永久删除的笔记数量
+    pub note_tags: usize,  // 删除的标签关联数量
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::repositories::{FolderRepository, NoteRepository, TagRepository};
+    use crate::models::UpdateAppSettings;
+    use std::collections::HashMap;
+
+    fn memory_service() -> CleanupService {
+        let pool = crate::database::memory_pool();
+
+        let app_settings = AppSettingsService::new(pool.clone());
+        let note_service = NoteService::new(
+            NoteRepository::new(pool.clone()),
+            FolderRepository::new(pool.clone()),
+            TagRepository::new(pool.clone()),
+            app_settings.clone(),
+        );
+        let folder_service = FolderService::new(FolderRepository::new(pool.clone()));
+        let tag_service = TagService::new(TagRepository::new(pool.clone()), app_settings);
+        CleanupService::new(note_service, folder_service, tag_service, pool)
+    }
+
+    fn insert_deleted_note(service: &CleanupService, id: &str, workspace_id: &str, deleted_days_ago: i64) {
+        let deleted_at = chrono::Utc::now().timestamp() - deleted_days_ago * 86400;
+        service.pool.get().unwrap().execute(
+            "INSERT INTO notes (id, title, content, workspace_id, is_deleted, deleted_at, created_at, updated_at)
+             VALUES (?1, 't', 'c', ?2, 1, ?3, 0, 0)",
+            params![id, workspace_id, deleted_at],
+        ).unwrap();
+    }
+
+    fn note_exists(service: &CleanupService, id: &str) -> bool {
+        service.pool.get().unwrap()
+            .query_row("SELECT COUNT(*) FROM notes WHERE id = ?", [id], |row| row.get::<_, i64>(0))
+            .unwrap() > 0
+    }
+
+    fn set_retention(service: &CleanupService, default_days: i32, overrides: &[(&str, i32)]) {
+        let mut updates: UpdateAppSettings = serde_json::from_value(serde_json::json!({})).unwrap();
+        updates.trash_retention_days = Some(default_days);
+        updates.workspace_trash_retention = Some(
+            overrides.iter().map(|(id, days)| (id.to_string(), *days)).collect::<HashMap<_, _>>(),
+        );
+        AppSettingsService::new(service.pool.clone()).update_settings(updates).unwrap();
+    }
+
+    #[test]
+    fn purges_only_workspaces_past_their_retention() {
+        let service = memory_service();
+        set_retention(&service, 30, &[("ws-short", 10), ("ws-long", 60)]);
+        insert_deleted_note(&service, "short-expired", "ws-short", 20);
+        insert_deleted_note(&service, "long-kept", "ws-long", 20);
+
+        let stats = service.do_cleanup().unwrap();
+
+        assert_eq!(stats.notes, 1);
+        assert!(!note_exists(&service, "short-expired"));
+        assert!(note_exists(&service, "long-kept"));
+    }
+
+    #[test]
+    fn zero_retention_never_purges() {
+        let service = memory_service();
+        set_retention(&service, 0, &[("ws-default", 30)]);
+        insert_deleted_note(&service, "default-expired", "ws-default", 90);
+        insert_deleted_note(&service, "never-purged", "ws-other", 3650);
+
+        service.do_cleanup().unwrap();
+
+        assert!(!note_exists(&service, "default-expired"));
+        assert!(note_exists(&service, "never-purged"));
+    }
+
+    #[test]
+    fn purge_trash_before_removes_only_older_trash_in_workspace() {
+        let service = memory_service();
+        insert_deleted_note(&service, "old", "ws-a", 20);
+        insert_deleted_note(&service, "recent", "ws-a", 5);
+        insert_deleted_note(&service, "other-workspace", "ws-b", 20);
+        {
+            let conn = service.pool.get().unwrap();
+            conn.execute(
+                "INSERT INTO tags (id, name, workspace_id, created_at, updated_at) VALUES ('tag-1', '标签', 'ws-a', 0, 0)",
+                [],
+            ).unwrap();
+            for note_id in ["old", "recent", "other-workspace"] {
+                conn.execute(
+                    "INSERT INTO note_tags (note_id, tag_id, created_at) VALUES (?1, 'tag-1', 0)",
+                    [note_id],
+                ).unwrap();
+            }
+        }
+
+        let cutoff = chrono::Utc::now().timestamp() - 10 * 86400;
+        let result = service.purge_trash_before("ws-a", cutoff).unwrap();
+
+        assert_eq!((result.notes, result.note_tags), (1, 1));
+        assert!(!note_exists(&service, "old"));
+        assert!(note_exists(&service, "recent"));
+        assert!(note_exists(&service, "other-workspace"));
+        let tagged: Vec<String> = service.pool.get().unwrap()
+            .prepare("SELECT note_id FROM note_tags ORDER BY note_id").unwrap()
+            .query_map([], |row| row.get(0)).unwrap()
+            .collect::<r2d2_sqlite::rusqlite::Result<_>>().unwrap();
+        assert_eq!(tagged, ["other-workspace", "recent"]);
+    }
+}
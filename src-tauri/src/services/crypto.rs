@@ -4,12 +4,69 @@ use aes_gcm::{
 };
 use base64::{engine::general_purpose, Engine as _};
 use pbkdf2::pbkdf2_hmac;
-use sha2::Sha256;
+use sha2::{Sha256, Sha512};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
 use crate::models::error::{AppError, Result};
 
+/// 本机安装密钥（保存在数据库之外，由 `CryptoService::init_install_secret` 加载）
+static INSTALL_SECRET: OnceLock<[u8; 32]> = OnceLock::new();
+
+/// token 密文的密钥派生方案
+///
+/// 新方案的密文带有 `v{N}:` 前缀，v1 为无前缀的旧格式；
+/// 解密时按前缀选择方案，因此更换方案后旧密文仍可读取
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyScheme {
+    /// PBKDF2-HMAC-SHA256，100,000 次迭代（无前缀）
+    V1,
+    /// PBKDF2-HMAC-SHA512，210,000 次迭代（前缀 `v2:`）
+    V2,
+    /// 同 v2，但额外混入本机安装密钥（前缀 `v3:`）
+    ///
+    /// 仅凭数据库文件和 device_id 无法解密
+    V3,
+}
+
+impl KeyScheme {
+    /// 新加密数据使用的方案
+    ///
+    /// 已加载安装密钥时使用 v3，否则（如数据目录不可写）退回 v2
+    pub fn current() -> KeyScheme {
+        if INSTALL_SECRET.get().is_some() {
+            KeyScheme::V3
+        } else {
+            KeyScheme::V2
+        }
+    }
+
+    /// 密文前缀（v1 无前缀）
+    fn prefix(self) -> &'static str {
+        match self {
+            KeyScheme::V1 => "",
+            KeyScheme::V2 => "v2:",
+            KeyScheme::V3 => "v3:",
+        }
+    }
+
+    /// 识别密文所用的方案，返回方案和去掉前缀后的 base64 数据
+    ///
+    /// base64 字母表不含 `:`，因此无前缀的 v1 密文不会被误判
+    pub fn of(encrypted: &str) -> Result<(KeyScheme, &str)> {
+        match encrypted.split_once(':') {
+            None => Ok((KeyScheme::V1, encrypted)),
+            Some(("v2", data)) => Ok((KeyScheme::V2, data)),
+            Some(("v3", data)) => Ok((KeyScheme::V3, data)),
+            Some((version, _)) => Err(AppError::EncryptionError(format!(
+                "不支持的加密版本: {}",
+                version
+            ))),
+        }
+    }
+}
+
 /// 加密工具类
 pub struct CryptoService;
 
@@ -17,30 +74,131 @@ impl CryptoService {
     /// 应用特定盐值（硬编码，防止跨应用密钥重用）
     const APP_SALT: &'static [u8] = b"markdown-notes-app-salt-abcdefg-2026";
 
+    /// v2 方案盐值（与 v1 区分，避免两个方案派生出相关密钥）
+    const APP_SALT_V2: &'static [u8] = b"markdown-notes-app-salt-v2-2026";
+
     /// PBKDF2 迭代次数（OWASP 推荐的最小迭代次数）
     const ITERATIONS: u32 = 100_000;
 
+    /// v2 方案 PBKDF2 迭代次数（OWASP 对 PBKDF2-HMAC-SHA512 的推荐值）
+    const ITERATIONS_V2: u32 = 210_000;
+
+    /// v3 方案盐值
+    const APP_SALT_V3: &'static [u8] = b"markdown-notes-app-salt-v3-2026";
+
+    /// 安装密钥文件名（位于应用数据目录）
+    const INSTALL_SECRET_FILE: &'static str = "install.key";
+
+    /// 加载本机安装密钥，不存在时生成随机密钥并保存
+    ///
+    /// 应在启动时、任何 token 加解密之前调用；失败时 `KeyScheme::current()` 退回 v2
+    pub fn init_install_secret(data_dir: &Path) -> Result<()> {
+        let secret = Self::load_or_create_secret(&data_dir.join(Self::INSTALL_SECRET_FILE))?;
+        let _ = INSTALL_SECRET.set(secret);
+        Ok(())
+    }
+
+    /// 读取安装密钥文件（base64），不存在时生成并写入
+    fn load_or_create_secret(path: &Path) -> Result<[u8; 32]> {
+        if path.exists() {
+            let encoded = fs::read_to_string(path)
+                .map_err(|e| AppError::EncryptionError(format!("读取安装密钥失败: {}", e)))?;
+            let bytes = general_purpose::STANDARD
+                .decode(encoded.trim())
+                .map_err(|e| AppError::EncryptionError(format!("安装密钥解码失败: {}", e)))?;
+            return bytes.try_into().map_err(|_| {
+                AppError::EncryptionError("安装密钥长度无效".to_string())
+            });
+        }
+
+        let secret: [u8; 32] = Aes256Gcm::generate_key(&mut OsRng).into();
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| {
+                AppError::EncryptionError(format!("创建目录失败: {}", e))
+            })?;
+        }
+
+        fs::write(path, general_purpose::STANDARD.encode(secret))
+            .map_err(|e| AppError::EncryptionError(format!("写入安装密钥失败: {}", e)))?;
+
+        // 仅允许当前用户读写
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let _ = fs::set_permissions(path, fs::Permissions::from_mode(0o600));
+        }
+
+        Ok(secret)
+    }
+
     /// 从 device_id 派生加密密钥
     ///
-    /// 使用 PBKDF2-HMAC-SHA256 算法从 device_id 派生一个 32 字节的加密密钥
-    pub fn derive_key_from_device_id(device_id: &str) -> [u8; 32] {
+    /// 按指定方案使用 PBKDF2 从 device_id 派生一个 32 字节的加密密钥；
+    /// v3 方案需要已加载的安装密钥
+    pub fn derive_key(scheme: KeyScheme, device_id: &str) -> Result<[u8; 32]> {
+        Self::derive_key_with_secret(scheme, device_id, INSTALL_SECRET.get())
+    }
+
+    fn derive_key_with_secret(
+        scheme: KeyScheme,
+        device_id: &str,
+        secret: Option<&[u8; 32]>,
+    ) -> Result<[u8; 32]> {
         let mut key = [0u8; 32];
 
-        pbkdf2_hmac::<Sha256>(
-            device_id.as_bytes(),
-            Self::APP_SALT,
-            Self::ITERATIONS,
-            &mut key,
-        );
+        match scheme {
+            KeyScheme::V1 => pbkdf2_hmac::<Sha256>(
+                device_id.as_bytes(),
+                Self::APP_SALT,
+                Self::ITERATIONS,
+                &mut key,
+            ),
+            KeyScheme::V2 => pbkdf2_hmac::<Sha512>(
+                device_id.as_bytes(),
+                Self::APP_SALT_V2,
+                Self::ITERATIONS_V2,
+                &mut key,
+            ),
+            KeyScheme::V3 => {
+                let secret = secret.ok_or_else(|| {
+                    AppError::EncryptionError("安装密钥未加载，无法使用 v3 加密方案".to_string())
+                })?;
+                let mut password = secret.to_vec();
+                password.extend_from_slice(device_id.as_bytes());
+                pbkdf2_hmac::<Sha512>(&password, Self::APP_SALT_V3, Self::ITERATIONS_V2, &mut key);
+            }
+        }
 
-        key
+        Ok(key)
     }
 
     /// 加密 token
     ///
-    /// 使用 Aes256Gcm 加密算法加密 token，返回 base64 编码的字符串
-    /// 格式: base64(nonce + ciphertext)
-    pub fn encrypt_token(token: &str, key: &[u8; 32]) -> Result<String> {
+    /// 使用当前方案（`KeyScheme::current()`）派生密钥并加密，
+    /// 格式: 方案前缀 + base64(nonce + ciphertext)
+    pub fn encrypt_token(token: &str, device_id: &str) -> Result<String> {
+        Self::encrypt_token_with(KeyScheme::current(), token, device_id)
+    }
+
+    /// 使用指定方案加密 token
+    pub fn encrypt_token_with(scheme: KeyScheme, token: &str, device_id: &str) -> Result<String> {
+        let key = Self::derive_key(scheme, device_id)?;
+        let sealed = Self::seal(token, &key)?;
+        Ok(format!("{}{}", scheme.prefix(), sealed))
+    }
+
+    /// 解密 token
+    ///
+    /// 根据密文前缀选择派生方案，v1 旧密文同样可以解密
+    pub fn decrypt_token(encrypted: &str, device_id: &str) -> Result<String> {
+        let (scheme, data) = KeyScheme::of(encrypted)?;
+        let key = Self::derive_key(scheme, device_id)?;
+        Self::open(data, &key)
+    }
+
+    /// 使用 Aes256Gcm 加密，返回 base64(nonce + ciphertext)
+    fn seal(token: &str, key: &[u8; 32]) -> Result<String> {
         let cipher = Aes256Gcm::new(key.into());
         let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
 
@@ -55,10 +213,8 @@ impl CryptoService {
         Ok(general_purpose::STANDARD.encode(&result))
     }
 
-    /// 解密 token
-    ///
-    /// 使用 Aes256Gcm 解密算法解密 token
-    pub fn decrypt_token(encrypted: &str, key: &[u8; 32]) -> Result<String> {
+    /// 解密 base64(nonce + ciphertext)
+    fn open(encrypted: &str, key: &[u8; 32]) -> Result<String> {
         let data = general_purpose::STANDARD
             .decode(encrypted)
             .map_err(|e| AppError::EncryptionError(format!("解码失败: {}", e)))?;
@@ -84,8 +240,8 @@ impl CryptoService {
     /// 加密字符串并保存到文件
     ///
     /// 用于保存敏感数据（如 refresh token）到本地文件
-    pub fn encrypt_to_file(data: &str, file_path: &PathBuf, key: &[u8; 32]) -> Result<()> {
-        let encrypted = Self::encrypt_token(data, key)?;
+    pub fn encrypt_to_file(data: &str, file_path: &PathBuf, device_id: &str) -> Result<()> {
+        let encrypted = Self::encrypt_token(data, device_id)?;
 
         // 确保目录存在
         if let Some(parent) = file_path.parent() {
@@ -103,11 +259,11 @@ impl CryptoService {
     /// 从文件读取并解密数据
     ///
     /// 用于从本地文件读取敏感数据
-    pub fn decrypt_from_file(file_path: &PathBuf, key: &[u8; 32]) -> Result<String> {
+    pub fn decrypt_from_file(file_path: &PathBuf, device_id: &str) -> Result<String> {
         let encrypted = fs::read_to_string(file_path)
             .map_err(|e| AppError::EncryptionError(format!("读取文件失败: {}", e)))?;
 
-        Self::decrypt_token(&encrypted, key)
+        Self::decrypt_token(&encrypted, device_id)
     }
 
     /// 生成随机设备 ID
@@ -123,28 +279,83 @@ mod tests {
     #[test]
     fn test_encrypt_decrypt_token() {
         let device_id = "test-device-123";
-        let key = CryptoService::derive_key_from_device_id(device_id);
         let original_token = "my-secret-access-token";
 
         // 加密
         let encrypted =
-            CryptoService::encrypt_token(original_token, &key).expect("Encryption failed");
+            CryptoService::encrypt_token(original_token, device_id).expect("Encryption failed");
 
         println!("Encrypted: {}", encrypted);
+        assert_eq!(KeyScheme::of(&encrypted).unwrap().0, KeyScheme::current());
 
         // 解密
-        let decrypted = CryptoService::decrypt_token(&encrypted, &key).expect("Decryption failed");
+        let decrypted =
+            CryptoService::decrypt_token(&encrypted, device_id).expect("Decryption failed");
 
         assert_eq!(original_token, decrypted);
         println!("✅ Encryption/Decryption test passed");
     }
 
+    #[test]
+    fn test_v1_ciphertext_still_decrypts() {
+        let device_id = "legacy-device";
+        let token = "legacy-token";
+
+        // 引入 v2 之前写入的密文：无前缀，使用 v1 密钥
+        let legacy = CryptoService::seal(token, &CryptoService::derive_key(KeyScheme::V1, device_id).unwrap())
+            .expect("Encryption failed");
+        assert_eq!(KeyScheme::of(&legacy).unwrap().0, KeyScheme::V1);
+
+        let decrypted = CryptoService::decrypt_token(&legacy, device_id).expect("Decryption failed");
+        assert_eq!(token, decrypted);
+    }
+
+    #[test]
+    fn test_v3_requires_install_secret() {
+        let device_id = "device-1";
+        let secret = [7u8; 32];
+        let token = "my-secret-token";
+
+        let key = CryptoService::derive_key_with_secret(KeyScheme::V3, device_id, Some(&secret)).unwrap();
+        let sealed = CryptoService::seal(token, &key).unwrap();
+
+        // 仅凭 device_id 派生的密钥无法解密
+        let device_only = CryptoService::derive_key(KeyScheme::V2, device_id).unwrap();
+        assert!(CryptoService::open(&sealed, &device_only).is_err());
+        assert!(CryptoService::derive_key_with_secret(KeyScheme::V3, device_id, None).is_err());
+
+        // 其他安装的密钥同样无法解密
+        let other = CryptoService::derive_key_with_secret(KeyScheme::V3, device_id, Some(&[8u8; 32])).unwrap();
+        assert!(CryptoService::open(&sealed, &other).is_err());
+
+        assert_eq!(CryptoService::open(&sealed, &key).unwrap(), token);
+    }
+
+    #[test]
+    fn test_install_secret_persisted() {
+        let path = std::env::temp_dir()
+            .join(format!("install-secret-{}", uuid::Uuid::new_v4()))
+            .join("install.key");
+
+        let created = CryptoService::load_or_create_secret(&path).unwrap();
+        let loaded = CryptoService::load_or_create_secret(&path).unwrap();
+        assert_eq!(created, loaded, "Install secret should be stable across restarts");
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_unknown_scheme_rejected() {
+        let result = CryptoService::decrypt_token("v9:AAAA", "device-1");
+        assert!(result.is_err(), "Unknown scheme prefix should be rejected");
+    }
+
     #[test]
     fn test_key_derivation_consistency() {
         let device_id = "consistent-device-id";
 
-        let key1 = CryptoService::derive_key_from_device_id(device_id);
-        let key2 = CryptoService::derive_key_from_device_id(device_id);
+        let key1 = CryptoService::derive_key(KeyScheme::V1, device_id).unwrap();
+        let key2 = CryptoService::derive_key(KeyScheme::V1, device_id).unwrap();
 
         assert_eq!(key1, key2, "Key derivation should be deterministic");
         println!("✅ Key derivation consistency test passed");
@@ -155,29 +366,28 @@ mod tests {
         let device_id1 = "device-1";
         let device_id2 = "device-2";
 
-        let key1 = CryptoService::derive_key_from_device_id(device_id1);
-        let key2 = CryptoService::derive_key_from_device_id(device_id2);
+        let key1 = CryptoService::derive_key(KeyScheme::V1, device_id1).unwrap();
+        let key2 = CryptoService::derive_key(KeyScheme::V1, device_id2).unwrap();
 
         assert_ne!(
             key1, key2,
             "Different device IDs should produce different keys"
         );
+        assert_ne!(
+            CryptoService::derive_key(KeyScheme::V1, device_id1).unwrap(),
+            CryptoService::derive_key(KeyScheme::V2, device_id1).unwrap(),
+            "Different schemes should produce different keys"
+        );
         println!("✅ Key derivation uniqueness test passed");
     }
 
     #[test]
     fn test_wrong_key_fails() {
-        let device_id1 = "device-1";
-        let device_id2 = "device-2";
-
-        let key1 = CryptoService::derive_key_from_device_id(device_id1);
-        let key2 = CryptoService::derive_key_from_device_id(device_id2);
-
         let token = "my-secret-token";
-        let encrypted = CryptoService::encrypt_token(token, &key1).expect("Encryption failed");
+        let encrypted = CryptoService::encrypt_token(token, "device-1").expect("Encryption failed");
 
-        // 使用错误的密钥解密应该失败
-        let result = CryptoService::decrypt_token(&encrypted, &key2);
+        // 使用错误的设备 ID 解密应该失败
+        let result = CryptoService::decrypt_token(&encrypted, "device-2");
 
         assert!(result.is_err(), "Decryption with wrong key should fail");
         println!("✅ Wrong key failure test passed");
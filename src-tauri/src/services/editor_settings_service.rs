@@ -1,5 +1,15 @@
 use crate::database::repositories::EditorSettingsRepository;
-use crate::models::{EditorSettings, UpdateEditorSettingsRequest, error::{Result, AppError}};
+use crate::models::{EditorSettings, UpdateEditorSettingsRequest, EditorSettingsExport, ExportedEditorSettings, DEFAULT_EDITOR_PROFILE_ID, error::{Result, AppError}};
+use crate::models::editor_settings::EDITOR_SETTINGS_EXPORT_VERSION;
+
+/// Markdown 预览样式可选值
+const MARKDOWN_PREVIEW_STYLES: &[&str] = &["minimal", "default", "rich"];
+
+/// 字体大小允许范围（px）
+const FONT_SIZE_RANGE: std::ops::RangeInclusive<i32> = 8..=72;
+
+/// 行高允许范围（倍数）
+const LINE_HEIGHT_RANGE: std::ops::RangeInclusive<f64> = 1.0..=3.0;
 
 pub struct EditorSettingsService {
     repo: EditorSettingsRepository,
@@ -10,14 +20,247 @@ impl EditorSettingsService {
         Self { repo }
     }
 
-    /// 获取编辑器设置
+    /// 获取编辑器设置（当前激活的配置）
     pub fn get_settings(&self) -> Result<EditorSettings> {
-        self.repo.find_by_id()?
+        self.repo.find_active()?
             .ok_or(AppError::Internal("编辑器设置未找到".to_string()))
     }
 
     /// 更新编辑器设置
+    ///
+    /// `req.profile_id` 为空时更新当前激活的配置
     pub fn update_settings(&self, req: UpdateEditorSettingsRequest) -> Result<EditorSettings> {
         self.repo.update(&req)
     }
+
+    /// 获取所有编辑器配置
+    pub fn list_profiles(&self) -> Result<Vec<EditorSettings>> {
+        // 确保默认配置存在
+        self.get_settings()?;
+        self.repo.list_profiles()
+    }
+
+    /// 以当前激活配置的各项设置新建命名配置
+    pub fn create_profile(&self, name: &str) -> Result<EditorSettings> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(AppError::Validation("配置名称不能为空".to_string()));
+        }
+        if self.list_profiles()?.iter().any(|p| p.name == name) {
+            return Err(AppError::InvalidOperation(format!("已存在名为「{}」的编辑器配置", name)));
+        }
+
+        let base = self.get_settings()?;
+        self.repo.create_profile(name, &base)
+    }
+
+    /// 删除编辑器配置（默认配置不能删除，删除当前配置后切换回默认配置）
+    pub fn delete_profile(&self, id: i32) -> Result<()> {
+        if id == DEFAULT_EDITOR_PROFILE_ID {
+            return Err(AppError::InvalidOperation("默认编辑器配置不能删除".to_string()));
+        }
+        if !self.repo.delete_profile(id)? {
+            return Err(AppError::NotFound(format!("编辑器配置 {}", id)));
+        }
+        Ok(())
+    }
+
+    /// 切换到指定的编辑器配置
+    pub fn activate_profile(&self, id: i32) -> Result<EditorSettings> {
+        self.repo.activate(id)
+    }
+
+    /// 导出当前编辑器设置为 JSON
+    pub fn export_settings(&self) -> Result<String> {
+        let export = EditorSettingsExport {
+            version: EDITOR_SETTINGS_EXPORT_VERSION.to_string(),
+            settings: self.get_settings()?.into(),
+        };
+        serde_json::to_string_pretty(&export)
+            .map_err(|e| AppError::Internal(format!("序列化编辑器设置失败: {}", e)))
+    }
+
+    /// 从 JSON 导入编辑器设置，应用到当前激活的配置
+    ///
+    /// 未知字段、类型错误或超出范围的值都会导致导入失败，不会部分应用
+    pub fn import_settings(&self, json_string: &str) -> Result<EditorSettings> {
+        let export: EditorSettingsExport = serde_json::from_str(json_string)
+            .map_err(|e| AppError::InvalidInput(format!("解析编辑器设置失败: {}", e)))?;
+
+        if export.version != EDITOR_SETTINGS_EXPORT_VERSION {
+            return Err(AppError::InvalidInput(format!("不支持的版本: {}", export.version)));
+        }
+        validate_settings(&export.settings)?;
+
+        let s = export.settings;
+        let settings = self.update_settings(UpdateEditorSettingsRequest {
+            profile_id: None,
+            content_font_family: s.content_font_family,
+            content_font_size: s.content_font_size,
+            content_font_weight: s.content_font_weight,
+            content_line_height: s.content_line_height,
+            heading_font_family: s.heading_font_family,
+            heading_font_weight: s.heading_font_weight,
+            code_font_family: s.code_font_family,
+            code_font_size: s.code_font_size,
+            markdown_preview_style: s.markdown_preview_style,
+        })?;
+        log::info!("编辑器设置导入成功: profile_id={}", settings.id);
+        Ok(settings)
+    }
+}
+
+/// 校验导入的编辑器设置取值
+fn validate_settings(settings: &ExportedEditorSettings) -> Result<()> {
+    let invalid = |field: &str, value: String| {
+        Err(AppError::InvalidInput(format!("字段 {} 的值无效: {}", field, value)))
+    };
+
+    for (field, family) in [
+        ("contentFontFamily", &settings.content_font_family),
+        ("headingFontFamily", &settings.heading_font_family),
+        ("codeFontFamily", &settings.code_font_family),
+    ] {
+        if family.as_deref().is_some_and(|f| f.trim().is_empty()) {
+            return invalid(field, "空字符串".to_string());
+        }
+    }
+    for (field, size) in [("contentFontSize", settings.content_font_size), ("codeFontSize", settings.code_font_size)] {
+        if let Some(size) = size.filter(|s| !FONT_SIZE_RANGE.contains(s)) {
+            return invalid(field, size.to_string());
+        }
+    }
+    for (field, weight) in [("contentFontWeight", settings.content_font_weight), ("headingFontWeight", settings.heading_font_weight)] {
+        if let Some(weight) = weight.filter(|w| !(100..=900).contains(w) || w % 100 != 0) {
+            return invalid(field, weight.to_string());
+        }
+    }
+    if let Some(height) = settings.content_line_height.filter(|h| !LINE_HEIGHT_RANGE.contains(h)) {
+        return invalid("contentLineHeight", height.to_string());
+    }
+    if let Some(style) = settings.markdown_preview_style.as_deref().filter(|s| !MARKDOWN_PREVIEW_STYLES.contains(s)) {
+        return invalid("markdownPreviewStyle", style.to_string());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory_service() -> EditorSettingsService {
+        let pool = crate::database::memory_pool();
+
+        EditorSettingsService::new(EditorSettingsRepository::new(pool))
+    }
+
+    fn font_size_request(profile_id: Option<i32>, size: i32) -> UpdateEditorSettingsRequest {
+        UpdateEditorSettingsRequest {
+            profile_id,
+            content_font_family: None,
+            content_font_size: Some(size),
+            content_font_weight: None,
+            content_line_height: None,
+            heading_font_family: None,
+            heading_font_weight: None,
+            code_font_family: None,
+            code_font_size: None,
+            markdown_preview_style: None,
+        }
+    }
+
+    #[test]
+    fn test_default_profile_is_active() {
+        let service = memory_service();
+
+        let settings = service.get_settings().unwrap();
+
+        assert_eq!(settings.id, DEFAULT_EDITOR_PROFILE_ID);
+        assert!(settings.is_active);
+        assert_eq!(service.list_profiles().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_switch_between_profiles() {
+        let service = memory_service();
+        let writing = service.create_profile("写作").unwrap();
+        let coding = service.create_profile("编程").unwrap();
+        service.update_settings(font_size_request(Some(writing.id), 18)).unwrap();
+        service.update_settings(font_size_request(Some(coding.id), 13)).unwrap();
+
+        service.activate_profile(writing.id).unwrap();
+        assert_eq!(service.get_settings().unwrap().content_font_size, 18);
+
+        service.activate_profile(coding.id).unwrap();
+        let active = service.get_settings().unwrap();
+        assert_eq!(active.name, "编程");
+        assert_eq!(active.content_font_size, 13);
+
+        let active_count = service.list_profiles().unwrap().iter().filter(|p| p.is_active).count();
+        assert_eq!(active_count, 1);
+    }
+
+    #[test]
+    fn test_update_without_profile_id_targets_active_profile() {
+        let service = memory_service();
+        let coding = service.create_profile("编程").unwrap();
+        service.activate_profile(coding.id).unwrap();
+
+        service.update_settings(font_size_request(None, 12)).unwrap();
+
+        let default = service.activate_profile(DEFAULT_EDITOR_PROFILE_ID).unwrap();
+        assert_eq!(default.content_font_size, 16);
+        assert_eq!(service.activate_profile(coding.id).unwrap().content_font_size, 12);
+    }
+
+    #[test]
+    fn test_export_import_round_trip() {
+        let service = memory_service();
+        service.update_settings(font_size_request(None, 20)).unwrap();
+        let exported = service.export_settings().unwrap();
+
+        let other = memory_service();
+        let imported = other.import_settings(&exported).unwrap();
+
+        assert_eq!(imported.content_font_size, 20);
+        assert_eq!(other.export_settings().unwrap(), exported);
+    }
+
+    #[test]
+    fn test_import_rejects_malformed_json() {
+        let service = memory_service();
+
+        for json in [
+            "{not json",
+            r#"{"version": "1.0", "settings": {"contentFontSize": "big"}}"#,
+            r#"{"version": "1.0", "settings": {"contentFontSzie": 18}}"#,
+            r#"{"version": "1.0", "settings": {}, "extra": true}"#,
+            r#"{"version": "2.0", "settings": {}}"#,
+            r#"{"version": "1.0", "settings": {"contentFontWeight": 450}}"#,
+            r#"{"version": "1.0", "settings": {"markdownPreviewStyle": "fancy"}}"#,
+        ] {
+            assert!(
+                matches!(service.import_settings(json), Err(AppError::InvalidInput(_))),
+                "应拒绝: {}",
+                json
+            );
+        }
+        assert_eq!(service.get_settings().unwrap().content_font_size, 16);
+    }
+
+    #[test]
+    fn test_delete_active_profile_falls_back_to_default() {
+        let service = memory_service();
+        let coding = service.create_profile("编程").unwrap();
+        service.activate_profile(coding.id).unwrap();
+
+        service.delete_profile(coding.id).unwrap();
+
+        assert_eq!(service.get_settings().unwrap().id, DEFAULT_EDITOR_PROFILE_ID);
+        assert!(matches!(
+            service.delete_profile(DEFAULT_EDITOR_PROFILE_ID),
+            Err(AppError::InvalidOperation(_))
+        ));
+    }
 }
@@ -1,6 +1,7 @@
 use crate::database::repositories::FolderRepository;
 use crate::models::{Folder, CreateFolderRequest, UpdateFolderRequest, MoveFolderRequest};
 use crate::models::error::{Result, AppError};
+use std::collections::HashMap;
 
 /// 文件夹业务逻辑层
 ///
@@ -124,6 +125,13 @@ impl FolderService {
         self.repo.delete(id)
     }
 
+    /// 从回收站恢复文件夹
+    ///
+    /// 连同与其一起被删除的子文件夹和笔记一并恢复，之前单独删除的笔记不受影响
+    pub fn restore_folder(&self, id: &str) -> Result<Folder> {
+        self.repo.restore(id)
+    }
+
     /// 获取所有文件夹
     pub fn list_folders(&self) -> Result<Vec<Folder>> {
         self.repo.find_all()
@@ -149,6 +157,13 @@ impl FolderService {
         self.update_folder(update_req)
     }
 
+    /// 获取工作空间内每个文件夹的最后活跃时间
+    ///
+    /// 用于文件夹树按最近活动排序，详见 [`FolderRepository::last_modified_map`]
+    pub fn get_folder_last_modified(&self, workspace_id: &str, include_descendants: bool) -> Result<HashMap<String, i64>> {
+        self.repo.last_modified_map(workspace_id, include_descendants)
+    }
+
     /// 获取文件夹路径
     pub fn get_folder_path(&self, id: &str) -> Result<Vec<Folder>> {
         self.repo.get_path(id)
@@ -177,14 +192,13 @@ impl FolderService {
         self.repo.hard_delete(id)
     }
 
-    /// 清理超过 30 天的软删除文件夹
+    /// 清理指定工作空间中超过保留天数的软删除文件夹
     ///
     /// ## 返回
     ///
     /// 返回清理的文件夹数量
-    pub fn purge_old_deleted_folders(&self) -> Result<i64> {
-        const PURGE_AFTER_DAYS: i64 = 30;
-        self.repo.purge_old_deleted_folders(PURGE_AFTER_DAYS)
+    pub fn purge_old_deleted_folders(&self, workspace_id: Option<&str>, days: i64) -> Result<i64> {
+        self.repo.purge_old_deleted_folders(workspace_id, days)
     }
 }
 
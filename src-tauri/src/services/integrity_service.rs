@@ -0,0 +1,527 @@
+use crate::models::error::{Result, AppError};
+use crate::models::Note;
+use crate::database::DbPool;
+use r2d2_sqlite::rusqlite::params;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// 数据完整性服务
+///
+/// 检测并修复跨工作空间移动等操作留下的不一致数据
+#[derive(Clone)]
+pub struct IntegrityService {
+    pool: DbPool,
+}
+
+impl IntegrityService {
+    /// 创建新的 IntegrityService 实例
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// 查找 workspace_id 与所在文件夹不一致的笔记
+    ///
+    /// 笔记的 `folder_id` 指向的文件夹属于另一个工作空间时，
+    /// 该笔记在两个工作空间中都无法正确显示
+    pub fn find_workspace_folder_mismatches(&self) -> Result<Vec<WorkspaceFolderMismatch>> {
+        let conn = self.pool.get()?;
+
+        let mut stmt = conn.prepare(
+            "SELECT n.id, n.title, n.workspace_id, f.id, f.workspace_id
+             FROM notes n
+             INNER JOIN folders f ON f.id = n.folder_id
+             WHERE n.workspace_id IS NOT f.workspace_id
+             ORDER BY n.updated_at DESC"
+        ).map_err(AppError::Database)?;
+
+        let mismatches = stmt.query_map([], |row| {
+            Ok(WorkspaceFolderMismatch {
+                note_id: row.get(0)?,
+                note_title: row.get(1)?,
+                note_workspace_id: row.get(2)?,
+                folder_id: row.get(3)?,
+                folder_workspace_id: row.get(4)?,
+            })
+        }).map_err(AppError::Database)?
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(AppError::Database)?;
+
+        log::info!("[IntegrityService] 发现 {} 篇笔记与文件夹的工作空间不一致", mismatches.len());
+
+        Ok(mismatches)
+    }
+
+    /// 修复 workspace_id 与所在文件夹不一致的笔记
+    ///
+    /// ## 修复方式
+    ///
+    /// - `move_to_folder_workspace = true`：将笔记移到文件夹所在的工作空间
+    /// - `move_to_folder_workspace = false`：保留笔记的工作空间，清空 `folder_id`
+    ///
+    /// 修复后的笔记会标记为 dirty，下次同步时上传
+    pub fn repair_workspace_folder_mismatches(&self, move_to_folder_workspace: bool) -> Result<usize> {
+        let conn = self.pool.get()?;
+        let now = chrono::Utc::now().timestamp();
+
+        let sql = if move_to_folder_workspace {
+            "UPDATE notes
+             SET workspace_id = (SELECT f.workspace_id FROM folders f WHERE f.id = notes.folder_id),
+                 updated_at = ?, is_dirty = 1
+             WHERE id IN (
+                SELECT n.id FROM notes n
+                INNER JOIN folders f ON f.id = n.folder_id
+                WHERE n.workspace_id IS NOT f.workspace_id
+             )"
+        } else {
+            "UPDATE notes
+             SET folder_id = NULL, updated_at = ?, is_dirty = 1
+             WHERE id IN (
+                SELECT n.id FROM notes n
+                INNER JOIN folders f ON f.id = n.folder_id
+                WHERE n.workspace_id IS NOT f.workspace_id
+             )"
+        };
+
+        let repaired = conn.execute(sql, params![now]).map_err(AppError::Database)?;
+
+        log::info!(
+            "[IntegrityService] 修复工作空间不一致的笔记: count={}, move_to_folder_workspace={}",
+            repaired,
+            move_to_folder_workspace
+        );
+
+        Ok(repaired)
+    }
+
+    /// 查找父文件夹属于另一个工作空间的文件夹
+    ///
+    /// 这类文件夹在两个工作空间的文件夹树中都无法正确显示
+    pub fn find_cross_workspace_folder_parents(&self) -> Result<Vec<CrossWorkspaceFolderParent>> {
+        let conn = self.pool.get()?;
+
+        let mut stmt = conn.prepare(
+            "SELECT c.id, c.name, c.workspace_id, p.id, p.workspace_id
+             FROM folders c
+             INNER JOIN folders p ON p.id = c.parent_id
+             WHERE c.workspace_id IS NOT p.workspace_id
+             ORDER BY c.updated_at DESC"
+        ).map_err(AppError::Database)?;
+
+        let folders = stmt.query_map([], |row| {
+            Ok(CrossWorkspaceFolderParent {
+                folder_id: row.get(0)?,
+                folder_name: row.get(1)?,
+                folder_workspace_id: row.get(2)?,
+                parent_id: row.get(3)?,
+                parent_workspace_id: row.get(4)?,
+            })
+        }).map_err(AppError::Database)?
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(AppError::Database)?;
+
+        log::info!("[IntegrityService] 发现 {} 个文件夹的父文件夹属于其他工作空间", folders.len());
+
+        Ok(folders)
+    }
+
+    /// 修复父文件夹属于另一个工作空间的文件夹
+    ///
+    /// ## 修复方式
+    ///
+    /// - `move_subtree = true`：将文件夹及其所有子文件夹、其中的笔记移到父文件夹所在的工作空间
+    /// - `move_subtree = false`：保留文件夹的工作空间，清空 `parent_id` 使其成为根文件夹
+    ///
+    /// 修改过的文件夹和笔记会标记为 dirty，下次同步时上传
+    ///
+    /// ## 返回
+    ///
+    /// 修改的文件夹数量
+    pub fn repair_cross_workspace_folder_parents(&self, move_subtree: bool) -> Result<usize> {
+        let conn = self.pool.get()?;
+        let now = chrono::Utc::now().timestamp();
+
+        if !move_subtree {
+            let repaired = conn.execute(
+                "UPDATE folders
+                 SET parent_id = NULL, updated_at = ?, is_dirty = 1
+                 WHERE id IN (
+                    SELECT c.id FROM folders c
+                    INNER JOIN folders p ON p.id = c.parent_id
+                    WHERE c.workspace_id IS NOT p.workspace_id
+                 )",
+                params![now],
+            ).map_err(AppError::Database)?;
+
+            log::info!("[IntegrityService] 修复跨工作空间父文件夹（设为根文件夹）: count={}", repaired);
+            return Ok(repaired);
+        }
+
+        // 子树：每个问题文件夹及其所有子文件夹，携带目标工作空间（父文件夹所在工作空间）
+        const SUBTREE: &str = "WITH RECURSIVE subtree(id, target_workspace_id) AS (
+                SELECT c.id, p.workspace_id FROM folders c
+                INNER JOIN folders p ON p.id = c.parent_id
+                WHERE c.workspace_id IS NOT p.workspace_id
+                UNION
+                SELECT f.id, s.target_workspace_id FROM folders f
+                INNER JOIN subtree s ON f.parent_id = s.id
+            )";
+
+        let tx = conn.unchecked_transaction().map_err(AppError::Database)?;
+
+        // 先移动笔记（此时文件夹还未修改，子树计算结果不变）
+        let notes = tx.execute(
+            &format!(
+                "{SUBTREE}
+                 UPDATE notes
+                 SET workspace_id = (SELECT s.target_workspace_id FROM subtree s WHERE s.id = notes.folder_id LIMIT 1),
+                     updated_at = ?1, is_dirty = 1
+                 WHERE folder_id IN (SELECT id FROM subtree)"
+            ),
+            params![now],
+        ).map_err(AppError::Database)?;
+
+        let folders = tx.execute(
+            &format!(
+                "{SUBTREE}
+                 UPDATE folders
+                 SET workspace_id = (SELECT s.target_workspace_id FROM subtree s WHERE s.id = folders.id LIMIT 1),
+                     updated_at = ?1, is_dirty = 1
+                 WHERE id IN (SELECT id FROM subtree)"
+            ),
+            params![now],
+        ).map_err(AppError::Database)?;
+
+        tx.commit().map_err(AppError::Database)?;
+
+        log::info!(
+            "[IntegrityService] 修复跨工作空间父文件夹（移动子树）: folders={}, notes={}",
+            folders,
+            notes
+        );
+
+        Ok(folders)
+    }
+
+    /// 查找内容损坏的笔记（无效 UTF-8、替换字符 U+FFFD 或控制字符）
+    ///
+    /// 直接按字节读取内容，即使不是合法 UTF-8 也能检测。
+    /// 每篇笔记只报告第一个问题的字节偏移，便于手动定位
+    pub fn find_invalid_content(&self, workspace_id: &str) -> Result<Vec<InvalidContentNote>> {
+        let conn = self.pool.get()?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, title, CAST(content AS BLOB) FROM notes WHERE workspace_id = ? AND is_deleted = 0"
+        ).map_err(AppError::Database)?;
+
+        let rows = stmt.query_map(params![workspace_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Vec<u8>>(2)?,
+            ))
+        }).map_err(AppError::Database)?;
+
+        let mut notes = Vec::new();
+        for row in rows {
+            let (note_id, title, content) = row.map_err(AppError::Database)?;
+            if let Some((issue, byte_offset)) = find_content_issue(&content) {
+                notes.push(InvalidContentNote { note_id, title, issue, byte_offset });
+            }
+        }
+
+        log::info!(
+            "[IntegrityService] 内容检测: workspace_id={}, invalid_notes={}",
+            workspace_id,
+            notes.len()
+        );
+
+        Ok(notes)
+    }
+
+    /// 清理损坏的笔记内容
+    ///
+    /// 无效 UTF-8 按替换字符解码后，移除所有 U+FFFD 和控制字符（保留换行、回车和制表符）。
+    /// 修复后的笔记会标记为 dirty，下次同步时上传
+    pub fn repair_invalid_content(&self, workspace_id: &str) -> Result<usize> {
+        let invalid = self.find_invalid_content(workspace_id)?;
+        if invalid.is_empty() {
+            return Ok(0);
+        }
+
+        let conn = self.pool.get()?;
+        let now = chrono::Utc::now().timestamp();
+
+        let tx = conn.unchecked_transaction().map_err(AppError::Database)?;
+
+        for note in &invalid {
+            let content: Vec<u8> = tx.query_row(
+                "SELECT CAST(content AS BLOB) FROM notes WHERE id = ?",
+                params![&note.note_id],
+                |row| row.get(0),
+            ).map_err(AppError::Database)?;
+
+            let cleaned: String = String::from_utf8_lossy(&content)
+                .chars()
+                .filter(|c| !is_invalid_char(*c))
+                .collect();
+
+            tx.execute(
+                "UPDATE notes SET content = ?, markdown_cache = ?, updated_at = ?, is_dirty = 1 WHERE id = ?",
+                params![&cleaned, Note::generate_markdown(&cleaned), now, &note.note_id],
+            ).map_err(AppError::Database)?;
+        }
+
+        tx.commit().map_err(AppError::Database)?;
+
+        log::info!("[IntegrityService] 清理损坏内容: workspace_id={}, count={}", workspace_id, invalid.len());
+
+        Ok(invalid.len())
+    }
+
+    /// 查找 parent_id 形成循环的文件夹
+    ///
+    /// 移动文件夹时会通过 `check_circular_reference` 拦截循环，但同步写入的损坏数据仍可能形成循环，
+    /// 导致 `find_tree` 和递归 CTE 无法终止或渲染错误。已软删除的文件夹也会参与检测
+    pub fn find_folder_cycles(&self, workspace_id: &str) -> Result<Vec<FolderCycle>> {
+        let conn = self.pool.get()?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, name, parent_id, updated_at FROM folders WHERE workspace_id = ?"
+        ).map_err(AppError::Database)?;
+
+        let mut parents = BTreeMap::new();
+        let mut info = HashMap::new();
+        let rows = stmt.query_map(params![workspace_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, i64>(3)?,
+            ))
+        }).map_err(AppError::Database)?;
+        for row in rows {
+            let (id, name, parent_id, updated_at) = row.map_err(AppError::Database)?;
+            parents.insert(id.clone(), parent_id);
+            info.insert(id, (name, updated_at));
+        }
+
+        let cycles: Vec<FolderCycle> = find_parent_cycles(&parents)
+            .into_iter()
+            .map(|folder_ids| {
+                // 最近修改的文件夹最可能是引入循环的那个，修复时将其设为根文件夹
+                let break_at = folder_ids.iter()
+                    .max_by_key(|id| info[*id].1)
+                    .cloned()
+                    .unwrap_or_default();
+                FolderCycle {
+                    folder_names: folder_ids.iter().map(|id| info[id].0.clone()).collect(),
+                    folder_ids,
+                    break_at,
+                }
+            })
+            .collect();
+
+        log::info!(
+            "[IntegrityService] 文件夹循环检测: workspace_id={}, cycles={}",
+            workspace_id,
+            cycles.len()
+        );
+
+        Ok(cycles)
+    }
+
+    /// 修复文件夹循环引用
+    ///
+    /// 每个循环中将 `break_at` 对应的文件夹设为根文件夹（清空 `parent_id`），
+    /// 循环中的其他文件夹保持原有层级。修复后的文件夹会标记为 dirty，下次同步时上传
+    ///
+    /// ## 返回
+    ///
+    /// 修改的文件夹数量
+    pub fn repair_folder_cycles(&self, workspace_id: &str) -> Result<usize> {
+        let cycles = self.find_folder_cycles(workspace_id)?;
+        if cycles.is_empty() {
+            return Ok(0);
+        }
+
+        let conn = self.pool.get()?;
+        let now = chrono::Utc::now().timestamp();
+
+        let tx = conn.unchecked_transaction().map_err(AppError::Database)?;
+        for cycle in &cycles {
+            tx.execute(
+                "UPDATE folders SET parent_id = NULL, updated_at = ?, is_dirty = 1 WHERE id = ?",
+                params![now, &cycle.break_at],
+            ).map_err(AppError::Database)?;
+        }
+        tx.commit().map_err(AppError::Database)?;
+
+        log::info!("[IntegrityService] 修复文件夹循环: workspace_id={}, count={}", workspace_id, cycles.len());
+
+        Ok(cycles.len())
+    }
+}
+
+/// 在 id -> parent_id 映射中查找所有循环
+///
+/// 每个循环按父链顺序返回，指向映射外的 parent_id 视为根
+fn find_parent_cycles(parents: &BTreeMap<String, Option<String>>) -> Vec<Vec<String>> {
+    // 0：未访问，1：在当前路径上，2：已确认不在新的循环中
+    let mut state: HashMap<&str, u8> = HashMap::new();
+    let mut cycles = Vec::new();
+
+    for start in parents.keys() {
+        let mut path: Vec<&str> = Vec::new();
+        let mut current = Some(start.as_str());
+
+        while let Some(id) = current {
+            match state.get(id).copied().unwrap_or(0) {
+                0 => {
+                    state.insert(id, 1);
+                    path.push(id);
+                    current = parents.get(id)
+                        .and_then(|parent| parent.as_deref())
+                        .filter(|parent| parents.contains_key(*parent));
+                }
+                1 => {
+                    let begin = path.iter().position(|p| *p == id).unwrap_or(0);
+                    cycles.push(path[begin..].iter().map(|p| p.to_string()).collect());
+                    break;
+                }
+                _ => break,
+            }
+        }
+
+        for id in path {
+            state.insert(id, 2);
+        }
+    }
+
+    cycles
+}
+
+/// 是否为需要清理的字符（替换字符或除换行、回车、制表符外的控制字符）
+fn is_invalid_char(c: char) -> bool {
+    c == char::REPLACEMENT_CHARACTER || (c.is_control() && !matches!(c, '\n' | '\r' | '\t'))
+}
+
+/// 查找内容中的第一个问题，返回问题类型和字节偏移
+fn find_content_issue(bytes: &[u8]) -> Option<(ContentIssue, usize)> {
+    let (text, invalid_at) = match std::str::from_utf8(bytes) {
+        Ok(text) => (text, None),
+        // 只检查合法前缀，之后的位置就是第一个无效字节
+        Err(e) => (
+            std::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap_or_default(),
+            Some(e.valid_up_to()),
+        ),
+    };
+
+    let char_issue = text.char_indices().find_map(|(offset, c)| {
+        if c == char::REPLACEMENT_CHARACTER {
+            Some((ContentIssue::ReplacementCharacter, offset))
+        } else if is_invalid_char(c) {
+            Some((ContentIssue::ControlCharacter, offset))
+        } else {
+            None
+        }
+    });
+
+    char_issue.or(invalid_at.map(|offset| (ContentIssue::InvalidUtf8, offset)))
+}
+
+/// 文件夹循环引用
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderCycle {
+    pub folder_ids: Vec<String>,    // 循环中的文件夹（按父链顺序）
+    pub folder_names: Vec<String>,  // 与 folder_ids 一一对应的名称
+    pub break_at: String,           // 修复时设为根文件夹的文件夹 ID
+}
+
+/// 笔记内容问题类型
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ContentIssue {
+    InvalidUtf8,           // 不是合法的 UTF-8
+    ReplacementCharacter,  // 包含 U+FFFD（通常来自错误的编码转换）
+    ControlCharacter,      // 包含控制字符（换行、回车、制表符除外）
+}
+
+/// 内容损坏的笔记
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvalidContentNote {
+    pub note_id: String,
+    pub title: String,
+    pub issue: ContentIssue,  // 第一个问题的类型
+    pub byte_offset: usize,   // 第一个问题在内容中的字节偏移
+}
+
+/// 笔记与文件夹工作空间不一致的记录
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceFolderMismatch {
+    pub note_id: String,
+    pub note_title: String,
+    pub note_workspace_id: Option<String>,    // 笔记所属工作空间
+    pub folder_id: String,
+    pub folder_workspace_id: Option<String>,  // 文件夹所属工作空间
+}
+
+/// 父文件夹属于另一个工作空间的文件夹
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrossWorkspaceFolderParent {
+    pub folder_id: String,
+    pub folder_name: String,
+    pub folder_workspace_id: Option<String>,  // 文件夹所属工作空间
+    pub parent_id: String,
+    pub parent_workspace_id: Option<String>,  // 父文件夹所属工作空间
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_content_has_no_issue() {
+        assert_eq!(find_content_issue("正常内容\n\tline".as_bytes()), None);
+    }
+
+    #[test]
+    fn test_reports_first_issue_offset() {
+        assert_eq!(
+            find_content_issue("ab\u{FFFD}c".as_bytes()),
+            Some((ContentIssue::ReplacementCharacter, 2))
+        );
+        assert_eq!(
+            find_content_issue("中\u{0}".as_bytes()),
+            Some((ContentIssue::ControlCharacter, 3))
+        );
+        assert_eq!(
+            find_content_issue(&[b'a', b'b', 0xFF, b'c']),
+            Some((ContentIssue::InvalidUtf8, 2))
+        );
+    }
+
+    #[test]
+    fn test_finds_parent_cycles() {
+        let parents: BTreeMap<String, Option<String>> = [
+            ("a", Some("b")),
+            ("b", Some("c")),
+            ("c", Some("a")),
+            ("d", Some("a")),
+            ("e", Some("e")),
+            ("f", None),
+            ("g", Some("missing")),
+        ]
+        .into_iter()
+        .map(|(id, parent)| (id.to_string(), parent.map(str::to_string)))
+        .collect();
+
+        assert_eq!(
+            find_parent_cycles(&parents),
+            vec![vec!["a", "b", "c"], vec!["e"]]
+        );
+    }
+}
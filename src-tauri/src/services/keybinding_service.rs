@@ -1,5 +1,5 @@
 use crate::database::repositories::KeybindingRepository;
-use crate::models::{KeybindingsData, KeybindingPreset};
+use crate::models::{KeybindingsData, KeybindingPreset, KeyCombination, KeybindingConflict, KeybindingSaveResult};
 use crate::models::error::{Result, AppError};
 use std::collections::HashMap;
 
@@ -22,16 +22,32 @@ impl KeybindingService {
     }
 
     /// 保存快捷键配置
-    pub fn save_keybindings(&self, keybindings: HashMap<String, crate::models::KeyCombination>, presets: Vec<KeybindingPreset>) -> Result<()> {
+    ///
+    /// 多个命令绑定同一快捷键组合时不保存并返回冲突列表，`overwrite` 为 true 时忽略冲突直接保存
+    pub fn save_keybindings(
+        &self,
+        keybindings: HashMap<String, KeyCombination>,
+        presets: Vec<KeybindingPreset>,
+        overwrite: bool,
+    ) -> Result<KeybindingSaveResult> {
+        let conflicts = find_conflicts(&keybindings);
+        if !conflicts.is_empty() && !overwrite {
+            log::warn!("快捷键存在冲突，未保存: conflicts={}", conflicts.len());
+            return Ok(KeybindingSaveResult { saved: false, conflicts });
+        }
+
         let data = KeybindingsData {
             keybindings,
             presets,
         };
-        self.repo.save(&data)
+        self.repo.save(&data)?;
+        Ok(KeybindingSaveResult { saved: true, conflicts })
     }
 
     /// 导入快捷键配置
-    pub fn import_keybindings(&self, json_string: &str) -> Result<()> {
+    ///
+    /// 冲突处理与 `save_keybindings` 相同
+    pub fn import_keybindings(&self, json_string: &str, overwrite: bool) -> Result<KeybindingSaveResult> {
         // 解析导入的 JSON
         let value: serde_json::Value = serde_json::from_str(json_string)
             .map_err(|e| AppError::InvalidInput(format!("解析 JSON 失败: {}", e)))?;
@@ -46,7 +62,7 @@ impl KeybindingService {
         }
 
         // 解析 keybindings
-        let keybindings: HashMap<String, crate::models::KeyCombination> =
+        let keybindings: HashMap<String, KeyCombination> =
             serde_json::from_value(
                 value.get("keybindings")
                     .ok_or(AppError::InvalidInput("缺少快捷键字段".to_string()))?
@@ -61,9 +77,11 @@ impl KeybindingService {
             vec![]
         };
 
-        self.save_keybindings(keybindings, presets)?;
-        log::info!("快捷键导入成功");
-        Ok(())
+        let result = self.save_keybindings(keybindings, presets, overwrite)?;
+        if result.saved {
+            log::info!("快捷键导入成功");
+        }
+        Ok(result)
     }
 
     /// 重置为默认配置
@@ -73,3 +91,91 @@ impl KeybindingService {
         Ok(())
     }
 }
+
+/// 查找绑定到同一快捷键组合的命令
+///
+/// 结果按第一个命令 ID 排序，保证输出稳定
+fn find_conflicts(keybindings: &HashMap<String, KeyCombination>) -> Vec<KeybindingConflict> {
+    let mut by_combination: HashMap<&KeyCombination, Vec<String>> = HashMap::new();
+    for (action, combination) in keybindings {
+        by_combination.entry(combination).or_default().push(action.clone());
+    }
+
+    let mut conflicts: Vec<KeybindingConflict> = by_combination
+        .into_iter()
+        .filter(|(_, actions)| actions.len() > 1)
+        .map(|(combination, mut actions)| {
+            actions.sort();
+            KeybindingConflict { combination: combination.clone(), actions }
+        })
+        .collect();
+    conflicts.sort_by(|a, b| a.actions.cmp(&b.actions));
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::get_default_keybindings;
+
+    fn temp_service() -> KeybindingService {
+        let path = std::env::temp_dir().join(format!("keybindings-{}.json", uuid::Uuid::new_v4()));
+        KeybindingService::new(KeybindingRepository::new(path))
+    }
+
+    fn ctrl(key: &str) -> KeyCombination {
+        KeyCombination { ctrl: true, alt: false, shift: false, meta: false, key: key.to_string() }
+    }
+
+    fn import_json(keybindings: &HashMap<String, KeyCombination>) -> String {
+        serde_json::json!({ "version": "1.0", "keybindings": keybindings }).to_string()
+    }
+
+    #[test]
+    fn test_default_keybindings_have_no_conflicts() {
+        assert!(find_conflicts(&get_default_keybindings().keybindings).is_empty());
+    }
+
+    #[test]
+    fn test_import_with_duplicate_combination_is_rejected() {
+        let service = temp_service();
+        let defaults = get_default_keybindings().keybindings;
+        let mut keybindings = defaults.clone();
+        keybindings.insert("note.togglePreview".to_string(), defaults["note.save"].clone());
+
+        let result = service.import_keybindings(&import_json(&keybindings), false).unwrap();
+
+        assert!(!result.saved);
+        assert_eq!(result.conflicts, vec![KeybindingConflict {
+            combination: defaults["note.save"].clone(),
+            actions: vec!["note.save".to_string(), "note.togglePreview".to_string()],
+        }]);
+        assert_eq!(
+            service.load_keybindings().unwrap().keybindings["note.togglePreview"],
+            defaults["note.togglePreview"]
+        );
+    }
+
+    #[test]
+    fn test_import_with_overwrite_saves_despite_conflicts() {
+        let service = temp_service();
+        let defaults = get_default_keybindings().keybindings;
+        let mut keybindings = defaults.clone();
+        keybindings.insert("note.togglePreview".to_string(), defaults["note.save"].clone());
+
+        let result = service.import_keybindings(&import_json(&keybindings), true).unwrap();
+
+        assert!(result.saved);
+        assert_eq!(result.conflicts.len(), 1);
+        assert_eq!(service.load_keybindings().unwrap().keybindings["note.togglePreview"], defaults["note.save"]);
+    }
+
+    #[test]
+    fn test_combinations_differing_in_modifiers_do_not_conflict() {
+        let mut keybindings = HashMap::new();
+        keybindings.insert("a".to_string(), ctrl("KeyS"));
+        keybindings.insert("b".to_string(), KeyCombination { shift: true, ..ctrl("KeyS") });
+
+        assert!(find_conflicts(&keybindings).is_empty());
+    }
+}
@@ -0,0 +1,181 @@
+use serde::Serialize;
+
+/// 编辑距离上限（超过后不再精确计算，直接输出整体删除 + 整体新增）
+///
+/// Myers 算法回溯需要 O(D²) 内存，限制 D 避免超大改动占用过多内存
+const MAX_EDIT_DISTANCE: usize = 4000;
+
+/// 差异行类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DiffLineKind {
+    Equal,    // 两边相同
+    Added,    // 仅本地存在（相对服务器新增）
+    Removed,  // 仅服务器存在（相对服务器删除）
+}
+
+/// 单行差异
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffLine {
+    pub kind: DiffLineKind,
+    pub content: String,
+}
+
+/// 按行比较 `old` 与 `new`（Myers 差分算法）
+///
+/// 先去掉公共前后缀再计算中间部分，常见的局部修改只需很少的内存
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+
+    let prefix = old_lines.iter()
+        .zip(&new_lines)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old_lines[prefix..].iter().rev()
+        .zip(new_lines[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let old_mid = &old_lines[prefix..old_lines.len() - suffix];
+    let new_mid = &new_lines[prefix..new_lines.len() - suffix];
+
+    let mut result: Vec<DiffLine> = old_lines[..prefix].iter()
+        .map(|line| line_of(DiffLineKind::Equal, line))
+        .collect();
+    result.extend(myers(old_mid, new_mid));
+    result.extend(
+        old_lines[old_lines.len() - suffix..].iter()
+            .map(|line| line_of(DiffLineKind::Equal, line))
+    );
+
+    result
+}
+
+fn line_of(kind: DiffLineKind, content: &str) -> DiffLine {
+    DiffLine { kind, content: content.to_string() }
+}
+
+/// 整体替换（旧内容全部删除，新内容全部新增）
+fn replace_all(old: &[&str], new: &[&str]) -> Vec<DiffLine> {
+    old.iter().map(|line| line_of(DiffLineKind::Removed, line))
+        .chain(new.iter().map(|line| line_of(DiffLineKind::Added, line)))
+        .collect()
+}
+
+fn myers(old: &[&str], new: &[&str]) -> Vec<DiffLine> {
+    let n = old.len() as isize;
+    let m = new.len() as isize;
+    if n == 0 || m == 0 {
+        return replace_all(old, new);
+    }
+
+    let max = (n + m) as usize;
+    let offset = max as isize;
+    let mut v = vec![0isize; 2 * max + 2];
+    // trace[d] 保存第 d 步开始前 k ∈ [-d, d] 的 V 值，用于回溯
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    let mut found = false;
+    'outer: for d in 0..=max.min(MAX_EDIT_DISTANCE) as isize {
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+
+        let mut k = -d;
+        while k <= d {
+            let idx = (offset + k) as usize;
+            let mut x = if k == -d || (k != d && v[idx - 1] < v[idx + 1]) {
+                v[idx + 1]
+            } else {
+                v[idx - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[idx] = x;
+
+            if x >= n && y >= m {
+                found = true;
+                break 'outer;
+            }
+            k += 2;
+        }
+    }
+
+    if !found {
+        return replace_all(old, new);
+    }
+
+    // 从终点回溯出编辑路径
+    let mut lines = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let get = |k: isize| v[(k + d) as usize];
+        let k = x - y;
+
+        let prev_k = if k == -d || (k != d && get(k - 1) < get(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = if d == 0 { 0 } else { get(prev_k) };
+        let prev_y = prev_x - prev_k;
+
+        while x > prev_x && y > prev_y {
+            lines.push(line_of(DiffLineKind::Equal, old[(x - 1) as usize]));
+            x -= 1;
+            y -= 1;
+        }
+
+        if d > 0 {
+            if x == prev_x {
+                lines.push(line_of(DiffLineKind::Added, new[(y - 1) as usize]));
+            } else {
+                lines.push(line_of(DiffLineKind::Removed, old[(x - 1) as usize]));
+            }
+        }
+
+        x = prev_x;
+        y = prev_y;
+    }
+
+    lines.reverse();
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(lines: &[DiffLine]) -> Vec<String> {
+        lines.iter().map(|l| {
+            let sign = match l.kind {
+                DiffLineKind::Equal => ' ',
+                DiffLineKind::Added => '+',
+                DiffLineKind::Removed => '-',
+            };
+            format!("{}{}", sign, l.content)
+        }).collect()
+    }
+
+    #[test]
+    fn diffs_changed_lines() {
+        let diff = diff_lines("a\nb\nc\nd", "a\nx\nc\nd\ne");
+        assert_eq!(render(&diff), vec![" a", "-b", "+x", " c", " d", "+e"]);
+    }
+
+    #[test]
+    fn empty_old_is_fully_added() {
+        let diff = diff_lines("", "one\ntwo");
+        assert_eq!(render(&diff), vec!["+one", "+two"]);
+    }
+
+    #[test]
+    fn identical_text_has_no_changes() {
+        let diff = diff_lines("same\ntext", "same\ntext");
+        assert!(diff.iter().all(|l| l.kind == DiffLineKind::Equal));
+    }
+}
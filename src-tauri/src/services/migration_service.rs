@@ -0,0 +1,557 @@
+use crate::database::{DbPool, schema};
+use crate::models::error::{Result, AppError};
+use r2d2_sqlite::rusqlite::{params, Connection};
+use serde::Serialize;
+
+/// 单个数据库迁移
+///
+/// `up` 必须是幂等的：即使列/索引已存在（例如旧版本通过其他方式添加过）也不能失败
+struct Migration {
+    version: i64,
+    description: &'static str,
+    /// 重建表的迁移需要在执行期间关闭外键约束，否则删除旧表会级联删除引用它的数据
+    disable_foreign_keys: bool,
+    up: fn(&Connection) -> Result<()>,
+}
+
+/// 按版本号升序排列的迁移列表
+///
+/// ⚠️ 只能在末尾追加新迁移，已发布的迁移不能修改或调整顺序
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "app_settings 添加 excerpt_length 列",
+        disable_foreign_keys: false,
+        up: |conn| {
+            schema::add_column_if_missing(conn, "app_settings", "excerpt_length", "INTEGER DEFAULT 200")
+                .map_err(|e| AppError::DatabaseError(e.to_string()))
+        },
+    },
+    Migration {
+        version: 2,
+        description: "app_settings 添加 max_note_length 列",
+        disable_foreign_keys: false,
+        up: |conn| {
+            schema::add_column_if_missing(conn, "app_settings", "max_note_length", "INTEGER DEFAULT 0")
+                .map_err(|e| AppError::DatabaseError(e.to_string()))
+        },
+    },
+    Migration {
+        version: 3,
+        description: "添加 delete_events 表记录 is_deleted 变化",
+        disable_foreign_keys: false,
+        up: |conn| {
+            // 用触发器记录，本地操作和同步写入的变化都能被捕获
+            // 同时记录变化时的 server_ver，同步写入的变化通常伴随版本号变化
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS delete_events (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    entity_type TEXT NOT NULL,
+                    entity_id TEXT NOT NULL,
+                    workspace_id TEXT,
+                    is_deleted BOOLEAN NOT NULL,
+                    server_ver INTEGER,
+                    occurred_at INTEGER NOT NULL
+                );
+
+                CREATE INDEX IF NOT EXISTS idx_delete_events_workspace ON delete_events(workspace_id, occurred_at);
+
+                CREATE TRIGGER IF NOT EXISTS notes_delete_events AFTER UPDATE OF is_deleted ON notes
+                WHEN OLD.is_deleted IS NOT NEW.is_deleted
+                BEGIN
+                    INSERT INTO delete_events (entity_type, entity_id, workspace_id, is_deleted, server_ver, occurred_at)
+                    VALUES ('note', NEW.id, NEW.workspace_id, NEW.is_deleted, NEW.server_ver, strftime('%s', 'now'));
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS folders_delete_events AFTER UPDATE OF is_deleted ON folders
+                WHEN OLD.is_deleted IS NOT NEW.is_deleted
+                BEGIN
+                    INSERT INTO delete_events (entity_type, entity_id, workspace_id, is_deleted, server_ver, occurred_at)
+                    VALUES ('folder', NEW.id, NEW.workspace_id, NEW.is_deleted, NEW.server_ver, strftime('%s', 'now'));
+                END;
+
+                CREATE TRIGGER IF NOT EXISTS tags_delete_events AFTER UPDATE OF is_deleted ON tags
+                WHEN OLD.is_deleted IS NOT NEW.is_deleted
+                BEGIN
+                    INSERT INTO delete_events (entity_type, entity_id, workspace_id, is_deleted, server_ver, occurred_at)
+                    VALUES ('tag', NEW.id, NEW.workspace_id, NEW.is_deleted, NEW.server_ver, strftime('%s', 'now'));
+                END;
+                "
+            ).map_err(AppError::Database)
+        },
+    },
+    Migration {
+        version: 4,
+        description: "app_settings 添加 conflict_strategies 列",
+        disable_foreign_keys: false,
+        up: |conn| {
+            schema::add_column_if_missing(conn, "app_settings", "conflict_strategies", "TEXT")
+                .map_err(|e| AppError::DatabaseError(e.to_string()))
+        },
+    },
+    Migration {
+        version: 5,
+        description: "notes 添加 pinned_at 列",
+        disable_foreign_keys: false,
+        up: |conn| {
+            schema::add_column_if_missing(conn, "notes", "pinned_at", "INTEGER")
+                .map_err(|e| AppError::DatabaseError(e.to_string()))
+        },
+    },
+    Migration {
+        version: 6,
+        description: "notes 添加 is_private 列",
+        disable_foreign_keys: false,
+        up: |conn| {
+            schema::add_column_if_missing(conn, "notes", "is_private", "BOOLEAN DEFAULT 0")
+                .map_err(|e| AppError::DatabaseError(e.to_string()))
+        },
+    },
+    Migration {
+        version: 7,
+        description: "添加 undo_log 表记录可撤销的批量操作",
+        disable_foreign_keys: false,
+        up: |conn| {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS undo_log (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    operation_id TEXT NOT NULL,
+                    operation_type TEXT NOT NULL,
+                    entity_id TEXT NOT NULL,
+                    previous_value TEXT,
+                    created_at INTEGER NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS idx_undo_log_operation ON undo_log(operation_type, operation_id);
+                "
+            ).map_err(AppError::Database)
+        },
+    },
+    Migration {
+        version: 8,
+        description: "app_settings 添加 tag_palette 列",
+        disable_foreign_keys: false,
+        up: |conn| {
+            schema::add_column_if_missing(conn, "app_settings", "tag_palette", "TEXT")
+                .map_err(|e| AppError::DatabaseError(e.to_string()))
+        },
+    },
+    Migration {
+        version: 9,
+        description: "app_settings 添加 max_synced_snapshot_bytes 列",
+        disable_foreign_keys: false,
+        up: |conn| {
+            schema::add_column_if_missing(conn, "app_settings", "max_synced_snapshot_bytes", "INTEGER DEFAULT 0")
+                .map_err(|e| AppError::DatabaseError(e.to_string()))
+        },
+    },
+    Migration {
+        version: 10,
+        description: "app_settings 添加省电 / 按流量计费网络下暂停自动同步的设置",
+        disable_foreign_keys: false,
+        up: |conn| {
+            for (column, definition) in [
+                ("pause_sync_on_low_battery", "BOOLEAN DEFAULT 0"),
+                ("low_battery_threshold", "INTEGER DEFAULT 20"),
+                ("pause_sync_on_metered", "BOOLEAN DEFAULT 0"),
+            ] {
+                schema::add_column_if_missing(conn, "app_settings", column, definition)
+                    .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+            }
+            Ok(())
+        },
+    },
+    Migration {
+        version: 11,
+        description: "添加 sync_queue 单项同步重试队列",
+        disable_foreign_keys: false,
+        up: |conn| {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS sync_queue (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    user_id TEXT,
+                    entity_type TEXT NOT NULL,
+                    entity_id TEXT NOT NULL,
+                    status TEXT NOT NULL DEFAULT 'pending',
+                    attempts INTEGER NOT NULL DEFAULT 0,
+                    last_error TEXT,
+                    next_attempt_at INTEGER NOT NULL,
+                    created_at INTEGER NOT NULL,
+                    updated_at INTEGER NOT NULL,
+                    UNIQUE(entity_type, entity_id)
+                );
+                CREATE INDEX IF NOT EXISTS idx_sync_queue_due ON sync_queue(user_id, status, next_attempt_at);
+                "
+            ).map_err(AppError::Database)?;
+
+            schema::add_column_if_missing(conn, "app_settings", "max_sync_retries", "INTEGER DEFAULT 5")
+                .map_err(|e| AppError::DatabaseError(e.to_string()))
+        },
+    },
+    Migration {
+        version: 12,
+        description: "添加 workspace_checkpoints 表（工作空间检查点）",
+        disable_foreign_keys: false,
+        up: |conn| {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS workspace_checkpoints (
+                    id TEXT PRIMARY KEY,
+                    workspace_id TEXT NOT NULL,
+                    name TEXT NOT NULL,
+                    note_count INTEGER NOT NULL DEFAULT 0,
+                    folder_count INTEGER NOT NULL DEFAULT 0,
+                    tag_count INTEGER NOT NULL DEFAULT 0,
+                    size_bytes INTEGER NOT NULL DEFAULT 0,
+                    data BLOB NOT NULL,
+                    created_at INTEGER NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS idx_workspace_checkpoints_workspace ON workspace_checkpoints(workspace_id, created_at);
+                "
+            ).map_err(AppError::Database)
+        },
+    },
+    Migration {
+        version: 13,
+        description: "note_tags 添加 is_dirty 列（只同步变化的标签关联）",
+        disable_foreign_keys: false,
+        up: |conn| {
+            // 已有关联默认为脏，升级后的第一次同步会完整推送一次
+            schema::add_column_if_missing(conn, "note_tags", "is_dirty", "BOOLEAN DEFAULT 1")
+                .map_err(|e| AppError::DatabaseError(e.to_string()))
+        },
+    },
+    Migration {
+        version: 14,
+        description: "app_settings 添加同步请求重试设置",
+        disable_foreign_keys: false,
+        up: |conn| {
+            for (column, definition) in [
+                ("sync_request_max_attempts", "INTEGER DEFAULT 3"),
+                ("sync_retry_base_delay_ms", "INTEGER DEFAULT 500"),
+            ] {
+                schema::add_column_if_missing(conn, "app_settings", column, definition)
+                    .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+            }
+            Ok(())
+        },
+    },
+    Migration {
+        version: 15,
+        description: "app_settings 添加 sync_compression_threshold_bytes 列",
+        disable_foreign_keys: false,
+        up: |conn| {
+            schema::add_column_if_missing(conn, "app_settings", "sync_compression_threshold_bytes", "INTEGER DEFAULT 32768")
+                .map_err(|e| AppError::DatabaseError(e.to_string()))
+        },
+    },
+    Migration {
+        version: 16,
+        description: "添加 sync_conflicts 表记录笔记冲突",
+        disable_foreign_keys: false,
+        up: |conn| {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS sync_conflicts (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    note_id TEXT NOT NULL,
+                    copy_id TEXT,
+                    title TEXT NOT NULL,
+                    local_version INTEGER NOT NULL,
+                    server_version INTEGER NOT NULL,
+                    local_content TEXT,
+                    server_content TEXT,
+                    local_updated_at INTEGER,
+                    server_updated_at INTEGER,
+                    created_at INTEGER NOT NULL,
+                    resolved_at INTEGER
+                );
+                CREATE INDEX IF NOT EXISTS idx_sync_conflicts_note ON sync_conflicts(note_id, resolved_at);
+                "
+            ).map_err(AppError::Database)
+        },
+    },
+    Migration {
+        version: 17,
+        description: "app_settings 添加 token_refresh_window_minutes 列",
+        disable_foreign_keys: false,
+        up: |conn| {
+            schema::add_column_if_missing(conn, "app_settings", "token_refresh_window_minutes", "INTEGER DEFAULT 10")
+                .map_err(|e| AppError::DatabaseError(e.to_string()))
+        },
+    },
+    Migration {
+        version: 18,
+        description: "app_settings 添加回收站保留天数设置（支持按工作空间覆盖）",
+        disable_foreign_keys: false,
+        up: |conn| {
+            for (column, definition) in [
+                ("trash_retention_days", "INTEGER DEFAULT 30"),
+                ("workspace_trash_retention", "TEXT"),
+            ] {
+                schema::add_column_if_missing(conn, "app_settings", column, definition)
+                    .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+            }
+            Ok(())
+        },
+    },
+    Migration {
+        version: 19,
+        description: "notes 添加 pin_sort_order 列（置顶笔记排序，已置顶的按置顶时间从新到旧）",
+        disable_foreign_keys: false,
+        up: |conn| {
+            schema::add_column_if_missing(conn, "notes", "pin_sort_order", "INTEGER")
+                .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+            conn.execute(
+                "UPDATE notes SET pin_sort_order = -COALESCE(pinned_at, updated_at)
+                 WHERE is_pinned = 1 AND pin_sort_order IS NULL",
+                [],
+            )?;
+            Ok(())
+        },
+    },
+    Migration {
+        version: 20,
+        description: "editor_settings 支持多个命名配置（原有设置作为默认配置并激活）",
+        disable_foreign_keys: false,
+        up: |conn| {
+            for (column, definition) in [
+                ("name", "TEXT NOT NULL DEFAULT '默认'"),
+                ("is_active", "BOOLEAN NOT NULL DEFAULT 0"),
+            ] {
+                schema::add_column_if_missing(conn, "editor_settings", column, definition)
+                    .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+            }
+            conn.execute(
+                "UPDATE editor_settings SET is_active = 1
+                 WHERE id = 1 AND NOT EXISTS (SELECT 1 FROM editor_settings WHERE is_active = 1)",
+                [],
+            )?;
+            Ok(())
+        },
+    },
+    Migration {
+        version: 21,
+        description: "添加 attachments 表（笔记附件）",
+        disable_foreign_keys: false,
+        up: |conn| {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS attachments (
+                    id TEXT PRIMARY KEY,
+                    note_id TEXT NOT NULL,
+                    workspace_id TEXT,
+                    filename TEXT NOT NULL,
+                    mime_type TEXT NOT NULL,
+                    size_bytes INTEGER NOT NULL DEFAULT 0,
+                    data TEXT NOT NULL,
+                    created_at INTEGER NOT NULL,
+                    updated_at INTEGER NOT NULL,
+                    is_deleted BOOLEAN DEFAULT 0,
+                    deleted_at INTEGER,
+                    server_ver INTEGER DEFAULT 0,
+                    is_dirty BOOLEAN DEFAULT 1,
+                    last_synced_at INTEGER,
+                    FOREIGN KEY (note_id) REFERENCES notes(id) ON DELETE CASCADE
+                );
+                CREATE INDEX IF NOT EXISTS idx_attachments_note_id ON attachments(note_id);
+                CREATE INDEX IF NOT EXISTS idx_attachments_workspace_id ON attachments(workspace_id);
+                "
+            ).map_err(AppError::Database)
+        },
+    },
+    Migration {
+        version: 22,
+        description: "tags 去掉 name 全局唯一约束（同名标签可以存在于不同工作空间）",
+        disable_foreign_keys: true,
+        up: |conn| {
+            // SQLite 不支持删除约束，只能重建表；执行期间关闭外键，删除旧表不会级联删除 note_tags
+            conn.execute_batch(
+                "DROP TABLE IF EXISTS tags_new;
+                CREATE TABLE tags_new (
+                    id TEXT PRIMARY KEY,
+                    name TEXT NOT NULL,
+                    color TEXT,
+                    workspace_id TEXT,
+                    created_at INTEGER NOT NULL,
+                    updated_at INTEGER NOT NULL,
+                    is_deleted BOOLEAN DEFAULT 0,
+                    deleted_at INTEGER,
+                    server_ver INTEGER DEFAULT 0,
+                    is_dirty BOOLEAN DEFAULT 0,
+                    last_synced_at INTEGER
+                );
+                INSERT INTO tags_new (id, name, color, workspace_id, created_at, updated_at,
+                                      is_deleted, deleted_at, server_ver, is_dirty, last_synced_at)
+                SELECT id, name, color, workspace_id, created_at, updated_at,
+                       is_deleted, deleted_at, server_ver, is_dirty, last_synced_at
+                FROM tags;
+                DROP TABLE tags;
+                ALTER TABLE tags_new RENAME TO tags;
+
+                CREATE INDEX IF NOT EXISTS idx_tags_workspace_id ON tags(workspace_id);
+                CREATE INDEX IF NOT EXISTS idx_tags_is_deleted ON tags(is_deleted);
+                CREATE INDEX IF NOT EXISTS idx_tags_workspace_name ON tags(workspace_id, name);
+
+                CREATE TRIGGER IF NOT EXISTS tags_delete_events AFTER UPDATE OF is_deleted ON tags
+                WHEN OLD.is_deleted IS NOT NEW.is_deleted
+                BEGIN
+                    INSERT INTO delete_events (entity_type, entity_id, workspace_id, is_deleted, server_ver, occurred_at)
+                    VALUES ('tag', NEW.id, NEW.workspace_id, NEW.is_deleted, NEW.server_ver, strftime('%s', 'now'));
+                END;
+                "
+            ).map_err(AppError::Database)
+        },
+    },
+];
+
+/// 数据库迁移服务
+///
+/// 通过 `schema_version` 表记录已执行的迁移，应用启动时按顺序执行未应用的迁移
+///
+/// ## 执行策略
+///
+/// - **有序**：按版本号从小到大依次执行
+/// - **原子**：每个迁移和它的版本记录在同一事务中提交
+/// - **幂等**：已记录的版本会被跳过，迁移本身也可重复执行
+#[derive(Clone)]
+pub struct MigrationService {
+    pool: DbPool,
+}
+
+impl MigrationService {
+    /// 创建新的 MigrationService 实例
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// 获取当前数据库的 schema 版本（未执行过任何迁移时为 0）
+    pub fn current_version(&self) -> Result<i64> {
+        let conn = self.pool.get()?;
+
+        let version = conn.query_row(
+            "SELECT COALESCE(MAX(version), 0) FROM schema_version",
+            [],
+            |row| row.get(0),
+        ).map_err(AppError::Database)?;
+
+        Ok(version)
+    }
+
+    /// 代码中定义的最新 schema 版本
+    pub fn latest_version() -> i64 {
+        MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+    }
+
+    /// 执行所有未应用的迁移
+    ///
+    /// 返回迁移后的 schema 版本
+    pub fn migrate_to_latest(&self) -> Result<i64> {
+        let current = self.current_version()?;
+        let latest = Self::latest_version();
+
+        if current >= latest {
+            log::info!("[MigrationService] schema 已是最新版本: version={}", current);
+            return Ok(current);
+        }
+
+        log::info!("[MigrationService] 开始迁移: {} -> {}", current, latest);
+
+        let conn = self.pool.get()?;
+
+        for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+            // PRAGMA foreign_keys 在事务中不生效，必须在事务开始前关闭、结束后恢复
+            let restore_foreign_keys = migration.disable_foreign_keys
+                && conn.query_row("PRAGMA foreign_keys", [], |row| row.get::<_, bool>(0)).map_err(AppError::Database)?;
+            if restore_foreign_keys {
+                conn.execute_batch("PRAGMA foreign_keys = OFF").map_err(AppError::Database)?;
+            }
+
+            let result = Self::apply_migration(&conn, migration);
+
+            if restore_foreign_keys {
+                conn.execute_batch("PRAGMA foreign_keys = ON").map_err(AppError::Database)?;
+            }
+            result?;
+
+            log::info!(
+                "[MigrationService] 已应用迁移: version={}, description={}",
+                migration.version,
+                migration.description
+            );
+        }
+
+        Ok(latest)
+    }
+
+    /// 在事务中执行单个迁移并记录版本
+    fn apply_migration(conn: &Connection, migration: &Migration) -> Result<()> {
+        let tx = conn.unchecked_transaction().map_err(AppError::Database)?;
+
+        (migration.up)(&tx).map_err(|e| {
+            log::error!(
+                "[MigrationService] 迁移失败: version={}, description={}, error={}",
+                migration.version,
+                migration.description,
+                e
+            );
+            e
+        })?;
+
+        tx.execute(
+            "INSERT OR REPLACE INTO schema_version (version, description, applied_at) VALUES (?, ?, ?)",
+            params![migration.version, migration.description, chrono::Utc::now().timestamp()],
+        ).map_err(AppError::Database)?;
+
+        tx.commit().map_err(AppError::Database)
+    }
+
+    /// 获取 schema 版本信息
+    pub fn get_schema_version(&self) -> Result<SchemaVersionInfo> {
+        let current_version = self.current_version()?;
+        let latest_version = Self::latest_version();
+
+        Ok(SchemaVersionInfo {
+            current_version,
+            latest_version,
+            needs_migration: current_version < latest_version,
+        })
+    }
+}
+
+/// schema 版本信息
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaVersionInfo {
+    pub current_version: i64,  // 数据库当前版本
+    pub latest_version: i64,   // 代码中的最新版本
+    pub needs_migration: bool, // 是否存在未应用的迁移
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rebuilding_tags_keeps_note_tags() {
+        let pool = crate::database::memory_pool();
+        let service = MigrationService::new(pool.clone());
+        {
+            let conn = pool.get().unwrap();
+            assert!(conn.query_row("PRAGMA foreign_keys", [], |row| row.get::<_, bool>(0)).unwrap());
+            conn.execute_batch(
+                "INSERT INTO notes (id, title, content, created_at, updated_at) VALUES ('note-1', 't', 'c', 0, 0);
+                 INSERT INTO tags (id, name, created_at, updated_at) VALUES ('tag-1', '工作', 0, 0);
+                 INSERT INTO note_tags (note_id, tag_id, created_at) VALUES ('note-1', 'tag-1', 0);
+                 DELETE FROM schema_version WHERE version = 22;"
+            ).unwrap();
+        }
+
+        // 模拟从版本 21 升级
+        assert_eq!(service.migrate_to_latest().unwrap(), 22);
+
+        let conn = pool.get().unwrap();
+        let links: i64 = conn.query_row("SELECT COUNT(*) FROM note_tags WHERE tag_id = 'tag-1'", [], |row| row.get(0)).unwrap();
+        assert_eq!(links, 1);
+        let tags: i64 = conn.query_row("SELECT COUNT(*) FROM tags", [], |row| row.get(0)).unwrap();
+        assert_eq!(tags, 1);
+        // 迁移结束后恢复外键约束
+        assert!(conn.query_row("PRAGMA foreign_keys", [], |row| row.get::<_, bool>(0)).unwrap());
+    }
+}
@@ -0,0 +1,69 @@
+//! 测试用的本地 HTTP 服务器
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+/// 服务器收到的请求
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub method: String,
+    pub path: String,
+    pub body: String,
+}
+
+/// 启动一个本地 HTTP 服务器，返回服务器地址（如 `http://127.0.0.1:12345`）
+///
+/// 每个请求交给 `handler` 生成 (状态码, JSON 响应体)，服务器线程随测试进程结束
+pub fn serve<F>(mut handler: F) -> String
+where
+    F: FnMut(&MockRequest) -> (u16, String) + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = format!("http://{}", listener.local_addr().unwrap());
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let request = read_request(&mut stream);
+            let (status, body) = handler(&request);
+
+            let response = format!(
+                "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status, body.len(), body
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+
+    addr
+}
+
+/// 读完请求头和请求体，避免客户端收到连接重置
+fn read_request(stream: &mut TcpStream) -> MockRequest {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 4096];
+    let body = loop {
+        let n = stream.read(&mut buf).unwrap();
+        request.extend_from_slice(&buf[..n]);
+        let text = String::from_utf8_lossy(&request);
+        if let Some(header_end) = text.find("\r\n\r\n") {
+            let content_length = text[..header_end].lines()
+                .find_map(|l| l.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                .unwrap_or(0);
+            if request.len() >= header_end + 4 + content_length {
+                break text[header_end + 4..].to_string();
+            }
+        }
+        if n == 0 {
+            break String::new();
+        }
+    };
+
+    let text = String::from_utf8_lossy(&request);
+    let mut request_line = text.split_whitespace();
+    MockRequest {
+        method: request_line.next().unwrap_or_default().to_string(),
+        path: request_line.next().unwrap_or_default().to_string(),
+        body,
+    }
+}
@@ -5,16 +5,29 @@ pub mod editor_settings_service;
 pub mod tag_service;
 pub mod workspace_service;
 pub mod cleanup_service;
+pub mod migration_service;
+pub mod integrity_service;
 // ===== 云端同步相关服务 =====
 pub mod sync_service;
+pub mod sync_events;
+#[cfg(test)]
+pub mod mock_http;
 pub mod single_sync_service;
 pub mod auto_sync_service;
+pub mod sync_history_service;
+pub mod sync_queue_service;
 pub mod auth_service;
 pub mod device_identifier_service;
 pub mod crypto;
+pub mod zip_writer;
+pub mod zip_reader;
+pub mod power_state;
+pub mod line_diff;
 pub mod snapshot_service;
+pub mod attachment_service;
 pub mod user_profile_service;
 pub mod app_settings_service;
+pub mod support_bundle_service;
 
 pub use note_service::NoteService;
 pub use folder_service::FolderService;
@@ -23,13 +36,19 @@ pub use editor_settings_service::EditorSettingsService;
 pub use tag_service::TagService;
 pub use workspace_service::WorkspaceService;
 pub use cleanup_service::CleanupService;
+pub use migration_service::MigrationService;
+pub use integrity_service::IntegrityService;
 // ===== 云端同步服务导出 =====
 pub use sync_service::SyncService;
 pub use single_sync_service::SingleSyncService;
 pub use auto_sync_service::AutoSyncService;
+pub use sync_history_service::SyncHistoryService;
+pub use sync_queue_service::SyncQueueService;
 pub use auth_service::AuthService;
 pub use device_identifier_service::DeviceIdentifierService;
 pub use crypto::CryptoService;
 pub use snapshot_service::SnapshotService;
+pub use attachment_service::AttachmentService;
 pub use user_profile_service::UserProfileService;
+pub use support_bundle_service::SupportBundleService;
 pub use app_settings_service::AppSettingsService;
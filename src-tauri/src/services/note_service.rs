@@ -1,7 +1,90 @@
-use crate::database::repositories::NoteRepository;
-use crate::database::repositories::FolderRepository;
-use crate::models::{Note, Folder, CreateNoteRequest, UpdateNoteRequest, MoveNotesRequest};
+use crate::database::repositories::{NoteRepository, note_repository::NoteActivity};
+use crate::database::repositories::{FolderRepository, TagRepository, folder_repository::FolderNode};
+use crate::services::{zip_reader, zip_writer::ZipWriter};
+use crate::services::AppSettingsService;
+use crate::models::{Note, Folder, NoteTagRequest, CreateNoteRequest, UpdateNoteRequest, MoveNotesRequest, RankedNote, NoteCursor, NotePage};
 use crate::models::error::{Result, AppError};
+use serde::Serialize;
+use chrono::{Duration, Local, NaiveDate, SecondsFormat, TimeZone, Utc};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// 复制笔记时追加到标题后的标记
+const DUPLICATE_TITLE_SUFFIX: &str = " (副本)";
+
+/// 重新编号结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenumberedNote {
+    pub note_id: String,
+    pub old_title: String,
+    pub new_title: String,
+}
+
+/// 单日写作统计
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyWritingStats {
+    pub date: String,  // 本地日期（YYYY-MM-DD）
+    pub notes_created: usize,  // 当天新建的笔记数量
+    pub words_written: u32,  // 当天新增的字数
+}
+
+/// 编辑最频繁的笔记
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MostEditedNote {
+    pub note_id: String,
+    pub title: String,
+    pub edit_count: usize,  // 窗口内记录到的版本数（快照 + 最近一次修改）
+    pub last_edited_at: i64,
+}
+
+/// 工作空间写作统计
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WritingStats {
+    pub days: i64,
+    pub daily: Vec<DailyWritingStats>,  // 按日期升序，包含没有活动的日期
+    pub total_notes_created: usize,
+    pub total_words_written: u32,
+    pub most_edited: Vec<MostEditedNote>,
+}
+
+/// 写作统计中返回的最常编辑笔记数量
+const MOST_EDITED_LIMIT: usize = 10;
+
+/// 撤销批量移动的结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UndoMoveResult {
+    pub operation_id: String,  // 被撤销的操作 ID
+    pub restored: usize,  // 恢复到原文件夹的笔记数量
+}
+
+/// 工作空间导出结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceExportResult {
+    pub path: String,  // 归档文件路径
+    pub note_count: usize,  // 导出的笔记数量
+    pub folder_count: usize,  // 导出的文件夹数量
+}
+
+/// Markdown 导入结果
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSummary {
+    pub notes_created: usize,  // 新建的笔记数量
+    pub notes_skipped: usize,  // 跳过的笔记数量（已存在相同笔记或内容超过长度上限）
+    pub folders_created: usize,  // 新建的文件夹数量
+    pub tags_created: usize,  // 新建的标签数量
+}
+
+/// 可撤销的批量移动操作最多保留的数量
+const MAX_UNDO_MOVES: usize = 5;
 
 /// 笔记业务逻辑层
 ///
@@ -10,20 +93,49 @@ use crate::models::error::{Result, AppError};
 pub struct NoteService {
     repo: NoteRepository,
     folder_repo: FolderRepository,  // 用于恢复笔记时创建/获取"已恢复笔记"文件夹
+    tag_repo: TagRepository,  // 用于导出笔记时读取标签
+    app_settings: AppSettingsService,  // 用于读取摘要长度等笔记相关设置
 }
 
 impl NoteService {
     /// 创建新的 NoteService 实例
-    pub fn new(repo: NoteRepository, folder_repo: FolderRepository) -> Self {
-        Self { repo, folder_repo }
+    pub fn new(
+        repo: NoteRepository,
+        folder_repo: FolderRepository,
+        tag_repo: TagRepository,
+        app_settings: AppSettingsService,
+    ) -> Self {
+        Self { repo, folder_repo, tag_repo, app_settings }
     }
 
     /// 创建笔记
     pub fn create_note(&self, req: CreateNoteRequest) -> Result<Note> {
-        let note = Note::new(req.title, req.content, req.folder_id);
+        self.check_content_length(&req.content)?;
+        let mut note = Note::new(req.title, req.content, req.folder_id);
+        note.refresh_excerpt(self.app_settings.get_excerpt_length()?);
         self.repo.create(&note)
     }
 
+    /// 检查笔记内容是否超过设置的长度上限
+    ///
+    /// 按 Unicode 字符计数而不是字节，中文内容不会因为 UTF-8 编码更长而被误判
+    fn check_content_length(&self, content: &str) -> Result<()> {
+        let max_length = self.app_settings.get_max_note_length()?;
+        if max_length == 0 {
+            return Ok(());
+        }
+
+        let length = content.chars().count();
+        if length > max_length {
+            return Err(AppError::Validation(format!(
+                "笔记内容过长: {} 字符，上限为 {} 字符",
+                length, max_length
+            )));
+        }
+
+        Ok(())
+    }
+
     /// 根据 ID 获取笔记
     pub fn get_note_by_id(&self, id: &str) -> Result<Note> {
         self.repo.find_by_id(id)?
@@ -38,7 +150,9 @@ impl NoteService {
             note.title = title;
         }
         if let Some(content) = req.content {
+            self.check_content_length(&content)?;
             note.update_content(content);
+            note.refresh_excerpt(self.app_settings.get_excerpt_length()?);
         }
         if let Some(folder_id) = req.folder_id {
             note.folder_id = Some(folder_id);
@@ -171,6 +285,14 @@ impl NoteService {
         self.repo.find_all()
     }
 
+    /// 分页获取笔记（键集游标，`cursor` 为空时从第一页开始）
+    pub fn list_notes_page(&self, limit: u32, cursor: Option<&NoteCursor>) -> Result<NotePage> {
+        if limit == 0 {
+            return Err(AppError::InvalidInput("limit 必须大于 0".to_string()));
+        }
+        self.repo.find_page(limit, cursor)
+    }
+
     /// 获取所有已删除的笔记（回收站）
     ///
     /// ## 返回
@@ -180,6 +302,13 @@ impl NoteService {
         self.repo.find_deleted()
     }
 
+    /// 分页获取收藏的笔记（跨文件夹），按更新时间倒序
+    ///
+    /// `workspace_id` 为 `None` 时使用当前工作空间
+    pub fn list_favorite_notes(&self, workspace_id: Option<&str>, limit: u32, offset: u32) -> Result<Vec<Note>> {
+        self.repo.find_favorites(workspace_id, limit, offset)
+    }
+
     /// 搜索笔记
     pub fn search_notes(&self, query: &str) -> Result<Vec<Note>> {
         if query.trim().is_empty() {
@@ -188,6 +317,38 @@ impl NoteService {
         self.repo.search(query)
     }
 
+    /// 按相关度搜索笔记（返回高亮标题和内容片段）
+    pub fn search_notes_ranked(
+        &self,
+        query: &str,
+        workspace_id: Option<&str>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<RankedNote>> {
+        if query.trim().is_empty() {
+            return Ok(vec![]);
+        }
+        self.repo.search_notes_ranked(query, workspace_id, limit, offset)
+    }
+
+    /// 跨工作空间搜索笔记
+    ///
+    /// 默认不返回私密笔记；私密笔记在所属工作空间内仍可通过 `search_notes` 搜到
+    pub fn search_global(&self, query: &str, include_private: bool) -> Result<Vec<Note>> {
+        if query.trim().is_empty() {
+            return Ok(vec![]);
+        }
+        self.repo.search_global(query, include_private)
+    }
+
+    /// 设置笔记是否私密（私密笔记不出现在全局搜索中）
+    pub fn set_private(&self, note_id: &str, private: bool) -> Result<Note> {
+        if !self.repo.set_private(note_id, private)? {
+            return Err(AppError::NoteNotFound(note_id.to_string()));
+        }
+        self.get_note_by_id(note_id)
+    }
+
     /// 批量移动笔记到文件夹
     pub fn move_notes_to_folder(&self, req: MoveNotesRequest) -> Result<Vec<Note>> {
         let mut moved_notes = Vec::new();
@@ -210,6 +371,192 @@ impl NoteService {
         Ok(moved_notes)
     }
 
+    /// 移动单篇笔记到文件夹
+    ///
+    /// 目标文件夹必须存在、未删除，且与笔记属于同一工作空间；
+    /// `target_folder_id` 为 `None` 时移动到根目录
+    pub fn move_note(&self, note_id: &str, target_folder_id: Option<&str>) -> Result<Note> {
+        let mut note = self.get_note_by_id(note_id)?;
+
+        if let Some(folder_id) = target_folder_id {
+            let folder = self.folder_repo.find_by_id_include_deleted(folder_id)?
+                .ok_or_else(|| AppError::FolderNotFound(folder_id.to_string()))?;
+
+            if folder.is_deleted {
+                return Err(AppError::InvalidOperation(format!("目标文件夹「{}」已删除", folder.name)));
+            }
+
+            if folder.workspace_id != note.workspace_id {
+                return Err(AppError::CrossWorkspaceMove(format!(
+                    "笔记「{}」与目标文件夹「{}」不在同一工作空间",
+                    note.title, folder.name
+                )));
+            }
+        }
+
+        note.folder_id = target_folder_id.map(str::to_string);
+        note.updated_at = chrono::Utc::now().timestamp();
+        // 云端同步：移动笔记时标记为需要同步
+        note.is_dirty = true;
+
+        self.repo.update(&note)
+    }
+
+    /// 复制笔记
+    ///
+    /// 新笔记使用新的 ID，标题追加"(副本)"，内容、摘要、文件夹和工作空间与原笔记相同；
+    /// 置顶和收藏状态不复制，标签关联会一并复制。新笔记作为从未同步过的笔记等待上传
+    pub fn duplicate_note(&self, note_id: &str) -> Result<Note> {
+        let original = self.get_note_by_id(note_id)?;
+        let now = chrono::Utc::now().timestamp();
+
+        let copy = Note {
+            id: uuid::Uuid::new_v4().to_string(),
+            title: format!("{}{}", original.title, DUPLICATE_TITLE_SUFFIX),
+            is_favorite: false,
+            is_pinned: false,
+            is_deleted: false,
+            created_at: now,
+            updated_at: now,
+            deleted_at: None,
+            server_ver: 0,
+            is_dirty: true,
+            last_synced_at: None,
+            ..original
+        };
+        let workspace_id = copy.workspace_id.clone();
+        let copy = self.repo.create_in_workspace(&copy, workspace_id.as_deref())?;
+
+        for relation in self.tag_repo.find_note_tag_relations(note_id)? {
+            self.tag_repo.add_tag_to_note_in_workspace(
+                &NoteTagRequest { note_id: copy.id.clone(), tag_id: relation.tag_id },
+                workspace_id.as_deref(),
+            )?;
+        }
+
+        Ok(copy)
+    }
+
+    /// 批量移动笔记到文件夹（可撤销）
+    ///
+    /// 与 `move_notes_to_folder` 相同，但会记录每篇笔记原来的文件夹，
+    /// 之后可以通过 `undo_last_move` 撤销。只保留最近 5 次移动的撤销信息。
+    pub fn move_notes_to_folder_with_undo(&self, req: MoveNotesRequest) -> Result<Vec<Note>> {
+        if req.note_ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        self.repo.move_with_undo(&req.note_ids, req.folder_id.as_deref(), MAX_UNDO_MOVES)?;
+
+        let mut moved_notes = Vec::new();
+        for note_id in &req.note_ids {
+            if let Some(note) = self.repo.find_by_id(note_id)? {
+                moved_notes.push(note);
+            }
+        }
+
+        Ok(moved_notes)
+    }
+
+    /// 撤销最近一次可撤销的批量移动
+    pub fn undo_last_move(&self) -> Result<UndoMoveResult> {
+        let (operation_id, restored) = self.repo.undo_last_move()?
+            .ok_or(AppError::NotFound("没有可撤销的移动操作".to_string()))?;
+
+        Ok(UndoMoveResult { operation_id, restored })
+    }
+
+    /// 统计工作空间最近 N 天的写作情况
+    ///
+    /// ## 统计口径
+    ///
+    /// - 按本地时区划分日期，窗口从 N-1 天前的本地零点开始
+    /// - **新建笔记**：按 `created_at` 归入对应日期
+    /// - **新增字数**：按版本序列（窗口前最后一个快照、窗口内快照、当前内容）
+    ///   计算相邻版本的字数增量，只累计增加的部分，归入较新版本的日期；
+    ///   窗口内新建的笔记以 0 字为起点，更早的笔记若没有基线快照则从窗口内第一个版本算起
+    /// - **最常编辑**：按窗口内的版本数排序
+    pub fn writing_stats(&self, workspace_id: &str, days: i64) -> Result<WritingStats> {
+        if !(1..=365).contains(&days) {
+            return Err(AppError::Validation(format!("统计天数必须在 1 到 365 之间: {}", days)));
+        }
+
+        let today = Local::now().date_naive();
+        let start_date = today - Duration::days(days - 1);
+        let since = start_date
+            .and_hms_opt(0, 0, 0)
+            .and_then(|dt| Local.from_local_datetime(&dt).earliest())
+            .map(|dt| dt.timestamp())
+            .ok_or_else(|| AppError::Internal("无法计算统计起始时间".to_string()))?;
+
+        let notes = self.repo.find_active_since(workspace_id, since)?;
+        let mut snapshots: HashMap<String, Vec<(i64, u32)>> = HashMap::new();
+        for (note_id, created_at, content) in self.repo.find_snapshots_for_stats(workspace_id, since)? {
+            snapshots.entry(note_id).or_default().push((created_at, Note::count_words(&content)));
+        }
+
+        let mut buckets: BTreeMap<NaiveDate, (usize, u32)> = (0..days)
+            .map(|offset| (start_date + Duration::days(offset), (0, 0)))
+            .collect();
+        let mut most_edited = Vec::new();
+
+        for NoteActivity { id: note_id, title, created_at, updated_at, word_count } in notes {
+            if created_at >= since {
+                if let Some(bucket) = local_date(created_at).and_then(|d| buckets.get_mut(&d)) {
+                    bucket.0 += 1;
+                }
+            }
+
+            // 版本序列：基线 -> 窗口内快照 -> 当前内容
+            let mut points = snapshots.remove(&note_id).unwrap_or_default();
+            let has_baseline = points.first().is_some_and(|(ts, _)| *ts < since);
+            if !has_baseline && created_at >= since {
+                points.insert(0, (created_at, 0));
+            }
+            if updated_at >= since && points.last().is_none_or(|(ts, _)| *ts <= updated_at) {
+                points.push((updated_at, word_count));
+            }
+
+            for (ts, words) in positive_word_deltas(&points) {
+                if let Some(bucket) = local_date(ts).and_then(|d| buckets.get_mut(&d)) {
+                    bucket.1 += words;
+                }
+            }
+
+            let edit_count = points.iter().filter(|(ts, _)| *ts >= since).count();
+            if edit_count > 0 {
+                most_edited.push(MostEditedNote {
+                    note_id,
+                    title,
+                    edit_count,
+                    last_edited_at: updated_at,
+                });
+            }
+        }
+
+        most_edited.sort_by(|a, b| {
+            b.edit_count.cmp(&a.edit_count).then(b.last_edited_at.cmp(&a.last_edited_at))
+        });
+        most_edited.truncate(MOST_EDITED_LIMIT);
+
+        let daily: Vec<DailyWritingStats> = buckets
+            .into_iter()
+            .map(|(date, (notes_created, words_written))| DailyWritingStats {
+                date: date.format("%Y-%m-%d").to_string(),
+                notes_created,
+                words_written,
+            })
+            .collect();
+
+        Ok(WritingStats {
+            days,
+            total_notes_created: daily.iter().map(|d| d.notes_created).sum(),
+            total_words_written: daily.iter().map(|d| d.words_written).sum(),
+            daily,
+            most_edited,
+        })
+    }
+
     /// 获取笔记数量（不包括软删除的笔记）
     ///
     /// ## 返回
@@ -219,6 +566,130 @@ impl NoteService {
         self.repo.count()
     }
 
+    /// 按当前设置的摘要长度重新生成所有笔记的摘要
+    ///
+    /// 修改 `excerpt_length` 设置后调用，已有笔记的摘要不会自动更新
+    ///
+    /// ## 返回
+    ///
+    /// 返回更新的笔记数量
+    pub fn regenerate_excerpts(&self) -> Result<usize> {
+        let excerpt_length = self.app_settings.get_excerpt_length()?;
+
+        let excerpts: Vec<(String, Option<String>)> = self.repo.find_all_contents()?
+            .into_iter()
+            .map(|(id, content)| (id, Note::generate_excerpt(&content, excerpt_length)))
+            .collect();
+
+        self.repo.update_excerpts(&excerpts)
+    }
+
+    /// 重新生成笔记的 Markdown 缓存
+    ///
+    /// Markdown 渲染规则变化后调用；缓存是派生数据，不修改 `updated_at`，也不触发同步
+    pub fn regenerate_markdown_cache(&self, note_id: &str) -> Result<Note> {
+        let mut note = self.get_note_by_id(note_id)?;
+        note.markdown_cache = Note::generate_markdown(&note.content);
+        self.repo.update_markdown_caches(&[(note.id.clone(), note.markdown_cache.clone())])?;
+        Ok(note)
+    }
+
+    /// 重新生成工作空间中所有笔记的 Markdown 缓存
+    ///
+    /// `workspace_id` 为 `None` 时使用当前工作空间
+    ///
+    /// ## 返回
+    ///
+    /// 返回更新的笔记数量
+    pub fn regenerate_all_markdown_caches(&self, workspace_id: Option<&str>) -> Result<usize> {
+        let caches: Vec<(String, Option<String>)> = self.repo.find_contents_by_workspace(workspace_id)?
+            .into_iter()
+            .map(|(id, content)| (id, Note::generate_markdown(&content)))
+            .collect();
+
+        self.repo.update_markdown_caches(&caches)
+    }
+
+    /// 为文件夹中的笔记标题添加顺序编号
+    ///
+    /// 笔记按创建时间排序，标题格式为 `{prefix}{编号} {原标题}`，编号至少两位（如 `01`）。
+    ///
+    /// ## 幂等性
+    ///
+    /// 本功能生成的编号前缀（`{prefix}` + 至少两位、不超过当前编号宽度的数字 + 空格）会被替换而不是叠加，
+    /// 重复执行不会产生变化，标题未变的笔记会被跳过
+    ///
+    /// ## 参数
+    ///
+    /// - `dry_run`: 为 true 时只返回拟修改的标题，不写入数据库
+    ///
+    /// ## 返回
+    ///
+    /// 返回标题发生变化的笔记（修改前会为每篇笔记创建快照）
+    pub fn renumber_folder(&self, folder_id: &str, prefix: &str, dry_run: bool) -> Result<Vec<RenumberedNote>> {
+        let notes = self.repo.find_titles_by_folder(folder_id)?;
+        let width = notes.len().to_string().len().max(2);
+
+        let changes: Vec<RenumberedNote> = notes
+            .into_iter()
+            .enumerate()
+            .filter_map(|(index, (note_id, old_title))| {
+                let base = strip_number_prefix(&old_title, prefix, width);
+                let new_title = if base.is_empty() {
+                    format!("{}{:0width$}", prefix, index + 1, width = width)
+                } else {
+                    format!("{}{:0width$} {}", prefix, index + 1, base, width = width)
+                };
+                (new_title != old_title).then_some(RenumberedNote { note_id, old_title, new_title })
+            })
+            .collect();
+
+        if !dry_run && !changes.is_empty() {
+            let titles: Vec<(String, String)> = changes
+                .iter()
+                .map(|c| (c.note_id.clone(), c.new_title.clone()))
+                .collect();
+            self.repo.rename_with_snapshots(&titles, "重新编号前")?;
+        }
+
+        log::info!(
+            "[NoteService] 重新编号: folder_id={}, changed={}, dry_run={}",
+            folder_id,
+            changes.len(),
+            dry_run
+        );
+
+        Ok(changes)
+    }
+
+    /// 批量收藏/取消收藏笔记
+    ///
+    /// ## 返回
+    ///
+    /// 返回状态发生变化的笔记数量（已处于目标状态的笔记不计入，也不会标记为需要同步）
+    pub fn set_favorite_batch(&self, note_ids: Vec<String>, favorite: bool) -> Result<usize> {
+        self.repo.set_favorite_batch(&note_ids, favorite)
+    }
+
+    /// 批量置顶/取消置顶笔记
+    ///
+    /// ## 返回
+    ///
+    /// 返回状态发生变化的笔记数量（已处于目标状态的笔记不计入，也不会标记为需要同步）
+    pub fn set_pinned_batch(&self, note_ids: Vec<String>, pinned: bool) -> Result<usize> {
+        self.repo.set_pinned_batch(&note_ids, pinned)
+    }
+
+    /// 调整置顶笔记的顺序
+    ///
+    /// `note_ids` 中的笔记必须都已置顶，按给定顺序排到置顶区最前面
+    pub fn set_pin_order(&self, note_ids: Vec<String>) -> Result<()> {
+        if note_ids.is_empty() {
+            return Ok(());
+        }
+        self.repo.set_pin_order(&note_ids)
+    }
+
     /// 永久删除笔记（硬删除）
     ///
     /// ## 行为
@@ -243,14 +714,960 @@ impl NoteService {
         self.repo.hard_delete_batch(&note_ids)
     }
 
-    /// 清理超过 30 天的软删除笔记
+    /// 清理指定工作空间中超过保留天数的软删除笔记
     ///
     /// ## 返回
     ///
     /// 返回清理的笔记数量
-    pub fn purge_old_deleted_notes(&self) -> Result<i64> {
-        const PURGE_AFTER_DAYS: i64 = 30;
-        self.repo.purge_old_deleted_notes(PURGE_AFTER_DAYS)
+    pub fn purge_old_deleted_notes(&self, workspace_id: Option<&str>, days: i64) -> Result<i64> {
+        self.repo.purge_old_deleted_notes(workspace_id, days)
+    }
+
+    /// 导出笔记为单个 Markdown 文件内容
+    ///
+    /// 开头为 YAML front-matter（标题、创建/更新时间、标签、文件夹路径），之后是笔记内容
+    /// （优先使用 markdown 缓存）
+    pub fn export_note_markdown(&self, note_id: &str) -> Result<String> {
+        let note = self.get_note_by_id(note_id)?;
+        self.render_note_markdown(&note)
+    }
+
+    /// 批量导出笔记为 Markdown
+    ///
+    /// ## 返回
+    ///
+    /// 返回 文件名 → 文件内容；文件名由标题生成，重名（不区分大小写）时追加 ` (2)`、` (3)`...
+    pub fn export_notes_markdown(&self, note_ids: &[String]) -> Result<BTreeMap<String, String>> {
+        let mut files = BTreeMap::new();
+        let mut used_names = HashSet::new();
+
+        for note_id in note_ids {
+            let note = self.get_note_by_id(note_id)?;
+            let content = self.render_note_markdown(&note)?;
+            let file_name = unique_file_name(&note.title, ".md", &mut used_names);
+            files.insert(file_name, content);
+        }
+
+        log::info!("已导出 Markdown: count={}", files.len());
+        Ok(files)
+    }
+
+    /// 导出整个工作空间为 zip 归档
+    ///
+    /// 归档内容：
+    /// - 文件夹树对应的目录（名称经过清理，不会产生 `..` 等路径穿越）
+    /// - 每篇笔记一个 `.md` 文件（带 YAML front-matter），位于所在文件夹的目录中
+    /// - `manifest.json`：文件夹和笔记与归档路径的对应关系
+    ///
+    /// 笔记逐条读取并写入归档，不会同时把所有笔记内容加载到内存
+    pub fn export_workspace(&self, workspace_id: &str, path: &str) -> Result<WorkspaceExportResult> {
+        let tree = self.folder_repo.find_tree_by_workspace(workspace_id)?;
+        let mut tags = self.tag_repo.find_tag_names_by_workspace(workspace_id)?;
+
+        // 目录名 → 已使用的条目名（小写），根目录预留 manifest.json
+        let mut used_names: HashMap<String, HashSet<String>> = HashMap::new();
+        used_names.entry(String::new()).or_default().insert(MANIFEST_FILE.to_string());
+        let mut dirs = Vec::new();
+        layout_export_dirs(&tree, "", &[], &mut used_names, &mut dirs);
+        let dir_by_folder: HashMap<&str, &ExportDir> = dirs.iter()
+            .map(|dir| (dir.folder.id.as_str(), dir))
+            .collect();
+
+        let file = File::create(path)
+            .map_err(|e| AppError::Internal(format!("创建归档文件失败: {}", e)))?;
+        let mut zip = ZipWriter::new(BufWriter::new(file));
+
+        for dir in &dirs {
+            zip.add_file(&format!("{}/", dir.path), b"", dir.folder.updated_at)?;
+        }
+
+        let mut manifest_notes = Vec::new();
+        let note_count = self.repo.for_each_in_workspace(workspace_id, |note| {
+            let dir = note.folder_id.as_deref().and_then(|id| dir_by_folder.get(id));
+            let (dir_path, folder_path) = match dir {
+                Some(dir) => (dir.path.as_str(), dir.names.as_slice()),
+                None => ("", &[][..]),
+            };
+
+            let file_name = unique_file_name(&note.title, ".md", used_names.entry(dir_path.to_string()).or_default());
+            let entry = if dir_path.is_empty() { file_name } else { format!("{}/{}", dir_path, file_name) };
+            let note_tags = tags.remove(&note.id).unwrap_or_default();
+            let content = markdown_with_front_matter(&note, &note_tags, folder_path);
+            zip.add_file(&entry, content.as_bytes(), note.updated_at)?;
+
+            manifest_notes.push(serde_json::json!({
+                "id": note.id,
+                "title": note.title,
+                "file": entry,
+            }));
+            Ok(())
+        })?;
+
+        let exported_at = Utc::now().timestamp();
+        let manifest = serde_json::json!({
+            "workspaceId": workspace_id,
+            "exportedAt": exported_at,
+            "folders": dirs.iter().map(|dir| serde_json::json!({
+                "id": dir.folder.id,
+                "name": dir.folder.name,
+                "dir": dir.path,
+            })).collect::<Vec<_>>(),
+            "notes": manifest_notes,
+        });
+        let manifest = serde_json::to_vec_pretty(&manifest)
+            .map_err(|e| AppError::Internal(format!("序列化归档清单失败: {}", e)))?;
+        zip.add_file(MANIFEST_FILE, &manifest, exported_at)?;
+
+        zip.finish()?;
+
+        log::info!("已导出工作空间: workspace_id={}, folders={}, notes={}, path={}",
+            workspace_id, dirs.len(), note_count, path);
+
+        Ok(WorkspaceExportResult {
+            path: path.to_string(),
+            note_count,
+            folder_count: dirs.len(),
+        })
     }
+
+    /// 从目录或 zip 归档导入 Markdown 笔记
+    ///
+    /// - 按目录结构重建文件夹，已存在的同名文件夹直接复用；有 `manifest.json`（由 `export_workspace` 生成）时恢复原始文件夹名
+    /// - 位于根目录的笔记使用 front-matter 中的 `folder` 作为文件夹路径
+    /// - 标签按名称复用或创建
+    /// - 同一文件夹中已有标题和内容都相同的笔记时跳过，重复导入同一归档不会产生重复笔记
+    ///
+    /// 导入的笔记、文件夹和标签都标记为待同步
+    pub fn import_markdown_archive(&self, path: &str, workspace_id: &str) -> Result<ImportSummary> {
+        let source = Path::new(path);
+        let mut entries = if source.is_dir() {
+            let mut entries = Vec::new();
+            read_markdown_dir(source, "", &mut entries)?;
+            entries
+        } else {
+            let bytes = std::fs::read(source)
+                .map_err(|e| AppError::Internal(format!("读取归档失败: {}", e)))?;
+            zip_reader::read_entries(&bytes)?
+                .into_iter()
+                .map(|entry| (entry.name, entry.data))
+                .collect()
+        };
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let dir_names = entries.iter()
+            .find(|(name, _)| name == MANIFEST_FILE)
+            .map(|(_, data)| manifest_dir_names(data))
+            .unwrap_or_default();
+
+        let mut folder_ids: HashMap<(Option<String>, String), String> = self.folder_repo
+            .find_by_workspace(workspace_id)?
+            .into_iter()
+            .map(|folder| ((folder.parent_id, folder.name), folder.id))
+            .collect();
+        let max_length = self.app_settings.get_max_note_length()?;
+        let excerpt_length = self.app_settings.get_excerpt_length()?;
+        let mut summary = ImportSummary::default();
+
+        for (name, data) in &entries {
+            let components = archive_path_components(name);
+            if name.ends_with('/') {
+                let folder_names = archive_dir_names(&components, &dir_names);
+                self.ensure_folder_path(workspace_id, &folder_names, &mut folder_ids, &mut summary)?;
+                continue;
+            }
+
+            let Some((file_name, dirs)) = components.split_last() else {
+                continue;
+            };
+            let Some(stem) = file_name.strip_suffix(".md").or_else(|| file_name.strip_suffix(".MD")) else {
+                continue;
+            };
+
+            let text = String::from_utf8_lossy(data);
+            let (meta, body) = parse_front_matter(&text);
+
+            let folder_names = if dirs.is_empty() {
+                front_matter_list(&meta, "folder")
+            } else {
+                archive_dir_names(dirs, &dir_names)
+            };
+            let folder_id = self.ensure_folder_path(workspace_id, &folder_names, &mut folder_ids, &mut summary)?;
+            let title = front_matter_str(&meta, "title").unwrap_or_else(|| stem.to_string());
+
+            if self.repo.exists_with_content(workspace_id, folder_id.as_deref(), &title, body)? {
+                summary.notes_skipped += 1;
+                continue;
+            }
+            if max_length > 0 && body.chars().count() > max_length {
+                log::warn!("导入时跳过超长笔记: file={}, length={}", name, body.chars().count());
+                summary.notes_skipped += 1;
+                continue;
+            }
+
+            let mut note = Note::new(title, body.to_string(), folder_id);
+            if let Some(created_at) = front_matter_timestamp(&meta, "created_at") {
+                note.created_at = created_at;
+            }
+            if let Some(updated_at) = front_matter_timestamp(&meta, "updated_at") {
+                note.updated_at = updated_at;
+            }
+            note.workspace_id = Some(workspace_id.to_string());
+            note.is_dirty = true;
+            note.refresh_excerpt(excerpt_length);
+            self.repo.create_in_workspace(&note, Some(workspace_id))?;
+            summary.notes_created += 1;
+
+            for tag_name in front_matter_list(&meta, "tags") {
+                let (tag, created) = self.tag_repo.find_or_create_by_name(&tag_name, workspace_id)?;
+                if created {
+                    summary.tags_created += 1;
+                }
+                self.tag_repo.add_tag_to_note_in_workspace(
+                    &NoteTagRequest { note_id: note.id.clone(), tag_id: tag.id },
+                    Some(workspace_id),
+                )?;
+            }
+        }
+
+        log::info!("已导入 Markdown: workspace_id={}, path={}, summary={:?}", workspace_id, path, summary);
+        Ok(summary)
+    }
+
+    /// 按名称路径逐级查找或创建文件夹，返回最后一级文件夹 ID（路径为空时返回 None）
+    fn ensure_folder_path(
+        &self,
+        workspace_id: &str,
+        names: &[String],
+        folder_ids: &mut HashMap<(Option<String>, String), String>,
+        summary: &mut ImportSummary,
+    ) -> Result<Option<String>> {
+        let mut parent_id: Option<String> = None;
+        for name in names {
+            let key = (parent_id.clone(), name.clone());
+            let id = match folder_ids.get(&key) {
+                Some(id) => id.clone(),
+                None => {
+                    let folder = Folder::new(name.clone(), parent_id.clone(), None, None, Some(workspace_id.to_string()));
+                    self.folder_repo.create(&folder)?;
+                    summary.folders_created += 1;
+                    folder_ids.insert(key, folder.id.clone());
+                    folder.id
+                }
+            };
+            parent_id = Some(id);
+        }
+        Ok(parent_id)
+    }
+
+    fn render_note_markdown(&self, note: &Note) -> Result<String> {
+        let tags: Vec<String> = self.tag_repo.find_by_note_id(&note.id)?
+            .into_iter()
+            .map(|tag| tag.name)
+            .collect();
+        let folder_path: Vec<String> = match &note.folder_id {
+            Some(folder_id) => self.folder_repo.get_path(folder_id)?
+                .into_iter()
+                .map(|folder| folder.name)
+                .collect(),
+            None => Vec::new(),
+        };
+
+        Ok(markdown_with_front_matter(note, &tags, &folder_path))
+    }
+}
+
+/// 生成带 YAML front-matter 的 Markdown 文本
+///
+/// 字段值以 JSON 语法写出（JSON 是 YAML 的子集），标题或标签中的冒号、引号不会破坏 front-matter
+fn markdown_with_front_matter(note: &Note, tags: &[String], folder_path: &[String]) -> String {
+    let body = note.markdown_cache.as_deref()
+        .filter(|m| !m.is_empty())
+        .unwrap_or(&note.content);
+
+    format!(
+        "---\ntitle: {}\ncreated_at: {}\nupdated_at: {}\ntags: {}\nfolder: {}\n---\n\n{}",
+        serde_json::json!(note.title),
+        serde_json::json!(format_rfc3339(note.created_at)),
+        serde_json::json!(format_rfc3339(note.updated_at)),
+        serde_json::json!(tags),
+        serde_json::json!(folder_path),
+        body,
+    )
+}
+
+/// 解析 Markdown 开头的 YAML front-matter
+///
+/// 只支持导入需要的子集：`key: value` 标量、JSON 或 `[a, b]` 形式的行内列表、`- item` 形式的块列表。
+/// 没有 front-matter 时返回空表和原文
+fn parse_front_matter(text: &str) -> (HashMap<String, serde_json::Value>, &str) {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let Some(rest) = text.strip_prefix("---\n").or_else(|| text.strip_prefix("---\r\n")) else {
+        return (HashMap::new(), text);
+    };
+
+    let mut meta = HashMap::new();
+    let mut list_key: Option<String> = None;
+    let mut offset = 0;
+    for raw_line in rest.split_inclusive('\n') {
+        offset += raw_line.len();
+        let line = raw_line.trim_end_matches(['\n', '\r']);
+
+        if line == "---" || line == "..." {
+            let body = &rest[offset..];
+            let body = body.strip_prefix("\r\n").or_else(|| body.strip_prefix('\n')).unwrap_or(body);
+            return (meta, body);
+        }
+
+        if let Some(item) = line.trim_start().strip_prefix("- ") {
+            if let Some(serde_json::Value::Array(items)) = list_key.as_ref().and_then(|key| meta.get_mut(key)) {
+                items.push(serde_json::Value::String(yaml_scalar(item)));
+            }
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let (key, value) = (key.trim().to_string(), value.trim());
+        if value.is_empty() {
+            meta.insert(key.clone(), serde_json::Value::Array(Vec::new()));
+            list_key = Some(key);
+        } else {
+            meta.insert(key, yaml_value(value));
+            list_key = None;
+        }
+    }
+
+    // 没有结束标记，不视为 front-matter
+    (HashMap::new(), text)
+}
+
+/// 解析 front-matter 的值（JSON 语法优先，其次是 `[a, b]` 行内列表和普通标量）
+fn yaml_value(value: &str) -> serde_json::Value {
+    if let Ok(json) = serde_json::from_str(value) {
+        return json;
+    }
+    match value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+        Some(items) => serde_json::Value::Array(
+            items.split(',')
+                .map(yaml_scalar)
+                .filter(|item| !item.is_empty())
+                .map(serde_json::Value::String)
+                .collect(),
+        ),
+        None => serde_json::Value::String(yaml_scalar(value)),
+    }
+}
+
+/// 去掉标量两侧的引号
+fn yaml_scalar(value: &str) -> String {
+    let value = value.trim();
+    if value.len() >= 2
+        && ((value.starts_with('"') && value.ends_with('"')) || (value.starts_with('\'') && value.ends_with('\'')))
+    {
+        return serde_json::from_str(value).unwrap_or_else(|_| value[1..value.len() - 1].to_string());
+    }
+    value.to_string()
+}
+
+fn front_matter_str(meta: &HashMap<String, serde_json::Value>, key: &str) -> Option<String> {
+    match meta.get(key)? {
+        serde_json::Value::String(s) if !s.trim().is_empty() => Some(s.clone()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// 读取列表字段（单个字符串按逗号拆分），去掉标签常见的 `#` 前缀
+fn front_matter_list(meta: &HashMap<String, serde_json::Value>, key: &str) -> Vec<String> {
+    let items: Vec<String> = match meta.get(key) {
+        Some(serde_json::Value::Array(items)) => items.iter()
+            .map(|item| match item {
+                serde_json::Value::String(s) => s.clone(),
+                other => other.to_string(),
+            })
+            .collect(),
+        Some(serde_json::Value::String(s)) => s.split(',').map(str::to_string).collect(),
+        _ => Vec::new(),
+    };
+    items.into_iter()
+        .map(|item| item.trim().trim_start_matches('#').to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// 读取时间字段（RFC 3339 字符串或 Unix 时间戳）
+fn front_matter_timestamp(meta: &HashMap<String, serde_json::Value>, key: &str) -> Option<i64> {
+    match meta.get(key)? {
+        serde_json::Value::Number(n) => n.as_i64(),
+        serde_json::Value::String(s) => chrono::DateTime::parse_from_rfc3339(s).ok().map(|dt| dt.timestamp()),
+        _ => None,
+    }
+}
+
+/// 归档内路径拆分为各级名称，丢弃空段、`.` 和 `..`
+fn archive_path_components(path: &str) -> Vec<String> {
+    path.split(['/', '\\'])
+        .filter(|part| !part.is_empty() && *part != "." && *part != "..")
+        .map(str::to_string)
+        .collect()
+}
+
+/// 目录各级对应的文件夹名（优先使用 manifest 中记录的原始名称）
+fn archive_dir_names(dirs: &[String], manifest_names: &HashMap<String, String>) -> Vec<String> {
+    (0..dirs.len())
+        .map(|i| {
+            manifest_names.get(&dirs[..=i].join("/"))
+                .cloned()
+                .unwrap_or_else(|| dirs[i].clone())
+        })
+        .collect()
+}
+
+/// 读取 `export_workspace` 生成的 manifest 中 归档目录 → 原始文件夹名 的对应关系
+fn manifest_dir_names(data: &[u8]) -> HashMap<String, String> {
+    let Ok(manifest) = serde_json::from_slice::<serde_json::Value>(data) else {
+        return HashMap::new();
+    };
+    manifest["folders"].as_array()
+        .map(|folders| folders.iter()
+            .filter_map(|folder| Some((folder["dir"].as_str()?.to_string(), folder["name"].as_str()?.to_string())))
+            .collect())
+        .unwrap_or_default()
+}
+
+/// 递归读取目录中的 `.md` 文件和 manifest，目录以 `名称/` 形式返回（用于重建空文件夹）
+fn read_markdown_dir(dir: &Path, prefix: &str, entries: &mut Vec<(String, Vec<u8>)>) -> Result<()> {
+    let read_dir = std::fs::read_dir(dir)
+        .map_err(|e| AppError::Internal(format!("读取目录失败: {}: {}", dir.display(), e)))?;
+
+    for entry in read_dir {
+        let entry = entry.map_err(|e| AppError::Internal(format!("读取目录失败: {}", e)))?;
+        let file_type = entry.file_type().map_err(|e| AppError::Internal(format!("读取目录失败: {}", e)))?;
+        let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+
+        if file_type.is_dir() {
+            let dir_name = format!("{}/", name);
+            entries.push((dir_name.clone(), Vec::new()));
+            read_markdown_dir(&entry.path(), &dir_name, entries)?;
+        } else if file_type.is_file() && (name.to_lowercase().ends_with(".md") || name == MANIFEST_FILE) {
+            let data = std::fs::read(entry.path())
+                .map_err(|e| AppError::Internal(format!("读取文件失败: {}: {}", name, e)))?;
+            entries.push((name, data));
+        }
+    }
+
+    Ok(())
+}
+
+/// Unix 时间戳格式化为 RFC 3339（UTC）
+fn format_rfc3339(timestamp: i64) -> String {
+    Utc.timestamp_opt(timestamp, 0)
+        .single()
+        .map(|dt| dt.to_rfc3339_opts(SecondsFormat::Secs, true))
+        .unwrap_or_default()
+}
+
+/// 工作空间归档中的清单文件名
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// 文件夹在工作空间归档中对应的目录
+struct ExportDir<'a> {
+    folder: &'a Folder,
+    path: String,  // 归档内的目录路径（已清理的名称，以 `/` 分隔）
+    names: Vec<String>,  // 从根到该文件夹的原始名称（写入 front-matter）
 }
 
+/// 按文件夹树先序遍历，为每个文件夹分配归档目录
+fn layout_export_dirs<'a>(
+    nodes: &'a [FolderNode],
+    parent_path: &str,
+    parent_names: &[String],
+    used: &mut HashMap<String, HashSet<String>>,
+    dirs: &mut Vec<ExportDir<'a>>,
+) {
+    for node in nodes {
+        let dir_name = unique_file_name(&node.folder.name, "", used.entry(parent_path.to_string()).or_default());
+        let path = if parent_path.is_empty() { dir_name } else { format!("{}/{}", parent_path, dir_name) };
+        let mut names = parent_names.to_vec();
+        names.push(node.folder.name.clone());
+
+        dirs.push(ExportDir { folder: &node.folder, path: path.clone(), names: names.clone() });
+        layout_export_dirs(&node.children, &path, &names, used, dirs);
+    }
+}
+
+/// 由标题或文件夹名生成同一目录下不重复的文件名（`extension` 如 `.md`，目录传空字符串）
+///
+/// 替换路径分隔符等文件系统不允许的字符，去掉首尾的 `.`（避免 `..` 路径穿越）；
+/// `used` 中保存已使用的文件名（小写），用于大小写不敏感的文件系统
+fn unique_file_name(name: &str, extension: &str, used: &mut HashSet<String>) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|c| if matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') || c.is_control() { '_' } else { c })
+        .collect();
+    let base = match sanitized.trim().trim_matches('.') {
+        "" => "untitled",
+        base => base,
+    };
+
+    let mut file_name = format!("{}{}", base, extension);
+    let mut n = 2;
+    while !used.insert(file_name.to_lowercase()) {
+        file_name = format!("{} ({}){}", base, n, extension);
+        n += 1;
+    }
+    file_name
+}
+
+/// 去掉标题开头由 [`NoteService::renumber_folder`] 生成的编号前缀
+///
+/// 只识别本功能的格式：`{prefix}` + 2 到 `max_width` 位数字，后跟一个空格或位于末尾。
+/// 用户自己写的 "2024 Review"、"3. Setup" 这类标题保持不变
+fn strip_number_prefix<'a>(title: &'a str, prefix: &str, max_width: usize) -> &'a str {
+    let Some(rest) = title.strip_prefix(prefix) else {
+        return title;
+    };
+
+    let digits_end = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+    if !(2..=max_width.max(2)).contains(&digits_end) {
+        return title;
+    }
+
+    let after_digits = &rest[digits_end..];
+    if after_digits.is_empty() {
+        return after_digits;
+    }
+
+    after_digits.strip_prefix(' ').unwrap_or(title)
+}
+
+/// 时间戳对应的本地日期
+fn local_date(timestamp: i64) -> Option<NaiveDate> {
+    Local.timestamp_opt(timestamp, 0).single().map(|dt| dt.date_naive())
+}
+
+/// 计算相邻版本之间的字数增量（只保留增加的部分）
+///
+/// `points` 为按时间排序的 `(时间戳, 字数)`，返回 `(较新版本的时间戳, 增加的字数)`
+fn positive_word_deltas(points: &[(i64, u32)]) -> Vec<(i64, u32)> {
+    points
+        .windows(2)
+        .filter(|pair| pair[1].1 > pair[0].1)
+        .map(|pair| (pair[1].0, pair[1].1 - pair[0].1))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_folder(service: &NoteService, workspace_id: &str) -> Folder {
+        let folder = Folder::new("目标".to_string(), None, None, None, Some(workspace_id.to_string()));
+        service.folder_repo.create(&folder).unwrap()
+    }
+
+    fn create_note(service: &NoteService, workspace_id: &str) -> Note {
+        let note = Note::new("笔记".to_string(), "内容".to_string(), None);
+        service.repo.create_in_workspace(&note, Some(workspace_id)).unwrap()
+    }
+
+    #[test]
+    fn test_move_note_to_folder_in_same_workspace() {
+        let service = memory_service();
+        let folder = create_folder(&service, "ws-a");
+        let note = create_note(&service, "ws-a");
+
+        let moved = service.move_note(&note.id, Some(&folder.id)).unwrap();
+
+        assert_eq!(moved.folder_id.as_deref(), Some(folder.id.as_str()));
+        assert!(moved.is_dirty);
+        assert_eq!(service.get_note_by_id(&note.id).unwrap().folder_id, Some(folder.id));
+    }
+
+    #[test]
+    fn test_move_note_rejects_deleted_folder() {
+        let service = memory_service();
+        let folder = create_folder(&service, "ws-a");
+        let note = create_note(&service, "ws-a");
+        service.folder_repo.delete(&folder.id).unwrap();
+
+        let result = service.move_note(&note.id, Some(&folder.id));
+
+        assert!(matches!(result, Err(AppError::InvalidOperation(_))));
+        assert_eq!(service.get_note_by_id(&note.id).unwrap().folder_id, None);
+    }
+
+    #[test]
+    fn test_move_note_rejects_cross_workspace_move() {
+        let service = memory_service();
+        let folder = create_folder(&service, "ws-b");
+        let note = create_note(&service, "ws-a");
+
+        let result = service.move_note(&note.id, Some(&folder.id));
+
+        assert!(matches!(result, Err(AppError::CrossWorkspaceMove(_))));
+        assert_eq!(service.get_note_by_id(&note.id).unwrap().folder_id, None);
+    }
+
+    #[test]
+    fn test_duplicate_note_gets_new_id_and_resets_flags() {
+        let service = seeded_service();
+        service.set_pinned_batch(vec!["n1".to_string()], true).unwrap();
+        service.set_favorite_batch(vec!["n1".to_string()], true).unwrap();
+
+        let copy = service.duplicate_note("n1").unwrap();
+
+        let original = service.get_note_by_id("n1").unwrap();
+        assert_ne!(copy.id, original.id);
+        assert_eq!(copy.title, "计划 (副本)");
+        assert_eq!(copy.content, original.content);
+        assert_eq!(copy.folder_id, original.folder_id);
+        assert_eq!(copy.workspace_id.as_deref(), Some("ws-1"));
+        assert!(!copy.is_pinned && !copy.is_favorite);
+        assert!(copy.is_dirty);
+        assert_eq!(copy.server_ver, 0);
+    }
+
+    #[test]
+    fn test_duplicate_note_content_is_independent() {
+        let service = seeded_service();
+        let copy = service.duplicate_note("n3").unwrap();
+
+        service.update_note(UpdateNoteRequest {
+            id: copy.id.clone(),
+            title: None,
+            content: Some("改过的内容".to_string()),
+            folder_id: None,
+            is_favorite: None,
+            is_pinned: None,
+            author: None,
+        }).unwrap();
+
+        assert_eq!(service.get_note_by_id("n3").unwrap().content, "# n3 正文");
+        assert_eq!(service.get_note_by_id(&copy.id).unwrap().content, "改过的内容");
+    }
+
+    #[test]
+    fn test_duplicate_note_copies_tag_links() {
+        let service = seeded_service();
+        let copy = service.duplicate_note("n1").unwrap();
+
+        let tag_ids = |id: &str| -> Vec<String> {
+            service.tag_repo.find_note_tag_relations(id).unwrap()
+                .into_iter()
+                .map(|r| r.tag_id)
+                .collect()
+        };
+        assert_eq!(tag_ids(&copy.id).len(), 1);
+        assert_eq!(tag_ids(&copy.id), tag_ids("n1"));
+    }
+
+
+    #[test]
+    fn test_favorites_list_follows_is_favorite() {
+        let service = memory_service();
+        let note = service.create_note(CreateNoteRequest {
+            title: "收藏".to_string(),
+            content: String::new(),
+            folder_id: None,
+        }).unwrap();
+        let favorite_ids = |service: &NoteService| -> Vec<String> {
+            service.list_favorite_notes(None, 50, 0).unwrap().into_iter().map(|n| n.id).collect()
+        };
+        assert!(favorite_ids(&service).is_empty());
+
+        service.set_favorite_batch(vec![note.id.clone()], true).unwrap();
+        assert_eq!(favorite_ids(&service), vec![note.id.clone()]);
+
+        service.set_favorite_batch(vec![note.id.clone()], false).unwrap();
+        assert!(favorite_ids(&service).is_empty());
+    }
+
+    fn listed_titles(service: &NoteService) -> Vec<String> {
+        service.list_all_notes().unwrap().into_iter().map(|n| n.title).collect()
+    }
+
+    #[test]
+    fn test_pinned_notes_listed_first_newest_pin_on_top() {
+        let service = memory_service();
+        let mut ids = Vec::new();
+        for title in ["a", "b", "c"] {
+            let note = service.create_note(CreateNoteRequest {
+                title: title.to_string(),
+                content: String::new(),
+                folder_id: None,
+            }).unwrap();
+            ids.push(note.id);
+        }
+
+        service.set_pinned_batch(vec![ids[0].clone()], true).unwrap();
+        service.set_pinned_batch(vec![ids[1].clone()], true).unwrap();
+
+        assert_eq!(listed_titles(&service), vec!["b", "a", "c"]);
+    }
+
+    #[test]
+    fn test_set_pin_order_reorders_pinned_notes() {
+        let service = memory_service();
+        let mut ids = Vec::new();
+        for title in ["a", "b", "c"] {
+            let note = service.create_note(CreateNoteRequest {
+                title: title.to_string(),
+                content: String::new(),
+                folder_id: None,
+            }).unwrap();
+            ids.push(note.id);
+        }
+        service.set_pinned_batch(vec![ids[0].clone()], true).unwrap();
+        service.set_pinned_batch(vec![ids[1].clone()], true).unwrap();
+
+        service.set_pin_order(vec![ids[0].clone(), ids[1].clone()]).unwrap();
+        assert_eq!(listed_titles(&service), vec!["a", "b", "c"]);
+
+        service.set_pin_order(vec![ids[1].clone()]).unwrap();
+        assert_eq!(listed_titles(&service), vec!["b", "a", "c"]);
+
+        let result = service.set_pin_order(vec![ids[2].clone()]);
+        assert!(matches!(result, Err(AppError::InvalidOperation(_))));
+    }
+
+    #[test]
+    fn test_positive_word_deltas() {
+        assert_eq!(positive_word_deltas(&[]), vec![]);
+        assert_eq!(positive_word_deltas(&[(10, 50)]), vec![]);
+        assert_eq!(
+            positive_word_deltas(&[(0, 0), (10, 100), (20, 80), (30, 120)]),
+            vec![(10, 100), (30, 40)]
+        );
+        assert_eq!(positive_word_deltas(&[(0, 30), (10, 30)]), vec![]);
+    }
+
+    #[test]
+    fn test_front_matter_round_trips_tags_and_folder_path() {
+        let mut note = Note::new("周报: \"第 1 周\"".to_string(), "# 内容".to_string(), Some("f2".to_string()));
+        note.created_at = 1_700_000_000;
+        note.updated_at = 1_700_000_060;
+        let tags = vec!["工作".to_string(), "a: b".to_string(), "引号\"".to_string()];
+        let folder_path = vec!["项目".to_string(), "2024 / Q1".to_string()];
+
+        let markdown = markdown_with_front_matter(&note, &tags, &folder_path);
+        let (meta, _) = parse_front_matter(&markdown);
+
+        assert_eq!(meta["title"], serde_json::json!(note.title));
+        assert_eq!(meta["created_at"], "2023-11-14T22:13:20Z");
+        assert_eq!(meta["updated_at"], "2023-11-14T22:14:20Z");
+        assert_eq!(serde_json::from_value::<Vec<String>>(meta["tags"].clone()).unwrap(), tags);
+        assert_eq!(serde_json::from_value::<Vec<String>>(meta["folder"].clone()).unwrap(), folder_path);
+        assert!(markdown.ends_with("---\n\n# 内容"));
+    }
+
+    #[test]
+    fn test_front_matter_prefers_markdown_cache() {
+        let mut note = Note::new("无文件夹".to_string(), "{\"type\":\"doc\"}".to_string(), None);
+        note.markdown_cache = Some("正文".to_string());
+
+        let markdown = markdown_with_front_matter(&note, &[], &[]);
+        let (meta, _) = parse_front_matter(&markdown);
+
+        assert_eq!(meta["tags"], serde_json::json!([]));
+        assert_eq!(meta["folder"], serde_json::json!([]));
+        assert!(markdown.ends_with("\n\n正文"));
+    }
+
+    #[test]
+    fn test_unique_file_name() {
+        let mut used = HashSet::new();
+        assert_eq!(unique_file_name("笔记", ".md", &mut used), "笔记.md");
+        assert_eq!(unique_file_name("笔记", ".md", &mut used), "笔记 (2).md");
+        assert_eq!(unique_file_name("笔记", ".md", &mut used), "笔记 (3).md");
+        assert_eq!(unique_file_name("Plan", ".md", &mut used), "Plan.md");
+        assert_eq!(unique_file_name("plan", ".md", &mut used), "plan (2).md");
+        assert_eq!(unique_file_name("a/b:c?", ".md", &mut used), "a_b_c_.md");
+        assert_eq!(unique_file_name("  ", ".md", &mut used), "untitled.md");
+        assert_eq!(unique_file_name("..", "", &mut used), "untitled");
+        assert_eq!(unique_file_name("../etc", "", &mut used), "_etc");
+    }
+
+    fn memory_service() -> NoteService {
+        let pool = crate::database::memory_pool();
+
+        NoteService::new(
+            NoteRepository::new(pool.clone()),
+            FolderRepository::new(pool.clone()),
+            TagRepository::new(pool.clone()),
+            AppSettingsService::new(pool),
+        )
+    }
+
+    /// 工作空间 ws-1：工作/项目 下两篇同名笔记（其中一篇带标签）、根目录一篇笔记、一个空文件夹 "../etc"；
+    /// 另有一篇 ws-2 的笔记
+    fn seeded_service() -> NoteService {
+        let service = memory_service();
+        let ws = Some("ws-1".to_string());
+        let work = Folder::new("工作".to_string(), None, None, None, ws.clone());
+        let project = Folder::new("项目".to_string(), Some(work.id.clone()), None, None, ws.clone());
+        let mut evil = Folder::new("../etc".to_string(), None, None, None, ws.clone());
+        evil.sort_order = 1;
+        let other = Folder::new("其他空间".to_string(), None, None, None, Some("ws-2".to_string()));
+        for folder in [&work, &project, &evil, &other] {
+            service.folder_repo.create(folder).unwrap();
+        }
+
+        for (id, title, folder_id, workspace_id) in [
+            ("n1", "计划", Some(project.id.clone()), "ws-1"),
+            ("n2", "计划", Some(project.id.clone()), "ws-1"),
+            ("n3", "随手记", None, "ws-1"),
+            ("n4", "别的空间", None, "ws-2"),
+        ] {
+            let mut note = Note::new(title.to_string(), format!("# {} 正文", id), folder_id);
+            note.id = id.to_string();
+            note.created_at = 1_700_000_000;
+            note.updated_at = 1_700_000_000;
+            service.repo.create_in_workspace(&note, Some(workspace_id)).unwrap();
+        }
+
+        let (tag, _) = service.tag_repo.find_or_create_by_name("重要", "ws-1").unwrap();
+        service.tag_repo.add_tag_to_note_in_workspace(
+            &NoteTagRequest { note_id: "n1".to_string(), tag_id: tag.id },
+            Some("ws-1"),
+        ).unwrap();
+
+        service
+    }
+
+    fn temp_zip_path() -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("workspace-export-{}.zip", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_export_workspace_mirrors_folder_tree() {
+        let service = seeded_service();
+        let path = temp_zip_path();
+        let result = service.export_workspace("ws-1", path.to_str().unwrap()).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(result.note_count, 3);
+        assert_eq!(result.folder_count, 3);
+
+        let entries = zip_reader::read_entries(&bytes).unwrap();
+        let names: Vec<&str> = entries.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(names, vec![
+            "工作/",
+            "工作/项目/",
+            "_etc/",
+            "工作/项目/计划.md",
+            "工作/项目/计划 (2).md",
+            "随手记.md",
+            "manifest.json",
+        ]);
+
+        let plan = String::from_utf8(entries[3].data.clone()).unwrap();
+        assert!(plan.contains("tags: [\"重要\"]\n"));
+        assert!(plan.contains("folder: [\"工作\",\"项目\"]\n"));
+    }
+
+    #[test]
+    fn test_import_exported_workspace_round_trips_counts() {
+        let path = temp_zip_path();
+        seeded_service().export_workspace("ws-1", path.to_str().unwrap()).unwrap();
+
+        let target = memory_service();
+        let summary = target.import_markdown_archive(path.to_str().unwrap(), "ws-new").unwrap();
+        assert_eq!(summary.notes_created, 3);
+        assert_eq!(summary.folders_created, 3);
+        assert_eq!(summary.tags_created, 1);
+        assert_eq!(summary.notes_skipped, 0);
+
+        // 文件夹层级和原始名称（含被清理过的 "../etc"）都被恢复
+        let tree = target.folder_repo.find_tree_by_workspace("ws-new").unwrap();
+        let mut roots: Vec<&str> = tree.iter().map(|node| node.folder.name.as_str()).collect();
+        roots.sort();
+        assert_eq!(roots, vec!["../etc", "工作"]);
+        let work = tree.iter().find(|node| node.folder.name == "工作").unwrap();
+        assert_eq!(work.children.len(), 1);
+        assert_eq!(work.children[0].folder.name, "项目");
+
+        let tags = target.tag_repo.find_tag_names_by_workspace("ws-new").unwrap();
+        assert_eq!(tags.values().collect::<Vec<_>>(), vec![&vec!["重要".to_string()]]);
+
+        // 再次导入同一归档不会重复创建
+        let again = target.import_markdown_archive(path.to_str().unwrap(), "ws-new").unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(again.notes_created, 0);
+        assert_eq!(again.notes_skipped, 3);
+        assert_eq!(again.folders_created, 0);
+        assert_eq!(again.tags_created, 0);
+    }
+
+    #[test]
+    fn test_parse_front_matter_yaml_subset() {
+        let text = "---\ntitle: 'Weekly: plan'\ntags:\n  - rust\n  - \"#async\"\nfolder: [Work, Notes]\ncreated_at: 1700000000\n---\n\nBody";
+        let (meta, body) = parse_front_matter(text);
+
+        assert_eq!(front_matter_str(&meta, "title").as_deref(), Some("Weekly: plan"));
+        assert_eq!(front_matter_list(&meta, "tags"), vec!["rust", "async"]);
+        assert_eq!(front_matter_list(&meta, "folder"), vec!["Work", "Notes"]);
+        assert_eq!(front_matter_timestamp(&meta, "created_at"), Some(1_700_000_000));
+        assert_eq!(body, "Body");
+
+        let (meta, body) = parse_front_matter("# 没有 front-matter");
+        assert!(meta.is_empty());
+        assert_eq!(body, "# 没有 front-matter");
+    }
+
+    #[test]
+    fn test_strip_number_prefix() {
+        assert_eq!(strip_number_prefix("01 介绍", "", 2), "介绍");
+        assert_eq!(strip_number_prefix("07", "", 2), "");
+        assert_eq!(strip_number_prefix("Ch02 Intro", "Ch", 2), "Intro");
+        assert_eq!(strip_number_prefix("012 Intro", "", 3), "Intro");
+        assert_eq!(strip_number_prefix("Intro", "Ch", 2), "Intro");
+    }
+
+    #[test]
+    fn test_strip_number_prefix_keeps_user_numbers() {
+        // 不是本功能生成的格式，数字属于标题本身
+        assert_eq!(strip_number_prefix("2024 Review", "", 2), "2024 Review");
+        assert_eq!(strip_number_prefix("2024年计划", "", 2), "2024年计划");
+        assert_eq!(strip_number_prefix("3. Setup", "", 2), "3. Setup");
+        assert_eq!(strip_number_prefix("10-Minute Tips", "", 2), "10-Minute Tips");
+        assert_eq!(strip_number_prefix("7 Habits", "", 2), "7 Habits");
+    }
+
+    #[test]
+    fn test_update_note_content_replaces_stale_markdown_cache() {
+        let service = memory_service();
+        let note = create_note(&service, "ws-a");
+        service.repo.update_markdown_caches(&[(note.id.clone(), Some("过期的缓存".to_string()))]).unwrap();
+
+        service.update_note(UpdateNoteRequest {
+            id: note.id.clone(),
+            title: None,
+            content: Some(r#"{"type":"doc","content":[{"type":"heading","attrs":{"level":1},"content":[{"type":"text","text":"新标题"}]}]}"#.to_string()),
+            folder_id: None,
+            is_favorite: None,
+            is_pinned: None,
+            author: None,
+        }).unwrap();
+
+        assert_eq!(service.get_note_by_id(&note.id).unwrap().markdown_cache.as_deref(), Some("# 新标题"));
+    }
+
+    #[test]
+    fn test_regenerate_markdown_caches() {
+        let service = memory_service();
+        let note = create_note(&service, "ws-a");
+        let other = create_note(&service, "ws-b");
+        service.repo.update_markdown_caches(&[(note.id.clone(), None), (other.id.clone(), None)]).unwrap();
+
+        let regenerated = service.regenerate_markdown_cache(&note.id).unwrap();
+        assert_eq!(regenerated.markdown_cache.as_deref(), Some("内容"));
+        assert_eq!(service.get_note_by_id(&note.id).unwrap().markdown_cache.as_deref(), Some("内容"));
+        assert_eq!(service.get_note_by_id(&note.id).unwrap().updated_at, note.updated_at);
+
+        assert_eq!(service.regenerate_all_markdown_caches(Some("ws-b")).unwrap(), 1);
+        assert_eq!(service.get_note_by_id(&other.id).unwrap().markdown_cache.as_deref(), Some("内容"));
+    }
+}
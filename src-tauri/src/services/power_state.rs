@@ -0,0 +1,219 @@
+use serde::Serialize;
+
+/// 设备电源与网络状态
+///
+/// 各字段为 `None` 表示当前平台无法获取（如台式机无电池、系统未暴露按流量计费信息），
+/// 调用方应视为"不限制"
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PowerState {
+    pub battery_percent: Option<u8>,  // 电池电量（0-100）
+    pub on_battery: Option<bool>,  // 是否正在使用电池供电（未接电源）
+    pub metered: Option<bool>,  // 当前网络是否按流量计费
+}
+
+/// 检测当前设备的电源与网络状态
+pub fn detect() -> PowerState {
+    platform::detect()
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::PowerState;
+    use std::fs;
+    use std::path::Path;
+    use std::process::Command;
+
+    const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+    pub fn detect() -> PowerState {
+        let (battery_percent, on_battery) = read_power_supply(Path::new(POWER_SUPPLY_DIR));
+        PowerState {
+            battery_percent,
+            on_battery,
+            metered: read_network_manager_metered(),
+        }
+    }
+
+    fn read_attr(dir: &Path, name: &str) -> Option<String> {
+        fs::read_to_string(dir.join(name)).ok().map(|s| s.trim().to_string())
+    }
+
+    /// 读取 sysfs 中的电池信息
+    ///
+    /// 只统计系统电池（忽略 scope=Device 的鼠标、键盘等外设电池），多块电池取平均电量
+    fn read_power_supply(root: &Path) -> (Option<u8>, Option<bool>) {
+        let entries = match fs::read_dir(root) {
+            Ok(entries) => entries,
+            Err(_) => return (None, None),
+        };
+
+        let mut capacities = Vec::new();
+        let mut discharging = false;
+        let mut mains_online: Option<bool> = None;
+
+        for entry in entries.flatten() {
+            let dir = entry.path();
+            match read_attr(&dir, "type").as_deref() {
+                Some("Battery") => {
+                    if read_attr(&dir, "scope").as_deref() == Some("Device") {
+                        continue;
+                    }
+                    if let Some(capacity) = read_attr(&dir, "capacity").and_then(|c| c.parse::<u8>().ok()) {
+                        capacities.push(capacity.min(100) as u32);
+                    }
+                    if read_attr(&dir, "status").as_deref() == Some("Discharging") {
+                        discharging = true;
+                    }
+                }
+                Some("Mains") => {
+                    let online = read_attr(&dir, "online").as_deref() == Some("1");
+                    mains_online = Some(mains_online.unwrap_or(false) || online);
+                }
+                _ => {}
+            }
+        }
+
+        if capacities.is_empty() {
+            return (None, None);
+        }
+
+        let percent = (capacities.iter().sum::<u32>() / capacities.len() as u32) as u8;
+        let on_battery = match mains_online {
+            Some(online) => !online,
+            None => discharging,
+        };
+
+        (Some(percent), Some(on_battery))
+    }
+
+    /// 通过 D-Bus 读取 NetworkManager 的全局 Metered 属性
+    ///
+    /// 取值：0 未知，1 是，2 否，3 推测是，4 推测否
+    fn read_network_manager_metered() -> Option<bool> {
+        let output = Command::new("busctl")
+            .args([
+                "--system",
+                "get-property",
+                "org.freedesktop.NetworkManager",
+                "/org/freedesktop/NetworkManager",
+                "org.freedesktop.NetworkManager",
+                "Metered",
+            ])
+            .output()
+            .ok()?;
+
+        if !output.status.success() {
+            return None;
+        }
+
+        // 输出格式："u 1"
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        match stdout.split_whitespace().nth(1)?.parse::<u32>().ok()? {
+            1 | 3 => Some(true),
+            2 | 4 => Some(false),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::PowerState;
+    use std::process::Command;
+
+    /// 解析 `pmset -g batt` 的输出
+    ///
+    /// ```text
+    /// Now drawing from 'Battery Power'
+    ///  -InternalBattery-0 (id=1234567)	85%; discharging; 4:12 remaining present: true
+    /// ```
+    ///
+    /// macOS 的低数据模式只能通过 Network.framework 获取，这里不检测按流量计费
+    pub fn detect() -> PowerState {
+        let output = match Command::new("pmset").args(["-g", "batt"]).output() {
+            Ok(output) if output.status.success() => output,
+            _ => return PowerState::default(),
+        };
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        let battery_percent = stdout
+            .lines()
+            .find(|line| line.contains("InternalBattery"))
+            .and_then(|line| {
+                let end = line.find('%')?;
+                let start = line[..end].rfind(|c: char| !c.is_ascii_digit())? + 1;
+                line[start..end].parse::<u8>().ok()
+            });
+
+        let on_battery = battery_percent.map(|_| stdout.contains("'Battery Power'"));
+
+        PowerState {
+            battery_percent,
+            on_battery,
+            metered: None,
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::PowerState;
+
+    /// `SYSTEM_POWER_STATUS`（winbase.h）
+    #[repr(C)]
+    #[derive(Default)]
+    struct SystemPowerStatus {
+        ac_line_status: u8,
+        battery_flag: u8,
+        battery_life_percent: u8,
+        system_status_flag: u8,
+        battery_life_time: u32,
+        battery_full_life_time: u32,
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetSystemPowerStatus(status: *mut SystemPowerStatus) -> i32;
+    }
+
+    /// 通过 GetSystemPowerStatus 读取电池状态
+    ///
+    /// 按流量计费信息只能通过 WinRT NetworkInformation 获取，这里不检测
+    pub fn detect() -> PowerState {
+        let mut status = SystemPowerStatus::default();
+        // SAFETY: status 是有效的可写指针，函数只会填充该结构体
+        if unsafe { GetSystemPowerStatus(&mut status) } == 0 {
+            return PowerState::default();
+        }
+
+        // battery_flag 128 表示无系统电池，255 / battery_life_percent 255 表示未知
+        let has_battery = status.battery_flag != 128 && status.battery_flag != 255;
+        let battery_percent = if has_battery && status.battery_life_percent <= 100 {
+            Some(status.battery_life_percent)
+        } else {
+            None
+        };
+
+        let on_battery = match (battery_percent, status.ac_line_status) {
+            (Some(_), 0) => Some(true),
+            (Some(_), 1) => Some(false),
+            _ => None,
+        };
+
+        PowerState {
+            battery_percent,
+            on_battery,
+            metered: None,
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+mod platform {
+    use super::PowerState;
+
+    pub fn detect() -> PowerState {
+        PowerState::default()
+    }
+}
@@ -2,10 +2,11 @@
 #[allow(unused_imports)]
 use crate::models::{
     Note, Folder, Tag, NoteSnapshot, SyncRequest, SyncResponse, SyncReport,
-    NoteTagRelation, ConflictStrategy
+    NoteTagRelation, ConflictStrategy, SyncQueueEntry
 };
 use crate::models::error::{Result, AppError};
-use crate::services::SyncService;
+use crate::services::{SyncService, SyncQueueService, AppSettingsService};
+use crate::services::sync_queue_service::STATUS_DEAD_LETTER;
 use crate::database::repositories::tag_repository::TagRepository;
 use crate::database::repositories::snapshot_repository::SnapshotRepository;
 use r2d2::Pool;
@@ -20,6 +21,7 @@ pub struct SingleSyncService {
     sync_service: SyncService,
     tag_repository: TagRepository,
     snapshot_repository: SnapshotRepository,
+    sync_queue: SyncQueueService,
 }
 
 impl SingleSyncService {
@@ -28,12 +30,19 @@ impl SingleSyncService {
         Self {
             sync_service,
             tag_repository: TagRepository::new(pool.clone()),
-            snapshot_repository: SnapshotRepository::new(pool),
+            snapshot_repository: SnapshotRepository::new(pool.clone()),
+            sync_queue: SyncQueueService::new(pool),
         }
     }
 
     /// 同步单个笔记及其关联数据（标签、快照）
     pub async fn sync_single_note(&self, note_id: &str) -> Result<SyncReport> {
+        self.sync_service.events()
+            .track("note", Some(note_id), self.run_sync_single_note(note_id))
+            .await
+    }
+
+    async fn run_sync_single_note(&self, note_id: &str) -> Result<SyncReport> {
         log::info!("[SingleSync] 同步单个笔记: {}", note_id);
 
         // 1. 尝试获取笔记数据（无论是否是脏数据）
@@ -43,16 +52,20 @@ impl SingleSyncService {
         let tags = self.get_dirty_tags_by_note_id(note_id)?;
 
         // 3. 获取该笔记的所有脏快照
-        let snapshots = self.get_dirty_snapshots_by_note_id(note_id)?;
+        let (snapshots, skipped_snapshots) = self.sync_service
+            .filter_syncable_snapshots(self.get_dirty_snapshots_by_note_id(note_id)?)?;
 
         // 4. 获取笔记-标签关联（只获取脏关联）
         let note_tags = self.get_dirty_note_tag_relations(note_id)?;
 
+        // 5. 获取该笔记的脏附件
+        let attachments = self.sync_service.get_dirty_attachments_for_notes(&[note_id.to_string()])?;
+
         // 记录是否有数据要推送（在移动之前）
-        let has_data_to_push = note_opt.is_some() || !tags.is_empty() || !snapshots.is_empty();
+        let has_data_to_push = note_opt.is_some() || !tags.is_empty() || !snapshots.is_empty() || !attachments.is_empty();
         let pushed_note_count = if note_opt.is_some() { 1 } else { 0 };
 
-        // 5. 构建同步请求
+        // 6. 构建同步请求
         // 如果笔记存在且是脏数据，就推送；否则只拉取服务器更新
         let request = SyncRequest {
             workspaces: None, // 不同步工作空间
@@ -61,24 +74,30 @@ impl SingleSyncService {
             tags: Some(tags.into_iter().map(|t| t.into()).collect()),
             snapshots: Some(snapshots.into_iter().map(|s| s.into()).collect()),
             note_tags: if note_tags.is_empty() { None } else { Some(note_tags.into_iter().map(|nt| nt.into()).collect()) },
+            attachments: if attachments.is_empty() { None } else { Some(attachments.into_iter().map(|a| a.into()).collect()) },
             last_sync_at: self.sync_service.get_last_sync_time()?,
+            cursor: self.sync_service.get_sync_cursor(None)?,
+            workspace_id: None,
             conflict_resolution: ConflictStrategy::default(),
+            conflict_strategies: self.sync_service.get_conflict_strategies()?,
             device_id: None, // 在 send_sync_request 中设置
+            idempotency_key: None, // 在 send_sync_request 中设置
         };
 
-        // 6. 发送同步请求
+        // 7. 发送同步请求
         let response = self.sync_service.send_sync_request(&request).await?;
 
-        // 7. 应用服务器响应
+        // 8. 应用服务器响应
         self.sync_service.apply_sync_response(&response)?;
 
-        // 8. 清理脏标记（如果有推送数据）
+        // 9. 清理脏标记（如果有推送数据）
         if has_data_to_push {
             self.sync_service.clear_dirty_markers(&request, response.last_sync_at)?;
         }
 
-        // 9. 更新同步状态
+        // 10. 更新同步状态
         self.sync_service.update_sync_state(response.last_sync_at, response.conflicts.len() as i32)?;
+        self.sync_service.save_sync_cursor(None, response.cursor)?;
 
         let report = SyncReport {
             success: response.status != "error",
@@ -89,6 +108,7 @@ impl SingleSyncService {
             pushed_tags: response.pushed_tags,
             pushed_snapshots: response.pushed_snapshots,
             pushed_note_tags: response.pushed_note_tags,
+            pushed_attachments: response.pushed_attachments,
             // ✅ 使用服务器计算的拉取统计
             pulled_workspaces: response.pulled_workspaces,
             pulled_notes: response.pulled_notes,
@@ -96,18 +116,22 @@ impl SingleSyncService {
             pulled_tags: response.pulled_tags,
             pulled_snapshots: response.pulled_snapshots,
             pulled_note_tags: response.pulled_note_tags,
+            pulled_attachments: response.pulled_attachments,
             // 删除的数据统计
             deleted_workspaces: response.deleted_workspace_ids.len(),
             deleted_notes: response.deleted_note_ids.len(),
             deleted_folders: response.deleted_folder_ids.len(),
             deleted_tags: response.deleted_tag_ids.len(),
+            deleted_attachments: response.deleted_attachment_ids.len(),
             // 冲突和错误
             conflict_count: response.conflicts.len(),
+            skipped_snapshots,
             error: if response.status == "error" {
                 Some("Single note sync failed".to_string())
             } else {
                 None
             },
+            timing: None,
             // 兼容旧版本
             pushed_count: None,
             pulled_count: None,
@@ -124,6 +148,12 @@ impl SingleSyncService {
 
     /// 同步单个标签
     pub async fn sync_single_tag(&self, tag_id: &str) -> Result<SyncReport> {
+        self.sync_service.events()
+            .track("tag", Some(tag_id), self.run_sync_single_tag(tag_id))
+            .await
+    }
+
+    async fn run_sync_single_tag(&self, tag_id: &str) -> Result<SyncReport> {
         log::info!("[SingleSync] 同步单个标签: {}", tag_id);
 
         // 1. 获取标签数据
@@ -138,9 +168,14 @@ impl SingleSyncService {
             tags: Some(vec![tag.into()]),
             snapshots: None,
             note_tags: None,
+            attachments: None,
             last_sync_at: self.sync_service.get_last_sync_time()?,
+            cursor: self.sync_service.get_sync_cursor(None)?,
+            workspace_id: None,
             conflict_resolution: ConflictStrategy::default(),
+            conflict_strategies: self.sync_service.get_conflict_strategies()?,
             device_id: None,
+            idempotency_key: None,
         };
 
         // 3. 发送同步请求
@@ -154,6 +189,7 @@ impl SingleSyncService {
 
         // 6. 更新同步状态
         self.sync_service.update_sync_state(response.last_sync_at, response.conflicts.len() as i32)?;
+        self.sync_service.save_sync_cursor(None, response.cursor)?;
 
         Ok(SyncReport {
             success: response.status != "error",
@@ -163,18 +199,23 @@ impl SingleSyncService {
             pushed_tags: response.pushed_tags,
             pushed_snapshots: response.pushed_snapshots,
             pushed_note_tags: response.pushed_note_tags,
+            pushed_attachments: response.pushed_attachments,
             pulled_workspaces: response.pulled_workspaces,
             pulled_notes: response.pulled_notes,
             pulled_folders: response.pulled_folders,
             pulled_tags: response.pulled_tags,
             pulled_snapshots: response.pulled_snapshots,
             pulled_note_tags: response.pulled_note_tags,
+            pulled_attachments: response.pulled_attachments,
             deleted_workspaces: response.deleted_workspace_ids.len(),
             deleted_notes: response.deleted_note_ids.len(),
             deleted_folders: response.deleted_folder_ids.len(),
             deleted_tags: response.deleted_tag_ids.len(),
+            deleted_attachments: response.deleted_attachment_ids.len(),
             conflict_count: response.conflicts.len(),
+            skipped_snapshots: 0,
             error: None,
+            timing: None,
             pushed_count: None,
             pulled_count: None,
         })
@@ -182,6 +223,12 @@ impl SingleSyncService {
 
     /// 同步单个快照
     pub async fn sync_single_snapshot(&self, snapshot_id: &str) -> Result<SyncReport> {
+        self.sync_service.events()
+            .track("snapshot", Some(snapshot_id), self.run_sync_single_snapshot(snapshot_id))
+            .await
+    }
+
+    async fn run_sync_single_snapshot(&self, snapshot_id: &str) -> Result<SyncReport> {
         log::info!("[SingleSync] 同步单个快照: {}", snapshot_id);
 
         // 1. 获取快照数据
@@ -196,9 +243,14 @@ impl SingleSyncService {
             tags: None,
             snapshots: Some(vec![snapshot.into()]),
             note_tags: None,
+            attachments: None,
             last_sync_at: self.sync_service.get_last_sync_time()?,
+            cursor: self.sync_service.get_sync_cursor(None)?,
+            workspace_id: None,
             conflict_resolution: ConflictStrategy::default(),
+            conflict_strategies: self.sync_service.get_conflict_strategies()?,
             device_id: None,
+            idempotency_key: None,
         };
 
         // 3. 发送同步请求
@@ -212,6 +264,7 @@ impl SingleSyncService {
 
         // 6. 更新同步状态
         self.sync_service.update_sync_state(response.last_sync_at, response.conflicts.len() as i32)?;
+        self.sync_service.save_sync_cursor(None, response.cursor)?;
 
         Ok(SyncReport {
             success: response.status != "error",
@@ -221,18 +274,23 @@ impl SingleSyncService {
             pushed_tags: response.pushed_tags,
             pushed_snapshots: response.pushed_snapshots,
             pushed_note_tags: response.pushed_note_tags,
+            pushed_attachments: response.pushed_attachments,
             pulled_workspaces: response.pulled_workspaces,
             pulled_notes: response.pulled_notes,
             pulled_folders: response.pulled_folders,
             pulled_tags: response.pulled_tags,
             pulled_snapshots: response.pulled_snapshots,
             pulled_note_tags: response.pulled_note_tags,
+            pulled_attachments: response.pulled_attachments,
             deleted_workspaces: response.deleted_workspace_ids.len(),
             deleted_notes: response.deleted_note_ids.len(),
             deleted_folders: response.deleted_folder_ids.len(),
             deleted_tags: response.deleted_tag_ids.len(),
+            deleted_attachments: response.deleted_attachment_ids.len(),
             conflict_count: response.conflicts.len(),
+            skipped_snapshots: 0,
             error: None,
+            timing: None,
             pushed_count: None,
             pulled_count: None,
         })
@@ -241,6 +299,12 @@ impl SingleSyncService {
     /// 同步单个文件夹及其包含的所有笔记（含标签和快照）
     /// 递归同步所有子文件夹和它们的笔记
     pub async fn sync_single_folder(&self, folder_id: &str) -> Result<SyncReport> {
+        self.sync_service.events()
+            .track("folder", Some(folder_id), self.run_sync_single_folder(folder_id))
+            .await
+    }
+
+    async fn run_sync_single_folder(&self, folder_id: &str) -> Result<SyncReport> {
         log::info!("[SingleSync] 同步单个文件夹: {}", folder_id);
 
         // 1. 递归获取所有子文件夹
@@ -282,6 +346,10 @@ impl SingleSyncService {
 
         let tags_vec: Vec<Tag> = all_tags.into_values().collect();
 
+        // 这些笔记的脏附件
+        let note_ids: Vec<String> = all_notes.iter().map(|n| n.id.clone()).collect();
+        let all_attachments = self.sync_service.get_dirty_attachments_for_notes(&note_ids)?;
+
         // 5. 构建同步请求
         let notes_count = all_notes.len();
         let folders_count = all_folders.len();
@@ -291,13 +359,18 @@ impl SingleSyncService {
         let request = SyncRequest {
             workspaces: None,
             conflict_resolution: ConflictStrategy::default(),
+            conflict_strategies: self.sync_service.get_conflict_strategies()?,
             device_id: None,
+            idempotency_key: None,
             notes: if all_notes.is_empty() { None } else { Some(all_notes.into_iter().map(|n| n.into()).collect()) },
             folders: if all_folders.is_empty() { None } else { Some(all_folders.into_iter().map(|f| f.into()).collect()) },
             tags: if tags_vec.is_empty() { None } else { Some(tags_vec.into_iter().map(|t| t.into()).collect()) },
             snapshots: if all_snapshots.is_empty() { None } else { Some(all_snapshots.into_iter().map(|s| s.into()).collect()) },
             note_tags: if all_note_tags.is_empty() { None } else { Some(all_note_tags.into_iter().map(|nt| nt.into()).collect()) },
+            attachments: if all_attachments.is_empty() { None } else { Some(all_attachments.into_iter().map(|a| a.into()).collect()) },
             last_sync_at: self.sync_service.get_last_sync_time()?,
+            cursor: self.sync_service.get_sync_cursor(None)?,
+            workspace_id: None,
         };
 
         // 6. 发送同步请求
@@ -311,6 +384,7 @@ impl SingleSyncService {
 
         // 9. 更新同步状态
         self.sync_service.update_sync_state(response.last_sync_at, response.conflicts.len() as i32)?;
+        self.sync_service.save_sync_cursor(None, response.cursor)?;
 
         let report = SyncReport {
             success: response.status != "error",
@@ -321,6 +395,7 @@ impl SingleSyncService {
             pushed_tags: response.pushed_tags,
             pushed_snapshots: response.pushed_snapshots,
             pushed_note_tags: response.pushed_note_tags,
+            pushed_attachments: response.pushed_attachments,
             // ✅ 使用服务器计算的拉取统计
             pulled_workspaces: response.pulled_workspaces,
             pulled_notes: response.pulled_notes,
@@ -328,18 +403,22 @@ impl SingleSyncService {
             pulled_tags: response.pulled_tags,
             pulled_snapshots: response.pulled_snapshots,
             pulled_note_tags: response.pulled_note_tags,
+            pulled_attachments: response.pulled_attachments,
             // 删除的数据统计
             deleted_workspaces: response.deleted_workspace_ids.len(),
             deleted_notes: response.deleted_note_ids.len(),
             deleted_folders: response.deleted_folder_ids.len(),
             deleted_tags: response.deleted_tag_ids.len(),
+            deleted_attachments: response.deleted_attachment_ids.len(),
             // 冲突和错误
             conflict_count: response.conflicts.len(),
+            skipped_snapshots: 0,
             error: if response.status == "error" {
                 Some("Folder sync failed".to_string())
             } else {
                 None
             },
+            timing: None,
             // 兼容旧版本
             pushed_count: None,
             pulled_count: None,
@@ -354,6 +433,97 @@ impl SingleSyncService {
         Ok(report)
     }
 
+    /// 按实体类型同步单个实体（note / tag / snapshot / folder）
+    pub async fn sync_entity(&self, entity_type: &str, entity_id: &str) -> Result<SyncReport> {
+        match entity_type {
+            "note" => self.sync_single_note(entity_id).await,
+            "tag" => self.sync_single_tag(entity_id).await,
+            "snapshot" => self.sync_single_snapshot(entity_id).await,
+            "folder" => self.sync_single_folder(entity_id).await,
+            _ => Err(AppError::InvalidInput(format!("不支持的同步类型: {}", entity_type))),
+        }
+    }
+
+    /// 同步单个实体，网络或服务器错误时加入重试队列，成功时移出队列
+    pub async fn sync_or_enqueue(&self, entity_type: &str, entity_id: &str) -> Result<SyncReport> {
+        match self.sync_entity(entity_type, entity_id).await {
+            Ok(report) => {
+                self.sync_queue.remove(entity_type, entity_id)?;
+                Ok(report)
+            }
+            Err(e) => {
+                if is_retryable(&e) {
+                    self.sync_queue.enqueue(entity_type, entity_id, &e.to_string())?;
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// 是否有已到重试时间的队列条目
+    pub fn has_due_retries(&self) -> Result<bool> {
+        Ok(!self.sync_queue.find_due()?.is_empty())
+    }
+
+    /// 重试队列中已到期的条目
+    ///
+    /// 失败次数达到 `max_retries` 或遇到不可重试的错误时转入死信。返回成功同步的数量
+    pub async fn retry_due(&self, max_retries: u32) -> Result<usize> {
+        let entries = self.sync_queue.find_due()?;
+        let mut succeeded = 0;
+
+        for entry in &entries {
+            match self.sync_entity(&entry.entity_type, &entry.entity_id).await {
+                Ok(_) => {
+                    self.sync_queue.remove(&entry.entity_type, &entry.entity_id)?;
+                    succeeded += 1;
+                }
+                Err(e) => {
+                    self.sync_queue.record_failure(entry, &e.to_string(), is_retryable(&e), max_retries)?;
+                }
+            }
+        }
+
+        if !entries.is_empty() {
+            log::info!("[SingleSync] 重试队列: due={}, succeeded={}", entries.len(), succeeded);
+        }
+
+        Ok(succeeded)
+    }
+
+    /// 列出死信条目（超过重试上限或不可重试的单项同步）
+    pub fn list_dead_letters(&self) -> Result<Vec<SyncQueueEntry>> {
+        self.sync_queue.find_dead_letters()
+    }
+
+    /// 手动重试死信条目
+    ///
+    /// 重试次数清零后立即同步一次；失败时重新进入自动重试流程
+    pub async fn retry_dead_letter(&self, id: i64) -> Result<SyncReport> {
+        let entry = self.sync_queue.find_by_id(id)?
+            .ok_or_else(|| AppError::NotFound(format!("重试队列条目不存在: {}", id)))?;
+
+        if entry.status != STATUS_DEAD_LETTER {
+            return Err(AppError::InvalidOperation(format!("条目 {} 不在死信队列中", id)));
+        }
+
+        self.sync_queue.reset(id)?;
+        let entry = SyncQueueEntry { attempts: 0, ..entry };
+
+        match self.sync_entity(&entry.entity_type, &entry.entity_id).await {
+            Ok(report) => {
+                self.sync_queue.remove(&entry.entity_type, &entry.entity_id)?;
+                Ok(report)
+            }
+            Err(e) => {
+                let max_retries = AppSettingsService::new(self.sync_service.get_pool().clone())
+                    .get_max_sync_retries()?;
+                self.sync_queue.record_failure(&entry, &e.to_string(), is_retryable(&e), max_retries)?;
+                Err(e)
+            }
+        }
+    }
+
     // ===== 私有辅助方法 =====
 
     /// 递归获取所有子文件夹ID（包括自己）
@@ -416,7 +586,7 @@ impl SingleSyncService {
 
         let mut stmt = conn.prepare(
             "SELECT id, name, parent_id, icon, color, sort_order,
-                    created_at, updated_at,
+                    workspace_id, created_at, updated_at,
                     server_ver, is_dirty, last_synced_at
              FROM folders
              WHERE id = ?1"
@@ -462,7 +632,7 @@ impl SingleSyncService {
 
         let query = format!(
             "SELECT id, name, parent_id, icon, color, sort_order,
-                    created_at, updated_at,
+                    workspace_id, created_at, updated_at,
                     server_ver, is_dirty, last_synced_at
              FROM folders
              WHERE id IN ({}) AND is_dirty = 1",  // ✅ 只返回脏数据
@@ -514,7 +684,7 @@ impl SingleSyncService {
             "SELECT id, title, content, excerpt, markdown_cache, workspace_id, folder_id,
                     is_favorite, is_deleted, is_pinned, author,
                     created_at, updated_at, deleted_at, word_count, read_time_minutes,
-                    server_ver, is_dirty, last_synced_at
+                    server_ver, is_dirty, last_synced_at, is_private
              FROM notes
              WHERE folder_id = ?1 AND is_deleted = 0 AND is_dirty = 1"  // ✅ 只返回脏数据
         ).map_err(|e| AppError::DatabaseError(format!("准备查询失败: {}", e)))?;
@@ -531,6 +701,7 @@ impl SingleSyncService {
                 is_favorite: row.get(7)?,
                 is_deleted: row.get(8)?,
                 is_pinned: row.get(9)?,
+                is_private: row.get(19)?,
                 author: row.get(10)?,
                 created_at: row.get(11)?,
                 updated_at: row.get(12)?,
@@ -563,7 +734,7 @@ impl SingleSyncService {
             "SELECT id, title, content, excerpt, markdown_cache, workspace_id, folder_id,
                     is_favorite, is_deleted, is_pinned, author,
                     created_at, updated_at, deleted_at, word_count, read_time_minutes,
-                    server_ver, is_dirty, last_synced_at
+                    server_ver, is_dirty, last_synced_at, is_private
              FROM notes
              WHERE id = ?1 AND is_dirty = 1"  // ✅ 只返回脏数据
         ).map_err(|e| AppError::DatabaseError(format!("准备查询失败: {}", e)))?;
@@ -580,6 +751,7 @@ impl SingleSyncService {
                 is_favorite: row.get(7)?,
                 is_deleted: row.get(8)?,
                 is_pinned: row.get(9)?,
+                is_private: row.get(19)?,
                 author: row.get(10)?,
                 created_at: row.get(11)?,
                 updated_at: row.get(12)?,
@@ -617,7 +789,7 @@ impl SingleSyncService {
             "SELECT id, title, content, excerpt, markdown_cache, workspace_id, folder_id,
                     is_favorite, is_deleted, is_pinned, author,
                     created_at, updated_at, deleted_at, word_count, read_time_minutes,
-                    server_ver, is_dirty, last_synced_at
+                    server_ver, is_dirty, last_synced_at, is_private
              FROM notes
              WHERE id = ?1 AND is_dirty = 1"  // ✅ 只返回脏数据
         ).map_err(|e| AppError::DatabaseError(format!("准备查询失败: {}", e)))?;
@@ -634,6 +806,7 @@ impl SingleSyncService {
                 is_favorite: row.get(7)?,
                 is_deleted: row.get(8)?,
                 is_pinned: row.get(9)?,
+                is_private: row.get(19)?,
                 author: row.get(10)?,
                 created_at: row.get(11)?,
                 updated_at: row.get(12)?,
@@ -728,7 +901,7 @@ impl SingleSyncService {
         Ok(snapshots)
     }
 
-    /// 获取笔记的所有脏笔记-标签关联（is_deleted = 0）
+    /// 获取笔记的所有脏笔记-标签关联（含已删除的关联，以便同步取消标签）
     /// 注意：客户端 note_tags 表没有 user_id 字段，使用空字符串代替
     fn get_dirty_note_tag_relations(&self, note_id: &str) -> Result<Vec<NoteTagRelation>> {
         use r2d2_sqlite::rusqlite::params;
@@ -741,7 +914,7 @@ impl SingleSyncService {
         let mut stmt = conn.prepare(
             "SELECT note_id, tag_id, created_at, is_deleted, deleted_at
              FROM note_tags
-             WHERE note_id = ?1 AND is_deleted = 0"  // ✅ 只返回未删除的关联
+             WHERE note_id = ?1 AND is_dirty = 1"  // ✅ 只返回有变化的关联
         ).map_err(|e| AppError::DatabaseError(format!("准备查询失败: {}", e)))?;
 
         let relations = stmt.query_map(params![note_id], |row| {
@@ -762,3 +935,8 @@ impl SingleSyncService {
         Ok(relations)
     }
 }
+
+/// 是否为可自动重试的错误（网络中断、服务器暂时不可用等）
+fn is_retryable(error: &AppError) -> bool {
+    matches!(error, AppError::NetworkError(_) | AppError::SyncError(_))
+}
@@ -1,9 +1,14 @@
-use crate::models::{NoteSnapshot, CreateSnapshotRequest, SnapshotListItem};
+use crate::models::{NoteSnapshot, CreateSnapshotRequest, SnapshotListItem, ServerLimits, DEFAULT_MAX_SNAPSHOTS_PER_NOTE};
 use crate::models::error::{Result, AppError};
 use uuid::Uuid;
 use r2d2::Pool;
-use r2d2_sqlite::SqliteConnectionManager;
+use r2d2_sqlite::{rusqlite, SqliteConnectionManager};
 use chrono::Utc;
+use serde::Serialize;
+use std::fs::File;
+use std::io::BufWriter;
+
+use super::zip_writer::ZipWriter;
 
 /// 快照服务
 ///
@@ -72,9 +77,44 @@ impl SnapshotService {
         ).map_err(|e| AppError::DatabaseError(format!("创建快照失败: {}", e)))?;
 
         log::info!("已为笔记 {} 创建快照 {}", id, snapshot.note_id);
+
+        // 与服务器保持一致，只保留最新的快照，避免同步后被服务器清理
+        let limit = Self::max_snapshots_per_note(&conn);
+        let pruned = Self::prune_snapshots(&conn, &snapshot.note_id, limit)?;
+        if pruned > 0 {
+            log::info!("笔记 {} 快照超过上限 {}，已删除 {} 个最旧的快照", snapshot.note_id, limit, pruned);
+        }
+
         Ok(snapshot)
     }
 
+    /// 每篇笔记保留的最大快照数量（读取缓存的服务器限制，未缓存时使用默认值）
+    fn max_snapshots_per_note(conn: &rusqlite::Connection) -> i64 {
+        conn.query_row(
+            "SELECT value FROM settings WHERE key = 'server_limits'",
+            [],
+            |row| row.get::<_, String>(0),
+        )
+        .ok()
+        .and_then(|json| serde_json::from_str::<ServerLimits>(&json).ok())
+        .map(|limits| limits.max_snapshots_per_note)
+        .filter(|limit| *limit > 0)
+        .unwrap_or(DEFAULT_MAX_SNAPSHOTS_PER_NOTE)
+    }
+
+    /// 删除笔记超出上限的最旧快照，返回删除数量
+    fn prune_snapshots(conn: &rusqlite::Connection, note_id: &str, limit: i64) -> Result<usize> {
+        conn.execute(
+            "DELETE FROM note_snapshots
+             WHERE note_id = ?1 AND id NOT IN (
+                 SELECT id FROM note_snapshots WHERE note_id = ?1
+                 ORDER BY created_at DESC, rowid DESC
+                 LIMIT ?2
+             )",
+            rusqlite::params![note_id, limit],
+        ).map_err(|e| AppError::DatabaseError(format!("清理旧快照失败: {}", e)))
+    }
+
     /// 列出笔记的所有快照
     pub fn list_snapshots(&self, note_id: &str) -> Result<Vec<SnapshotListItem>> {
         let conn = self.pool.get()
@@ -164,6 +204,138 @@ impl SnapshotService {
             [note_id]
         ).map_err(|e| AppError::DatabaseError(format!("删除笔记快照失败: {}", e)))
     }
+
+    /// 导出笔记归档（zip）
+    ///
+    /// 归档内容：
+    /// - `note.md`：笔记当前内容（优先使用 markdown 缓存）
+    /// - `snapshots/<时间>-<id>.md|json`：每个快照一个文件，按创建时间命名
+    /// - `manifest.json`：笔记元数据及快照列表
+    pub fn export_note_archive(&self, note_id: &str, path: &str) -> Result<NoteArchiveResult> {
+        let conn = self.pool.get()
+            .map_err(|e| AppError::DatabaseError(format!("获取数据库连接失败: {}", e)))?;
+
+        let (title, content, markdown_cache, created_at, updated_at): (String, String, Option<String>, i64, i64) = conn
+            .query_row(
+                "SELECT title, content, markdown_cache, created_at, updated_at
+                 FROM notes
+                 WHERE id = ?1 AND is_deleted = 0",
+                [note_id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
+            )
+            .map_err(|_| AppError::NoteNotFound(note_id.to_string()))?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, note_id, title, content, snapshot_name, created_at, workspace_id, server_ver, is_dirty, last_synced_at
+             FROM note_snapshots
+             WHERE note_id = ?1
+             ORDER BY created_at ASC"
+        ).map_err(|e| AppError::DatabaseError(format!("列出快照失败: {}", e)))?;
+
+        let snapshots = stmt.query_map([note_id], |row| {
+            Ok(NoteSnapshot {
+                id: row.get(0)?,
+                note_id: row.get(1)?,
+                title: row.get(2)?,
+                content: row.get(3)?,
+                snapshot_name: row.get(4)?,
+                created_at: row.get(5)?,
+                workspace_id: row.get(6)?,
+                server_ver: row.get(7)?,
+                is_dirty: row.get(8)?,
+                last_synced_at: row.get(9)?,
+            })
+        })
+        .map_err(|e| AppError::DatabaseError(format!("解析快照失败: {}", e)))?
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| AppError::DatabaseError(format!("收集快照失败: {}", e)))?;
+
+        let file = File::create(path)
+            .map_err(|e| AppError::Internal(format!("创建归档文件失败: {}", e)))?;
+        let mut zip = ZipWriter::new(BufWriter::new(file));
+
+        let note_file = match markdown_cache.filter(|m| !m.is_empty()) {
+            Some(markdown) => {
+                zip.add_file("note.md", markdown.as_bytes(), updated_at)?;
+                "note.md".to_string()
+            }
+            None => {
+                let name = format!("note.{}", content_extension(&content));
+                zip.add_file(&name, content.as_bytes(), updated_at)?;
+                name
+            }
+        };
+
+        let mut manifest_snapshots = Vec::with_capacity(snapshots.len());
+        for snapshot in &snapshots {
+            let short_id: String = snapshot.id.chars().take(8).collect();
+            let file_name = format!(
+                "snapshots/{}-{}.{}",
+                format_file_timestamp(snapshot.created_at),
+                short_id,
+                content_extension(&snapshot.content),
+            );
+            zip.add_file(&file_name, snapshot.content.as_bytes(), snapshot.created_at)?;
+
+            manifest_snapshots.push(serde_json::json!({
+                "id": snapshot.id,
+                "title": snapshot.title,
+                "snapshotName": snapshot.snapshot_name,
+                "createdAt": snapshot.created_at,
+                "file": file_name,
+            }));
+        }
+
+        let exported_at = Utc::now().timestamp();
+        let manifest = serde_json::json!({
+            "noteId": note_id,
+            "title": title,
+            "createdAt": created_at,
+            "updatedAt": updated_at,
+            "exportedAt": exported_at,
+            "noteFile": note_file,
+            "snapshots": manifest_snapshots,
+        });
+        let manifest = serde_json::to_vec_pretty(&manifest)
+            .map_err(|e| AppError::Internal(format!("序列化归档清单失败: {}", e)))?;
+        zip.add_file("manifest.json", &manifest, exported_at)?;
+
+        zip.finish()?;
+
+        log::info!("已导出笔记归档: note_id={}, snapshots={}, path={}", note_id, snapshots.len(), path);
+
+        Ok(NoteArchiveResult {
+            path: path.to_string(),
+            snapshot_count: snapshots.len(),
+        })
+    }
+}
+
+/// 笔记归档导出结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteArchiveResult {
+    pub path: String,           // 归档文件路径
+    pub snapshot_count: usize,  // 归档中的快照数量
+}
+
+/// 根据内容判断文件扩展名（Tiptap JSON 使用 .json，其余按 Markdown 处理）
+fn content_extension(content: &str) -> &'static str {
+    let trimmed = content.trim_start();
+    if trimmed.starts_with('{') && serde_json::from_str::<serde_json::Value>(content).is_ok() {
+        "json"
+    } else {
+        "md"
+    }
+}
+
+/// 格式化时间戳为文件名安全的字符串（本地时区）
+fn format_file_timestamp(timestamp: i64) -> String {
+    chrono::DateTime::<Utc>::from_timestamp(timestamp, 0)
+        .unwrap_or_else(Utc::now)
+        .with_timezone(&chrono::Local)
+        .format("%Y%m%d-%H%M%S")
+        .to_string()
 }
 
 /// 格式化时间戳为可读字符串
@@ -172,3 +344,44 @@ fn format_datetime(timestamp: i64) -> String {
         .unwrap_or_else(|| chrono::Utc::now());
     datetime.format("%Y-%m-%d %H:%M:%S").to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory_service() -> SnapshotService {
+        let pool = crate::database::memory_pool();
+        SnapshotService::new(pool)
+    }
+
+    fn snapshot_request(name: &str) -> CreateSnapshotRequest {
+        CreateSnapshotRequest {
+            note_id: "note-1".to_string(),
+            title: "标题".to_string(),
+            content: name.to_string(),
+            snapshot_name: Some(name.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_create_snapshot_keeps_newest_within_server_limit() {
+        let service = memory_service();
+        service.pool.get().unwrap().execute_batch(
+            "INSERT INTO notes (id, title, content, created_at, updated_at) VALUES ('note-1', '标题', '', 0, 0);
+             INSERT INTO settings (key, value) VALUES ('server_limits', '{\"max_note_length\":0,\"max_snapshots_per_note\":3}');",
+        ).unwrap();
+
+        for i in 1..=5 {
+            service.create_snapshot(snapshot_request(&format!("v{}", i))).unwrap();
+        }
+
+        let names: Vec<_> = service.list_snapshots("note-1").unwrap()
+            .into_iter()
+            .map(|s| s.snapshot_name.unwrap())
+            .collect();
+        assert_eq!(names.len(), 3);
+        for name in ["v3", "v4", "v5"] {
+            assert!(names.contains(&name.to_string()), "missing {}", name);
+        }
+    }
+}
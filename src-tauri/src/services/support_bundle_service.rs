@@ -0,0 +1,445 @@
+use crate::database::DbPool;
+use crate::database::repositories::{EditorSettingsRepository, KeybindingRepository};
+use crate::models::{PendingChangeKind, SyncStatus};
+use crate::models::error::{Result, AppError};
+use crate::services::{AppSettingsService, EditorSettingsService, KeybindingService, MigrationService, SyncQueueService, SyncService};
+use crate::services::zip_writer::ZipWriter;
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{BufWriter, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// 打包的最近日志文件数量
+const MAX_LOG_FILES: usize = 3;
+
+/// 每个日志文件最多打包的字节数（保留末尾部分）
+const MAX_LOG_BYTES: u64 = 512 * 1024;
+
+/// 需要脱敏的字段名（ASCII 不区分大小写）
+const SECRET_KEYS: &[&str] = &[
+    "access_token",
+    "accesstoken",
+    "refresh_token",
+    "refreshtoken",
+    "token",
+    "password",
+    "secret",
+    "authorization",
+    "api_key",
+    "apikey",
+];
+
+/// 脱敏后的占位符
+const REDACTED: &str = "[REDACTED]";
+const REDACTED_EMAIL: &str = "[REDACTED_EMAIL]";
+const REDACTED_TOKEN: &str = "[REDACTED_TOKEN]";
+
+/// 支持包导出结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SupportBundleResult {
+    pub path: String,
+    pub files: Vec<String>,   // 包内的文件列表
+    pub redactions: usize,    // 脱敏替换次数
+}
+
+/// 同步健康状况
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SyncHealth {
+    status: SyncStatus,
+    dead_letters: Vec<DeadLetterSummary>,
+}
+
+/// 死信条目摘要
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DeadLetterSummary {
+    entity_type: String,
+    entity_id: String,
+    attempts: u32,
+    last_error: Option<String>,
+}
+
+/// 按实体类型和变更类型统计的待同步数量
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PendingCount {
+    entity_type: String,
+    change: PendingChangeKind,
+    count: usize,
+}
+
+/// 数据目录中的文件
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DataDirEntry {
+    name: String,
+    is_dir: bool,
+    size_bytes: u64,  // 目录为其中文件大小之和（不递归）
+}
+
+/// 支持包服务
+///
+/// 将排查问题所需的配置、同步状态、数据目录信息和最近日志打包为一个 ZIP，
+/// 方便用户附加到问题反馈中。写入包内的每个文件都会经过脱敏处理
+pub struct SupportBundleService {
+    pool: DbPool,
+    data_dir: PathBuf,
+    app_settings_service: AppSettingsService,
+    sync_service: SyncService,
+    sync_queue: SyncQueueService,
+}
+
+impl SupportBundleService {
+    /// 创建新的 SupportBundleService 实例
+    pub fn new(pool: DbPool, data_dir: PathBuf, app_settings_service: AppSettingsService, sync_service: SyncService) -> Self {
+        let sync_queue = SyncQueueService::new(pool.clone());
+        Self {
+            pool,
+            data_dir,
+            app_settings_service,
+            sync_service,
+            sync_queue,
+        }
+    }
+
+    /// 导出支持包到 `path`
+    ///
+    /// 某一部分收集失败时，对应文件中记录错误信息，不影响其他部分
+    pub fn export(&self, path: &Path) -> Result<SupportBundleResult> {
+        let file = File::create(path)
+            .map_err(|e| AppError::Internal(format!("创建支持包失败: {}", e)))?;
+        let mut zip = ZipWriter::new(BufWriter::new(file));
+        let now = chrono::Utc::now().timestamp();
+        let home = dirs::home_dir().map(|dir| dir.to_string_lossy().into_owned());
+
+        let mut files = Vec::new();
+        let mut redactions = 0;
+        let mut add = |zip: &mut ZipWriter<_>, name: &str, content: &str| -> Result<()> {
+            // 先替换家目录（路径中通常包含系统用户名），再执行通用脱敏
+            let content = match &home {
+                Some(home) if !home.is_empty() => content.replace(home.as_str(), "~"),
+                _ => content.to_string(),
+            };
+            let (content, count) = redact(&content);
+            redactions += count;
+            zip.add_file(name, content.as_bytes(), now)?;
+            files.push(name.to_string());
+            Ok(())
+        };
+
+        add(&mut zip, "system.json", &to_json(Ok(serde_json::json!({
+            "appVersion": env!("CARGO_PKG_VERSION"),
+            "os": std::env::consts::OS,
+            "arch": std::env::consts::ARCH,
+            "exportedAt": now,
+        }))))?;
+        add(&mut zip, "app_settings.json", &to_json(self.app_settings_service.get_settings()))?;
+        add(&mut zip, "editor_settings.json", &to_json(
+            EditorSettingsService::new(EditorSettingsRepository::new(self.pool.clone())).get_settings()
+        ))?;
+        add(&mut zip, "keybindings.json", &to_json(
+            KeybindingService::new(KeybindingRepository::new(self.data_dir.join("keybindings.json"))).load_keybindings()
+        ))?;
+        add(&mut zip, "schema_version.json", &to_json(MigrationService::new(self.pool.clone()).get_schema_version()))?;
+        add(&mut zip, "sync_health.json", &to_json(self.collect_sync_health()))?;
+        add(&mut zip, "pending_changes.json", &to_json(self.collect_pending_counts()))?;
+        add(&mut zip, "data_dir.json", &to_json(self.collect_data_dir()))?;
+
+        for (name, content) in self.read_recent_logs() {
+            add(&mut zip, &format!("logs/{}", name), &content)?;
+        }
+
+        zip.finish()?;
+
+        log::info!(
+            "[SupportBundleService] 支持包已导出: path={:?}, files={}, redactions={}",
+            path,
+            files.len(),
+            redactions
+        );
+
+        Ok(SupportBundleResult {
+            path: path.to_string_lossy().into_owned(),
+            files,
+            redactions,
+        })
+    }
+
+    fn collect_sync_health(&self) -> Result<SyncHealth> {
+        let dead_letters = self.sync_queue.find_dead_letters()?
+            .into_iter()
+            .map(|entry| DeadLetterSummary {
+                entity_type: entry.entity_type,
+                entity_id: entry.entity_id,
+                attempts: entry.attempts,
+                last_error: entry.last_error,
+            })
+            .collect();
+
+        Ok(SyncHealth {
+            status: self.sync_service.get_sync_status()?,
+            dead_letters,
+        })
+    }
+
+    /// 只统计数量，不包含笔记标题
+    fn collect_pending_counts(&self) -> Result<Vec<PendingCount>> {
+        let mut counts: Vec<PendingCount> = Vec::new();
+        for change in self.sync_service.list_pending_changes()? {
+            match counts.iter_mut().find(|c| c.entity_type == change.entity_type && c.change == change.change) {
+                Some(count) => count.count += 1,
+                None => counts.push(PendingCount {
+                    entity_type: change.entity_type,
+                    change: change.change,
+                    count: 1,
+                }),
+            }
+        }
+        Ok(counts)
+    }
+
+    fn collect_data_dir(&self) -> Result<serde_json::Value> {
+        let entries = fs::read_dir(&self.data_dir)
+            .map_err(|e| AppError::Internal(format!("读取数据目录失败: {}", e)))?;
+
+        let mut files: Vec<DataDirEntry> = entries.flatten()
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                let size_bytes = if metadata.is_dir() {
+                    fs::read_dir(entry.path()).ok()?
+                        .flatten()
+                        .filter_map(|child| child.metadata().ok())
+                        .filter(|m| m.is_file())
+                        .map(|m| m.len())
+                        .sum()
+                } else {
+                    metadata.len()
+                };
+                Some(DataDirEntry {
+                    name: entry.file_name().to_string_lossy().into_owned(),
+                    is_dir: metadata.is_dir(),
+                    size_bytes,
+                })
+            })
+            .collect();
+        files.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(serde_json::json!({
+            "path": self.data_dir.to_string_lossy(),
+            "entries": files,
+        }))
+    }
+
+    /// 读取最近的日志文件（文件名带时间戳，按名称倒序即为最新在前）
+    ///
+    /// 超过 `MAX_LOG_BYTES` 的文件只保留末尾部分，从第一个完整行开始
+    fn read_recent_logs(&self) -> Vec<(String, String)> {
+        let mut names: Vec<String> = match fs::read_dir(self.data_dir.join("log")) {
+            Ok(entries) => entries.flatten()
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .filter(|name| name.ends_with(".log"))
+                .collect(),
+            Err(_) => return Vec::new(),
+        };
+        names.sort_by(|a, b| b.cmp(a));
+
+        names.into_iter()
+            .take(MAX_LOG_FILES)
+            .filter_map(|name| {
+                let mut file = File::open(self.data_dir.join("log").join(&name)).ok()?;
+                let len = file.metadata().ok()?.len();
+                let start = len.saturating_sub(MAX_LOG_BYTES);
+                file.seek(SeekFrom::Start(start)).ok()?;
+
+                let mut bytes = Vec::new();
+                file.read_to_end(&mut bytes).ok()?;
+                if start > 0 {
+                    let first_line = bytes.iter().position(|b| *b == b'\n').map(|i| i + 1).unwrap_or(0);
+                    bytes.drain(..first_line);
+                }
+
+                Some((name, String::from_utf8_lossy(&bytes).into_owned()))
+            })
+            .collect()
+    }
+}
+
+/// 序列化为格式化 JSON，收集失败时记录错误信息
+fn to_json<T: Serialize>(value: Result<T>) -> String {
+    let value = match value {
+        Ok(value) => serde_json::to_value(value).unwrap_or_else(|e| serde_json::json!({ "error": e.to_string() })),
+        Err(e) => serde_json::json!({ "error": e.to_string() }),
+    };
+    serde_json::to_string_pretty(&value).unwrap_or_default()
+}
+
+fn is_word_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+fn starts_with_ignore_case(bytes: &[u8], prefix: &str) -> bool {
+    bytes.len() >= prefix.len() && bytes[..prefix.len()].eq_ignore_ascii_case(prefix.as_bytes())
+}
+
+/// 脱敏文本中的邮箱、JWT、Bearer 令牌以及 token / password 等字段的值
+///
+/// 邮箱按字符匹配，支持中文等非 ASCII 邮箱；其余只匹配 ASCII 模式。
+/// 只在字符边界上开始匹配，替换不会破坏 UTF-8。
+/// 返回脱敏后的文本和替换次数
+pub fn redact(text: &str) -> (String, usize) {
+    let bytes = text.as_bytes();
+    let mut out = String::with_capacity(text.len());
+    let mut count = 0;
+    let mut copied = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if !text.is_char_boundary(i) || (i > 0 && is_word_byte(bytes[i - 1])) {
+            i += 1;
+            continue;
+        }
+
+        let matched = match_email(text, i)
+            .map(|end| (i, end, REDACTED_EMAIL))
+            .or_else(|| match_jwt(bytes, i).map(|end| (i, end, REDACTED_TOKEN)))
+            .or_else(|| match_secret_value(bytes, i).map(|(start, end)| (start, end, REDACTED)));
+
+        match matched {
+            Some((start, end, replacement)) => {
+                out.push_str(&text[copied..start]);
+                out.push_str(replacement);
+                copied = end;
+                i = end;
+                count += 1;
+            }
+            None => i += 1,
+        }
+    }
+
+    out.push_str(&text[copied..]);
+    (out, count)
+}
+
+/// 匹配 `local@domain.tld`（包括 `张三@例子.中国` 这类国际化邮箱），返回结束位置
+fn match_email(text: &str, start: usize) -> Option<usize> {
+    let is_local = |c: char| c.is_alphanumeric() || matches!(c, '.' | '_' | '%' | '+' | '-');
+    let is_domain = |c: char| c.is_alphanumeric() || matches!(c, '.' | '-');
+    fn prefix_len(s: &str, accept: impl Fn(char) -> bool) -> usize {
+        s.chars().take_while(|c| accept(*c)).map(char::len_utf8).sum()
+    }
+
+    let at = start + prefix_len(&text[start..], is_local);
+    if at == start || !text[at..].starts_with('@') {
+        return None;
+    }
+
+    let domain_start = at + 1;
+    let domain = &text[domain_start..domain_start + prefix_len(&text[domain_start..], is_domain)];
+    // 去掉结尾的句点（如句子末尾的邮箱）
+    let domain = domain.trim_end_matches('.');
+
+    (domain.contains('.') && !domain.starts_with('.')).then_some(domain_start + domain.len())
+}
+
+/// 匹配 JWT（`eyJ` 开头、包含两个 `.` 的 base64url 串），返回结束位置
+fn match_jwt(bytes: &[u8], start: usize) -> Option<usize> {
+    if !bytes[start..].starts_with(b"eyJ") {
+        return None;
+    }
+    let mut len = bytes[start..].iter()
+        .take_while(|b| b.is_ascii_alphanumeric() || matches!(**b, b'-' | b'_' | b'.'))
+        .count();
+    while len > 0 && bytes[start + len - 1] == b'.' {
+        len -= 1;
+    }
+    let dots = bytes[start..start + len].iter().filter(|b| **b == b'.').count();
+    (len >= 20 && dots >= 2).then_some(start + len)
+}
+
+/// 匹配 `key: value`、`key=value`、`"key": "value"` 和 `Bearer value`，返回值的起止位置
+fn match_secret_value(bytes: &[u8], start: usize) -> Option<(usize, usize)> {
+    let rest = &bytes[start..];
+
+    let mut pos = if starts_with_ignore_case(rest, "bearer ") {
+        start + "bearer ".len()
+    } else {
+        let key = SECRET_KEYS.iter().find(|key| {
+            starts_with_ignore_case(rest, key) && !rest.get(key.len()).copied().is_some_and(is_word_byte)
+        })?;
+        let mut pos = start + key.len();
+        if matches!(bytes.get(pos), Some(b'"') | Some(b'\'')) {
+            pos += 1;
+        }
+        while bytes.get(pos) == Some(&b' ') {
+            pos += 1;
+        }
+        if !matches!(bytes.get(pos), Some(b':') | Some(b'=')) {
+            return None;
+        }
+        pos += 1;
+        while bytes.get(pos) == Some(&b' ') {
+            pos += 1;
+        }
+        if matches!(bytes.get(pos), Some(b'"') | Some(b'\'')) {
+            pos += 1;
+        }
+        for scheme in ["bearer ", "basic "] {
+            if starts_with_ignore_case(&bytes[pos..], scheme) {
+                pos += scheme.len();
+            }
+        }
+        pos
+    };
+
+    while bytes.get(pos) == Some(&b' ') {
+        pos += 1;
+    }
+    if bytes[pos..].starts_with(b"[REDACTED") {
+        return None;
+    }
+
+    let len = bytes[pos..].iter()
+        .take_while(|b| !b.is_ascii_whitespace() && !matches!(**b, b'"' | b'\'' | b',' | b'&' | b';' | b'}' | b')' | b']'))
+        .count();
+    // JSON 中的 null / 空值不需要脱敏
+    if len == 0 || bytes[pos..pos + len] == b"null"[..] {
+        return None;
+    }
+
+    Some((pos, pos + len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_emails_and_tokens() {
+        let (text, count) = redact("user alice.smith+notes@example.co.uk logged in, jwt=eyJhbGciOiJIUzI1NiJ9.eyJzdWIiOiIxIn0.sig_-x.");
+        assert_eq!(text, "user [REDACTED_EMAIL] logged in, jwt=[REDACTED_TOKEN].");
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn redacts_secret_fields() {
+        let (text, count) = redact(r#"{"accessToken": "abc123", "refresh_token":"def", "name": "token", "password": null}"#);
+        assert_eq!(text, r#"{"accessToken": "[REDACTED]", "refresh_token":"[REDACTED]", "name": "token", "password": null}"#);
+        assert_eq!(count, 2);
+
+        let (text, _) = redact("Authorization: Bearer xyz.789 token=42&page=1 note_token_count=3");
+        assert_eq!(text, "Authorization: Bearer [REDACTED] token=[REDACTED]&page=1 note_token_count=3");
+    }
+
+    #[test]
+    fn keeps_non_ascii_text_intact() {
+        let (text, count) = redact("同步失败：密码错误 password=秘密 联系 张三@例子.中国。");
+        assert_eq!(text, "同步失败：密码错误 password=[REDACTED] 联系 [REDACTED_EMAIL]。");
+        assert_eq!(count, 2);
+
+        // 中文标点不属于邮箱
+        let (text, _) = redact("联系：user@example.com，谢谢");
+        assert_eq!(text, "联系：[REDACTED_EMAIL]，谢谢");
+    }
+}
@@ -0,0 +1,94 @@
+use crate::models::SyncReport;
+use crate::models::error::Result;
+use serde::Serialize;
+use std::future::Future;
+use std::sync::Arc;
+
+/// 同步开始事件
+pub const SYNC_STARTED_EVENT: &str = "sync://started";
+/// 同步完成事件（payload 为 SyncReport）
+pub const SYNC_COMPLETED_EVENT: &str = "sync://completed";
+/// 同步失败事件
+pub const SYNC_ERROR_EVENT: &str = "sync://error";
+
+/// 同步开始事件的内容
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStartedPayload {
+    pub kind: String,               // full / note / tag / snapshot / folder
+    pub entity_id: Option<String>,  // 单个同步时的数据 ID
+}
+
+/// 同步失败事件的内容
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncErrorPayload {
+    pub kind: String,
+    pub entity_id: Option<String>,
+    pub error: String,
+}
+
+/// 同步事件的接收方（应用中为 Tauri AppHandle，测试中可以记录事件）
+pub trait SyncEventSink: Send + Sync {
+    fn emit(&self, event: &str, payload: serde_json::Value);
+}
+
+impl SyncEventSink for tauri::AppHandle {
+    fn emit(&self, event: &str, payload: serde_json::Value) {
+        if let Err(e) = tauri::Emitter::emit(self, event, payload) {
+            log::warn!("[SyncEvents] 发送事件失败: event={}, error={}", event, e);
+        }
+    }
+}
+
+/// 同步事件发送器
+///
+/// 同步开始、完成和失败时向前端广播事件，没有等待同步命令返回的窗口也能及时刷新。
+/// 未设置接收方时不发送任何事件
+#[derive(Clone, Default)]
+pub struct SyncEvents {
+    sink: Option<Arc<dyn SyncEventSink>>,
+}
+
+impl SyncEvents {
+    /// 创建发送到指定接收方的事件发送器
+    pub fn new(sink: Arc<dyn SyncEventSink>) -> Self {
+        Self { sink: Some(sink) }
+    }
+
+    /// 执行一次同步并发送对应的事件
+    ///
+    /// 开始前发送 `sync://started`，成功后发送 `sync://completed`（内容与返回的 SyncReport 相同），
+    /// 失败时发送 `sync://error`
+    pub async fn track<F>(&self, kind: &str, entity_id: Option<&str>, sync: F) -> Result<SyncReport>
+    where
+        F: Future<Output = Result<SyncReport>>,
+    {
+        let entity_id = entity_id.map(str::to_string);
+        self.emit(SYNC_STARTED_EVENT, &SyncStartedPayload {
+            kind: kind.to_string(),
+            entity_id: entity_id.clone(),
+        });
+
+        let result = sync.await;
+        match &result {
+            Ok(report) => self.emit(SYNC_COMPLETED_EVENT, report),
+            Err(e) => self.emit(SYNC_ERROR_EVENT, &SyncErrorPayload {
+                kind: kind.to_string(),
+                entity_id,
+                error: e.to_string(),
+            }),
+        }
+        result
+    }
+
+    fn emit<T: Serialize>(&self, event: &str, payload: &T) {
+        let Some(sink) = &self.sink else {
+            return;
+        };
+        match serde_json::to_value(payload) {
+            Ok(payload) => sink.emit(event, payload),
+            Err(e) => log::warn!("[SyncEvents] 序列化事件失败: event={}, error={}", event, e),
+        }
+    }
+}
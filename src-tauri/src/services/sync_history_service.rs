@@ -0,0 +1,124 @@
+use crate::models::{DeviceSyncSummary, SyncHistoryFilter, SyncHistoryPage};
+use crate::models::error::{Result, AppError};
+use crate::services::AuthService;
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use reqwest::Client;
+use std::time::Duration;
+
+/// 同步历史服务
+///
+/// 从服务器 `/sync/history` 查询同步记录，支持按结果和时间范围过滤
+#[derive(Clone)]
+pub struct SyncHistoryService {
+    pool: Pool<SqliteConnectionManager>,
+    client: Client,
+}
+
+impl SyncHistoryService {
+    /// 创建新的 SyncHistoryService 实例
+    pub fn new(pool: Pool<SqliteConnectionManager>) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .expect("Failed to create HTTP client");
+
+        Self { pool, client }
+    }
+
+    /// 查询同步历史（按过滤条件分页）
+    ///
+    /// 过滤在服务器端完成，每条记录包含推送/拉取/冲突数量和耗时；
+    /// 将返回的 `next_cursor` 作为下一次查询的 `before` 即可翻页
+    pub async fn list(&self, filter: SyncHistoryFilter) -> Result<SyncHistoryPage> {
+        let auth_service = AuthService::new(self.pool.clone());
+        let (server_url, token) = auth_service.get_auth_info()?;
+
+        let url = format!("{}/sync/history", server_url.trim_end_matches('/'));
+
+        let mut query: Vec<(&str, String)> = vec![("outcome", filter.outcome.as_query_value().to_string())];
+        if let Some(sync_type) = &filter.sync_type {
+            query.push(("sync_type", sync_type.clone()));
+        }
+        if let Some(from) = filter.from {
+            query.push(("from", from.to_string()));
+        }
+        if let Some(to) = filter.to {
+            query.push(("to", to.to_string()));
+        }
+        if let Some(before) = &filter.before {
+            query.push(("before", before.clone()));
+        }
+        if let Some(limit) = filter.limit {
+            query.push(("limit", limit.to_string()));
+        }
+
+        log::info!("[SyncHistoryService] 查询同步历史: url={}, filter={:?}", url, filter);
+
+        let response = self.client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .query(&query)
+            .send()
+            .await
+            .map_err(|e| {
+                log::error!("[SyncHistoryService] 请求失败: {}", e);
+                AppError::NetworkError(format!("查询同步历史失败: {}", e))
+            })?;
+
+        let status = response.status();
+
+        if status == reqwest::StatusCode::UNAUTHORIZED {
+            return Err(AppError::NotAuthenticated("登录已过期，请重新登录".to_string()));
+        }
+
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            log::error!("[SyncHistoryService] 服务器返回错误 {}: {}", status, error_text);
+            return Err(AppError::NetworkError(format!("服务器错误 {}: {}", status, error_text)));
+        }
+
+        let page: SyncHistoryPage = response.json().await.map_err(|e| {
+            log::error!("[SyncHistoryService] 解析响应失败: {}", e);
+            AppError::NetworkError(format!("响应无效: {}", e))
+        })?;
+
+        log::info!("[SyncHistoryService] 查询成功: count={}, has_more={}", page.entries.len(), page.next_cursor.is_some());
+        Ok(page)
+    }
+
+    /// 按设备汇总同步历史（推送/拉取总数，按最近同步时间倒序）
+    pub async fn summarize_by_device(&self) -> Result<Vec<DeviceSyncSummary>> {
+        let auth_service = AuthService::new(self.pool.clone());
+        let (server_url, token) = auth_service.get_auth_info()?;
+
+        let url = format!("{}/sync/history/devices", server_url.trim_end_matches('/'));
+
+        let response = self.client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await
+            .map_err(|e| {
+                log::error!("[SyncHistoryService] 请求失败: {}", e);
+                AppError::NetworkError(format!("查询设备同步统计失败: {}", e))
+            })?;
+
+        let status = response.status();
+
+        if status == reqwest::StatusCode::UNAUTHORIZED {
+            return Err(AppError::NotAuthenticated("登录已过期，请重新登录".to_string()));
+        }
+
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            log::error!("[SyncHistoryService] 服务器返回错误 {}: {}", status, error_text);
+            return Err(AppError::NetworkError(format!("服务器错误 {}: {}", status, error_text)));
+        }
+
+        response.json().await.map_err(|e| {
+            log::error!("[SyncHistoryService] 解析响应失败: {}", e);
+            AppError::NetworkError(format!("响应无效: {}", e))
+        })
+    }
+}
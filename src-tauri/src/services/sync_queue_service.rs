@@ -0,0 +1,195 @@
+use crate::models::SyncQueueEntry;
+use crate::models::error::{Result, AppError};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use r2d2_sqlite::rusqlite::{params, OptionalExtension};
+use chrono::Utc;
+
+/// 重试基础间隔（秒），每次失败后翻倍
+const RETRY_BASE_DELAY_SECS: i64 = 30;
+
+/// 重试间隔上限（秒）
+const RETRY_MAX_DELAY_SECS: i64 = 3600;
+
+/// 队列状态：等待重试
+pub const STATUS_PENDING: &str = "pending";
+
+/// 队列状态：超过重试上限，等待用户手动处理
+pub const STATUS_DEAD_LETTER: &str = "dead_letter";
+
+const SELECT_FIELDS: &str = "id, entity_type, entity_id, status, attempts, last_error, next_attempt_at, created_at, updated_at";
+
+/// 当前登录用户（队列按用户隔离，切换账号后不会重试其他账号的数据）
+const CURRENT_USER: &str = "(SELECT user_id FROM user_auth WHERE is_current = 1 LIMIT 1)";
+
+/// 单项同步重试队列
+///
+/// 单个笔记 / 标签 / 快照 / 文件夹同步失败后加入队列，由 AutoSyncService 按指数退避重试，
+/// 超过 `max_sync_retries` 次后转为 dead_letter 状态，不再自动重试
+#[derive(Clone)]
+pub struct SyncQueueService {
+    pool: Pool<SqliteConnectionManager>,
+}
+
+impl SyncQueueService {
+    /// 创建新的 SyncQueueService 实例
+    pub fn new(pool: Pool<SqliteConnectionManager>) -> Self {
+        Self { pool }
+    }
+
+    /// 加入重试队列（已存在时只更新错误信息，不重置重试次数）
+    pub fn enqueue(&self, entity_type: &str, entity_id: &str, error: &str) -> Result<()> {
+        let conn = self.pool.get()?;
+        let now = Utc::now().timestamp();
+
+        conn.execute(
+            &format!(
+                "INSERT INTO sync_queue
+                 (user_id, entity_type, entity_id, status, attempts, last_error, next_attempt_at, created_at, updated_at)
+                 VALUES ({}, ?1, ?2, ?3, 1, ?4, ?5, ?6, ?6)
+                 ON CONFLICT(entity_type, entity_id) DO UPDATE SET
+                    last_error = excluded.last_error,
+                    updated_at = excluded.updated_at",
+                CURRENT_USER
+            ),
+            params![entity_type, entity_id, STATUS_PENDING, error, now + retry_delay(1), now],
+        ).map_err(AppError::Database)?;
+
+        log::info!("[SyncQueue] 加入重试队列: {}={}, error={}", entity_type, entity_id, error);
+        Ok(())
+    }
+
+    /// 同步成功后移出队列
+    pub fn remove(&self, entity_type: &str, entity_id: &str) -> Result<()> {
+        let conn = self.pool.get()?;
+        conn.execute(
+            "DELETE FROM sync_queue WHERE entity_type = ?1 AND entity_id = ?2",
+            params![entity_type, entity_id],
+        ).map_err(AppError::Database)?;
+        Ok(())
+    }
+
+    /// 当前用户已到重试时间的条目
+    pub fn find_due(&self) -> Result<Vec<SyncQueueEntry>> {
+        let now = Utc::now().timestamp();
+        self.query(
+            &format!(
+                "SELECT {} FROM sync_queue
+                 WHERE user_id = {} AND status = ?1 AND next_attempt_at <= ?2
+                 ORDER BY next_attempt_at ASC",
+                SELECT_FIELDS, CURRENT_USER
+            ),
+            params![STATUS_PENDING, now],
+        )
+    }
+
+    /// 当前用户的死信条目
+    pub fn find_dead_letters(&self) -> Result<Vec<SyncQueueEntry>> {
+        self.query(
+            &format!(
+                "SELECT {} FROM sync_queue
+                 WHERE user_id = {} AND status = ?1
+                 ORDER BY updated_at DESC",
+                SELECT_FIELDS, CURRENT_USER
+            ),
+            params![STATUS_DEAD_LETTER],
+        )
+    }
+
+    /// 根据 ID 获取条目（仅当前用户）
+    pub fn find_by_id(&self, id: i64) -> Result<Option<SyncQueueEntry>> {
+        let conn = self.pool.get()?;
+        conn.query_row(
+            &format!("SELECT {} FROM sync_queue WHERE id = ?1 AND user_id = {}", SELECT_FIELDS, CURRENT_USER),
+            params![id],
+            Self::row_to_entry,
+        ).optional().map_err(AppError::Database)
+    }
+
+    /// 记录一次重试失败
+    ///
+    /// 重试次数达到 `max_retries` 或错误不可重试时转为 dead_letter，否则按指数退避安排下次重试。
+    /// 返回更新后的状态
+    pub fn record_failure(&self, entry: &SyncQueueEntry, error: &str, retryable: bool, max_retries: u32) -> Result<&'static str> {
+        let conn = self.pool.get()?;
+        let now = Utc::now().timestamp();
+        let attempts = entry.attempts + 1;
+
+        let status = if !retryable || attempts >= max_retries {
+            STATUS_DEAD_LETTER
+        } else {
+            STATUS_PENDING
+        };
+
+        conn.execute(
+            "UPDATE sync_queue
+             SET status = ?1, attempts = ?2, last_error = ?3, next_attempt_at = ?4, updated_at = ?5
+             WHERE id = ?6",
+            params![status, attempts, error, now + retry_delay(attempts), now, entry.id],
+        ).map_err(AppError::Database)?;
+
+        if status == STATUS_DEAD_LETTER {
+            log::warn!("[SyncQueue] 重试失败，转入死信: {}={}, attempts={}, error={}",
+                entry.entity_type, entry.entity_id, attempts, error);
+        }
+
+        Ok(status)
+    }
+
+    /// 将死信条目重新置为待重试（重试次数清零）
+    pub fn reset(&self, id: i64) -> Result<()> {
+        let conn = self.pool.get()?;
+        let now = Utc::now().timestamp();
+        conn.execute(
+            "UPDATE sync_queue
+             SET status = ?1, attempts = 0, next_attempt_at = ?2, updated_at = ?2
+             WHERE id = ?3",
+            params![STATUS_PENDING, now, id],
+        ).map_err(AppError::Database)?;
+        Ok(())
+    }
+
+    fn query(&self, sql: &str, params: impl r2d2_sqlite::rusqlite::Params) -> Result<Vec<SyncQueueEntry>> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(sql).map_err(AppError::Database)?;
+        let entries = stmt.query_map(params, Self::row_to_entry)
+            .map_err(AppError::Database)?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(AppError::Database)?;
+        Ok(entries)
+    }
+
+    fn row_to_entry(row: &r2d2_sqlite::rusqlite::Row) -> r2d2_sqlite::rusqlite::Result<SyncQueueEntry> {
+        Ok(SyncQueueEntry {
+            id: row.get(0)?,
+            entity_type: row.get(1)?,
+            entity_id: row.get(2)?,
+            status: row.get(3)?,
+            attempts: row.get(4)?,
+            last_error: row.get(5)?,
+            next_attempt_at: row.get(6)?,
+            created_at: row.get(7)?,
+            updated_at: row.get(8)?,
+        })
+    }
+}
+
+/// 第 `attempts` 次失败后的重试间隔（秒）
+fn retry_delay(attempts: u32) -> i64 {
+    let exponent = attempts.saturating_sub(1).min(16);
+    (RETRY_BASE_DELAY_SECS << exponent).min(RETRY_MAX_DELAY_SECS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_delay_doubles_up_to_cap() {
+        assert_eq!(retry_delay(1), 30);
+        assert_eq!(retry_delay(2), 60);
+        assert_eq!(retry_delay(3), 120);
+        assert_eq!(retry_delay(10), RETRY_MAX_DELAY_SECS);
+        assert_eq!(retry_delay(u32::MAX), RETRY_MAX_DELAY_SECS);
+    }
+}
@@ -1,13 +1,41 @@
-use crate::models::{Note, Folder, Tag, NoteSnapshot, NoteTagRelation, SyncRequest, SyncResponse, SyncReport, ConflictInfo, SyncStatus, ConflictStrategy, Workspace};
+use crate::models::{Note, Folder, Tag, NoteSnapshot, Attachment, NoteTagRelation, SyncRequest, SyncResponse, SyncReport, SyncTiming, ConflictInfo, SyncStatus, ConflictStrategy, ConflictStrategies, Workspace, DeleteEvent, FlappingItem, PendingChange, PendingChangeKind, LocalOnlyItem, FolderRebuildReport, ServerLimits, PendingConflict};
 use crate::models::error::{Result, AppError};
-use crate::services::auth_service::AuthService;
+use crate::services::auth_service::{self, AuthService};
 use crate::services::crypto::CryptoService;
+use crate::services::AppSettingsService;
+use crate::services::line_diff::{self, DiffLine, DiffLineKind};
+use crate::services::sync_events::{SyncEvents, SyncEventSink};
+use serde::Serialize;
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
 use r2d2_sqlite::rusqlite::{self, params};
 use chrono::Utc;
 use reqwest::Client;
-use std::time::Duration;
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 反复删除检测的时间窗口（7 天）
+const FLAPPING_WINDOW_SECS: i64 = 7 * 24 * 3600;
+
+/// 窗口内 is_deleted 至少变化这么多次才视为反复删除（删除 → 复活 → 再删除）
+const FLAPPING_MIN_TOGGLES: usize = 3;
+
+/// 服务器限制缓存有效期（1 小时）
+const SERVER_LIMITS_TTL_SECS: i64 = 3600;
+
+/// 同步请求重试间隔上限（毫秒）
+const MAX_RETRY_DELAY_MS: u64 = 30_000;
+
+/// 同步请求 ID 请求头（服务器沿用该 ID 记录日志，并在响应中回传）
+const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// 服务器错误码：相同幂等键的请求仍在处理中
+const IDEMPOTENCY_KEY_IN_USE: &str = "IDEMPOTENCY_KEY_IN_USE";
 
 /// 同步会话状态
 ///
@@ -18,6 +46,7 @@ pub struct SyncSession {
     pub user_id: String,              // 同步开始时的用户 ID
     pub workspace_id: Option<String>, // 同步开始时的工作空间 ID
     pub started_at: i64,              // 开始时间戳
+    pub account_epoch: u64,           // 同步开始时的账号纪元
 }
 
 /// 同步服务
@@ -27,6 +56,7 @@ pub struct SyncSession {
 pub struct SyncService {
     pool: Pool<SqliteConnectionManager>,
     client: Client,
+    events: SyncEvents,
 }
 
 impl SyncService {
@@ -37,7 +67,18 @@ impl SyncService {
             .build()
             .expect("Failed to create HTTP client");
 
-        Self { pool, client }
+        Self { pool, client, events: SyncEvents::default() }
+    }
+
+    /// 设置同步事件的接收方（同步开始、完成、失败时通知前端）
+    pub fn with_event_sink(mut self, sink: Arc<dyn SyncEventSink>) -> Self {
+        self.events = SyncEvents::new(sink);
+        self
+    }
+
+    /// 获取同步事件发送器（供单个同步服务使用）
+    pub fn events(&self) -> &SyncEvents {
+        &self.events
     }
 
     /// 获取数据库连接池（供其他服务使用）
@@ -50,6 +91,9 @@ impl SyncService {
     /// 在同步开始时调用，记录当前用户和工作空间的状态。
     /// 同步过程中可以通过 verify_sync_session 检查状态是否改变。
     fn begin_sync_session(&self) -> Result<SyncSession> {
+        // 先记录账号纪元，再读取用户，避免读取期间切换账号未被察觉
+        let account_epoch = auth_service::account_epoch();
+
         let conn = self.pool.get()
             .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
 
@@ -72,6 +116,7 @@ impl SyncService {
             user_id,
             workspace_id,
             started_at: chrono::Utc::now().timestamp(),
+            account_epoch,
         };
 
         log::info!("[SyncService] 开始同步会话: session_id={}, user_id={}, workspace_id={:?}",
@@ -85,6 +130,12 @@ impl SyncService {
     /// 在同步过程中的关键步骤调用此方法，检查用户或工作空间是否发生变化。
     /// 如果状态改变，应立即取消同步。
     fn verify_sync_session(&self, session: &SyncSession) -> Result<bool> {
+        // 账号切换或登出后即使又切回原账号，也视为会话失效
+        if auth_service::account_epoch() != session.account_epoch {
+            log::warn!("[SyncService] 同步会话失效: session_id={}, 账号已切换或登出", session.session_id);
+            return Ok(false);
+        }
+
         let conn = self.pool.get()
             .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
 
@@ -137,16 +188,24 @@ impl SyncService {
         }).map_err(|_| AppError::NotAuthenticated("User not logged in".to_string()))?;
 
         // 使用 device_id 解密 token
-        let key = CryptoService::derive_key_from_device_id(&device_id);
-        let token = CryptoService::decrypt_token(&encrypted_token, &key)?;
+        let token = CryptoService::decrypt_token(&encrypted_token, &device_id)?;
 
         Ok((server_url, token, device_id))
     }
 
     /// 完整同步（使用统一的 /sync 端点）
+    ///
+    /// 同步过程中会发送 sync://started、sync://completed 或 sync://error 事件
     pub async fn full_sync(&self) -> Result<SyncReport> {
+        self.events.track("full", None, self.run_full_sync()).await
+    }
+
+    async fn run_full_sync(&self) -> Result<SyncReport> {
         log::info!("Starting full sync");
 
+        let started = Instant::now();
+        let mut timing = SyncTiming::default();
+
         // 1. 开始同步会话（记录当前用户和工作空间状态）
         let session = self.begin_sync_session()?;
 
@@ -154,25 +213,33 @@ impl SyncService {
         if !self.verify_sync_session(&session)? {
             return Err(AppError::SyncCancelled("用户或工作空间已切换".to_string()));
         }
-        let request = self.build_sync_request()?;
+        let phase = Instant::now();
+        let (request, skipped_snapshots) = self.build_sync_request(None)?;
+        timing.build_request_ms = elapsed_ms(phase);
 
         // 3. 发送同步请求（统一的 /sync 端点）
         if !self.verify_sync_session(&session)? {
             return Err(AppError::SyncCancelled("用户或工作空间已切换".to_string()));
         }
+        let phase = Instant::now();
         let response = self.send_sync_request(&request).await?;
+        timing.network_ms = elapsed_ms(phase);
 
         // 4. 应用服务器响应，并获取修正后的统计（基于实际应用的数量）
         if !self.verify_sync_session(&session)? {
             return Err(AppError::SyncCancelled("用户或工作空间已切换，已取消同步".to_string()));
         }
-        let corrected_response = self.apply_sync_response(&response)?;
+        let phase = Instant::now();
+        let corrected_response = self.apply_sync_response_to(&response, None, session.account_epoch)?;
+        timing.apply_response_ms = elapsed_ms(phase);
 
         // 5. 清理脏标记
         if !self.verify_sync_session(&session)? {
             log::warn!("[SyncService] 应用完成但验证失败，跳过清理脏标记");
         } else {
+            let phase = Instant::now();
             self.clear_dirty_markers(&request, response.last_sync_at)?;
+            timing.clear_markers_ms = elapsed_ms(phase);
         }
 
         // 6. 更新同步状态
@@ -180,7 +247,18 @@ impl SyncService {
             log::warn!("[SyncService] 验证失败，跳过更新同步状态");
         } else {
             self.update_sync_state(response.last_sync_at, response.conflicts.len() as i32)?;
+            self.save_sync_cursor(None, response.cursor)?;
         }
+        timing.total_ms = elapsed_ms(started);
+
+        log::info!(
+            "[SyncService] 同步耗时: build={}ms, network={}ms, apply={}ms, clear={}ms, total={}ms",
+            timing.build_request_ms,
+            timing.network_ms,
+            timing.apply_response_ms,
+            timing.clear_markers_ms,
+            timing.total_ms
+        );
 
         let report = SyncReport {
             success: response.status != "error",
@@ -191,6 +269,7 @@ impl SyncService {
             pushed_tags: response.pushed_tags,
             pushed_snapshots: response.pushed_snapshots,
             pushed_note_tags: response.pushed_note_tags,
+            pushed_attachments: response.pushed_attachments,
             // ✅ 使用修正后的拉取统计（基于实际应用的数据数量）
             pulled_workspaces: corrected_response.pulled_workspaces,
             pulled_notes: corrected_response.pulled_notes,
@@ -198,18 +277,22 @@ impl SyncService {
             pulled_tags: corrected_response.pulled_tags,
             pulled_snapshots: corrected_response.pulled_snapshots,
             pulled_note_tags: corrected_response.pulled_note_tags,
+            pulled_attachments: corrected_response.pulled_attachments,
             // 删除的数据统计
             deleted_workspaces: response.deleted_workspace_ids.len(),
             deleted_notes: response.deleted_note_ids.len(),
             deleted_folders: response.deleted_folder_ids.len(),
             deleted_tags: response.deleted_tag_ids.len(),
+            deleted_attachments: response.deleted_attachment_ids.len(),
             // 冲突和错误
             conflict_count: response.conflicts.len(),
+            skipped_snapshots,
             error: if response.status == "error" {
                 Some("Sync failed".to_string())
             } else {
                 None
             },
+            timing: Some(timing),
             // 兼容旧版本
             pushed_count: None,
             pulled_count: None,
@@ -224,11 +307,202 @@ impl SyncService {
         Ok(report)
     }
 
+    /// 只同步收藏和置顶的脏笔记（及其标签）
+    ///
+    /// 用于按流量计费或网络较差时优先推送重要内容：请求中只包含 `is_favorite` 或 `is_pinned`
+    /// 的脏笔记、它们关联的脏标签和标签关联，其他脏数据保持 is_dirty = 1，留给下次完整同步。
+    /// 没有需要推送的笔记时不发起网络请求
+    pub async fn sync_priority_notes(&self) -> Result<SyncReport> {
+        let session = self.begin_sync_session()?;
+
+        let notes = self.get_dirty_priority_notes()?;
+        if notes.is_empty() {
+            log::info!("[SyncService] 没有需要优先同步的笔记");
+            return Ok(SyncReport {
+                success: true,
+                ..Default::default()
+            });
+        }
+
+        let note_ids: Vec<String> = notes.iter().map(|n| n.id.clone()).collect();
+        let note_ids_json = serde_json::to_string(&note_ids)
+            .map_err(|e| AppError::Internal(format!("序列化笔记 ID 失败: {}", e)))?;
+        let tags = self.get_dirty_tags_for_notes(&note_ids_json)?;
+        let note_tags = self.get_note_tags_for_notes(&note_ids_json)?;
+        let attachments = self.get_dirty_attachments_for_notes(&note_ids)?;
+
+        log::info!(
+            "[SyncService] 优先同步: notes={}, tags={}, note_tags={}, attachments={}",
+            notes.len(),
+            tags.len(),
+            note_tags.len(),
+            attachments.len()
+        );
+
+        let request = SyncRequest {
+            workspaces: None,
+            notes: Some(notes.into_iter().map(|n| n.into()).collect()),
+            folders: None,
+            tags: Some(tags.into_iter().map(|t| t.into()).collect()),
+            snapshots: None,
+            note_tags: if note_tags.is_empty() { None } else { Some(note_tags.into_iter().map(|nt| nt.into()).collect()) },
+            attachments: if attachments.is_empty() { None } else { Some(attachments.into_iter().map(|a| a.into()).collect()) },
+            last_sync_at: self.get_last_sync_time()?,
+            cursor: self.get_sync_cursor(None)?,
+            workspace_id: None,
+            conflict_resolution: ConflictStrategy::default(),
+            conflict_strategies: self.get_conflict_strategies()?,
+            device_id: None, // 在 send_sync_request 中设置
+            idempotency_key: None, // 在 send_sync_request 中设置
+        };
+
+        if !self.verify_sync_session(&session)? {
+            return Err(AppError::SyncCancelled("用户或工作空间已切换".to_string()));
+        }
+        let response = self.send_sync_request(&request).await?;
+
+        if !self.verify_sync_session(&session)? {
+            return Err(AppError::SyncCancelled("用户或工作空间已切换，已取消同步".to_string()));
+        }
+        let corrected_response = self.apply_sync_response_to(&response, None, session.account_epoch)?;
+        self.clear_dirty_markers(&request, response.last_sync_at)?;
+        self.update_sync_state(response.last_sync_at, response.conflicts.len() as i32)?;
+        self.save_sync_cursor(None, response.cursor)?;
+
+        let report = SyncReport {
+            success: response.status != "error",
+            pushed_workspaces: response.pushed_workspaces,
+            pushed_notes: response.pushed_notes,
+            pushed_folders: response.pushed_folders,
+            pushed_tags: response.pushed_tags,
+            pushed_snapshots: response.pushed_snapshots,
+            pushed_note_tags: response.pushed_note_tags,
+            pushed_attachments: response.pushed_attachments,
+            pulled_workspaces: corrected_response.pulled_workspaces,
+            pulled_notes: corrected_response.pulled_notes,
+            pulled_folders: corrected_response.pulled_folders,
+            pulled_tags: corrected_response.pulled_tags,
+            pulled_snapshots: corrected_response.pulled_snapshots,
+            pulled_note_tags: corrected_response.pulled_note_tags,
+            pulled_attachments: corrected_response.pulled_attachments,
+            deleted_workspaces: response.deleted_workspace_ids.len(),
+            deleted_notes: response.deleted_note_ids.len(),
+            deleted_folders: response.deleted_folder_ids.len(),
+            deleted_tags: response.deleted_tag_ids.len(),
+            deleted_attachments: response.deleted_attachment_ids.len(),
+            conflict_count: response.conflicts.len(),
+            skipped_snapshots: 0,
+            error: if response.status == "error" {
+                Some("Priority sync failed".to_string())
+            } else {
+                None
+            },
+            timing: None,
+            pushed_count: None,
+            pulled_count: None,
+        };
+
+        log::info!(
+            "[SyncService] 优先同步完成: pushed_notes={}, pushed_tags={}, pulled_notes={}, conflicts={}",
+            report.pushed_notes,
+            report.pushed_tags,
+            report.pulled_notes,
+            report.conflict_count
+        );
+
+        Ok(report)
+    }
+
+    /// 同步指定工作空间（无需切换到该工作空间）
+    ///
+    /// 只推送该工作空间的脏数据，并在请求中指明 workspace_id，拉取的数据归属到该工作空间。
+    /// 增量拉取使用该工作空间自己的上次同步时间，不影响全局同步状态
+    pub async fn sync_workspace(&self, workspace_id: &str) -> Result<SyncReport> {
+        log::info!("[SyncService] 同步指定工作空间: workspace_id={}", workspace_id);
+
+        let session = self.begin_sync_session()?;
+
+        {
+            let conn = self.pool.get()
+                .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
+            let owned: bool = conn.query_row(
+                "SELECT EXISTS(
+                    SELECT 1 FROM workspaces w
+                    INNER JOIN user_auth u ON u.user_id = w.user_id
+                    WHERE w.id = ?1 AND u.is_current = 1 AND w.is_deleted = 0
+                 )",
+                params![workspace_id],
+                |row| row.get(0),
+            ).map_err(AppError::Database)?;
+            if !owned {
+                return Err(AppError::NotFound(format!("工作空间不存在: {}", workspace_id)));
+            }
+        }
+
+        let (request, skipped_snapshots) = self.build_sync_request(Some(workspace_id))?;
+
+        if !self.verify_sync_session(&session)? {
+            return Err(AppError::SyncCancelled("用户或工作空间已切换".to_string()));
+        }
+        let response = self.send_sync_request(&request).await?;
+
+        if !self.verify_sync_session(&session)? {
+            return Err(AppError::SyncCancelled("用户或工作空间已切换，已取消同步".to_string()));
+        }
+        let corrected_response = self.apply_sync_response_to(&response, Some(workspace_id), session.account_epoch)?;
+        self.clear_dirty_markers(&request, response.last_sync_at)?;
+        self.set_workspace_last_sync_at(workspace_id, response.last_sync_at)?;
+        self.save_sync_cursor(Some(workspace_id), response.cursor)?;
+
+        let report = SyncReport {
+            success: response.status != "error",
+            pushed_workspaces: response.pushed_workspaces,
+            pushed_notes: response.pushed_notes,
+            pushed_folders: response.pushed_folders,
+            pushed_tags: response.pushed_tags,
+            pushed_snapshots: response.pushed_snapshots,
+            pushed_note_tags: response.pushed_note_tags,
+            pushed_attachments: response.pushed_attachments,
+            pulled_workspaces: corrected_response.pulled_workspaces,
+            pulled_notes: corrected_response.pulled_notes,
+            pulled_folders: corrected_response.pulled_folders,
+            pulled_tags: corrected_response.pulled_tags,
+            pulled_snapshots: corrected_response.pulled_snapshots,
+            pulled_note_tags: corrected_response.pulled_note_tags,
+            pulled_attachments: corrected_response.pulled_attachments,
+            deleted_workspaces: response.deleted_workspace_ids.len(),
+            deleted_notes: response.deleted_note_ids.len(),
+            deleted_folders: response.deleted_folder_ids.len(),
+            deleted_tags: response.deleted_tag_ids.len(),
+            deleted_attachments: response.deleted_attachment_ids.len(),
+            conflict_count: response.conflicts.len(),
+            skipped_snapshots,
+            error: if response.status == "error" {
+                Some("Workspace sync failed".to_string())
+            } else {
+                None
+            },
+            timing: None,
+            pushed_count: None,
+            pulled_count: None,
+        };
+
+        log::info!(
+            "[SyncService] 工作空间同步完成: workspace_id={}, pushed_notes={}, pulled_notes={}, conflicts={}",
+            workspace_id,
+            report.pushed_notes,
+            report.pulled_notes,
+            report.conflict_count
+        );
+
+        Ok(report)
+    }
+
     /// 推送到服务器（旧方法，保留以保持兼容性）
     #[deprecated(note = "使用 full_sync() 代替")]
     pub async fn push_to_server(&self) -> Result<SyncResponse> {
         // 使用新的统一同步方法
-        let request = self.build_sync_request()?;
+        let (request, _) = self.build_sync_request(None)?;
 
         // 发送同步请求
         let response = self.send_sync_request(&request).await?;
@@ -251,9 +525,14 @@ impl SyncService {
             tags: None,
             snapshots: None,
             note_tags: None,
+            attachments: None,
             last_sync_at: self.get_last_sync_at()?,
+            cursor: self.get_sync_cursor(None)?,
+            workspace_id: None,
             conflict_resolution: ConflictStrategy::default(),
+            conflict_strategies: ConflictStrategies::default(),
             device_id: None,
+            idempotency_key: None,
         };
 
         // 发送同步请求
@@ -282,21 +561,448 @@ impl SyncService {
                 pending_count: row.get(1)?,
                 conflict_count: row.get(2)?,
                 last_error: row.get(3)?,
+                effective_interval_secs: None,
+                consecutive_failures: 0,
             })
         }).unwrap_or_else(|_| SyncStatus {
             last_sync_at: None,
             pending_count: 0,
             conflict_count: 0,
             last_error: None,
+            effective_interval_secs: None,
+            consecutive_failures: 0,
         });
 
         Ok(status)
     }
 
+    /// 列出待同步的变更（同步前预览）
+    ///
+    /// 只读取 id、标题和状态字段，不加载笔记内容。变更类型根据本地状态推断：
+    ///
+    /// - `is_deleted = 1` → 删除
+    /// - `server_ver = 0` → 新建（从未同步过）
+    /// - 其他 → 修改
+    pub fn list_pending_changes(&self) -> Result<Vec<PendingChange>> {
+        let conn = self.pool.get()
+            .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
+
+        let mut stmt = conn.prepare(
+            "SELECT 'workspace', id, name, server_ver, is_deleted, updated_at FROM workspaces WHERE is_dirty = 1
+             UNION ALL
+             SELECT 'note', id, title, server_ver, is_deleted, updated_at FROM notes WHERE is_dirty = 1
+             UNION ALL
+             SELECT 'folder', id, name, server_ver, is_deleted, updated_at FROM folders WHERE is_dirty = 1
+             UNION ALL
+             SELECT 'tag', id, name, server_ver, is_deleted, updated_at FROM tags WHERE is_dirty = 1
+             UNION ALL
+             SELECT 'snapshot', id, COALESCE(snapshot_name, title), server_ver, 0, created_at FROM note_snapshots WHERE is_dirty = 1
+             ORDER BY 6 DESC"
+        ).map_err(|e| AppError::DatabaseError(format!("Failed to get pending changes: {}", e)))?;
+
+        let changes = stmt.query_map([], |row| {
+            let server_ver: Option<i32> = row.get(3)?;
+            let is_deleted: Option<bool> = row.get(4)?;
+
+            let change = if is_deleted.unwrap_or(false) {
+                PendingChangeKind::Deleted
+            } else if server_ver.unwrap_or(0) == 0 {
+                PendingChangeKind::New
+            } else {
+                PendingChangeKind::Edited
+            };
+
+            Ok(PendingChange {
+                entity_type: row.get(0)?,
+                id: row.get(1)?,
+                title: row.get(2)?,
+                change,
+                updated_at: row.get(5)?,
+            })
+        })
+        .map_err(|e| AppError::DatabaseError(format!("Failed to parse pending changes: {}", e)))?
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| AppError::DatabaseError(format!("Failed to collect pending changes: {}", e)))?;
+
+        log::info!("[SyncService] 待同步变更: count={}", changes.len());
+
+        Ok(changes)
+    }
+
+    /// 列出仅存在于本地、从未被服务器确认的数据
+    ///
+    /// 判定条件为 `last_synced_at IS NULL OR server_ver = 0`，比 is_dirty 更严格：
+    /// 早期同步失败可能导致数据已清除脏标记但从未到达服务器
+    ///
+    /// `include_folders_tags` 为 true 时同时返回该工作空间下的文件夹和标签
+    pub fn list_local_only(&self, workspace_id: &str, include_folders_tags: bool) -> Result<Vec<LocalOnlyItem>> {
+        let conn = self.pool.get()
+            .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
+
+        let mut stmt = conn.prepare(
+            "SELECT 'note', id, title, is_dirty, updated_at FROM notes
+             WHERE workspace_id = ?1 AND is_deleted = 0
+               AND (last_synced_at IS NULL OR server_ver = 0)
+             UNION ALL
+             SELECT 'folder', id, name, is_dirty, updated_at FROM folders
+             WHERE ?2 = 1 AND workspace_id = ?1 AND is_deleted = 0
+               AND (last_synced_at IS NULL OR server_ver = 0)
+             UNION ALL
+             SELECT 'tag', id, name, is_dirty, updated_at FROM tags
+             WHERE ?2 = 1 AND workspace_id = ?1 AND is_deleted = 0
+               AND (last_synced_at IS NULL OR server_ver = 0)
+             ORDER BY 5 DESC"
+        ).map_err(|e| AppError::DatabaseError(format!("Failed to get local-only items: {}", e)))?;
+
+        let items = stmt.query_map(params![workspace_id, include_folders_tags], |row| {
+            Ok(LocalOnlyItem {
+                entity_type: row.get(0)?,
+                id: row.get(1)?,
+                title: row.get(2)?,
+                is_dirty: row.get::<_, Option<bool>>(3)?.unwrap_or(false),
+                updated_at: row.get(4)?,
+            })
+        })
+        .map_err(|e| AppError::DatabaseError(format!("Failed to parse local-only items: {}", e)))?
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| AppError::DatabaseError(format!("Failed to collect local-only items: {}", e)))?;
+
+        log::info!("[SyncService] 仅本地数据: workspace_id={}, count={}", workspace_id, items.len());
+
+        Ok(items)
+    }
+
+    /// 获取未处理的笔记冲突
+    ///
+    /// 原笔记或冲突副本已被删除的冲突视为已处理，不再返回。
+    /// 服务器未返回双方内容时（旧版本服务器），以冲突副本和原笔记的当前内容代替
+    pub fn get_pending_conflicts(&self) -> Result<Vec<PendingConflict>> {
+        let mut conflicts = {
+            let conn = self.pool.get()
+                .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
+
+            let mut stmt = conn.prepare(
+                "SELECT c.id, c.note_id, c.copy_id, c.title, c.local_version, c.server_version,
+                        COALESCE(c.local_content, cp.content, ''), COALESCE(c.server_content, n.content, ''),
+                        c.local_updated_at, c.server_updated_at, c.created_at
+                 FROM sync_conflicts c
+                 JOIN notes n ON n.id = c.note_id AND n.is_deleted = 0
+                 LEFT JOIN notes cp ON cp.id = c.copy_id
+                 WHERE c.resolved_at IS NULL
+                   AND (c.copy_id IS NULL OR cp.is_deleted = 0)
+                 ORDER BY c.created_at DESC, c.id DESC"
+            ).map_err(|e| AppError::DatabaseError(format!("Failed to get conflicts: {}", e)))?;
+
+            let conflicts = stmt.query_map([], |row| {
+                Ok(PendingConflict {
+                    id: row.get(0)?,
+                    note_id: row.get(1)?,
+                    copy_id: row.get(2)?,
+                    title: row.get(3)?,
+                    local_version: row.get(4)?,
+                    server_version: row.get(5)?,
+                    local_content: row.get(6)?,
+                    server_content: row.get(7)?,
+                    local_updated_at: row.get(8)?,
+                    server_updated_at: row.get(9)?,
+                    original: None,
+                    copy: None,
+                    created_at: row.get(10)?,
+                })
+            })
+            .map_err(|e| AppError::DatabaseError(format!("Failed to parse conflicts: {}", e)))?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| AppError::DatabaseError(format!("Failed to collect conflicts: {}", e)))?;
+            conflicts
+        };
+
+        for conflict in &mut conflicts {
+            conflict.original = self.get_note_by_id(&conflict.note_id)?;
+            if let Some(copy_id) = &conflict.copy_id {
+                conflict.copy = self.get_note_by_id(copy_id)?;
+            }
+        }
+
+        Ok(conflicts)
+    }
+
+    /// 手动合并解决笔记冲突
+    ///
+    /// 写入合并后的内容，将 server_ver 提升到服务器版本之后并标记为脏，
+    /// 删除冲突副本并将冲突记录标记为已解决。推送由调用方触发
+    pub fn resolve_note_conflict(&self, note_id: &str, merged_content: &str) -> Result<Note> {
+        let mut note = self.get_note_by_id(note_id)?
+            .ok_or(AppError::NotFound(format!("Note {} not found", note_id)))?;
+
+        let conn = self.pool.get()
+            .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, copy_id, server_version FROM sync_conflicts
+             WHERE note_id = ? AND resolved_at IS NULL"
+        ).map_err(|e| AppError::DatabaseError(format!("Failed to get conflicts: {}", e)))?;
+
+        let conflicts = stmt.query_map([note_id], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?, row.get::<_, i32>(2)?))
+        })
+        .map_err(|e| AppError::DatabaseError(format!("Failed to parse conflicts: {}", e)))?
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| AppError::DatabaseError(format!("Failed to collect conflicts: {}", e)))?;
+        drop(stmt);
+
+        let server_version = conflicts.iter()
+            .map(|(_, _, version)| *version)
+            .fold(note.server_ver, i32::max);
+
+        let now = Utc::now().timestamp();
+        note.update_content(merged_content.to_string());
+        note.server_ver = server_version + 1;
+        note.is_dirty = true;
+        note.updated_at = now;
+
+        conn.execute(
+            "UPDATE notes SET content = ?1, excerpt = ?2, markdown_cache = ?3,
+                word_count = ?4, read_time_minutes = ?5, server_ver = ?6, is_dirty = 1, updated_at = ?7
+             WHERE id = ?8",
+            params![note.content, note.excerpt, note.markdown_cache, note.word_count, note.read_time_minutes, note.server_ver, now, note.id],
+        ).map_err(|e| AppError::DatabaseError(format!("Failed to write merged note: {}", e)))?;
+
+        for (id, copy_id, _) in &conflicts {
+            if let Some(copy_id) = copy_id {
+                conn.execute(
+                    "UPDATE notes SET is_deleted = 1, deleted_at = ?1, updated_at = ?1, is_dirty = 1
+                     WHERE id = ?2 AND is_deleted = 0",
+                    params![now, copy_id],
+                ).map_err(|e| AppError::DatabaseError(format!("Failed to delete conflict copy: {}", e)))?;
+            }
+
+            conn.execute(
+                "UPDATE sync_conflicts SET resolved_at = ? WHERE id = ?",
+                params![now, id],
+            ).map_err(|e| AppError::DatabaseError(format!("Failed to mark conflict resolved: {}", e)))?;
+        }
+
+        log::info!("Resolved conflict for note {}: server_ver={}, removed {} conflict copies",
+            note_id, note.server_ver, conflicts.iter().filter(|(_, c, _)| c.is_some()).count());
+
+        Ok(note)
+    }
+
+    /// 从服务器重建工作空间的文件夹（恢复工具）
+    ///
+    /// 与普通同步不同，这里忽略本地版本，直接以服务器数据覆盖本地文件夹：
+    /// - 服务器上的文件夹全部写入本地（is_dirty = 0）
+    /// - 本地存在但服务器上没有的文件夹被删除（包括尚未同步的新文件夹）
+    /// - 笔记按原 folder_id 重新挂接，找不到文件夹的笔记移到根目录
+    ///
+    /// ⚠️ 会丢弃本地未同步的文件夹修改，调用方必须传入 `confirm = true`
+    pub async fn rebuild_folders_from_server(&self, workspace_id: &str, confirm: bool) -> Result<FolderRebuildReport> {
+        if !confirm {
+            return Err(AppError::InvalidOperation(
+                "从服务器重建文件夹会覆盖本地文件夹，请确认后再执行".to_string()
+            ));
+        }
+
+        {
+            let conn = self.pool.get()
+                .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
+            let owned: bool = conn.query_row(
+                "SELECT EXISTS(
+                    SELECT 1 FROM workspaces w
+                    INNER JOIN user_auth u ON u.user_id = w.user_id
+                    WHERE w.id = ?1 AND u.is_current = 1 AND w.is_deleted = 0
+                 )",
+                params![workspace_id],
+                |row| row.get(0),
+            ).map_err(AppError::Database)?;
+            if !owned {
+                return Err(AppError::NotFound(format!("工作空间不存在: {}", workspace_id)));
+            }
+        }
+
+        let server_folders = self.request_server_folders(workspace_id).await?;
+
+        let conn = self.pool.get()
+            .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
+        let tx = conn.unchecked_transaction().map_err(AppError::Database)?;
+        let now = Utc::now().timestamp();
+
+        // 先覆盖写入服务器文件夹（使用 upsert 而不是先删后插，避免 folder_id 外键被置空）
+        for folder in &server_folders {
+            tx.execute(
+                "INSERT INTO folders
+                 (id, name, parent_id, workspace_id, created_at, updated_at,
+                  is_deleted, deleted_at, server_ver, is_dirty, last_synced_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, 0, NULL, ?7, 0, ?8)
+                 ON CONFLICT(id) DO UPDATE SET
+                    name = excluded.name,
+                    parent_id = excluded.parent_id,
+                    workspace_id = excluded.workspace_id,
+                    created_at = excluded.created_at,
+                    updated_at = excluded.updated_at,
+                    is_deleted = 0,
+                    deleted_at = NULL,
+                    server_ver = excluded.server_ver,
+                    is_dirty = 0,
+                    last_synced_at = excluded.last_synced_at",
+                params![
+                    folder.id, folder.name, folder.parent_id, workspace_id,
+                    folder.created_at, folder.updated_at, folder.server_ver, now,
+                ],
+            ).map_err(|e| AppError::DatabaseError(format!("Failed to write server folder: {}", e)))?;
+        }
+
+        let server_ids = serde_json::to_string(
+            &server_folders.iter().map(|f| f.id.as_str()).collect::<Vec<_>>()
+        ).map_err(|e| AppError::Internal(format!("序列化文件夹 ID 失败: {}", e)))?;
+
+        // 删除服务器上不存在的本地文件夹
+        let removed_local_folders = tx.execute(
+            "DELETE FROM folders
+             WHERE workspace_id = ?1 AND id NOT IN (SELECT value FROM json_each(?2))",
+            params![workspace_id, server_ids],
+        ).map_err(|e| AppError::DatabaseError(format!("Failed to remove local folders: {}", e)))?;
+
+        // 文件夹已不存在的笔记移到根目录
+        let orphaned_notes = tx.execute(
+            "UPDATE notes SET folder_id = NULL, is_dirty = 1, updated_at = ?2
+             WHERE workspace_id = ?1 AND folder_id IS NOT NULL
+               AND folder_id NOT IN (SELECT value FROM json_each(?3))",
+            params![workspace_id, now, server_ids],
+        ).map_err(|e| AppError::DatabaseError(format!("Failed to unlink notes: {}", e)))?;
+
+        let relinked_notes: i64 = tx.query_row(
+            "SELECT COUNT(*) FROM notes WHERE workspace_id = ?1 AND folder_id IS NOT NULL",
+            params![workspace_id],
+            |row| row.get(0),
+        ).map_err(AppError::Database)?;
+
+        tx.commit().map_err(AppError::Database)?;
+
+        let report = FolderRebuildReport {
+            server_folders: server_folders.len(),
+            removed_local_folders,
+            relinked_notes: relinked_notes as usize,
+            orphaned_notes,
+        };
+
+        log::info!("[SyncService] 已从服务器重建文件夹: workspace_id={}, {:?}", workspace_id, report);
+
+        Ok(report)
+    }
+
+    /// 对比笔记的本地版本与服务器版本（按行 diff）
+    ///
+    /// 两边都有 markdown 缓存时比较 markdown，否则比较原始内容。
+    /// 服务器上不存在该笔记时（从未同步），本地内容全部显示为新增
+    pub async fn diff_against_server(&self, note_id: &str) -> Result<NoteServerDiff> {
+        let (local_title, local_content, local_markdown, local_server_ver) = {
+            let conn = self.pool.get()
+                .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
+            conn.query_row(
+                "SELECT title, content, markdown_cache, server_ver FROM notes WHERE id = ?1",
+                params![note_id],
+                |row| Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, i32>(3)?,
+                )),
+            ).map_err(|_| AppError::NoteNotFound(note_id.to_string()))?
+        };
+
+        let server_note = self.request_server_note(note_id).await?;
+
+        let (server_text, local_text) = match &server_note {
+            Some(server) => match (&server.markdown_cache, &local_markdown) {
+                (Some(server_md), Some(local_md)) if !server_md.is_empty() && !local_md.is_empty() => {
+                    (server_md.as_str(), local_md.as_str())
+                }
+                _ => (server.content.as_str(), local_content.as_str()),
+            },
+            None => ("", local_markdown.as_deref().filter(|m| !m.is_empty()).unwrap_or(&local_content)),
+        };
+
+        let lines = line_diff::diff_lines(server_text, local_text);
+        let added = lines.iter().filter(|l| l.kind == DiffLineKind::Added).count();
+        let removed = lines.iter().filter(|l| l.kind == DiffLineKind::Removed).count();
+
+        log::info!("[SyncService] 笔记与服务器对比: note_id={}, exists_on_server={}, added={}, removed={}",
+            note_id, server_note.is_some(), added, removed);
+
+        Ok(NoteServerDiff {
+            note_id: note_id.to_string(),
+            exists_on_server: server_note.is_some(),
+            local_title,
+            server_title: server_note.as_ref().map(|n| n.title.clone()),
+            local_server_ver,
+            server_ver: server_note.as_ref().map(|n| n.server_ver),
+            server_updated_at: server_note.as_ref().map(|n| n.updated_at),
+            server_deleted: server_note.as_ref().is_some_and(|n| n.is_deleted),
+            added,
+            removed,
+            lines,
+        })
+    }
+
     // ===== 私有方法 =====
 
-    /// 获取所有脏笔记
-    fn get_dirty_notes(&self) -> Result<Vec<Note>> {
+    /// 从服务器获取单个笔记（GET /notes/:id），不存在时返回 None
+    async fn request_server_note(&self, note_id: &str) -> Result<Option<crate::models::sync::ServerNote>> {
+        let (server_url, token, _) = self.get_auth_info()?;
+        let url = format!("{}/notes/{}", server_url.trim_end_matches('/'), note_id);
+
+        let response = self.client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await
+            .map_err(|e| AppError::NetworkError(format!("获取服务器笔记失败: {}", e)))?;
+
+        let status = response.status();
+        if status.as_u16() == 404 {
+            return Ok(None);
+        }
+
+        let body = response.text().await
+            .map_err(|e| AppError::NetworkError(format!("读取服务器笔记失败: {}", e)))?;
+
+        if !status.is_success() {
+            return Err(AppError::NetworkError(format!("服务器错误 {}: {}", status, body)));
+        }
+
+        serde_json::from_str(&body)
+            .map(Some)
+            .map_err(|e| AppError::NetworkError(format!("服务器笔记响应无效: {}", e)))
+    }
+
+    /// 从服务器获取工作空间的全部文件夹（GET /folders）
+    async fn request_server_folders(&self, workspace_id: &str) -> Result<Vec<crate::models::sync::ServerFolder>> {
+        let (server_url, token, _) = self.get_auth_info()?;
+        let url = format!("{}/folders", server_url.trim_end_matches('/'));
+
+        let response = self.client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .json(&serde_json::json!({ "workspace_id": workspace_id }))
+            .send()
+            .await
+            .map_err(|e| AppError::NetworkError(format!("获取服务器文件夹失败: {}", e)))?;
+
+        let status = response.status();
+        let body = response.text().await
+            .map_err(|e| AppError::NetworkError(format!("读取服务器文件夹失败: {}", e)))?;
+
+        if !status.is_success() {
+            return Err(AppError::NetworkError(format!("服务器错误 {}: {}", status, body)));
+        }
+
+        serde_json::from_str(&body)
+            .map_err(|e| AppError::NetworkError(format!("服务器文件夹响应无效: {}", e)))
+    }
+
+    /// 获取所有脏笔记（指定 workspace_id 时只返回该工作空间的笔记）
+    fn get_dirty_notes(&self, workspace_id: Option<&str>) -> Result<Vec<Note>> {
         let conn = self.pool.get()
             .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
 
@@ -304,12 +1010,12 @@ impl SyncService {
             "SELECT id, title, content, excerpt, markdown_cache, workspace_id, folder_id,
                     is_favorite, is_deleted, is_pinned, author,
                     created_at, updated_at, deleted_at, word_count, read_time_minutes,
-                    server_ver, is_dirty, last_synced_at
+                    server_ver, is_dirty, last_synced_at, is_private
              FROM notes
-             WHERE is_dirty = 1 AND is_deleted = 0"
+             WHERE is_dirty = 1 AND is_deleted = 0 AND (?1 IS NULL OR workspace_id = ?1)"
         ).map_err(|e| AppError::DatabaseError(format!("Failed to get dirty notes: {}", e)))?;
 
-        let notes = stmt.query_map([], |row| {
+        let notes = stmt.query_map(params![workspace_id], |row| {
             Ok(Note {
                 id: row.get(0)?,
                 title: row.get(1)?,
@@ -321,6 +1027,7 @@ impl SyncService {
                 is_favorite: row.get(7)?,
                 is_deleted: row.get(8)?,
                 is_pinned: row.get(9)?,
+                is_private: row.get(19)?,
                 author: row.get(10)?,
                 created_at: row.get(11)?,
                 updated_at: row.get(12)?,
@@ -362,8 +1069,118 @@ impl SyncService {
         Ok(notes)
     }
 
-    /// 获取所有脏工作空间
-    fn get_dirty_workspaces(&self) -> Result<Vec<Workspace>> {
+    /// 获取收藏或置顶的脏笔记
+    fn get_dirty_priority_notes(&self) -> Result<Vec<Note>> {
+        let conn = self.pool.get()
+            .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, title, content, excerpt, markdown_cache, workspace_id, folder_id,
+                    is_favorite, is_deleted, is_pinned, author,
+                    created_at, updated_at, deleted_at, word_count, read_time_minutes,
+                    server_ver, is_dirty, last_synced_at, is_private
+             FROM notes
+             WHERE is_dirty = 1 AND is_deleted = 0 AND (is_favorite = 1 OR is_pinned = 1)"
+        ).map_err(|e| AppError::DatabaseError(format!("Failed to get priority notes: {}", e)))?;
+
+        let notes = stmt.query_map([], |row| {
+            Ok(Note {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                content: row.get(2)?,
+                excerpt: row.get(3)?,
+                markdown_cache: row.get(4)?,
+                workspace_id: row.get(5)?,
+                folder_id: row.get(6)?,
+                is_favorite: row.get(7)?,
+                is_deleted: row.get(8)?,
+                is_pinned: row.get(9)?,
+                is_private: row.get(19)?,
+                author: row.get(10)?,
+                created_at: row.get(11)?,
+                updated_at: row.get(12)?,
+                deleted_at: row.get(13)?,
+                word_count: row.get(14)?,
+                read_time_minutes: row.get(15)?,
+                server_ver: row.get(16)?,
+                is_dirty: row.get(17)?,
+                last_synced_at: row.get(18)?,
+            })
+        })
+        .map_err(|e| AppError::DatabaseError(format!("Failed to parse priority notes: {}", e)))?
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| AppError::DatabaseError(format!("Failed to collect priority notes: {}", e)))?;
+
+        Ok(notes)
+    }
+
+    /// 获取指定笔记（JSON 数组形式的 ID 列表）关联的脏标签
+    fn get_dirty_tags_for_notes(&self, note_ids_json: &str) -> Result<Vec<Tag>> {
+        let conn = self.pool.get()
+            .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, name, color, workspace_id, created_at, updated_at, deleted_at, server_ver, is_dirty, last_synced_at
+             FROM tags
+             WHERE is_dirty = 1 AND is_deleted = 0
+               AND id IN (
+                   SELECT tag_id FROM note_tags
+                   WHERE is_deleted = 0 AND note_id IN (SELECT value FROM json_each(?1))
+               )"
+        ).map_err(|e| AppError::DatabaseError(format!("Failed to get note tags: {}", e)))?;
+
+        let tags = stmt.query_map(params![note_ids_json], |row| {
+            Ok(Tag {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                color: row.get(2)?,
+                workspace_id: row.get(3)?,
+                created_at: row.get(4)?,
+                updated_at: row.get(5)?,
+                deleted_at: row.get(6)?,
+                server_ver: row.get(7)?,
+                is_dirty: row.get(8)?,
+                last_synced_at: row.get(9)?,
+                is_deleted: false,
+            })
+        })
+        .map_err(|e| AppError::DatabaseError(format!("Failed to parse tags: {}", e)))?
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| AppError::DatabaseError(format!("Failed to collect tags: {}", e)))?;
+
+        Ok(tags)
+    }
+
+    /// 获取指定笔记（JSON 数组形式的 ID 列表）有变化的标签关联（含已删除的关联，以便同步取消标签）
+    fn get_note_tags_for_notes(&self, note_ids_json: &str) -> Result<Vec<NoteTagRelation>> {
+        let conn = self.pool.get()
+            .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
+
+        let mut stmt = conn.prepare(
+            "SELECT note_id, tag_id, created_at, is_deleted, deleted_at
+             FROM note_tags
+             WHERE is_dirty = 1 AND note_id IN (SELECT value FROM json_each(?1))"
+        ).map_err(|e| AppError::DatabaseError(format!("Failed to get note tags: {}", e)))?;
+
+        let note_tags = stmt.query_map(params![note_ids_json], |row| {
+            Ok(NoteTagRelation {
+                note_id: row.get(0)?,
+                tag_id: row.get(1)?,
+                user_id: String::new(), // 本地不存储 user_id
+                created_at: row.get(2)?,
+                is_deleted: row.get(3)?,
+                deleted_at: row.get(4)?,
+            })
+        })
+        .map_err(|e| AppError::DatabaseError(format!("Failed to parse note tags: {}", e)))?
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| AppError::DatabaseError(format!("Failed to collect note tags: {}", e)))?;
+
+        Ok(note_tags)
+    }
+
+    /// 获取所有脏工作空间（指定 workspace_id 时只返回该工作空间）
+    fn get_dirty_workspaces(&self, workspace_id: Option<&str>) -> Result<Vec<Workspace>> {
         let conn = self.pool.get()
             .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
 
@@ -371,10 +1188,10 @@ impl SyncService {
             "SELECT id, user_id, name, description, icon, color, is_default, is_current, sort_order,
                     created_at, updated_at, is_deleted, deleted_at, server_ver, is_dirty, last_synced_at
              FROM workspaces
-             WHERE is_dirty = 1 AND is_deleted = 0"
+             WHERE is_dirty = 1 AND is_deleted = 0 AND (?1 IS NULL OR id = ?1)"
         ).map_err(|e| AppError::DatabaseError(format!("Failed to get dirty workspaces: {}", e)))?;
 
-        let workspaces = stmt.query_map([], |row| {
+        let workspaces = stmt.query_map(params![workspace_id], |row| {
             Ok(Workspace {
                 id: row.get(0)?,
                 user_id: row.get(1)?,
@@ -401,20 +1218,20 @@ impl SyncService {
         Ok(workspaces)
     }
 
-    /// 获取所有脏文件夹
-    fn get_dirty_folders(&self) -> Result<Vec<Folder>> {
+    /// 获取所有脏文件夹（指定 workspace_id 时只返回该工作空间的文件夹）
+    fn get_dirty_folders(&self, workspace_id: Option<&str>) -> Result<Vec<Folder>> {
         let conn = self.pool.get()
             .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
 
         let mut stmt = conn.prepare(
             "SELECT id, name, parent_id, icon, color, sort_order,
-                    is_deleted, created_at, updated_at, deleted_at,
+                    workspace_id, is_deleted, created_at, updated_at, deleted_at,
                     server_ver, is_dirty, last_synced_at
              FROM folders
-             WHERE is_dirty = 1 AND is_deleted = 0"
+             WHERE is_dirty = 1 AND is_deleted = 0 AND (?1 IS NULL OR workspace_id = ?1)"
         ).map_err(|e| AppError::DatabaseError(format!("Failed to get dirty folders: {}", e)))?;
 
-        let folders = stmt.query_map([], |row| {
+        let folders = stmt.query_map(params![workspace_id], |row| {
             Ok(Folder {
                 id: row.get(0)?,
                 name: row.get(1)?,
@@ -457,23 +1274,37 @@ impl SyncService {
         let conn = self.pool.get()
             .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
 
+        // 与 apply_server_note_v2 一致，归属到当前工作空间（未登录时保留笔记自带的 workspace_id）
+        let workspace_id: Option<String> = conn
+            .query_row(
+                "SELECT w.id FROM workspaces w
+                 JOIN user_auth u ON u.user_id = w.user_id
+                 WHERE u.is_current = 1 AND w.is_current = 1 AND w.is_deleted = 0
+                 LIMIT 1",
+                [],
+                |row| row.get(0),
+            )
+            .ok()
+            .or_else(|| note.workspace_id.clone());
+
         let now = Utc::now().timestamp();
+        let markdown_cache = Note::generate_markdown(&note.content);
         conn.execute(
             "INSERT OR REPLACE INTO notes
-             (id, title, content, excerpt, markdown_cache, folder_id,
+             (id, title, content, excerpt, markdown_cache, folder_id, workspace_id,
               is_favorite, is_deleted, is_pinned, author,
               created_at, updated_at, deleted_at, word_count, read_time_minutes,
-              server_ver, is_dirty, last_synced_at)
+              server_ver, is_dirty, last_synced_at, is_private)
              VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10,
-                     ?11, ?12, ?13, ?14, ?15, ?16, 0, ?17)",
+                     ?11, ?12, ?13, ?14, ?15, ?16, ?17, 0, ?18, ?19)",
             [
                 &note.id as &dyn rusqlite::ToSql, &note.title, &note.content, &note.excerpt,
-                &note.markdown_cache, &note.folder_id, &note.is_favorite as &dyn rusqlite::ToSql,
+                &markdown_cache, &note.folder_id, &workspace_id, &note.is_favorite as &dyn rusqlite::ToSql,
                 &note.is_deleted as &dyn rusqlite::ToSql, &note.is_pinned as &dyn rusqlite::ToSql,
                 &note.author, &note.created_at as &dyn rusqlite::ToSql, &now as &dyn rusqlite::ToSql,
                 &note.deleted_at as &dyn rusqlite::ToSql, &note.word_count as &dyn rusqlite::ToSql,
                 &note.read_time_minutes as &dyn rusqlite::ToSql, &note.server_ver as &dyn rusqlite::ToSql,
-                &now as &dyn rusqlite::ToSql,
+                &now as &dyn rusqlite::ToSql, &note.is_private as &dyn rusqlite::ToSql,
             ],
         ).map_err(|e| AppError::DatabaseError(format!("Failed to apply server note: {}", e)))?;
 
@@ -559,35 +1390,36 @@ impl SyncService {
         Ok(true)
     }
 
-    /// 标记工作空间为已删除
-    fn mark_workspace_deleted(&self, workspace_id: &str) -> Result<()> {
+    /// 批量标记工作空间为已删除（单条 UPDATE，默认工作空间不会被删除）
+    fn mark_workspaces_deleted(&self, workspace_ids: &[String]) -> Result<usize> {
+        if workspace_ids.is_empty() {
+            return Ok(0);
+        }
+
         let conn = self.pool.get()
             .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
 
         let now = Utc::now().timestamp();
+        let ids = ids_json(workspace_ids)?;
 
         // 软删除工作空间（但保护默认工作空间）
-        let is_default: bool = conn.query_row(
-            "SELECT is_default FROM workspaces WHERE id = ?",
-            [workspace_id],
-            |row| row.get(0),
-        ).unwrap_or(false);
+        let updated = conn.execute(
+            "UPDATE workspaces SET is_deleted = 1, deleted_at = ?1, is_dirty = 0
+             WHERE id IN (SELECT value FROM json_each(?2)) AND is_default = 0",
+            (now, &ids),
+        ).map_err(|e| AppError::DatabaseError(format!("标记工作空间已删除失败: {}", e)))?;
 
-        if is_default {
-            log::warn!("拒绝删除默认工作空间: {}", workspace_id);
-            return Ok(());  // 静默跳过
+        if updated < workspace_ids.len() {
+            log::warn!("拒绝删除默认工作空间或工作空间不存在: requested={}, deleted={}", workspace_ids.len(), updated);
         }
 
-        conn.execute(
-            "UPDATE workspaces SET is_deleted = 1, deleted_at = ?, is_dirty = 0 WHERE id = ?",
-            (now, workspace_id),
-        ).map_err(|e| AppError::DatabaseError(format!("标记工作空间已删除失败: {}", e)))?;
-
-        log::debug!("Workspace marked as deleted: {}", workspace_id);
-        Ok(())
+        log::debug!("Workspaces marked as deleted: {}", updated);
+        Ok(updated)
     }
 
     /// 解决冲突（保留服务器版本，创建本地副本）
+    ///
+    /// 笔记冲突同时记录到 sync_conflicts，可通过 get_pending_conflicts 查看双方内容
     fn resolve_conflict(&self, conflict: &ConflictInfo) -> Result<()> {
         if conflict.entity_type == "note" {
             // 创建本地副本
@@ -595,23 +1427,27 @@ impl SyncService {
                 .ok_or(AppError::NotFound(format!("Note {} not found", conflict.id)))?;
 
             // 使用 Note::conflict_copy() 方法创建冲突副本
-            let conflict_note = original_note.conflict_copy("冲突副本 - 本地");
+            // 原笔记此时可能已被服务器版本覆盖，服务器返回了本地推送的内容时以它为准
+            let mut conflict_note = original_note.conflict_copy("冲突副本 - 本地");
+            if let Some(local_content) = &conflict.local_content {
+                conflict_note.update_content(local_content.clone());
+            }
 
             let conn = self.pool.get()
                 .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
 
             conn.execute(
                 "INSERT INTO notes
-                 (id, title, content, excerpt, markdown_cache, folder_id,
+                 (id, title, content, excerpt, markdown_cache, folder_id, workspace_id,
                   is_favorite, is_deleted, is_pinned, author,
                   created_at, updated_at, deleted_at, word_count, read_time_minutes,
-                  server_ver, is_dirty, last_synced_at)
+                  server_ver, is_dirty, last_synced_at, is_private)
                  VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10,
-                         ?11, ?12, ?13, ?14, ?15, ?16, 1, ?17)",
+                         ?11, ?12, ?13, ?14, ?15, ?16, ?17, 1, ?18, ?19)",
                 [
                     &conflict_note.id as &dyn rusqlite::ToSql, &conflict_note.title,
                     &conflict_note.content, &conflict_note.excerpt, &conflict_note.markdown_cache,
-                    &conflict_note.folder_id, &conflict_note.is_favorite as &dyn rusqlite::ToSql,
+                    &conflict_note.folder_id, &conflict_note.workspace_id, &conflict_note.is_favorite as &dyn rusqlite::ToSql,
                     &conflict_note.is_deleted as &dyn rusqlite::ToSql,
                     &conflict_note.is_pinned as &dyn rusqlite::ToSql,
                     &conflict_note.author, &conflict_note.created_at as &dyn rusqlite::ToSql,
@@ -621,9 +1457,22 @@ impl SyncService {
                     &conflict_note.read_time_minutes as &dyn rusqlite::ToSql,
                     &conflict_note.server_ver as &dyn rusqlite::ToSql,
                     &conflict_note.last_synced_at as &dyn rusqlite::ToSql,
+                    &conflict_note.is_private as &dyn rusqlite::ToSql,
                 ],
             ).map_err(|e| AppError::DatabaseError(format!("Failed to create conflict copy: {}", e)))?;
 
+            conn.execute(
+                "INSERT INTO sync_conflicts
+                 (note_id, copy_id, title, local_version, server_version,
+                  local_content, server_content, local_updated_at, server_updated_at, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    conflict.id, conflict_note.id, conflict.title, conflict.local_version, conflict.server_version,
+                    conflict.local_content, conflict.server_content, conflict.local_updated_at, conflict.server_updated_at,
+                    Utc::now().timestamp(),
+                ],
+            ).map_err(|e| AppError::DatabaseError(format!("Failed to record conflict: {}", e)))?;
+
             log::warn!("Created conflict copy for note {} as {}", conflict.id, conflict_note.id);
         }
 
@@ -639,7 +1488,7 @@ impl SyncService {
             "SELECT id, title, content, excerpt, markdown_cache, workspace_id, folder_id,
                     is_favorite, is_deleted, is_pinned, author,
                     created_at, updated_at, deleted_at, word_count, read_time_minutes,
-                    server_ver, is_dirty, last_synced_at
+                    server_ver, is_dirty, last_synced_at, is_private
              FROM notes
              WHERE id = ?1"
         ).map_err(|e| AppError::DatabaseError(format!("Failed to get note: {}", e)))?;
@@ -656,6 +1505,7 @@ impl SyncService {
                 is_favorite: row.get(7)?,
                 is_deleted: row.get(8)?,
                 is_pinned: row.get(9)?,
+                is_private: row.get(19)?,
                 author: row.get(10)?,
                 created_at: row.get(11)?,
                 updated_at: row.get(12)?,
@@ -708,6 +1558,60 @@ impl SyncService {
         Ok(note_count + folder_count)
     }
 
+    /// 检测反复删除/复活的实体
+    ///
+    /// 一台设备删除后，另一台设备用过期数据同步又把它复活，会导致 is_deleted 来回变化。
+    /// 基于本地 `delete_events` 记录，返回最近 7 天内变化至少 3 次的实体及其变化时间线，
+    /// 每条记录附带变化时的 `server_ver`，便于判断是哪次同步带来的变化
+    pub fn detect_flapping(&self, workspace_id: &str) -> Result<Vec<FlappingItem>> {
+        let conn = self.pool.get()
+            .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
+
+        let since = Utc::now().timestamp() - FLAPPING_WINDOW_SECS;
+
+        let mut stmt = conn.prepare(
+            "SELECT entity_type, entity_id, is_deleted, server_ver, occurred_at
+             FROM delete_events
+             WHERE workspace_id = ? AND occurred_at >= ?
+             ORDER BY occurred_at ASC, id ASC"
+        )?;
+
+        let rows = stmt.query_map(params![workspace_id, since], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                DeleteEvent {
+                    is_deleted: row.get(2)?,
+                    server_ver: row.get(3)?,
+                    occurred_at: row.get(4)?,
+                },
+            ))
+        })?;
+
+        let mut timelines: HashMap<(String, String), Vec<DeleteEvent>> = HashMap::new();
+        for row in rows {
+            let (entity_type, entity_id, event) = row?;
+            timelines.entry((entity_type, entity_id)).or_default().push(event);
+        }
+
+        let mut items: Vec<FlappingItem> = timelines
+            .into_iter()
+            .filter(|(_, timeline)| timeline.len() >= FLAPPING_MIN_TOGGLES)
+            .map(|((entity_type, entity_id), timeline)| FlappingItem {
+                entity_type,
+                entity_id,
+                toggle_count: timeline.len(),
+                timeline,
+            })
+            .collect();
+
+        items.sort_by_key(|item| std::cmp::Reverse(item.toggle_count));
+
+        log::info!("[SyncService] 反复删除检测: workspace_id={}, items={}", workspace_id, items.len());
+
+        Ok(items)
+    }
+
     /// ===== 新增辅助方法 =====
 
     /// 获取上次同步时间（别名，供 SingleSyncService 使用）
@@ -728,13 +1632,100 @@ impl SyncService {
         }
     }
 
+    /// 获取指定工作空间上次单独同步的时间（从未单独同步过时为空，此时拉取该工作空间的全部数据）
+    fn get_workspace_last_sync_at(&self, workspace_id: &str) -> Result<Option<i64>> {
+        let conn = self.pool.get()
+            .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
+
+        let value: Option<String> = conn.query_row(
+            "SELECT value FROM settings WHERE key = ?1",
+            params![format!("workspace_last_sync_at:{}", workspace_id)],
+            |row| row.get(0),
+        ).ok();
+
+        Ok(value.and_then(|v| v.parse().ok()))
+    }
+
+    /// 记录指定工作空间单独同步的时间
+    fn set_workspace_last_sync_at(&self, workspace_id: &str, last_sync_at: i64) -> Result<()> {
+        let conn = self.pool.get()
+            .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
+
+        conn.execute(
+            "INSERT OR REPLACE INTO settings (key, value, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?3)",
+            params![format!("workspace_last_sync_at:{}", workspace_id), last_sync_at.to_string(), Utc::now().timestamp()],
+        ).map_err(|e| AppError::DatabaseError(format!("Failed to save workspace sync time: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// 获取服务器上次返回的变更游标（指定工作空间时读取该工作空间单独同步的游标）
+    ///
+    /// 游标按用户保存（服务器的变更序号按用户递增）。从未同步过或服务器不支持游标时为空，
+    /// 此时服务器按 last_sync_at 拉取
+    pub fn get_sync_cursor(&self, workspace_id: Option<&str>) -> Result<Option<i64>> {
+        let conn = self.pool.get()
+            .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
+
+        let user_id: Option<String> = conn.query_row(
+            "SELECT user_id FROM user_auth WHERE is_current = 1 LIMIT 1",
+            [],
+            |row| row.get(0),
+        ).ok();
+        let Some(user_id) = user_id else {
+            return Ok(None);
+        };
+
+        let value: Option<String> = conn.query_row(
+            "SELECT value FROM settings WHERE key = ?1",
+            params![sync_cursor_key(&user_id, workspace_id)],
+            |row| row.get(0),
+        ).ok();
+
+        Ok(value.and_then(|v| v.parse().ok()))
+    }
+
+    /// 保存服务器返回的变更游标（旧服务器不返回游标时保持不变）
+    pub fn save_sync_cursor(&self, workspace_id: Option<&str>, cursor: Option<i64>) -> Result<()> {
+        let Some(cursor) = cursor else {
+            return Ok(());
+        };
+
+        let conn = self.pool.get()
+            .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
+
+        let user_id: String = conn.query_row(
+            "SELECT user_id FROM user_auth WHERE is_current = 1 LIMIT 1",
+            [],
+            |row| row.get(0),
+        ).map_err(|_| AppError::NotAuthenticated("User not logged in".to_string()))?;
+
+        conn.execute(
+            "INSERT OR REPLACE INTO settings (key, value, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?3)",
+            params![sync_cursor_key(&user_id, workspace_id), cursor.to_string(), Utc::now().timestamp()],
+        ).map_err(|e| AppError::DatabaseError(format!("Failed to save sync cursor: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// 获取按实体类型配置的冲突解决策略（来自应用设置）
+    pub fn get_conflict_strategies(&self) -> Result<ConflictStrategies> {
+        let settings = AppSettingsService::new(self.pool.clone()).get_settings()?;
+        Ok(settings.conflict_strategies)
+    }
+
     /// 构建同步请求（收集所有脏数据）
-    fn build_sync_request(&self) -> Result<SyncRequest> {
+    ///
+    /// 指定 `workspace_id` 时只收集该工作空间的脏数据，并在请求中指明目标工作空间。
+    /// 返回同步请求以及因超过大小上限而跳过的快照数量
+    fn build_sync_request(&self, workspace_id: Option<&str>) -> Result<(SyncRequest, usize)> {
         use crate::models::ConflictStrategy;
 
-        let dirty_workspaces = self.get_dirty_workspaces()?;
-        let dirty_notes = self.get_dirty_notes()?;
-        let dirty_folders = self.get_dirty_folders()?;
+        let dirty_workspaces = self.get_dirty_workspaces(workspace_id)?;
+        let dirty_notes = self.get_dirty_notes(workspace_id)?;
+        let dirty_folders = self.get_dirty_folders(workspace_id)?;
 
         log::info!("[SyncService] 构建同步请求: dirty_workspaces={}, dirty_notes={}, dirty_folders={}",
             dirty_workspaces.len(), dirty_notes.len(), dirty_folders.len());
@@ -747,31 +1738,66 @@ impl SyncService {
             }
         }
 
-        Ok(SyncRequest {
+        let (snapshots, skipped_snapshots) = self.filter_syncable_snapshots(self.get_dirty_snapshots(workspace_id)?)?;
+
+        let request = SyncRequest {
             workspaces: Some(dirty_workspaces.into_iter().map(|w| w.into()).collect()),
             notes: Some(dirty_notes.into_iter().map(|n| n.into()).collect()),
             folders: Some(dirty_folders.into_iter().map(|f| f.into()).collect()),
-            tags: Some(self.get_dirty_tags()?.into_iter().map(|t| t.into()).collect()),
-            snapshots: Some(self.get_dirty_snapshots()?.into_iter().map(|s| s.into()).collect()),
-            note_tags: Some(self.get_note_tags_relations()?.into_iter().map(|nt| nt.into()).collect()),
-            last_sync_at: self.get_last_sync_at()?,
+            tags: Some(self.get_dirty_tags(workspace_id)?.into_iter().map(|t| t.into()).collect()),
+            snapshots: Some(snapshots.into_iter().map(|s| s.into()).collect()),
+            note_tags: Some(self.get_dirty_note_tags_relations(workspace_id)?.into_iter().map(|nt| nt.into()).collect()),
+            attachments: Some(self.get_dirty_attachments(workspace_id)?.into_iter().map(|a| a.into()).collect()),
+            last_sync_at: match workspace_id {
+                Some(id) => self.get_workspace_last_sync_at(id)?,
+                None => self.get_last_sync_at()?,
+            },
+            cursor: self.get_sync_cursor(workspace_id)?,
+            workspace_id: workspace_id.map(str::to_string),
             conflict_resolution: ConflictStrategy::default(),
+            conflict_strategies: self.get_conflict_strategies()?,
             device_id: None, // 在 send_sync_request 中设置
-        })
+            idempotency_key: None, // 在 send_sync_request 中设置
+        };
+
+        Ok((request, skipped_snapshots))
+    }
+
+    /// 过滤掉内容超过同步大小上限的快照
+    ///
+    /// 被跳过的快照保持 is_dirty = 1，不会进入请求，因此也不会被 clear_dirty_markers 标记为已同步
+    pub fn filter_syncable_snapshots(&self, snapshots: Vec<NoteSnapshot>) -> Result<(Vec<NoteSnapshot>, usize)> {
+        let max_bytes = AppSettingsService::new(self.pool.clone()).get_max_synced_snapshot_bytes()?;
+        if max_bytes == 0 {
+            return Ok((snapshots, 0));
+        }
+
+        let total = snapshots.len();
+        let syncable: Vec<NoteSnapshot> = snapshots
+            .into_iter()
+            .filter(|s| s.content.len() <= max_bytes)
+            .collect();
+        let skipped = total - syncable.len();
+
+        if skipped > 0 {
+            log::info!("[SyncService] 跳过 {} 个超过 {} 字节的快照（仅保留在本地）", skipped, max_bytes);
+        }
+
+        Ok((syncable, skipped))
     }
 
-    /// 获取所有脏标签
-    fn get_dirty_tags(&self) -> Result<Vec<Tag>> {
+    /// 获取所有脏标签（指定 workspace_id 时只返回该工作空间的标签）
+    fn get_dirty_tags(&self, workspace_id: Option<&str>) -> Result<Vec<Tag>> {
         let conn = self.pool.get()
             .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
 
         let mut stmt = conn.prepare(
-            "SELECT id, name, color, created_at, updated_at, deleted_at, server_ver, is_dirty, last_synced_at
+            "SELECT id, name, color, workspace_id, created_at, updated_at, deleted_at, server_ver, is_dirty, last_synced_at
              FROM tags
-             WHERE is_dirty = 1 AND is_deleted = 0"
+             WHERE is_dirty = 1 AND is_deleted = 0 AND (?1 IS NULL OR workspace_id = ?1)"
         ).map_err(|e| AppError::DatabaseError(format!("Failed to get dirty tags: {}", e)))?;
 
-        let tags = stmt.query_map([], |row| {
+        let tags = stmt.query_map(params![workspace_id], |row| {
             Ok(Tag {
                 id: row.get(0)?,
                 name: row.get(1)?,
@@ -793,13 +1819,15 @@ impl SyncService {
         Ok(tags)
     }
 
-    /// 获取所有脏快照
-    fn get_dirty_snapshots(&self) -> Result<Vec<NoteSnapshot>> {
+    /// 获取所有脏快照（当前工作空间或指定工作空间）
+    fn get_dirty_snapshots(&self, target_workspace_id: Option<&str>) -> Result<Vec<NoteSnapshot>> {
         let conn = self.pool.get()
             .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
 
-        // 获取当前 workspace_id（通过当前用户的 is_current 标记）
-        let workspace_id: Option<String> = {
+        // 获取目标 workspace_id（未指定时通过当前用户的 is_current 标记获取当前工作空间）
+        let workspace_id: Option<String> = if let Some(id) = target_workspace_id {
+            Some(id.to_string())
+        } else {
             // 获取当前用户 ID
             let user_id: Option<String> = conn
                 .query_row(
@@ -852,25 +1880,77 @@ impl SyncService {
         Ok(snapshots)
     }
 
-    /// 获取所有笔记标签关联
-    fn get_note_tags_relations(&self) -> Result<Vec<NoteTagRelation>> {
+    /// 获取所有脏附件（含已删除的附件，指定 workspace_id 时只返回该工作空间的附件）
+    fn get_dirty_attachments(&self, workspace_id: Option<&str>) -> Result<Vec<Attachment>> {
+        self.query_dirty_attachments("(?1 IS NULL OR workspace_id = ?1)", workspace_id)
+    }
+
+    /// 获取指定笔记的脏附件（含已删除的附件，供单个同步和优先同步使用）
+    pub fn get_dirty_attachments_for_notes(&self, note_ids: &[String]) -> Result<Vec<Attachment>> {
+        if note_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.query_dirty_attachments("note_id IN (SELECT value FROM json_each(?1))", ids_json(note_ids)?)
+    }
+
+    fn query_dirty_attachments(&self, filter: &str, param: impl rusqlite::ToSql) -> Result<Vec<Attachment>> {
+        let conn = self.pool.get()
+            .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, note_id, workspace_id, filename, mime_type, size_bytes, data, created_at, updated_at,
+                    is_deleted, deleted_at, server_ver, is_dirty, last_synced_at
+             FROM attachments
+             WHERE is_dirty = 1 AND {}",
+            filter
+        )).map_err(|e| AppError::DatabaseError(format!("Failed to get dirty attachments: {}", e)))?;
+
+        let attachments = stmt.query_map(params![param], |row| {
+            Ok(Attachment {
+                id: row.get(0)?,
+                note_id: row.get(1)?,
+                workspace_id: row.get(2)?,
+                filename: row.get(3)?,
+                mime_type: row.get(4)?,
+                size_bytes: row.get(5)?,
+                data: row.get(6)?,
+                created_at: row.get(7)?,
+                updated_at: row.get(8)?,
+                is_deleted: row.get(9)?,
+                deleted_at: row.get(10)?,
+                server_ver: row.get(11)?,
+                is_dirty: row.get(12)?,
+                last_synced_at: row.get(13)?,
+            })
+        })
+        .map_err(|e| AppError::DatabaseError(format!("Failed to parse attachments: {}", e)))?
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| AppError::DatabaseError(format!("Failed to collect attachments: {}", e)))?;
+
+        Ok(attachments)
+    }
+
+    /// 获取所有笔记标签关联（指定 workspace_id 时只返回该工作空间笔记的关联）
+    pub fn get_dirty_note_tags_relations(&self, workspace_id: Option<&str>) -> Result<Vec<NoteTagRelation>> {
         let conn = self.pool.get()
             .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
 
-        // 查询所有标签关联
+        // 只查询有变化的标签关联（含已删除的关联，以便同步取消标签）
         let mut stmt = conn.prepare(
-            "SELECT nt.note_id, nt.tag_id, nt.created_at
-             FROM note_tags nt"
+            "SELECT note_id, tag_id, created_at, is_deleted, deleted_at
+             FROM note_tags
+             WHERE is_dirty = 1
+               AND (?1 IS NULL OR note_id IN (SELECT id FROM notes WHERE workspace_id = ?1))"
         ).map_err(|e| AppError::DatabaseError(format!("Failed to get note tags: {}", e)))?;
 
-        let note_tags = stmt.query_map([], |row| {
+        let note_tags = stmt.query_map(params![workspace_id], |row| {
             Ok(NoteTagRelation {
                 note_id: row.get(0)?,
                 tag_id: row.get(1)?,
                 user_id: String::new(), // 本地不存储 user_id
                 created_at: row.get(2)?,
-                is_deleted: false,
-                deleted_at: None,
+                is_deleted: row.get(3)?,
+                deleted_at: row.get(4)?,
             })
         })
         .map_err(|e| AppError::DatabaseError(format!("Failed to parse note tags: {}", e)))?
@@ -883,6 +1963,22 @@ impl SyncService {
     /// 应用服务器响应（完整实现）
     /// 返回实际新拉取并应用的数据数量（修正服务器统计）
     pub fn apply_sync_response(&self, response: &SyncResponse) -> Result<SyncResponse> {
+        self.apply_sync_response_to(response, None, auth_service::account_epoch())
+    }
+
+    /// 应用服务器响应，拉取的数据归属到 `target_workspace_id`（为空时归属到当前工作空间）
+    ///
+    /// 每写入一条数据前检查账号纪元，与 `account_epoch` 不一致时立即取消，
+    /// 避免把服务器数据写入切换后的账号
+    fn apply_sync_response_to(&self, response: &SyncResponse, target_workspace_id: Option<&str>, account_epoch: u64) -> Result<SyncResponse> {
+        let ensure_account_unchanged = || -> Result<()> {
+            if auth_service::account_epoch() != account_epoch {
+                log::warn!("[SyncService] 应用服务器响应时账号已切换，取消同步");
+                return Err(AppError::SyncCancelled("账号已切换，已取消同步".to_string()));
+            }
+            Ok(())
+        };
+
         let sync_time = response.last_sync_at;
 
         // 1. 应用 upserted 数据（新增或更新），统计实际应用的数量
@@ -892,56 +1988,69 @@ impl SyncService {
         let mut actually_applied_tags = 0usize;
         let mut actually_applied_snapshots = 0usize;
         let mut actually_applied_note_tags = 0usize;
+        let mut actually_applied_attachments = 0usize;
 
         // ✅ 优先应用 workspaces（其他数据依赖 workspace_id）
         for workspace in &response.upserted_workspaces {
+            ensure_account_unchanged()?;
             if self.apply_server_workspace_v2(workspace, sync_time)? {
                 actually_applied_workspaces += 1;
             }
         }
 
         for note in &response.upserted_notes {
-            if self.apply_server_note_v2(note, sync_time)? {
+            ensure_account_unchanged()?;
+            if self.apply_server_note_v2(note, sync_time, target_workspace_id)? {
                 actually_applied_notes += 1;
             }
         }
         for folder in &response.upserted_folders {
-            if self.apply_server_folder_v2(folder, sync_time)? {
+            ensure_account_unchanged()?;
+            if self.apply_server_folder_v2(folder, sync_time, target_workspace_id)? {
                 actually_applied_folders += 1;
             }
         }
         for tag in &response.upserted_tags {
-            if self.apply_server_tag_v2(tag, sync_time)? {
+            ensure_account_unchanged()?;
+            if self.apply_server_tag_v2(tag, sync_time, target_workspace_id)? {
                 actually_applied_tags += 1;
             }
         }
         for snapshot in &response.upserted_snapshots {
-            if self.apply_server_snapshot_v2(snapshot, sync_time)? {
+            ensure_account_unchanged()?;
+            if self.apply_server_snapshot_v2(snapshot, sync_time, target_workspace_id)? {
                 actually_applied_snapshots += 1;
             }
         }
         for relation in &response.upserted_note_tags {
-            if self.apply_server_note_tag_v2(relation)? {
+            ensure_account_unchanged()?;
+            if self.apply_server_note_tag_v2(relation, target_workspace_id)? {
                 actually_applied_note_tags += 1;
             }
         }
-
-        // 2. 应用 deleted 数据（使用软删除）
-        for workspace_id in &response.deleted_workspace_ids {
-            self.mark_workspace_deleted(workspace_id)?;
-        }
-        for note_id in &response.deleted_note_ids {
-            self.mark_note_deleted(note_id)?;
-        }
-        for folder_id in &response.deleted_folder_ids {
-            self.mark_folder_deleted(folder_id)?;
-        }
-        for tag_id in &response.deleted_tag_ids {
-            self.mark_tag_deleted(tag_id)?;
+        // 附件依赖笔记，放在笔记之后应用
+        for attachment in &response.upserted_attachments {
+            ensure_account_unchanged()?;
+            if self.apply_server_attachment_v2(attachment, sync_time, target_workspace_id)? {
+                actually_applied_attachments += 1;
+            }
         }
 
+        // 2. 应用 deleted 数据（使用软删除，每种实体一条 UPDATE）
+        ensure_account_unchanged()?;
+        self.mark_workspaces_deleted(&response.deleted_workspace_ids)?;
+        ensure_account_unchanged()?;
+        self.mark_notes_deleted(&response.deleted_note_ids)?;
+        ensure_account_unchanged()?;
+        self.mark_folders_deleted(&response.deleted_folder_ids)?;
+        ensure_account_unchanged()?;
+        self.mark_tags_deleted(&response.deleted_tag_ids)?;
+        ensure_account_unchanged()?;
+        self.mark_attachments_deleted(&response.deleted_attachment_ids)?;
+
         // 3. 处理冲突
         for conflict in &response.conflicts {
+            ensure_account_unchanged()?;
             self.resolve_conflict(conflict)?;
         }
 
@@ -953,8 +2062,9 @@ impl SyncService {
         corrected_response.pulled_tags = actually_applied_tags;
         corrected_response.pulled_snapshots = actually_applied_snapshots;
         corrected_response.pulled_note_tags = actually_applied_note_tags;
+        corrected_response.pulled_attachments = actually_applied_attachments;
         corrected_response.pulled_total = actually_applied_workspaces + actually_applied_notes + actually_applied_folders
-            + actually_applied_tags + actually_applied_snapshots + actually_applied_note_tags;
+            + actually_applied_tags + actually_applied_snapshots + actually_applied_note_tags + actually_applied_attachments;
 
         Ok(corrected_response)
     }
@@ -1035,19 +2145,45 @@ impl SyncService {
             }
         }
 
+        // 清理 note_tags
+        if let Some(note_tags) = &request.note_tags {
+            log::info!("[SyncService] 清理 {} 个标签关联的脏标记", note_tags.len());
+            for relation in note_tags {
+                conn.execute(
+                    "UPDATE note_tags SET is_dirty = 0 WHERE note_id = ? AND tag_id = ?",
+                    (&relation.note_id, &relation.tag_id),
+                ).map_err(|e| AppError::DatabaseError(format!("清除标签关联脏标记失败: {}", e)))?;
+            }
+        }
+
+        // 清理 attachments
+        if let Some(attachments) = &request.attachments {
+            log::info!("[SyncService] 清理 {} 个附件的脏标记", attachments.len());
+            for attachment in attachments {
+                conn.execute(
+                    "UPDATE attachments SET is_dirty = 0, last_synced_at = ? WHERE id = ?",
+                    (sync_time, &attachment.id),
+                ).map_err(|e| AppError::DatabaseError(format!("清除附件脏标记失败: {}", e)))?;
+            }
+        }
+
         log::info!("[SyncService] 清理脏标记完成");
         Ok(())
     }
 
     /// 应用服务器笔记（v2，接受 ServerNote）
     /// 返回是否真的应用了数据（true = 应用/更新，false = 跳过）
-    fn apply_server_note_v2(&self, server_note: &crate::models::sync::ServerNote, sync_time: i64) -> Result<bool> {
-        let note: Note = server_note.clone().into();
+    fn apply_server_note_v2(&self, server_note: &crate::models::sync::ServerNote, sync_time: i64, target_workspace_id: Option<&str>) -> Result<bool> {
+        let mut note: Note = server_note.clone().into();
+        // Markdown 缓存按拉取到的内容重新生成，不沿用本地或服务器上可能过期的缓存
+        note.markdown_cache = Note::generate_markdown(&note.content);
         let conn = self.pool.get()
             .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
 
-        // 获取当前工作空间 ID（通过当前用户的 is_current 标记）
-        let workspace_id: Option<String> = {
+        // 获取目标工作空间 ID（未指定时通过当前用户的 is_current 标记获取当前工作空间）
+        let workspace_id: Option<String> = if let Some(id) = target_workspace_id {
+            Some(id.to_string())
+        } else {
             // 获取当前用户 ID
             let user_id: Option<String> = conn
                 .query_row(
@@ -1098,19 +2234,20 @@ impl SyncService {
              (id, title, content, excerpt, markdown_cache, folder_id, workspace_id,
               is_favorite, is_deleted, is_pinned, author,
               created_at, updated_at, deleted_at, word_count, read_time_minutes,
-              server_ver, is_dirty, last_synced_at)
+              server_ver, is_dirty, last_synced_at, is_private)
              VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10,
-                     ?11, ?12, ?13, ?14, ?15, ?16, ?17, 0, ?18)
+                     ?11, ?12, ?13, ?14, ?15, ?16, ?17, 0, ?18, ?19)
              ON CONFLICT(id) DO UPDATE SET
                 title = excluded.title,
                 content = excluded.content,
                 excerpt = excluded.excerpt,
-                markdown_cache = COALESCE(excluded.markdown_cache, notes.markdown_cache),
+                markdown_cache = excluded.markdown_cache,
                 folder_id = excluded.folder_id,
                 workspace_id = excluded.workspace_id,
                 is_favorite = excluded.is_favorite,
                 is_deleted = excluded.is_deleted,
                 is_pinned = excluded.is_pinned,
+                is_private = excluded.is_private,
                 author = COALESCE(excluded.author, notes.author),
                 updated_at = excluded.updated_at,
                 deleted_at = excluded.deleted_at,
@@ -1127,7 +2264,7 @@ impl SyncService {
                 &note.created_at as &dyn rusqlite::ToSql, &note.updated_at as &dyn rusqlite::ToSql,
                 &note.deleted_at as &dyn rusqlite::ToSql, &note.word_count as &dyn rusqlite::ToSql,
                 &note.read_time_minutes as &dyn rusqlite::ToSql, &note.server_ver as &dyn rusqlite::ToSql,
-                &sync_time as &dyn rusqlite::ToSql,
+                &sync_time as &dyn rusqlite::ToSql, &note.is_private as &dyn rusqlite::ToSql,
             ],
         ).map_err(|e| AppError::DatabaseError(format!("Failed to apply server note: {}", e)))?;
 
@@ -1138,13 +2275,15 @@ impl SyncService {
     }
 
     /// 应用服务器文件夹（v2，接受 ServerFolder）
-    fn apply_server_folder_v2(&self, server_folder: &crate::models::sync::ServerFolder, sync_time: i64) -> Result<bool> {
+    fn apply_server_folder_v2(&self, server_folder: &crate::models::sync::ServerFolder, sync_time: i64, target_workspace_id: Option<&str>) -> Result<bool> {
         let folder: Folder = server_folder.clone().into();
         let conn = self.pool.get()
             .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
 
-        // 获取当前工作空间 ID（通过当前用户的 is_current 标记）
-        let workspace_id: Option<String> = {
+        // 获取目标工作空间 ID（未指定时通过当前用户的 is_current 标记获取当前工作空间）
+        let workspace_id: Option<String> = if let Some(id) = target_workspace_id {
+            Some(id.to_string())
+        } else {
             // 获取当前用户 ID
             let user_id: Option<String> = conn
                 .query_row(
@@ -1221,13 +2360,15 @@ impl SyncService {
     }
 
     /// 应用服务器标签（v2，检查版本）
-    fn apply_server_tag_v2(&self, server_tag: &crate::models::sync::ServerTag, sync_time: i64) -> Result<bool> {
+    fn apply_server_tag_v2(&self, server_tag: &crate::models::sync::ServerTag, sync_time: i64, target_workspace_id: Option<&str>) -> Result<bool> {
         let tag: Tag = server_tag.clone().into();
         let conn = self.pool.get()
             .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
 
-        // 获取当前工作空间 ID（通过当前用户的 is_current 标记）
-        let workspace_id: Option<String> = {
+        // 获取目标工作空间 ID（未指定时通过当前用户的 is_current 标记获取当前工作空间）
+        let workspace_id: Option<String> = if let Some(id) = target_workspace_id {
+            Some(id.to_string())
+        } else {
             // 获取当前用户 ID
             let user_id: Option<String> = conn
                 .query_row(
@@ -1294,13 +2435,15 @@ impl SyncService {
     }
 
     /// 应用服务器快照（v2，检查版本）
-    fn apply_server_snapshot_v2(&self, server_snapshot: &crate::models::sync::ServerNoteSnapshot, sync_time: i64) -> Result<bool> {
+    fn apply_server_snapshot_v2(&self, server_snapshot: &crate::models::sync::ServerNoteSnapshot, sync_time: i64, target_workspace_id: Option<&str>) -> Result<bool> {
         let snapshot: NoteSnapshot = server_snapshot.clone().into();
         let conn = self.pool.get()
             .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
 
-        // 获取当前工作空间 ID（通过当前用户的 is_current 标记）
-        let workspace_id: Option<String> = {
+        // 获取目标工作空间 ID（未指定时通过当前用户的 is_current 标记获取当前工作空间）
+        let workspace_id: Option<String> = if let Some(id) = target_workspace_id {
+            Some(id.to_string())
+        } else {
             // 获取当前用户 ID
             let user_id: Option<String> = conn
                 .query_row(
@@ -1371,14 +2514,83 @@ impl SyncService {
         Ok(true)
     }
 
+    /// 应用服务器附件（v2，检查版本）
+    ///
+    /// 附件归属到 `target_workspace_id`，未指定时归属到所属笔记的工作空间
+    fn apply_server_attachment_v2(&self, server_attachment: &crate::models::sync::ServerAttachment, sync_time: i64, target_workspace_id: Option<&str>) -> Result<bool> {
+        let attachment: Attachment = server_attachment.clone().into();
+        let conn = self.pool.get()
+            .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
+
+        let workspace_id: Option<String> = match target_workspace_id {
+            Some(id) => Some(id.to_string()),
+            None => conn.query_row(
+                "SELECT workspace_id FROM notes WHERE id = ?",
+                [&attachment.note_id],
+                |row| row.get(0),
+            ).ok().flatten(),
+        };
+
+        // 检查本地附件的 server_ver
+        let local_server_ver: Option<i32> = conn.query_row(
+            "SELECT server_ver FROM attachments WHERE id = ?",
+            [&attachment.id],
+            |row| row.get(0)
+        ).ok();
+
+        match local_server_ver {
+            Some(local_ver) if local_ver >= server_attachment.server_ver => {
+                log::info!("[SyncService] ⏭️ 跳过服务器附件（本地版本更新或相同）: id={}, local_ver={}, server_ver={}",
+                    attachment.id, local_ver, server_attachment.server_ver);
+                return Ok(false);
+            },
+            _ => {
+                log::info!("[SyncService] ✅ 应用服务器附件: id={}, note_id={}, local_ver={:?}, server_ver={}",
+                    attachment.id, attachment.note_id, local_server_ver, server_attachment.server_ver);
+            }
+        }
+
+        conn.execute(
+            "INSERT INTO attachments
+             (id, note_id, workspace_id, filename, mime_type, size_bytes, data,
+              created_at, updated_at, is_deleted, deleted_at, server_ver, is_dirty, last_synced_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, 0, ?13)
+             ON CONFLICT(id) DO UPDATE SET
+                note_id = excluded.note_id,
+                workspace_id = excluded.workspace_id,
+                filename = excluded.filename,
+                mime_type = excluded.mime_type,
+                size_bytes = excluded.size_bytes,
+                data = excluded.data,
+                updated_at = excluded.updated_at,
+                is_deleted = excluded.is_deleted,
+                deleted_at = excluded.deleted_at,
+                server_ver = excluded.server_ver,
+                is_dirty = 0,
+                last_synced_at = excluded.last_synced_at",
+            params![
+                &attachment.id, &attachment.note_id, &workspace_id,
+                &attachment.filename, &attachment.mime_type, attachment.size_bytes, &attachment.data,
+                attachment.created_at, attachment.updated_at,
+                attachment.is_deleted, attachment.deleted_at,
+                server_attachment.server_ver,
+                sync_time,
+            ],
+        ).map_err(|e| AppError::DatabaseError(format!("应用服务器附件失败: {}", e)))?;
+
+        Ok(true)
+    }
+
     /// 应用服务器笔记标签关联（v2，返回是否真的插入了）
-    fn apply_server_note_tag_v2(&self, server_relation: &crate::models::sync::ServerNoteTagRelation) -> Result<bool> {
+    fn apply_server_note_tag_v2(&self, server_relation: &crate::models::sync::ServerNoteTagRelation, target_workspace_id: Option<&str>) -> Result<bool> {
         let relation: NoteTagRelation = server_relation.clone().into();
         let conn = self.pool.get()
             .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
 
-        // 获取当前工作空间 ID（通过当前用户的 is_current 标记）
-        let workspace_id: Option<String> = {
+        // 获取目标工作空间 ID（未指定时通过当前用户的 is_current 标记获取当前工作空间）
+        let workspace_id: Option<String> = if let Some(id) = target_workspace_id {
+            Some(id.to_string())
+        } else {
             // 获取当前用户 ID
             let user_id: Option<String> = conn
                 .query_row(
@@ -1403,112 +2615,230 @@ impl SyncService {
             }
         };
 
+        // 已存在的关联采用服务器的删除状态（本地有未同步修改时保留本地状态）
         let rows_affected = conn.execute(
-            "INSERT OR IGNORE INTO note_tags (note_id, tag_id, workspace_id, created_at)
-             VALUES (?1, ?2, ?3, ?4)",
-            (&relation.note_id, &relation.tag_id, &workspace_id, relation.created_at),
+            "INSERT INTO note_tags (note_id, tag_id, workspace_id, created_at, is_deleted, deleted_at, is_dirty)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, 0)
+             ON CONFLICT(note_id, tag_id) DO UPDATE SET
+                is_deleted = excluded.is_deleted,
+                deleted_at = excluded.deleted_at
+             WHERE note_tags.is_dirty = 0
+               AND (note_tags.is_deleted IS NOT excluded.is_deleted OR note_tags.deleted_at IS NOT excluded.deleted_at)",
+            params![
+                &relation.note_id,
+                &relation.tag_id,
+                &workspace_id,
+                relation.created_at,
+                relation.is_deleted,
+                relation.deleted_at,
+            ],
         ).map_err(|e| AppError::DatabaseError(format!("应用服务器笔记标签关联失败: {}", e)))?;
 
-        // rows_affected > 0 表示真的插入了新数据，= 0 表示已存在（被 IGNORE）
+        // rows_affected > 0 表示插入或更新了数据，= 0 表示没有变化或本地有未同步修改
         Ok(rows_affected > 0)
     }
 
-    /// 标记笔记为已删除
-    fn mark_note_deleted(&self, note_id: &str) -> Result<()> {
+    /// 批量标记笔记为已删除（单条 UPDATE）
+    fn mark_notes_deleted(&self, note_ids: &[String]) -> Result<usize> {
+        if note_ids.is_empty() {
+            return Ok(0);
+        }
+
         let conn = self.pool.get()
             .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
 
         let now = Utc::now().timestamp();
-        conn.execute(
-            "UPDATE notes SET is_deleted = 1, deleted_at = ?, is_dirty = 0 WHERE id = ?",
-            (now, note_id),
+        let updated = conn.execute(
+            "UPDATE notes SET is_deleted = 1, deleted_at = ?1, is_dirty = 0 WHERE id IN (SELECT value FROM json_each(?2))",
+            (now, ids_json(note_ids)?),
         ).map_err(|e| AppError::DatabaseError(format!("标记笔记已删除失败: {}", e)))?;
 
-        log::debug!("Note marked as deleted: {}", note_id);
-        Ok(())
+        log::debug!("Notes marked as deleted: {}", updated);
+        Ok(updated)
+    }
+
+    /// 批量标记附件为已删除（服务器删除，单条 UPDATE）
+    fn mark_attachments_deleted(&self, attachment_ids: &[String]) -> Result<usize> {
+        if attachment_ids.is_empty() {
+            return Ok(0);
+        }
+
+        let conn = self.pool.get()
+            .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
+
+        let now = Utc::now().timestamp();
+        let updated = conn.execute(
+            "UPDATE attachments SET is_deleted = 1, deleted_at = ?1, is_dirty = 0 WHERE id IN (SELECT value FROM json_each(?2))",
+            (now, ids_json(attachment_ids)?),
+        ).map_err(|e| AppError::DatabaseError(format!("标记附件已删除失败: {}", e)))?;
+
+        log::debug!("Attachments marked as deleted: {}", updated);
+        Ok(updated)
     }
 
-    /// 标记文件夹为已删除（服务器删除）
-    fn mark_folder_deleted(&self, folder_id: &str) -> Result<()> {
+    /// 批量标记文件夹为已删除（服务器删除，连同所有子文件夹，单条 UPDATE）
+    fn mark_folders_deleted(&self, folder_ids: &[String]) -> Result<usize> {
+        if folder_ids.is_empty() {
+            return Ok(0);
+        }
+
         let conn = self.pool.get()
             .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
 
         let now = Utc::now().timestamp();
 
         // 软删除文件夹及所有子文件夹
-        conn.execute(
+        let updated = conn.execute(
             "WITH RECURSIVE folder_tree AS (
-                SELECT id FROM folders WHERE id = ?1
-                UNION ALL
+                SELECT id FROM folders WHERE id IN (SELECT value FROM json_each(?1))
+                UNION
                 SELECT f.id FROM folders f
                 INNER JOIN folder_tree ft ON f.parent_id = ft.id
                 WHERE f.is_deleted = 0
             )
             UPDATE folders SET is_deleted = 1, deleted_at = ?2, is_dirty = 0
             WHERE id IN folder_tree",
-            (folder_id, now),
+            (ids_json(folder_ids)?, now),
         ).map_err(|e| AppError::DatabaseError(format!("标记文件夹删除失败: {}", e)))?;
 
-        log::debug!("Folder marked as deleted: {}", folder_id);
-        Ok(())
+        log::debug!("Folders marked as deleted: {}", updated);
+        Ok(updated)
     }
 
-    /// 标记标签为已删除（服务器删除）
-    fn mark_tag_deleted(&self, tag_id: &str) -> Result<()> {
+    /// 批量标记标签为已删除（服务器删除）
+    ///
+    /// 标签和它们的笔记关联各一条 UPDATE
+    fn mark_tags_deleted(&self, tag_ids: &[String]) -> Result<usize> {
+        if tag_ids.is_empty() {
+            return Ok(0);
+        }
+
         let conn = self.pool.get()
             .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
 
         let now = Utc::now().timestamp();
+        let ids = ids_json(tag_ids)?;
 
         // 软删除标签
-        conn.execute(
-            "UPDATE tags SET is_deleted = 1, deleted_at = ?, is_dirty = 0 WHERE id = ?",
-            (now, tag_id),
+        let updated = conn.execute(
+            "UPDATE tags SET is_deleted = 1, deleted_at = ?1, is_dirty = 0 WHERE id IN (SELECT value FROM json_each(?2))",
+            (now, &ids),
         ).map_err(|e| AppError::DatabaseError(format!("标记标签删除失败: {}", e)))?;
 
         // 同时软删除所有关联的 note_tags
         conn.execute(
-            "UPDATE note_tags SET is_deleted = 1, deleted_at = ? WHERE tag_id = ?",
-            (now, tag_id),
+            "UPDATE note_tags SET is_deleted = 1, deleted_at = ?1, is_dirty = 0 WHERE tag_id IN (SELECT value FROM json_each(?2))",
+            (now, &ids),
         ).map_err(|e| AppError::DatabaseError(format!("标记标签的笔记关联删除失败: {}", e)))?;
 
-        log::debug!("Tag marked as deleted: {}", tag_id);
-        Ok(())
+        log::debug!("Tags marked as deleted: {}", updated);
+        Ok(updated)
     }
 
     /// ===== 统一同步方法 =====
 
     /// 发送同步请求到服务器（统一的 /sync 端点）
+    /// 获取服务器限制（带本地缓存）
+    ///
+    /// 缓存保存在 `settings` 表的 `server_limits` 键中，有效期 1 小时。
+    /// `force_refresh` 为 true 时忽略缓存；请求失败但存在旧缓存时返回旧缓存
+    pub async fn fetch_server_limits(&self, force_refresh: bool) -> Result<ServerLimits> {
+        let cached = self.get_cached_server_limits()?;
+        let now = Utc::now().timestamp();
+
+        if !force_refresh {
+            if let Some((limits, fetched_at)) = &cached {
+                if now - fetched_at < SERVER_LIMITS_TTL_SECS {
+                    return Ok(limits.clone());
+                }
+            }
+        }
+
+        match self.request_server_limits().await {
+            Ok((limits, body)) => {
+                let conn = self.pool.get()
+                    .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
+                conn.execute(
+                    "INSERT OR REPLACE INTO settings (key, value, created_at, updated_at)
+                     VALUES ('server_limits', ?1, ?2, ?2)",
+                    params![body, now],
+                ).map_err(|e| AppError::DatabaseError(format!("Failed to cache server limits: {}", e)))?;
+
+                log::info!("[SyncService] 已更新服务器限制: {:?}", limits);
+                Ok(limits)
+            }
+            Err(e) => match cached {
+                Some((limits, _)) => {
+                    log::warn!("[SyncService] 获取服务器限制失败，使用缓存: {}", e);
+                    Ok(limits)
+                }
+                None => Err(e),
+            },
+        }
+    }
+
+    /// 读取缓存的服务器限制和缓存时间
+    fn get_cached_server_limits(&self) -> Result<Option<(ServerLimits, i64)>> {
+        let conn = self.pool.get()
+            .map_err(|e| AppError::DatabaseError(format!("Failed to get connection: {}", e)))?;
+
+        let cached: Option<(String, i64)> = conn.query_row(
+            "SELECT value, updated_at FROM settings WHERE key = 'server_limits'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).ok();
+
+        Ok(cached.and_then(|(json, fetched_at)| {
+            serde_json::from_str(&json).ok().map(|limits| (limits, fetched_at))
+        }))
+    }
+
+    /// 请求服务器 `/limits` 接口，返回解析结果和原始响应（用于缓存）
+    async fn request_server_limits(&self) -> Result<(ServerLimits, String)> {
+        let (server_url, token, _) = self.get_auth_info()?;
+        let url = format!("{}/limits", server_url.trim_end_matches('/'));
+
+        let response = self.client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", token))
+            .send()
+            .await
+            .map_err(|e| AppError::NetworkError(format!("获取服务器限制失败: {}", e)))?;
+
+        let status = response.status();
+        let body = response.text().await
+            .map_err(|e| AppError::NetworkError(format!("读取服务器限制失败: {}", e)))?;
+
+        if !status.is_success() {
+            return Err(AppError::NetworkError(format!("服务器错误 {}: {}", status, body)));
+        }
+
+        let limits = serde_json::from_str(&body)
+            .map_err(|e| AppError::NetworkError(format!("服务器限制响应无效: {}", e)))?;
+
+        Ok((limits, body))
+    }
+
     pub async fn send_sync_request(&self, request: &SyncRequest) -> Result<SyncResponse> {
         let (server_url, token, device_id) = self.get_auth_info()?;
         let url = format!("{}/sync", server_url.trim_end_matches('/'));
 
-        log::info!("[SyncService] 发送同步请求到: {}, device_id={}", url, device_id);
+        // 每次同步使用新的请求 ID（重试和刷新 token 后重发沿用同一个），服务器日志使用相同的 ID；
+        // 同时作为幂等键，重试时服务器不会重复应用已处理的推送
+        let request_id = uuid::Uuid::new_v4().to_string();
+
+        log::info!("[SyncService] 发送同步请求到: {}, device_id={}, request_id={}", url, device_id, request_id);
 
         // 创建包含 device_id 的请求
         let mut request_with_device = request.clone();
         request_with_device.device_id = Some(device_id.clone());
+        request_with_device.idempotency_key = Some(request_id.clone());
 
         // 构建 User-Agent
         let user_agent = build_user_agent();
 
-        // 发送请求
-        let response = self.client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", token))
-            .header("Content-Type", "application/json")
-            .header("User-Agent", user_agent.clone())
-            .json(&request_with_device)
-            .send()
-            .await;
-
-        let response = match response {
-            Ok(r) => r,
-            Err(e) => {
-                log::error!("Failed to send sync request: {}", e);
-                return Err(AppError::NetworkError(format!("同步请求失败: {}", e)));
-            }
-        };
+        // 发送请求（连接失败、超时和 5xx 按设置重试）
+        let response = self.post_sync_with_retry(&url, &token, &user_agent, &request_id, &request_with_device).await?;
 
         let status = response.status();
 
@@ -1526,21 +2856,13 @@ impl SyncService {
                     let (server_url, new_token, device_id) = self.get_auth_info()?;
                     let mut request_with_device = request.clone();
                     request_with_device.device_id = Some(device_id);
+                    request_with_device.idempotency_key = Some(request_id.clone());
+
+                    let url = format!("{}/sync", server_url.trim_end_matches('/'));
+                    let response = self.post_sync_with_retry(&url, &new_token, &user_agent, &request_id, &request_with_device).await?;
 
-                    let response = self.client
-                        .post(&format!("{}/sync", server_url.trim_end_matches('/')))
-                        .header("Authorization", format!("Bearer {}", new_token))
-                        .header("Content-Type", "application/json")
-                        .header("User-Agent", user_agent)
-                        .json(&request_with_device)
-                        .send()
-                        .await
-                        .map_err(|e| {
-                            log::error!("Failed to retry sync request: {}", e);
-                            AppError::NetworkError(format!("重试同步请求失败: {}", e))
-                        })?;
-
-                    return self.parse_sync_response(response).await;
+                    return self.parse_sync_response(response).await
+                        .inspect_err(|e| log::error!("[SyncService] 同步失败: request_id={}, error={}", request_id, e));
                 }
                 Err(e) => {
                     log::error!("Failed to refresh token: {}", e);
@@ -1550,13 +2872,86 @@ impl SyncService {
         }
 
         self.parse_sync_response(response).await
+            .inspect_err(|e| log::error!("[SyncService] 同步失败: request_id={}, error={}", request_id, e))
+    }
+
+    /// 发送同步 POST 请求，连接失败、超时或服务器 5xx 时按指数退避重试
+    ///
+    /// 4xx 不重试（401 由调用方刷新 token 后重新发送），但 409 `IDEMPOTENCY_KEY_IN_USE`
+    /// （同一次同步的上一次尝试仍在处理中）会用同一个幂等键重试。最后一次尝试仍返回 5xx 时原样返回响应，
+    /// 由 parse_sync_response 转换为错误。
+    /// 请求体达到压缩阈值时使用 gzip 发送，同时声明接受 gzip 响应。
+    /// 每次尝试都携带同一个 `X-Request-Id`，便于在服务器日志中查找
+    async fn post_sync_with_retry(&self, url: &str, token: &str, user_agent: &str, request_id: &str, body: &SyncRequest) -> Result<reqwest::Response> {
+        let settings = AppSettingsService::new(self.pool.clone());
+        let (max_attempts, base_delay_ms) = settings.get_sync_retry_policy()?;
+        let (payload, compressed) = encode_sync_body(body, settings.get_sync_compression_threshold()?)?;
+
+        let mut attempt = 1;
+        loop {
+            let mut request = self.client
+                .post(url)
+                .header("Authorization", format!("Bearer {}", token))
+                .header("Content-Type", "application/json")
+                .header("Accept-Encoding", "gzip")
+                .header("User-Agent", user_agent)
+                .header(REQUEST_ID_HEADER, request_id);
+            if compressed {
+                request = request.header("Content-Encoding", "gzip");
+            }
+
+            let result = request
+                .body(payload.clone())
+                .send()
+                .await;
+
+            let retry_reason = match result {
+                // 同一个幂等键的上一次尝试仍在服务器处理中，稍后重试即可拿到它的结果
+                Ok(response) if response.status() == reqwest::StatusCode::CONFLICT => {
+                    let (error_code, error_msg) = read_error_response(response).await;
+                    if error_code.as_deref() != Some(IDEMPOTENCY_KEY_IN_USE) || attempt >= max_attempts {
+                        log::error!("Server returned error 409: request_id={}, error={}", request_id, error_msg);
+                        return Err(AppError::SyncError(error_msg));
+                    }
+                    error_msg
+                }
+                Ok(response) if response.status().is_server_error() && attempt < max_attempts => {
+                    format!("服务器返回 {}", response.status())
+                }
+                Err(e) if (e.is_connect() || e.is_timeout()) && attempt < max_attempts => e.to_string(),
+                result => {
+                    return result.map_err(|e| {
+                        log::error!("Failed to send sync request: request_id={}, error={}", request_id, e);
+                        AppError::NetworkError(format!("同步请求失败: {}", e))
+                    });
+                }
+            };
+
+            let delay = retry_backoff_ms(base_delay_ms, attempt, jitter_fraction());
+            log::warn!("[SyncService] 同步请求失败，{}ms 后重试 ({}/{}): request_id={}, {}", delay, attempt, max_attempts, request_id, retry_reason);
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            attempt += 1;
+        }
     }
 
     /// 解析同步响应
     async fn parse_sync_response(&self, response: reqwest::Response) -> Result<SyncResponse> {
         let status = response.status();
+        let gzipped = response.headers()
+            .get(reqwest::header::CONTENT_ENCODING)
+            .is_some_and(|v| v.as_bytes().eq_ignore_ascii_case(b"gzip"));
+
+        let bytes = response.bytes().await.map_err(|e| {
+            log::error!("Failed to read response: {}", e);
+            AppError::NetworkError(format!("读取响应失败: {}", e))
+        })?;
 
-        let response_json: serde_json::Value = response.json().await.map_err(|e| {
+        let parsed = if gzipped {
+            serde_json::from_reader(GzDecoder::new(bytes.as_ref()))
+        } else {
+            serde_json::from_slice(&bytes)
+        };
+        let response_json: serde_json::Value = parsed.map_err(|e| {
             log::error!("Failed to parse response: {}", e);
             AppError::NetworkError(format!("响应无效: {}", e))
         })?;
@@ -1584,6 +2979,14 @@ impl SyncService {
     }
 }
 
+/// 读取错误响应的错误码和错误信息
+async fn read_error_response(response: reqwest::Response) -> (Option<String>, String) {
+    let body: serde_json::Value = response.json().await.unwrap_or_default();
+    let error_code = body["error_code"].as_str().map(str::to_string);
+    let error_msg = body["error"].as_str().unwrap_or("Unknown error").to_string();
+    (error_code, error_msg)
+}
+
 /// 构建 User-Agent 头部
 fn build_user_agent() -> String {
     format!(
@@ -1593,6 +2996,59 @@ fn build_user_agent() -> String {
     )
 }
 
+/// 第 `attempt` 次失败后的重试间隔（毫秒）
+///
+/// 以 `base_ms` 为起点每次翻倍，再乘以 `[0.5, 1.0]` 区间的抖动系数，避免多个客户端同时重试
+fn retry_backoff_ms(base_ms: u64, attempt: u32, jitter: f64) -> u64 {
+    let exponent = attempt.saturating_sub(1).min(16);
+    let delay = base_ms.saturating_mul(1 << exponent).min(MAX_RETRY_DELAY_MS);
+    (delay as f64 * (0.5 + jitter.clamp(0.0, 1.0) * 0.5)) as u64
+}
+
+/// `[0, 1]` 区间的随机抖动系数
+fn jitter_fraction() -> f64 {
+    uuid::Uuid::new_v4().as_bytes()[0] as f64 / u8::MAX as f64
+}
+
+/// 序列化同步请求体，达到 `threshold` 字节时使用 gzip 压缩（`threshold` 为 0 表示不压缩）
+///
+/// 返回请求体和是否已压缩
+fn encode_sync_body(body: &SyncRequest, threshold: usize) -> Result<(Vec<u8>, bool)> {
+    let json = serde_json::to_vec(body)
+        .map_err(|e| AppError::Internal(format!("序列化同步请求失败: {}", e)))?;
+
+    if threshold == 0 || json.len() < threshold {
+        return Ok((json, false));
+    }
+
+    let mut encoder = GzEncoder::new(Vec::with_capacity(json.len() / 4), Compression::default());
+    encoder.write_all(&json)
+        .and_then(|_| encoder.finish())
+        .map(|compressed| {
+            log::info!("[SyncService] 同步请求体已压缩: {} -> {} 字节", json.len(), compressed.len());
+            (compressed, true)
+        })
+        .map_err(|e| AppError::Internal(format!("压缩同步请求失败: {}", e)))
+}
+
+/// 从 `start` 到现在经过的毫秒数（单调时钟）
+fn elapsed_ms(start: Instant) -> u64 {
+    start.elapsed().as_millis() as u64
+}
+
+/// id 列表序列化为 JSON 数组，配合 `json_each` 在一条语句中处理多行
+fn ids_json(ids: &[String]) -> Result<String> {
+    serde_json::to_string(ids).map_err(|e| AppError::Internal(format!("序列化 id 列表失败: {}", e)))
+}
+
+/// 变更游标在 settings 表中的键
+fn sync_cursor_key(user_id: &str, workspace_id: Option<&str>) -> String {
+    match workspace_id {
+        Some(id) => format!("sync_cursor:{}:{}", user_id, id),
+        None => format!("sync_cursor:{}", user_id),
+    }
+}
+
 /// 获取平台信息字符串
 fn get_platform_info() -> String {
     #[cfg(target_os = "windows")]
@@ -1611,3 +3067,640 @@ fn get_platform_info() -> String {
     )))]
     { "Unknown".to_string() }
 }
+
+/// 笔记本地版本与服务器版本的差异
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteServerDiff {
+    pub note_id: String,
+    pub exists_on_server: bool,  // 服务器上是否存在该笔记
+    pub local_title: String,
+    pub server_title: Option<String>,
+    pub local_server_ver: i32,  // 本地记录的服务器版本号
+    pub server_ver: Option<i32>,  // 服务器当前版本号
+    pub server_updated_at: Option<i64>,
+    pub server_deleted: bool,  // 服务器上是否已删除
+    pub added: usize,  // 本地新增的行数
+    pub removed: usize,  // 本地删除的行数（仅服务器存在）
+    pub lines: Vec<DiffLine>,  // 逐行差异（以服务器版本为基准）
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::sync_events::{SYNC_COMPLETED_EVENT, SYNC_ERROR_EVENT, SYNC_STARTED_EVENT};
+
+    fn memory_service() -> SyncService {
+        let pool = crate::database::memory_pool();
+        SyncService::new(pool)
+    }
+
+    #[test]
+    fn test_apply_server_note_keeps_workspace_id() {
+        let service = memory_service();
+        {
+            let conn = service.pool.get().unwrap();
+            conn.execute_batch(
+                "INSERT INTO user_auth (user_id, server_url, email, access_token_encrypted, device_id, is_current, created_at, updated_at)
+                 VALUES ('user-1', 'http://localhost', 'a@example.com', 'token', 'device-1', 1, 0, 0);
+                 INSERT INTO workspaces (id, user_id, name, is_current, created_at, updated_at)
+                 VALUES ('workspace-1', 'user-1', '默认', 1, 0, 0);"
+            ).unwrap();
+        }
+
+        let mut note = Note::new("标题".to_string(), "内容".to_string(), None);
+        note.server_ver = 2;
+        service.apply_server_note(&note).unwrap();
+
+        let workspace_id: Option<String> = service.pool.get().unwrap()
+            .query_row("SELECT workspace_id FROM notes WHERE id = ?1", [&note.id], |row| row.get(0))
+            .unwrap();
+        assert_eq!(workspace_id.as_deref(), Some("workspace-1"));
+    }
+
+    #[test]
+    fn test_apply_server_note_regenerates_markdown_cache() {
+        let service = memory_service();
+        service.pool.get().unwrap().execute_batch(
+            "INSERT INTO notes (id, title, content, markdown_cache, created_at, updated_at, server_ver)
+             VALUES ('note-1', '标题', '旧内容', '旧内容', 0, 0, 1);"
+        ).unwrap();
+
+        let content = r#"{"type":"doc","content":[{"type":"paragraph","content":[{"type":"text","text":"服务器上的新内容"}]}]}"#;
+        let server_note: crate::models::sync::ServerNote = serde_json::from_value(serde_json::json!({
+            "id": "note-1", "user_id": "user-1", "title": "标题", "content": content,
+            "folder_id": null, "is_deleted": false, "deleted_at": null,
+            "created_at": 0, "updated_at": 10, "server_ver": 2,
+        })).unwrap();
+        assert!(service.apply_server_note_v2(&server_note, 10, Some("ws-1")).unwrap());
+
+        let cache: Option<String> = service.pool.get().unwrap()
+            .query_row("SELECT markdown_cache FROM notes WHERE id = 'note-1'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(cache, Note::generate_markdown(content));
+        assert!(cache.unwrap().contains("服务器上的新内容"));
+    }
+
+    #[test]
+    fn test_build_sync_request_skips_clean_note_tags() {
+        let service = memory_service();
+        service.pool.get().unwrap().execute_batch(
+            "INSERT INTO notes (id, title, content, created_at, updated_at, is_dirty) VALUES ('note-1', 't', 'c', 0, 0, 0);
+             INSERT INTO tags (id, name, created_at, updated_at, is_dirty) VALUES ('tag-clean', 'a', 0, 0, 0), ('tag-dirty', 'b', 0, 0, 0);
+             INSERT INTO note_tags (note_id, tag_id, created_at, is_dirty) VALUES ('note-1', 'tag-clean', 0, 0);
+             INSERT INTO note_tags (note_id, tag_id, created_at, is_deleted, deleted_at, is_dirty) VALUES ('note-1', 'tag-dirty', 0, 1, 10, 1);"
+        ).unwrap();
+
+        let (request, _) = service.build_sync_request(None).unwrap();
+        let note_tags = request.note_tags.unwrap();
+
+        assert_eq!(note_tags.len(), 1);
+        assert_eq!(note_tags[0].tag_id, "tag-dirty");
+        assert!(note_tags[0].is_deleted);
+    }
+
+    #[test]
+    fn test_build_sync_request_for_workspace_excludes_other_workspaces() {
+        let service = memory_service();
+        service.pool.get().unwrap().execute_batch(
+            "INSERT INTO notes (id, title, content, workspace_id, created_at, updated_at, is_dirty)
+                 VALUES ('note-a', 't', 'c', 'ws-a', 0, 0, 1), ('note-b', 't', 'c', 'ws-b', 0, 0, 1);
+             INSERT INTO folders (id, name, workspace_id, created_at, updated_at, is_dirty)
+                 VALUES ('folder-a', 'a', 'ws-a', 0, 0, 1), ('folder-b', 'b', 'ws-b', 0, 0, 1);
+             INSERT INTO tags (id, name, workspace_id, created_at, updated_at, is_dirty)
+                 VALUES ('tag-a', 'a', 'ws-a', 0, 0, 1), ('tag-b', 'b', 'ws-b', 0, 0, 1);
+             INSERT INTO note_tags (note_id, tag_id, created_at, is_dirty)
+                 VALUES ('note-a', 'tag-a', 0, 1), ('note-b', 'tag-b', 0, 1);"
+        ).unwrap();
+
+        let (request, _) = service.build_sync_request(Some("ws-a")).unwrap();
+
+        assert_eq!(request.workspace_id.as_deref(), Some("ws-a"));
+        let note_ids: Vec<_> = request.notes.unwrap().into_iter().map(|n| n.id).collect();
+        assert_eq!(note_ids, vec!["note-a"]);
+        let folder_ids: Vec<_> = request.folders.unwrap().into_iter().map(|f| f.id).collect();
+        assert_eq!(folder_ids, vec!["folder-a"]);
+        let tag_ids: Vec<_> = request.tags.unwrap().into_iter().map(|t| t.id).collect();
+        assert_eq!(tag_ids, vec!["tag-a"]);
+        let note_tags = request.note_tags.unwrap();
+        assert_eq!(note_tags.len(), 1);
+        assert_eq!(note_tags[0].note_id, "note-a");
+
+        let (request, _) = service.build_sync_request(None).unwrap();
+        assert!(request.workspace_id.is_none());
+        assert_eq!(request.notes.unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_note_conflict_surfaces_both_versions() {
+        let service = memory_service();
+        service.pool.get().unwrap().execute(
+            "INSERT INTO notes (id, title, content, workspace_id, created_at, updated_at, server_ver, is_dirty)
+             VALUES ('note-1', '标题', '服务器版本', 'workspace-1', 0, 20, 3, 0)",
+            [],
+        ).unwrap();
+
+        service.resolve_conflict(&ConflictInfo {
+            id: "note-1".to_string(),
+            entity_type: "note".to_string(),
+            local_version: 2,
+            server_version: 3,
+            title: "标题".to_string(),
+            local_content: Some("本地版本".to_string()),
+            server_content: Some("服务器版本".to_string()),
+            local_updated_at: Some(10),
+            server_updated_at: Some(20),
+        }).unwrap();
+
+        let conflicts = service.get_pending_conflicts().unwrap();
+        assert_eq!(conflicts.len(), 1);
+
+        let conflict = &conflicts[0];
+        assert_eq!(conflict.local_content, "本地版本");
+        assert_eq!(conflict.server_content, "服务器版本");
+        assert_eq!(conflict.original.as_ref().unwrap().content, "服务器版本");
+
+        let copy = conflict.copy.as_ref().unwrap();
+        assert_eq!(copy.content, "本地版本");
+        assert_eq!(copy.workspace_id.as_deref(), Some("workspace-1"));
+    }
+
+    fn conflicted_service() -> SyncService {
+        let service = memory_service();
+        service.pool.get().unwrap().execute(
+            "INSERT INTO notes (id, title, content, workspace_id, created_at, updated_at, server_ver, is_dirty)
+             VALUES ('note-1', '标题', '服务器版本', 'workspace-1', 0, 20, 3, 0)",
+            [],
+        ).unwrap();
+
+        service.resolve_conflict(&ConflictInfo {
+            id: "note-1".to_string(),
+            entity_type: "note".to_string(),
+            local_version: 2,
+            server_version: 5,
+            title: "标题".to_string(),
+            local_content: Some("本地版本".to_string()),
+            server_content: Some("服务器版本".to_string()),
+            local_updated_at: Some(10),
+            server_updated_at: Some(20),
+        }).unwrap();
+        service
+    }
+
+    #[test]
+    fn test_resolve_note_conflict_writes_merged_content() {
+        let service = conflicted_service();
+
+        let note = service.resolve_note_conflict("note-1", "合并版本").unwrap();
+        assert_eq!(note.content, "合并版本");
+        assert!(note.is_dirty);
+
+        let stored = service.get_note_by_id("note-1").unwrap().unwrap();
+        assert_eq!(stored.content, "合并版本");
+        assert!(stored.is_dirty);
+    }
+
+    #[test]
+    fn test_resolve_note_conflict_bumps_past_server_version() {
+        let service = conflicted_service();
+
+        let note = service.resolve_note_conflict("note-1", "合并版本").unwrap();
+        assert_eq!(note.server_ver, 6);
+        assert_eq!(service.get_note_by_id("note-1").unwrap().unwrap().server_ver, 6);
+    }
+
+    #[test]
+    fn test_resolve_note_conflict_removes_copy() {
+        let service = conflicted_service();
+        let copy_id = service.get_pending_conflicts().unwrap()[0].copy_id.clone().unwrap();
+
+        service.resolve_note_conflict("note-1", "合并版本").unwrap();
+
+        let copy = service.get_note_by_id(&copy_id).unwrap().unwrap();
+        assert!(copy.is_deleted);
+        assert!(copy.is_dirty);
+        assert!(service.get_pending_conflicts().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_retry_backoff_doubles_with_jitter() {
+        assert_eq!(retry_backoff_ms(500, 1, 1.0), 500);
+        assert_eq!(retry_backoff_ms(500, 2, 1.0), 1000);
+        assert_eq!(retry_backoff_ms(500, 3, 1.0), 2000);
+        assert_eq!(retry_backoff_ms(500, 3, 0.0), 1000);
+        assert_eq!(retry_backoff_ms(500, 30, 1.0), MAX_RETRY_DELAY_MS);
+        assert_eq!(retry_backoff_ms(0, 3, 1.0), 0);
+    }
+
+    /// 启动一个按顺序返回指定状态码的 HTTP 服务器，返回地址和已处理的请求数
+    fn mock_server(responses: Vec<(u16, String)>) -> (String, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+        let hits = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = hits.clone();
+        let mut responses = responses.into_iter();
+        let addr = crate::services::mock_http::serve(move |_| {
+            counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            responses.next().unwrap_or((500, r#"{"error":"no more responses"}"#.to_string()))
+        });
+
+        (addr, hits)
+    }
+
+    fn empty_sync_response() -> String {
+        serde_json::json!({
+            "status": "success", "server_time": 1, "last_sync_at": 1,
+            "upserted_workspaces": [], "upserted_notes": [], "upserted_folders": [],
+            "upserted_tags": [], "upserted_snapshots": [], "upserted_note_tags": [],
+            "pushed_workspaces": 0, "pushed_notes": 0, "pushed_folders": 0, "pushed_tags": 0,
+            "pushed_snapshots": 0, "pushed_note_tags": 0, "pushed_total": 0,
+            "pulled_workspaces": 0, "pulled_notes": 0, "pulled_folders": 0, "pulled_tags": 0,
+            "pulled_snapshots": 0, "pulled_note_tags": 0, "pulled_total": 0
+        }).to_string()
+    }
+
+    /// 已登录到指定服务器、最多尝试 3 次的同步服务
+    fn logged_in_service(server_url: &str) -> SyncService {
+        let service = memory_service();
+        {
+            let device_id = "device-1";
+            let token = CryptoService::encrypt_token("token", device_id).unwrap();
+            let conn = service.pool.get().unwrap();
+            conn.execute(
+                "INSERT INTO user_auth (user_id, server_url, email, access_token_encrypted, device_id, is_current, created_at, updated_at)
+                 VALUES ('user-1', ?1, 'a@example.com', ?2, ?3, 1, 0, 0)",
+                params![server_url, token, device_id],
+            ).unwrap();
+            conn.execute("UPDATE app_settings SET sync_request_max_attempts = 3, sync_retry_base_delay_ms = 1", []).unwrap();
+        }
+        service
+    }
+
+    #[test]
+    fn test_send_sync_request_retries_server_errors() {
+        let unavailable = r#"{"error":"unavailable"}"#.to_string();
+        let (server_url, hits) = mock_server(vec![(503, unavailable.clone()), (503, unavailable), (200, empty_sync_response())]);
+        let service = logged_in_service(&server_url);
+
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        let response = runtime.block_on(service.send_sync_request(&SyncRequest::default())).unwrap();
+
+        assert_eq!(response.status, "success");
+        assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_send_sync_request_retries_only_idempotency_conflicts() {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+
+        // 上一次尝试仍在服务器处理中：用同一个幂等键重试
+        let in_use = r#"{"status":409,"error_code":"IDEMPOTENCY_KEY_IN_USE","error":"相同幂等键的请求正在处理中"}"#.to_string();
+        let (server_url, hits) = mock_server(vec![(409, in_use), (200, empty_sync_response())]);
+        let response = runtime.block_on(logged_in_service(&server_url).send_sync_request(&SyncRequest::default())).unwrap();
+        assert_eq!(response.status, "success");
+        assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+        // 其他 409（如同步锁被占用）直接返回错误
+        let locked = r#"{"status":409,"error_code":"SYNC_IN_PROGRESS","error":"其他工作空间正在同步"}"#.to_string();
+        let (server_url, hits) = mock_server(vec![(409, locked), (200, empty_sync_response())]);
+        let result = runtime.block_on(logged_in_service(&server_url).send_sync_request(&SyncRequest::default()));
+        assert!(matches!(result, Err(AppError::SyncError(msg)) if msg == "其他工作空间正在同步"));
+        assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_apply_server_note_tag_takes_server_delete_state() {
+        let service = memory_service();
+        service.pool.get().unwrap().execute_batch(
+            "INSERT INTO notes (id, title, content, workspace_id, created_at, updated_at) VALUES ('note-1', 't', 'c', 'ws-1', 0, 0);
+             INSERT INTO tags (id, name, workspace_id, created_at, updated_at) VALUES
+                ('tag-synced', 'a', 'ws-1', 0, 0), ('tag-dirty', 'b', 'ws-1', 0, 0), ('tag-new', 'c', 'ws-1', 0, 0);
+             INSERT INTO note_tags (note_id, tag_id, workspace_id, created_at, is_deleted, is_dirty) VALUES
+                ('note-1', 'tag-synced', 'ws-1', 0, 0, 0),
+                ('note-1', 'tag-dirty', 'ws-1', 0, 0, 1);"
+        ).unwrap();
+        let relation = |tag_id: &str, is_deleted: bool| crate::models::sync::ServerNoteTagRelation {
+            note_id: "note-1".to_string(),
+            tag_id: tag_id.to_string(),
+            user_id: "user-1".to_string(),
+            created_at: 0,
+            is_deleted,
+            deleted_at: is_deleted.then_some(100),
+        };
+        let state = |tag_id: &str| -> (bool, Option<i64>) {
+            service.pool.get().unwrap().query_row(
+                "SELECT is_deleted, deleted_at FROM note_tags WHERE note_id = 'note-1' AND tag_id = ?",
+                [tag_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            ).unwrap()
+        };
+
+        // 其他设备移除了标签
+        assert!(service.apply_server_note_tag_v2(&relation("tag-synced", true), Some("ws-1")).unwrap());
+        assert_eq!(state("tag-synced"), (true, Some(100)));
+        // 重复拉取没有变化
+        assert!(!service.apply_server_note_tag_v2(&relation("tag-synced", true), Some("ws-1")).unwrap());
+
+        // 服务器上已删除的关联不会作为有效关联插入
+        assert!(service.apply_server_note_tag_v2(&relation("tag-new", true), Some("ws-1")).unwrap());
+        assert_eq!(state("tag-new"), (true, Some(100)));
+
+        // 本地未同步的修改优先
+        assert!(!service.apply_server_note_tag_v2(&relation("tag-dirty", true), Some("ws-1")).unwrap());
+        assert_eq!(state("tag-dirty"), (false, None));
+    }
+
+    /// 记录所有同步事件的接收方
+    #[derive(Default)]
+    struct RecordingSink {
+        events: std::sync::Mutex<Vec<(String, serde_json::Value)>>,
+    }
+
+    impl SyncEventSink for RecordingSink {
+        fn emit(&self, event: &str, payload: serde_json::Value) {
+            self.events.lock().unwrap().push((event.to_string(), payload));
+        }
+    }
+
+    #[test]
+    fn test_full_sync_emits_completed_event_with_report() {
+        let success = serde_json::json!({
+            "status": "success", "server_time": 1, "last_sync_at": 1,
+            "upserted_workspaces": [], "upserted_notes": [], "upserted_folders": [],
+            "upserted_tags": [], "upserted_snapshots": [], "upserted_note_tags": [],
+            "pushed_workspaces": 0, "pushed_notes": 0, "pushed_folders": 0, "pushed_tags": 0,
+            "pushed_snapshots": 0, "pushed_note_tags": 0, "pushed_total": 0,
+            "pulled_workspaces": 0, "pulled_notes": 0, "pulled_folders": 0, "pulled_tags": 0,
+            "pulled_snapshots": 0, "pulled_note_tags": 0, "pulled_total": 0
+        }).to_string();
+        let (server_url, _) = mock_server(vec![(200, success)]);
+
+        let sink = Arc::new(RecordingSink::default());
+        let service = memory_service().with_event_sink(sink.clone());
+        {
+            let device_id = "device-1";
+            let token = CryptoService::encrypt_token("token", device_id).unwrap();
+            service.pool.get().unwrap().execute(
+                "INSERT INTO user_auth (user_id, server_url, email, access_token_encrypted, device_id, is_current, created_at, updated_at)
+                 VALUES ('user-1', ?1, 'a@example.com', ?2, ?3, 1, 0, 0)",
+                params![server_url, token, device_id],
+            ).unwrap();
+        }
+
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        let report = runtime.block_on(service.full_sync()).unwrap();
+
+        let events = sink.events.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].0, SYNC_STARTED_EVENT);
+        assert_eq!(events[0].1, serde_json::json!({ "kind": "full", "entityId": null }));
+        assert_eq!(events[1].0, SYNC_COMPLETED_EVENT);
+        assert_eq!(events[1].1, serde_json::to_value(&report).unwrap());
+    }
+
+    #[test]
+    fn test_full_sync_emits_error_event_on_failure() {
+        // 未登录时同步失败
+        let sink = Arc::new(RecordingSink::default());
+        let service = memory_service().with_event_sink(sink.clone());
+
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        let error = runtime.block_on(service.full_sync()).unwrap_err();
+
+        let events = sink.events.lock().unwrap();
+        let names: Vec<&str> = events.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec![SYNC_STARTED_EVENT, SYNC_ERROR_EVENT]);
+        assert_eq!(events[1].1["kind"], "full");
+        assert_eq!(events[1].1["error"], error.to_string());
+    }
+
+    #[test]
+    fn test_encode_sync_body_compresses_only_large_payloads() {
+        let mut note = Note::new("标题".to_string(), "长内容 ".repeat(20_000), None);
+        note.workspace_id = Some("workspace-1".to_string());
+        let large = SyncRequest { notes: Some(vec![note.into()]), ..SyncRequest::default() };
+
+        let (payload, compressed) = encode_sync_body(&large, 32 * 1024).unwrap();
+        assert!(compressed);
+        assert_eq!(&payload[..2], &[0x1f, 0x8b]);
+        let decoded: SyncRequest = serde_json::from_reader(GzDecoder::new(payload.as_slice())).unwrap();
+        assert_eq!(decoded.notes.unwrap()[0].content, "长内容 ".repeat(20_000));
+
+        let (payload, compressed) = encode_sync_body(&SyncRequest::default(), 32 * 1024).unwrap();
+        assert!(!compressed);
+        assert_eq!(payload, serde_json::to_vec(&SyncRequest::default()).unwrap());
+
+        let (_, compressed) = encode_sync_body(&large, 0).unwrap();
+        assert!(!compressed);
+    }
+    #[test]
+    fn test_sync_cursor_is_kept_per_user_and_workspace() {
+        let service = memory_service();
+        service.pool.get().unwrap().execute_batch(
+            "INSERT INTO user_auth (user_id, server_url, email, access_token_encrypted, device_id, is_current, created_at, updated_at)
+             VALUES ('user-1', 'http://localhost', 'a@example.com', 'token', 'device-1', 1, 0, 0);"
+        ).unwrap();
+        assert_eq!(service.get_sync_cursor(None).unwrap(), None);
+
+        service.save_sync_cursor(None, Some(42)).unwrap();
+        service.save_sync_cursor(Some("ws-a"), Some(7)).unwrap();
+        // 旧服务器不返回游标时保持原值
+        service.save_sync_cursor(None, None).unwrap();
+
+        assert_eq!(service.get_sync_cursor(None).unwrap(), Some(42));
+        assert_eq!(service.get_sync_cursor(Some("ws-a")).unwrap(), Some(7));
+        assert_eq!(service.build_sync_request(Some("ws-a")).unwrap().0.cursor, Some(7));
+
+        // 切换账号后不使用其他用户的游标
+        service.pool.get().unwrap().execute_batch(
+            "UPDATE user_auth SET is_current = 0;
+             INSERT INTO user_auth (user_id, server_url, email, access_token_encrypted, device_id, is_current, created_at, updated_at)
+             VALUES ('user-2', 'http://localhost', 'b@example.com', 'token', 'device-1', 1, 0, 0);"
+        ).unwrap();
+        assert_eq!(service.get_sync_cursor(None).unwrap(), None);
+    }
+
+    #[test]
+    fn test_account_switch_cancels_apply_sync_response() {
+        let service = memory_service();
+        service.pool.get().unwrap().execute_batch(
+            "INSERT INTO user_auth (user_id, server_url, email, access_token_encrypted, device_id, is_current, created_at, updated_at)
+             VALUES ('user-1', 'http://localhost', 'a@example.com', 'token', 'device-1', 1, 0, 0),
+                    ('user-2', 'http://localhost', 'b@example.com', 'token', 'device-1', 0, 0, 0);"
+        ).unwrap();
+        let response: SyncResponse = serde_json::from_value(serde_json::json!({
+            "status": "success", "server_time": 100, "last_sync_at": 100,
+            "upserted_workspaces": [], "upserted_folders": [], "upserted_tags": [],
+            "upserted_snapshots": [], "upserted_note_tags": [],
+            "upserted_notes": [{
+                "id": "note-1", "user_id": "user-1", "title": "t", "content": "c", "folder_id": null,
+                "is_deleted": false, "deleted_at": null, "created_at": 0, "updated_at": 0, "server_ver": 1
+            }],
+            "pushed_workspaces": 0, "pushed_notes": 0, "pushed_folders": 0, "pushed_tags": 0,
+            "pushed_snapshots": 0, "pushed_note_tags": 0, "pushed_total": 0,
+            "pulled_workspaces": 0, "pulled_notes": 1, "pulled_folders": 0, "pulled_tags": 0,
+            "pulled_snapshots": 0, "pulled_note_tags": 0, "pulled_total": 1
+        })).unwrap();
+
+        // 同步开始后切换账号
+        let epoch = auth_service::account_epoch();
+        AuthService::new(service.pool.clone()).switch_account("user-2").unwrap();
+
+        let result = service.apply_sync_response_to(&response, None, epoch);
+
+        assert!(matches!(result, Err(AppError::SyncCancelled(_))));
+        let notes: i64 = service.pool.get().unwrap()
+            .query_row("SELECT COUNT(*) FROM notes", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(notes, 0);
+    }
+
+    #[test]
+    fn test_attachment_sync_round_trip() {
+        let service = memory_service();
+        service.pool.get().unwrap().execute_batch(
+            "INSERT INTO notes (id, title, content, workspace_id, created_at, updated_at, is_dirty)
+                 VALUES ('note-1', 't', 'c', 'ws-1', 0, 0, 0);
+             INSERT INTO attachments (id, note_id, workspace_id, filename, mime_type, size_bytes, data, created_at, updated_at, is_dirty)
+                 VALUES ('att-local', 'note-1', 'ws-1', 'a.png', 'image/png', 3, 'YWJj', 0, 0, 1);"
+        ).unwrap();
+
+        // 推送：脏附件带上所属笔记
+        let (request, _) = service.build_sync_request(Some("ws-1")).unwrap();
+        let attachments = request.attachments.clone().unwrap();
+        assert_eq!(attachments.len(), 1);
+        assert_eq!(attachments[0].id, "att-local");
+        assert_eq!(attachments[0].note_id, "note-1");
+        service.clear_dirty_markers(&request, 50).unwrap();
+        assert!(service.get_dirty_attachments(None).unwrap().is_empty());
+
+        // 拉取：服务器附件归属到本地笔记的工作空间，并应用删除
+        let response: SyncResponse = serde_json::from_value(serde_json::json!({
+            "status": "success", "server_time": 100, "last_sync_at": 100,
+            "upserted_workspaces": [], "upserted_notes": [], "upserted_folders": [], "upserted_tags": [],
+            "upserted_snapshots": [], "upserted_note_tags": [],
+            "upserted_attachments": [{
+                "id": "att-remote", "note_id": "note-1", "filename": "b.png", "mime_type": "image/png",
+                "size_bytes": 3, "data": "ZGVm", "created_at": 0, "updated_at": 0,
+                "is_deleted": false, "deleted_at": null, "server_ver": 1
+            }],
+            "deleted_attachment_ids": ["att-local"],
+            "pushed_workspaces": 0, "pushed_notes": 0, "pushed_folders": 0, "pushed_tags": 0,
+            "pushed_snapshots": 0, "pushed_note_tags": 0, "pushed_total": 0,
+            "pulled_workspaces": 0, "pulled_notes": 0, "pulled_folders": 0, "pulled_tags": 0,
+            "pulled_snapshots": 0, "pulled_note_tags": 0, "pulled_attachments": 1, "pulled_total": 1
+        })).unwrap();
+
+        let applied = service.apply_sync_response_to(&response, None, auth_service::account_epoch()).unwrap();
+
+        assert_eq!(applied.pulled_attachments, 1);
+        let conn = service.pool.get().unwrap();
+        let (note_id, workspace_id, data, is_dirty): (String, Option<String>, String, bool) = conn
+            .query_row(
+                "SELECT note_id, workspace_id, data, is_dirty FROM attachments WHERE id = 'att-remote'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .unwrap();
+        assert_eq!(note_id, "note-1");
+        assert_eq!(workspace_id.as_deref(), Some("ws-1"));
+        assert_eq!(data, "ZGVm");
+        assert!(!is_dirty);
+        let local_deleted: bool = conn
+            .query_row("SELECT is_deleted FROM attachments WHERE id = 'att-local'", [], |row| row.get(0))
+            .unwrap();
+        assert!(local_deleted);
+    }
+
+    #[test]
+    fn test_dirty_attachments_for_notes_only_returns_those_notes() {
+        let service = memory_service();
+        service.pool.get().unwrap().execute_batch(
+            "INSERT INTO notes (id, title, content, workspace_id, created_at, updated_at) VALUES
+                 ('note-1', 't', 'c', 'ws-1', 0, 0), ('note-2', 't', 'c', 'ws-1', 0, 0);
+             INSERT INTO attachments (id, note_id, workspace_id, filename, mime_type, size_bytes, data, created_at, updated_at, is_dirty) VALUES
+                 ('att-1', 'note-1', 'ws-1', 'a.png', 'image/png', 3, 'YWJj', 0, 0, 1),
+                 ('att-clean', 'note-1', 'ws-1', 'b.png', 'image/png', 3, 'YWJj', 0, 0, 0),
+                 ('att-2', 'note-2', 'ws-1', 'c.png', 'image/png', 3, 'YWJj', 0, 0, 1);"
+        ).unwrap();
+
+        let ids: Vec<String> = service.get_dirty_attachments_for_notes(&["note-1".to_string()]).unwrap()
+            .into_iter()
+            .map(|a| a.id)
+            .collect();
+
+        assert_eq!(ids, vec!["att-1".to_string()]);
+        assert!(service.get_dirty_attachments_for_notes(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_mark_500_notes_deleted_in_one_statement() {
+        let service = memory_service();
+        let ids: Vec<String> = (0..500).map(|i| format!("note-{:03}", i)).collect();
+        {
+            let conn = service.pool.get().unwrap();
+            for id in &ids {
+                conn.execute(
+                    "INSERT INTO notes (id, title, content, created_at, updated_at, is_dirty) VALUES (?1, 't', 'c', 0, 0, 1)",
+                    [id],
+                ).unwrap();
+            }
+            conn.execute("INSERT INTO notes (id, title, content, created_at, updated_at) VALUES ('kept', 't', 'c', 0, 0)", []).unwrap();
+        }
+
+        assert_eq!(service.mark_notes_deleted(&ids).unwrap(), 500);
+
+        // 连接池只有一个连接：changes() 是最后一条语句修改的行数，500 说明所有笔记在同一条语句中删除
+        let conn = service.pool.get().unwrap();
+        assert_eq!(conn.changes(), 500);
+        let (deleted, dirty): (i64, i64) = conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(is_dirty), 0) FROM notes WHERE is_deleted = 1 AND deleted_at IS NOT NULL",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).unwrap();
+        assert_eq!((deleted, dirty), (500, 0));
+        let kept: bool = conn.query_row("SELECT is_deleted FROM notes WHERE id = 'kept'", [], |row| row.get(0)).unwrap();
+        assert!(!kept);
+    }
+
+    #[test]
+    fn test_apply_deleted_ids_in_batches() {
+        let service = memory_service();
+        service.pool.get().unwrap().execute_batch(
+            "INSERT INTO workspaces (id, user_id, name, is_default, created_at, updated_at)
+                 VALUES ('ws-default', 'user-1', '默认', 1, 0, 0), ('ws-b', 'user-1', 'B', 0, 0, 0);
+             INSERT INTO folders (id, name, parent_id, created_at, updated_at) VALUES
+                 ('f-root', 'r', NULL, 0, 0), ('f-child', 'c', 'f-root', 0, 0), ('f-other', 'o', NULL, 0, 0);
+             INSERT INTO notes (id, title, content, created_at, updated_at) VALUES ('n-1', 't', 'c', 0, 0);
+             INSERT INTO tags (id, name, created_at, updated_at) VALUES ('t-1', 'a', 0, 0), ('t-2', 'b', 0, 0);
+             INSERT INTO note_tags (note_id, tag_id, created_at) VALUES ('n-1', 't-1', 0), ('n-1', 't-2', 0);"
+        ).unwrap();
+        let response: SyncResponse = serde_json::from_value(serde_json::json!({
+            "status": "success", "server_time": 100, "last_sync_at": 100,
+            "upserted_workspaces": [], "upserted_notes": [], "upserted_folders": [], "upserted_tags": [],
+            "upserted_snapshots": [], "upserted_note_tags": [],
+            "deleted_workspace_ids": ["ws-default", "ws-b"],
+            "deleted_folder_ids": ["f-root"],
+            "deleted_tag_ids": ["t-1", "t-2"],
+            "pushed_workspaces": 0, "pushed_notes": 0, "pushed_folders": 0, "pushed_tags": 0,
+            "pushed_snapshots": 0, "pushed_note_tags": 0, "pushed_total": 0,
+            "pulled_workspaces": 0, "pulled_notes": 0, "pulled_folders": 0, "pulled_tags": 0,
+            "pulled_snapshots": 0, "pulled_note_tags": 0, "pulled_total": 0
+        })).unwrap();
+
+        service.apply_sync_response_to(&response, None, auth_service::account_epoch()).unwrap();
+
+        let deleted = |table: &str| -> Vec<String> {
+            let conn = service.pool.get().unwrap();
+            let mut stmt = conn.prepare(&format!("SELECT id FROM {} WHERE is_deleted = 1 ORDER BY id", table)).unwrap();
+            let ids = stmt.query_map([], |row| row.get(0)).unwrap().collect::<std::result::Result<Vec<String>, _>>().unwrap();
+            ids
+        };
+        // 默认工作空间不会被删除，子文件夹随父文件夹删除
+        assert_eq!(deleted("workspaces"), ["ws-b"]);
+        assert_eq!(deleted("folders"), ["f-child", "f-root"]);
+        assert_eq!(deleted("tags"), ["t-1", "t-2"]);
+        let deleted_relations: i64 = service.pool.get().unwrap()
+            .query_row("SELECT COUNT(*) FROM note_tags WHERE is_deleted = 1", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(deleted_relations, 2);
+    }
+}
@@ -1,14 +1,22 @@
 use crate::database::repositories::TagRepository;
-use crate::models::{Tag, CreateTagRequest, UpdateTagRequest, NoteTagRequest, error::{Result, AppError}};
+use crate::services::AppSettingsService;
+use crate::models::{Tag, TagColorInconsistency, SimilarTag, SimilarTagGroup, TagMergeResult, CreateTagRequest, UpdateTagRequest, NoteTagRequest, error::{Result, AppError}};
+
+/// 相似标签检测的默认阈值
+const DEFAULT_SIMILARITY_THRESHOLD: f64 = 0.8;
+
+/// 拉丁字母标签参与模糊匹配的最小长度（更短的只做精确匹配，避免 "ui"/"ux" 被合并）
+const MIN_FUZZY_LATIN_LEN: usize = 4;
 
 #[derive(Clone)]
 pub struct TagService {
     repo: TagRepository,
+    app_settings: AppSettingsService,
 }
 
 impl TagService {
-    pub fn new(repo: TagRepository) -> Self {
-        Self { repo }
+    pub fn new(repo: TagRepository, app_settings: AppSettingsService) -> Self {
+        Self { repo, app_settings }
     }
 
     /// 获取所有标签
@@ -28,12 +36,51 @@ impl TagService {
     }
 
     /// 创建标签
-    pub fn create_tag(&self, req: CreateTagRequest) -> Result<Tag> {
+    ///
+    /// 名称去除首尾空白后保存；同一工作空间内标签名不能重复（忽略大小写），
+    /// `merge_on_conflict` 为 true 时直接返回已有标签；未指定颜色时，从调色板中自动分配当前工作空间使用最少的颜色
+    pub fn create_tag(&self, mut req: CreateTagRequest) -> Result<Tag> {
+        req.name = req.name.trim().to_string();
+        let workspace_id = self.repo.get_current_workspace_id()?;
+        if let Some(existing) = self.repo.find_by_name_in_workspace(&req.name, workspace_id.as_deref())? {
+            if req.merge_on_conflict {
+                return Ok(existing);
+            }
+            return Err(duplicate_name_error(&existing));
+        }
+
+        if req.color.as_deref().is_none_or(|c| c.trim().is_empty()) {
+            let palette = self.app_settings.get_tag_palette()?;
+            let used: Vec<String> = self.repo.find_all()?
+                .into_iter()
+                .filter_map(|tag| tag.color)
+                .collect();
+            req.color = next_palette_color(&palette, &used);
+        }
         self.repo.create(&req)
     }
 
     /// 更新标签
-    pub fn update_tag(&self, id: &str, req: UpdateTagRequest) -> Result<Tag> {
+    ///
+    /// 新名称去除首尾空白后与同一工作空间的其他标签重复时（忽略大小写）报错；
+    /// `merge_on_conflict` 为 true 时改为把当前标签合并到已有标签，并返回已有标签
+    pub fn update_tag(&self, id: &str, mut req: UpdateTagRequest) -> Result<Tag> {
+        req.name = req.name.map(|name| name.trim().to_string());
+        if let Some(name) = req.name.as_deref() {
+            let current = self.repo.find_by_id(id)?
+                .ok_or_else(|| AppError::TagNotFound(id.to_string()))?;
+            let conflict = self.repo.find_by_name_in_workspace(name, current.workspace_id.as_deref())?
+                .filter(|tag| tag.id != current.id);
+
+            if let Some(existing) = conflict {
+                if !req.merge_on_conflict {
+                    return Err(duplicate_name_error(&existing));
+                }
+                self.merge_tags(&current.id, &existing.id)?;
+                return Ok(existing);
+            }
+        }
+
         self.repo.update(id, &req)
     }
 
@@ -42,6 +89,31 @@ impl TagService {
         self.repo.delete(id)
     }
 
+    /// 合并标签：把源标签的笔记关联转移到目标标签，然后软删除源标签
+    ///
+    /// 两个标签必须不同、都未删除且属于同一工作空间；
+    /// 同时带有两个标签的笔记只保留目标标签的关联
+    pub fn merge_tags(&self, source_tag_id: &str, target_tag_id: &str) -> Result<TagMergeResult> {
+        if source_tag_id == target_tag_id {
+            return Err(AppError::InvalidOperation("不能将标签合并到自身".to_string()));
+        }
+
+        let find = |id: &str| {
+            self.repo.find_by_id(id)?
+                .ok_or_else(|| AppError::TagNotFound(id.to_string()))
+        };
+        let source = find(source_tag_id)?;
+        let target = find(target_tag_id)?;
+        if source.workspace_id != target.workspace_id {
+            return Err(AppError::InvalidOperation(format!(
+                "标签「{}」与「{}」不在同一工作空间，无法合并",
+                source.name, target.name
+            )));
+        }
+
+        self.repo.merge_into(&source.id, &target.id)
+    }
+
     /// 为笔记添加标签
     pub fn add_tag_to_note(&self, req: NoteTagRequest) -> Result<()> {
         self.repo.add_tag_to_note(&req)
@@ -79,13 +151,396 @@ impl TagService {
         self.repo.hard_delete_batch(&tag_ids)
     }
 
-    /// 清理超过 30 天的软删除标签
+    /// 清理指定工作空间中超过保留天数的软删除标签
     ///
     /// ## 返回
     ///
     /// 返回清理的标签数量
-    pub fn purge_old_deleted_tags(&self) -> Result<i64> {
-        const PURGE_AFTER_DAYS: i64 = 30;
-        self.repo.purge_old_deleted_tags(PURGE_AFTER_DAYS)
+    pub fn purge_old_deleted_tags(&self, workspace_id: Option<&str>, days: i64) -> Result<i64> {
+        self.repo.purge_old_deleted_tags(workspace_id, days)
+    }
+
+    /// 查找跨工作空间颜色不一致的同名标签
+    ///
+    /// 只检查当前用户的工作空间，按标签名称分组返回
+    pub fn find_color_inconsistencies(&self) -> Result<Vec<TagColorInconsistency>> {
+        let tags = self.repo.find_color_inconsistent_tags()?;
+
+        // 查询结果已按名称排序，相邻的同名标签归为一组
+        let mut groups: Vec<TagColorInconsistency> = Vec::new();
+        for tag in tags {
+            match groups.last_mut() {
+                Some(group) if group.name == tag.name => group.tags.push(tag),
+                _ => groups.push(TagColorInconsistency {
+                    name: tag.name.clone(),
+                    tags: vec![tag],
+                }),
+            }
+        }
+
+        Ok(groups)
+    }
+
+    /// 将当前用户所有工作空间中的同名标签设为同一颜色
+    ///
+    /// ## 返回
+    ///
+    /// 返回实际修改的标签数量（被修改的标签会标记为需要同步）
+    pub fn normalize_tag_colors(&self, name: &str, color: &str) -> Result<usize> {
+        if name.trim().is_empty() {
+            return Err(AppError::Validation("标签名称不能为空".to_string()));
+        }
+        if color.trim().is_empty() {
+            return Err(AppError::Validation("标签颜色不能为空".to_string()));
+        }
+        self.repo.set_color_by_name(name, color)
+    }
+
+    /// 查找工作空间内名称相似的标签，给出合并建议
+    ///
+    /// ## 相似度规则
+    ///
+    /// - 先归一化名称：去除空白和连接符、全角转半角、忽略大小写
+    /// - 归一化后相同的标签相似度为 1.0
+    /// - 否则按字符（而非字节）计算编辑距离，相似度 = 1 - 距离 / 较长名称的字符数
+    /// - 较短的纯拉丁字母名称只做精确匹配
+    ///
+    /// 相似关系可传递（A~B、B~C 则归为一组），每组建议保留关联笔记最多的标签
+    pub fn find_similar_tags(&self, workspace_id: &str, threshold: Option<f64>) -> Result<Vec<SimilarTagGroup>> {
+        let threshold = threshold.unwrap_or(DEFAULT_SIMILARITY_THRESHOLD);
+        if !(0.0..=1.0).contains(&threshold) {
+            return Err(AppError::Validation(format!("相似度阈值必须在 0 到 1 之间: {}", threshold)));
+        }
+
+        let tags = self.repo.find_with_note_counts(workspace_id)?;
+        let normalized: Vec<String> = tags.iter().map(|(tag, _)| normalize_tag_name(&tag.name)).collect();
+
+        // 并查集：把相似的标签合并到同一组
+        let mut parent: Vec<usize> = (0..tags.len()).collect();
+        fn find(parent: &mut [usize], i: usize) -> usize {
+            let mut root = i;
+            while parent[root] != root {
+                root = parent[root];
+            }
+            parent[i] = root;
+            root
+        }
+
+        for i in 0..tags.len() {
+            for j in (i + 1)..tags.len() {
+                if tag_similarity(&normalized[i], &normalized[j]) >= threshold {
+                    let (ri, rj) = (find(&mut parent, i), find(&mut parent, j));
+                    if ri != rj {
+                        parent[rj] = ri;
+                    }
+                }
+            }
+        }
+
+        let mut members: std::collections::BTreeMap<usize, Vec<usize>> = std::collections::BTreeMap::new();
+        for i in 0..tags.len() {
+            let root = find(&mut parent, i);
+            members.entry(root).or_default().push(i);
+        }
+
+        let mut groups = Vec::new();
+        for indices in members.into_values().filter(|m| m.len() > 1) {
+            // 关联笔记最多者优先，其次是最早创建的（查询已按 created_at 排序）
+            let target = indices
+                .iter()
+                .copied()
+                .max_by(|&a, &b| tags[a].1.cmp(&tags[b].1).then(b.cmp(&a)))
+                .unwrap_or(indices[0]);
+
+            let mut group_tags: Vec<SimilarTag> = indices
+                .iter()
+                .map(|&i| SimilarTag {
+                    tag: tags[i].0.clone(),
+                    note_count: tags[i].1,
+                    score: tag_similarity(&normalized[target], &normalized[i]),
+                })
+                .collect();
+            group_tags.sort_by(|a, b| b.score.total_cmp(&a.score).then(b.note_count.cmp(&a.note_count)));
+
+            groups.push(SimilarTagGroup {
+                suggested_target_id: tags[target].0.id.clone(),
+                tags: group_tags,
+            });
+        }
+
+        log::info!(
+            "[TagService] 相似标签检测: workspace_id={}, threshold={}, groups={}",
+            workspace_id,
+            threshold,
+            groups.len()
+        );
+        Ok(groups)
+    }
+}
+
+/// 工作空间内标签重名的错误
+fn duplicate_name_error(existing: &Tag) -> AppError {
+    AppError::InvalidOperation(format!("当前工作空间已存在同名标签「{}」", existing.name))
+}
+
+/// 从调色板中选出下一个颜色
+///
+/// 优先选择尚未使用的颜色；全部用过后选择使用次数最少的（按调色板顺序），实现循环分配。
+/// 颜色比较忽略大小写，调色板为空时返回 None。
+fn next_palette_color(palette: &[String], used: &[String]) -> Option<String> {
+    palette
+        .iter()
+        .enumerate()
+        .min_by_key(|(index, color)| {
+            let count = used.iter().filter(|u| u.eq_ignore_ascii_case(color)).count();
+            (count, *index)
+        })
+        .map(|(_, color)| color.clone())
+}
+
+/// 归一化标签名称用于比较
+///
+/// 全角字符转半角、去除空白和常见连接符、统一小写
+fn normalize_tag_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+            _ => c,
+        })
+        .filter(|c| !c.is_whitespace() && !matches!(c, '-' | '_' | '.' | '·'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// 是否为 CJK 字符（汉字、假名、谚文）
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{4E00}'..='\u{9FFF}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{3040}'..='\u{30FF}'
+        | '\u{AC00}'..='\u{D7AF}')
+}
+
+/// 计算两个已归一化名称的相似度（0.0 ~ 1.0）
+///
+/// 按字符计算编辑距离，一个汉字和一个字母同样计为一个字符。
+/// 汉字单字即有完整含义，所以 "工作"/"工具" 这类短词只有 0.5，不会被误判为相似；
+/// 不含 CJK 字符的短名称只在完全相同时视为相似。
+fn tag_similarity(a: &str, b: &str) -> f64 {
+    if a == b {
+        return 1.0;
+    }
+
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let max_len = a.len().max(b.len());
+    if max_len == 0 {
+        return 0.0;
+    }
+
+    let has_cjk = a.iter().chain(b.iter()).any(|&c| is_cjk(c));
+    if !has_cjk && a.len().min(b.len()) < MIN_FUZZY_LATIN_LEN {
+        return 0.0;
+    }
+
+    1.0 - levenshtein(&a, &b) as f64 / max_len as f64
+}
+
+/// 字符级编辑距离
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+
+    for (i, ca) in a.iter().enumerate() {
+        curr[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == cb { 0 } else { 1 };
+            curr[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 内存数据库中预置笔记 n1、n2、n3
+    fn memory_service() -> TagService {
+        let pool = crate::database::memory_pool();
+        pool.get().unwrap().execute_batch(
+            "INSERT INTO notes (id, title, content, created_at, updated_at) VALUES
+                ('n1', 'n1', '', 0, 0), ('n2', 'n2', '', 0, 0), ('n3', 'n3', '', 0, 0);",
+        ).unwrap();
+
+        TagService::new(TagRepository::new(pool.clone()), AppSettingsService::new(pool))
+    }
+
+    fn link(service: &TagService, note_id: &str, tag_id: &str) {
+        service.repo.add_tag_to_note_in_workspace(
+            &NoteTagRequest { note_id: note_id.to_string(), tag_id: tag_id.to_string() },
+            Some("ws-1"),
+        ).unwrap();
+    }
+
+    fn tag_ids(service: &TagService, note_id: &str) -> Vec<String> {
+        let mut ids: Vec<String> = service.repo.find_note_tag_relations(note_id).unwrap()
+            .into_iter()
+            .map(|r| r.tag_id)
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_merge_tags_consolidates_relations() {
+        let service = memory_service();
+        let (source, _) = service.repo.find_or_create_by_name("todo", "ws-1").unwrap();
+        let (target, _) = service.repo.find_or_create_by_name("TODO", "ws-1").unwrap();
+        link(&service, "n1", &source.id);
+        link(&service, "n2", &source.id);
+        link(&service, "n2", &target.id);
+        link(&service, "n3", &target.id);
+
+        let result = service.merge_tags(&source.id, &target.id).unwrap();
+
+        assert_eq!(result.moved_relations, 1);
+        assert_eq!(result.already_tagged, 1);
+        for note_id in ["n1", "n2", "n3"] {
+            assert_eq!(tag_ids(&service, note_id), vec![target.id.clone()]);
+        }
+    }
+
+    #[test]
+    fn test_merge_tags_soft_deletes_source() {
+        let service = memory_service();
+        let (source, _) = service.repo.find_or_create_by_name("todo", "ws-1").unwrap();
+        let (target, _) = service.repo.find_or_create_by_name("TODO", "ws-1").unwrap();
+        link(&service, "n1", &source.id);
+
+        service.merge_tags(&source.id, &target.id).unwrap();
+
+        assert!(service.repo.find_by_id(&source.id).unwrap().is_none());
+        assert!(service.repo.find_by_id(&target.id).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_merge_tags_leaves_no_duplicate_relations() {
+        let service = memory_service();
+        let (source, _) = service.repo.find_or_create_by_name("todo", "ws-1").unwrap();
+        let (target, _) = service.repo.find_or_create_by_name("TODO", "ws-1").unwrap();
+        link(&service, "n1", &source.id);
+        link(&service, "n1", &target.id);
+
+        service.merge_tags(&source.id, &target.id).unwrap();
+
+        assert_eq!(tag_ids(&service, "n1"), vec![target.id.clone()]);
+    }
+
+    #[test]
+    fn test_merge_tags_rejects_same_tag_and_cross_workspace() {
+        let service = memory_service();
+        let (tag, _) = service.repo.find_or_create_by_name("todo", "ws-1").unwrap();
+        let (other, _) = service.repo.find_or_create_by_name("todo", "ws-2").unwrap();
+
+        assert!(matches!(service.merge_tags(&tag.id, &tag.id), Err(AppError::InvalidOperation(_))));
+        assert!(matches!(service.merge_tags(&tag.id, &other.id), Err(AppError::InvalidOperation(_))));
+    }
+
+    fn create_request(name: &str, merge_on_conflict: bool) -> CreateTagRequest {
+        CreateTagRequest { name: name.to_string(), color: None, merge_on_conflict }
+    }
+
+    fn rename_request(name: &str, merge_on_conflict: bool) -> UpdateTagRequest {
+        UpdateTagRequest { name: Some(name.to_string()), color: None, merge_on_conflict }
+    }
+
+    #[test]
+    fn test_create_tag_rejects_duplicate_name_ignoring_case() {
+        let service = memory_service();
+        let existing = service.create_tag(create_request("todo", false)).unwrap();
+
+        let result = service.create_tag(create_request("TODO", false));
+        assert!(matches!(result, Err(AppError::InvalidOperation(_))));
+
+        let result = service.create_tag(create_request("  todo ", false));
+        assert!(matches!(result, Err(AppError::InvalidOperation(_))));
+
+        let merged = service.create_tag(create_request("Todo", true)).unwrap();
+        assert_eq!(merged.id, existing.id);
+
+        let trimmed = service.create_tag(create_request(" work  ", false)).unwrap();
+        assert_eq!(trimmed.name, "work");
+    }
+
+    #[test]
+    fn test_rename_tag_rejects_duplicate_name() {
+        let service = memory_service();
+        service.create_tag(create_request("todo", false)).unwrap();
+        let work = service.create_tag(create_request("work", false)).unwrap();
+
+        let result = service.update_tag(&work.id, rename_request("TODO", false));
+        assert!(matches!(result, Err(AppError::InvalidOperation(_))));
+        assert_eq!(service.get_tag(&work.id).unwrap().name, "work");
+
+        // 首尾空白不影响重名判断
+        let result = service.update_tag(&work.id, rename_request(" todo ", false));
+        assert!(matches!(result, Err(AppError::InvalidOperation(_))));
+
+        // 只改大小写不算与自身冲突
+        let renamed = service.update_tag(&work.id, rename_request("Work", false)).unwrap();
+        assert_eq!(renamed.name, "Work");
+    }
+
+    #[test]
+    fn test_rename_tag_merges_into_existing_on_conflict() {
+        let service = memory_service();
+        let todo = service.create_tag(create_request("todo", false)).unwrap();
+        let work = service.create_tag(create_request("work", false)).unwrap();
+        link(&service, "n1", &work.id);
+
+        let result = service.update_tag(&work.id, rename_request("TODO", true)).unwrap();
+
+        assert_eq!(result.id, todo.id);
+        assert!(service.repo.find_by_id(&work.id).unwrap().is_none());
+        assert_eq!(tag_ids(&service, "n1"), vec![todo.id.clone()]);
+    }
+
+    #[test]
+    fn test_normalize_tag_name() {
+        assert_eq!(normalize_tag_name("工作 "), "工作");
+        assert_eq!(normalize_tag_name(" Work"), "work");
+        assert_eq!(normalize_tag_name("ＷＯＲＫ"), "work");
+        assert_eq!(normalize_tag_name("to-do"), "todo");
+        assert_eq!(normalize_tag_name("Rust 学习"), "rust学习");
+    }
+
+    #[test]
+    fn test_next_palette_color() {
+        let palette: Vec<String> = ["#111111", "#222222", "#333333"].iter().map(|c| c.to_string()).collect();
+        let used = |colors: &[&str]| colors.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(next_palette_color(&palette, &[]), Some("#111111".to_string()));
+        assert_eq!(next_palette_color(&palette, &used(&["#111111", "#333333"])), Some("#222222".to_string()));
+        assert_eq!(next_palette_color(&palette, &used(&["#111111", "#222222", "#333333"])), Some("#111111".to_string()));
+        assert_eq!(next_palette_color(&palette, &used(&["#111111", "#222222", "#333333", "#111111"])), Some("#222222".to_string()));
+        assert_eq!(
+            next_palette_color(&["#aaaaaa".to_string(), "#bbbbbb".to_string()], &used(&["#AAAAAA"])),
+            Some("#bbbbbb".to_string())
+        );
+        assert_eq!(next_palette_color(&[], &[]), None);
+    }
+
+    #[test]
+    fn test_tag_similarity() {
+        assert_eq!(tag_similarity("工作", "工作"), 1.0);
+        assert_eq!(tag_similarity("工作", "工具"), 0.5);
+        assert!(tag_similarity("javascript", "javascrpt") >= 0.8);
+        assert!(tag_similarity("reading", "readings") >= 0.8);
+        assert_eq!(tag_similarity("ui", "ux"), 0.0);
+        assert!(tag_similarity("work", "工作") < 0.5);
     }
 }
@@ -1,8 +1,44 @@
 use crate::database::repositories::WorkspaceRepository;
-use crate::models::{Workspace, CreateWorkspaceRequest, UpdateWorkspaceRequest};
+use crate::models::{Workspace, WorkspaceCheckpoint, CreateWorkspaceRequest, UpdateWorkspaceRequest, StorageCounts, StorageStats};
 use crate::models::error::{Result, AppError};
+use crate::models::app_settings::is_valid_hex_color;
+use crate::models::workspace::{MAX_WORKSPACE_DESCRIPTION_LENGTH, MAX_WORKSPACE_ICON_LENGTH, MAX_WORKSPACE_NAME_LENGTH};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
 use serde::Serialize;
 use r2d2_sqlite::rusqlite::params;
+use std::io::Write;
+
+/// 流式导出时每页读取的笔记数量
+const EXPORT_PAGE_SIZE: usize = 200;
+
+/// 每个工作空间最多保留的检查点数量（超出后删除最旧的）
+const MAX_CHECKPOINTS_PER_WORKSPACE: usize = 10;
+
+/// 每个工作空间检查点的总大小上限（压缩后，字节）
+const MAX_CHECKPOINT_BYTES_PER_WORKSPACE: i64 = 100 * 1024 * 1024;
+
+/// 检查点恢复结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointRestoreResult {
+    pub checkpoint_id: String,
+    pub backup_checkpoint_id: String,  // 恢复前自动创建的检查点
+    pub notes: usize,
+    pub folders: usize,
+    pub tags: usize,
+}
+
+/// 工作空间复制结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceDuplicateResult {
+    pub workspace: Workspace,  // 新建的工作空间
+    pub folders: usize,
+    pub tags: usize,
+    pub notes: usize,
+}
 
 /// 迁移结果统计
 #[derive(Debug, Clone, Serialize)]
@@ -13,6 +49,43 @@ pub struct MigrateResult {
     pub snapshots: usize,
 }
 
+/// 待迁移的孤立数据条目
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanItem {
+    pub id: String,
+    pub name: String,  // 笔记标题 / 文件夹名称 / 标签名称
+}
+
+/// 孤立数据迁移预览（workspace_id 为 NULL 的数据）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanMigrationPreview {
+    pub notes: Vec<OrphanItem>,
+    pub folders: Vec<OrphanItem>,
+    pub tags: Vec<OrphanItem>,
+    pub snapshots: usize,  // 快照数量
+    pub note_tags: usize,  // 笔记-标签关联数量
+}
+
+impl OrphanMigrationPreview {
+    /// 是否没有任何孤立数据
+    pub fn is_empty(&self) -> bool {
+        self.notes.is_empty()
+            && self.folders.is_empty()
+            && self.tags.is_empty()
+            && self.snapshots == 0
+            && self.note_tags == 0
+    }
+}
+
+/// 工作空间导出结果统计
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleExportResult {
+    pub notes: usize,  // 导出的笔记数量
+}
+
 
 
 /// 工作空间业务逻辑层
@@ -42,8 +115,13 @@ impl WorkspaceService {
         // 获取最大排序值
         let sort_order = self.repo.get_max_sort_order(&user_id)? + 1;
 
+        let name = normalize_workspace_name(&req.name)?;
+        let description = normalize_workspace_description(req.description)?;
+        let icon = normalize_workspace_icon(req.icon)?;
+        let color = normalize_workspace_color(req.color)?;
+
         // 创建工作空间
-        let mut workspace = Workspace::new(user_id, req.name, req.description, req.icon, req.color);
+        let mut workspace = Workspace::new(user_id, name, description, icon, color);
         workspace.sort_order = sort_order;
 
         self.repo.create(&workspace)
@@ -61,6 +139,19 @@ impl WorkspaceService {
         self.repo.find_by_user_id(&user_id)
     }
 
+    /// 获取当前用户的存储统计（各工作空间及合计）
+    pub fn get_storage_stats(&self) -> Result<StorageStats> {
+        let user_id = self.get_current_user_id()?;
+        let workspaces = self.repo.storage_stats(&user_id)?;
+
+        let mut total = StorageCounts::default();
+        for workspace in &workspaces {
+            total.add(&workspace.counts);
+        }
+
+        Ok(StorageStats { workspaces, total })
+    }
+
     /// 获取当前工作空间
     pub fn get_current_workspace(&self) -> Result<Workspace> {
         let user_id = self.get_current_user_id()?;
@@ -90,18 +181,18 @@ impl WorkspaceService {
     pub fn update_workspace(&self, req: UpdateWorkspaceRequest) -> Result<Workspace> {
         let mut workspace = self.get_workspace(&req.id)?;
 
-        // 只允许更新以下字段
+        // 只允许更新以下字段（描述、图标、颜色传空字符串时清除）
         if let Some(name) = req.name {
-            workspace.name = name;
+            workspace.name = normalize_workspace_name(&name)?;
         }
-        if let Some(description) = req.description {
-            workspace.description = Some(description);
+        if req.description.is_some() {
+            workspace.description = normalize_workspace_description(req.description)?;
         }
-        if let Some(icon) = req.icon {
-            workspace.icon = Some(icon);
+        if req.icon.is_some() {
+            workspace.icon = normalize_workspace_icon(req.icon)?;
         }
-        if let Some(color) = req.color {
-            workspace.color = Some(color);
+        if req.color.is_some() {
+            workspace.color = normalize_workspace_color(req.color)?;
         }
 
         workspace.updated_at = chrono::Utc::now().timestamp();
@@ -112,6 +203,8 @@ impl WorkspaceService {
     }
 
     /// 删除工作空间（软删除）
+    ///
+    /// 删除当前工作空间时自动切换到默认工作空间（没有默认空间时切换到排在最前面的工作空间）
     pub fn delete_workspace(&self, id: &str) -> Result<()> {
         // 检查是否存在
         let workspace = self.get_workspace(id)?;
@@ -121,6 +214,11 @@ impl WorkspaceService {
             return Err(AppError::InvalidOperation("不允许删除默认工作空间".to_string()));
         }
 
+        // 至少保留一个工作空间，否则数据没有归属，也无法选择当前工作空间
+        if !workspace.is_deleted && self.repo.count_active_by_user_id(&workspace.user_id)? <= 1 {
+            return Err(AppError::InvalidOperation("不能删除最后一个工作空间".to_string()));
+        }
+
         self.repo.delete(id)
     }
 
@@ -150,4 +248,718 @@ impl WorkspaceService {
         // 调用 Repository 层的迁移方法，直接返回详细的统计信息
         self.repo.migrate_orphan_data_to_workspace(workspace_id)
     }
+
+    /// 预览孤立数据迁移
+    ///
+    /// 列出所有 workspace_id = NULL 的笔记、文件夹、标签以及快照和标签关联数量，不修改任何数据
+    pub fn preview_orphan_migration(&self) -> Result<OrphanMigrationPreview> {
+        self.repo.find_orphan_data()
+    }
+
+    /// 迁移孤立数据到指定工作空间（支持试运行）
+    ///
+    /// 与 `migrate_orphan_data_to_workspace` 不同，会先校验目标工作空间属于当前账号；
+    /// `dry_run` 为 true 时只返回将要迁移的数量，不修改数据
+    pub fn migrate_orphan_data(&self, workspace_id: &str, dry_run: bool) -> Result<MigrateResult> {
+        self.ensure_owned_workspace(workspace_id)?;
+
+        if dry_run {
+            let preview = self.repo.find_orphan_data()?;
+            log::info!(
+                "[WorkspaceService] 孤立数据迁移试运行: workspace_id={}, notes={}, folders={}, tags={}, snapshots={}",
+                workspace_id,
+                preview.notes.len(),
+                preview.folders.len(),
+                preview.tags.len(),
+                preview.snapshots
+            );
+            return Ok(MigrateResult {
+                notes: preview.notes.len(),
+                folders: preview.folders.len(),
+                tags: preview.tags.len(),
+                snapshots: preview.snapshots,
+            });
+        }
+
+        self.migrate_orphan_data_to_workspace(workspace_id)
+    }
+
+    /// 创建工作空间检查点
+    ///
+    /// 将工作空间的全部笔记、文件夹、标签和标签关联以 gzip 压缩的 JSON 保存到本地，
+    /// 创建后按数量和总大小上限清理最旧的检查点
+    pub fn create_checkpoint(&self, workspace_id: &str, name: &str) -> Result<WorkspaceCheckpoint> {
+        self.ensure_owned_workspace(workspace_id)?;
+
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(AppError::InvalidInput("检查点名称不能为空".to_string()));
+        }
+
+        let tables = self.repo.dump_checkpoint_tables(workspace_id)?;
+        let active_count = |table: &str| {
+            tables.get(table)
+                .and_then(|rows| rows.as_array())
+                .map(|rows| rows.iter().filter(|row| !is_deleted_row(row)).count())
+                .unwrap_or(0)
+        };
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        serde_json::to_writer(&mut encoder, &tables)
+            .map_err(|e| AppError::Internal(format!("序列化检查点失败: {}", e)))?;
+        let data = encoder.finish()
+            .map_err(|e| AppError::Internal(format!("压缩检查点失败: {}", e)))?;
+
+        let size_bytes = data.len() as i64;
+        if size_bytes > MAX_CHECKPOINT_BYTES_PER_WORKSPACE {
+            return Err(AppError::InvalidOperation(format!(
+                "检查点大小 {} 字节超过上限 {} 字节",
+                size_bytes, MAX_CHECKPOINT_BYTES_PER_WORKSPACE
+            )));
+        }
+
+        let checkpoint = WorkspaceCheckpoint {
+            id: uuid::Uuid::new_v4().to_string(),
+            workspace_id: workspace_id.to_string(),
+            name: name.to_string(),
+            note_count: active_count("notes"),
+            folder_count: active_count("folders"),
+            tag_count: active_count("tags"),
+            size_bytes,
+            created_at: chrono::Utc::now().timestamp(),
+        };
+        self.repo.insert_checkpoint(&checkpoint, &data)?;
+
+        let pruned = self.repo.prune_checkpoints(
+            workspace_id,
+            MAX_CHECKPOINTS_PER_WORKSPACE,
+            MAX_CHECKPOINT_BYTES_PER_WORKSPACE,
+        )?;
+
+        log::info!(
+            "[WorkspaceService] 已创建检查点: workspace_id={}, id={}, notes={}, size={}, pruned={}",
+            workspace_id,
+            checkpoint.id,
+            checkpoint.note_count,
+            size_bytes,
+            pruned
+        );
+
+        Ok(checkpoint)
+    }
+
+    /// 列出工作空间的检查点（最新的在前）
+    pub fn list_checkpoints(&self, workspace_id: &str) -> Result<Vec<WorkspaceCheckpoint>> {
+        self.ensure_owned_workspace(workspace_id)?;
+        self.repo.find_checkpoints(workspace_id)
+    }
+
+    /// 将工作空间恢复到检查点
+    ///
+    /// 恢复前会先为当前状态创建一个检查点，误操作时可以再恢复回来。
+    /// 恢复后的变化会标记为需要同步，下次同步时推送到服务器
+    pub fn restore_checkpoint(&self, checkpoint_id: &str) -> Result<CheckpointRestoreResult> {
+        let (checkpoint, data) = self.repo.find_checkpoint(checkpoint_id)?
+            .ok_or_else(|| AppError::NotFound(format!("检查点 {} 未找到", checkpoint_id)))?;
+        self.ensure_owned_workspace(&checkpoint.workspace_id)?;
+
+        // 先解压再创建备份：备份触发的清理可能会删除正在恢复的检查点
+        let tables: serde_json::Map<String, serde_json::Value> = serde_json::from_reader(GzDecoder::new(data.as_slice()))
+            .map_err(|e| AppError::Internal(format!("解析检查点失败: {}", e)))?;
+
+        let backup = self.create_checkpoint(
+            &checkpoint.workspace_id,
+            &format!("恢复「{}」前自动备份", checkpoint.name),
+        )?;
+
+        self.repo.restore_checkpoint_tables(&checkpoint.workspace_id, &tables)?;
+
+        log::info!(
+            "[WorkspaceService] 已恢复检查点: workspace_id={}, id={}, backup_id={}",
+            checkpoint.workspace_id,
+            checkpoint.id,
+            backup.id
+        );
+
+        Ok(CheckpointRestoreResult {
+            checkpoint_id: checkpoint.id,
+            backup_checkpoint_id: backup.id,
+            notes: checkpoint.note_count,
+            folders: checkpoint.folder_count,
+            tags: checkpoint.tag_count,
+        })
+    }
+
+    /// 复制工作空间（用作模板）
+    ///
+    /// 新工作空间沿用原工作空间的描述、图标和颜色，复制全部文件夹（保持层级）和标签；
+    /// `include_notes` 为 true 时同时复制笔记及其标签关联。复制出的数据都使用新 id，
+    /// 并标记为需要同步
+    pub fn duplicate_workspace(&self, workspace_id: &str, new_name: &str, include_notes: bool) -> Result<WorkspaceDuplicateResult> {
+        let source = self.ensure_owned_workspace(workspace_id)?;
+
+        let workspace = self.create_workspace(CreateWorkspaceRequest {
+            name: new_name.to_string(),
+            description: source.description.clone(),
+            icon: source.icon.clone(),
+            color: source.color.clone(),
+        })?;
+
+        let (folders, tags, notes) = match self.repo.duplicate_workspace_contents(&source.id, &workspace.id, include_notes) {
+            Ok(counts) => counts,
+            Err(e) => {
+                // 复制在事务中失败时没有写入任何数据，删除刚创建的空工作空间
+                if let Err(cleanup_err) = self.repo.delete(&workspace.id) {
+                    log::warn!("[WorkspaceService] 清理复制失败的工作空间失败: id={}, error={}", workspace.id, cleanup_err);
+                }
+                return Err(e);
+            }
+        };
+
+        log::info!(
+            "[WorkspaceService] 已复制工作空间: source={}, id={}, folders={}, tags={}, notes={}",
+            source.id,
+            workspace.id,
+            folders,
+            tags,
+            notes
+        );
+
+        Ok(WorkspaceDuplicateResult { workspace, folders, tags, notes })
+    }
+
+    /// 校验工作空间存在且属于当前账号
+    fn ensure_owned_workspace(&self, workspace_id: &str) -> Result<Workspace> {
+        let workspace = self.get_workspace(workspace_id)?;
+        let user_id = self.get_current_user_id()?;
+        if workspace.user_id != user_id || workspace.is_deleted {
+            return Err(AppError::InvalidOperation(format!("工作空间 {} 不属于当前账号", workspace_id)));
+        }
+        Ok(workspace)
+    }
+
+    /// 流式导出工作空间（JSON）
+    ///
+    /// 按页读取笔记并逐条写入 `writer`，内存中最多只保留一页笔记，
+    /// 适合包含数万篇笔记的工作空间
+    ///
+    /// ## 输出格式
+    ///
+    /// ```json
+    /// { "workspace": { ... }, "exportedAt": 1710000000, "notes": [ { ... }, ... ] }
+    /// ```
+    pub fn export_bundle_streaming<W: Write>(&self, workspace_id: &str, mut writer: W) -> Result<BundleExportResult> {
+        let workspace = self.get_workspace(workspace_id)?;
+        let io_err = |e: std::io::Error| AppError::Internal(format!("写入导出文件失败: {}", e));
+        let json_err = |e: serde_json::Error| AppError::Internal(format!("序列化导出数据失败: {}", e));
+
+        writer.write_all(b"{\"workspace\":").map_err(io_err)?;
+        serde_json::to_writer(&mut writer, &workspace).map_err(json_err)?;
+        write!(writer, ",\"exportedAt\":{},\"notes\":[", chrono::Utc::now().timestamp()).map_err(io_err)?;
+
+        let mut count = 0;
+        let mut after_id: Option<String> = None;
+
+        loop {
+            let page = self.repo.find_notes_page(workspace_id, after_id.as_deref(), EXPORT_PAGE_SIZE)?;
+            if page.is_empty() {
+                break;
+            }
+
+            for note in &page {
+                if count > 0 {
+                    writer.write_all(b",").map_err(io_err)?;
+                }
+                serde_json::to_writer(&mut writer, note).map_err(json_err)?;
+                count += 1;
+            }
+
+            if page.len() < EXPORT_PAGE_SIZE {
+                break;
+            }
+            after_id = page.last().map(|n| n.id.clone());
+        }
+
+        writer.write_all(b"]}").map_err(io_err)?;
+        writer.flush().map_err(io_err)?;
+
+        log::info!("[WorkspaceService] 流式导出完成: workspace_id={}, notes={}", workspace_id, count);
+
+        Ok(BundleExportResult { notes: count })
+    }
+}
+
+/// 检查点中的行是否已软删除（is_deleted 可能以整数或布尔值存储）
+fn is_deleted_row(row: &serde_json::Value) -> bool {
+    match row.get("is_deleted") {
+        Some(serde_json::Value::Bool(deleted)) => *deleted,
+        Some(serde_json::Value::Number(n)) => n.as_i64().unwrap_or(0) != 0,
+        _ => false,
+    }
+}
+
+/// 校验工作空间名称（去除首尾空白后不能为空，且不超过长度上限）
+fn normalize_workspace_name(name: &str) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::InvalidOperation("工作空间名称不能为空".to_string()));
+    }
+    if name.chars().count() > MAX_WORKSPACE_NAME_LENGTH {
+        return Err(AppError::InvalidOperation(format!(
+            "工作空间名称不能超过 {} 个字符",
+            MAX_WORKSPACE_NAME_LENGTH
+        )));
+    }
+    Ok(name.to_string())
+}
+
+/// 去除首尾空白，空字符串视为未设置
+fn non_empty(value: Option<String>) -> Option<String> {
+    value
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// 校验工作空间描述长度
+fn normalize_workspace_description(description: Option<String>) -> Result<Option<String>> {
+    let description = non_empty(description);
+    if description.as_ref().is_some_and(|d| d.chars().count() > MAX_WORKSPACE_DESCRIPTION_LENGTH) {
+        return Err(AppError::InvalidOperation(format!(
+            "工作空间描述不能超过 {} 个字符",
+            MAX_WORKSPACE_DESCRIPTION_LENGTH
+        )));
+    }
+    Ok(description)
+}
+
+/// 校验工作空间图标（不含空白和控制字符，且不超过长度上限）
+fn normalize_workspace_icon(icon: Option<String>) -> Result<Option<String>> {
+    let icon = non_empty(icon);
+    if let Some(icon) = &icon {
+        if icon.chars().count() > MAX_WORKSPACE_ICON_LENGTH
+            || icon.chars().any(|c| c.is_whitespace() || c.is_control())
+        {
+            return Err(AppError::InvalidOperation(format!(
+                "工作空间图标无效（不能包含空白字符，且不超过 {} 个字符）",
+                MAX_WORKSPACE_ICON_LENGTH
+            )));
+        }
+    }
+    Ok(icon)
+}
+
+/// 校验工作空间颜色（`#RGB` 或 `#RRGGBB`）
+fn normalize_workspace_color(color: Option<String>) -> Result<Option<String>> {
+    let color = non_empty(color);
+    if color.as_deref().is_some_and(|c| !is_valid_hex_color(c)) {
+        return Err(AppError::InvalidOperation(
+            "工作空间颜色格式无效（应为 #RGB 或 #RRGGBB）".to_string(),
+        ));
+    }
+    Ok(color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory_pool(seed_sql: &str) -> crate::database::DbPool {
+        let pool = crate::database::memory_pool();
+        pool.get().unwrap().execute_batch(seed_sql).unwrap();
+        pool
+    }
+
+    fn memory_service(seed_sql: &str) -> WorkspaceService {
+        WorkspaceService::new(WorkspaceRepository::new(memory_pool(seed_sql)))
+    }
+
+    #[test]
+    fn test_storage_stats_counts_per_workspace_and_total() {
+        let service = memory_service(
+            "INSERT INTO user_auth (user_id, server_url, email, access_token_encrypted, device_id, is_current, created_at, updated_at)
+             VALUES ('user-1', 'http://localhost', 'a@example.com', 'token', 'device-1', 1, 0, 0);
+             INSERT INTO workspaces (id, user_id, name, sort_order, created_at, updated_at)
+             VALUES ('ws-a', 'user-1', 'A', 1, 0, 0), ('ws-b', 'user-1', 'B', 2, 0, 0);
+             INSERT INTO workspaces (id, user_id, name, created_at, updated_at) VALUES ('ws-other', 'user-2', 'C', 0, 0);
+             INSERT INTO notes (id, title, content, workspace_id, is_deleted, created_at, updated_at) VALUES
+                 ('n1', 't', 'abcd', 'ws-a', 0, 0, 0),
+                 ('n2', 't', '笔记', 'ws-a', 0, 0, 0),
+                 ('n3', 't', 'xy', 'ws-a', 1, 0, 0),
+                 ('n4', 't', 'z', 'ws-b', 0, 0, 0),
+                 ('n5', 't', 'other', 'ws-other', 0, 0, 0);
+             INSERT INTO folders (id, name, workspace_id, is_deleted, created_at, updated_at) VALUES
+                 ('f1', 'f', 'ws-a', 0, 0, 0), ('f2', 'f', 'ws-b', 1, 0, 0);
+             INSERT INTO tags (id, name, workspace_id, is_deleted, created_at, updated_at) VALUES
+                 ('t1', 'a', 'ws-a', 0, 0, 0), ('t2', 'b', 'ws-a', 1, 0, 0), ('t3', 'c', 'ws-b', 0, 0, 0);
+             INSERT INTO note_snapshots (id, note_id, title, content, workspace_id, created_at) VALUES
+                 ('s1', 'n1', 't', 'abc', 'ws-a', 0);"
+        );
+
+        let stats = service.get_storage_stats().unwrap();
+
+        let ids: Vec<&str> = stats.workspaces.iter().map(|w| w.workspace_id.as_str()).collect();
+        assert_eq!(ids, ["ws-a", "ws-b"]);
+        assert_eq!(stats.workspaces[0].counts, StorageCounts {
+            notes: 2,
+            folders: 1,
+            tags: 1,
+            snapshots: 1,
+            trashed_notes: 1,
+            trashed_folders: 0,
+            trashed_tags: 1,
+            // 笔记 4 + 6（UTF-8）+ 2（回收站），快照 3
+            approx_bytes: 15,
+        });
+        assert_eq!(stats.workspaces[1].counts.notes, 1);
+        assert_eq!(stats.workspaces[1].counts.folders, 0);
+        assert_eq!(stats.workspaces[1].counts.trashed_folders, 1);
+
+        assert_eq!(stats.total, StorageCounts {
+            notes: 3,
+            folders: 1,
+            tags: 2,
+            snapshots: 1,
+            trashed_notes: 1,
+            trashed_folders: 1,
+            trashed_tags: 1,
+            approx_bytes: 16,
+        });
+    }
+
+    #[test]
+    fn test_storage_stats_empty_workspace() {
+        let service = memory_service(
+            "INSERT INTO user_auth (user_id, server_url, email, access_token_encrypted, device_id, is_current, created_at, updated_at)
+             VALUES ('user-1', 'http://localhost', 'a@example.com', 'token', 'device-1', 1, 0, 0);
+             INSERT INTO workspaces (id, user_id, name, created_at, updated_at) VALUES ('ws-a', 'user-1', 'A', 0, 0);"
+        );
+
+        let stats = service.get_storage_stats().unwrap();
+
+        assert_eq!(stats.workspaces.len(), 1);
+        assert_eq!(stats.total, StorageCounts::default());
+    }
+
+    const CURRENT_USER_SQL: &str =
+        "INSERT INTO user_auth (user_id, server_url, email, access_token_encrypted, device_id, is_current, created_at, updated_at)
+         VALUES ('user-1', 'http://localhost', 'a@example.com', 'token', 'device-1', 1, 0, 0);";
+
+    fn create_request(name: &str) -> CreateWorkspaceRequest {
+        CreateWorkspaceRequest { name: name.to_string(), description: None, icon: None, color: None }
+    }
+
+    fn update_request(id: &str) -> UpdateWorkspaceRequest {
+        UpdateWorkspaceRequest { id: id.to_string(), name: None, description: None, icon: None, color: None }
+    }
+
+    fn invalid_operation_message(err: AppError) -> String {
+        match err {
+            AppError::InvalidOperation(message) => message,
+            other => panic!("expected InvalidOperation, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_create_workspace_rejects_empty_name() {
+        let service = memory_service(CURRENT_USER_SQL);
+
+        let message = invalid_operation_message(service.create_workspace(create_request("   ")).err().unwrap());
+
+        assert!(message.contains("名称不能为空"), "{}", message);
+        assert!(service.list_workspaces().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_create_workspace_trims_and_validates_fields() {
+        let service = memory_service(CURRENT_USER_SQL);
+
+        let workspace = service.create_workspace(CreateWorkspaceRequest {
+            name: "  工作  ".to_string(),
+            description: Some(" ".to_string()),
+            icon: Some("📁".to_string()),
+            color: Some("#3b82f6".to_string()),
+        }).unwrap();
+        assert_eq!(workspace.name, "工作");
+        assert_eq!(workspace.description, None);
+
+        let message = invalid_operation_message(service.create_workspace(CreateWorkspaceRequest {
+            color: Some("blue".to_string()),
+            ..create_request("A")
+        }).err().unwrap());
+        assert!(message.contains("颜色"), "{}", message);
+
+        let message = invalid_operation_message(service.create_workspace(CreateWorkspaceRequest {
+            icon: Some("my icon".to_string()),
+            ..create_request("A")
+        }).err().unwrap());
+        assert!(message.contains("图标"), "{}", message);
+    }
+
+    #[test]
+    fn test_update_workspace_rejects_over_length_fields() {
+        let service = memory_service(CURRENT_USER_SQL);
+        let workspace = service.create_workspace(create_request("A")).unwrap();
+
+        // 上限以内可以保存（按字符计数）
+        let name = "名".repeat(MAX_WORKSPACE_NAME_LENGTH);
+        let updated = service.update_workspace(UpdateWorkspaceRequest {
+            name: Some(name.clone()),
+            ..update_request(&workspace.id)
+        }).unwrap();
+        assert_eq!(updated.name, name);
+
+        let message = invalid_operation_message(service.update_workspace(UpdateWorkspaceRequest {
+            name: Some("名".repeat(MAX_WORKSPACE_NAME_LENGTH + 1)),
+            ..update_request(&workspace.id)
+        }).err().unwrap());
+        assert!(message.contains("名称"), "{}", message);
+
+        let message = invalid_operation_message(service.update_workspace(UpdateWorkspaceRequest {
+            description: Some("x".repeat(MAX_WORKSPACE_DESCRIPTION_LENGTH + 1)),
+            ..update_request(&workspace.id)
+        }).err().unwrap());
+        assert!(message.contains("描述"), "{}", message);
+
+        // 校验失败时不修改已保存的数据
+        assert_eq!(service.get_workspace(&workspace.id).unwrap().name, name);
+    }
+
+    #[test]
+    fn test_update_workspace_clears_optional_fields_with_empty_string() {
+        let service = memory_service(CURRENT_USER_SQL);
+        let workspace = service.create_workspace(CreateWorkspaceRequest {
+            icon: Some("📁".to_string()),
+            color: Some("#fff".to_string()),
+            ..create_request("A")
+        }).unwrap();
+
+        let updated = service.update_workspace(UpdateWorkspaceRequest {
+            icon: Some(String::new()),
+            color: Some(String::new()),
+            ..update_request(&workspace.id)
+        }).unwrap();
+
+        assert_eq!(updated.icon, None);
+        assert_eq!(updated.color, None);
+        assert_eq!(updated.name, "A");
+    }
+
+    #[test]
+    fn test_delete_last_workspace_is_rejected() {
+        let service = memory_service(&format!(
+            "{}
+             INSERT INTO workspaces (id, user_id, name, is_current, created_at, updated_at)
+             VALUES ('ws-a', 'user-1', 'A', 1, 0, 0);",
+            CURRENT_USER_SQL
+        ));
+
+        let message = invalid_operation_message(service.delete_workspace("ws-a").err().unwrap());
+
+        assert!(message.contains("最后一个"), "{}", message);
+        assert_eq!(service.get_current_workspace().unwrap().id, "ws-a");
+    }
+
+    #[test]
+    fn test_delete_current_workspace_switches_current() {
+        let service = memory_service(&format!(
+            "{}
+             INSERT INTO workspaces (id, user_id, name, is_default, is_current, sort_order, created_at, updated_at) VALUES
+                 ('ws-default', 'user-1', '默认', 1, 0, 2, 0, 0),
+                 ('ws-a', 'user-1', 'A', 0, 1, 1, 0, 0),
+                 ('ws-b', 'user-1', 'B', 0, 0, 0, 0, 0);",
+            CURRENT_USER_SQL
+        ));
+
+        // 优先切换到默认工作空间
+        service.delete_workspace("ws-a").unwrap();
+        assert_eq!(service.get_current_workspace().unwrap().id, "ws-default");
+
+        let ids: Vec<String> = service.list_workspaces().unwrap().into_iter().map(|w| w.id).collect();
+        assert_eq!(ids, ["ws-b", "ws-default"]);
+    }
+
+    #[test]
+    fn test_delete_current_workspace_without_default_switches_to_first() {
+        let service = memory_service(&format!(
+            "{}
+             INSERT INTO workspaces (id, user_id, name, is_current, sort_order, created_at, updated_at) VALUES
+                 ('ws-a', 'user-1', 'A', 1, 0, 0, 0),
+                 ('ws-b', 'user-1', 'B', 0, 2, 0, 0),
+                 ('ws-c', 'user-1', 'C', 0, 1, 0, 0);",
+            CURRENT_USER_SQL
+        ));
+
+        service.delete_workspace("ws-a").unwrap();
+
+        assert_eq!(service.get_current_workspace().unwrap().id, "ws-c");
+    }
+
+    /// 模板工作空间：三层文件夹、一个已删除的文件夹、两个标签和两篇笔记
+    fn duplicate_source_sql() -> String {
+        format!(
+            "{}
+             INSERT INTO workspaces (id, user_id, name, icon, is_current, sort_order, created_at, updated_at)
+             VALUES ('ws-a', 'user-1', 'A', '📁', 1, 0, 0, 0);
+             INSERT INTO folders (id, name, parent_id, workspace_id, sort_order, is_deleted, server_ver, created_at, updated_at) VALUES
+                 ('f-root', '项目', NULL, 'ws-a', 0, 0, 3, 0, 0),
+                 ('f-docs', '文档', 'f-root', 'ws-a', 1, 0, 3, 0, 0),
+                 ('f-api', '接口', 'f-docs', 'ws-a', 0, 0, 3, 0, 0),
+                 ('f-misc', '杂项', NULL, 'ws-a', 2, 0, 3, 0, 0),
+                 ('f-old', '旧文件夹', 'f-root', 'ws-a', 3, 1, 3, 0, 0);
+             INSERT INTO tags (id, name, workspace_id, server_ver, created_at, updated_at) VALUES
+                 ('t-todo', 'todo', 'ws-a', 2, 0, 0),
+                 ('t-done', 'done', 'ws-a', 2, 0, 0);
+             INSERT INTO notes (id, title, content, workspace_id, folder_id, server_ver, created_at, updated_at) VALUES
+                 ('n-1', '接口说明', 'GET /notes', 'ws-a', 'f-api', 5, 0, 0),
+                 ('n-2', '随手记', 'abc', 'ws-a', NULL, 5, 0, 0);
+             INSERT INTO note_tags (note_id, tag_id, workspace_id, created_at) VALUES ('n-1', 't-todo', 'ws-a', 0);",
+            CURRENT_USER_SQL
+        )
+    }
+
+    /// 工作空间的文件夹树：(路径, 排序值)，路径由各级文件夹名称组成
+    fn folder_tree(pool: &crate::database::DbPool, workspace_id: &str) -> Vec<(String, i64)> {
+        let conn = pool.get().unwrap();
+        let mut stmt = conn.prepare(
+            "WITH RECURSIVE tree(id, path, sort_order) AS (
+                 SELECT id, name, sort_order FROM folders WHERE workspace_id = ?1 AND parent_id IS NULL AND is_deleted = 0
+                 UNION ALL
+                 SELECT f.id, tree.path || '/' || f.name, f.sort_order
+                 FROM folders f JOIN tree ON f.parent_id = tree.id
+                 WHERE f.is_deleted = 0
+             )
+             SELECT path, sort_order FROM tree ORDER BY path"
+        ).unwrap();
+        let tree = stmt.query_map(params![workspace_id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .unwrap();
+        tree
+    }
+
+    fn ids(pool: &crate::database::DbPool, table: &str, workspace_id: &str) -> Vec<String> {
+        let conn = pool.get().unwrap();
+        let mut stmt = conn.prepare(&format!("SELECT id FROM {} WHERE workspace_id = ?1", table)).unwrap();
+        let ids = stmt.query_map(params![workspace_id], |row| row.get(0))
+            .unwrap()
+            .collect::<std::result::Result<Vec<String>, _>>()
+            .unwrap();
+        ids
+    }
+
+    #[test]
+    fn test_duplicate_workspace_copies_folder_tree_with_new_ids() {
+        let pool = memory_pool(&duplicate_source_sql());
+        let service = WorkspaceService::new(WorkspaceRepository::new(pool.clone()));
+
+        let result = service.duplicate_workspace("ws-a", "A 模板", true).unwrap();
+        let copy_id = result.workspace.id.clone();
+
+        assert_eq!(result.workspace.name, "A 模板");
+        assert_eq!(result.workspace.icon.as_deref(), Some("📁"));
+        assert_eq!((result.folders, result.tags, result.notes), (4, 2, 2));
+
+        // 文件夹树相同（已删除的文件夹不复制），但 id 全部不同
+        assert_eq!(folder_tree(&pool, &copy_id), folder_tree(&pool, "ws-a"));
+        assert_eq!(folder_tree(&pool, &copy_id).len(), 4);
+        for table in ["folders", "tags", "notes"] {
+            let source = ids(&pool, table, "ws-a");
+            let copy = ids(&pool, table, &copy_id);
+            assert!(!copy.is_empty(), "{}", table);
+            assert!(copy.iter().all(|id| !source.contains(id)), "{}", table);
+        }
+
+        let conn = pool.get().unwrap();
+        // 笔记指向复制出的文件夹，标签关联指向复制出的笔记和标签
+        let (folder_name, folder_workspace): (String, String) = conn.query_row(
+            "SELECT f.name, f.workspace_id FROM notes n JOIN folders f ON f.id = n.folder_id
+             WHERE n.workspace_id = ?1 AND n.title = '接口说明'",
+            params![copy_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).unwrap();
+        assert_eq!((folder_name.as_str(), folder_workspace.as_str()), ("接口", copy_id.as_str()));
+
+        let (note_title, tag_name): (String, String) = conn.query_row(
+            "SELECT n.title, t.name FROM note_tags nt
+             JOIN notes n ON n.id = nt.note_id AND n.workspace_id = ?1
+             JOIN tags t ON t.id = nt.tag_id AND t.workspace_id = ?1
+             WHERE nt.workspace_id = ?1",
+            params![copy_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).unwrap();
+        assert_eq!((note_title.as_str(), tag_name.as_str()), ("接口说明", "todo"));
+
+        // 复制出的数据都需要同步，且从未同步过
+        for table in ["folders", "tags", "notes", "note_tags"] {
+            let unsynced: i64 = conn.query_row(
+                &format!("SELECT COUNT(*) FROM {} WHERE workspace_id = ?1 AND (is_dirty = 0 OR {})", table,
+                    if table == "note_tags" { "0" } else { "server_ver != 0" }),
+                params![copy_id],
+                |row| row.get(0),
+            ).unwrap();
+            assert_eq!(unsynced, 0, "{}", table);
+        }
+
+        // 原工作空间不受影响
+        assert_eq!(ids(&pool, "notes", "ws-a").len(), 2);
+        assert_eq!(service.get_current_workspace().unwrap().id, "ws-a");
+    }
+
+    #[test]
+    fn test_duplicate_workspace_without_notes_copies_structure_only() {
+        let pool = memory_pool(&duplicate_source_sql());
+        let service = WorkspaceService::new(WorkspaceRepository::new(pool.clone()));
+
+        let result = service.duplicate_workspace("ws-a", "空模板", false).unwrap();
+        let copy_id = result.workspace.id;
+
+        assert_eq!((result.folders, result.tags, result.notes), (4, 2, 0));
+        assert!(ids(&pool, "notes", &copy_id).is_empty());
+        assert_eq!(folder_tree(&pool, &copy_id), folder_tree(&pool, "ws-a"));
+
+        let note_tags: i64 = pool.get().unwrap().query_row(
+            "SELECT COUNT(*) FROM note_tags WHERE workspace_id = ?1",
+            params![copy_id],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(note_tags, 0);
+    }
+
+    #[test]
+    fn test_duplicate_workspace_rejects_invalid_name() {
+        let pool = memory_pool(&duplicate_source_sql());
+        let service = WorkspaceService::new(WorkspaceRepository::new(pool.clone()));
+
+        assert!(matches!(service.duplicate_workspace("ws-a", "  ", false), Err(AppError::InvalidOperation(_))));
+        assert_eq!(service.list_workspaces().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_migrate_orphan_data_returns_counts() {
+        let service = memory_service(&format!(
+            "{}
+             INSERT INTO workspaces (id, user_id, name, is_current, created_at, updated_at) VALUES ('ws-a', 'user-1', 'A', 1, 0, 0);
+             INSERT INTO folders (id, name, created_at, updated_at) VALUES ('f-1', '离线文件夹', 0, 0);
+             INSERT INTO tags (id, name, created_at, updated_at) VALUES ('t-1', '离线', 0, 0), ('t-2', '草稿', 0, 0);
+             INSERT INTO notes (id, title, content, workspace_id, created_at, updated_at) VALUES
+                 ('n-1', '离线笔记', 'a', NULL, 0, 0),
+                 ('n-2', '离线笔记 2', 'b', NULL, 0, 0),
+                 ('n-3', '离线笔记 3', 'c', NULL, 0, 0),
+                 ('n-4', '已有笔记', 'd', 'ws-a', 0, 0);
+             INSERT INTO note_snapshots (id, note_id, title, content, created_at) VALUES ('s-1', 'n-1', '离线笔记', 'a', 0);",
+            CURRENT_USER_SQL
+        ));
+
+        let plan = service.migrate_orphan_data("ws-a", true).unwrap();
+        let result = service.migrate_orphan_data("ws-a", false).unwrap();
+
+        for counts in [&plan, &result] {
+            assert_eq!((counts.notes, counts.folders, counts.tags, counts.snapshots), (3, 1, 2, 1));
+        }
+        assert!(service.preview_orphan_migration().unwrap().is_empty());
+
+        // 再次迁移时没有数据需要迁移
+        let again = service.migrate_orphan_data("ws-a", false).unwrap();
+        assert_eq!((again.notes, again.folders, again.tags, again.snapshots), (0, 0, 0, 0));
+    }
 }
@@ -0,0 +1,132 @@
+use flate2::read::DeflateDecoder;
+use std::io::Read;
+
+use crate::models::error::{AppError, Result};
+
+/// zip 中的一个条目
+pub struct ZipEntry {
+    pub name: String,  // 条目路径（目录以 `/` 结尾）
+    pub data: Vec<u8>,
+}
+
+/// 单个条目解压后的大小上限（防止压缩炸弹）
+const MAX_ENTRY_SIZE: u64 = 64 * 1024 * 1024;
+
+/// 读取 zip 中的所有条目
+///
+/// 通过中央目录定位条目，支持 STORED 和 DEFLATE 两种方式，不支持 ZIP64 和加密，
+/// 与 `ZipWriter` 配套，也能读取其他工具生成的普通 zip
+pub fn read_entries(bytes: &[u8]) -> Result<Vec<ZipEntry>> {
+    let eocd = find_end_of_central_directory(bytes)
+        .ok_or_else(|| AppError::InvalidInput("不是有效的 zip 文件".to_string()))?;
+    let entry_count = read_u16(bytes, eocd + 10)? as usize;
+    let mut pos = read_u32(bytes, eocd + 16)? as usize;
+
+    let mut entries = Vec::with_capacity(entry_count);
+    for _ in 0..entry_count {
+        if read_u32(bytes, pos)? != 0x0201_4b50 {
+            return Err(corrupted());
+        }
+        let flags = read_u16(bytes, pos + 8)?;
+        let method = read_u16(bytes, pos + 10)?;
+        let compressed_size = read_u32(bytes, pos + 20)? as usize;
+        let size = read_u32(bytes, pos + 24)? as u64;
+        let name_len = read_u16(bytes, pos + 28)? as usize;
+        let extra_len = read_u16(bytes, pos + 30)? as usize;
+        let comment_len = read_u16(bytes, pos + 32)? as usize;
+        let local_offset = read_u32(bytes, pos + 42)? as usize;
+        let name = String::from_utf8_lossy(slice(bytes, pos + 46, name_len)?).into_owned();
+        pos += 46 + name_len + extra_len + comment_len;
+
+        if flags & 1 != 0 {
+            return Err(AppError::InvalidInput(format!("不支持加密的 zip 条目: {}", name)));
+        }
+        if size > MAX_ENTRY_SIZE {
+            return Err(AppError::InvalidInput(format!("zip 条目过大: {}", name)));
+        }
+
+        // 本地文件头的文件名和扩展字段长度可能与中央目录不同，需要重新读取
+        if read_u32(bytes, local_offset)? != 0x0403_4b50 {
+            return Err(corrupted());
+        }
+        let data_start = local_offset + 30
+            + read_u16(bytes, local_offset + 26)? as usize
+            + read_u16(bytes, local_offset + 28)? as usize;
+        let raw = slice(bytes, data_start, compressed_size)?;
+
+        let data = match method {
+            0 => raw.to_vec(),
+            8 => {
+                let mut data = Vec::with_capacity(size as usize);
+                DeflateDecoder::new(raw)
+                    .take(MAX_ENTRY_SIZE)
+                    .read_to_end(&mut data)
+                    .map_err(|e| AppError::InvalidInput(format!("解压 zip 条目失败: {}: {}", name, e)))?;
+                data
+            }
+            _ => return Err(AppError::InvalidInput(format!("不支持的 zip 压缩方式 {}: {}", method, name))),
+        };
+
+        entries.push(ZipEntry { name, data });
+    }
+
+    Ok(entries)
+}
+
+/// 从末尾向前查找中央目录结束记录（其后最多有 65535 字节的注释）
+fn find_end_of_central_directory(bytes: &[u8]) -> Option<usize> {
+    if bytes.len() < 22 {
+        return None;
+    }
+    let lowest = bytes.len().saturating_sub(22 + u16::MAX as usize);
+    (lowest..=bytes.len() - 22)
+        .rev()
+        .find(|&pos| bytes[pos..pos + 4] == 0x0605_4b50u32.to_le_bytes())
+}
+
+fn slice(bytes: &[u8], start: usize, len: usize) -> Result<&[u8]> {
+    start.checked_add(len)
+        .and_then(|end| bytes.get(start..end))
+        .ok_or_else(corrupted)
+}
+
+fn read_u16(bytes: &[u8], pos: usize) -> Result<u16> {
+    let b = slice(bytes, pos, 2)?;
+    Ok(u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn read_u32(bytes: &[u8], pos: usize) -> Result<u32> {
+    let b = slice(bytes, pos, 4)?;
+    Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn corrupted() -> AppError {
+    AppError::InvalidInput("zip 文件已损坏".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::zip_writer::ZipWriter;
+
+    #[test]
+    fn reads_entries_written_by_zip_writer() {
+        let mut zip = ZipWriter::new(Vec::new());
+        zip.add_file("工作/", b"", 1_700_000_000).unwrap();
+        zip.add_file("工作/计划.md", "# 计划".as_bytes(), 1_700_000_000).unwrap();
+        let bytes = zip.finish().unwrap();
+
+        let entries = read_entries(&bytes).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].name, "工作/");
+        assert!(entries[0].data.is_empty());
+        assert_eq!(entries[1].name, "工作/计划.md");
+        assert_eq!(entries[1].data, "# 计划".as_bytes());
+    }
+
+    #[test]
+    fn rejects_non_zip_input() {
+        assert!(read_entries(b"not a zip").is_err());
+        assert!(read_entries(&[]).is_err());
+    }
+}
@@ -0,0 +1,175 @@
+use chrono::{Datelike, Local, TimeZone, Timelike};
+use std::io::Write;
+
+use crate::models::error::{AppError, Result};
+
+/// 极简 ZIP 写入器
+///
+/// 仅支持 STORED（不压缩）方式，不支持 ZIP64，
+/// 用于导出笔记归档等小体积文本文件，避免引入额外的压缩依赖
+pub struct ZipWriter<W: Write> {
+    writer: W,
+    offset: u64,
+    entries: Vec<ZipEntry>,
+}
+
+struct ZipEntry {
+    name: String,
+    crc32: u32,
+    size: u32,
+    dos_time: u16,
+    dos_date: u16,
+    offset: u32,
+}
+
+/// 通用标志位：文件名使用 UTF-8 编码
+const FLAG_UTF8: u16 = 1 << 11;
+/// 解压所需的最低版本（2.0）
+const VERSION: u16 = 20;
+
+impl<W: Write> ZipWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            offset: 0,
+            entries: Vec::new(),
+        }
+    }
+
+    /// 写入一个文件条目
+    ///
+    /// `modified_at` 为 Unix 时间戳（秒），按本地时区转换为 DOS 时间
+    pub fn add_file(&mut self, name: &str, data: &[u8], modified_at: i64) -> Result<()> {
+        let size = u32::try_from(data.len())
+            .map_err(|_| AppError::InvalidOperation(format!("文件过大，无法写入归档: {}", name)))?;
+        let offset = u32::try_from(self.offset)
+            .map_err(|_| AppError::InvalidOperation("归档体积超过 4GB 限制".to_string()))?;
+        let name_len = u16::try_from(name.len())
+            .map_err(|_| AppError::InvalidOperation(format!("文件名过长: {}", name)))?;
+
+        let (dos_time, dos_date) = dos_datetime(modified_at);
+        let crc32 = crc32fast::hash(data);
+
+        let mut header = Vec::with_capacity(30 + name.len());
+        header.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        header.extend_from_slice(&VERSION.to_le_bytes());
+        header.extend_from_slice(&FLAG_UTF8.to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes()); // 压缩方式：STORED
+        header.extend_from_slice(&dos_time.to_le_bytes());
+        header.extend_from_slice(&dos_date.to_le_bytes());
+        header.extend_from_slice(&crc32.to_le_bytes());
+        header.extend_from_slice(&size.to_le_bytes()); // 压缩后大小
+        header.extend_from_slice(&size.to_le_bytes()); // 原始大小
+        header.extend_from_slice(&name_len.to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes()); // 扩展字段长度
+        header.extend_from_slice(name.as_bytes());
+
+        self.write_all(&header)?;
+        self.write_all(data)?;
+
+        self.entries.push(ZipEntry {
+            name: name.to_string(),
+            crc32,
+            size,
+            dos_time,
+            dos_date,
+            offset,
+        });
+
+        Ok(())
+    }
+
+    /// 写入中央目录并返回底层 writer
+    pub fn finish(mut self) -> Result<W> {
+        let cd_offset = u32::try_from(self.offset)
+            .map_err(|_| AppError::InvalidOperation("归档体积超过 4GB 限制".to_string()))?;
+        let entry_count = u16::try_from(self.entries.len())
+            .map_err(|_| AppError::InvalidOperation("归档文件数量过多".to_string()))?;
+
+        let mut directory = Vec::new();
+        for entry in &self.entries {
+            directory.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+            directory.extend_from_slice(&VERSION.to_le_bytes()); // 创建版本
+            directory.extend_from_slice(&VERSION.to_le_bytes()); // 解压所需版本
+            directory.extend_from_slice(&FLAG_UTF8.to_le_bytes());
+            directory.extend_from_slice(&0u16.to_le_bytes());
+            directory.extend_from_slice(&entry.dos_time.to_le_bytes());
+            directory.extend_from_slice(&entry.dos_date.to_le_bytes());
+            directory.extend_from_slice(&entry.crc32.to_le_bytes());
+            directory.extend_from_slice(&entry.size.to_le_bytes());
+            directory.extend_from_slice(&entry.size.to_le_bytes());
+            directory.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+            directory.extend_from_slice(&0u16.to_le_bytes()); // 扩展字段长度
+            directory.extend_from_slice(&0u16.to_le_bytes()); // 注释长度
+            directory.extend_from_slice(&0u16.to_le_bytes()); // 起始磁盘号
+            directory.extend_from_slice(&0u16.to_le_bytes()); // 内部属性
+            directory.extend_from_slice(&0u32.to_le_bytes()); // 外部属性
+            directory.extend_from_slice(&entry.offset.to_le_bytes());
+            directory.extend_from_slice(entry.name.as_bytes());
+        }
+        let cd_size = directory.len() as u32;
+
+        // 中央目录结束记录
+        directory.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+        directory.extend_from_slice(&0u16.to_le_bytes()); // 当前磁盘号
+        directory.extend_from_slice(&0u16.to_le_bytes()); // 中央目录起始磁盘号
+        directory.extend_from_slice(&entry_count.to_le_bytes());
+        directory.extend_from_slice(&entry_count.to_le_bytes());
+        directory.extend_from_slice(&cd_size.to_le_bytes());
+        directory.extend_from_slice(&cd_offset.to_le_bytes());
+        directory.extend_from_slice(&0u16.to_le_bytes()); // 注释长度
+
+        self.write_all(&directory)?;
+        self.writer.flush()
+            .map_err(|e| AppError::Internal(format!("写入归档失败: {}", e)))?;
+
+        Ok(self.writer)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+        self.writer.write_all(buf)
+            .map_err(|e| AppError::Internal(format!("写入归档失败: {}", e)))?;
+        self.offset += buf.len() as u64;
+        Ok(())
+    }
+}
+
+/// 将 Unix 时间戳转换为 DOS 时间/日期（本地时区，最早 1980-01-01）
+fn dos_datetime(timestamp: i64) -> (u16, u16) {
+    let datetime = match Local.timestamp_opt(timestamp, 0).single() {
+        Some(dt) if dt.year() >= 1980 => dt,
+        _ => return (0, (1 << 5) | 1),
+    };
+
+    let time = ((datetime.hour() as u16) << 11)
+        | ((datetime.minute() as u16) << 5)
+        | (datetime.second() as u16 / 2);
+    let date = (((datetime.year() - 1980).min(127) as u16) << 9)
+        | ((datetime.month() as u16) << 5)
+        | datetime.day() as u16;
+
+    (time, date)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_entries_and_end_of_central_directory() {
+        let mut zip = ZipWriter::new(Vec::new());
+        zip.add_file("note.md", b"# hello", 1_700_000_000).unwrap();
+        zip.add_file("snapshots/快照.md", b"old", 1_700_000_000).unwrap();
+        let bytes = zip.finish().unwrap();
+
+        assert_eq!(&bytes[..4], &0x0403_4b50u32.to_le_bytes());
+        let eocd = &bytes[bytes.len() - 22..];
+        assert_eq!(&eocd[..4], &0x0605_4b50u32.to_le_bytes());
+        assert_eq!(u16::from_le_bytes([eocd[10], eocd[11]]), 2);
+    }
+
+    #[test]
+    fn clamps_pre_1980_timestamps() {
+        assert_eq!(dos_datetime(0), (0, (1 << 5) | 1));
+    }
+}